@@ -72,3 +72,107 @@ export function loadFromIndexedDB(dbName, storeName, key) {
     request.onerror = () => reject(request.error)
   })
 }
+
+export function deleteFromIndexedDB(dbName, storeName, key) {
+  return new Promise((resolve, reject) => {
+    const request = indexedDB.open(dbName, IDB_VERSION)
+
+    request.onsuccess = function (event) {
+      const db = event.target.result
+      const tx = db.transaction(storeName, "readwrite")
+      tx.objectStore(storeName).delete(key)
+
+      tx.oncomplete = () => resolve(true)
+      tx.onerror = () => reject(tx.error)
+    }
+
+    request.onerror = () => reject(request.error)
+  })
+}
+
+// localStorage, for beforeunload/pagehide handlers, which can't wait for
+// IndexedDB. Quota and privacy-mode errors are reported, not thrown.
+export function savePendingSync(key, value) {
+  try {
+    localStorage.setItem(key, value)
+    return true
+  } catch (error) {
+    console.error("Failed to write pending changes:", error)
+    return false
+  }
+}
+
+export function loadPendingSync(key) {
+  try {
+    return localStorage.getItem(key) ?? undefined
+  } catch (error) {
+    console.error("Failed to read pending changes:", error)
+    return undefined
+  }
+}
+
+export function clearPendingSync(key) {
+  try {
+    localStorage.removeItem(key)
+  } catch (error) {
+    console.error("Failed to clear pending changes:", error)
+  }
+}
+
+// How much storage the origin uses and may use, or undefined where the
+// browser can't say
+export async function estimateStorage() {
+  if (typeof navigator === "undefined" || !navigator.storage?.estimate) {
+    return undefined
+  }
+  try {
+    const { usage, quota } = await navigator.storage.estimate()
+    return { usage, quota }
+  } catch (error) {
+    console.error("Failed to estimate storage:", error)
+    return undefined
+  }
+}
+
+// Tabs take turns writing: the one holding this lock is the writer. Resolves
+// true if this tab got it, which it keeps until it closes. Otherwise resolves
+// false and queues for it, calling `onAcquired` once the writer closes.
+export function claimWriterLock(name, onAcquired) {
+  if (typeof navigator === "undefined" || !navigator.locks) {
+    return Promise.resolve(true)
+  }
+  const held = () => new Promise(() => {})
+  return new Promise((resolve, reject) => {
+    navigator.locks
+      .request(name, { ifAvailable: true }, (lock) => {
+        if (lock) {
+          resolve(true)
+          return held()
+        }
+        resolve(false)
+        navigator.locks
+          .request(name, () => {
+            onAcquired()
+            return held()
+          })
+          .catch((error) => console.error("Failed to wait for writer lock:", error))
+      })
+      .catch(reject)
+  })
+}
+
+// A channel to the app's other tabs, or undefined where there's none
+export function openTabChannel(name, onMessage) {
+  if (typeof BroadcastChannel === "undefined") return undefined
+  const channel = new BroadcastChannel(name)
+  channel.onmessage = (event) => onMessage(event.data)
+  return channel
+}
+
+export function postTabMessage(channel, message) {
+  try {
+    channel?.postMessage(message)
+  } catch (error) {
+    console.error("Failed to message other tabs:", error)
+  }
+}
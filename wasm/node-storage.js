@@ -0,0 +1,92 @@
+// node-storage.js
+// Stand-in for indexeddb.js when the package is built with `--features node`.
+// Keeps data in memory, or on disk under ORGAN_STORAGE_DIR when it is set,
+// so scripts and tests can use the same Store API as the web app.
+
+const memory = new Map()
+
+function storageDir() {
+  return typeof process !== "undefined" ? process.env.ORGAN_STORAGE_DIR : undefined
+}
+
+async function filePath(dir, dbName, storeName, key) {
+  const path = await import("node:path")
+  return path.join(dir, dbName, storeName, encodeURIComponent(key))
+}
+
+export async function saveToIndexedDB(dbName, storeName, key, value) {
+  const dir = storageDir()
+  if (!dir) {
+    // Copy, since the caller's view may point into wasm memory
+    memory.set(`${dbName}/${storeName}/${key}`, new Uint8Array(value))
+    return true
+  }
+
+  const fs = await import("node:fs/promises")
+  const path = await import("node:path")
+  const file = await filePath(dir, dbName, storeName, key)
+  await fs.mkdir(path.dirname(file), { recursive: true })
+  await fs.writeFile(file, value)
+  return true
+}
+
+export async function loadFromIndexedDB(dbName, storeName, key) {
+  const dir = storageDir()
+  if (!dir) {
+    // Missing keys resolve to undefined, as IndexedDB's get() does
+    return memory.get(`${dbName}/${storeName}/${key}`)
+  }
+
+  const fs = await import("node:fs/promises")
+  try {
+    return new Uint8Array(await fs.readFile(await filePath(dir, dbName, storeName, key)))
+  } catch (error) {
+    if (error.code === "ENOENT") return undefined
+    throw error
+  }
+}
+
+export async function deleteFromIndexedDB(dbName, storeName, key) {
+  const dir = storageDir()
+  if (!dir) {
+    memory.delete(`${dbName}/${storeName}/${key}`)
+    return true
+  }
+
+  const fs = await import("node:fs/promises")
+  await fs.rm(await filePath(dir, dbName, storeName, key), { force: true })
+  return true
+}
+
+// Pending changes flushed on exit, kept in memory: a script that exits
+// saves through the async calls above instead
+const pending = new Map()
+
+export function savePendingSync(key, value) {
+  pending.set(key, value)
+  return true
+}
+
+export function loadPendingSync(key) {
+  return pending.get(key)
+}
+
+export function clearPendingSync(key) {
+  pending.delete(key)
+}
+
+// Disk and memory have no quota worth reporting here
+export async function estimateStorage() {
+  return undefined
+}
+
+// A script is the only writer of its storage directory
+export async function claimWriterLock(name, onAcquired) {
+  return true
+}
+
+export function openTabChannel(name, onMessage) {
+  return undefined
+}
+
+export function postTabMessage(channel, message) {}
@@ -0,0 +1,13 @@
+//! Render a site from JSON on stdin, for WASI runtimes:
+//!
+//! ```sh
+//! cargo build --release --target wasm32-wasi --features wasi --bin render
+//! wasmtime target/wasm32-wasi/release/render.wasm < site.json > output.json
+//! ```
+
+fn main() {
+    if let Err(e) = minissg::wasi::run() {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
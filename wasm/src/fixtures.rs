@@ -0,0 +1,359 @@
+//! Sample content for tests, benchmarks and demos.
+//!
+//! `generate` builds pages, posts with lorem ipsum rich text, and small PNG
+//! assets from a seed, so the same options always give the same content.
+//! Ids are handed out when the content is added to a project, so they
+//! differ between runs; names, titles, text, dates and image bytes don't.
+//!
+//! Only built with the `fixtures` feature.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageOutputFormat, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::import::{resolve_image_sources, ImportedAsset, ImportedPage};
+use crate::render::graph::TAGS_FIELD;
+use crate::render::site::{PageSource, SiteSource};
+
+#[rustfmt::skip]
+const WORDS: &[&str] = &[
+    "lorem", "ipsum", "dolor", "sit", "amet", "consectetur", "adipiscing", "elit", "sed", "do",
+    "eiusmod", "tempor", "incididunt", "ut", "labore", "et", "dolore", "magna", "aliqua", "enim",
+    "ad", "minim", "veniam", "quis", "nostrud", "exercitation", "ullamco", "laboris", "nisi",
+    "aliquip", "ex", "ea", "commodo", "consequat", "duis", "aute", "irure", "in", "reprehenderit",
+    "voluptate", "velit", "esse", "cillum", "fugiat", "nulla", "pariatur", "excepteur", "sint",
+    "occaecat", "cupidatat", "non", "proident", "sunt", "culpa", "qui", "officia", "deserunt",
+    "mollit", "anim", "id", "est", "laborum",
+];
+
+#[rustfmt::skip]
+const TAGS: &[&str] = &[
+    "news", "events", "zines", "interviews", "reviews", "music", "art", "community",
+];
+
+/// How much content to generate, and from which seed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct FixtureOptions {
+    pub seed: u64,
+    pub pages: usize,
+    pub posts: usize,
+    pub assets: usize,
+    /// Paragraphs in each post's body; pages get half as many
+    pub paragraphs: usize,
+}
+
+impl Default for FixtureOptions {
+    fn default() -> Self {
+        FixtureOptions {
+            seed: 1,
+            pages: 3,
+            posts: 10,
+            assets: 4,
+            paragraphs: 4,
+        }
+    }
+}
+
+/// Generated content, ready to add to a site the way imports are
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub pages: Vec<ImportedPage>,
+    /// Each asset's key is the image `src` used by the posts showing it
+    pub assets: Vec<ImportedAsset>,
+}
+
+/// SplitMix64: small, fast, and the same on every platform
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `low..=high`
+    pub fn range(&mut self, low: usize, high: usize) -> usize {
+        low + (self.next_u64() % (high - low + 1) as u64) as usize
+    }
+
+    /// True about one time in `n`
+    pub fn one_in(&mut self, n: usize) -> bool {
+        self.range(1, n) == 1
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.range(0, items.len() - 1)]
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn words(rng: &mut Rng, count: usize) -> Vec<&'static str> {
+    (0..count).map(|_| *rng.pick(WORDS)).collect()
+}
+
+fn title(rng: &mut Rng) -> String {
+    let count = rng.range(2, 5);
+    words(rng, count)
+        .iter()
+        .map(|word| capitalize(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn slug(title: &str, number: usize) -> String {
+    format!("{}-{}", title.to_lowercase().replace(' ', "-"), number)
+}
+
+fn sentence(rng: &mut Rng) -> String {
+    let count = rng.range(6, 14);
+    let text = words(rng, count).join(" ");
+    format!("{}.", capitalize(&text))
+}
+
+fn text(text: String) -> Value {
+    json!({ "type": "text", "text": text })
+}
+
+/// A paragraph of a few sentences, with a word now and then in bold or italics
+fn paragraph(rng: &mut Rng) -> Value {
+    let count = rng.range(2, 5);
+    let sentences: Vec<String> = (0..count).map(|_| sentence(rng)).collect();
+    let body = sentences.join(" ");
+    if !rng.one_in(3) {
+        return json!({ "type": "paragraph", "content": [text(body)] });
+    }
+
+    let mark = if rng.one_in(2) { "strong" } else { "em" };
+    let emphasized = *rng.pick(WORDS);
+    json!({
+        "type": "paragraph",
+        "content": [
+            text(format!("{} ", body)),
+            { "type": "text", "text": emphasized, "marks": [{ "type": mark }] },
+            text(".".to_string()),
+        ]
+    })
+}
+
+fn image(key: &str, alt: String) -> Value {
+    json!({
+        "type": "paragraph",
+        "content": [{
+            "type": "image",
+            "attrs": { "src": key, "alt": alt, "title": null }
+        }]
+    })
+}
+
+/// A body of `paragraphs` paragraphs, with a subheading halfway through
+/// long ones and maybe one of `assets` as an image
+fn body(rng: &mut Rng, paragraphs: usize, assets: &[ImportedAsset]) -> Value {
+    let mut content = Vec::new();
+    for i in 0..paragraphs {
+        if paragraphs >= 3 && i == paragraphs / 2 {
+            content.push(json!({
+                "type": "heading",
+                "attrs": { "level": 2 },
+                "content": [text(title(rng))]
+            }));
+        }
+        content.push(paragraph(rng));
+    }
+    if !assets.is_empty() && rng.one_in(2) {
+        let asset = rng.pick(assets);
+        let at = rng.range(0, content.len());
+        content.insert(at, image(&asset.key, sentence(rng)));
+    }
+    json!({ "type": "doc", "content": content })
+}
+
+/// A small PNG of a two-colour gradient
+fn png(rng: &mut Rng) -> Result<Vec<u8>, String> {
+    let (width, height) = (rng.range(16, 64) as u32, rng.range(16, 64) as u32);
+    let mut colour = || {
+        let value = rng.next_u64();
+        [value as u8, (value >> 8) as u8, (value >> 16) as u8]
+    };
+    let (from, to) = (colour(), colour());
+    let image = RgbImage::from_fn(width, height, |x, _| {
+        let t = x as f32 / width as f32;
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t) as u8;
+        Rgb([
+            mix(from[0], to[0]),
+            mix(from[1], to[1]),
+            mix(from[2], to[2]),
+        ])
+    });
+    let mut buffer = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(image)
+        .write_to(&mut buffer, ImageOutputFormat::Png)
+        .map_err(|e| format!("Can't write PNG: {}", e))?;
+    Ok(buffer.into_inner())
+}
+
+/// Posts are a few days apart, newest first, counting back from the start of 2024
+fn post_date(rng: &mut Rng, days_back: &mut i64) -> String {
+    *days_back += rng.range(1, 9) as i64;
+    let date =
+        chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() - chrono::Duration::days(*days_back);
+    format!("{}T09:00:00.000Z", date)
+}
+
+/// Content for `options`, the same every time for the same options
+pub fn generate(options: &FixtureOptions) -> Result<Fixture, String> {
+    let mut rng = Rng::new(options.seed);
+
+    let mut assets = Vec::new();
+    for i in 0..options.assets {
+        assets.push(ImportedAsset {
+            key: format!("fixture-asset-{}", i + 1),
+            name: format!("fixture-{}.png", i + 1),
+            mime_type: "image/png".to_string(),
+            data: png(&mut rng)?,
+        });
+    }
+
+    let mut pages = Vec::new();
+    for i in 0..options.pages {
+        let title = title(&mut rng);
+        pages.push(ImportedPage {
+            collection: "page",
+            name: slug(&title, i + 1),
+            content: body(&mut rng, (options.paragraphs / 2).max(1), &[]),
+            title,
+            meta: Map::new(),
+            draft: false,
+        });
+    }
+
+    let mut days_back = 0;
+    for i in 0..options.posts {
+        let title = title(&mut rng);
+        let mut meta = Map::new();
+        meta.insert(
+            "date".to_string(),
+            json!(post_date(&mut rng, &mut days_back)),
+        );
+        meta.insert("excerpt".to_string(), json!(sentence(&mut rng)));
+        let count = rng.range(0, 3);
+        let mut tags: Vec<&str> = Vec::new();
+        for _ in 0..count {
+            let tag = *rng.pick(TAGS);
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        meta.insert(TAGS_FIELD.to_string(), json!(tags));
+        pages.push(ImportedPage {
+            collection: "post",
+            name: slug(&title, i + 1),
+            content: body(&mut rng, options.paragraphs, &assets),
+            title,
+            meta,
+            draft: rng.one_in(10),
+        });
+    }
+
+    Ok(Fixture { pages, assets })
+}
+
+/// Add `fixture`'s pages and posts to `source`, as the store would load
+/// them once added to a site
+///
+/// Each asset is served at `/assets/{name}`; only its metadata is added, so
+/// rendering links to the images without writing them.
+pub fn add_to_site_source(fixture: Fixture, source: &mut SiteSource) {
+    let mut urls = HashMap::new();
+    for asset in &fixture.assets {
+        let path = format!("/assets/{}", asset.name);
+        source.asset_meta.push(json!({
+            "id": asset.key,
+            "name": asset.name,
+            "mime_type": asset.mime_type,
+            "path": path,
+        }));
+        urls.insert(asset.key.clone(), path);
+    }
+
+    for page in fixture.pages {
+        let mut body = page.content;
+        resolve_image_sources(&mut body, &urls);
+        let mut meta = page.meta;
+        meta.insert("title".to_string(), json!(page.title));
+        if page.draft {
+            meta.insert("status".to_string(), json!("draft"));
+        }
+        source.pages.push(PageSource {
+            collection: page.collection.to_string(),
+            name: page.name,
+            meta,
+            body,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_generate_is_deterministic() {
+        let options = FixtureOptions {
+            seed: 42,
+            pages: 2,
+            posts: 5,
+            assets: 3,
+            paragraphs: 4,
+        };
+        let first = generate(&options).unwrap();
+        let second = generate(&options).unwrap();
+
+        assert_eq!(first.pages, second.pages);
+        assert_eq!(first.assets.len(), 3);
+        for (a, b) in first.assets.iter().zip(&second.assets) {
+            assert_eq!(a.data, b.data);
+        }
+        let posts: Vec<_> = first
+            .pages
+            .iter()
+            .filter(|p| p.collection == "post")
+            .collect();
+        assert_eq!(posts.len(), 5);
+        assert_eq!(first.pages.len(), 7);
+
+        let other = generate(&FixtureOptions {
+            seed: 43,
+            ..options
+        })
+        .unwrap();
+        assert_ne!(first.pages, other.pages);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_generated_assets_are_pngs() {
+        let fixture = generate(&FixtureOptions::default()).unwrap();
+        for asset in &fixture.assets {
+            assert!(crate::import::images::decode(&asset.data).is_ok());
+        }
+    }
+}
@@ -0,0 +1,593 @@
+//! Medium and Tumblr archive importer.
+//!
+//! Both platforms export a zip of one HTML file per post. Each post becomes
+//! a post here, keeping its title, date, tags and author where the export
+//! has them, plus the address it was published at, so the store can add a
+//! redirect from the old path. Images shipped in the archive (Tumblr's
+//! `media/`) become assets, one per distinct file; images left on a CDN
+//! (Medium's) are listed once each, however many sizes posts link to.
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read};
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use zip::ZipArchive;
+
+use super::article::resolve_url;
+use super::bundle::{sha256_hex, MAX_BUNDLE_BYTES};
+use super::html::{attr, decode_entities, html_to_pm, parse_attrs};
+use super::{mime_type_for, ImportedAsset, ImportedPage};
+use crate::render::graph::TAGS_FIELD;
+use crate::render::slugify;
+
+/// Base for resolving paths inside the archive with [`resolve_url`]
+const ARCHIVE_ROOT: &str = "archive://export/";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    Medium,
+    Tumblr,
+}
+
+/// A post and where it used to live
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivePost {
+    pub page: ImportedPage,
+    /// The post's address on the old platform, when known
+    pub canonical_url: Option<String>,
+    /// The path to redirect to the post, e.g. `/post/123456`
+    pub old_path: Option<String>,
+}
+
+/// Everything taken from an archive
+#[derive(Debug, Clone)]
+pub struct Archive {
+    pub platform: Platform,
+    pub posts: Vec<ArchivePost>,
+    /// Images from the archive itself; posts' image sources are their keys
+    pub assets: Vec<ImportedAsset>,
+    pub warnings: Vec<String>,
+}
+
+impl Archive {
+    /// Remote images the posts use, once each
+    pub fn remote_images(&self) -> Vec<String> {
+        let mut images = Vec::new();
+        for post in &self.posts {
+            collect_images(&post.page.content, &mut images);
+        }
+        images.retain(|image| image.starts_with("https://") || image.starts_with("http://"));
+        images.sort();
+        images.dedup();
+        images
+    }
+}
+
+fn warn(warnings: &mut Vec<String>, warning: String) {
+    if !warnings.contains(&warning) {
+        warnings.push(warning);
+    }
+}
+
+fn collect_images(node: &Value, images: &mut Vec<String>) {
+    if node["type"] == "image" {
+        if let Some(src) = node["attrs"]["src"].as_str() {
+            images.push(src.to_string());
+        }
+    }
+    for child in node["content"].as_array().map(Vec::as_slice).unwrap_or(&[]) {
+        collect_images(child, images);
+    }
+}
+
+fn rewrite_images(node: &mut Value, rewrite: &mut dyn FnMut(&str) -> Option<String>) {
+    if node["type"] == "image" {
+        if let Some(Value::String(src)) =
+            node.get_mut("attrs").and_then(|attrs| attrs.get_mut("src"))
+        {
+            if let Some(new) = rewrite(src.as_str()) {
+                *src = new;
+            }
+        }
+    }
+    if let Some(Value::Array(children)) = node.get_mut("content") {
+        for child in children {
+            rewrite_images(child, rewrite);
+        }
+    }
+}
+
+/// An element found in a page, by byte offsets
+#[derive(Debug)]
+struct Found {
+    attrs: Vec<(String, String)>,
+    start: usize,
+    inner_start: usize,
+    inner_end: usize,
+    end: usize,
+}
+
+fn open_tag_regex() -> Regex {
+    Regex::new(r"<([a-zA-Z][a-zA-Z0-9]*)([^>]*)>").expect("valid open tag regex")
+}
+
+/// The first element at or after `from` that `matches` accepts
+fn find_element(
+    html: &str,
+    from: usize,
+    matches: &dyn Fn(&str, &[(String, String)]) -> bool,
+) -> Option<Found> {
+    for captures in open_tag_regex().captures_iter(&html[from..]) {
+        let whole = captures.get(0)?;
+        let name = captures[1].to_ascii_lowercase();
+        let attrs = parse_attrs(captures[2].trim_end_matches('/'));
+        if !matches(&name, &attrs) {
+            continue;
+        }
+
+        let start = from + whole.start();
+        let inner_start = from + whole.end();
+        if captures[2].ends_with('/') {
+            return Some(Found {
+                attrs,
+                start,
+                inner_start,
+                inner_end: inner_start,
+                end: inner_start,
+            });
+        }
+        let tags = Regex::new(&format!(r"(?i)<(/?){}\b[^>]*>", regex::escape(&name)))
+            .expect("valid element regex");
+        let mut depth = 1;
+        for tag in tags.captures_iter(&html[inner_start..]) {
+            let position = tag.get(0)?;
+            if tag[1].is_empty() {
+                depth += 1;
+                continue;
+            }
+            depth -= 1;
+            if depth == 0 {
+                return Some(Found {
+                    attrs,
+                    start,
+                    inner_start,
+                    inner_end: inner_start + position.start(),
+                    end: inner_start + position.end(),
+                });
+            }
+        }
+        // Never closed: it runs to the end
+        return Some(Found {
+            attrs,
+            start,
+            inner_start,
+            inner_end: html.len(),
+            end: html.len(),
+        });
+    }
+    None
+}
+
+fn has_class(attrs: &[(String, String)], class: &str) -> bool {
+    attr(attrs, "class").map_or(false, |classes| {
+        classes.split_whitespace().any(|c| c == class)
+    })
+}
+
+fn by_class<'a>(name: &'a str, class: &'a str) -> impl Fn(&str, &[(String, String)]) -> bool + 'a {
+    move |element: &str, attrs: &[(String, String)]| {
+        (name.is_empty() || element == name) && has_class(attrs, class)
+    }
+}
+
+fn inner<'a>(html: &'a str, found: &Found) -> &'a str {
+    &html[found.inner_start..found.inner_end]
+}
+
+/// Text of some HTML, with tags dropped and whitespace collapsed
+fn text_of(html: &str) -> String {
+    let tags = Regex::new(r"<[^>]*>").expect("valid tag regex");
+    decode_entities(&tags.replace_all(html, " "))
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// HTML with every element `matches` accepts taken out
+fn remove_elements(html: &str, matches: &dyn Fn(&str, &[(String, String)]) -> bool) -> String {
+    let mut kept = String::with_capacity(html.len());
+    let mut position = 0;
+    while let Some(found) = find_element(html, position, matches) {
+        kept.push_str(&html[position..found.start]);
+        position = found.end;
+    }
+    kept.push_str(&html[position..]);
+    kept
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Read every file in the archive, unpacking a nested `posts.zip`
+fn read_files(data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let mut archive =
+        ZipArchive::new(Cursor::new(data)).map_err(|e| format!("Not a valid archive: {}", e))?;
+    let mut files = Vec::new();
+    for index in 0..archive.len() {
+        let mut file = archive
+            .by_index(index)
+            .map_err(|e| format!("Failed to read archive: {}", e))?;
+        if file.is_dir() || file.size() > MAX_BUNDLE_BYTES as u64 {
+            continue;
+        }
+        let path = file.name().to_string();
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        files.push((path, bytes));
+    }
+
+    // Tumblr wraps the posts in an archive of their own
+    let nested: Vec<Vec<u8>> = files
+        .iter()
+        .filter(|(path, _)| file_name(path) == "posts.zip")
+        .map(|(_, bytes)| bytes.clone())
+        .collect();
+    for bytes in nested {
+        files.extend(read_files(&bytes)?);
+    }
+    Ok(files)
+}
+
+// Medium
+
+/// `2019-03-08_Zine-Fair-Recap-1a2b3c4d5e6f.html` → `zine-fair-recap`
+fn medium_name(path: &str) -> String {
+    let stem = file_name(path).trim_end_matches(".html");
+    let stem = stem.strip_prefix("draft_").unwrap_or(stem);
+    let dated = Regex::new(r"^\d{4}-\d{2}-\d{2}_").expect("valid date prefix regex");
+    let stem = dated.replace(stem, "");
+    let id = Regex::new(r"-[0-9a-f]{10,12}$").expect("valid Medium id regex");
+    slugify(&id.replace(&stem, ""))
+}
+
+/// The path of an absolute URL, e.g. `/@zines/fair-1a2b3c`
+fn url_path(url: &str) -> Option<String> {
+    let rest = url.split_once("://")?.1;
+    let path = &rest[rest.find('/')?..];
+    let path = path.split(|c: char| c == '?' || c == '#').next()?;
+    Some(path.to_string()).filter(|path| path.len() > 1)
+}
+
+fn read_medium_post(path: &str, html: &str, warnings: &mut Vec<String>) -> ArchivePost {
+    let find = |matches: &dyn Fn(&str, &[(String, String)]) -> bool| find_element(html, 0, matches);
+    let field = |name: &'static str| {
+        move |_: &str, attrs: &[(String, String)]| attr(attrs, "data-field") == Some(name)
+    };
+
+    let title = find(&by_class("h1", "p-name"))
+        .or_else(|| find(&|name, _| name == "title"))
+        .map(|found| text_of(inner(html, &found)))
+        .filter(|title| !title.is_empty());
+    let body = find(&field("body")).map_or("", |found| inner(html, &found));
+    // The title and subtitle are repeated at the top of the body
+    let body = remove_elements(body, &|_, attrs| {
+        has_class(attrs, "graf--title") || has_class(attrs, "graf--subtitle")
+    });
+
+    let mut document = html_to_pm(&body);
+    for warning in document.warnings.drain(..) {
+        warn(warnings, format!("{}: {}", path, warning));
+    }
+
+    let mut meta = Map::new();
+    if let Some(subtitle) = find(&field("subtitle")).map(|found| text_of(inner(html, &found))) {
+        if !subtitle.is_empty() {
+            meta.insert("excerpt".to_string(), json!(subtitle));
+        }
+    }
+    let published = find(&by_class("time", "dt-published"));
+    if let Some(date) = published
+        .as_ref()
+        .and_then(|found| attr(&found.attrs, "datetime"))
+    {
+        meta.insert("date".to_string(), json!(date));
+    }
+    if let Some(author) = find(&by_class("a", "p-author")) {
+        meta.insert("author".to_string(), json!(text_of(inner(html, &author))));
+    }
+    let canonical_url = find(&by_class("a", "p-canonical"))
+        .and_then(|found| attr(&found.attrs, "href").map(str::to_string));
+
+    let name = medium_name(path);
+    ArchivePost {
+        page: ImportedPage {
+            collection: "post",
+            title: title.or(document.title).unwrap_or_else(|| name.clone()),
+            name,
+            meta,
+            draft: file_name(path).starts_with("draft_"),
+            content: document.content,
+        },
+        old_path: canonical_url.as_deref().and_then(url_path),
+        canonical_url,
+    }
+}
+
+/// Point every size of a Medium image at one URL, so it's fetched once
+fn dedupe_medium_images(posts: &mut [ArchivePost]) {
+    let mut first_seen: HashMap<String, String> = HashMap::new();
+    for post in posts.iter_mut() {
+        rewrite_images(&mut post.page.content, &mut |src: &str| {
+            // cdn-images-1.medium.com/max/800/1*abc.png: the id is the last segment
+            let id = file_name(src).to_string();
+            if !src.contains("medium.com/") || id.is_empty() {
+                return None;
+            }
+            let first = first_seen.entry(id).or_insert_with(|| src.to_string());
+            Some(first.clone()).filter(|first| first.as_str() != src)
+        });
+    }
+}
+
+// Tumblr
+
+/// Tumblr's `March 8th, 2019 10:04am` as `2019-03-08T10:04:00`
+fn tumblr_date(timestamp: &str) -> Option<String> {
+    let pattern = Regex::new(
+        r"(?i)([a-z]+)\s+(\d{1,2})(?:st|nd|rd|th)?,?\s+(\d{4})(?:\s+(\d{1,2}):(\d{2})\s*([ap]m))?",
+    )
+    .expect("valid Tumblr date regex");
+    let captures = pattern.captures(timestamp)?;
+    let date = chrono::NaiveDate::parse_from_str(
+        &format!("{} {} {}", &captures[1], &captures[2], &captures[3]),
+        "%B %d %Y",
+    )
+    .ok()?;
+    let (hour, minute) = match (captures.get(4), captures.get(5), captures.get(6)) {
+        (Some(hour), Some(minute), Some(half)) => {
+            let hour: u32 = hour.as_str().parse().ok()?;
+            let minute: u32 = minute.as_str().parse().ok()?;
+            let afternoon = half.as_str().eq_ignore_ascii_case("pm");
+            ((hour % 12) + if afternoon { 12 } else { 0 }, minute)
+        }
+        _ => return Some(date.format("%Y-%m-%d").to_string()),
+    };
+    let time = date.and_hms_opt(hour, minute, 0)?;
+    Some(time.format("%Y-%m-%dT%H:%M:%S").to_string())
+}
+
+fn read_tumblr_post(
+    path: &str,
+    id: &str,
+    html: &str,
+    blog_url: Option<&str>,
+    warnings: &mut Vec<String>,
+) -> ArchivePost {
+    let body =
+        find_element(html, 0, &|name, _| name == "body").map_or(html, |found| inner(html, &found));
+    let footer_matches = |name: &str, attrs: &[(String, String)]| {
+        name == "div" && attr(attrs, "id") == Some("footer")
+    };
+    let footer = find_element(body, 0, &footer_matches).map_or("", |found| inner(body, &found));
+
+    let mut meta = Map::new();
+    let timestamp = find_element(footer, 0, &|_, attrs| {
+        attr(attrs, "id") == Some("timestamp")
+    });
+    if let Some(date) = timestamp.and_then(|found| tumblr_date(&text_of(inner(footer, &found)))) {
+        meta.insert("date".to_string(), json!(date));
+    }
+    let mut tags = Vec::new();
+    let mut position = 0;
+    while let Some(found) = find_element(footer, position, &by_class("span", "tag")) {
+        tags.push(text_of(inner(footer, &found)));
+        position = found.end;
+    }
+    if !tags.is_empty() {
+        meta.insert(TAGS_FIELD.to_string(), json!(tags));
+    }
+
+    let title = find_element(body, 0, &|name, _| name == "h1")
+        .map(|found| text_of(inner(body, &found)))
+        .filter(|title| !title.is_empty());
+    let body = remove_elements(body, &|name, attrs| {
+        name == "h1" || footer_matches(name, attrs)
+    });
+    let mut document = html_to_pm(&body);
+    for warning in document.warnings.drain(..) {
+        warn(warnings, format!("{}: {}", path, warning));
+    }
+
+    // Media are beside the posts, linked relatively
+    let base = format!("{}{}", ARCHIVE_ROOT, path);
+    rewrite_images(&mut document.content, &mut |src: &str| {
+        let resolved = resolve_url(&base, src);
+        resolved.strip_prefix(ARCHIVE_ROOT).map(str::to_string)
+    });
+
+    let name = match title.as_deref().map(slugify) {
+        Some(slug) if !slug.is_empty() => slug,
+        _ => format!("post-{}", id),
+    };
+    let canonical_url = blog_url.map(|url| format!("{}/post/{}", url.trim_end_matches('/'), id));
+    ArchivePost {
+        page: ImportedPage {
+            collection: "post",
+            title: title.unwrap_or_else(|| format!("Post {}", id)),
+            name,
+            meta,
+            draft: false,
+            content: document.content,
+        },
+        canonical_url,
+        old_path: Some(format!("/post/{}", id)),
+    }
+}
+
+/// Turn the archive paths posts' images point at into assets, one per
+/// distinct file
+fn archive_assets(
+    posts: &mut [ArchivePost],
+    files: &HashMap<&str, &[u8]>,
+    warnings: &mut Vec<String>,
+) -> Vec<ImportedAsset> {
+    let mut assets: Vec<ImportedAsset> = Vec::new();
+    // Content hash → key of the asset holding those bytes
+    let mut by_hash: HashMap<String, String> = HashMap::new();
+    for post in posts.iter_mut() {
+        rewrite_images(&mut post.page.content, &mut |src: &str| {
+            if src.contains("://") {
+                return None;
+            }
+            let data = match files.get(src) {
+                Some(data) => *data,
+                None => {
+                    warn(warnings, format!("Image {} isn't in the archive", src));
+                    return None;
+                }
+            };
+            let hash = sha256_hex(data);
+            if let Some(key) = by_hash.get(&hash) {
+                return Some(key.clone());
+            }
+            let name = file_name(src).to_string();
+            assets.push(ImportedAsset {
+                key: src.to_string(),
+                mime_type: mime_type_for(&name).to_string(),
+                name,
+                data: data.to_vec(),
+            });
+            by_hash.insert(hash, src.to_string());
+            None
+        });
+    }
+    assets
+}
+
+/// Give posts that share a name distinct ones
+fn unique_names(posts: &mut [ArchivePost]) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for post in posts.iter_mut() {
+        let count = counts.entry(post.page.name.clone()).or_insert(0);
+        *count += 1;
+        if *count > 1 {
+            post.page.name = format!("{}-{}", post.page.name, count);
+        }
+    }
+}
+
+/// Read a Medium or Tumblr export
+///
+/// `blog_url` is where a Tumblr blog was published; Medium exports name
+/// each post's address themselves.
+pub fn read_archive(data: &[u8], blog_url: Option<&str>) -> Result<Archive, String> {
+    let files = read_files(data)?;
+    let tumblr_post = Regex::new(r"(?:^|/)html/(\d+)\.html$").expect("valid Tumblr path regex");
+    let is_medium_post = |path: &str| {
+        path.ends_with(".html") && (path.starts_with("posts/") || path.contains("/posts/"))
+    };
+
+    let platform = if files.iter().any(|(path, _)| tumblr_post.is_match(path)) {
+        Platform::Tumblr
+    } else if files.iter().any(|(path, _)| is_medium_post(path.as_str())) {
+        Platform::Medium
+    } else {
+        return Err("Not a Medium or Tumblr export: no posts found".to_string());
+    };
+
+    let mut posts = Vec::new();
+    let mut warnings = Vec::new();
+    for (path, bytes) in &files {
+        let html = String::from_utf8_lossy(bytes);
+        match platform {
+            Platform::Medium if is_medium_post(path.as_str()) => {
+                posts.push(read_medium_post(path, &html, &mut warnings));
+            }
+            Platform::Tumblr => {
+                if let Some(captures) = tumblr_post.captures(path) {
+                    let post = read_tumblr_post(path, &captures[1], &html, blog_url, &mut warnings);
+                    posts.push(post);
+                }
+            }
+            _ => {}
+        }
+    }
+    posts.sort_by(|a, b| {
+        let date = |post: &ArchivePost| {
+            post.page
+                .meta
+                .get("date")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        date(a).cmp(&date(b))
+    });
+    unique_names(&mut posts);
+
+    let assets = match platform {
+        Platform::Medium => {
+            dedupe_medium_images(&mut posts);
+            Vec::new()
+        }
+        Platform::Tumblr => {
+            let by_path: HashMap<&str, &[u8]> = files
+                .iter()
+                .map(|(path, bytes)| (path.as_str(), bytes.as_slice()))
+                .collect();
+            archive_assets(&mut posts, &by_path, &mut warnings)
+        }
+    };
+
+    Ok(Archive {
+        platform,
+        posts,
+        assets,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_read_medium_post() {
+        let html = r#"<html><head><title>Zine Fair</title></head><body><article>
+            <h1 class="p-name">Zine Fair Recap</h1>
+            <section data-field="subtitle">What we saw</section>
+            <section data-field="body"><h3 class="graf graf--h3 graf--title">Zine Fair Recap</h3>
+            <p class="graf graf--p">Tables <strong>everywhere</strong>.</p></section>
+            <footer><a href="https://medium.com/@zines" class="p-author h-card">Zines</a>
+            <time class="dt-published" datetime="2019-03-08T10:04:00.000Z">March 8</time>
+            <a href="https://medium.com/@zines/zine-fair-1a2b3c4d5e6f" class="p-canonical">Link</a>
+            </footer></article></body></html>"#;
+        let mut warnings = Vec::new();
+
+        let post = read_medium_post(
+            "posts/2019-03-08_Zine-Fair-Recap-1a2b3c4d5e6f.html",
+            html,
+            &mut warnings,
+        );
+
+        assert_eq!(post.page.name, "zine-fair-recap");
+        assert_eq!(post.page.title, "Zine Fair Recap");
+        assert!(!post.page.draft);
+        assert_eq!(post.page.meta["excerpt"], json!("What we saw"));
+        assert_eq!(post.page.meta["author"], json!("Zines"));
+        assert_eq!(post.page.meta["date"], json!("2019-03-08T10:04:00.000Z"));
+        assert_eq!(
+            post.old_path.as_deref(),
+            Some("/@zines/zine-fair-1a2b3c4d5e6f")
+        );
+        // Only the paragraph is left once the repeated title is removed
+        let content = post.page.content["content"].as_array().unwrap();
+        assert_eq!(content.len(), 1);
+        assert_eq!(content[0]["type"], json!("paragraph"));
+    }
+}
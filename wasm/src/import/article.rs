@@ -0,0 +1,606 @@
+//! Article extraction for importing posts from other sites.
+//!
+//! Finds the main content of a web page the way reader modes do: every
+//! paragraph scores its parent and grandparent by how much prose it holds,
+//! and the best-scoring element, discounted by how much of its text is
+//! links, is taken as the article. Navigation, sidebars, comment threads
+//! and share buttons inside it are cut out before it goes to
+//! [`html_to_pm`](super::html::html_to_pm).
+
+use serde_json::Value;
+
+use super::html::{attr, decode_entities, parse_attrs};
+
+/// Elements whose content is never part of the article text
+const RAW: [&str; 6] = ["script", "style", "noscript", "template", "svg", "title"];
+
+/// Elements that never contain anything
+const VOID: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements that are page furniture rather than content
+const UNLIKELY_TAGS: [&str; 4] = ["nav", "aside", "footer", "form"];
+
+/// Class and id words marking page furniture
+const NEGATIVE_WORDS: [&str; 16] = [
+    "ad",
+    "banner",
+    "comment",
+    "comments",
+    "cookie",
+    "footer",
+    "menu",
+    "nav",
+    "newsletter",
+    "promo",
+    "related",
+    "share",
+    "sidebar",
+    "social",
+    "sponsor",
+    "widget",
+];
+
+/// Class and id words marking the content itself
+const POSITIVE_WORDS: [&str; 9] = [
+    "article", "blog", "body", "content", "entry", "main", "post", "story", "text",
+];
+
+/// Attributes lazy-loading scripts keep the real image URL in
+const LAZY_SOURCES: [&str; 3] = ["data-src", "data-original", "data-lazy-src"];
+
+/// The main content of a web page
+#[derive(Debug, Clone, PartialEq)]
+pub struct Article {
+    pub title: Option<String>,
+    /// When the page says it was published, if it does (`YYYY-MM-DD`)
+    pub date: Option<String>,
+    /// HTML of the article element, with furniture removed
+    pub html: String,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    start: usize,
+    end: usize,
+    parent: Option<usize>,
+    unlikely: bool,
+    /// Characters of text inside, and how many of them are in links
+    text: usize,
+    link_text: usize,
+    commas: usize,
+    score: f64,
+    /// Text of headings, to compare with the title
+    heading: String,
+}
+
+fn hint_words(attrs: &[(String, String)]) -> Vec<String> {
+    let hints = format!(
+        "{} {}",
+        attr(attrs, "class").unwrap_or(""),
+        attr(attrs, "id").unwrap_or("")
+    );
+    hints
+        .to_lowercase()
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn is_negative(attrs: &[(String, String)]) -> bool {
+    let words = hint_words(attrs);
+    let has = |list: &[&str]| words.iter().any(|word| list.contains(&word.as_str()));
+    has(&NEGATIVE_WORDS) && !has(&POSITIVE_WORDS)
+}
+
+fn is_positive(attrs: &[(String, String)]) -> bool {
+    hint_words(attrs)
+        .iter()
+        .any(|word| POSITIVE_WORDS.contains(&word.as_str()))
+}
+
+/// Every element of the page with its offsets and text statistics, plus
+/// the contents of `<title>`
+fn scan(html: &str) -> (Vec<Element>, Option<String>) {
+    let lower = html.to_ascii_lowercase();
+    let mut elements: Vec<Element> = Vec::new();
+    let mut stack: Vec<usize> = Vec::new();
+    let mut title = None;
+    let mut position = 0;
+
+    let add_text = |elements: &mut Vec<Element>, stack: &[usize], text: &str| {
+        let text = decode_entities(text);
+        let length: usize = text
+            .split_whitespace()
+            .map(|word| word.chars().count() + 1)
+            .sum();
+        if length == 0 {
+            return;
+        }
+        let in_link = stack.iter().any(|&index| elements[index].name == "a");
+        let commas = text.matches(',').count();
+        for &index in stack {
+            let element = &mut elements[index];
+            element.text += length;
+            element.commas += commas;
+            if in_link {
+                element.link_text += length;
+            }
+            if matches!(element.name.as_str(), "h1" | "h2") {
+                element.heading.push_str(&text);
+            }
+        }
+    };
+
+    while let Some(offset) = html[position..].find('<') {
+        let open = position + offset;
+        add_text(&mut elements, &stack, &html[position..open]);
+
+        if lower[open..].starts_with("<!--") {
+            position = lower[open..]
+                .find("-->")
+                .map_or(html.len(), |end| open + end + 3);
+            continue;
+        }
+        let is_tag = html[open + 1..].chars().next().map_or(false, |c| {
+            c.is_ascii_alphabetic() || c == '/' || c == '!' || c == '?'
+        });
+        if !is_tag {
+            position = open + 1;
+            continue;
+        }
+        let close = match html[open..].find('>') {
+            Some(close) => open + close,
+            None => break,
+        };
+        let tag = &html[open + 1..close];
+        position = close + 1;
+
+        if let Some(name) = tag.strip_prefix('/') {
+            let name = name.trim().to_lowercase();
+            if let Some(depth) = stack
+                .iter()
+                .rposition(|&index| elements[index].name == name)
+            {
+                // Anything left open inside ends where its parent does
+                for &index in &stack[depth + 1..] {
+                    elements[index].end = open;
+                }
+                elements[stack[depth]].end = position;
+                stack.truncate(depth);
+            }
+            continue;
+        }
+        if !tag.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+
+        let name_end = tag
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(tag.len());
+        let name = tag[..name_end].to_lowercase();
+        if RAW.contains(&name.as_str()) && !tag.ends_with('/') {
+            let end = lower[position..]
+                .find(&format!("</{}", name))
+                .map_or(html.len(), |end| position + end);
+            if name == "title" && title.is_none() {
+                title = Some(decode_entities(html[position..end].trim()));
+            }
+            position = lower[end..]
+                .find('>')
+                .map_or(html.len(), |close| end + close + 1);
+            continue;
+        }
+
+        let attrs = parse_attrs(&tag[name_end..]);
+        let parent = stack.last().copied();
+        let unlikely = UNLIKELY_TAGS.contains(&name.as_str())
+            || is_negative(&attrs)
+            || parent.map_or(false, |parent| elements[parent].unlikely);
+        let is_void = VOID.contains(&name.as_str()) || tag.ends_with('/');
+        elements.push(Element {
+            name,
+            attrs,
+            start: open,
+            end: if is_void { position } else { html.len() },
+            parent,
+            unlikely,
+            text: 0,
+            link_text: 0,
+            commas: 0,
+            score: 0.0,
+            heading: String::new(),
+        });
+        if !is_void {
+            stack.push(elements.len() - 1);
+        }
+    }
+    add_text(&mut elements, &stack, &html[position.min(html.len())..]);
+    (elements, title.filter(|title| !title.is_empty()))
+}
+
+/// Let each paragraph of prose score its parent and, at half weight, its
+/// grandparent
+fn score_paragraphs(elements: &mut [Element]) {
+    for index in 0..elements.len() {
+        let paragraph = &elements[index];
+        if !matches!(paragraph.name.as_str(), "p" | "pre" | "td") || paragraph.unlikely {
+            continue;
+        }
+        if paragraph.text < 25 {
+            continue;
+        }
+        let score = 1.0 + paragraph.commas as f64 + (paragraph.text as f64 / 100.0).min(3.0);
+        if let Some(parent) = paragraph.parent {
+            elements[parent].score += score;
+            if let Some(grandparent) = elements[parent].parent {
+                elements[grandparent].score += score / 2.0;
+            }
+        }
+    }
+}
+
+/// The element most likely to be the article
+fn best_candidate(elements: &[Element]) -> Option<usize> {
+    let final_score = |element: &Element| {
+        let link_density = element.link_text as f64 / element.text.max(1) as f64;
+        let mut score = element.score * (1.0 - link_density);
+        if matches!(element.name.as_str(), "article" | "main") || is_positive(&element.attrs) {
+            score *= 1.25;
+        }
+        score
+    };
+    elements
+        .iter()
+        .enumerate()
+        .filter(|(_, element)| element.score > 0.0 && !element.unlikely)
+        .map(|(index, element)| (index, final_score(element)))
+        .fold(
+            None,
+            |best: Option<(usize, f64)>, (index, score)| match best {
+                Some((_, best_score)) if best_score >= score => best,
+                _ => Some((index, score)),
+            },
+        )
+        .map(|(index, _)| index)
+}
+
+fn is_within(elements: &[Element], mut index: usize, ancestor: usize) -> bool {
+    while let Some(parent) = elements[index].parent {
+        if parent == ancestor {
+            return true;
+        }
+        index = parent;
+    }
+    false
+}
+
+fn meta_content<'a>(elements: &'a [Element], key: &str) -> Option<&'a str> {
+    elements
+        .iter()
+        .filter(|element| element.name == "meta")
+        .find(|element| {
+            attr(&element.attrs, "property").or_else(|| attr(&element.attrs, "name")) == Some(key)
+        })
+        .and_then(|element| attr(&element.attrs, "content"))
+        .map(str::trim)
+        .filter(|content| !content.is_empty())
+}
+
+fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+}
+
+/// An `<img>` with a lazy-loaded source swapped in, if it has one
+fn lazy_image(element: &Element) -> Option<String> {
+    let src = attr(&element.attrs, "src").unwrap_or("");
+    if !src.is_empty() && !src.starts_with("data:") {
+        return None;
+    }
+    let lazy = LAZY_SOURCES
+        .iter()
+        .find_map(|name| attr(&element.attrs, name))
+        .filter(|lazy| !lazy.is_empty())?;
+    let alt = attr(&element.attrs, "alt").unwrap_or("");
+    Some(format!(
+        "<img src=\"{}\" alt=\"{}\">",
+        escape_attr(lazy),
+        escape_attr(alt)
+    ))
+}
+
+/// Extract the main content of a web page
+///
+/// The title comes from `og:title`, then a heading in the article, then
+/// `<title>`; a leading heading repeating it is dropped from the content.
+/// Pages without any recognisable prose are returned from `<body>` on.
+pub fn extract_article(html: &str) -> Article {
+    let (mut elements, page_title) = scan(html);
+    score_paragraphs(&mut elements);
+
+    let candidate = best_candidate(&elements)
+        .or_else(|| elements.iter().position(|element| element.name == "body"));
+    let (start, end) = match candidate {
+        Some(index) => (elements[index].start, elements[index].end),
+        None => (0, html.len()),
+    };
+    let inside = |index: usize| match candidate {
+        Some(candidate) => is_within(&elements, index, candidate),
+        None => true,
+    };
+
+    let heading = elements
+        .iter()
+        .enumerate()
+        .find(|(index, element)| element.name == "h1" && inside(*index))
+        .or_else(|| {
+            elements
+                .iter()
+                .enumerate()
+                .find(|(index, element)| element.name == "h2" && inside(*index))
+        })
+        .map(|(index, element)| {
+            let text: Vec<&str> = element.heading.split_whitespace().collect();
+            (index, text.join(" "))
+        })
+        .filter(|(_, text)| !text.is_empty());
+    let title = meta_content(&elements, "og:title")
+        .map(str::to_string)
+        .or_else(|| heading.as_ref().map(|(_, text)| text.clone()))
+        .or(page_title);
+
+    // Cuts and replacements within the article, in document order
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    for (index, element) in elements.iter().enumerate() {
+        let in_edit = edits
+            .last()
+            .map_or(false, |(_, edit_end, _)| element.start < *edit_end);
+        if !inside(index) || in_edit {
+            continue;
+        }
+        let repeats_title = match &heading {
+            Some((heading, text)) => *heading == index && title.as_deref() == Some(text.as_str()),
+            None => false,
+        };
+        if element.unlikely || repeats_title {
+            edits.push((element.start, element.end, String::new()));
+        } else if element.name == "img" {
+            if let Some(image) = lazy_image(element) {
+                edits.push((element.start, element.end, image));
+            }
+        }
+    }
+
+    let mut article = String::with_capacity(end - start);
+    let mut position = start;
+    for (cut_start, cut_end, replacement) in edits {
+        article.push_str(&html[position..cut_start]);
+        article.push_str(&replacement);
+        position = cut_end;
+    }
+    article.push_str(&html[position..end]);
+
+    let date = meta_content(&elements, "article:published_time")
+        .and_then(|date| date.get(..10))
+        .filter(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok())
+        .map(str::to_string);
+
+    Article {
+        title,
+        date,
+        html: article,
+    }
+}
+
+/// Resolve a link or image URL found on the page at `base`
+pub fn resolve_url(base: &str, url: &str) -> String {
+    let url = url.trim();
+    let has_scheme = url
+        .find(|c: char| c == ':' || c == '/' || c == '?' || c == '#')
+        .map_or(false, |end| end > 0 && url[end..].starts_with(':'));
+    if has_scheme || url.is_empty() || url.starts_with('#') {
+        return url.to_string();
+    }
+
+    let scheme_end = match base.find("://") {
+        Some(end) => end,
+        None => return url.to_string(),
+    };
+    if url.starts_with("//") {
+        return format!("{}:{}", &base[..scheme_end], url);
+    }
+    let authority_end = base[scheme_end + 3..]
+        .find(|c: char| c == '/' || c == '?' || c == '#')
+        .map_or(base.len(), |end| scheme_end + 3 + end);
+    let origin = &base[..authority_end];
+    let path = base[authority_end..]
+        .split(|c: char| c == '?' || c == '#')
+        .next()
+        .unwrap_or("");
+    if url.starts_with('?') {
+        return format!("{}{}{}", origin, path, url);
+    }
+
+    let joined = if url.starts_with('/') {
+        url.to_string()
+    } else {
+        let directory = &path[..path.rfind('/').map_or(0, |slash| slash + 1)];
+        format!("/{}{}", directory.trim_start_matches('/'), url)
+    };
+    let (joined_path, suffix) = match joined.find(|c: char| c == '?' || c == '#') {
+        Some(index) => joined.split_at(index),
+        None => (joined.as_str(), ""),
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    let parts: Vec<&str> = joined_path.split('/').skip(1).collect();
+    for (index, segment) in parts.iter().enumerate() {
+        match *segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+        // A trailing `.` or `..` still names a directory
+        if index == parts.len() - 1 && matches!(*segment, "." | "..") {
+            segments.push("");
+        }
+    }
+    format!("{}/{}{}", origin, segments.join("/"), suffix)
+}
+
+/// Make the links and image sources of imported content absolute, so they
+/// still work once it's on another site
+///
+/// Returns the http(s) image URLs, once each, in document order.
+pub fn absolutize_urls(node: &mut Value, base: &str) -> Vec<String> {
+    let mut images = Vec::new();
+    absolutize_node(node, base, &mut images);
+    images
+}
+
+fn absolutize_node(node: &mut Value, base: &str, images: &mut Vec<String>) {
+    let is_image = node.get("type").and_then(Value::as_str) == Some("image");
+    if let Some(Value::String(src)) = node.get_mut("attrs").and_then(|attrs| attrs.get_mut("src")) {
+        let resolved = resolve_url(base, src);
+        *src = resolved;
+        let is_remote = src.starts_with("https://") || src.starts_with("http://");
+        if is_image && is_remote && !images.contains(src) {
+            images.push(src.clone());
+        }
+    }
+    if let Some(Value::Array(marks)) = node.get_mut("marks") {
+        for mark in marks {
+            if let Some(Value::String(href)) = mark
+                .get_mut("attrs")
+                .and_then(|attrs| attrs.get_mut("href"))
+            {
+                let resolved = resolve_url(base, href);
+                *href = resolved;
+            }
+        }
+    }
+    if let Some(Value::Array(children)) = node.get_mut("content") {
+        for child in children {
+            absolutize_node(child, base, images);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_extract_article() {
+        let html = r#"<html><head>
+            <title>Risograph tips | Old Blog</title>
+            <meta property="article:published_time" content="2019-03-08T10:00:00Z">
+            <script>var p = "<p>not this</p>";</script>
+            </head><body>
+            <nav><a href="/">Home</a> <a href="/about">About</a></nav>
+            <div class="wrapper">
+              <div class="post-content">
+                <h1>Risograph tips</h1>
+                <p>Riso drums are fussy, so let the ink settle, clean the screen, and
+                   print a few test sheets before the real run.</p>
+                <p>Soy inks take a day to dry, which means stacking, collating and
+                   trimming all have to wait until tomorrow.</p>
+                <img src="data:image/gif;base64,R0lGOD" data-src="/img/drum.jpg" alt="Drum">
+                <div class="share-buttons"><a href="https://example.com/share">Share</a></div>
+              </div>
+              <aside class="sidebar"><p>Subscribe to the newsletter for more posts like
+                 this one, every single week, forever.</p></aside>
+            </div>
+            <div id="comments"><p>Great post, thanks, I learned a lot from this, really!</p></div>
+            </body></html>"#;
+
+        let article = extract_article(html);
+        assert_eq!(article.title.as_deref(), Some("Risograph tips"));
+        assert_eq!(article.date.as_deref(), Some("2019-03-08"));
+        assert!(article.html.starts_with("<div class=\"post-content\">"));
+        assert!(article.html.contains("Riso drums are fussy"));
+        assert!(article
+            .html
+            .contains("<img src=\"/img/drum.jpg\" alt=\"Drum\">"));
+        assert!(!article.html.contains("<h1>"));
+        assert!(!article.html.contains("Share"));
+        assert!(!article.html.contains("newsletter"));
+        assert!(!article.html.contains("Great post"));
+        assert!(!article.html.contains("Home"));
+
+        let bare = extract_article("<title>Hi</title><body><p>Short</p></body>");
+        assert_eq!(bare.title.as_deref(), Some("Hi"));
+        assert_eq!(bare.html, "<body><p>Short</p></body>");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resolve_url() {
+        let base = "https://old.example.com/blog/2019/tips.html?ref=rss";
+        assert_eq!(
+            resolve_url(base, "drum.jpg"),
+            "https://old.example.com/blog/2019/drum.jpg"
+        );
+        assert_eq!(
+            resolve_url(base, "../index.html"),
+            "https://old.example.com/blog/index.html"
+        );
+        assert_eq!(
+            resolve_url(base, "/img/a.png?w=2"),
+            "https://old.example.com/img/a.png?w=2"
+        );
+        assert_eq!(
+            resolve_url(base, "//cdn.example.com/a.png"),
+            "https://cdn.example.com/a.png"
+        );
+        assert_eq!(
+            resolve_url(base, "?page=2"),
+            "https://old.example.com/blog/2019/tips.html?page=2"
+        );
+        assert_eq!(resolve_url(base, "#notes"), "#notes");
+        assert_eq!(
+            resolve_url(base, "mailto:me@example.com"),
+            "mailto:me@example.com"
+        );
+        assert_eq!(
+            resolve_url("https://example.com", "a/b"),
+            "https://example.com/a/b"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_absolutize_urls() {
+        let mut doc = json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [
+                { "type": "text", "text": "Earlier", "marks": [
+                    { "type": "link", "attrs": { "href": "/2018/intro" } }
+                ]},
+                { "type": "image", "attrs": { "src": "a.png" } },
+                { "type": "image", "attrs": { "src": "https://old.example.com/a.png" } }
+            ]}
+        ]});
+
+        let images = absolutize_urls(&mut doc, "https://old.example.com/post/");
+        assert_eq!(
+            images,
+            vec![
+                "https://old.example.com/post/a.png",
+                "https://old.example.com/a.png"
+            ]
+        );
+        assert_eq!(
+            doc["content"][0]["content"][0]["marks"][0]["attrs"]["href"],
+            json!("https://old.example.com/2018/intro")
+        );
+    }
+}
@@ -0,0 +1,395 @@
+//! Theme and starter bundles, installed from a URL.
+//!
+//! A bundle is a zip archive containing:
+//!
+//! - `manifest.json`: `{name, type, version, description, files}`, where
+//!   `type` is "site" or "theme" and `files` maps every other path in the
+//!   archive to its SHA-256
+//! - `project.loro`: the exported project document
+//! - `files/{id}`: each file's full document, as saved to IndexedDB
+//! - `assets/{id}`: uploaded asset bytes
+//!
+//! Paths missing from the manifest, or whose hash doesn't match, reject the
+//! whole bundle. A gallery that publishes a bundle's own hash can pass it
+//! along so a swapped download is rejected too. File and asset ids must be
+//! short ids or UUIDs, and are swapped for new short ids before anything is installed, so a
+//! bundle can't write over files already in the workspace.
+
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Cursor, Read};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use zip::ZipArchive;
+
+use crate::model::remap::remap_document;
+use crate::model::short_id::{is_short_id, unique_short_id};
+use crate::types::ProjectType;
+
+pub const MANIFEST_PATH: &str = "manifest.json";
+pub const PROJECT_PATH: &str = "project.loro";
+const FILES_PREFIX: &str = "files/";
+const ASSETS_PREFIX: &str = "assets/";
+
+/// Largest bundle, and largest single entry, that will be installed
+pub const MAX_BUNDLE_BYTES: usize = 50 * 1024 * 1024;
+/// Largest total of all entries once unpacked
+pub const MAX_UNPACKED_BYTES: usize = 4 * MAX_BUNDLE_BYTES;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BundleManifest {
+    pub name: String,
+    /// "site" or "theme"
+    #[serde(rename = "type")]
+    pub project_type: String,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Archive path → SHA-256 (hex)
+    pub files: BTreeMap<String, String>,
+}
+
+impl BundleManifest {
+    pub fn project_type(&self) -> Result<ProjectType, String> {
+        match self.project_type.as_str() {
+            "site" => Ok(ProjectType::Site),
+            "theme" => Ok(ProjectType::Theme),
+            other => Err(format!("Unknown bundle type: {}", other)),
+        }
+    }
+}
+
+/// A verified bundle, ready to install
+#[derive(Debug, Clone)]
+pub struct Bundle {
+    pub manifest: BundleManifest,
+    pub project: Vec<u8>,
+    /// File id → full document
+    pub files: Vec<(String, Vec<u8>)>,
+    /// Asset id → bytes
+    pub assets: Vec<(String, Vec<u8>)>,
+}
+
+/// SHA-256 of some bytes, as lowercase hex
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Id from a `files/{id}` or `assets/{id}` path, if it's a short id or UUID
+fn entry_id<'a>(path: &'a str, prefix: &str) -> Option<&'a str> {
+    path.strip_prefix(prefix)
+        .filter(|id| is_short_id(id) || (id.len() == 36 && Uuid::parse_str(id).is_ok()))
+}
+
+/// Read at most `limit` bytes of an entry, whatever the archive says its
+/// size is, since a zip's headers can claim anything
+fn read_limited(reader: impl Read, limit: usize, path: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    reader
+        .take(limit as u64 + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    if bytes.len() > limit {
+        return Err(format!("{} is too large", path));
+    }
+    Ok(bytes)
+}
+
+impl Bundle {
+    /// The bundle with a new short id for every file and asset, and every
+    /// reference to one rewritten to match
+    ///
+    /// Installing a bundle twice then gives two separate projects, and
+    /// nothing in a bundle can take the id of a file already stored.
+    pub fn with_fresh_ids(self) -> Result<Bundle, String> {
+        let mut ids: HashMap<String, String> = HashMap::new();
+        for (id, _) in self.files.iter().chain(&self.assets) {
+            let fresh = unique_short_id(|candidate| {
+                ids.contains_key(candidate) || ids.values().any(|new| new == candidate)
+            })?;
+            ids.insert(id.clone(), fresh);
+        }
+
+        let files = self
+            .files
+            .iter()
+            .map(|(id, bytes)| Ok((ids[id].clone(), remap_document(bytes, &ids)?)))
+            .collect::<Result<Vec<_>, String>>()?;
+        let assets = self
+            .assets
+            .into_iter()
+            .map(|(id, bytes)| (ids[&id].clone(), bytes))
+            .collect();
+        Ok(Bundle {
+            project: remap_document(&self.project, &ids)?,
+            files,
+            assets,
+            manifest: self.manifest,
+        })
+    }
+}
+
+/// Unpack and verify a bundle
+///
+/// `expected_sha256` is the hash of the whole archive, when the caller has one.
+pub fn read_bundle(data: &[u8], expected_sha256: Option<&str>) -> Result<Bundle, String> {
+    if data.len() > MAX_BUNDLE_BYTES {
+        return Err(format!(
+            "Bundle is too large ({} bytes, limit {})",
+            data.len(),
+            MAX_BUNDLE_BYTES
+        ));
+    }
+    if let Some(expected) = expected_sha256 {
+        let actual = sha256_hex(data);
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            return Err(format!(
+                "Bundle hash mismatch: expected {}, got {}",
+                expected, actual
+            ));
+        }
+    }
+
+    let mut archive =
+        ZipArchive::new(Cursor::new(data)).map_err(|e| format!("Not a valid bundle: {}", e))?;
+
+    let manifest: BundleManifest = {
+        let mut file = archive
+            .by_name(MANIFEST_PATH)
+            .map_err(|_| format!("Bundle is missing {}", MANIFEST_PATH))?;
+        let bytes = read_limited(&mut file, MAX_BUNDLE_BYTES, MANIFEST_PATH)?;
+        serde_json::from_slice(&bytes).map_err(|e| format!("Invalid {}: {}", MANIFEST_PATH, e))?
+    };
+    manifest.project_type()?;
+
+    let mut bundle = Bundle {
+        manifest,
+        project: Vec::new(),
+        files: Vec::new(),
+        assets: Vec::new(),
+    };
+    let mut has_project = false;
+    let mut unpacked = 0;
+
+    for index in 0..archive.len() {
+        let mut file = archive
+            .by_index(index)
+            .map_err(|e| format!("Failed to read bundle: {}", e))?;
+        let path = file.name().to_string();
+        if file.is_dir() || path == MANIFEST_PATH {
+            continue;
+        }
+
+        let expected = bundle
+            .manifest
+            .files
+            .get(&path)
+            .ok_or_else(|| format!("{} is not listed in the manifest", path))?
+            .clone();
+        if file.size() > MAX_BUNDLE_BYTES as u64 {
+            return Err(format!("{} is too large", path));
+        }
+        let limit = MAX_BUNDLE_BYTES.min(MAX_UNPACKED_BYTES - unpacked);
+        let bytes = read_limited(&mut file, limit, &path)?;
+        unpacked += bytes.len();
+        if !sha256_hex(&bytes).eq_ignore_ascii_case(&expected) {
+            return Err(format!("{} does not match its hash in the manifest", path));
+        }
+
+        if path == PROJECT_PATH {
+            bundle.project = bytes;
+            has_project = true;
+        } else if let Some(id) = entry_id(&path, FILES_PREFIX) {
+            bundle.files.push((id.to_string(), bytes));
+        } else if let Some(id) = entry_id(&path, ASSETS_PREFIX) {
+            bundle.assets.push((id.to_string(), bytes));
+        } else {
+            return Err(format!("Unexpected path in bundle: {}", path));
+        }
+    }
+
+    if !has_project {
+        return Err(format!("Bundle is missing {}", PROJECT_PATH));
+    }
+    let found = 1 + bundle.files.len() + bundle.assets.len();
+    if found != bundle.manifest.files.len() {
+        return Err("Bundle is missing files listed in its manifest".to_string());
+    }
+
+    Ok(bundle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+    use wasm_bindgen_test::*;
+
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut writer = zip::ZipWriter::new(&mut buffer);
+            let options = zip::write::FileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored);
+            for (name, bytes) in entries {
+                writer.start_file(*name, options).unwrap();
+                writer.write_all(bytes).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+        buffer.into_inner()
+    }
+
+    fn manifest(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let hashes: BTreeMap<&str, String> = files
+            .iter()
+            .map(|(name, bytes)| (*name, sha256_hex(bytes)))
+            .collect();
+        json!({ "name": "Zine", "type": "theme", "version": "1.0.0", "files": hashes })
+            .to_string()
+            .into_bytes()
+    }
+
+    const FILE_ID: &str = "k3v9x2mq";
+    const ASSET_ID: &str = "9f8e7d6c-5b4a-4392-8170-6f5e4d3c2b1a";
+
+    #[wasm_bindgen_test]
+    fn test_read_bundle() {
+        let file_path = format!("files/{}", FILE_ID);
+        let asset_path = format!("assets/{}", ASSET_ID);
+        let files: [(&str, &[u8]); 3] = [
+            ("project.loro", b"project"),
+            (&file_path, b"template"),
+            (&asset_path, b"logo"),
+        ];
+        let manifest = manifest(&files);
+        let mut entries = vec![("manifest.json", &manifest[..])];
+        entries.extend_from_slice(&files);
+        let data = zip(&entries);
+
+        let bundle = read_bundle(&data, Some(&sha256_hex(&data))).expect("valid bundle");
+        assert_eq!(bundle.manifest.name, "Zine");
+        assert!(matches!(
+            bundle.manifest.project_type(),
+            Ok(ProjectType::Theme)
+        ));
+        assert_eq!(bundle.project, b"project");
+        assert_eq!(
+            bundle.files,
+            vec![(FILE_ID.to_string(), b"template".to_vec())]
+        );
+        assert_eq!(
+            bundle.assets,
+            vec![(ASSET_ID.to_string(), b"logo".to_vec())]
+        );
+
+        assert!(read_bundle(&data, Some(&sha256_hex(b"other"))).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_read_bundle_rejects_tampering() {
+        let manifest = manifest(&[("project.loro", b"project")]);
+
+        let changed = zip(&[("manifest.json", &manifest), ("project.loro", b"changed")]);
+        assert!(read_bundle(&changed, None)
+            .unwrap_err()
+            .contains("does not match"));
+
+        let extra = zip(&[
+            ("manifest.json", &manifest),
+            ("project.loro", b"project"),
+            ("files/extra", b"extra"),
+        ]);
+        assert!(read_bundle(&extra, None)
+            .unwrap_err()
+            .contains("not listed"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_read_bundle_requires_valid_ids() {
+        let files: [(&str, &[u8]); 2] = [("project.loro", b"project"), ("files/abc", b"x")];
+        let manifest = manifest(&files);
+        let mut entries = vec![("manifest.json", &manifest[..])];
+        entries.extend_from_slice(&files);
+        assert!(read_bundle(&zip(&entries), None)
+            .unwrap_err()
+            .contains("Unexpected path"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_read_limited_ignores_claimed_size() {
+        // A zip bomb's headers claim a small size; only the bytes count
+        let endless = std::io::repeat(0);
+        assert!(read_limited(endless, 1024, "files/bomb")
+            .unwrap_err()
+            .contains("too large"));
+        assert_eq!(read_limited(&b"fits"[..], 4, "files/ok").unwrap(), b"fits");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_with_fresh_ids() {
+        use loro::LoroDoc;
+
+        let project = LoroDoc::new();
+        let tree = project.get_tree("files");
+        let node = tree.create(None).unwrap();
+        tree.get_meta(node).unwrap().insert("id", FILE_ID).unwrap();
+        project
+            .get_map("meta")
+            .insert("logo", format!("/assets/{}/logo.png", ASSET_ID))
+            .unwrap();
+        project.commit();
+
+        let file = LoroDoc::new();
+        file.get_map("meta").insert("id", FILE_ID).unwrap();
+        file.commit();
+
+        let bundle = Bundle {
+            manifest: BundleManifest {
+                name: "Zine".to_string(),
+                project_type: "theme".to_string(),
+                version: None,
+                description: None,
+                files: BTreeMap::new(),
+            },
+            project: project.export(loro::ExportMode::Snapshot).unwrap(),
+            files: vec![(
+                FILE_ID.to_string(),
+                file.export(loro::ExportMode::Snapshot).unwrap(),
+            )],
+            assets: vec![(ASSET_ID.to_string(), b"logo".to_vec())],
+        };
+
+        let first = bundle.clone().with_fresh_ids().unwrap();
+        let second = bundle.with_fresh_ids().unwrap();
+        // Installed twice, the copies share nothing with each other or the bundle
+        for installed in &[&first, &second] {
+            assert!(is_short_id(&installed.files[0].0));
+            assert!(is_short_id(&installed.assets[0].0));
+            assert_eq!(installed.assets[0].1, b"logo");
+        }
+        assert_ne!(first.files[0].0, second.files[0].0);
+        assert_ne!(first.assets[0].0, second.assets[0].0);
+
+        // References follow the files to their new ids
+        let file_id = &first.files[0].0;
+        let copy = LoroDoc::new();
+        copy.import(&first.files[0].1).unwrap();
+        assert_eq!(
+            copy.get_map("meta").get("id").unwrap().get_deep_value(),
+            file_id.as_str().into()
+        );
+        let copy = LoroDoc::new();
+        copy.import(&first.project).unwrap();
+        let value = serde_json::to_string(&copy.get_deep_value()).unwrap();
+        assert!(value.contains(file_id.as_str()));
+        assert!(value.contains(&format!("/assets/{}/logo.png", first.assets[0].0)));
+        assert!(!value.contains(FILE_ID) && !value.contains(ASSET_ID));
+    }
+}
@@ -0,0 +1,255 @@
+//! CSV importer for user-defined collections.
+//!
+//! The first row is the header. Each following row becomes one file, with
+//! columns mapped onto collection fields and every cell coerced to its
+//! field's type. Problems are collected per row so a single bad line does
+//! not stop the rest of the sheet from importing.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+use crate::types::FieldDefinition;
+
+/// Mapping target that sets the file name rather than a field
+pub const NAME_TARGET: &str = "name";
+
+/// One data row after mapping and coercion
+#[derive(Debug, Clone, Default)]
+pub struct CsvRow {
+    /// 1-based position among the data rows (the header is not counted)
+    pub row: usize,
+    pub name: Option<String>,
+    pub values: Map<String, Value>,
+    pub errors: Vec<String>,
+}
+
+/// Parse CSV text (RFC 4180: quoted fields, doubled quotes, CRLF or LF)
+pub fn parse_csv(data: &str) -> Result<Vec<Vec<String>>, String> {
+    let data = data.strip_prefix('\u{feff}').unwrap_or(data);
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut chars = data.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                '\n' => {
+                    line += 1;
+                    field.push(c);
+                }
+                _ => field.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if field.is_empty() => in_quotes = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                line += 1;
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return Err(format!(
+            "Unterminated quoted field starting before line {}",
+            line
+        ));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+
+    // Blank lines carry no data
+    rows.retain(|row| !(row.len() == 1 && row[0].trim().is_empty()));
+    Ok(rows)
+}
+
+/// Map CSV rows onto collection fields
+///
+/// `mapping` goes from column header to field name; the special target
+/// `"name"` sets the file name. Columns that are not mapped are ignored.
+pub fn map_rows(
+    data: &str,
+    mapping: &HashMap<String, String>,
+    fields: &[FieldDefinition],
+) -> Result<Vec<CsvRow>, String> {
+    let mut rows = parse_csv(data)?.into_iter();
+    let header = rows.next().ok_or("CSV has no header row")?;
+    let header: Vec<String> = header
+        .iter()
+        .map(|column| column.trim().to_string())
+        .collect();
+
+    // Resolve every mapped column up front so a bad mapping fails the whole import
+    let mut columns = Vec::new();
+    for (column, target) in mapping {
+        let index = header
+            .iter()
+            .position(|h| h == column)
+            .ok_or_else(|| format!("Column not found in CSV: {}", column))?;
+        let field = if target == NAME_TARGET {
+            None
+        } else {
+            Some(
+                fields
+                    .iter()
+                    .find(|field| &field.name == target)
+                    .ok_or_else(|| format!("Field not found in collection: {}", target))?,
+            )
+        };
+        columns.push((index, target.as_str(), field));
+    }
+    columns.sort_by_key(|(index, _, _)| *index);
+
+    let mut result = Vec::new();
+    for (i, cells) in rows.enumerate() {
+        let mut row = CsvRow {
+            row: i + 1,
+            ..Default::default()
+        };
+
+        for (index, target, field) in &columns {
+            let cell = cells.get(*index).map(|c| c.trim()).unwrap_or("");
+            if cell.is_empty() {
+                continue;
+            }
+            match field {
+                None => row.name = Some(cell.to_string()),
+                Some(field) => match field.field_type.coerce(cell) {
+                    Ok(value) => {
+                        row.values.insert(target.to_string(), value);
+                    }
+                    Err(e) => row.errors.push(format!("{}: {}", target, e)),
+                },
+            }
+        }
+
+        for field in fields.iter().filter(|field| field.required) {
+            let failed = row
+                .errors
+                .iter()
+                .any(|e| e.starts_with(&format!("{}:", field.name)));
+            if !row.values.contains_key(&field.name) && !failed {
+                row.errors
+                    .push(format!("{}: required field is missing", field.name));
+            }
+        }
+
+        result.push(row);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::FieldType;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn field(name: &str, field_type: FieldType, required: bool) -> FieldDefinition {
+        FieldDefinition {
+            name: name.to_string(),
+            field_type,
+            required,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_csv_quoting() {
+        let rows = parse_csv(
+            "title,notes\r\n\"Launch, party\",\"She said \"\"hi\"\"\nthen left\"\r\n\r\nplain,\n",
+        )
+        .expect("Failed to parse CSV");
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[1][0], "Launch, party");
+        assert_eq!(rows[1][1], "She said \"hi\"\nthen left");
+        assert_eq!(rows[2], vec!["plain".to_string(), String::new()]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_csv_unterminated_quote() {
+        assert!(parse_csv("a,\"b\n1,2").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_map_rows_coerces_and_reports() {
+        let fields = vec![
+            field("date", FieldType::DateTime, true),
+            field("capacity", FieldType::Number, false),
+            field("tags", FieldType::List, false),
+        ];
+        let mapping: HashMap<String, String> = vec![
+            ("Event", "name"),
+            ("When", "date"),
+            ("Seats", "capacity"),
+            ("Tags", "tags"),
+        ]
+        .into_iter()
+        .map(|(column, target)| (column.to_string(), target.to_string()))
+        .collect();
+
+        let data = "Event,When,Seats,Tags,Ignored\n\
+                    Reading,21/03/2025,40,\"poetry; zines\",x\n\
+                    Workshop,soon,lots,,y\n\
+                    Picnic,,,,z\n";
+        let rows = map_rows(data, &mapping, &fields).expect("Failed to map rows");
+
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].name.as_deref(), Some("Reading"));
+        assert_eq!(rows[0].values["date"], json!("2025-03-21"));
+        assert_eq!(rows[0].values["capacity"], json!(40));
+        assert_eq!(rows[0].values["tags"], json!(["poetry", "zines"]));
+        assert!(rows[0].errors.is_empty());
+
+        assert_eq!(rows[1].row, 2);
+        assert_eq!(rows[1].errors.len(), 2);
+
+        assert_eq!(
+            rows[2].errors,
+            vec!["date: required field is missing".to_string()]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_map_rows_unknown_field() {
+        let mut mapping = HashMap::new();
+        mapping.insert("When".to_string(), "starts".to_string());
+
+        let result = map_rows("When\n2025-01-01\n", &mapping, &[]);
+        assert!(result.is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_coerce_field_types() {
+        assert_eq!(FieldType::Number.coerce("2.5").unwrap(), json!(2.5));
+        assert_eq!(
+            FieldType::DateTime.coerce("2025-03-21 19:30").unwrap(),
+            json!("2025-03-21T19:30:00")
+        );
+        assert_eq!(
+            FieldType::Map.coerce(r#"{"room": "B"}"#).unwrap(),
+            json!({ "room": "B" })
+        );
+        assert!(FieldType::Map.coerce("room B").is_err());
+        assert!(FieldType::Blob.coerce("data").is_err());
+    }
+}
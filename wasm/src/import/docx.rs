@@ -0,0 +1,754 @@
+//! Word (DOCX) importer.
+//!
+//! A DOCX file is a zip archive. The body lives in `word/document.xml`,
+//! links and embedded images are resolved through
+//! `word/_rels/document.xml.rels`, and `word/numbering.xml` says whether a
+//! list level is bulleted or numbered. Only the structure the editor can
+//! represent is kept: headings, paragraphs, lists, bold/italic, links,
+//! line breaks and images.
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek};
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use serde_json::{json, Value};
+use zip::ZipArchive;
+
+use super::{mime_type_for, ImportedAsset, ImportedDocument};
+
+const DOCUMENT_PATH: &str = "word/document.xml";
+const RELATIONSHIPS_PATH: &str = "word/_rels/document.xml.rels";
+const NUMBERING_PATH: &str = "word/numbering.xml";
+
+/// numId -> list level -> whether that level is numbered
+type NumberingFormats = HashMap<String, HashMap<usize, bool>>;
+
+#[derive(Debug, Clone)]
+struct Relationship {
+    target: String,
+    external: bool,
+}
+
+#[derive(Debug, Default)]
+struct Paragraph {
+    style: Option<String>,
+    numbering: Option<(String, usize)>,
+    inlines: Vec<Value>,
+}
+
+#[derive(Debug)]
+struct ListEntry {
+    level: usize,
+    ordered: bool,
+    node: Value,
+}
+
+/// Convert a DOCX file into ProseMirror JSON
+///
+/// Images are returned as assets; their `src` in the document is the
+/// relationship id until the caller swaps in the stored asset URL.
+pub fn docx_to_pm(data: &[u8]) -> Result<ImportedDocument, String> {
+    let mut archive =
+        ZipArchive::new(Cursor::new(data)).map_err(|e| format!("Not a valid DOCX file: {}", e))?;
+
+    let document = read_text(&mut archive, DOCUMENT_PATH)?
+        .ok_or_else(|| format!("DOCX file is missing {}", DOCUMENT_PATH))?;
+    let relationships = match read_text(&mut archive, RELATIONSHIPS_PATH)? {
+        Some(xml) => parse_relationships(&xml)?,
+        None => HashMap::new(),
+    };
+    let numbering = match read_text(&mut archive, NUMBERING_PATH)? {
+        Some(xml) => parse_numbering(&xml)?,
+        None => HashMap::new(),
+    };
+
+    let mut parser = DocumentParser::new(&relationships);
+    parser.parse(&document)?;
+
+    let mut warnings = parser.warnings;
+    let mut assets = Vec::new();
+    for key in &parser.image_keys {
+        match relationships.get(key) {
+            Some(relationship) if !relationship.external => {
+                let path = resolve_part_path(&relationship.target);
+                match read_bytes(&mut archive, &path)? {
+                    Some(bytes) => {
+                        let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+                        assets.push(ImportedAsset {
+                            key: key.clone(),
+                            mime_type: mime_type_for(&name).to_string(),
+                            name,
+                            data: bytes,
+                        });
+                    }
+                    None => warnings.push(format!("Image {} is missing from the archive", path)),
+                }
+            }
+            _ => warnings.push(format!("Linked image {} was not imported", key)),
+        }
+    }
+
+    Ok(ImportedDocument {
+        title: parser.title,
+        content: build_doc(parser.paragraphs, &numbering),
+        assets,
+        warnings,
+    })
+}
+
+fn read_bytes<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    path: &str,
+) -> Result<Option<Vec<u8>>, String> {
+    let mut file = match archive.by_name(path) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(format!("Failed to read {}: {}", path, e)),
+    };
+
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    Ok(Some(bytes))
+}
+
+fn read_text<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    path: &str,
+) -> Result<Option<String>, String> {
+    match read_bytes(archive, path)? {
+        Some(bytes) => String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|e| format!("{} is not valid UTF-8: {}", path, e)),
+        None => Ok(None),
+    }
+}
+
+/// Relationship targets are relative to `word/` unless they start with `/`
+fn resolve_part_path(target: &str) -> String {
+    match target.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => format!("word/{}", target),
+    }
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Option<String> {
+    element
+        .try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|attr| attr.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+/// `<w:b/>` turns a property on; `<w:b w:val="0"/>` turns it off
+fn is_toggled_on(element: &BytesStart) -> bool {
+    match attribute(element, "w:val") {
+        Some(value) => !matches!(value.as_str(), "0" | "false" | "off" | "none"),
+        None => true,
+    }
+}
+
+fn parse_relationships(xml: &str) -> Result<HashMap<String, Relationship>, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut relationships = HashMap::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) if e.name().as_ref() == b"Relationship" => {
+                if let (Some(id), Some(target)) = (attribute(&e, "Id"), attribute(&e, "Target")) {
+                    let external = attribute(&e, "TargetMode").as_deref() == Some("External");
+                    relationships.insert(id, Relationship { target, external });
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("Failed to parse relationships: {}", e)),
+            _ => {}
+        }
+    }
+
+    Ok(relationships)
+}
+
+fn parse_numbering(xml: &str) -> Result<NumberingFormats, String> {
+    let mut reader = Reader::from_str(xml);
+    let mut abstract_levels: HashMap<String, HashMap<usize, bool>> = HashMap::new();
+    let mut num_to_abstract: HashMap<String, String> = HashMap::new();
+
+    let mut current_abstract: Option<String> = None;
+    let mut current_level: Option<usize> = None;
+    let mut current_num: Option<String> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) | Ok(Event::Empty(e)) => match e.name().as_ref() {
+                b"w:abstractNum" => current_abstract = attribute(&e, "w:abstractNumId"),
+                b"w:lvl" => current_level = attribute(&e, "w:ilvl").and_then(|l| l.parse().ok()),
+                b"w:numFmt" => {
+                    if let (Some(abstract_id), Some(level)) = (&current_abstract, current_level) {
+                        let ordered = !matches!(
+                            attribute(&e, "w:val").as_deref(),
+                            Some("bullet") | Some("none") | None
+                        );
+                        abstract_levels
+                            .entry(abstract_id.clone())
+                            .or_default()
+                            .insert(level, ordered);
+                    }
+                }
+                b"w:num" => current_num = attribute(&e, "w:numId"),
+                b"w:abstractNumId" => {
+                    if let (Some(num_id), Some(abstract_id)) =
+                        (&current_num, attribute(&e, "w:val"))
+                    {
+                        num_to_abstract.insert(num_id.clone(), abstract_id);
+                    }
+                }
+                _ => {}
+            },
+            Ok(Event::End(e)) => match e.name().as_ref() {
+                b"w:abstractNum" => current_abstract = None,
+                b"w:lvl" => current_level = None,
+                b"w:num" => current_num = None,
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("Failed to parse numbering: {}", e)),
+            _ => {}
+        }
+    }
+
+    Ok(num_to_abstract
+        .into_iter()
+        .filter_map(|(num_id, abstract_id)| {
+            abstract_levels
+                .get(&abstract_id)
+                .map(|levels| (num_id, levels.clone()))
+        })
+        .collect())
+}
+
+/// Streaming walk over `word/document.xml`, collecting paragraphs
+struct DocumentParser<'a> {
+    relationships: &'a HashMap<String, Relationship>,
+    title: Option<String>,
+    paragraphs: Vec<Paragraph>,
+    image_keys: Vec<String>,
+    warnings: Vec<String>,
+
+    paragraph: Option<Paragraph>,
+    in_paragraph_props: bool,
+    in_run_props: bool,
+    in_text: bool,
+    bold: bool,
+    italic: bool,
+    link: Option<String>,
+    image_alt: Option<String>,
+    warned_tables: bool,
+}
+
+impl<'a> DocumentParser<'a> {
+    fn new(relationships: &'a HashMap<String, Relationship>) -> Self {
+        DocumentParser {
+            relationships,
+            title: None,
+            paragraphs: Vec::new(),
+            image_keys: Vec::new(),
+            warnings: Vec::new(),
+            paragraph: None,
+            in_paragraph_props: false,
+            in_run_props: false,
+            in_text: false,
+            bold: false,
+            italic: false,
+            link: None,
+            image_alt: None,
+            warned_tables: false,
+        }
+    }
+
+    fn parse(&mut self, xml: &str) -> Result<(), String> {
+        let mut reader = Reader::from_str(xml);
+
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(e)) => self.open(&e, false),
+                Ok(Event::Empty(e)) => self.open(&e, true),
+                Ok(Event::End(e)) => self.close(e.name().as_ref()),
+                Ok(Event::Text(text)) if self.in_text => {
+                    let text = text
+                        .unescape()
+                        .map_err(|e| format!("Failed to read text: {}", e))?;
+                    self.push_text(&text);
+                }
+                Ok(Event::Eof) => break,
+                Err(e) => return Err(format!("Failed to parse document: {}", e)),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn open(&mut self, element: &BytesStart, empty: bool) {
+        match element.name().as_ref() {
+            b"w:p" => {
+                self.paragraph = Some(Paragraph::default());
+                if empty {
+                    self.finish_paragraph();
+                }
+            }
+            b"w:pPr" => self.in_paragraph_props = !empty,
+            b"w:pStyle" if self.in_paragraph_props => {
+                if let Some(paragraph) = self.paragraph.as_mut() {
+                    paragraph.style = attribute(element, "w:val");
+                }
+            }
+            b"w:ilvl" if self.in_paragraph_props => {
+                let level = attribute(element, "w:val")
+                    .and_then(|l| l.parse().ok())
+                    .unwrap_or(0);
+                if let Some(paragraph) = self.paragraph.as_mut() {
+                    let num_id = paragraph
+                        .numbering
+                        .take()
+                        .map(|(id, _)| id)
+                        .unwrap_or_default();
+                    paragraph.numbering = Some((num_id, level));
+                }
+            }
+            b"w:numId" if self.in_paragraph_props => {
+                if let (Some(paragraph), Some(num_id)) =
+                    (self.paragraph.as_mut(), attribute(element, "w:val"))
+                {
+                    let level = paragraph.numbering.take().map(|(_, l)| l).unwrap_or(0);
+                    paragraph.numbering = Some((num_id, level));
+                }
+            }
+            b"w:r" => {
+                self.bold = false;
+                self.italic = false;
+            }
+            b"w:rPr" if !self.in_paragraph_props => self.in_run_props = !empty,
+            b"w:b" if self.in_run_props => self.bold = is_toggled_on(element),
+            b"w:i" if self.in_run_props => self.italic = is_toggled_on(element),
+            b"w:t" => self.in_text = !empty,
+            b"w:tab" if !self.in_paragraph_props => self.push_text("\t"),
+            b"w:br" | b"w:cr" => {
+                if attribute(element, "w:type").as_deref() != Some("page") {
+                    self.push_inline(json!({ "type": "hard_break" }));
+                }
+            }
+            b"w:hyperlink" => {
+                self.link = attribute(element, "r:id")
+                    .and_then(|id| self.relationships.get(&id))
+                    .map(|relationship| relationship.target.clone())
+                    .or_else(|| attribute(element, "w:anchor").map(|a| format!("#{}", a)));
+                if empty {
+                    self.link = None;
+                }
+            }
+            b"wp:docPr" => {
+                self.image_alt = attribute(element, "descr")
+                    .filter(|alt| !alt.is_empty())
+                    .or_else(|| attribute(element, "title"));
+            }
+            b"a:blip" => {
+                let key = attribute(element, "r:embed").or_else(|| attribute(element, "r:link"));
+                if let Some(key) = key {
+                    let alt = self.image_alt.clone().unwrap_or_default();
+                    self.image_keys.push(key.clone());
+                    self.push_inline(json!({
+                        "type": "image",
+                        "attrs": { "src": key, "alt": alt, "title": null }
+                    }));
+                }
+            }
+            b"w:tbl" if !self.warned_tables => {
+                self.warned_tables = true;
+                self.warnings
+                    .push("Tables were imported as plain paragraphs".to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn close(&mut self, name: &[u8]) {
+        match name {
+            b"w:p" => self.finish_paragraph(),
+            b"w:pPr" => self.in_paragraph_props = false,
+            b"w:rPr" => self.in_run_props = false,
+            b"w:t" => self.in_text = false,
+            b"w:hyperlink" => self.link = None,
+            b"w:drawing" => self.image_alt = None,
+            _ => {}
+        }
+    }
+
+    fn push_inline(&mut self, inline: Value) {
+        if let Some(paragraph) = self.paragraph.as_mut() {
+            paragraph.inlines.push(inline);
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+
+        let mut marks = Vec::new();
+        if let Some(href) = &self.link {
+            marks.push(json!({ "type": "link", "attrs": { "href": href, "title": null } }));
+        }
+        if self.italic {
+            marks.push(json!({ "type": "em" }));
+        }
+        if self.bold {
+            marks.push(json!({ "type": "strong" }));
+        }
+
+        let paragraph = match self.paragraph.as_mut() {
+            Some(paragraph) => paragraph,
+            None => return,
+        };
+
+        // Word splits runs freely, so merge neighbours with the same marks
+        if let Some(last) = paragraph.inlines.last_mut() {
+            let last_marks = last.get("marks").cloned().unwrap_or_else(|| json!([]));
+            let same_marks = last_marks == Value::Array(marks.clone());
+            if last["type"] == "text" && same_marks {
+                let joined = format!("{}{}", last["text"].as_str().unwrap_or(""), text);
+                last["text"] = Value::String(joined);
+                return;
+            }
+        }
+
+        let mut node = json!({ "type": "text", "text": text });
+        if !marks.is_empty() {
+            node["marks"] = Value::Array(marks);
+        }
+        paragraph.inlines.push(node);
+    }
+
+    fn finish_paragraph(&mut self) {
+        let paragraph = match self.paragraph.take() {
+            Some(paragraph) => paragraph,
+            None => return,
+        };
+
+        let is_title = paragraph
+            .style
+            .as_deref()
+            .map(|style| style.eq_ignore_ascii_case("title"))
+            .unwrap_or(false);
+
+        if is_title && self.title.is_none() {
+            let title: String = paragraph
+                .inlines
+                .iter()
+                .filter_map(|inline| inline["text"].as_str())
+                .collect();
+            self.title = Some(title.trim().to_string());
+            return;
+        }
+
+        self.paragraphs.push(paragraph);
+    }
+}
+
+/// Whether a paragraph is a list item, and if so (ordered, level)
+fn list_kind(paragraph: &Paragraph, numbering: &NumberingFormats) -> Option<(bool, usize)> {
+    if let Some((num_id, level)) = &paragraph.numbering {
+        // numId 0 explicitly removes numbering inherited from the style
+        if num_id.is_empty() || num_id == "0" {
+            return None;
+        }
+        let ordered = numbering
+            .get(num_id)
+            .and_then(|levels| levels.get(level))
+            .copied()
+            .unwrap_or(false);
+        return Some((ordered, *level));
+    }
+
+    let style = paragraph.style.as_deref()?.to_lowercase();
+    if style.starts_with("listbullet") {
+        Some((false, 0))
+    } else if style.starts_with("listnumber") {
+        Some((true, 0))
+    } else {
+        None
+    }
+}
+
+fn with_content(mut node: Value, inlines: Vec<Value>) -> Value {
+    if !inlines.is_empty() {
+        node["content"] = Value::Array(inlines);
+    }
+    node
+}
+
+fn block_node(paragraph: Paragraph) -> Value {
+    let style = paragraph.style.as_deref().unwrap_or("").to_lowercase();
+
+    if let Some(level) = style.strip_prefix("heading") {
+        if let Ok(level) = level.parse::<u64>() {
+            let node = json!({ "type": "heading", "attrs": { "level": level.clamp(1, 6) } });
+            return with_content(node, paragraph.inlines);
+        }
+    }
+
+    if style == "title" {
+        let node = json!({ "type": "heading", "attrs": { "level": 1 } });
+        return with_content(node, paragraph.inlines);
+    }
+
+    let node = with_content(json!({ "type": "paragraph" }), paragraph.inlines);
+    if style == "quote" || style == "intensequote" {
+        return json!({ "type": "blockquote", "content": [node] });
+    }
+
+    node
+}
+
+fn build_list(entries: &[ListEntry], index: &mut usize, level: usize) -> Value {
+    let ordered = entries[*index].ordered;
+    let mut items: Vec<Value> = Vec::new();
+
+    while *index < entries.len() {
+        let entry = &entries[*index];
+        if entry.level < level {
+            break;
+        }
+
+        if entry.level > level {
+            // A deeper level nests inside the previous item
+            let nested = build_list(entries, index, entry.level);
+            match items
+                .last_mut()
+                .and_then(|item| item["content"].as_array_mut())
+            {
+                Some(content) => content.push(nested),
+                None => items.push(json!({
+                    "type": "list_item",
+                    "content": [{ "type": "paragraph" }, nested]
+                })),
+            }
+            continue;
+        }
+
+        if entry.ordered != ordered && !items.is_empty() {
+            break;
+        }
+
+        items.push(json!({ "type": "list_item", "content": [entry.node.clone()] }));
+        *index += 1;
+    }
+
+    let list_type = if ordered {
+        "ordered_list"
+    } else {
+        "bullet_list"
+    };
+    json!({ "type": list_type, "content": items })
+}
+
+fn flush_list(content: &mut Vec<Value>, entries: &mut Vec<ListEntry>) {
+    if entries.is_empty() {
+        return;
+    }
+
+    let base_level = entries.iter().map(|entry| entry.level).min().unwrap_or(0);
+    let mut index = 0;
+    while index < entries.len() {
+        content.push(build_list(entries, &mut index, base_level));
+    }
+    entries.clear();
+}
+
+fn build_doc(paragraphs: Vec<Paragraph>, numbering: &NumberingFormats) -> Value {
+    let mut content = Vec::new();
+    let mut list_entries = Vec::new();
+
+    for paragraph in paragraphs {
+        match list_kind(&paragraph, numbering) {
+            Some((ordered, level)) => list_entries.push(ListEntry {
+                level,
+                ordered,
+                node: with_content(json!({ "type": "paragraph" }), paragraph.inlines),
+            }),
+            None => {
+                flush_list(&mut content, &mut list_entries);
+                content.push(block_node(paragraph));
+            }
+        }
+    }
+    flush_list(&mut content, &mut list_entries);
+
+    if content.is_empty() {
+        content.push(json!({ "type": "paragraph" }));
+    }
+
+    json!({ "type": "doc", "content": content })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use wasm_bindgen_test::*;
+
+    const W_NS: &str = r#"xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships" xmlns:wp="http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing" xmlns:a="http://schemas.openxmlformats.org/drawingml/2006/main""#;
+
+    fn build_docx(
+        body: &str,
+        rels: &str,
+        numbering: Option<&str>,
+        media: &[(&str, &[u8])],
+    ) -> Vec<u8> {
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut writer = zip::ZipWriter::new(&mut buffer);
+            let options = zip::write::FileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored);
+
+            let document = format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?><w:document {}><w:body>{}</w:body></w:document>"#,
+                W_NS, body
+            );
+            writer.start_file(DOCUMENT_PATH, options).unwrap();
+            writer.write_all(document.as_bytes()).unwrap();
+
+            let rels = format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?><Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">{}</Relationships>"#,
+                rels
+            );
+            writer.start_file(RELATIONSHIPS_PATH, options).unwrap();
+            writer.write_all(rels.as_bytes()).unwrap();
+
+            if let Some(numbering) = numbering {
+                let numbering = format!(r#"<w:numbering {}>{}</w:numbering>"#, W_NS, numbering);
+                writer.start_file(NUMBERING_PATH, options).unwrap();
+                writer.write_all(numbering.as_bytes()).unwrap();
+            }
+
+            for (path, bytes) in media {
+                writer.start_file(*path, options).unwrap();
+                writer.write_all(bytes).unwrap();
+            }
+
+            writer.finish().unwrap();
+        }
+        buffer.into_inner()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_title_headings_and_paragraphs() {
+        let body = r#"
+            <w:p><w:pPr><w:pStyle w:val="Title"/></w:pPr><w:r><w:t>My Essay</w:t></w:r></w:p>
+            <w:p><w:pPr><w:pStyle w:val="Heading2"/></w:pPr><w:r><w:t>Intro</w:t></w:r></w:p>
+            <w:p><w:r><w:t xml:space="preserve">Hello </w:t></w:r><w:r><w:t>world</w:t></w:r></w:p>
+            <w:p/>
+        "#;
+        let imported = docx_to_pm(&build_docx(body, "", None, &[])).unwrap();
+
+        assert_eq!(imported.title.as_deref(), Some("My Essay"));
+        let content = imported.content["content"].as_array().unwrap();
+        assert_eq!(content.len(), 3);
+        assert_eq!(content[0]["type"], "heading");
+        assert_eq!(content[0]["attrs"]["level"], 2);
+        assert_eq!(content[1]["content"][0]["text"], "Hello world");
+        assert_eq!(content[2], json!({ "type": "paragraph" }));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_marks_and_links() {
+        let body = r#"
+            <w:p>
+                <w:r><w:rPr><w:b/></w:rPr><w:t>bold</w:t></w:r>
+                <w:r><w:rPr><w:i/><w:b w:val="0"/></w:rPr><w:t>italic</w:t></w:r>
+                <w:hyperlink r:id="rId9"><w:r><w:t>a link</w:t></w:r></w:hyperlink>
+            </w:p>
+        "#;
+        let rels = r#"<Relationship Id="rId9" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink" Target="https://example.com" TargetMode="External"/>"#;
+        let imported = docx_to_pm(&build_docx(body, rels, None, &[])).unwrap();
+
+        let inlines = imported.content["content"][0]["content"]
+            .as_array()
+            .unwrap();
+        assert_eq!(inlines[0]["marks"], json!([{ "type": "strong" }]));
+        assert_eq!(inlines[1]["marks"], json!([{ "type": "em" }]));
+        assert_eq!(inlines[2]["marks"][0]["type"], "link");
+        assert_eq!(
+            inlines[2]["marks"][0]["attrs"]["href"],
+            "https://example.com"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_nested_and_ordered_lists() {
+        let numbering = r#"
+            <w:abstractNum w:abstractNumId="0">
+                <w:lvl w:ilvl="0"><w:numFmt w:val="bullet"/></w:lvl>
+                <w:lvl w:ilvl="1"><w:numFmt w:val="decimal"/></w:lvl>
+            </w:abstractNum>
+            <w:num w:numId="1"><w:abstractNumId w:val="0"/></w:num>
+        "#;
+        let item = |level: usize, text: &str| {
+            format!(
+                r#"<w:p><w:pPr><w:numPr><w:ilvl w:val="{}"/><w:numId w:val="1"/></w:numPr></w:pPr><w:r><w:t>{}</w:t></w:r></w:p>"#,
+                level, text
+            )
+        };
+        let body = [item(0, "one"), item(1, "one.a"), item(0, "two")].concat();
+        let imported = docx_to_pm(&build_docx(&body, "", Some(numbering), &[])).unwrap();
+
+        let content = imported.content["content"].as_array().unwrap();
+        assert_eq!(content.len(), 1);
+        let list = &content[0];
+        assert_eq!(list["type"], "bullet_list");
+        assert_eq!(list["content"].as_array().unwrap().len(), 2);
+        let nested = &list["content"][0]["content"][1];
+        assert_eq!(nested["type"], "ordered_list");
+        assert_eq!(
+            nested["content"][0]["content"][0]["content"][0]["text"],
+            "one.a"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_images_become_assets() {
+        let body = r#"
+            <w:p><w:r><w:drawing><wp:inline>
+                <wp:docPr id="1" name="Picture 1" descr="A cat"/>
+                <a:graphic><a:graphicData><a:blip r:embed="rId7"/></a:graphicData></a:graphic>
+            </wp:inline></w:drawing></w:r></w:p>
+        "#;
+        let rels = r#"<Relationship Id="rId7" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/image" Target="media/image1.png"/>"#;
+        let png: &[u8] = &[0x89, b'P', b'N', b'G'];
+        let imported = docx_to_pm(&build_docx(
+            body,
+            rels,
+            None,
+            &[("word/media/image1.png", png)],
+        ))
+        .unwrap();
+
+        assert_eq!(imported.assets.len(), 1);
+        assert_eq!(imported.assets[0].key, "rId7");
+        assert_eq!(imported.assets[0].name, "image1.png");
+        assert_eq!(imported.assets[0].mime_type, "image/png");
+        assert_eq!(imported.assets[0].data, png.to_vec());
+
+        let image = &imported.content["content"][0]["content"][0];
+        assert_eq!(image["type"], "image");
+        assert_eq!(image["attrs"]["src"], "rId7");
+        assert_eq!(image["attrs"]["alt"], "A cat");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_invalid_archive() {
+        assert!(docx_to_pm(b"not a zip").is_err());
+    }
+}
@@ -0,0 +1,600 @@
+//! Ghost importer.
+//!
+//! Reads the JSON file Ghost's Labs → Export produces. Each post or page
+//! keeps its slug, title, publish date, tags, authors, excerpt and feature
+//! image; its body comes from the `html` Ghost rendered, or is rendered here
+//! from the `mobiledoc` or `lexical` source when a draft has no HTML yet.
+//! Ghost writes its own address as `__GHOST_URL__`, which is swapped for the
+//! site's URL when it's known.
+
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
+
+use super::html::html_to_pm;
+use super::{ImportedDocument, ImportedPage};
+use crate::render::graph::TAGS_FIELD;
+use crate::render::html::escape_html;
+use crate::render::slugify;
+
+/// Ghost's placeholder for its own address in exported URLs
+const GHOST_URL: &str = "__GHOST_URL__";
+
+/// Everything taken from a Ghost export
+#[derive(Debug, Clone, PartialEq)]
+pub struct GhostExport {
+    pub pages: Vec<ImportedPage>,
+    pub warnings: Vec<String>,
+}
+
+impl GhostExport {
+    /// Images uploaded to the Ghost site at `site_url`, which can be copied
+    /// over rather than linked to
+    pub fn hosted_images(&self, site_url: &str) -> Vec<String> {
+        let prefix = format!("{}/content/images/", site_url.trim_end_matches('/'));
+        let mut images = Vec::new();
+        for page in &self.pages {
+            collect_images(&page.content, &mut images);
+            if let Some(image) = page.meta.get("feature_image").and_then(Value::as_str) {
+                images.push(image.to_string());
+            }
+        }
+        images.retain(|image| image.starts_with(&prefix));
+        images.sort();
+        images.dedup();
+        images
+    }
+}
+
+fn collect_images(node: &Value, images: &mut Vec<String>) {
+    if node["type"] == "image" {
+        if let Some(src) = node["attrs"]["src"].as_str() {
+            images.push(src.to_string());
+        }
+    }
+    for child in node["content"].as_array().map(Vec::as_slice).unwrap_or(&[]) {
+        collect_images(child, images);
+    }
+}
+
+fn warn(warnings: &mut Vec<String>, warning: String) {
+    if !warnings.contains(&warning) {
+        warnings.push(warning);
+    }
+}
+
+fn string<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|text| !text.is_empty())
+}
+
+/// Ghost's `data` object: exports wrap it in `db`, older ones don't
+fn export_data(export: &Value) -> Option<&Value> {
+    export
+        .get("db")
+        .and_then(|db| db.get(0))
+        .unwrap_or(export)
+        .get("data")
+}
+
+/// Names from a `posts_tags` or `posts_authors` join table, by post id
+fn joined_names(
+    data: &Value,
+    join: &str,
+    foreign_key: &str,
+    names: &HashMap<&str, &str>,
+) -> HashMap<String, Vec<String>> {
+    let mut rows: Vec<&Value> = data
+        .get(join)
+        .and_then(Value::as_array)
+        .map(|rows| rows.iter().collect())
+        .unwrap_or_default();
+    rows.sort_by_key(|row| row.get("sort_order").and_then(Value::as_i64).unwrap_or(0));
+
+    let mut joined: HashMap<String, Vec<String>> = HashMap::new();
+    for row in rows {
+        let post = string(row, "post_id");
+        let name = string(row, foreign_key).and_then(|id| names.get(id));
+        if let (Some(post), Some(name)) = (post, name) {
+            joined
+                .entry(post.to_string())
+                .or_default()
+                .push(name.to_string());
+        }
+    }
+    joined
+}
+
+/// Id → name for Ghost's `tags` or `users`
+fn names_by_id<'a>(data: &'a Value, table: &str) -> HashMap<&'a str, &'a str> {
+    data.get(table)
+        .and_then(Value::as_array)
+        .map(|rows| {
+            rows.iter()
+                .filter_map(|row| Some((string(row, "id")?, string(row, "name")?)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Mobiledoc (Ghost 1–4)
+
+/// Opening tag for a Mobiledoc markup: `["a", ["href", "/x"]]`
+fn open_markup(markup: &Value) -> Option<(String, String)> {
+    let tag = markup.get(0)?.as_str()?.to_ascii_lowercase();
+    let mut open = format!("<{}", tag);
+    if let Some(attrs) = markup.get(1).and_then(Value::as_array) {
+        for pair in attrs.chunks(2) {
+            if let [name, value] = pair {
+                open.push_str(&format!(
+                    " {}=\"{}\"",
+                    name.as_str().unwrap_or(""),
+                    escape_html(value.as_str().unwrap_or(""))
+                ));
+            }
+        }
+    }
+    open.push('>');
+    Some((open, format!("</{}>", tag)))
+}
+
+/// Text with markups opened and closed around it, as markers list them
+fn mobiledoc_markers(markers: &Value, doc: &Value) -> String {
+    let markups = doc["markups"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    let atoms = doc["atoms"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    let mut html = String::new();
+    let mut open: Vec<String> = Vec::new();
+    for marker in markers.as_array().map(Vec::as_slice).unwrap_or(&[]) {
+        let opens = marker[1].as_array().map(Vec::as_slice).unwrap_or(&[]);
+        for index in opens {
+            let markup = index.as_u64().and_then(|index| markups.get(index as usize));
+            match markup.and_then(open_markup) {
+                Some((start, end)) => {
+                    html.push_str(&start);
+                    open.push(end);
+                }
+                // Keep the stack in step with the closing count
+                None => open.push(String::new()),
+            }
+        }
+
+        match marker[0].as_u64() {
+            Some(1) => {
+                let atom = marker[3]
+                    .as_u64()
+                    .and_then(|index| atoms.get(index as usize));
+                match atom.and_then(|atom| atom[0].as_str()) {
+                    Some("soft-return") => html.push_str("<br>"),
+                    Some(_) => {
+                        let text = atom.and_then(|atom| atom[1].as_str()).unwrap_or("");
+                        html.push_str(&escape_html(text));
+                    }
+                    None => {}
+                }
+            }
+            _ => html.push_str(&escape_html(marker[3].as_str().unwrap_or(""))),
+        }
+
+        let closes = marker[2].as_u64().unwrap_or(0);
+        for _ in 0..closes {
+            if let Some(end) = open.pop() {
+                html.push_str(&end);
+            }
+        }
+    }
+    while let Some(end) = open.pop() {
+        html.push_str(&end);
+    }
+    html
+}
+
+fn markdown_html(markdown: &str) -> String {
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, pulldown_cmark::Parser::new(markdown));
+    html
+}
+
+fn image_html(src: &str, alt: &str, caption: &str) -> String {
+    let image = format!(
+        "<img src=\"{}\" alt=\"{}\">",
+        escape_html(src),
+        escape_html(alt)
+    );
+    if caption.is_empty() {
+        format!("<p>{}</p>", image)
+    } else {
+        // Captions are HTML already
+        format!(
+            "<figure>{}<figcaption>{}</figcaption></figure>",
+            image, caption
+        )
+    }
+}
+
+/// A Ghost card (used by both Mobiledoc and Lexical) as HTML
+fn card_html(name: &str, payload: &Value, warnings: &mut Vec<String>) -> String {
+    let field = |key: &str| payload.get(key).and_then(Value::as_str).unwrap_or("");
+    match name {
+        "image" => image_html(field("src"), field("alt"), field("caption")),
+        "gallery" => payload["images"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+            .iter()
+            .map(|image| {
+                let field = |key: &str| image.get(key).and_then(Value::as_str).unwrap_or("");
+                image_html(field("src"), field("alt"), field("caption"))
+            })
+            .collect(),
+        "markdown" | "card-markdown" => markdown_html(field("markdown")),
+        "html" | "embed" => field("html").to_string(),
+        "code" | "codeblock" => {
+            let language = match field("language") {
+                "" => String::new(),
+                language => format!(" class=\"language-{}\"", escape_html(language)),
+            };
+            format!(
+                "<pre><code{}>{}</code></pre>",
+                language,
+                escape_html(field("code"))
+            )
+        }
+        "hr" | "horizontalrule" => "<hr>".to_string(),
+        "bookmark" => {
+            let url = field("url");
+            let title = payload["metadata"]["title"].as_str().unwrap_or(url);
+            format!(
+                "<p><a href=\"{}\">{}</a></p>",
+                escape_html(url),
+                escape_html(title)
+            )
+        }
+        _ => {
+            warn(warnings, format!("Ghost `{}` cards were dropped", name));
+            String::new()
+        }
+    }
+}
+
+/// Render a Mobiledoc document (Ghost's editor format before 5.0) to HTML
+pub fn mobiledoc_to_html(doc: &Value, warnings: &mut Vec<String>) -> String {
+    let cards = doc["cards"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+    let mut html = String::new();
+    for section in doc["sections"].as_array().map(Vec::as_slice).unwrap_or(&[]) {
+        match section[0].as_u64() {
+            // Markup section: ["p", markers]
+            Some(1) => {
+                let tag = match section[1].as_str().unwrap_or("p").to_ascii_lowercase() {
+                    tag if tag == "pull-quote" || tag == "aside" => "blockquote".to_string(),
+                    tag => tag,
+                };
+                let text = mobiledoc_markers(&section[2], doc);
+                html.push_str(&format!("<{0}>{1}</{0}>", tag, text));
+            }
+            // Image section: src
+            Some(2) => html.push_str(&image_html(section[1].as_str().unwrap_or(""), "", "")),
+            // List section: ["ul", [markers, ...]]
+            Some(3) => {
+                let tag = section[1].as_str().unwrap_or("ul").to_ascii_lowercase();
+                html.push_str(&format!("<{}>", tag));
+                for item in section[2].as_array().map(Vec::as_slice).unwrap_or(&[]) {
+                    let text = mobiledoc_markers(item, doc);
+                    html.push_str(&format!("<li>{}</li>", text));
+                }
+                html.push_str(&format!("</{}>", tag));
+            }
+            // Card section: index into `cards`
+            Some(10) => {
+                let card = section[1]
+                    .as_u64()
+                    .and_then(|index| cards.get(index as usize));
+                if let Some(card) = card {
+                    let name = card[0].as_str().unwrap_or("");
+                    html.push_str(&card_html(name, &card[1], warnings));
+                }
+            }
+            _ => warn(
+                warnings,
+                "Unknown Mobiledoc sections were dropped".to_string(),
+            ),
+        }
+    }
+    html
+}
+
+// Lexical (Ghost 5)
+
+/// Lexical's text format bit flags, outermost first
+const LEXICAL_FORMATS: [(u64, &str); 5] =
+    [(1, "strong"), (2, "em"), (4, "s"), (8, "u"), (16, "code")];
+
+fn lexical_children(node: &Value, warnings: &mut Vec<String>) -> String {
+    node["children"]
+        .as_array()
+        .map(Vec::as_slice)
+        .unwrap_or(&[])
+        .iter()
+        .map(|child| lexical_node(child, warnings))
+        .collect()
+}
+
+fn lexical_node(node: &Value, warnings: &mut Vec<String>) -> String {
+    let kind = node["type"].as_str().unwrap_or("");
+    match kind {
+        "text" | "extended-text" => {
+            let format = node["format"].as_u64().unwrap_or(0);
+            let mut html = escape_html(node["text"].as_str().unwrap_or(""));
+            for (flag, tag) in LEXICAL_FORMATS.iter().rev() {
+                if format & flag != 0 {
+                    html = format!("<{0}>{1}</{0}>", tag, html);
+                }
+            }
+            html
+        }
+        "linebreak" => "<br>".to_string(),
+        "paragraph" => format!("<p>{}</p>", lexical_children(node, warnings)),
+        "heading" | "extended-heading" => {
+            let tag = node["tag"].as_str().unwrap_or("h2");
+            format!("<{0}>{1}</{0}>", tag, lexical_children(node, warnings))
+        }
+        "quote" | "extended-quote" | "aside" => {
+            format!(
+                "<blockquote>{}</blockquote>",
+                lexical_children(node, warnings)
+            )
+        }
+        "list" => {
+            let tag = match node["listType"].as_str() {
+                Some("number") => "ol",
+                _ => "ul",
+            };
+            format!("<{0}>{1}</{0}>", tag, lexical_children(node, warnings))
+        }
+        "listitem" => format!("<li>{}</li>", lexical_children(node, warnings)),
+        "link" | "autolink" => format!(
+            "<a href=\"{}\">{}</a>",
+            escape_html(node["url"].as_str().unwrap_or("")),
+            lexical_children(node, warnings)
+        ),
+        "image" => {
+            let field = |key: &str| node.get(key).and_then(Value::as_str).unwrap_or("");
+            image_html(field("src"), field("alt"), field("caption"))
+        }
+        _ if node["children"].is_array() => lexical_children(node, warnings),
+        // Cards keep their payload on the node itself
+        card => card_html(card, node, warnings),
+    }
+}
+
+/// Render a Lexical document (Ghost's editor format since 5.0) to HTML
+pub fn lexical_to_html(doc: &Value, warnings: &mut Vec<String>) -> String {
+    lexical_children(&doc["root"], warnings)
+}
+
+/// A post's body as HTML, from whichever format the export has
+fn post_html(post: &Value, warnings: &mut Vec<String>) -> String {
+    if let Some(html) = string(post, "html") {
+        return html.to_string();
+    }
+    // Sources are JSON in a string
+    let source = |key: &str| string(post, key).and_then(|text| serde_json::from_str(text).ok());
+    if let Some(lexical) = source("lexical") {
+        return lexical_to_html(&lexical, warnings);
+    }
+    if let Some(mobiledoc) = source("mobiledoc") {
+        return mobiledoc_to_html(&mobiledoc, warnings);
+    }
+    String::new()
+}
+
+/// Read a Ghost JSON export
+///
+/// `site_url` is where the Ghost site was published; without it, links and
+/// images to the site become root-relative.
+pub fn read_ghost_export(json: &str, site_url: Option<&str>) -> Result<GhostExport, String> {
+    let export: Value = serde_json::from_str(json).map_err(|e| format!("Invalid JSON: {}", e))?;
+    let data = export_data(&export).ok_or("Not a Ghost export: no `data`")?;
+    let posts = data["posts"]
+        .as_array()
+        .ok_or("Not a Ghost export: no `posts`")?;
+
+    let tag_names = names_by_id(data, "tags");
+    let user_names = names_by_id(data, "users");
+    let tags = joined_names(data, "posts_tags", "tag_id", &tag_names);
+    let authors = joined_names(data, "posts_authors", "author_id", &user_names);
+    let site_url = site_url.unwrap_or("").trim_end_matches('/');
+
+    let mut pages = Vec::new();
+    let mut warnings = Vec::new();
+    for post in posts {
+        let id = string(post, "id").unwrap_or("");
+        let title = string(post, "title").unwrap_or("Untitled").to_string();
+        let name = match string(post, "slug").map(slugify) {
+            Some(slug) if !slug.is_empty() => slug,
+            _ => slugify(&title),
+        };
+
+        let mut post_warnings = Vec::new();
+        let html = post_html(post, &mut post_warnings).replace(GHOST_URL, site_url);
+        let ImportedDocument {
+            content,
+            warnings: html_warnings,
+            ..
+        } = html_to_pm(&html);
+        for warning in post_warnings.into_iter().chain(html_warnings) {
+            warn(&mut warnings, format!("{}: {}", name, warning));
+        }
+
+        let mut meta = Map::new();
+        let date = string(post, "published_at").or_else(|| string(post, "created_at"));
+        if let Some(date) = date {
+            meta.insert("date".to_string(), json!(date));
+        }
+        if let Some(excerpt) = string(post, "custom_excerpt") {
+            meta.insert("excerpt".to_string(), json!(excerpt));
+        }
+        if let Some(image) = string(post, "feature_image") {
+            meta.insert(
+                "feature_image".to_string(),
+                json!(image.replace(GHOST_URL, site_url)),
+            );
+        }
+        // Tags starting with # are Ghost's internal ones
+        let post_tags: Vec<&String> = tags
+            .get(id)
+            .map(|tags| tags.iter().filter(|tag| !tag.starts_with('#')).collect())
+            .unwrap_or_default();
+        if !post_tags.is_empty() {
+            meta.insert(TAGS_FIELD.to_string(), json!(post_tags));
+        }
+        // Exports before Ghost 1.22 name a single author on the post
+        let post_authors = authors.get(id).cloned().unwrap_or_else(|| {
+            string(post, "author_id")
+                .and_then(|author| user_names.get(author))
+                .map(|author| vec![author.to_string()])
+                .unwrap_or_default()
+        });
+        if let Some(author) = post_authors.first() {
+            meta.insert("author".to_string(), json!(author));
+        }
+        if post_authors.len() > 1 {
+            meta.insert("authors".to_string(), json!(post_authors));
+        }
+
+        let collection = match string(post, "type") {
+            Some("page") => "page",
+            _ => "post",
+        };
+        pages.push(ImportedPage {
+            collection,
+            name,
+            title,
+            meta,
+            draft: string(post, "status") != Some("published"),
+            content,
+        });
+    }
+
+    if site_url.is_empty() && json.contains(GHOST_URL) {
+        warn(
+            &mut warnings,
+            "Links to the Ghost site are root-relative; give its URL to keep them absolute"
+                .to_string(),
+        );
+    }
+    Ok(GhostExport { pages, warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_read_ghost_export() {
+        let mobiledoc = json!({
+            "version": "0.3.1",
+            "atoms": [["soft-return", "", {}]],
+            "cards": [
+                ["image", { "src": "__GHOST_URL__/content/images/stall.jpg", "alt": "Stall" }]
+            ],
+            "markups": [["strong"], ["a", ["href", "https://example.com"]]],
+            "sections": [
+                [1, "p", [[0, [0], 1, "Bring"], [0, [], 0, " zines"], [1, [], 0, 0],
+                    [0, [1], 1, "and friends"]]],
+                [10, 0],
+                [3, "ul", [[[0, [], 0, "tables"]], [[0, [], 0, "chairs"]]]]
+            ]
+        });
+        let export = json!({
+            "db": [{
+                "meta": { "version": "4.48.0" },
+                "data": {
+                    "posts": [
+                        {
+                            "id": "p1",
+                            "title": "Zine Fair",
+                            "slug": "zine-fair-2023",
+                            "html": "<p>See <a href=\"__GHOST_URL__/about/\">about</a></p>",
+                            "status": "published",
+                            "published_at": "2023-05-01T10:00:00.000Z",
+                            "type": "post"
+                        },
+                        {
+                            "id": "p2",
+                            "title": "Stall list",
+                            "slug": "stalls",
+                            "html": null,
+                            "mobiledoc": mobiledoc.to_string(),
+                            "status": "draft",
+                            "type": "page"
+                        }
+                    ],
+                    "tags": [
+                        { "id": "t1", "name": "Events" },
+                        { "id": "t2", "name": "#internal" }
+                    ],
+                    "posts_tags": [
+                        { "post_id": "p1", "tag_id": "t2", "sort_order": 1 },
+                        { "post_id": "p1", "tag_id": "t1", "sort_order": 0 }
+                    ],
+                    "users": [{ "id": "u1", "name": "Sam" }],
+                    "posts_authors": [{ "post_id": "p1", "author_id": "u1" }]
+                }
+            }]
+        });
+
+        let ghost = read_ghost_export(&export.to_string(), Some("https://zines.example/")).unwrap();
+        assert!(ghost.warnings.is_empty());
+
+        let post = &ghost.pages[0];
+        assert_eq!(
+            (post.collection, post.name.as_str()),
+            ("post", "zine-fair-2023")
+        );
+        assert!(!post.draft);
+        assert_eq!(post.meta["date"], "2023-05-01T10:00:00.000Z");
+        assert_eq!(post.meta[TAGS_FIELD], json!(["Events"]));
+        assert_eq!(post.meta["author"], "Sam");
+        assert!(post
+            .content
+            .to_string()
+            .contains("https://zines.example/about/"));
+
+        let page = &ghost.pages[1];
+        assert_eq!((page.collection, page.name.as_str()), ("page", "stalls"));
+        assert!(page.draft);
+        let content = page.content.to_string();
+        assert!(content.contains("https://zines.example/content/images/stall.jpg"));
+        assert!(content.contains("\"strong\""));
+        assert!(content.contains("chairs"));
+        assert_eq!(
+            ghost.hosted_images("https://zines.example"),
+            vec!["https://zines.example/content/images/stall.jpg"]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_lexical_to_html() {
+        let lexical = json!({
+            "root": { "children": [
+                { "type": "heading", "tag": "h2", "children": [{ "type": "text", "text": "Hi" }] },
+                { "type": "paragraph", "children": [
+                    { "type": "text", "text": "bold", "format": 3 },
+                    { "type": "link", "url": "/x", "children": [{ "type": "text", "text": "x" }] }
+                ] },
+                { "type": "horizontalrule" },
+                { "type": "signup" }
+            ] }
+        });
+
+        let mut warnings = Vec::new();
+        assert_eq!(
+            lexical_to_html(&lexical, &mut warnings),
+            "<h2>Hi</h2><p><strong><em>bold</em></strong><a href=\"/x\">x</a></p><hr>"
+        );
+        assert_eq!(warnings, vec!["Ghost `signup` cards were dropped"]);
+    }
+}
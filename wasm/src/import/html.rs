@@ -0,0 +1,499 @@
+//! HTML importer.
+//!
+//! A forgiving tokenizer rather than a full HTML parser: it reads tags,
+//! attributes, comments and entities, and leaves nesting repairs to the
+//! [`DocBuilder`]. Scripts, styles and anything the editor can't represent
+//! are dropped; unsafe link and image URLs are removed with a warning.
+
+use serde_json::json;
+
+use super::pm::DocBuilder;
+use super::{is_safe_url, ImportedDocument};
+
+/// Elements whose content is never shown
+const SKIPPED: [&str; 8] = [
+    "script", "style", "head", "title", "template", "noscript", "iframe", "svg",
+];
+
+/// Elements that only separate blocks
+const SECTIONS: [&str; 12] = [
+    "div",
+    "section",
+    "article",
+    "main",
+    "header",
+    "footer",
+    "aside",
+    "nav",
+    "figure",
+    "figcaption",
+    "table",
+    "tr",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Start {
+        name: String,
+        attrs: Vec<(String, String)>,
+    },
+    End(String),
+    Text(String),
+}
+
+/// Replace character references with the characters they stand for
+pub fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = match rest[1..].find(|c: char| c == ';' || c == '&' || c.is_whitespace()) {
+            Some(end) if rest.as_bytes()[end + 1] == b';' && end <= 32 => end + 1,
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let name = &rest[1..end];
+        let character = match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            "hellip" => Some('…'),
+            "copy" => Some('©'),
+            _ => {
+                let code = if let Some(hex) = name.strip_prefix("#x").or(name.strip_prefix("#X")) {
+                    u32::from_str_radix(hex, 16).ok()
+                } else {
+                    name.strip_prefix('#').and_then(|dec| dec.parse().ok())
+                };
+                code.and_then(char::from_u32)
+            }
+        };
+        match character {
+            Some(character) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+pub(super) fn parse_attrs(source: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() || c == '/' {
+            chars.next();
+            continue;
+        }
+
+        let mut name_end = source.len();
+        while let Some(&(i, c)) = chars.peek() {
+            if c.is_whitespace() || c == '=' || c == '/' {
+                name_end = i;
+                break;
+            }
+            chars.next();
+        }
+        let name = source[start..name_end].to_lowercase();
+
+        while let Some(&(_, c)) = chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            chars.next();
+        }
+        let mut value = String::new();
+        if let Some(&(_, '=')) = chars.peek() {
+            chars.next();
+            while let Some(&(_, c)) = chars.peek() {
+                if !c.is_whitespace() {
+                    break;
+                }
+                chars.next();
+            }
+            match chars.peek() {
+                Some(&(_, quote)) if quote == '"' || quote == '\'' => {
+                    chars.next();
+                    for (_, c) in chars.by_ref() {
+                        if c == quote {
+                            break;
+                        }
+                        value.push(c);
+                    }
+                }
+                _ => {
+                    while let Some(&(_, c)) = chars.peek() {
+                        if c.is_whitespace() {
+                            break;
+                        }
+                        value.push(c);
+                        chars.next();
+                    }
+                }
+            }
+        }
+        if !name.is_empty() {
+            attrs.push((name, decode_entities(&value)));
+        }
+    }
+    attrs
+}
+
+fn tokenize(html: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        let start = match rest.find('<') {
+            Some(start) => start,
+            None => {
+                tokens.push(Token::Text(decode_entities(rest)));
+                break;
+            }
+        };
+        if start > 0 {
+            tokens.push(Token::Text(decode_entities(&rest[..start])));
+        }
+        rest = &rest[start..];
+
+        if rest.starts_with("<!--") {
+            rest = match rest.find("-->") {
+                Some(end) => &rest[end + 3..],
+                None => "",
+            };
+            continue;
+        }
+        let is_tag = rest[1..]
+            .chars()
+            .next()
+            .map_or(false, |c| c.is_ascii_alphabetic() || c == '/' || c == '!');
+        if !is_tag {
+            tokens.push(Token::Text("<".to_string()));
+            rest = &rest[1..];
+            continue;
+        }
+
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        if tag.starts_with('!') || tag.starts_with('?') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            tokens.push(Token::End(name.trim().to_lowercase()));
+            continue;
+        }
+        let name_end = tag
+            .find(|c: char| c.is_whitespace() || c == '/')
+            .unwrap_or(tag.len());
+        let name = tag[..name_end].to_lowercase();
+
+        // Skip the contents of elements that are never shown
+        if SKIPPED.contains(&name.as_str()) && !tag.ends_with('/') {
+            let close = format!("</{}", name);
+            rest = match rest.to_ascii_lowercase().find(&close) {
+                Some(position) => match rest[position..].find('>') {
+                    Some(end) => &rest[position + end + 1..],
+                    None => "",
+                },
+                None => "",
+            };
+            continue;
+        }
+
+        tokens.push(Token::Start {
+            name,
+            attrs: parse_attrs(&tag[name_end..]),
+        });
+    }
+    tokens
+}
+
+pub(super) fn attr<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Collapse whitespace runs to single spaces, as a browser would
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_whitespace() && c != '\u{a0}' {
+            if !in_space {
+                collapsed.push(' ');
+            }
+            in_space = true;
+        } else {
+            collapsed.push(c);
+            in_space = false;
+        }
+    }
+    collapsed
+}
+
+fn mark_for(name: &str) -> Option<&'static str> {
+    match name {
+        "strong" | "b" => Some("strong"),
+        "em" | "i" => Some("em"),
+        "s" | "strike" | "del" => Some("strike"),
+        "u" => Some("underline"),
+        "code" => Some("code"),
+        _ => None,
+    }
+}
+
+/// Convert HTML into ProseMirror JSON
+///
+/// The `<title>`, or failing that the first `<h1>`, becomes the title. A
+/// full page is read from its `<body>` on.
+pub fn html_to_pm(html: &str) -> ImportedDocument {
+    let mut builder = DocBuilder::new();
+    let mut warnings = Vec::new();
+    let mut title: Option<String> = None;
+    let mut h1_text: Option<String> = None;
+    let mut pre_depth = 0;
+    // Whether each open <a> added a link mark
+    let mut links = Vec::new();
+
+    // <head> is skipped by the tokenizer, but its <title> is worth keeping
+    let lower = html.to_ascii_lowercase();
+    if let Some(start) = lower.find("<title") {
+        if let Some(open_end) = lower[start..].find('>') {
+            let text_start = start + open_end + 1;
+            if let Some(end) = lower[text_start..].find("</title") {
+                let text = decode_entities(&html[text_start..text_start + end]);
+                title = Some(text.trim().to_string()).filter(|text| !text.is_empty());
+            }
+        }
+    }
+
+    for token in tokenize(html) {
+        match token {
+            Token::Start { name, attrs } => match name.as_str() {
+                "p" => builder.open(json!({ "type": "paragraph", "content": [] })),
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    let level: u64 = name[1..].parse().unwrap_or(1);
+                    if level == 1 && title.is_none() && h1_text.is_none() {
+                        h1_text = Some(String::new());
+                    }
+                    builder.open(json!({
+                        "type": "heading",
+                        "attrs": { "level": level },
+                        "content": []
+                    }));
+                }
+                "blockquote" => builder.open(json!({ "type": "blockquote", "content": [] })),
+                "ul" => builder.open(json!({ "type": "bullet_list", "content": [] })),
+                "ol" => {
+                    let order: u64 = attr(&attrs, "start")
+                        .and_then(|start| start.trim().parse().ok())
+                        .unwrap_or(1);
+                    builder.open(json!({
+                        "type": "ordered_list",
+                        "attrs": { "order": order },
+                        "content": []
+                    }));
+                }
+                "li" => {
+                    builder.close_sibling("list_item");
+                    builder.open(json!({ "type": "list_item", "content": [] }));
+                }
+                "pre" => {
+                    pre_depth += 1;
+                    builder.open(json!({
+                        "type": "code_block",
+                        "attrs": { "language": null },
+                        "content": []
+                    }));
+                }
+                "hr" => builder.leaf(json!({ "type": "horizontal_rule" })),
+                "br" => builder.inline(json!({ "type": "hard_break" })),
+                "img" => {
+                    let src = attr(&attrs, "src").unwrap_or("");
+                    if src.is_empty() {
+                        continue;
+                    }
+                    if !is_safe_url(src) {
+                        warnings.push(format!("Removed image from {}", src));
+                        continue;
+                    }
+                    builder.inline(json!({
+                        "type": "image",
+                        "attrs": {
+                            "src": src,
+                            "alt": attr(&attrs, "alt").unwrap_or(""),
+                            "title": attr(&attrs, "title"),
+                        }
+                    }));
+                }
+                "a" => match attr(&attrs, "href") {
+                    Some(href) if is_safe_url(href) => {
+                        builder.push_mark(json!({
+                            "type": "link",
+                            "attrs": { "href": href, "title": attr(&attrs, "title") }
+                        }));
+                        links.push(true);
+                    }
+                    Some(href) => {
+                        warnings.push(format!("Removed link to {}", href));
+                        links.push(false);
+                    }
+                    None => links.push(false),
+                },
+                "body" => {
+                    builder = DocBuilder::new();
+                    h1_text = None;
+                }
+                "td" | "th" => builder.text(" "),
+                name if SECTIONS.contains(&name) => builder.close("paragraph"),
+                name => {
+                    if let Some(mark) = mark_for(name) {
+                        if pre_depth == 0 {
+                            builder.push_mark(json!({ "type": mark }));
+                        }
+                    }
+                }
+            },
+            Token::End(name) => match name.as_str() {
+                "p" => builder.close("paragraph"),
+                "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                    if name == "h1" {
+                        if let Some(text) = h1_text.take() {
+                            title = Some(text.trim().to_string()).filter(|text| !text.is_empty());
+                        }
+                    }
+                    builder.close("heading");
+                }
+                "blockquote" => builder.close("blockquote"),
+                "ul" => builder.close("bullet_list"),
+                "ol" => builder.close("ordered_list"),
+                "li" => builder.close("list_item"),
+                "pre" => {
+                    pre_depth = (pre_depth - 1).max(0);
+                    builder.close("code_block");
+                }
+                "a" => {
+                    if links.pop() == Some(true) {
+                        builder.pop_mark("link");
+                    }
+                }
+                name if SECTIONS.contains(&name) => builder.close("paragraph"),
+                name => {
+                    if let Some(mark) = mark_for(name) {
+                        if pre_depth == 0 {
+                            builder.pop_mark(mark);
+                        }
+                    }
+                }
+            },
+            Token::Text(text) => {
+                if pre_depth > 0 {
+                    builder.text(&text);
+                    continue;
+                }
+                let text = collapse_whitespace(&text);
+                if let Some(heading) = h1_text.as_mut() {
+                    heading.push_str(&text);
+                }
+                builder.text(&text);
+            }
+        }
+    }
+
+    ImportedDocument {
+        title,
+        content: builder.finish(),
+        assets: Vec::new(),
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_decode_entities() {
+        assert_eq!(
+            decode_entities("Fish &amp; chips &#8212; &#x41;&lt;"),
+            "Fish & chips — A<"
+        );
+        assert_eq!(
+            decode_entities("AT&T & co &unknown;"),
+            "AT&T & co &unknown;"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_html_to_pm() {
+        let imported = html_to_pm(
+            "<!DOCTYPE html><html><head><title>Zine &amp; Fair</title>\
+             <style>p { color: red }</style></head><body>\
+             <h1>Ignored for title</h1>\
+             <p>Bring <b>zines</b>\n   and <a href=\"/friends/\">friends</a><br>soon</p>\
+             <div>Loose text<ul><li>tables<li>chairs<ol><li>one</li></ol></ul></div>\
+             <pre>  keep   this</pre>\
+             <p><a href=\"javascript:alert(1)\">bad</a><img src=\"/a.png\" alt=\"A\">\
+             <script>alert(1)</script></p></body></html>",
+        );
+
+        assert_eq!(imported.title.as_deref(), Some("Zine & Fair"));
+        let content = imported.content["content"].as_array().unwrap();
+        assert_eq!(content[0]["type"], "heading");
+
+        let inlines = content[1]["content"].as_array().unwrap();
+        assert_eq!(inlines[0], json!({ "type": "text", "text": "Bring " }));
+        assert_eq!(inlines[1]["marks"], json!([{ "type": "strong" }]));
+        assert_eq!(inlines[2], json!({ "type": "text", "text": " and " }));
+        assert_eq!(inlines[3]["marks"][0]["attrs"]["href"], "/friends/");
+        assert_eq!(inlines[4], json!({ "type": "hard_break" }));
+
+        assert_eq!(content[2]["content"][0]["text"], "Loose text");
+        assert_eq!(content[3]["type"], "bullet_list");
+        assert_eq!(content[3]["content"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            content[3]["content"][1]["content"][1]["type"],
+            "ordered_list"
+        );
+        assert_eq!(content[4]["content"][0]["text"], "  keep   this");
+
+        let last = content[5]["content"].as_array().unwrap();
+        assert_eq!(last[0], json!({ "type": "text", "text": "bad" }));
+        assert_eq!(last[1]["type"], "image");
+        assert_eq!(last.len(), 2);
+        assert_eq!(
+            imported.warnings,
+            vec!["Removed link to javascript:alert(1)"]
+        );
+    }
+}
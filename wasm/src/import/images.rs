@@ -0,0 +1,231 @@
+//! Decoding uploaded images, for their dimensions, thumbnails and edits.
+//!
+//! Only the raster formats browsers show everywhere are decoded; anything
+//! else (SVG included) is stored as it is, without a thumbnail.
+
+use std::io::Cursor;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageOutputFormat};
+
+use crate::types::AssetTransform;
+
+/// Longest side of a generated thumbnail, in pixels
+pub const THUMBNAIL_SIZE: u32 = 320;
+/// Quality of the JPEGs written for images without transparency
+const JPEG_QUALITY: u8 = 82;
+
+/// A smaller copy of an image, for pickers and listings
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thumbnail {
+    pub data: Vec<u8>,
+    pub mime_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Whether images of this type can be decoded
+pub fn is_decodable(mime_type: &str) -> bool {
+    matches!(
+        mime_type,
+        "image/png" | "image/jpeg" | "image/gif" | "image/webp"
+    )
+}
+
+pub fn decode(data: &[u8]) -> Result<DynamicImage, String> {
+    image::load_from_memory(data).map_err(|e| format!("Can't read image: {}", e))
+}
+
+/// Encode an image as PNG if it has transparency to keep, otherwise as JPEG
+pub fn encode(image: &DynamicImage) -> Result<(Vec<u8>, &'static str), String> {
+    let mut buffer = Cursor::new(Vec::new());
+    let mime_type = if image.color().has_alpha() {
+        image
+            .write_to(&mut buffer, ImageOutputFormat::Png)
+            .map_err(|e| format!("Can't write PNG: {}", e))?;
+        "image/png"
+    } else {
+        // The JPEG encoder only takes 8-bit colour
+        DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_to(&mut buffer, ImageOutputFormat::Jpeg(JPEG_QUALITY))
+            .map_err(|e| format!("Can't write JPEG: {}", e))?;
+        "image/jpeg"
+    };
+    Ok((buffer.into_inner(), mime_type))
+}
+
+/// Encode an image in the format it was uploaded in, so its name and URL
+/// still fit; WebP can only be read, not written
+pub fn encode_as(image: &DynamicImage, mime_type: &str) -> Result<Vec<u8>, String> {
+    let format = match mime_type {
+        "image/png" => ImageOutputFormat::Png,
+        "image/jpeg" => ImageOutputFormat::Jpeg(JPEG_QUALITY),
+        "image/gif" => ImageOutputFormat::Gif,
+        other => return Err(format!("Can't write {} images", other)),
+    };
+    let mut buffer = Cursor::new(Vec::new());
+    let image = match format {
+        ImageOutputFormat::Jpeg(_) => DynamicImage::ImageRgb8(image.to_rgb8()),
+        _ => image.clone(),
+    };
+    image
+        .write_to(&mut buffer, format)
+        .map_err(|e| format!("Can't write {}: {}", mime_type, e))?;
+    Ok(buffer.into_inner())
+}
+
+/// The image with a transform's crop, rotation and size applied
+pub fn apply_transform(
+    image: &DynamicImage,
+    transform: &AssetTransform,
+) -> Result<DynamicImage, String> {
+    let mut image = image.clone();
+    if let Some(crop) = transform.crop {
+        let (width, height) = image.dimensions();
+        let fits = crop.width > 0
+            && crop.height > 0
+            && crop
+                .x
+                .checked_add(crop.width)
+                .map_or(false, |right| right <= width)
+            && crop
+                .y
+                .checked_add(crop.height)
+                .map_or(false, |bottom| bottom <= height);
+        if !fits {
+            return Err(format!(
+                "Crop {}×{} at ({}, {}) doesn't fit a {}×{} image",
+                crop.width, crop.height, crop.x, crop.y, width, height
+            ));
+        }
+        image = image.crop_imm(crop.x, crop.y, crop.width, crop.height);
+    }
+
+    image = match transform.rotate {
+        0 => image,
+        90 => image.rotate90(),
+        180 => image.rotate180(),
+        270 => image.rotate270(),
+        other => {
+            return Err(format!(
+                "Images can only be turned by 90, 180 or 270 degrees, not {}",
+                other
+            ))
+        }
+    };
+
+    let (width, height) = image.dimensions();
+    let max_width = transform.max_width.unwrap_or(width).min(width);
+    let max_height = transform.max_height.unwrap_or(height).min(height);
+    if max_width == 0 || max_height == 0 {
+        return Err("The largest size can't be zero".to_string());
+    }
+    if max_width < width || max_height < height {
+        image = image.resize(max_width, max_height, FilterType::Lanczos3);
+    }
+    Ok(image)
+}
+
+/// An image's bytes with a transform applied, in the same format
+pub fn transformed(
+    data: &[u8],
+    mime_type: &str,
+    transform: &AssetTransform,
+) -> Result<Vec<u8>, String> {
+    encode_as(&apply_transform(&decode(data)?, transform)?, mime_type)
+}
+
+/// A copy of the image no bigger than [`THUMBNAIL_SIZE`] on either side
+pub fn thumbnail(image: &DynamicImage) -> Result<Thumbnail, String> {
+    let (width, height) = image.dimensions();
+    let small = if width <= THUMBNAIL_SIZE && height <= THUMBNAIL_SIZE {
+        image.clone()
+    } else {
+        image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE)
+    };
+    let (data, mime_type) = encode(&small)?;
+    Ok(Thumbnail {
+        data,
+        mime_type,
+        width: small.width(),
+        height: small.height(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::CropRect;
+    use image::{Rgba, RgbaImage};
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_thumbnail() {
+        let (png, _) = encode(&DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            640,
+            200,
+            Rgba([200, 40, 40, 128]),
+        )))
+        .unwrap();
+
+        let image = decode(&png).unwrap();
+        assert_eq!(image.dimensions(), (640, 200));
+        let small = thumbnail(&image).unwrap();
+        assert_eq!((small.width, small.height), (320, 100));
+        assert_eq!(small.mime_type, "image/png");
+        assert!(decode(b"not an image").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_transform() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(400, 300, Rgba([0, 0, 0, 255])));
+
+        let edited = apply_transform(
+            &image,
+            &AssetTransform {
+                crop: Some(CropRect {
+                    x: 100,
+                    y: 0,
+                    width: 200,
+                    height: 100,
+                }),
+                rotate: 90,
+                max_width: Some(50),
+                max_height: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(edited.dimensions(), (50, 100));
+
+        // Never enlarged
+        let same = apply_transform(
+            &image,
+            &AssetTransform {
+                max_width: Some(1000),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(same.dimensions(), (400, 300));
+
+        let outside = AssetTransform {
+            crop: Some(CropRect {
+                x: 300,
+                y: 0,
+                width: 200,
+                height: 100,
+            }),
+            ..Default::default()
+        };
+        assert!(apply_transform(&image, &outside).is_err());
+        let tilted = AssetTransform {
+            rotate: 45,
+            ..Default::default()
+        };
+        assert!(apply_transform(&image, &tilted).is_err());
+
+        let png = encode_as(&edited, "image/png").unwrap();
+        assert_eq!(decode(&png).unwrap().dimensions(), (50, 100));
+        assert!(encode_as(&edited, "image/webp").is_err());
+    }
+}
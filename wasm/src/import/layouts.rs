@@ -0,0 +1,899 @@
+//! Layout converters for sites moving from Jekyll or Hugo.
+//!
+//! Liquid and Go templates are converted to Handlebars tag by tag: output
+//! tags, conditionals, loops and includes map across, with `page.title` or
+//! `.Title` becoming `title`. Filters Handlebars has no equivalent for are
+//! dropped, and tags it can't express are kept in a `{{!-- --}}` comment,
+//! each with a warning, so a converted layout always compiles and the gaps
+//! are easy to find.
+
+use regex::Regex;
+
+/// A converted layout and what couldn't be carried across
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Converted {
+    pub template: String,
+    pub warnings: Vec<String>,
+}
+
+/// Filters and pipeline functions that only escape or prefix URLs, which
+/// the site takes care of itself
+const QUIET_FILTERS: [&str; 14] = [
+    "relative_url",
+    "absolute_url",
+    "escape",
+    "escape_once",
+    "xml_escape",
+    "safeHTML",
+    "safeHTMLAttr",
+    "safeCSS",
+    "safeJS",
+    "safeURL",
+    "relURL",
+    "absURL",
+    "relLangURL",
+    "absLangURL",
+];
+
+fn liquid_tag_regex() -> Regex {
+    Regex::new(r"(?s)\{\{-?(.*?)-?\}\}|\{%-?(.*?)-?%\}").expect("valid Liquid tag regex")
+}
+
+fn go_action_regex() -> Regex {
+    Regex::new(r"(?s)\{\{-?(.*?)-?\}\}").expect("valid Go action regex")
+}
+
+fn warn(warnings: &mut Vec<String>, warning: String) {
+    if !warnings.contains(&warning) {
+        warnings.push(warning);
+    }
+}
+
+/// A Handlebars comment holding source that couldn't be converted
+fn comment(source: &str) -> String {
+    format!("{{{{!-- {} --}}}}", source.trim().replace("--}}", "-- }}"))
+}
+
+/// Split on whitespace, keeping quoted strings and parenthesised groups whole
+fn split_args(source: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut depth = 0;
+    for c in source.chars() {
+        match quote {
+            Some(open) => {
+                current.push(c);
+                if c == open {
+                    quote = None;
+                }
+            }
+            None if c == '"' || c == '\'' || c == '`' => {
+                quote = Some(c);
+                current.push(c);
+            }
+            None if c == '(' => {
+                depth += 1;
+                current.push(c);
+            }
+            None if c == ')' => {
+                depth -= 1;
+                current.push(c);
+            }
+            None if c.is_whitespace() && depth == 0 => {
+                if !current.is_empty() {
+                    args.push(std::mem::take(&mut current));
+                }
+            }
+            None => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        args.push(current);
+    }
+    args
+}
+
+/// Split a pipeline on `|`, outside quotes and parentheses
+fn split_pipeline(source: &str) -> Vec<&str> {
+    let mut stages = Vec::new();
+    let mut quote = None;
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in source.char_indices() {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' | '`' => quote = Some(c),
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                '|' if depth == 0 => {
+                    stages.push(source[start..index].trim());
+                    start = index + 1;
+                }
+                _ => {}
+            },
+        }
+    }
+    stages.push(source[start..].trim());
+    stages
+}
+
+fn unquote(token: &str) -> Option<&str> {
+    let first = token.chars().next()?;
+    if token.len() >= 2 && matches!(first, '"' | '\'' | '`') && token.ends_with(first) {
+        Some(&token[1..token.len() - 1])
+    } else {
+        None
+    }
+}
+
+/// A string literal in Handlebars syntax
+fn string_literal(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\\\""))
+}
+
+fn is_literal(token: &str) -> bool {
+    token == "true" || token == "false" || token.parse::<f64>().is_ok()
+}
+
+/// Name of the partial an include or partial call refers to
+pub fn partial_name(path: &str) -> String {
+    let path = path.trim().trim_start_matches('/');
+    let stem = match path.rfind('.') {
+        Some(dot) if !path[dot..].contains('/') => &path[..dot],
+        _ => path,
+    };
+    stem.replace('/', "-")
+}
+
+/// `LinkTitle` → `link_title`
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let mut after_lowercase = false;
+    for c in name.chars() {
+        if c.is_ascii_uppercase() && after_lowercase {
+            snake.push('_');
+        }
+        after_lowercase = c.is_ascii_lowercase() || c.is_ascii_digit();
+        snake.push(c.to_ascii_lowercase());
+    }
+    snake
+}
+
+// Liquid (Jekyll)
+
+#[derive(Debug)]
+enum LiquidBlock {
+    If,
+    Unless,
+    For(String),
+    Highlight,
+    Unsupported(String),
+}
+
+/// The Handlebars path for a Liquid variable, given the enclosing loops'
+/// variable names
+fn liquid_path(path: &str, loops: &[String]) -> String {
+    let (first, rest) = match path.find('.') {
+        Some(dot) => (&path[..dot], &path[dot + 1..]),
+        None => (path, ""),
+    };
+    if let Some(position) = loops.iter().rposition(|name| name == first) {
+        let parents = "../".repeat(loops.len() - 1 - position);
+        return match (rest.is_empty(), parents.is_empty()) {
+            (true, true) => "this".to_string(),
+            (true, false) => parents.trim_end_matches('/').to_string(),
+            (false, _) => format!("{}{}", parents, rest),
+        };
+    }
+
+    let root = if loops.is_empty() { "" } else { "@root." };
+    match (first, rest) {
+        ("forloop", "index0") | ("forloop", "index") => "@index".to_string(),
+        ("forloop", "first") => "@first".to_string(),
+        ("forloop", "last") => "@last".to_string(),
+        ("page", "") if loops.is_empty() => "this".to_string(),
+        ("page", "") => "@root".to_string(),
+        ("page", field) => format!("{}{}", root, field),
+        ("site", "title") => format!("{}site.name", root),
+        ("site", "baseurl") => format!("{}site.base_path", root),
+        ("site", "posts") | ("paginator", "posts") => format!("{}posts", root),
+        ("site", "pages") => format!("{}pages", root),
+        ("content", "") => format!("{}content", root),
+        _ => path.to_string(),
+    }
+}
+
+fn liquid_operand(token: &str, loops: &[String]) -> String {
+    match unquote(token) {
+        Some(text) => string_literal(text),
+        None if is_literal(token) => token.to_string(),
+        None => liquid_path(token, loops),
+    }
+}
+
+/// A Liquid condition as a Handlebars expression: a path or subexpression
+fn liquid_condition(source: &str, loops: &[String], warnings: &mut Vec<String>) -> String {
+    let tokens = split_args(source);
+    let mut clauses: Vec<String> = Vec::new();
+    let mut operators: Vec<&str> = Vec::new();
+    for clause in tokens.split(|token| token == "and" || token == "or") {
+        let clause = match clause {
+            [operand] => liquid_operand(operand, loops),
+            [left, operator, right] => {
+                let left = liquid_operand(left, loops);
+                let empty = matches!(right.as_str(), "nil" | "null" | "empty" | "blank");
+                match (operator.as_str(), empty) {
+                    ("==", true) => format!("(not {})", left),
+                    ("!=", true) | ("<>", true) => left,
+                    (operator, _) => {
+                        let helper = match operator {
+                            "==" => "eq",
+                            "!=" | "<>" => "ne",
+                            ">" => "gt",
+                            "<" => "lt",
+                            ">=" => "gte",
+                            "<=" => "lte",
+                            _ => {
+                                warn(
+                                    warnings,
+                                    format!("Liquid operator `{}` isn't supported", operator),
+                                );
+                                clauses.push(left);
+                                continue;
+                            }
+                        };
+                        format!("({} {} {})", helper, left, liquid_operand(right, loops))
+                    }
+                }
+            }
+            _ => {
+                warn(
+                    warnings,
+                    format!("Liquid condition `{}` isn't supported", source.trim()),
+                );
+                clause
+                    .first()
+                    .map_or_else(|| "false".to_string(), |token| liquid_operand(token, loops))
+            }
+        };
+        clauses.push(clause);
+    }
+    for token in &tokens {
+        if token == "and" || token == "or" {
+            operators.push(token);
+        }
+    }
+
+    // Liquid evaluates `and` and `or` from the right
+    let mut expression = clauses.pop().unwrap_or_else(|| "false".to_string());
+    while let (Some(clause), Some(operator)) = (clauses.pop(), operators.pop()) {
+        expression = format!("({} {} {})", operator, clause, expression);
+    }
+    expression
+}
+
+fn liquid_output(source: &str, loops: &[String], warnings: &mut Vec<String>) -> String {
+    let stages = split_pipeline(source);
+    let value = stages[0];
+    for filter in &stages[1..] {
+        let name = filter.split(':').next().unwrap_or("").trim();
+        if !QUIET_FILTERS.contains(&name) {
+            warn(warnings, format!("Liquid filter `{}` was dropped", name));
+        }
+    }
+    if let Some(text) = unquote(value) {
+        return text.to_string();
+    }
+    // The site adds its base path to root-relative links itself
+    if value == "site.baseurl" {
+        return String::new();
+    }
+    let path = liquid_path(value, loops);
+    if path.ends_with("content") && !path.contains("..") {
+        format!("{{{{{{{}}}}}}}", path)
+    } else {
+        format!("{{{{{}}}}}", path)
+    }
+}
+
+/// Convert a Jekyll (Liquid) layout or include to Handlebars
+pub fn liquid_to_handlebars(source: &str) -> Converted {
+    let tag = liquid_tag_regex();
+    let mut output = String::with_capacity(source.len());
+    let mut warnings = Vec::new();
+    let mut blocks: Vec<LiquidBlock> = Vec::new();
+    let mut position = 0;
+
+    while let Some(captures) = tag.captures(&source[position..]) {
+        let whole = captures.get(0).unwrap();
+        output.push_str(&source[position..position + whole.start()]);
+        let tag_end = position + whole.end();
+        position = tag_end;
+        let loops: Vec<String> = blocks
+            .iter()
+            .filter_map(|block| match block {
+                LiquidBlock::For(name) => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+
+        if let Some(expression) = captures.get(1) {
+            output.push_str(&liquid_output(
+                expression.as_str().trim(),
+                &loops,
+                &mut warnings,
+            ));
+            continue;
+        }
+        let inner = captures.get(2).map_or("", |inner| inner.as_str()).trim();
+        let (name, args) = match inner.find(char::is_whitespace) {
+            Some(space) => (&inner[..space], inner[space..].trim()),
+            None => (inner, ""),
+        };
+
+        // Blocks whose contents aren't converted
+        if matches!(name, "comment" | "raw" | "capture") {
+            let end_pattern = format!(r"\{{%-?\s*end{}\s*-?%\}}", name);
+            let end = Regex::new(&end_pattern)
+                .ok()
+                .and_then(|end| end.find(&source[position..]));
+            let (body, after) = match end {
+                Some(end) => (
+                    &source[position..position + end.start()],
+                    position + end.end(),
+                ),
+                None => (&source[position..], source.len()),
+            };
+            match name {
+                "raw" => {
+                    output.push_str(&format!("{{{{{{{{raw}}}}}}}}{}{{{{{{{{/raw}}}}}}}}", body))
+                }
+                "capture" => {
+                    warn(
+                        &mut warnings,
+                        "Liquid `capture` isn't supported; its contents were dropped".to_string(),
+                    );
+                    output.push_str(&comment(&format!("capture {}", args)));
+                }
+                _ => {}
+            }
+            position = after;
+            continue;
+        }
+
+        match name {
+            "if" => {
+                let condition = liquid_condition(args, &loops, &mut warnings);
+                output.push_str(&format!("{{{{#if {}}}}}", condition));
+                blocks.push(LiquidBlock::If);
+            }
+            "unless" => {
+                let condition = liquid_condition(args, &loops, &mut warnings);
+                output.push_str(&format!("{{{{#unless {}}}}}", condition));
+                blocks.push(LiquidBlock::Unless);
+            }
+            "elsif" => {
+                let condition = liquid_condition(args, &loops, &mut warnings);
+                output.push_str(&format!("{{{{else if {}}}}}", condition));
+            }
+            "else" => output.push_str("{{else}}"),
+            "for" => {
+                let tokens = split_args(args);
+                match tokens.as_slice() {
+                    [variable, keyword, collection, options @ ..] if keyword == "in" => {
+                        if !options.is_empty() {
+                            warn(
+                                &mut warnings,
+                                format!("Liquid loop options were dropped: {}", options.join(" ")),
+                            );
+                        }
+                        let collection = liquid_path(collection, &loops);
+                        output.push_str(&format!("{{{{#each {}}}}}", collection));
+                        blocks.push(LiquidBlock::For(variable.clone()));
+                    }
+                    _ => {
+                        warn(
+                            &mut warnings,
+                            format!("Liquid loop `for {}` isn't supported", args),
+                        );
+                        output.push_str(&comment(inner));
+                        blocks.push(LiquidBlock::Unsupported("for".to_string()));
+                    }
+                }
+            }
+            "include" => {
+                let tokens = split_args(args);
+                match tokens.first() {
+                    Some(file) if !file.contains("{{") => {
+                        if tokens.len() > 1 {
+                            warn(
+                                &mut warnings,
+                                format!("Parameters to the include {} were dropped", file),
+                            );
+                        }
+                        output.push_str(&format!("{{{{> {}}}}}", partial_name(file)));
+                    }
+                    _ => {
+                        warn(
+                            &mut warnings,
+                            "Dynamic includes aren't supported".to_string(),
+                        );
+                        output.push_str(&comment(inner));
+                    }
+                }
+            }
+            "highlight" => {
+                let language = args.split_whitespace().next().unwrap_or("");
+                output.push_str(&format!("<pre><code class=\"language-{}\">", language));
+                blocks.push(LiquidBlock::Highlight);
+            }
+            end if end.starts_with("end") => {
+                let expected = &end[3..];
+                let block = match blocks.iter().rposition(|block| match block {
+                    LiquidBlock::If => expected == "if",
+                    LiquidBlock::Unless => expected == "unless",
+                    LiquidBlock::For(_) => expected == "for",
+                    LiquidBlock::Highlight => expected == "highlight",
+                    LiquidBlock::Unsupported(name) => expected == name,
+                }) {
+                    Some(index) => blocks.remove(index),
+                    None => {
+                        output.push_str(&comment(inner));
+                        continue;
+                    }
+                };
+                match block {
+                    LiquidBlock::If => output.push_str("{{/if}}"),
+                    LiquidBlock::Unless => output.push_str("{{/unless}}"),
+                    LiquidBlock::For(_) => output.push_str("{{/each}}"),
+                    LiquidBlock::Highlight => output.push_str("</code></pre>"),
+                    LiquidBlock::Unsupported(_) => output.push_str(&comment(inner)),
+                }
+            }
+            _ => {
+                warn(
+                    &mut warnings,
+                    format!("Liquid tag `{}` isn't supported", name),
+                );
+                output.push_str(&comment(inner));
+                if matches!(name, "case" | "tablerow") {
+                    blocks.push(LiquidBlock::Unsupported(name.to_string()));
+                }
+            }
+        }
+    }
+    output.push_str(&source[position..]);
+
+    while let Some(block) = blocks.pop() {
+        warn(
+            &mut warnings,
+            "Unclosed Liquid block was closed at the end".to_string(),
+        );
+        match block {
+            LiquidBlock::If => output.push_str("{{/if}}"),
+            LiquidBlock::Unless => output.push_str("{{/unless}}"),
+            LiquidBlock::For(_) => output.push_str("{{/each}}"),
+            LiquidBlock::Highlight => output.push_str("</code></pre>"),
+            LiquidBlock::Unsupported(_) => {}
+        }
+    }
+
+    Converted {
+        template: output,
+        warnings,
+    }
+}
+
+// Go templates (Hugo)
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum GoBlock {
+    If,
+    Range,
+    With,
+    /// `define` and `block`, whose contents are kept as they are
+    Transparent,
+    Unsupported,
+}
+
+/// An action (`{{ ... }}`) in a Go template, by byte offsets
+#[derive(Debug)]
+struct Action<'a> {
+    start: usize,
+    end: usize,
+    inner: &'a str,
+}
+
+fn go_actions(source: &str) -> Vec<Action<'_>> {
+    go_action_regex()
+        .captures_iter(source)
+        .map(|captures| {
+            let whole = captures.get(0).unwrap();
+            Action {
+                start: whole.start(),
+                end: whole.end(),
+                inner: captures.get(1).unwrap().as_str().trim(),
+            }
+        })
+        .collect()
+}
+
+fn go_keyword(inner: &str) -> &str {
+    inner.split_whitespace().next().unwrap_or("")
+}
+
+fn opens_go_block(inner: &str) -> bool {
+    matches!(
+        go_keyword(inner),
+        "if" | "range" | "with" | "define" | "block"
+    )
+}
+
+/// Index of the `end` closing the block opened by `actions[open]`
+fn matching_end(actions: &[Action], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, action) in actions.iter().enumerate().skip(open) {
+        if opens_go_block(action.inner) {
+            depth += 1;
+        } else if action.inner == "end" {
+            depth -= 1;
+            if depth == 0 {
+                return Some(index);
+            }
+        }
+    }
+    None
+}
+
+/// The quoted name after a `define` or `block` keyword
+fn block_name(inner: &str) -> Option<String> {
+    split_args(inner)
+        .get(1)
+        .and_then(|name| unquote(name).map(str::to_string))
+}
+
+/// Fill a Hugo base template's `block`s with a layout's `define`s
+///
+/// Blocks the layout doesn't define keep their default contents.
+pub fn apply_go_defines(base: &str, layout: &str) -> String {
+    let layout_actions = go_actions(layout);
+    let mut defines = Vec::new();
+    for (index, action) in layout_actions.iter().enumerate() {
+        if go_keyword(action.inner) != "define" {
+            continue;
+        }
+        if let (Some(name), Some(end)) = (
+            block_name(action.inner),
+            matching_end(&layout_actions, index),
+        ) {
+            defines.push((name, &layout[action.end..layout_actions[end].start]));
+        }
+    }
+
+    let actions = go_actions(base);
+    let mut output = String::with_capacity(base.len() + layout.len());
+    let mut position = 0;
+    let mut index = 0;
+    while index < actions.len() {
+        let action = &actions[index];
+        let end = match (go_keyword(action.inner), matching_end(&actions, index)) {
+            ("block", Some(end)) => end,
+            _ => {
+                index += 1;
+                continue;
+            }
+        };
+        let name = block_name(action.inner).unwrap_or_default();
+        let contents = defines
+            .iter()
+            .find(|(define, _)| *define == name)
+            .map_or(&base[action.end..actions[end].start], |(_, body)| *body);
+        output.push_str(&base[position..action.start]);
+        output.push_str(contents);
+        position = actions[end].end;
+        index = end + 1;
+    }
+    output.push_str(&base[position..]);
+    output
+}
+
+/// The Handlebars path for a Hugo field reference like `.Site.Title`
+fn go_path(path: &str) -> String {
+    let segments: Vec<&str> = path
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let snake = |segments: &[&str]| -> String {
+        segments
+            .iter()
+            .map(|segment| snake_case(segment))
+            .collect::<Vec<_>>()
+            .join(".")
+    };
+    match segments.as_slice() {
+        [] => "this".to_string(),
+        ["Site", "Title"] => "site.name".to_string(),
+        ["Site", "BaseURL"] => "site.url".to_string(),
+        ["Site", "Params", rest @ ..] => format!("site.{}", snake(rest)),
+        ["Site", "RegularPages"] | ["Site", "Pages"] | ["Pages"] | ["RegularPages"] => {
+            "posts".to_string()
+        }
+        ["Data", "Pages"] => "posts".to_string(),
+        ["Params", rest @ ..] if !rest.is_empty() => snake(rest),
+        ["Permalink"] | ["RelPermalink"] => "url".to_string(),
+        ["PublishDate"] => "date".to_string(),
+        rest => snake(rest),
+    }
+}
+
+/// A Go template expression as a Handlebars one, if it can be expressed
+fn go_expression(tokens: &[String], warnings: &mut Vec<String>) -> Option<String> {
+    let (head, args) = tokens.split_first()?;
+    if args.is_empty() {
+        return go_operand(head, warnings);
+    }
+    let helper = match head.as_str() {
+        "eq" | "ne" | "gt" | "lt" | "and" | "or" | "not" | "len" => head.as_str(),
+        "ge" => "gte",
+        "le" => "lte",
+        _ if head.starts_with('.') && args.len() == 1 && head.ends_with(".Format") => {
+            warn(warnings, "Date formats were dropped".to_string());
+            return Some(go_path(head.trim_end_matches(".Format")));
+        }
+        _ => {
+            warn(
+                warnings,
+                format!("Go template function `{}` isn't supported", head),
+            );
+            return None;
+        }
+    };
+    let args = args
+        .iter()
+        .map(|arg| go_operand(arg, warnings))
+        .collect::<Option<Vec<_>>>()?;
+    Some(format!("({} {})", helper, args.join(" ")))
+}
+
+fn go_operand(token: &str, warnings: &mut Vec<String>) -> Option<String> {
+    if let Some(text) = unquote(token) {
+        return Some(string_literal(text));
+    }
+    if is_literal(token) {
+        return Some(token.to_string());
+    }
+    if let Some(inner) = token
+        .strip_prefix('(')
+        .and_then(|token| token.strip_suffix(')'))
+    {
+        return go_expression(&split_args(inner), warnings);
+    }
+    if token == "$" {
+        return Some("@root".to_string());
+    }
+    if let Some(path) = token.strip_prefix("$.") {
+        return Some(format!("@root.{}", go_path(path)));
+    }
+    if token.starts_with('.') {
+        return Some(go_path(token));
+    }
+    warn(
+        warnings,
+        format!("Go template value `{}` isn't supported", token),
+    );
+    None
+}
+
+fn go_output(inner: &str, warnings: &mut Vec<String>) -> Option<String> {
+    let stages = split_pipeline(inner);
+    for stage in &stages[1..] {
+        let name = go_keyword(stage);
+        if !QUIET_FILTERS.contains(&name) {
+            warn(
+                warnings,
+                format!("Go template function `{}` was dropped", name),
+            );
+        }
+    }
+    let tokens = split_args(stages[0]);
+    // URL helpers around a literal just make it site-relative
+    if let [function, path] = tokens.as_slice() {
+        if let (true, Some(path)) = (QUIET_FILTERS.contains(&function.as_str()), unquote(path)) {
+            return Some(format!("/{}", path.trim_start_matches('/')));
+        }
+    }
+    let expression = go_expression(&tokens, warnings)?;
+    if expression == "content" {
+        Some("{{{content}}}".to_string())
+    } else {
+        Some(format!("{{{{{}}}}}", expression))
+    }
+}
+
+/// Convert a Hugo (Go template) layout or partial to Handlebars
+///
+/// `define` and `block` wrappers are dropped, keeping their contents; fill
+/// a base template with [`apply_go_defines`] first.
+pub fn go_to_handlebars(source: &str) -> Converted {
+    let mut output = String::with_capacity(source.len());
+    let mut warnings = Vec::new();
+    let mut blocks: Vec<GoBlock> = Vec::new();
+    let mut position = 0;
+
+    for action in go_actions(source) {
+        output.push_str(&source[position..action.start]);
+        position = action.end;
+        let inner = action.inner;
+        if inner.starts_with("/*") {
+            continue;
+        }
+
+        let keyword = go_keyword(inner);
+        let args = inner[keyword.len()..].trim();
+        match keyword {
+            "if" | "with" => {
+                let block = if keyword == "if" {
+                    GoBlock::If
+                } else {
+                    GoBlock::With
+                };
+                match go_expression(&split_args(args), &mut warnings) {
+                    Some(expression) => {
+                        output.push_str(&format!("{{{{#{} {}}}}}", keyword, expression));
+                        blocks.push(block);
+                    }
+                    None => {
+                        output.push_str(&comment(inner));
+                        blocks.push(GoBlock::Unsupported);
+                    }
+                }
+            }
+            "else" if args.starts_with("if ") => {
+                match go_expression(&split_args(&args[3..]), &mut warnings) {
+                    Some(expression) => output.push_str(&format!("{{{{else if {}}}}}", expression)),
+                    None => output.push_str(&comment(inner)),
+                }
+            }
+            "else" => output.push_str("{{else}}"),
+            "range" => {
+                let tokens = split_args(args);
+                if tokens.iter().any(|token| token == ":=") {
+                    warn(
+                        &mut warnings,
+                        "Loop variables in `range` aren't supported".to_string(),
+                    );
+                }
+                let expression = if tokens.iter().any(|token| token.contains("Pages")) {
+                    if tokens.len() > 1 {
+                        warn(
+                            &mut warnings,
+                            format!("Filtering in `range {}` was dropped", args),
+                        );
+                    }
+                    Some("posts".to_string())
+                } else {
+                    go_expression(&tokens, &mut warnings)
+                };
+                match expression {
+                    Some(expression) => {
+                        output.push_str(&format!("{{{{#each {}}}}}", expression));
+                        blocks.push(GoBlock::Range);
+                    }
+                    None => {
+                        output.push_str(&comment(inner));
+                        blocks.push(GoBlock::Unsupported);
+                    }
+                }
+            }
+            "define" | "block" => blocks.push(GoBlock::Transparent),
+            "end" => match blocks.pop() {
+                Some(GoBlock::If) => output.push_str("{{/if}}"),
+                Some(GoBlock::With) => output.push_str("{{/with}}"),
+                Some(GoBlock::Range) => output.push_str("{{/each}}"),
+                Some(GoBlock::Unsupported) => output.push_str(&comment(inner)),
+                Some(GoBlock::Transparent) | None => {}
+            },
+            "partial" | "partialCached" => {
+                let tokens = split_args(args);
+                match tokens.first().and_then(|name| unquote(name)) {
+                    Some(name) => {
+                        if tokens.get(1).map_or(false, |context| context != ".") {
+                            warn(
+                                &mut warnings,
+                                format!("The context passed to partial {} was dropped", name),
+                            );
+                        }
+                        output.push_str(&format!("{{{{> {}}}}}", partial_name(name)));
+                    }
+                    None => {
+                        warn(
+                            &mut warnings,
+                            "Dynamic partials aren't supported".to_string(),
+                        );
+                        output.push_str(&comment(inner));
+                    }
+                }
+            }
+            "template" => {
+                warn(
+                    &mut warnings,
+                    format!("`template {}` isn't supported", args),
+                );
+                output.push_str(&comment(inner));
+            }
+            _ if inner.contains(":=") || (inner.starts_with('$') && inner.contains('=')) => {
+                warn(
+                    &mut warnings,
+                    "Template variables aren't supported".to_string(),
+                );
+                output.push_str(&comment(inner));
+            }
+            _ => match go_output(inner, &mut warnings) {
+                Some(converted) => output.push_str(&converted),
+                None => output.push_str(&comment(inner)),
+            },
+        }
+    }
+    output.push_str(&source[position..]);
+
+    while let Some(block) = blocks.pop() {
+        match block {
+            GoBlock::If => output.push_str("{{/if}}"),
+            GoBlock::With => output.push_str("{{/with}}"),
+            GoBlock::Range => output.push_str("{{/each}}"),
+            GoBlock::Transparent | GoBlock::Unsupported => continue,
+        }
+        warn(
+            &mut warnings,
+            "Unclosed Go template block was closed at the end".to_string(),
+        );
+    }
+
+    Converted {
+        template: output,
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_liquid_to_handlebars() {
+        let converted = liquid_to_handlebars(
+            "{% if page.title %}<h1>{{ page.title | escape }}</h1>{% endif %}\
+             {% for post in site.posts %}<a href=\"{{ post.url | relative_url }}\">\
+             {{ post.title }}</a>{% endfor %}{% include footer.html %}\
+             {{ content }}{{ page.date | date: \"%Y\" }}",
+        );
+
+        assert_eq!(
+            converted.template,
+            "{{#if title}}<h1>{{title}}</h1>{{/if}}{{#each posts}}<a href=\"{{url}}\">\
+             {{title}}</a>{{/each}}{{> footer}}{{{content}}}{{date}}"
+        );
+        assert_eq!(converted.warnings, vec!["Liquid filter `date` was dropped"]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_go_to_handlebars() {
+        let base =
+            "<html>{{ partial \"head.html\" . }}{{ block \"main\" . }}default{{ end }}</html>";
+        let layout = "{{ define \"main\" }}<h1>{{ .Title }}</h1>\
+                      {{ range .Site.RegularPages }}{{ .Params.subtitle }}{{ end }}\
+                      {{ .Content }}{{ end }}";
+
+        let converted = go_to_handlebars(&apply_go_defines(base, layout));
+        assert_eq!(
+            converted.template,
+            "<html>{{> head}}<h1>{{title}}</h1>{{#each posts}}{{subtitle}}{{/each}}\
+             {{{content}}}</html>"
+        );
+        assert!(converted.warnings.is_empty());
+    }
+}
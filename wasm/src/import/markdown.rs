@@ -0,0 +1,264 @@
+//! Markdown importer.
+//!
+//! CommonMark plus strikethrough, converted to the same nodes the editor
+//! produces. Raw HTML and tables aren't representable; their text is kept
+//! and a warning says so.
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag};
+use serde_json::{json, Value};
+
+use super::pm::DocBuilder;
+use super::{is_safe_url, ImportedDocument};
+
+/// An image being read: its alt text arrives as text events
+struct PendingImage {
+    src: String,
+    title: String,
+    alt: String,
+}
+
+fn link_mark(href: &str, title: &str) -> Value {
+    let title = if title.is_empty() {
+        Value::Null
+    } else {
+        Value::String(title.to_string())
+    };
+    json!({ "type": "link", "attrs": { "href": href, "title": title } })
+}
+
+/// Convert Markdown into ProseMirror JSON
+///
+/// A leading `# Heading` becomes the title (and stays in the body).
+pub fn markdown_to_pm(markdown: &str) -> ImportedDocument {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+
+    let mut builder = DocBuilder::new();
+    let mut warnings = Vec::new();
+    let mut image: Option<PendingImage> = None;
+    let mut title: Option<String> = None;
+    let mut heading_text: Option<String> = None;
+    let mut seen_block = false;
+    // Links with unsafe URLs keep their text but lose the mark
+    let mut dropped_links = Vec::new();
+
+    for event in Parser::new_ext(markdown, options) {
+        if let Some(pending) = image.as_mut() {
+            match event {
+                Event::End(Tag::Image(..)) => {}
+                Event::Text(text) | Event::Code(text) => {
+                    pending.alt.push_str(&text);
+                    continue;
+                }
+                _ => continue,
+            }
+        }
+
+        match event {
+            Event::Start(tag) => match tag {
+                Tag::Paragraph => {
+                    seen_block = true;
+                    builder.open(json!({ "type": "paragraph", "content": [] }));
+                }
+                Tag::Heading(level, _, _) => {
+                    let level = level as u64;
+                    if level == 1 && !seen_block && title.is_none() {
+                        heading_text = Some(String::new());
+                    }
+                    seen_block = true;
+                    builder.open(json!({
+                        "type": "heading",
+                        "attrs": { "level": level },
+                        "content": []
+                    }));
+                }
+                Tag::BlockQuote => {
+                    seen_block = true;
+                    builder.open(json!({ "type": "blockquote", "content": [] }));
+                }
+                Tag::CodeBlock(kind) => {
+                    seen_block = true;
+                    let language = match kind {
+                        CodeBlockKind::Fenced(info) => info
+                            .split_whitespace()
+                            .next()
+                            .map(|language| Value::String(language.to_string()))
+                            .unwrap_or(Value::Null),
+                        CodeBlockKind::Indented => Value::Null,
+                    };
+                    builder.open(json!({
+                        "type": "code_block",
+                        "attrs": { "language": language },
+                        "content": []
+                    }));
+                }
+                Tag::List(start) => {
+                    seen_block = true;
+                    builder.open(match start {
+                        Some(order) => json!({
+                            "type": "ordered_list",
+                            "attrs": { "order": order },
+                            "content": []
+                        }),
+                        None => json!({ "type": "bullet_list", "content": [] }),
+                    });
+                }
+                Tag::Item => builder.open(json!({ "type": "list_item", "content": [] })),
+                Tag::Emphasis => builder.push_mark(json!({ "type": "em" })),
+                Tag::Strong => builder.push_mark(json!({ "type": "strong" })),
+                Tag::Strikethrough => builder.push_mark(json!({ "type": "strike" })),
+                Tag::Link(_, href, link_title) => {
+                    if is_safe_url(&href) {
+                        builder.push_mark(link_mark(&href, &link_title));
+                        dropped_links.push(false);
+                    } else {
+                        warnings.push(format!("Removed link to {}", href));
+                        dropped_links.push(true);
+                    }
+                }
+                Tag::Image(_, src, image_title) => {
+                    image = Some(PendingImage {
+                        src: src.to_string(),
+                        title: image_title.to_string(),
+                        alt: String::new(),
+                    });
+                }
+                Tag::Table(_) => {
+                    seen_block = true;
+                    warnings.push("Tables aren't supported; their text was kept".to_string());
+                }
+                Tag::TableRow | Tag::TableHead => {
+                    builder.open(json!({ "type": "paragraph", "content": [] }))
+                }
+                Tag::TableCell | Tag::FootnoteDefinition(_) => {}
+            },
+            Event::End(tag) => match tag {
+                Tag::Paragraph => builder.close("paragraph"),
+                Tag::Heading(..) => {
+                    if let Some(text) = heading_text.take() {
+                        title = Some(text.trim().to_string()).filter(|text| !text.is_empty());
+                    }
+                    builder.close("heading");
+                }
+                Tag::BlockQuote => builder.close("blockquote"),
+                Tag::CodeBlock(_) => builder.close("code_block"),
+                Tag::List(Some(_)) => builder.close("ordered_list"),
+                Tag::List(None) => builder.close("bullet_list"),
+                Tag::Item => builder.close("list_item"),
+                Tag::Emphasis => builder.pop_mark("em"),
+                Tag::Strong => builder.pop_mark("strong"),
+                Tag::Strikethrough => builder.pop_mark("strike"),
+                Tag::Link(..) => {
+                    if dropped_links.pop() == Some(false) {
+                        builder.pop_mark("link");
+                    }
+                }
+                Tag::Image(..) => {
+                    if let Some(pending) = image.take() {
+                        if is_safe_url(&pending.src) {
+                            let title = Some(pending.title).filter(|title| !title.is_empty());
+                            builder.inline(json!({
+                                "type": "image",
+                                "attrs": { "src": pending.src, "alt": pending.alt, "title": title }
+                            }));
+                        } else {
+                            warnings.push(format!("Removed image from {}", pending.src));
+                        }
+                    }
+                }
+                Tag::TableRow | Tag::TableHead => builder.close("paragraph"),
+                Tag::TableCell => builder.text(" "),
+                Tag::Table(_) | Tag::FootnoteDefinition(_) => {}
+            },
+            Event::Text(text) => {
+                if let Some(heading) = heading_text.as_mut() {
+                    heading.push_str(&text);
+                }
+                builder.text(&text);
+            }
+            Event::Code(code) => {
+                if let Some(heading) = heading_text.as_mut() {
+                    heading.push_str(&code);
+                }
+                builder.push_mark(json!({ "type": "code" }));
+                builder.text(&code);
+                builder.pop_mark("code");
+            }
+            Event::Html(html) => {
+                let html = html.trim();
+                if !html.is_empty() {
+                    warnings.push(format!("Raw HTML was left out: {}", html));
+                }
+            }
+            Event::SoftBreak => builder.text(" "),
+            Event::HardBreak => builder.inline(json!({ "type": "hard_break" })),
+            Event::Rule => {
+                seen_block = true;
+                builder.leaf(json!({ "type": "horizontal_rule" }));
+            }
+            Event::FootnoteReference(_) | Event::TaskListMarker(_) => {}
+        }
+    }
+
+    ImportedDocument {
+        title,
+        content: builder.finish(),
+        assets: Vec::new(),
+        warnings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_markdown_to_pm() {
+        let imported = markdown_to_pm(
+            "# Zine Fair\n\nBring *your* **zines** and [friends](https://example.com).\n\n\
+             - tables\n- chairs\n\n1. arrive\n2. set up\n\n> quoted\n\n---\n\n\
+             ![A stall](/assets/stall.jpg)\n",
+        );
+
+        assert_eq!(imported.title.as_deref(), Some("Zine Fair"));
+        assert!(imported.warnings.is_empty());
+        let content = imported.content["content"].as_array().unwrap();
+        assert_eq!(content[0]["type"], "heading");
+        assert_eq!(content[0]["attrs"]["level"], 1);
+
+        let inlines = content[1]["content"].as_array().unwrap();
+        assert_eq!(
+            inlines[1],
+            json!({ "type": "text", "text": "your", "marks": [{ "type": "em" }] })
+        );
+        assert_eq!(inlines[3]["marks"], json!([{ "type": "strong" }]));
+        assert_eq!(
+            inlines[5]["marks"][0]["attrs"]["href"],
+            "https://example.com"
+        );
+
+        assert_eq!(content[2]["type"], "bullet_list");
+        let chairs = &content[2]["content"][1]["content"][0]["content"][0];
+        assert_eq!(chairs["text"], "chairs");
+        assert_eq!(content[3]["type"], "ordered_list");
+        assert_eq!(content[3]["attrs"]["order"], 1);
+        assert_eq!(content[4]["type"], "blockquote");
+        assert_eq!(content[5]["type"], "horizontal_rule");
+        assert_eq!(content[6]["content"][0]["attrs"]["alt"], "A stall");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_markdown_unsafe_links() {
+        let imported = markdown_to_pm("[click](javascript:alert(1)) here");
+        let inlines = imported.content["content"][0]["content"]
+            .as_array()
+            .unwrap();
+        assert_eq!(inlines[0], json!({ "type": "text", "text": "click here" }));
+        assert_eq!(
+            imported.warnings,
+            vec!["Removed link to javascript:alert(1)"]
+        );
+    }
+}
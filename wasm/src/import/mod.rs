@@ -0,0 +1,175 @@
+//! Importers that convert documents from other tools into the rich-text model.
+//!
+//! Each importer produces an [`ImportedDocument`]: ProseMirror JSON for the
+//! body plus any embedded binaries, which the store saves as assets before
+//! writing the body into a new file.
+
+use std::collections::HashMap;
+
+use serde_json::{Map, Value};
+
+pub mod archive;
+pub mod article;
+pub mod bundle;
+pub mod csv;
+pub mod docx;
+pub mod ghost;
+pub mod html;
+pub mod images;
+pub mod layouts;
+pub mod markdown;
+pub mod pm;
+pub mod static_site;
+pub mod svg;
+
+/// A binary file extracted from an imported document
+#[derive(Debug, Clone)]
+pub struct ImportedAsset {
+    /// Placeholder used as the image `src` until the asset has been stored
+    pub key: String,
+    pub name: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// The result of converting an external document
+#[derive(Debug, Clone)]
+pub struct ImportedDocument {
+    pub title: Option<String>,
+    /// ProseMirror JSON (`{"type": "doc", "content": [...]}`)
+    pub content: Value,
+    pub assets: Vec<ImportedAsset>,
+    /// Things that could not be converted faithfully
+    pub warnings: Vec<String>,
+}
+
+/// A post or page from another platform, ready to be created
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedPage {
+    /// "post" or "page"
+    pub collection: &'static str,
+    pub name: String,
+    pub title: String,
+    /// Fields to set, already renamed to this site's (`layout` → `template`)
+    pub meta: Map<String, Value>,
+    pub draft: bool,
+    /// ProseMirror JSON; image sources are still the original URLs
+    pub content: Value,
+}
+
+/// Guess a MIME type from a file name's extension
+pub fn mime_type_for(name: &str) -> &'static str {
+    let extension = name.rsplit('.').next().unwrap_or("").to_lowercase();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        "emf" => "image/emf",
+        "wmf" => "image/wmf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// The folders a file sits in, from its path relative to a dropped folder
+///
+/// Empty, `.` and `..` parts are left out, so a path can't climb out of
+/// the folder it was dropped into.
+pub fn path_folders(path: &str) -> Vec<String> {
+    let mut parts: Vec<String> = path
+        .split(|c: char| c == '/' || c == '\\')
+        .map(str::trim)
+        .filter(|part| !part.is_empty() && *part != "." && *part != "..")
+        .map(str::to_string)
+        .collect();
+    // The last part is the file itself
+    parts.pop();
+    parts
+}
+
+/// Whether a link or image URL from outside content is safe to keep
+///
+/// Relative URLs and http(s), mailto and tel are; anything else (notably
+/// `javascript:`) could run code when clicked.
+pub fn is_safe_url(url: &str) -> bool {
+    let url = url.trim();
+    let scheme_end = url.find(|c: char| c == ':' || c == '/' || c == '?' || c == '#');
+    match scheme_end {
+        Some(end) if url[end..].starts_with(':') => {
+            let scheme = url[..end].to_ascii_lowercase();
+            matches!(scheme.as_str(), "http" | "https" | "mailto" | "tel")
+        }
+        _ => true,
+    }
+}
+
+/// Replace image `src` placeholders with the URLs of the stored assets
+pub fn resolve_image_sources(node: &mut Value, urls: &HashMap<String, String>) {
+    if let Some(Value::String(src)) = node.get_mut("attrs").and_then(|attrs| attrs.get_mut("src")) {
+        if let Some(url) = urls.get(src.as_str()) {
+            *src = url.clone();
+        }
+    }
+
+    if let Some(Value::Array(children)) = node.get_mut("content") {
+        for child in children {
+            resolve_image_sources(child, urls);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_resolve_image_sources() {
+        let mut doc = json!({
+            "type": "doc",
+            "content": [
+                { "type": "paragraph", "content": [
+                    { "type": "image", "attrs": { "src": "rId7", "alt": "A cat" } }
+                ]}
+            ]
+        });
+        let mut urls = HashMap::new();
+        urls.insert("rId7".to_string(), "/assets/cat.png".to_string());
+
+        resolve_image_sources(&mut doc, &urls);
+
+        assert_eq!(
+            doc["content"][0]["content"][0]["attrs"]["src"],
+            json!("/assets/cat.png")
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_is_safe_url() {
+        assert!(is_safe_url("https://example.com/a?b=c:d"));
+        assert!(is_safe_url("/assets/cat.png"));
+        assert!(is_safe_url("#top"));
+        assert!(is_safe_url("mailto:editor@example.com"));
+        assert!(!is_safe_url("javascript:alert(1)"));
+        assert!(!is_safe_url(" JavaScript:alert(1)"));
+        assert!(!is_safe_url("data:text/html,<script>"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_path_folders() {
+        assert_eq!(path_folders("Zine/covers/2024/front.png"), vec!["Zine", "covers", "2024"]);
+        assert_eq!(path_folders("../.\\scans//back.jpg"), vec!["scans"]);
+        assert!(path_folders("loose.gif").is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_mime_type_for() {
+        assert_eq!(mime_type_for("media/image1.PNG"), "image/png");
+        assert_eq!(mime_type_for("photo.jpeg"), "image/jpeg");
+        assert_eq!(mime_type_for("unknown"), "application/octet-stream");
+    }
+}
@@ -0,0 +1,269 @@
+//! Build ProseMirror JSON from a stream of open/close/text events.
+//!
+//! Markdown and HTML importers describe what they read; the builder keeps the
+//! result valid for the editor: inline content outside a paragraph gets one,
+//! blocks close any paragraph they interrupt, and closing a node that was
+//! never opened is ignored, since real-world HTML is rarely well-formed.
+
+use serde_json::{json, Value};
+
+/// Nodes whose content is inline
+fn is_textblock(node_type: &str) -> bool {
+    matches!(node_type, "paragraph" | "heading" | "code_block")
+}
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(Value::as_str).unwrap_or("")
+}
+
+fn push_child(parent: &mut Value, child: Value) {
+    if !parent["content"].is_array() {
+        parent["content"] = json!([]);
+    }
+    if let Some(content) = parent["content"].as_array_mut() {
+        content.push(child);
+    }
+}
+
+struct Open {
+    node: Value,
+    /// Opened by the builder to hold stray inline content
+    implicit: bool,
+}
+
+pub struct DocBuilder {
+    stack: Vec<Open>,
+    marks: Vec<Value>,
+}
+
+impl Default for DocBuilder {
+    fn default() -> Self {
+        DocBuilder::new()
+    }
+}
+
+impl DocBuilder {
+    pub fn new() -> Self {
+        DocBuilder {
+            stack: vec![Open {
+                node: json!({ "type": "doc", "content": [] }),
+                implicit: false,
+            }],
+            marks: Vec::new(),
+        }
+    }
+
+    fn top_type(&self) -> &str {
+        self.stack
+            .last()
+            .map(|open| node_type(&open.node))
+            .unwrap_or("")
+    }
+
+    /// Close the innermost node, adding it to its parent
+    fn pop(&mut self) {
+        if self.stack.len() < 2 {
+            return;
+        }
+        let open = self.stack.pop().unwrap();
+        let empty = open.node["content"]
+            .as_array()
+            .map_or(true, |content| content.is_empty());
+        // Whitespace between blocks opens paragraphs that never get content
+        if open.implicit && empty {
+            return;
+        }
+        let mut node = open.node;
+        if empty && node_type(&node) == "list_item" {
+            node["content"] = json!([{ "type": "paragraph" }]);
+        } else if empty {
+            if let Some(object) = node.as_object_mut() {
+                object.remove("content");
+            }
+        }
+        push_child(&mut self.stack.last_mut().unwrap().node, node);
+    }
+
+    /// Start a block node (`content` is filled in as children arrive)
+    pub fn open(&mut self, node: Value) {
+        // Blocks can't sit inside inline content, so end the textblock
+        while is_textblock(self.top_type()) {
+            self.pop();
+        }
+        self.stack.push(Open {
+            node,
+            implicit: false,
+        });
+    }
+
+    /// End the innermost open node of this type, and anything opened inside it
+    pub fn close(&mut self, node_type_name: &str) {
+        let position = self
+            .stack
+            .iter()
+            .rposition(|open| !open.implicit && node_type(&open.node) == node_type_name);
+        if let Some(position) = position {
+            while self.stack.len() > position.max(1) {
+                self.pop();
+            }
+        }
+    }
+
+    /// Close a node left open by a sibling's start, like `<li>` without `</li>`
+    ///
+    /// Only the innermost block container counts, so a nested list's items
+    /// don't close their parent item.
+    pub fn close_sibling(&mut self, node_type_name: &str) {
+        let innermost = self
+            .stack
+            .iter()
+            .rev()
+            .find(|open| !open.implicit && !is_textblock(node_type(&open.node)));
+        if innermost.map_or(false, |open| node_type(&open.node) == node_type_name) {
+            self.close(node_type_name);
+        }
+    }
+
+    /// Make sure inline content has a textblock to go into
+    fn ensure_textblock(&mut self) {
+        if !is_textblock(self.top_type()) {
+            self.stack.push(Open {
+                node: json!({ "type": "paragraph", "content": [] }),
+                implicit: true,
+            });
+        }
+    }
+
+    fn top_is_empty(&self) -> bool {
+        self.stack
+            .last()
+            .and_then(|open| open.node["content"].as_array())
+            .map_or(true, |content| content.is_empty())
+    }
+
+    pub fn push_mark(&mut self, mark: Value) {
+        self.marks.push(mark);
+    }
+
+    /// Remove the innermost mark of this type
+    pub fn pop_mark(&mut self, mark_type: &str) {
+        if let Some(position) = self
+            .marks
+            .iter()
+            .rposition(|mark| node_type(mark) == mark_type)
+        {
+            self.marks.remove(position);
+        }
+    }
+
+    /// Add text with the current marks
+    pub fn text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let in_code = self.top_type() == "code_block";
+        if !in_code && !is_textblock(self.top_type()) && text.trim().is_empty() {
+            return;
+        }
+        self.ensure_textblock();
+        let text = if !in_code && self.top_is_empty() {
+            text.trim_start()
+        } else {
+            text
+        };
+        if text.is_empty() {
+            return;
+        }
+
+        let marks = if in_code {
+            Vec::new()
+        } else {
+            self.marks.clone()
+        };
+        let top = &mut self.stack.last_mut().unwrap().node;
+        if let Some(last) = top["content"].as_array_mut().and_then(|c| c.last_mut()) {
+            let last_marks = last.get("marks").cloned().unwrap_or_else(|| json!([]));
+            if node_type(last) == "text" && last_marks == Value::Array(marks.clone()) {
+                let joined = format!("{}{}", last["text"].as_str().unwrap_or(""), text);
+                last["text"] = Value::String(joined);
+                return;
+            }
+        }
+
+        let mut node = json!({ "type": "text", "text": text });
+        if !marks.is_empty() {
+            node["marks"] = Value::Array(marks);
+        }
+        push_child(top, node);
+    }
+
+    /// Add an inline leaf such as an image or hard break
+    pub fn inline(&mut self, node: Value) {
+        self.ensure_textblock();
+        push_child(&mut self.stack.last_mut().unwrap().node, node);
+    }
+
+    /// Add a block leaf such as a horizontal rule
+    pub fn leaf(&mut self, node: Value) {
+        self.open(node);
+        self.pop();
+    }
+
+    /// Close everything and return the document
+    pub fn finish(mut self) -> Value {
+        while self.stack.len() > 1 {
+            self.pop();
+        }
+        let mut doc = self.stack.pop().unwrap().node;
+        if doc["content"].as_array().map_or(true, |c| c.is_empty()) {
+            doc["content"] = json!([{ "type": "paragraph" }]);
+        }
+        doc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_inline_content_gets_a_paragraph() {
+        let mut builder = DocBuilder::new();
+        builder.open(json!({ "type": "blockquote", "content": [] }));
+        builder.text("  Quoted ");
+        builder.push_mark(json!({ "type": "em" }));
+        builder.text("softly");
+        builder.pop_mark("em");
+        builder.close("blockquote");
+        builder.text("\n");
+        builder.leaf(json!({ "type": "horizontal_rule" }));
+
+        assert_eq!(
+            builder.finish(),
+            json!({ "type": "doc", "content": [
+                { "type": "blockquote", "content": [
+                    { "type": "paragraph", "content": [
+                        { "type": "text", "text": "Quoted " },
+                        { "type": "text", "text": "softly", "marks": [{ "type": "em" }] }
+                    ]}
+                ]},
+                { "type": "horizontal_rule" }
+            ]})
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_unbalanced_close_is_ignored() {
+        let mut builder = DocBuilder::new();
+        builder.open(json!({ "type": "paragraph", "content": [] }));
+        builder.text("One");
+        builder.close("list_item");
+        builder.open(json!({ "type": "paragraph", "content": [] }));
+        builder.text("Two");
+
+        let doc = builder.finish();
+        assert_eq!(doc["content"].as_array().unwrap().len(), 2);
+        assert_eq!(doc["content"][1]["content"][0]["text"], "Two");
+    }
+}
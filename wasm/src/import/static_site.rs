@@ -0,0 +1,1111 @@
+//! Jekyll and Hugo site importer.
+//!
+//! Sorts a generator's repository into what a site and its theme are made
+//! of: Markdown posts and pages (front matter becomes metadata), layouts and
+//! includes (converted by [`layouts`](super::layouts) into templates and
+//! partials), stylesheets, and everything else as static assets. Nothing is
+//! stored here; the store creates the projects from a [`StaticSite`].
+
+use std::collections::BTreeMap;
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use super::html::html_to_pm;
+use super::layouts::{
+    apply_go_defines, go_to_handlebars, liquid_to_handlebars, partial_name, Converted,
+};
+use super::markdown::markdown_to_pm;
+use super::{ImportedDocument, ImportedPage};
+use crate::render::manifest::mime_type_for_path;
+use crate::render::slugify;
+use crate::types::SourceFile;
+
+/// Hugo content sections whose pages become posts
+const POST_SECTIONS: [&str; 5] = ["posts", "post", "blog", "articles", "news"];
+
+/// Front matter fields that name something this site stores elsewhere
+const RESERVED_FIELDS: [&str; 6] = ["id", "name", "type", "version", "body", "content"];
+
+const HUGO_CONFIGS: [&str; 6] = [
+    "hugo.toml",
+    "hugo.yaml",
+    "hugo.yml",
+    "config.toml",
+    "config.yaml",
+    "config.yml",
+];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Generator {
+    Jekyll,
+    Hugo,
+}
+
+/// A file the old site served as it was
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticAsset {
+    /// Where the old site served it, e.g. `/images/logo.png`
+    pub url: String,
+    pub name: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Everything taken from a Jekyll or Hugo repository
+#[derive(Debug, Clone, PartialEq)]
+pub struct StaticSite {
+    pub generator: Generator,
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub base_path: Option<String>,
+    pub pages: Vec<ImportedPage>,
+    /// Name → Handlebars
+    pub templates: Vec<(String, String)>,
+    pub partials: Vec<(String, String)>,
+    /// Name → CSS; names keep their folders, so `/css/main.css` stays put
+    pub styles: Vec<(String, String)>,
+    pub assets: Vec<StaticAsset>,
+    pub warnings: Vec<String>,
+}
+
+impl StaticSite {
+    fn new(generator: Generator) -> Self {
+        StaticSite {
+            generator,
+            name: None,
+            url: None,
+            base_path: None,
+            pages: Vec::new(),
+            templates: Vec::new(),
+            partials: Vec::new(),
+            styles: Vec::new(),
+            assets: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn warn(&mut self, warning: String) {
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn add_template(&mut self, path: &str, name: String, converted: Converted) {
+        for warning in converted.warnings {
+            self.warn(format!("{}: {}", path, warning));
+        }
+        self.templates.push((name, converted.template));
+    }
+
+    fn add_asset(&mut self, url: String, file: &SourceFile) {
+        let name = url.rsplit('/').next().unwrap_or("").to_string();
+        self.assets.push(StaticAsset {
+            mime_type: mime_type_for_path(&name).to_string(),
+            name,
+            url,
+            data: file
+                .data
+                .clone()
+                .unwrap_or_else(|| file.content.as_bytes().to_vec()),
+        });
+    }
+
+    fn has_template(&self, name: &str) -> bool {
+        self.templates.iter().any(|(template, _)| template == name)
+    }
+}
+
+fn parse_yaml(source: &str) -> Result<Map<String, Value>, String> {
+    let yaml: serde_yaml::Value = serde_yaml::from_str(source).map_err(|e| e.to_string())?;
+    match serde_json::to_value(yaml).map_err(|e| e.to_string())? {
+        Value::Object(map) => Ok(map),
+        Value::Null => Ok(Map::new()),
+        _ => Err("front matter isn't a set of fields".to_string()),
+    }
+}
+
+fn toml_value(source: &str) -> Value {
+    if let Some(items) = source
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+    {
+        let mut values = Vec::new();
+        let mut quote = None;
+        let mut start = 0;
+        for (index, c) in items.char_indices() {
+            match quote {
+                Some(open) if c == open => quote = None,
+                Some(_) => {}
+                None if c == '"' || c == '\'' => quote = Some(c),
+                None if c == ',' => {
+                    values.push(&items[start..index]);
+                    start = index + 1;
+                }
+                None => {}
+            }
+        }
+        values.push(&items[start..]);
+        return Value::Array(
+            values
+                .into_iter()
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(toml_value)
+                .collect(),
+        );
+    }
+    for quote in &['"', '\''] {
+        if source.len() >= 2 && source.starts_with(*quote) && source.ends_with(*quote) {
+            let text = &source[1..source.len() - 1];
+            return Value::String(text.replace("\\\"", "\"").replace("\\\\", "\\"));
+        }
+    }
+    match source {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
+    }
+    if let Ok(integer) = source.replace('_', "").parse::<i64>() {
+        return json!(integer);
+    }
+    match source.parse::<f64>() {
+        Ok(float) if float.is_finite() => json!(float),
+        // Dates and anything else stay as written
+        _ => Value::String(source.to_string()),
+    }
+}
+
+/// The simple subset of TOML front matter and config files use: top-level
+/// keys plus one level of tables, each value on one line
+///
+/// Keys in `[params]` are flattened, as Hugo exposes them as page fields.
+fn parse_toml(source: &str) -> Map<String, Value> {
+    let mut fields = Map::new();
+    let mut table: Option<String> = None;
+    for line in source.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            table = Some(
+                line.trim_matches(|c: char| c == '[' || c == ']')
+                    .trim()
+                    .to_string(),
+            );
+            continue;
+        }
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim().trim_matches('"').to_string(), value.trim()),
+            None => continue,
+        };
+        let value = toml_value(value);
+        match table.as_deref() {
+            None | Some("params") => {
+                fields.insert(key, value);
+            }
+            Some(table) => {
+                let entry = fields
+                    .entry(table.to_string())
+                    .or_insert_with(|| Value::Object(Map::new()));
+                if let Some(entry) = entry.as_object_mut() {
+                    entry.insert(key, value);
+                }
+            }
+        }
+    }
+    fields
+}
+
+/// A file's front matter (YAML between `---`, or TOML between `+++`) and
+/// the body after it; no front matter gives `None`
+pub fn front_matter(source: &str) -> Result<Option<(Map<String, Value>, &str)>, String> {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    let fence = match source.get(..3) {
+        Some(fence @ "---") | Some(fence @ "+++") => fence,
+        _ => return Ok(None),
+    };
+    let first_line = match source.find('\n') {
+        Some(end) => end + 1,
+        None => return Ok(None),
+    };
+    if source[..first_line].trim() != fence {
+        return Ok(None);
+    }
+
+    let rest = &source[first_line..];
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == fence {
+            let header = &rest[..offset];
+            let body = &rest[offset + line.len()..];
+            let fields = if fence == "+++" {
+                parse_toml(header)
+            } else {
+                parse_yaml(header)?
+            };
+            return Ok(Some((fields, body)));
+        }
+        offset += line.len();
+    }
+    Err("front matter is never closed".to_string())
+}
+
+/// What a page's front matter says about it
+#[derive(Debug, Default)]
+struct PageFields {
+    title: Option<String>,
+    layout: Option<String>,
+    slug: Option<String>,
+    draft: bool,
+    meta: Map<String, Value>,
+}
+
+fn value_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) if !text.is_empty() => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn page_fields(front: Map<String, Value>, path: &str, site: &mut StaticSite) -> PageFields {
+    let mut fields = PageFields::default();
+    for (key, value) in front {
+        match key.as_str() {
+            "title" => fields.title = value_string(&value),
+            "layout" => fields.layout = value_string(&value),
+            "slug" => fields.slug = value_string(&value),
+            "draft" => fields.draft |= value == Value::Bool(true),
+            "published" => fields.draft |= value == Value::Bool(false),
+            "permalink" | "url" => {
+                if let Some(url) = value_string(&value) {
+                    fields.meta.insert("url".to_string(), json!(url));
+                }
+            }
+            "date" => {
+                if let Some(date) = value_string(&value) {
+                    fields.meta.insert("date".to_string(), json!(date));
+                }
+            }
+            key if RESERVED_FIELDS.contains(&key) => {
+                site.warn(format!(
+                    "{}: front matter field `{}` was dropped",
+                    path, key
+                ));
+            }
+            _ => {
+                fields.meta.insert(key, value);
+            }
+        }
+    }
+    fields
+}
+
+/// Read a file's front matter, warning (and using none) if it's broken
+fn read_front_matter<'a>(
+    path: &str,
+    source: &'a str,
+    site: &mut StaticSite,
+) -> Option<(Map<String, Value>, &'a str)> {
+    match front_matter(source) {
+        Ok(front) => front,
+        Err(e) => {
+            site.warn(format!("{}: {}", path, e));
+            None
+        }
+    }
+}
+
+/// Drop the Liquid that only prefixes URLs, so Markdown links still parse
+fn strip_liquid_urls(body: &str) -> String {
+    let base = Regex::new(r"\{\{\s*site\.baseurl\s*\}\}").expect("valid baseurl regex");
+    let filtered = Regex::new(r#"\{\{\s*["']([^"']+)["']\s*\|\s*(?:relative|absolute)_url\s*\}\}"#)
+        .expect("valid URL filter regex");
+    filtered
+        .replace_all(&base.replace_all(body, ""), "$1")
+        .into_owned()
+}
+
+/// Convert a post or page body, warning about template code left in it
+fn convert_body(path: &str, body: &str, site: &mut StaticSite) -> ImportedDocument {
+    let body = match site.generator {
+        Generator::Jekyll => strip_liquid_urls(body),
+        Generator::Hugo => body.to_string(),
+    };
+    if body.contains("{{<") || body.contains("{{%") {
+        site.warn(format!("{}: shortcodes were kept as text", path));
+    } else if body.contains("{%") || body.contains("{{") {
+        site.warn(format!("{}: Liquid tags were kept as text", path));
+    }
+    let document = if path.ends_with(".html") {
+        html_to_pm(&body)
+    } else {
+        markdown_to_pm(&body)
+    };
+    for warning in &document.warnings {
+        site.warn(format!("{}: {}", path, warning));
+    }
+    document
+}
+
+/// Point relative image sources at where the old site served them
+fn resolve_relative_images(node: &mut Value, base_url: &str) {
+    let is_image = node.get("type").and_then(Value::as_str) == Some("image");
+    if let Some(Value::String(src)) = node.get_mut("attrs").and_then(|attrs| attrs.get_mut("src")) {
+        let is_relative = !src.starts_with('/') && !src.starts_with('#') && !src.contains(':');
+        if is_image && is_relative && !src.is_empty() {
+            *src = format!("{}{}", base_url, src.trim_start_matches("./"));
+        }
+    }
+    if let Some(Value::Array(children)) = node.get_mut("content") {
+        for child in children {
+            resolve_relative_images(child, base_url);
+        }
+    }
+}
+
+fn file_stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => &name[..dot],
+        _ => name,
+    }
+}
+
+fn extension(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name.rfind('.') {
+        Some(dot) if dot > 0 => name[dot + 1..].to_ascii_lowercase(),
+        _ => String::new(),
+    }
+}
+
+/// Repository files that aren't part of the site
+fn is_repository_file(path: &str) -> bool {
+    let first = path.split('/').next().unwrap_or("");
+    if first.starts_with('.')
+        || matches!(
+            first,
+            "node_modules" | "vendor" | "_site" | "public" | "resources" | "bin" | "script"
+        )
+    {
+        return true;
+    }
+    if path.contains('/') {
+        return false;
+    }
+    let upper = path.to_ascii_uppercase();
+    let known = [
+        "README",
+        "LICENSE",
+        "CHANGELOG",
+        "GEMFILE",
+        "RAKEFILE",
+        "CNAME",
+        "PACKAGE",
+    ];
+    known.iter().any(|prefix| upper.starts_with(prefix))
+        || matches!(
+            extension(path).as_str(),
+            "yml" | "yaml" | "toml" | "lock" | "gemspec" | "json"
+        )
+}
+
+/// Which generator the paths look like they belong to
+pub fn detect_generator<'a>(paths: impl Iterator<Item = &'a str> + Clone) -> Option<Generator> {
+    let jekyll = paths.clone().any(|path| {
+        path == "_config.yml"
+            || path == "_config.yaml"
+            || path.starts_with("_posts/")
+            || path.starts_with("_layouts/")
+    });
+    if jekyll {
+        return Some(Generator::Jekyll);
+    }
+    let hugo = paths.clone().any(|path| {
+        HUGO_CONFIGS.contains(&path)
+            || path.starts_with("archetypes/")
+            || path.starts_with("content/")
+            || path.starts_with("layouts/")
+    });
+    if hugo {
+        return Some(Generator::Hugo);
+    }
+    None
+}
+
+/// Clean up paths, unwrap a repository downloaded inside one folder, and
+/// fill in a Hugo theme's files wherever the site doesn't override them
+fn normalize_paths(files: &[SourceFile]) -> Vec<(String, &SourceFile)> {
+    let mut paths: Vec<(String, &SourceFile)> = files
+        .iter()
+        .map(|file| {
+            let path = file.path.replace('\\', "/");
+            let path = path.trim_start_matches("./").trim_start_matches('/');
+            (path.to_string(), file)
+        })
+        .filter(|(path, _)| !path.is_empty() && !path.ends_with('/'))
+        .collect();
+
+    if detect_generator(paths.iter().map(|(path, _)| path.as_str())).is_none() {
+        let folder = paths
+            .first()
+            .and_then(|(path, _)| path.split_once('/'))
+            .map(|(folder, _)| format!("{}/", folder));
+        if let Some(folder) = folder {
+            if paths.iter().all(|(path, _)| path.starts_with(&folder)) {
+                for (path, _) in paths.iter_mut() {
+                    *path = path[folder.len()..].to_string();
+                }
+            }
+        }
+    }
+
+    let theme_files: Vec<(String, &SourceFile)> = paths
+        .iter()
+        .filter_map(|(path, file)| {
+            let (_, inner) = path.strip_prefix("themes/")?.split_once('/')?;
+            Some((inner.to_string(), *file))
+        })
+        .collect();
+    paths.retain(|(path, _)| !path.starts_with("themes/"));
+    for (path, file) in theme_files {
+        if !paths.iter().any(|(existing, _)| *existing == path) {
+            paths.push((path, file));
+        }
+    }
+    paths.sort_by(|a, b| a.0.cmp(&b.0));
+    paths
+}
+
+fn read_config(path: &str, source: &str, site: &mut StaticSite) -> Map<String, Value> {
+    if path.ends_with(".toml") {
+        return parse_toml(source);
+    }
+    match parse_yaml(source) {
+        Ok(config) => config,
+        Err(e) => {
+            site.warn(format!("{}: {}", path, e));
+            Map::new()
+        }
+    }
+}
+
+/// The template a Jekyll layout becomes: `default` is the site's default
+fn jekyll_template_name(
+    layout: &str,
+    layouts: &BTreeMap<String, (Option<String>, String)>,
+) -> String {
+    if layout == "default" && !layouts.contains_key("index") {
+        "index".to_string()
+    } else {
+        layout.to_string()
+    }
+}
+
+/// A Jekyll layout with the layouts it sits inside wrapped around it
+fn flatten_jekyll_layout(
+    name: &str,
+    layouts: &BTreeMap<String, (Option<String>, String)>,
+    site: &mut StaticSite,
+) -> Option<String> {
+    let (mut parent, mut template) = layouts.get(name).cloned()?;
+    let mut seen = vec![name.to_string()];
+    while let Some(outer) = parent.take() {
+        if seen.contains(&outer) {
+            site.warn(format!("layout {}: layouts include each other", name));
+            break;
+        }
+        let (outer_parent, outer_template) = match layouts.get(&outer) {
+            Some(layout) => layout.clone(),
+            None => {
+                site.warn(format!("layout {}: layout {} not found", name, outer));
+                break;
+            }
+        };
+        if !outer_template.contains("{{{content}}}") {
+            site.warn(format!(
+                "layout {}: layout {} has no {{{{ content }}}}",
+                name, outer
+            ));
+            break;
+        }
+        template = outer_template.replacen("{{{content}}}", &template, 1);
+        seen.push(outer);
+        parent = outer_parent;
+    }
+    Some(template)
+}
+
+fn import_jekyll(files: &[(String, &SourceFile)], site: &mut StaticSite) {
+    // Layout name → (parent layout, Handlebars)
+    let mut layouts: BTreeMap<String, (Option<String>, String)> = BTreeMap::new();
+    // Pages whose body is a template, with their layout
+    let mut page_templates: Vec<(String, Option<String>, String)> = Vec::new();
+
+    for (path, file) in files {
+        let path = path.as_str();
+        if path == "_config.yml" || path == "_config.yaml" {
+            let config = read_config(path, &file.content, site);
+            site.name = config.get("title").and_then(value_string);
+            site.url = config.get("url").and_then(value_string);
+            site.base_path = config.get("baseurl").and_then(value_string);
+            continue;
+        }
+
+        if let Some(rest) = path.strip_prefix("_layouts/") {
+            let (front, body) = read_front_matter(path, &file.content, site)
+                .unwrap_or((Map::new(), file.content.as_str()));
+            let converted = liquid_to_handlebars(body);
+            for warning in converted.warnings {
+                site.warn(format!("{}: {}", path, warning));
+            }
+            let parent = front.get("layout").and_then(value_string);
+            layouts.insert(file_stem(rest).to_string(), (parent, converted.template));
+            continue;
+        }
+        if let Some(rest) = path.strip_prefix("_includes/") {
+            let converted = liquid_to_handlebars(&file.content);
+            for warning in converted.warnings {
+                site.warn(format!("{}: {}", path, warning));
+            }
+            site.partials.push((partial_name(rest), converted.template));
+            continue;
+        }
+
+        let is_draft = path.starts_with("_drafts/");
+        if path.starts_with("_posts/") || is_draft {
+            if !matches!(extension(path).as_str(), "md" | "markdown" | "html") {
+                site.add_asset(format!("/{}", path), file);
+                continue;
+            }
+            let (front, body) = read_front_matter(path, &file.content, site)
+                .unwrap_or((Map::new(), file.content.as_str()));
+            let mut fields = page_fields(front, path, site);
+            // Posts are named `YYYY-MM-DD-title.md`
+            let stem = file_stem(path);
+            let dated = stem
+                .get(..10)
+                .filter(|date| chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok());
+            let slug = match dated {
+                Some(date) => {
+                    fields
+                        .meta
+                        .entry("date".to_string())
+                        .or_insert_with(|| json!(date));
+                    stem[10..].trim_start_matches('-')
+                }
+                None => stem,
+            };
+            fields.draft |= is_draft;
+            add_page(site, "post", slug, None, fields, path, body);
+            continue;
+        }
+
+        if path.starts_with('_') || is_repository_file(path) {
+            if path.starts_with("_sass/") {
+                site.warn("Sass in _sass/ can't be compiled and wasn't imported".to_string());
+            } else if !is_repository_file(path) {
+                let folder = path.split('/').next().unwrap_or(path);
+                site.warn(format!("{} wasn't imported", folder));
+            }
+            continue;
+        }
+
+        let extension = extension(path);
+        match extension.as_str() {
+            "md" | "markdown" | "html" => {
+                let (front, body) = match read_front_matter(path, &file.content, site) {
+                    Some(front) => front,
+                    // Jekyll copies files without front matter as they are
+                    None if extension == "html" => {
+                        site.add_asset(format!("/{}", path), file);
+                        continue;
+                    }
+                    None => continue,
+                };
+                let fields = page_fields(front, path, site);
+                let stem = path.rsplit_once('.').map_or(path, |(stem, _)| stem);
+                let folder = if stem == "index" {
+                    Some("")
+                } else {
+                    stem.strip_suffix("/index")
+                };
+                let (name, url) = match folder {
+                    Some("") => ("index".to_string(), None),
+                    Some(folder) => (slugify(folder), Some(format!("/{}/", folder))),
+                    None if stem.contains('/') => (slugify(stem), Some(format!("/{}/", stem))),
+                    None => (slugify(stem), None),
+                };
+
+                // A page written in Liquid is really a template of its own
+                let is_template =
+                    extension == "html" && (body.contains("{%") || body.contains("{{"));
+                if is_template {
+                    let converted = liquid_to_handlebars(body);
+                    let template = format!("page-{}", name);
+                    site.add_template(path, template.clone(), converted);
+                    page_templates.push((
+                        template.clone(),
+                        fields.layout.clone(),
+                        path.to_string(),
+                    ));
+                    let mut fields = fields;
+                    fields.layout = None;
+                    fields.meta.insert("template".to_string(), json!(template));
+                    add_page(site, "page", &name, url, fields, path, "");
+                } else {
+                    add_page(site, "page", &name, url, fields, path, body);
+                }
+            }
+            "css" => {
+                let css = read_front_matter(path, &file.content, site)
+                    .map_or(file.content.as_str(), |(_, body)| body);
+                let name = path.trim_end_matches(".css").to_string();
+                site.styles.push((name, css.to_string()));
+            }
+            "scss" | "sass" => {
+                site.warn(format!(
+                    "{}: Sass can't be compiled and wasn't imported",
+                    path
+                ));
+            }
+            _ => site.add_asset(format!("/{}", path), file),
+        }
+    }
+
+    let names: Vec<String> = layouts.keys().cloned().collect();
+    for name in names {
+        if let Some(template) = flatten_jekyll_layout(&name, &layouts, site) {
+            site.templates
+                .push((jekyll_template_name(&name, &layouts), template));
+        }
+    }
+    // Pages that were templates sit inside their layouts too
+    for (template, layout, path) in page_templates {
+        let layout = match layout {
+            Some(layout) => layout,
+            None => continue,
+        };
+        let wrapper = match flatten_jekyll_layout(&layout, &layouts, site) {
+            Some(wrapper) => wrapper,
+            None => {
+                site.warn(format!("{}: layout {} not found", path, layout));
+                continue;
+            }
+        };
+        if let Some((_, body)) = site
+            .templates
+            .iter_mut()
+            .find(|(name, _)| *name == template)
+        {
+            *body = wrapper.replacen("{{{content}}}", body, 1);
+        }
+    }
+
+    for page in site.pages.iter_mut() {
+        if let Some(Value::String(layout)) = page.meta.remove("layout") {
+            let template = jekyll_template_name(&layout, &layouts);
+            page.meta.insert("template".to_string(), json!(template));
+        }
+    }
+    check_templates(site);
+}
+
+/// Add a page, converting its body; `layout` is kept under `layout` until
+/// the templates are known
+fn add_page(
+    site: &mut StaticSite,
+    collection: &'static str,
+    name: &str,
+    url: Option<String>,
+    fields: PageFields,
+    path: &str,
+    body: &str,
+) {
+    let PageFields {
+        title,
+        layout,
+        slug,
+        draft,
+        mut meta,
+    } = fields;
+    let name = slugify(slug.as_deref().unwrap_or(name));
+    let document = convert_body(path, body, site);
+    if let Some(url) = url {
+        meta.entry("url".to_string()).or_insert_with(|| json!(url));
+    }
+    if let Some(layout) = layout {
+        meta.insert("layout".to_string(), json!(layout));
+    }
+    site.pages.push(ImportedPage {
+        collection,
+        title: title.or(document.title).unwrap_or_else(|| name.clone()),
+        name,
+        meta,
+        draft,
+        content: document.content,
+    });
+}
+
+/// Drop templates pages ask for that the theme doesn't have
+fn check_templates(site: &mut StaticSite) {
+    let mut missing = Vec::new();
+    for page in &site.pages {
+        if let Some(template) = page.meta.get("template").and_then(Value::as_str) {
+            if !site.has_template(template) {
+                missing.push((page.name.clone(), template.to_string()));
+            }
+        }
+    }
+    for (name, template) in missing {
+        site.warn(format!(
+            "{}: template {} not found; using the default",
+            name, template
+        ));
+        if let Some(page) = site.pages.iter_mut().find(|page| page.name == name) {
+            page.meta.remove("template");
+        }
+    }
+}
+
+fn import_hugo(files: &[(String, &SourceFile)], site: &mut StaticSite) {
+    let file = |path: &str| {
+        files
+            .iter()
+            .find(|(candidate, _)| candidate == path)
+            .map(|(_, file)| file.content.as_str())
+    };
+
+    // Layouts first, so content can be matched to its templates
+    for (path, source) in files {
+        let path = path.as_str();
+        if HUGO_CONFIGS.contains(&path) && !path.contains('/') {
+            let config = read_config(path, &source.content, site);
+            site.name = config.get("title").and_then(value_string);
+            site.url = config
+                .get("baseURL")
+                .or_else(|| config.get("baseurl"))
+                .and_then(value_string)
+                .map(|url| url.trim_end_matches('/').to_string());
+            continue;
+        }
+        let rest = match path.strip_prefix("layouts/") {
+            Some(rest) => rest,
+            None => continue,
+        };
+        if let Some(partial) = rest.strip_prefix("partials/") {
+            let converted = go_to_handlebars(&source.content);
+            for warning in converted.warnings {
+                site.warn(format!("{}: {}", path, warning));
+            }
+            site.partials
+                .push((partial_name(partial), converted.template));
+            continue;
+        }
+        if rest.starts_with("shortcodes/") {
+            site.warn("Shortcodes can't be converted and weren't imported".to_string());
+            continue;
+        }
+        if extension(rest) != "html" {
+            continue;
+        }
+
+        let (section, kind) = match rest.rsplit_once('/') {
+            Some((section, name)) => (section, file_stem(name)),
+            None => ("", file_stem(rest)),
+        };
+        let name = match (section, kind) {
+            (_, "baseof") => continue,
+            ("_default", "single") => "index".to_string(),
+            (section, "single") if POST_SECTIONS.contains(&section) => "post".to_string(),
+            (section, "single") => slugify(section),
+            ("", "index") => "home".to_string(),
+            _ => {
+                site.warn(format!(
+                    "{}: list and taxonomy layouts aren't imported; use a collection listing",
+                    path
+                ));
+                continue;
+            }
+        };
+
+        let base = file(&format!("layouts/{}/baseof.html", section))
+            .or_else(|| file("layouts/_default/baseof.html"));
+        let layout = match base {
+            Some(base) if source.content.contains("define") => {
+                apply_go_defines(base, &source.content)
+            }
+            _ => source.content.clone(),
+        };
+        site.add_template(path, name, go_to_handlebars(&layout));
+    }
+
+    let mut has_home_page = false;
+    for (path, source) in files {
+        let path = path.as_str();
+        if let Some(rest) = path.strip_prefix("static/") {
+            if extension(rest) == "css" {
+                let name = rest.trim_end_matches(".css").to_string();
+                site.styles.push((name, source.content.clone()));
+            } else {
+                site.add_asset(format!("/{}", rest), source);
+            }
+            continue;
+        }
+        if path.starts_with("assets/") {
+            site.warn("Hugo Pipes files in assets/ weren't imported".to_string());
+            continue;
+        }
+        let rest = match path.strip_prefix("content/") {
+            Some(rest) => rest,
+            None => continue,
+        };
+        if !matches!(extension(rest).as_str(), "md" | "markdown" | "html") {
+            // Page bundle resources are served beside their page
+            site.add_asset(format!("/{}", rest), source);
+            continue;
+        }
+
+        let (folder, file_name) = rest.rsplit_once('/').unwrap_or(("", rest));
+        let (front, body) = read_front_matter(path, &source.content, site)
+            .unwrap_or((Map::new(), source.content.as_str()));
+        let mut fields = page_fields(front, path, site);
+
+        if file_stem(file_name) == "_index" {
+            if folder.is_empty() {
+                has_home_page = true;
+                if site.has_template("home") {
+                    fields.meta.insert("template".to_string(), json!("home"));
+                }
+                add_page(
+                    site,
+                    "page",
+                    "index",
+                    Some("/".to_string()),
+                    fields,
+                    path,
+                    body,
+                );
+            } else {
+                site.warn(format!("{}: section list pages aren't imported", path));
+            }
+            continue;
+        }
+
+        // A bundle's page is its folder's index.md
+        let is_bundle = file_stem(file_name) == "index";
+        let (page_folder, name) = match (is_bundle, folder.rsplit_once('/')) {
+            (true, Some((parent, name))) => (parent, name),
+            (true, None) => ("", folder),
+            (false, _) => (folder, file_stem(file_name)),
+        };
+        let section = page_folder.split('/').next().unwrap_or("");
+        let collection = if POST_SECTIONS.contains(&section) {
+            "post"
+        } else {
+            "page"
+        };
+        let slug = slugify(fields.slug.as_deref().unwrap_or(name));
+        let url = match (collection, section) {
+            (_, "") | ("post", "posts") => None,
+            _ => Some(format!("/{}/{}/", page_folder, slug)),
+        };
+
+        let section_template = Some(slugify(section)).filter(|_| collection == "page");
+        let template = fields.layout.take().or(section_template);
+        if let Some(template) = template.filter(|template| site.has_template(template)) {
+            fields.meta.insert("template".to_string(), json!(template));
+        }
+
+        add_page(site, collection, name, url, fields, path, body);
+        if let Some(page) = site.pages.last_mut() {
+            let base_url = if is_bundle {
+                format!("/{}/", folder)
+            } else if folder.is_empty() {
+                "/".to_string()
+            } else {
+                format!("/{}/", folder)
+            };
+            resolve_relative_images(&mut page.content, &base_url);
+        }
+    }
+
+    // The home layout needs a page to render it
+    if !has_home_page && site.has_template("home") {
+        let mut fields = PageFields::default();
+        fields.meta.insert("template".to_string(), json!("home"));
+        let title = site.name.clone().unwrap_or_else(|| "Home".to_string());
+        fields.title = Some(title);
+        add_page(
+            site,
+            "page",
+            "index",
+            Some("/".to_string()),
+            fields,
+            "content/_index.md",
+            "",
+        );
+    }
+    check_templates(site);
+}
+
+/// Sort a Jekyll or Hugo repository into pages, templates and assets
+pub fn import_static_site(files: &[SourceFile]) -> Result<StaticSite, String> {
+    let files = normalize_paths(files);
+    let generator =
+        detect_generator(files.iter().map(|(path, _)| path.as_str())).ok_or_else(|| {
+            "Not a Jekyll or Hugo site: no _config.yml, config.toml, _posts/ or content/"
+                .to_string()
+        })?;
+
+    let mut site = StaticSite::new(generator);
+    match generator {
+        Generator::Jekyll => import_jekyll(&files, &mut site),
+        Generator::Hugo => import_hugo(&files, &mut site),
+    }
+    Ok(site)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn text(path: &str, content: &str) -> SourceFile {
+        SourceFile {
+            path: path.to_string(),
+            content: content.to_string(),
+            data: None,
+        }
+    }
+
+    fn binary(path: &str, data: &[u8]) -> SourceFile {
+        SourceFile {
+            path: path.to_string(),
+            content: String::new(),
+            data: Some(data.to_vec()),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_import_jekyll() {
+        let files = vec![
+            text("blog/_config.yml", "title: Zine Fair\nbaseurl: /fair\n"),
+            text("blog/_layouts/default.html", "<main>{{ content }}</main>"),
+            text(
+                "blog/_layouts/post.html",
+                "---\nlayout: default\n---\n<h1>{{ page.title }}</h1>{{ content }}",
+            ),
+            text(
+                "blog/_posts/2024-03-01-stalls-open.md",
+                "---\ntitle: Stalls open\ntags: [zines, fair]\n---\n\
+                 ![Map]({{ site.baseurl }}/images/map.png)\n",
+            ),
+            text(
+                "blog/about.md",
+                "---\ntitle: About\nlayout: default\n---\nHello\n",
+            ),
+            text("blog/css/site.css", "main { margin: auto; }"),
+            binary("blog/images/map.png", &[0x89, b'P', b'N', b'G']),
+            text("blog/Gemfile", "gem \"jekyll\""),
+        ];
+
+        let site = import_static_site(&files).unwrap();
+        assert_eq!(site.generator, Generator::Jekyll);
+        assert_eq!(site.name.as_deref(), Some("Zine Fair"));
+        assert_eq!(site.base_path.as_deref(), Some("/fair"));
+        assert_eq!(
+            site.templates,
+            vec![
+                (
+                    "index".to_string(),
+                    "<main>{{{content}}}</main>".to_string()
+                ),
+                (
+                    "post".to_string(),
+                    "<main><h1>{{title}}</h1>{{{content}}}</main>".to_string()
+                ),
+            ]
+        );
+
+        let post = &site.pages[0];
+        assert_eq!(
+            (post.collection, post.name.as_str()),
+            ("post", "stalls-open")
+        );
+        assert_eq!(post.title, "Stalls open");
+        assert_eq!(post.meta["date"], "2024-03-01");
+        assert_eq!(post.meta["tags"], json!(["zines", "fair"]));
+        assert!(post.content.to_string().contains("\"/images/map.png\""));
+
+        let about = &site.pages[1];
+        assert_eq!((about.collection, about.name.as_str()), ("page", "about"));
+        assert_eq!(about.meta["template"], "index");
+
+        assert_eq!(
+            site.styles,
+            vec![("css/site".to_string(), "main { margin: auto; }".to_string())]
+        );
+        assert_eq!(site.assets.len(), 1);
+        assert_eq!(site.assets[0].url, "/images/map.png");
+        assert_eq!(site.assets[0].mime_type, "image/png");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_import_hugo() {
+        let files = vec![
+            text(
+                "config.toml",
+                "baseURL = \"https://example.com/\"\ntitle = \"Notes\"\n\n\
+                 [params]\nauthor = \"Sam\"\n",
+            ),
+            text(
+                "layouts/_default/baseof.html",
+                "<body>{{ block \"main\" . }}{{ end }}</body>",
+            ),
+            text(
+                "layouts/_default/single.html",
+                "{{ define \"main\" }}<h1>{{ .Title }}</h1>{{ .Content }}{{ end }}",
+            ),
+            text("layouts/_default/list.html", "{{ range .Pages }}{{ end }}"),
+            text(
+                "content/posts/first/index.md",
+                "+++\ntitle = \"First\"\ndate = 2024-01-02\ndraft = true\n+++\n\
+                 ![Cover](cover.jpg)\n",
+            ),
+            binary("content/posts/first/cover.jpg", &[0xff, 0xd8]),
+            text("content/about.md", "---\ntitle: About\n---\nHello\n"),
+        ];
+
+        let site = import_static_site(&files).unwrap();
+        assert_eq!(site.generator, Generator::Hugo);
+        assert_eq!(site.name.as_deref(), Some("Notes"));
+        assert_eq!(site.url.as_deref(), Some("https://example.com"));
+        assert_eq!(
+            site.templates,
+            vec![(
+                "index".to_string(),
+                "<body><h1>{{title}}</h1>{{{content}}}</body>".to_string()
+            )]
+        );
+        assert_eq!(site.warnings.len(), 1);
+
+        let about = &site.pages[0];
+        assert_eq!((about.collection, about.name.as_str()), ("page", "about"));
+
+        let post = &site.pages[1];
+        assert_eq!((post.collection, post.name.as_str()), ("post", "first"));
+        assert!(post.draft);
+        assert_eq!(post.meta["date"], "2024-01-02");
+        assert!(post
+            .content
+            .to_string()
+            .contains("\"/posts/first/cover.jpg\""));
+        assert_eq!(site.assets[0].url, "/posts/first/cover.jpg");
+    }
+}
@@ -0,0 +1,440 @@
+//! Cleaning uploaded SVG images.
+//!
+//! An SVG is a document, not just a picture: it can carry scripts, event
+//! handlers and `javascript:` links that run wherever it's shown inline.
+//! This reads the markup with a small tokenizer, drops anything that could
+//! run code and writes the rest back as it was. SVGs that can't be read
+//! reliably are rejected rather than guessed at.
+//!
+//! Optionally it also drops what only drawing programs use (comments,
+//! metadata, Inkscape and Sodipodi attributes, whitespace between tags).
+
+use super::html::decode_entities;
+use super::is_safe_url;
+
+pub const SVG_MIME_TYPE: &str = "image/svg+xml";
+
+/// Elements removed along with everything inside them
+const BANNED: [&str; 6] = [
+    "script",
+    "foreignobject",
+    "iframe",
+    "embed",
+    "object",
+    "handler",
+];
+
+/// Elements that can set another element's attributes as they animate
+const ANIMATIONS: [&str; 4] = ["set", "animate", "animatetransform", "animatemotion"];
+
+/// Namespaces only drawing programs read
+const EDITOR_PREFIXES: [&str; 3] = ["sodipodi", "inkscape", "sketch"];
+
+/// Elements whose whitespace is part of what's shown
+const WHITESPACE_KEPT: [&str; 6] = ["text", "tspan", "textpath", "style", "title", "desc"];
+
+/// An SVG with anything that could run code taken out
+#[derive(Debug, Clone, PartialEq)]
+pub struct SanitizedSvg {
+    pub svg: String,
+    /// What was taken out, e.g. "<script> element"
+    pub removed: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Attr<'a> {
+    name: &'a str,
+    /// The attribute as written, `name="value"`
+    raw: &'a str,
+    value: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Tag<'a> {
+    name: &'a str,
+    attrs: Vec<Attr<'a>>,
+    self_closing: bool,
+}
+
+fn local_name(name: &str) -> String {
+    name.rsplit(':').next().unwrap_or(name).to_ascii_lowercase()
+}
+
+fn is_editor_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let prefix = name.strip_prefix("xmlns:").unwrap_or(&name);
+    EDITOR_PREFIXES
+        .iter()
+        .any(|editor| prefix == *editor || prefix.starts_with(&format!("{}:", editor)))
+}
+
+/// Whether a link in an SVG can be followed without running code
+///
+/// Fragments, safe URLs and embedded raster images are.
+fn is_safe_svg_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters in schemes
+    let compact: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    let lower = compact.to_ascii_lowercase();
+    lower.starts_with('#')
+        || is_safe_url(&compact)
+        || ["png", "jpeg", "gif", "webp"]
+            .iter()
+            .any(|format| lower.starts_with(&format!("data:image/{}", format)))
+}
+
+/// Whether CSS could run script or pull in more CSS
+fn is_unsafe_css(css: &str) -> bool {
+    let compact: String = decode_entities(css)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    [
+        "javascript:",
+        "expression(",
+        "@import",
+        "-moz-binding",
+        "behavior:",
+    ]
+    .iter()
+    .any(|pattern| compact.contains(pattern))
+}
+
+/// Read a start tag from just after its `<`; also returns its length
+fn parse_tag(source: &str) -> Result<(Tag<'_>, usize), String> {
+    let name_end = source
+        .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .ok_or("A tag is never closed")?;
+    let is_name = |c: char| c.is_alphanumeric() || matches!(c, ':' | '-' | '_' | '.');
+    let name = &source[..name_end];
+    if name.is_empty() || !name.chars().all(is_name) {
+        return Err(format!("<{}> isn't a tag name", name));
+    }
+
+    let mut attrs = Vec::new();
+    let mut pos = name_end;
+    loop {
+        let rest = &source[pos..];
+        let trimmed = rest.trim_start();
+        pos += rest.len() - trimmed.len();
+        if trimmed.starts_with("/>") {
+            return Ok((
+                Tag {
+                    name,
+                    attrs,
+                    self_closing: true,
+                },
+                pos + 2,
+            ));
+        }
+        if trimmed.starts_with('>') {
+            return Ok((
+                Tag {
+                    name,
+                    attrs,
+                    self_closing: false,
+                },
+                pos + 1,
+            ));
+        }
+        if trimmed.is_empty() {
+            return Err(format!("<{}> is never closed", name));
+        }
+
+        let start = pos;
+        let attr_end = trimmed
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '/' || c == '>')
+            .ok_or_else(|| format!("<{}> is never closed", name))?;
+        let attr_name = &trimmed[..attr_end];
+        if attr_name.is_empty() || !attr_name.chars().all(is_name) {
+            return Err(format!("Stray characters in <{}>", name));
+        }
+        pos += attr_end;
+
+        let rest = &source[pos..];
+        let after_space = rest.trim_start();
+        let mut value = String::new();
+        if let Some(after_equals) = after_space.strip_prefix('=') {
+            let value_source = after_equals.trim_start();
+            pos += rest.len() - value_source.len();
+            let (raw_value, len) = match value_source.chars().next() {
+                Some(quote @ '"') | Some(quote @ '\'') => {
+                    let end = value_source[1..]
+                        .find(quote)
+                        .ok_or_else(|| format!("An attribute of <{}> is never closed", name))?;
+                    (&value_source[1..end + 1], end + 2)
+                }
+                _ => {
+                    let end = value_source
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(value_source.len());
+                    (&value_source[..end], end)
+                }
+            };
+            value = decode_entities(raw_value);
+            pos += len;
+        }
+        attrs.push(Attr {
+            name: attr_name,
+            raw: &source[start..pos],
+            value,
+        });
+    }
+}
+
+/// Why an attribute can't stay, if it can't
+fn attr_problem(element: &str, attr: &Attr) -> Option<String> {
+    let name = attr.name.to_ascii_lowercase();
+    if local_name(&name).starts_with("on") {
+        return Some(format!("{} attribute on <{}>", attr.name, element));
+    }
+    if local_name(&name) == "href" && !is_safe_svg_url(&attr.value) {
+        return Some(format!("unsafe link on <{}>", element));
+    }
+    if name == "style" && is_unsafe_css(&attr.value) {
+        return Some(format!("unsafe style on <{}>", element));
+    }
+    None
+}
+
+/// Take anything that could run code out of an SVG
+///
+/// With `optimize`, also drop what only drawing programs use.
+pub fn sanitize(source: &str, optimize: bool) -> Result<SanitizedSvg, String> {
+    let source = source.trim_start_matches('\u{feff}');
+    let mut svg = String::with_capacity(source.len());
+    let mut removed: Vec<String> = Vec::new();
+    // Open elements, and how many were open when a dropped one started
+    let mut stack: Vec<&str> = Vec::new();
+    let mut skip_from: Option<usize> = None;
+    let mut seen_root = false;
+
+    let mut rest = source;
+    while !rest.is_empty() {
+        let skipping = skip_from.is_some();
+        if let Some(after) = rest.strip_prefix("<!--") {
+            let end = after.find("-->").ok_or("A comment is never closed")?;
+            if !optimize && !skipping {
+                svg.push_str(&rest[..4 + end + 3]);
+            }
+            rest = &after[end + 3..];
+        } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+            let end = after.find("]]>").ok_or("A CDATA section is never closed")?;
+            let in_style = stack
+                .last()
+                .map_or(false, |open| local_name(open) == "style");
+            if in_style && is_unsafe_css(&after[..end]) {
+                removed.push("unsafe CSS in <style>".to_string());
+            } else if !skipping {
+                svg.push_str(&rest[..9 + end + 3]);
+            }
+            rest = &after[end + 3..];
+        } else if rest.starts_with("<!") {
+            let end = rest.find('>').ok_or("A declaration is never closed")?;
+            if rest[..end].contains('[') {
+                return Err("The SVG declares its own entities, which can't be checked".to_string());
+            }
+            if !optimize {
+                svg.push_str(&rest[..=end]);
+            }
+            rest = &rest[end + 1..];
+        } else if rest.starts_with("<?") {
+            let end = rest
+                .find("?>")
+                .ok_or("A processing instruction is never closed")?;
+            let instruction = &rest[..end + 2];
+            // Only the XML declaration is kept; stylesheet links could load anything
+            if instruction.starts_with("<?xml ") {
+                if !optimize {
+                    svg.push_str(instruction);
+                }
+            } else {
+                removed.push(format!("{} instruction", instruction));
+            }
+            rest = &rest[end + 2..];
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').ok_or("A closing tag is never closed")?;
+            let name = after[..end].trim();
+            match stack.pop() {
+                Some(open) if open == name => {}
+                _ => return Err(format!("</{}> doesn't match an open element", name)),
+            }
+            if skip_from == Some(stack.len()) {
+                skip_from = None;
+            } else if !skipping {
+                svg.push_str("</");
+                svg.push_str(name);
+                svg.push('>');
+            }
+            rest = &after[end + 1..];
+        } else if let Some(after) = rest.strip_prefix('<') {
+            let (tag, len) = parse_tag(after)?;
+            rest = &after[len..];
+            let name = local_name(tag.name);
+            if !seen_root {
+                if name != "svg" {
+                    return Err("Not an SVG image".to_string());
+                }
+                seen_root = true;
+            }
+            if !tag.self_closing {
+                stack.push(tag.name);
+            }
+            if skipping {
+                continue;
+            }
+
+            let changes_href = ANIMATIONS.contains(&name.as_str())
+                && tag.attrs.iter().any(|attr| {
+                    let target = local_name(attr.value.trim());
+                    attr.name.eq_ignore_ascii_case("attributeName")
+                        && (target == "href" || target.starts_with("on"))
+                });
+            let problem = if BANNED.contains(&name.as_str()) {
+                Some(format!("<{}> element", tag.name))
+            } else if changes_href {
+                Some(format!("<{}> changing a link or handler", tag.name))
+            } else {
+                None
+            };
+            let dropped = optimize && (name == "metadata" || is_editor_name(tag.name));
+            if problem.is_some() || dropped {
+                removed.extend(problem);
+                if !tag.self_closing {
+                    skip_from = Some(stack.len() - 1);
+                }
+                continue;
+            }
+
+            svg.push('<');
+            svg.push_str(tag.name);
+            for attr in &tag.attrs {
+                if let Some(problem) = attr_problem(tag.name, attr) {
+                    removed.push(problem);
+                    continue;
+                }
+                if optimize && is_editor_name(attr.name) {
+                    continue;
+                }
+                svg.push(' ');
+                svg.push_str(attr.raw);
+            }
+            svg.push_str(if tag.self_closing { "/>" } else { ">" });
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = &rest[..end];
+            rest = &rest[end..];
+            if skipping {
+                continue;
+            }
+            let in_style = stack
+                .last()
+                .map_or(false, |open| local_name(open) == "style");
+            let keeps_whitespace = stack
+                .iter()
+                .any(|open| WHITESPACE_KEPT.contains(&local_name(open).as_str()));
+            if in_style && is_unsafe_css(text) {
+                removed.push("unsafe CSS in <style>".to_string());
+            } else if !(optimize && !keeps_whitespace && text.trim().is_empty()) {
+                svg.push_str(text);
+            }
+        }
+    }
+
+    if let Some(open) = stack.last() {
+        return Err(format!("<{}> is never closed", open));
+    }
+    if !seen_root {
+        return Err("Not an SVG image".to_string());
+    }
+    let mut seen = Vec::new();
+    removed.retain(|problem| {
+        let first = !seen.contains(problem);
+        seen.push(problem.clone());
+        first
+    });
+    Ok(SanitizedSvg { svg, removed })
+}
+
+/// Sanitize an uploaded SVG file's bytes
+pub fn sanitize_bytes(data: &[u8], optimize: bool) -> Result<SanitizedSvg, String> {
+    let source = std::str::from_utf8(data).map_err(|_| "The SVG isn't UTF-8 text".to_string())?;
+    sanitize(source, optimize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_sanitize() {
+        let clean = sanitize(
+            "<?xml version=\"1.0\"?>\n\
+             <svg xmlns=\"http://www.w3.org/2000/svg\" onload=\"alert(1)\">\
+             <script>alert(2)</script>\
+             <a href=\" jav&#x61;script:alert(3)\"><rect width=\"10\" style=\"fill: red\"/></a>\
+             <use href=\"#dot\" ONCLICK='steal()'/>\
+             <set attributeName=\"href\" to=\"javascript:alert(4)\"/>\
+             <foreignObject><iframe src=\"x\"></iframe></foreignObject>\
+             <text>a &lt; b</text></svg>",
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            clean.svg,
+            "<?xml version=\"1.0\"?>\n\
+             <svg xmlns=\"http://www.w3.org/2000/svg\">\
+             <a><rect width=\"10\" style=\"fill: red\"/></a>\
+             <use href=\"#dot\"/>\
+             <text>a &lt; b</text></svg>"
+        );
+        assert_eq!(
+            clean.removed,
+            vec![
+                "onload attribute on <svg>",
+                "<script> element",
+                "unsafe link on <a>",
+                "ONCLICK attribute on <use>",
+                "<set> changing a link or handler",
+                "<foreignObject> element",
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_sanitize_optimize() {
+        let clean = sanitize(
+            "<!-- Generator: Inkscape -->\n\
+             <svg xmlns:inkscape=\"http://www.inkscape.org\" inkscape:version=\"1.2\" \
+             viewBox=\"0 0 4 4\">\n  <metadata><rdf:RDF/></metadata>\n  \
+             <sodipodi:namedview id=\"view\"/>\n  <circle r=\"2\"/>\n  \
+             <text> two  words </text>\n</svg>\n",
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(
+            clean.svg,
+            "<svg viewBox=\"0 0 4 4\"><circle r=\"2\"/><text> two  words </text></svg>"
+        );
+        assert!(clean.removed.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_sanitize_rejects() {
+        assert!(sanitize("<html><script>alert(1)</script></html>", false).is_err());
+        assert!(sanitize("<svg><g></svg>", false).is_err());
+        assert!(sanitize("<svg><rect width=\"1></svg>", false).is_err());
+        assert!(sanitize(
+            "<!DOCTYPE svg [<!ENTITY lol \"lol\">]><svg>&lol;</svg>",
+            false
+        )
+        .is_err());
+        assert!(sanitize_bytes(&[0xff, 0xfe, 0x3c], false).is_err());
+    }
+}
@@ -1,7 +1,9 @@
 pub mod js_conversions {
     
-    use crate::model::{Collection, File};
-    use crate::types::{FieldType, ProjectType};
+    use std::collections::HashMap;
+
+    use crate::model::{Collection, File, Folder};
+    use crate::types::{FieldType, ListFilesOptions, ProjectType};
     use serde_json::{json, Value};
     
     use wasm_bindgen::prelude::*;
@@ -60,7 +62,9 @@ pub mod js_conversions {
 
         Ok(json!({
             "name": collection.name(),
-            "fields": fields
+            "fields": fields,
+            "listing": collection.listing().unwrap_or(None),
+            "schema": collection.pm_schema().unwrap_or(None)
         }))
     }
 
@@ -80,6 +84,109 @@ pub mod js_conversions {
         Ok(json!(result))
     }
 
+    /// The page of `files` that `ListFiles` options ask for, and the cursor
+    /// for the next page if there is one
+    ///
+    /// Paging comes before any file is converted to JSON, so a page of a
+    /// large collection only costs its own files. The cursor is the id of the
+    /// last file on the previous page.
+    pub fn page_files<FileType: File>(
+        files: Vec<FileType>,
+        options: &ListFilesOptions,
+    ) -> Result<(Vec<FileType>, Option<String>), String> {
+        let start = match &options.cursor {
+            Some(cursor) => {
+                files
+                    .iter()
+                    .position(|file| file.id().ok().as_deref() == Some(cursor.as_str()))
+                    .ok_or_else(|| format!("Unknown cursor: {}", cursor))?
+                    + 1
+            }
+            None => 0,
+        };
+
+        let mut page: Vec<FileType> = files.into_iter().skip(start).collect();
+        let next_cursor = match options.limit {
+            Some(limit) if page.len() > limit => {
+                page.truncate(limit);
+                page.last().and_then(|file| file.id().ok())
+            }
+            _ => None,
+        };
+        Ok((page, next_cursor))
+    }
+
+    /// A file's JSON with only the keys `ListFiles` options ask for
+    ///
+    /// `body_fields` are dropped unless the options ask for bodies.
+    pub fn select_file_fields(
+        file: Value,
+        options: &ListFilesOptions,
+        body_fields: &[String],
+    ) -> Value {
+        match file {
+            Value::Object(mut fields) => {
+                if !options.include_body {
+                    fields.retain(|key, _| !body_fields.contains(key));
+                }
+                if let Some(keep) = &options.fields {
+                    fields.retain(|key, _| key == "id" || keep.contains(key));
+                }
+                Value::Object(fields)
+            }
+            other => other,
+        }
+    }
+
+    /// Files nested under their folders, as `{folders, files}` where each
+    /// folder is `{id, name, folders, files}`
+    ///
+    /// `file_folders` maps the id of each file that's in a folder to the folder's id.
+    pub fn nest_files_json(
+        files: Vec<Value>,
+        folders: &[Folder],
+        file_folders: &HashMap<String, String>,
+    ) -> Value {
+        let folder_of = |file: &Value| {
+            file["id"]
+                .as_str()
+                .and_then(|id| file_folders.get(id))
+                .cloned()
+        };
+        let mut by_folder: HashMap<Option<String>, Vec<Value>> = HashMap::new();
+        for file in files {
+            by_folder.entry(folder_of(&file)).or_default().push(file);
+        }
+
+        /// The folders and files directly inside `parent`
+        fn nest(
+            parent: Option<&str>,
+            folders: &[Folder],
+            by_folder: &mut HashMap<Option<String>, Vec<Value>>,
+        ) -> (Vec<Value>, Vec<Value>) {
+            let children = folders
+                .iter()
+                .filter(|folder| folder.parent.as_deref() == parent)
+                .map(|folder| {
+                    let (children, files) = nest(Some(&folder.id), folders, by_folder);
+                    json!({
+                        "id": folder.id,
+                        "name": folder.name,
+                        "folders": children,
+                        "files": files,
+                    })
+                })
+                .collect();
+            let files = by_folder
+                .remove(&parent.map(str::to_string))
+                .unwrap_or_default();
+            (children, files)
+        }
+
+        let (folders, files) = nest(None, folders, &mut by_folder);
+        json!({ "folders": folders, "files": files })
+    }
+
     /// Convert a list of Collections to a JS-friendly JSON array
     pub fn collections_to_json<FileType: File>(
         collections: &[Collection<FileType>],
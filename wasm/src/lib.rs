@@ -7,43 +7,78 @@ use serde_json::Value;
 use wasm_bindgen::prelude::*;
 // use wasm_bindgen::JsValue;
 
+// The `wasi` feature builds only the rendering core (render and the shared
+// types) for server runtimes; everything that talks to JS or IndexedDB is
+// browser-only.
+#[cfg(not(feature = "wasi"))]
 mod events;
+#[cfg(all(feature = "fixtures", not(feature = "wasi")))]
+pub mod fixtures;
+#[cfg(not(feature = "wasi"))]
+mod import;
+#[cfg(not(feature = "wasi"))]
 mod js_conversions;
+#[cfg(not(feature = "wasi"))]
 mod messages;
+#[cfg(not(feature = "wasi"))]
 mod model;
+pub mod render;
+#[cfg(not(feature = "wasi"))]
 mod store;
 mod types;
+#[cfg(feature = "wasi")]
+pub mod wasi;
 
 // Re-export our public components
+#[cfg(not(feature = "wasi"))]
 pub use events::EventEmitter;
 
 // Re-export LoroDoc type
 pub use loro::LoroDoc;
+#[cfg(not(feature = "wasi"))]
 pub use model::*;
+#[cfg(not(feature = "wasi"))]
 pub use store::*;
 pub use types::*;
 
+#[cfg(not(feature = "wasi"))]
 use js_sys::{JsString, Promise};
+#[cfg(not(feature = "wasi"))]
 use wasm_bindgen_futures::JsFuture;
 
+#[cfg(not(feature = "wasi"))]
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_namespace = console)]
     fn log(s: &str); // log to JS console
 }
 
+#[cfg(not(feature = "wasi"))]
 macro_rules! console_log {
     ($($t:tt)*) => (log(&format!("[Lib (WASM)] {}", format!($($t)*))))
 }
 
 // Import the JavaScript functions
-#[wasm_bindgen(module = "/indexeddb.js")]
+// Under Node (`node` feature) the same calls go to an in-memory or
+// filesystem-backed store instead of IndexedDB
+#[cfg(not(feature = "wasi"))]
+#[cfg_attr(not(feature = "node"), wasm_bindgen(module = "/indexeddb.js"))]
+#[cfg_attr(feature = "node", wasm_bindgen(module = "/node-storage.js"))]
 extern "C" {
     fn saveToIndexedDB(db_name: &str, store_name: &str, key: &str, value: &JsValue) -> Promise;
     fn loadFromIndexedDB(db_name: &str, store_name: &str, key: &str) -> Promise;
+    fn deleteFromIndexedDB(db_name: &str, store_name: &str, key: &str) -> Promise;
+    fn savePendingSync(key: &str, value: &str) -> bool;
+    fn loadPendingSync(key: &str) -> Option<String>;
+    fn clearPendingSync(key: &str);
+    fn estimateStorage() -> Promise;
+    fn claimWriterLock(name: &str, on_acquired: &js_sys::Function) -> Promise;
+    fn openTabChannel(name: &str, on_message: &js_sys::Function) -> JsValue;
+    fn postTabMessage(channel: &JsValue, message: &str);
 }
 
 // // Asynchronous function to save data to IndexedDB
+#[cfg(not(feature = "wasi"))]
 #[wasm_bindgen]
 pub async fn save_data(
     store_name: &str,
@@ -57,6 +92,7 @@ pub async fn save_data(
 }
 
 // // Asynchronous function to load data from IndexedDB
+#[cfg(not(feature = "wasi"))]
 #[wasm_bindgen]
 pub async fn load_data(store_name: &str, key: &str) -> Result<JsString, JsValue> {
     let promise = loadFromIndexedDB(IDB_DB_NAME, store_name, key);
@@ -65,6 +101,75 @@ pub async fn load_data(store_name: &str, key: &str) -> Result<JsString, JsValue>
     Ok(result.into())
 }
 
+// Asynchronous function to delete data from IndexedDB; missing keys are fine
+#[cfg(not(feature = "wasi"))]
+pub async fn delete_data(store_name: &str, key: &str) -> Result<(), JsValue> {
+    let promise = deleteFromIndexedDB(IDB_DB_NAME, store_name, key);
+    JsFuture::from(promise).await?;
+    console_log!("Deleted data from IndexedDB: {}", key);
+    Ok(())
+}
+
+/// The origin's storage `(usage, quota)` in bytes, if the browser reports it
+#[cfg(not(feature = "wasi"))]
+pub async fn storage_estimate() -> Result<Option<(f64, f64)>, JsValue> {
+    let estimate = JsFuture::from(estimateStorage()).await?;
+    if estimate.is_undefined() || estimate.is_null() {
+        return Ok(None);
+    }
+    let field = |name: &str| {
+        js_sys::Reflect::get(&estimate, &JsValue::from_str(name))
+            .ok()
+            .and_then(|value| value.as_f64())
+    };
+    Ok(field("usage").zip(field("quota")))
+}
+
+/// Write a string where it can be read back after the tab closes, without
+/// waiting; false if the browser refused it
+#[cfg(not(feature = "wasi"))]
+pub(crate) fn save_pending_sync(key: &str, value: &str) -> bool {
+    savePendingSync(key, value)
+}
+
+#[cfg(not(feature = "wasi"))]
+pub(crate) fn load_pending_sync(key: &str) -> Option<String> {
+    loadPendingSync(key)
+}
+
+#[cfg(not(feature = "wasi"))]
+pub(crate) fn clear_pending_sync(key: &str) {
+    clearPendingSync(key)
+}
+
+/// Whether this tab holds the writer lock now; if not, `on_acquired` is
+/// called when it's handed over
+#[cfg(not(feature = "wasi"))]
+pub(crate) async fn claim_writer_lock(
+    name: &str,
+    on_acquired: &js_sys::Function,
+) -> Result<bool, JsValue> {
+    let held = JsFuture::from(claimWriterLock(name, on_acquired)).await?;
+    Ok(held.as_bool().unwrap_or(false))
+}
+
+/// A channel to other tabs, or None where the browser has none
+#[cfg(not(feature = "wasi"))]
+pub(crate) fn open_tab_channel(name: &str, on_message: &js_sys::Function) -> Option<JsValue> {
+    let channel = openTabChannel(name, on_message);
+    if channel.is_undefined() {
+        None
+    } else {
+        Some(channel)
+    }
+}
+
+#[cfg(not(feature = "wasi"))]
+pub(crate) fn post_tab_message(channel: &JsValue, message: &str) {
+    postTabMessage(channel, message)
+}
+
+#[cfg(not(feature = "wasi"))]
 #[wasm_bindgen(start)]
 pub fn start() {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
@@ -1,5 +1,11 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
+use crate::types::{
+    AssetEntry, AssetTransform, Collaborator, ContentReportOptions, ExportFilter, FieldDefinition,
+    FindReplaceScope, FormDefinition, ListFilesOptions, ListingConfig, PlainTextOptions,
+    RepairOptions, SiteSettings, SourceFile, Submission, ThemeManifest, UrlStrategy,
+};
 
 /// Messages that can be sent to the Actor system.
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -14,13 +20,47 @@ pub enum Message {
         name: String,
     },
     GetTheme,
+    /// The collections and fields the active theme needs from a site, and
+    /// the page templates it offers
+    GetThemeManifest,
+    /// Replace the active theme's manifest, checking the active site against it
+    SetThemeManifest {
+        manifest: ThemeManifest,
+    },
+    /// The active theme's templates as a chooser shows them (label,
+    /// description, thumbnail asset), only those `collection` may use if given
+    ListTemplates {
+        #[serde(default)]
+        collection: Option<String>,
+    },
+    /// What the active site is missing that the active theme's manifest asks for
+    CheckThemeRequirements,
+    /// Switch the active site to a stored theme, reporting what the site is
+    /// missing that the theme needs
+    SelectTheme {
+        theme_id: String,
+    },
+    /// Sites saved in this workspace or open this session, marking the
+    /// active one and those that share its theme
+    ListSites,
+    /// Make another site using the active theme the active site; the
+    /// current one stays open with its unsaved changes
+    SwitchSite {
+        site_id: String,
+    },
+    /// Freeze a project's content, refusing changes to it while still
+    /// allowing renders and exports, or lift the freeze
+    SetProjectReadOnly {
+        project_type: String,
+        enabled: bool,
+    },
 
     // Collection operations
-    // AddCollection {
-    //     project_type: String,
-    //     name: String,
-    //     fields: Vec<FieldDefinition>,
-    // },
+    AddCollection {
+        project_type: String,
+        name: String,
+        fields: Vec<FieldDefinition>,
+    },
     GetCollection {
         project_type: String,
         name: String,
@@ -28,12 +68,37 @@ pub enum Message {
     ListCollections {
         project_type: String,
     },
+    /// The project's collections and fields as a JSON Schema document
+    ExportSchema {
+        project_type: String,
+    },
+    /// Add the collections described by an exported schema to the project
+    ImportSchema {
+        project_type: String,
+        schema: serde_json::Value,
+    },
+    /// Publish a data collection as a listing page (None removes it)
+    SetCollectionListing {
+        project_type: String,
+        collection: String,
+        listing: Option<ListingConfig>,
+    },
+    /// Set the ProseMirror schema for a collection's documents, migrating
+    /// the ones it already has
+    SetCollectionSchema {
+        project_type: String,
+        collection: String,
+        schema: serde_json::Value,
+    },
 
     // File operations
     CreateFile {
         project_type: String,
         collection_name: String,
         name: String,
+        /// Id of a skeleton whose body a new page, post or snippet starts as
+        #[serde(default)]
+        skeleton: Option<String>,
     },
     UpdateFile {
         project_type: String,
@@ -46,9 +111,106 @@ pub enum Message {
         collection_name: String,
         file_id: String,
     },
+    /// A file's custom metadata, and the fields its collection defines
+    GetMeta {
+        project_type: String,
+        collection_name: String,
+        file_id: String,
+    },
     ListFiles {
         project_type: String,
         collection_name: String,
+        #[serde(default)]
+        options: ListFilesOptions,
+    },
+    /// Replace `query` across bodies and meta fields, or with `dry_run` just
+    /// report the matches per file
+    FindReplace {
+        query: String,
+        replacement: String,
+        #[serde(default)]
+        scope: FindReplaceScope,
+        /// Treat `query` as a regular expression; `$1` in `replacement` is a group
+        #[serde(default)]
+        regex: bool,
+        #[serde(default)]
+        case_sensitive: bool,
+        #[serde(default)]
+        dry_run: bool,
+    },
+    /// Copy a site snippet's content into a page, post or snippet, before
+    /// the top-level block at `position` (None appends)
+    InsertSnippet {
+        file_id: String,
+        snippet_id: String,
+        #[serde(default)]
+        position: Option<usize>,
+        /// Insert a `block_ref` that renders the snippet's current content
+        /// instead of a copy
+        #[serde(default)]
+        reference: bool,
+    },
+    /// What would break if the file were deleted: pages using a template,
+    /// templates including a partial, or content referencing an asset
+    CheckDelete {
+        project_type: String,
+        collection_name: String,
+        file_id: String,
+    },
+    /// Take a file out of its collection and delete its stored document (or
+    /// an asset's bytes), emitting `file:deleted`
+    DeleteFile {
+        project_type: String,
+        collection_name: String,
+        file_id: String,
+        /// Delete even if other files depend on it
+        #[serde(default)]
+        force: bool,
+    },
+    /// Assets nothing links to or embeds, with the bytes they take up
+    FindOrphanAssets,
+    /// Move assets to the trash if they're still unreferenced; trashed
+    /// assets are left out of the published site
+    TrashOrphanAssets {
+        asset_ids: Vec<String>,
+    },
+    /// Add a folder to a project's assets, inside `parent_id` if given
+    CreateAssetFolder {
+        project_type: String,
+        name: String,
+        #[serde(default)]
+        parent_id: Option<String>,
+    },
+    RenameAssetFolder {
+        project_type: String,
+        folder_id: String,
+        name: String,
+    },
+    /// Move an asset folder into another, or to the top level with None
+    MoveAssetFolder {
+        project_type: String,
+        folder_id: String,
+        #[serde(default)]
+        parent_id: Option<String>,
+    },
+    /// Move an asset into a folder, or to the top level with None
+    MoveAsset {
+        project_type: String,
+        asset_id: String,
+        #[serde(default)]
+        folder_id: Option<String>,
+    },
+    /// Crop, turn or shrink an image asset when it's exported, keeping the
+    /// original; a transform that changes nothing undoes the edits
+    TransformAsset {
+        project_type: String,
+        asset_id: String,
+        transform: AssetTransform,
+    },
+    /// Take scripts out of SVG assets uploaded before they were checked,
+    /// flagging those that can't be made safe so they aren't exported
+    SanitizeSvgAssets {
+        project_type: String,
     },
 
     // Storage operations
@@ -59,6 +221,18 @@ pub enum Message {
         site_id: Option<String>,
         theme_id: Option<String>,
     },
+    /// Rewrite the site's and theme's stored documents as snapshots, keeping
+    /// each one's previous export as a backup; `shallow` drops edit history
+    CompactProjects {
+        #[serde(default)]
+        shallow: bool,
+    },
+    /// Check that the active site's and theme's files, documents, collections
+    /// and theme link agree, fixing what `repair` allows
+    VerifyProject {
+        #[serde(default)]
+        repair: RepairOptions,
+    },
     ExportProject {
         project_type: String,
     },
@@ -70,7 +244,232 @@ pub enum Message {
         updated: f64,
     },
 
+    // Import operations
+    /// Create a post from a Word document
+    ImportDocx {
+        data: Vec<u8>,
+        name: String,
+    },
+    /// Create one entry per CSV row in a custom collection
+    /// `mapping` goes from column header to field name ("name" sets the file name)
+    ImportCsv {
+        collection: String,
+        mapping: HashMap<String, String>,
+        data: String,
+    },
+    /// Create a draft post from an external submission, pending review
+    ImportSubmission {
+        submission: Submission,
+    },
+    /// Create a draft post from the article on a web page, downloading its
+    /// images as assets
+    ImportFromUrl {
+        url: String,
+    },
+    /// Add many files to the site's assets, as dropped from a folder
+    /// Folders are created to match their paths and duplicates are skipped
+    ImportAssets {
+        entries: Vec<AssetEntry>,
+    },
+    /// Create posts and pages from a Ghost JSON export
+    /// `site_url` is the Ghost site's address, for copying its images
+    ImportGhost {
+        data: String,
+        #[serde(default)]
+        site_url: Option<String>,
+    },
+    /// Create posts from a Medium or Tumblr export zip, with redirects from
+    /// their old paths; `blog_url` is a Tumblr blog's address
+    ImportArchive {
+        data: Vec<u8>,
+        #[serde(default)]
+        blog_url: Option<String>,
+    },
+    /// Create a theme and site from a Jekyll or Hugo repository's files,
+    /// converting layouts to templates and Markdown to posts and pages
+    ImportStaticSite {
+        files: Vec<SourceFile>,
+    },
+    /// Add generated pages, posts and assets to the active site; the same
+    /// options always give the same content
+    #[cfg(feature = "fixtures")]
+    GenerateFixture {
+        #[serde(default)]
+        options: crate::fixtures::FixtureOptions,
+    },
+    /// Submitted posts, only those whose review state matches if given
+    ListSubmissions {
+        #[serde(default)]
+        review: Option<String>,
+    },
+    /// Fetch a theme or starter site bundle, verify it and make it active
+    /// `sha256` is the expected hash of the whole bundle, when known
+    InstallFromUrl {
+        url: String,
+        #[serde(default)]
+        sha256: Option<String>,
+    },
+
+    // Site settings
+    GetSiteSettings,
+    SetSiteSettings {
+        settings: SiteSettings,
+    },
+    /// Publish a collection's files at a URL pattern (`/blog/:year/:slug/`),
+    /// or back at the default with None; moved files get redirects
+    SetUrlPattern {
+        collection: String,
+        #[serde(default)]
+        pattern: Option<String>,
+    },
+
+    // Collaborators
+    /// Everyone credited on the active project, by name
+    ListCollaborators {
+        project_type: String,
+    },
+    /// Add someone to the project's credits (without an id), or update
+    /// them; returns the collaborator as stored
+    SetCollaborator {
+        project_type: String,
+        collaborator: Collaborator,
+    },
+    RemoveCollaborator {
+        project_type: String,
+        id: String,
+    },
+
+    // Form operations
+    /// Create or replace a form in the active site's form collection
+    SaveForm {
+        form: FormDefinition,
+    },
+
     // Rendering operations
+    /// Render the active site with the active theme to path → contents
+    /// `url_strategy` overrides the site setting for this export
+    RenderSite {
+        #[serde(default)]
+        url_strategy: Option<UrlStrategy>,
+        /// Only render some collections, tags, a section or chosen files
+        #[serde(default)]
+        filter: ExportFilter,
+    },
+    /// Render the site with its assets as a ZIP archive (`zip`, bytes) to
+    /// download and host anywhere
+    BuildSite {
+        #[serde(default)]
+        url_strategy: Option<UrlStrategy>,
+        #[serde(default)]
+        filter: ExportFilter,
+    },
+    /// Render the site and its assets as path → {body (base64), mime, hash}
+    GetPreviewManifest,
+    /// The whole site, or the page at `page`, as one HTML file with CSS and
+    /// assets up to `max_asset_bytes` inlined
+    ExportSingleFile {
+        #[serde(default)]
+        page: Option<String>,
+        #[serde(default)]
+        max_asset_bytes: Option<usize>,
+    },
+    /// Pages and posts as gemtext (path → contents) with an index, plus
+    /// warnings for anything gemtext can't represent
+    ExportGemtext,
+    /// Missing required fields, missing templates, duplicate URLs, empty
+    /// titles and past-dated drafts in what an export would publish, plus
+    /// what rendering finds (errors only under the strict profile); errors
+    /// block the export, warnings don't
+    ValidateExport {
+        #[serde(default)]
+        filter: ExportFilter,
+    },
+    /// Output paths added/changed/removed since the last recorded deploy
+    GetPublishDiff {
+        target: Option<String>,
+    },
+    /// Remember the current output hashes as deployed
+    RecordDeploy {
+        target: Option<String>,
+    },
+    /// Render a page or post now and again after every edit, as `preview:updated` events
+    WatchPreview {
+        file_id: String,
+    },
+    UnwatchPreview,
+    /// Plugin name → hooks and helpers registered from JS
+    ListPlugins,
+    /// Scheduled posts, drafts older than `draft_days` and pages not updated
+    /// in `stale_months`
+    GetContentReport {
+        #[serde(default)]
+        options: ContentReportOptions,
+    },
+    /// Image assets, and images in pages and posts, that have no alt text
+    AuditAltText,
+    /// Totals, storage used, posts per month, top tags and average post length
+    GetProjectStats,
+    /// Ops, containers and text held by each open document, plus the size
+    /// of wasm's linear memory
+    GetMemoryReport,
+    /// Which open documents have changes that aren't committed or aren't
+    /// saved to IndexedDB yet, for warning before the tab closes
+    GetDirtyState,
+    /// Storage used and available, and whether saves have switched to
+    /// snapshots because it's nearly full
+    GetStorageEstimate,
+    /// Whether this tab writes to storage or is read-only while another
+    /// tab does
+    GetTabRole,
+    /// The role set by the host and the capabilities it grants
+    GetCapabilities,
+    /// Changes committed since the feed was at `cursor` (0 for all it
+    /// holds), each as {version, project, file, kind}
+    GetChangesSince {
+        #[serde(default)]
+        cursor: u64,
+    },
+    /// The active project's id, name and document id → version (base64),
+    /// for an invite or to tell a peer what to send
+    GetShareState {
+        project_type: String,
+    },
+    /// Document id → Loro updates (base64) that a peer at `versions`, from
+    /// its `GetShareState`, doesn't have yet
+    GetShareUpdates {
+        project_type: String,
+        #[serde(default)]
+        versions: HashMap<String, String>,
+    },
+    /// Merge a peer's `GetShareUpdates` into the project and its stored
+    /// documents, making the project if this side doesn't have it yet
+    ApplyShareUpdates {
+        project_type: String,
+        project_id: String,
+        updates: HashMap<String, String>,
+    },
+    /// Pages and posts linking to `url`, or every target URL → its backlinks
+    GetBacklinks {
+        #[serde(default)]
+        url: Option<String>,
+    },
+    /// `[[wikilinks]]` that don't match any page or post, as {page, target}
+    GetUnresolvedLinks,
+    /// Nodes (pages, posts, entry pages) and edges (links, references, shared tags)
+    GetContentGraph,
+    /// Pages using each template, plus unused and missing templates and partials
+    GetTemplateUsage,
+    /// Custom properties (`--primary`) declared by the theme, with defaults,
+    /// site overrides and a kind (color, length, ...) for choosing a control
+    GetThemeVariables,
+    /// A template or partial as Handlebars tags (blocks, partials, variable
+    /// paths) with positions, for highlighting and completion
+    ParseTemplate {
+        file_id: String,
+    },
+    /// Stylesheet selectors matching no template or page, and template
+    /// classes no stylesheet defines
+    GetCssReport,
     // RenderFile {
     //     file_id: String,
     //     context: serde_json::Value,
@@ -83,6 +482,35 @@ pub enum Message {
     GetDocument {
         document_id: String,
     },
+    /// The text of a page, post or snippet, without formatting
+    GetPlainText {
+        file_id: String,
+        #[serde(default)]
+        options: PlainTextOptions,
+    },
+    /// The heading tree of a page, post or snippet
+    GetOutline {
+        file_id: String,
+    },
+    /// A token for a position in a page, post or snippet that survives edits
+    CreateAnchor {
+        file_id: String,
+        position: usize,
+    },
+    /// The current position of an anchor from `CreateAnchor`
+    ResolveAnchor {
+        file_id: String,
+        anchor: String,
+    },
+    /// Remember where the user is in a page, post or snippet, in this browser
+    SaveCursor {
+        file_id: String,
+        position: usize,
+    },
+    /// Where the user left off in a page, post or snippet
+    RestoreCursor {
+        file_id: String,
+    },
     // ApplySteps {
     //     document_id: String,
     //     steps: Vec<serde_json::Value>, // Serialized ProseMirror steps
@@ -101,6 +529,15 @@ pub enum FileUpdate {
     SetUrl(String),
     SetMimeType(String),
     SetAlt(String),
+    /// Extra HTML for the head of this page only (pages and posts)
+    SetHeadHtml(String),
+    /// CSS for this page only (pages and posts)
+    SetPageCss(String),
+    /// Set custom metadata keys for templates, removing those set to null;
+    /// values must fit the collection's field of the same name, if it has one.
+    /// An asset's caption, credit and license are set this way; the keys
+    /// describing its bytes can't be
+    SetMeta(serde_json::Map<String, serde_json::Value>),
 }
 
 /// Response from the Actor system
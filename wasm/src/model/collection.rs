@@ -1,11 +1,15 @@
-use crate::types::{FieldDefinition, FieldType};
-use crate::{ApplyMap, ID_KEY};
+use crate::types::{FieldDefinition, FieldType, ListingConfig};
+use crate::{ApplyMap, ProseMirrorSchema, ID_KEY, PM_SCHEMA_KEY};
 use loro::{
-    Container, ContainerTrait, LoroDoc, LoroMap, LoroTree, LoroValue, TreeID, ValueOrContainer,
+    Container, ContainerTrait, LoroDoc, LoroMap, LoroTree, LoroValue, TreeID, TreeParentId,
+    ValueOrContainer,
 };
 
+use serde::Serialize;
 use serde_json::Value;
+use uuid::Uuid;
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::marker::PhantomData;
 
@@ -24,7 +28,10 @@ use crate::model::file::{File, FileBuilder};
 pub const COLLECTIONS_KEY: &str = "collections";
 pub const FIELDS_KEY: &str = "fields";
 pub const FILES_KEY: &str = "files";
+pub const LISTING_KEY: &str = "listing";
 pub const TYPE_KEY: &str = "type";
+/// Type of the file tree nodes that hold other files rather than being one
+pub const FOLDER_TYPE: &str = "folder";
 
 #[wasm_bindgen]
 extern "C" {
@@ -37,6 +44,33 @@ macro_rules! console_log {
   ($($t:tt)*) => (log(&format!("[Collection (WASM)] {}", format!($($t)*))))
 }
 
+/// A folder in a collection's file tree
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Folder {
+    pub id: String,
+    pub name: String,
+    /// The folder it's in, if it isn't at the top level
+    pub parent: Option<String>,
+}
+
+/// The ProseMirror schema stored on a collection's map, if it has one
+pub fn stored_pm_schema(map: &LoroMap) -> Result<Option<ProseMirrorSchema>, String> {
+    match map.get(PM_SCHEMA_KEY) {
+        Some(ValueOrContainer::Value(LoroValue::String(schema))) => {
+            ProseMirrorSchema::try_from(schema.to_string()).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// A string value from a file tree node's meta
+fn node_str(tree: &LoroTree, node: TreeID, key: &str) -> Option<String> {
+    match tree.get_meta(node).ok()?.get(key) {
+        Some(ValueOrContainer::Value(LoroValue::String(value))) => Some(value.to_string()),
+        _ => None,
+    }
+}
+
 /// Builder for creating a Collection with immutable fields
 #[derive(Debug, Clone)]
 pub struct CollectionBuilder<TFile: File> {
@@ -241,6 +275,45 @@ impl<TFile: File> Collection<TFile> {
         Ok(fields)
     }
 
+    /// Listing page configuration, stored on the collection as JSON
+    pub fn listing(&self) -> Result<Option<ListingConfig>, String> {
+        match self.map.get(LISTING_KEY) {
+            Some(ValueOrContainer::Value(LoroValue::String(listing))) => {
+                serde_json::from_str(&listing)
+                    .map(Some)
+                    .map_err(|e| format!("Invalid listing config: {}", e))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn set_listing(&self, listing: Option<&ListingConfig>) -> Result<(), String> {
+        match listing {
+            Some(listing) => {
+                let listing = serde_json::to_string(listing).map_err(|e| e.to_string())?;
+                self.map
+                    .insert(LISTING_KEY, listing)
+                    .map_err(|e| format!("Failed to set listing: {}", e))
+            }
+            None => self
+                .map
+                .delete(LISTING_KEY)
+                .map_err(|e| format!("Failed to remove listing: {}", e)),
+        }
+    }
+
+    /// ProseMirror schema for the collection's documents, stored as JSON;
+    /// None means new documents get the default schema
+    pub fn pm_schema(&self) -> Result<Option<ProseMirrorSchema>, String> {
+        stored_pm_schema(&self.map)
+    }
+
+    pub fn set_pm_schema(&self, schema: &ProseMirrorSchema) -> Result<(), String> {
+        self.map
+            .insert(PM_SCHEMA_KEY, schema.to_string())
+            .map_err(|e| format!("Failed to set schema: {}", e))
+    }
+
     pub fn get_field(&self, name: &str) -> Result<FieldDefinition, String> {
         let fields = self.fields_map()?;
 
@@ -421,6 +494,173 @@ impl<TFile: File> Collection<TFile> {
         Ok(target_file)
     }
 
+    /// Remove a file's node from the collection file tree
+    ///
+    /// Its stored document, if it has one, is left where it is.
+    pub fn remove_file(&self, file_id: &str) -> Result<(), String> {
+        if !self.map.is_attached() {
+            return Err("(remove_file) Map is not attached".to_string());
+        }
+
+        let files_tree = self.files_tree()?;
+        let node = files_tree.get_nodes(false).into_iter().find(|node| {
+            match files_tree.get_meta(node.id).map(|meta| meta.get("id")) {
+                Ok(Some(ValueOrContainer::Value(LoroValue::String(id)))) => id == file_id.into(),
+                _ => false,
+            }
+        });
+        match node {
+            Some(node) => files_tree
+                .delete(node.id)
+                .map_err(|e| format!("(remove_file) {}", e)),
+            None => Err(format!("(remove_file) File {} not found", file_id)),
+        }
+    }
+
+    /// The tree node of the file or folder with this id
+    fn find_node(&self, tree: &LoroTree, id: &str) -> Option<TreeID> {
+        tree.get_nodes(false)
+            .into_iter()
+            .find(|node| node_str(tree, node.id, "id").as_deref() == Some(id))
+            .map(|node| node.id)
+    }
+
+    fn folder_node(&self, tree: &LoroTree, folder_id: &str) -> Result<TreeID, String> {
+        match self.find_node(tree, folder_id) {
+            Some(node) if node_str(tree, node, TYPE_KEY).as_deref() == Some(FOLDER_TYPE) => {
+                Ok(node)
+            }
+            _ => Err(format!("Folder {} not found", folder_id)),
+        }
+    }
+
+    /// Ids of every file and folder in the collection
+    pub fn node_ids(&self) -> Result<Vec<String>, String> {
+        let tree = self.files_tree()?;
+        Ok(tree
+            .get_nodes(false)
+            .iter()
+            .filter_map(|node| node_str(&tree, node.id, "id"))
+            .collect())
+    }
+
+    /// Every folder in the collection
+    pub fn folders(&self) -> Result<Vec<Folder>, String> {
+        let tree = self.files_tree()?;
+        let nodes = tree.get_nodes(false);
+        let folders = nodes
+            .iter()
+            .filter(|node| node_str(&tree, node.id, TYPE_KEY).as_deref() == Some(FOLDER_TYPE))
+            .filter_map(|node| {
+                let parent = match node.parent {
+                    TreeParentId::Node(parent) => node_str(&tree, parent, "id"),
+                    _ => None,
+                };
+                Some(Folder {
+                    id: node_str(&tree, node.id, "id")?,
+                    name: node_str(&tree, node.id, crate::NAME_KEY).unwrap_or_default(),
+                    parent,
+                })
+            })
+            .collect();
+        Ok(folders)
+    }
+
+    /// File id → id of the folder it's in, for the files that are in one
+    pub fn file_folders(&self) -> Result<HashMap<String, String>, String> {
+        let tree = self.files_tree()?;
+        let mut folders = HashMap::new();
+        for node in tree.get_nodes(false) {
+            let parent = match node.parent {
+                TreeParentId::Node(parent) => parent,
+                _ => continue,
+            };
+            if node_str(&tree, node.id, TYPE_KEY).as_deref() == Some(FOLDER_TYPE) {
+                continue;
+            }
+            if let (Some(id), Some(folder)) =
+                (node_str(&tree, node.id, "id"), node_str(&tree, parent, "id"))
+            {
+                folders.insert(id, folder);
+            }
+        }
+        Ok(folders)
+    }
+
+    /// Add a folder, inside `parent_id` or at the top level
+    pub fn create_folder(&self, name: &str, parent_id: Option<&str>) -> Result<Folder, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Folder name cannot be empty".to_string());
+        }
+        let tree = self.files_tree()?;
+        let parent = match parent_id {
+            Some(parent_id) => Some(self.folder_node(&tree, parent_id)?),
+            None => None,
+        };
+
+        let node = tree
+            .create(parent)
+            .map_err(|e| format!("Failed to create folder: {}", e))?;
+        let meta = tree
+            .get_meta(node)
+            .map_err(|e| format!("Failed to create folder: {}", e))?;
+        let id = Uuid::new_v4().to_string();
+        for (key, value) in [
+            ("id", id.as_str()),
+            (crate::NAME_KEY, name),
+            (TYPE_KEY, FOLDER_TYPE),
+        ] {
+            meta.insert(key, value.to_string())
+                .map_err(|e| format!("Failed to create folder: {}", e))?;
+        }
+        Ok(Folder {
+            id,
+            name: name.to_string(),
+            parent: parent_id.map(str::to_string),
+        })
+    }
+
+    pub fn rename_folder(&self, folder_id: &str, name: &str) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Folder name cannot be empty".to_string());
+        }
+        let tree = self.files_tree()?;
+        let node = self.folder_node(&tree, folder_id)?;
+        tree.get_meta(node)
+            .and_then(|meta| meta.insert(crate::NAME_KEY, name.to_string()))
+            .map_err(|e| format!("Failed to rename folder: {}", e))
+    }
+
+    /// Move a file or folder into `folder_id`, or to the top level
+    pub fn move_to_folder(&self, id: &str, folder_id: Option<&str>) -> Result<(), String> {
+        let tree = self.files_tree()?;
+        let node = self
+            .find_node(&tree, id)
+            .ok_or_else(|| format!("{} not found", id))?;
+        let parent = match folder_id {
+            Some(folder_id) => {
+                let folder = self.folder_node(&tree, folder_id)?;
+                // A folder can't go inside itself or one of its own folders
+                let mut ancestor = Some(folder);
+                while let Some(current) = ancestor {
+                    if current == node {
+                        return Err("A folder can't be moved into itself".to_string());
+                    }
+                    ancestor = match tree.parent(current) {
+                        Some(TreeParentId::Node(parent)) => Some(parent),
+                        _ => None,
+                    };
+                }
+                Some(folder)
+            }
+            None => None,
+        };
+        tree.mov(node, parent)
+            .map_err(|e| format!("Failed to move {}: {}", id, e))
+    }
+
     pub async fn get_files(&self, collection_type: &str) -> Result<Vec<TFile>, String> {
         // Check if the file tree is attached
         if !self.map.is_attached() {
@@ -619,6 +859,30 @@ mod tests {
         assert!(!content_field.required);
     }
 
+    #[wasm_bindgen_test]
+    async fn test_listing_config() {
+        let collection = setup_test_collection().await;
+        assert_eq!(collection.listing().unwrap(), None);
+
+        let listing = ListingConfig {
+            path: "events".to_string(),
+            template: "events".to_string(),
+            detail_template: None,
+            sort_by: Some("date".to_string()),
+            descending: false,
+            group_by: Some("date".to_string()),
+            group_by_date: Some(crate::types::DateGrouping::Month),
+            upcoming_field: None,
+        };
+        collection
+            .set_listing(Some(&listing))
+            .expect("Failed to set listing");
+        assert_eq!(collection.listing().unwrap(), Some(listing));
+
+        collection.set_listing(None).expect("Failed to clear listing");
+        assert_eq!(collection.listing().unwrap(), None);
+    }
+
     #[wasm_bindgen_test]
     async fn test_create_file() {
         let collection = setup_test_collection().await;
@@ -868,6 +1132,39 @@ mod tests {
         assert_eq!(files[0].id(), file.id());
     }
 
+    #[wasm_bindgen_test]
+    async fn test_folders() {
+        let collection = setup_test_collection().await;
+        let builder = collection
+            .create_file("logo.png", "test", crate::FileStore::Cache(LoroMap::new()))
+            .expect("Failed to create file");
+        let file = collection
+            .attach_file(builder)
+            .await
+            .expect("Failed to attach file");
+        let file_id = file.id().unwrap();
+
+        let images = collection.create_folder("Images", None).unwrap();
+        let logos = collection.create_folder("Logos", Some(&images.id)).unwrap();
+        collection.move_to_folder(&file_id, Some(&logos.id)).unwrap();
+        collection.rename_folder(&logos.id, "Brand").unwrap();
+
+        let folders = collection.folders().unwrap();
+        assert_eq!(folders.len(), 2);
+        let brand = folders.iter().find(|f| f.id == logos.id).unwrap();
+        assert_eq!(brand.name, "Brand");
+        assert_eq!(brand.parent.as_deref(), Some(images.id.as_str()));
+        assert_eq!(collection.file_folders().unwrap().get(&file_id), Some(&logos.id));
+
+        // Folders aren't files, and can't go inside themselves
+        assert_eq!(collection.get_files("test").await.unwrap().len(), 1);
+        assert!(collection.move_to_folder(&images.id, Some(&logos.id)).is_err());
+        assert!(collection.create_folder("Stray", Some(&file_id)).is_err());
+
+        collection.move_to_folder(&file_id, None).unwrap();
+        assert!(collection.file_folders().unwrap().is_empty());
+    }
+
     #[wasm_bindgen_test]
     async fn test_collection_error_handling() {
         let collection = setup_test_collection().await;
@@ -1,8 +1,58 @@
-use crate::model::file::{Chainable, File, FileBuilder, FileStore, HasAlt, HasMimeType, HasUrl};
+use crate::model::file::{
+    Chainable, File, FileBuilder, FileStore, HasAlt, HasMimeType, HasUrl, MIME_TYPE_KEY, URL_KEY,
+};
+use crate::types::AssetTransform;
 use loro::LoroMap;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
+/// SHA-256 of the asset's bytes (hex), for spotting duplicates
+pub const SHA256_KEY: &str = "sha256";
+/// Size of the asset's bytes
+pub const SIZE_KEY: &str = "size";
+pub const WIDTH_KEY: &str = "width";
+pub const HEIGHT_KEY: &str = "height";
+/// Key of the thumbnail's bytes in the assets store
+pub const THUMBNAIL_KEY: &str = "thumbnail";
+/// Edits applied when the asset is exported, as JSON
+pub const TRANSFORM_KEY: &str = "transform";
+/// Set once an SVG's scripts have been taken out
+pub const SANITIZED_KEY: &str = "sanitized";
+/// Why an SVG couldn't be made safe; such assets aren't exported
+pub const UNSAFE_KEY: &str = "unsafe";
+pub const CAPTION_KEY: &str = "caption";
+/// Who made the asset, as it should be credited
+pub const CREDIT_KEY: &str = "credit";
+/// SPDX id (e.g. `CC-BY-4.0`), URL or wording of the asset's license
+pub const LICENSE_KEY: &str = "license";
+
+/// Keys describing an asset's stored bytes, set by the store when the bytes
+/// are stored or edited and never by hand
+pub const ASSET_BYTES_KEYS: [&str; 10] = [
+    MIME_TYPE_KEY,
+    URL_KEY,
+    SHA256_KEY,
+    SIZE_KEY,
+    WIDTH_KEY,
+    HEIGHT_KEY,
+    THUMBNAIL_KEY,
+    SANITIZED_KEY,
+    UNSAFE_KEY,
+    TRANSFORM_KEY,
+];
+
+/// Where an asset's thumbnail is stored, beside its bytes
+pub fn thumbnail_key(id: &str) -> String {
+    format!("{}/thumbnail", id)
+}
+
+/// An asset's bytes are stored once, under its id, and not replaced (SVGs
+/// stored before uploads were sanitized are cleaned once): `sha256`
+/// identifies them, and image edits are kept as a `transform` made to the
+/// exported copy. What describes them (alt text, caption, credit,
+/// license) lives on the files tree, where edits from different peers merge
+/// key by key.
+///
 /// Asset LoroDoc contains:
 /// - meta
 ///   - type
@@ -12,6 +62,10 @@ use serde_json::{Map, Value};
 ///   - mime_type
 ///   - url
 ///   - alt
+///   - caption, credit and license, if given
+///   - sha256, size, width, height and thumbnail, once known
+///   - transform, if the image has been edited
+///   - sanitized or unsafe, for SVGs
 #[derive(Debug, Clone, Default)]
 pub struct Asset {
     pub store: FileStore,
@@ -85,10 +139,54 @@ impl File for Asset {
         self.add_field_or_default(&mut result, "url", self.get_url())?;
         self.add_field_or_default(&mut result, "mime_type", self.get_mime_type())?;
         self.add_field_or_default(&mut result, "alt", self.get_alt())?;
+        for key in [
+            CAPTION_KEY,
+            CREDIT_KEY,
+            LICENSE_KEY,
+            SHA256_KEY,
+            SIZE_KEY,
+            WIDTH_KEY,
+            HEIGHT_KEY,
+            THUMBNAIL_KEY,
+            SANITIZED_KEY,
+            UNSAFE_KEY,
+        ] {
+            if let Ok(value) = self.get_field(key) {
+                result.insert(key.to_string(), value);
+            }
+        }
+        if let Some(transform) = self.transform()? {
+            result.insert(
+                TRANSFORM_KEY.to_string(),
+                serde_json::to_value(transform).map_err(|e| e.to_string())?,
+            );
+        }
         Ok(Value::Object(result))
     }
 }
 
+impl Asset {
+    /// The edits made to the image, if any
+    pub fn transform(&self) -> Result<Option<AssetTransform>, String> {
+        match self.get_field(TRANSFORM_KEY) {
+            Ok(Value::String(json)) if !json.is_empty() => serde_json::from_str(&json)
+                .map(Some)
+                .map_err(|e| format!("Invalid transform: {}", e)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Replace the image's edits; one that changes nothing clears them
+    pub async fn set_transform(&self, transform: &AssetTransform) -> Result<(), String> {
+        let json = if transform.is_identity() {
+            String::new()
+        } else {
+            serde_json::to_string(transform).map_err(|e| e.to_string())?
+        };
+        self.set_field(TRANSFORM_KEY, &json).await
+    }
+}
+
 impl HasMimeType for Asset {}
 
 impl HasUrl for Asset {}
@@ -192,6 +290,46 @@ mod tests {
         assert_eq!(alt, "Test image");
     }
 
+    #[wasm_bindgen_test]
+    async fn test_asset_transform() {
+        let asset = Asset::builder()
+            .with_meta(LoroMap::new())
+            .expect("Failed to set meta")
+            .build()
+            .await
+            .expect("Failed to build asset");
+        assert_eq!(asset.transform().unwrap(), None);
+
+        let transform = AssetTransform {
+            rotate: 90,
+            max_width: Some(800),
+            ..Default::default()
+        };
+        asset.set_transform(&transform).await.unwrap();
+        assert_eq!(asset.transform().unwrap(), Some(transform));
+        assert_eq!(asset.to_json().unwrap()["transform"]["rotate"], 90);
+
+        asset.set_transform(&AssetTransform::default()).await.unwrap();
+        assert_eq!(asset.transform().unwrap(), None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_asset_credit() {
+        let asset = Asset::builder()
+            .with_meta(LoroMap::new())
+            .expect("Failed to set meta")
+            .build()
+            .await
+            .expect("Failed to build asset");
+        assert!(asset.to_json().unwrap().get(CREDIT_KEY).is_none());
+
+        asset.set_field(CREDIT_KEY, "Union archive").await.unwrap();
+        asset.set_field(LICENSE_KEY, "CC-BY-4.0").await.unwrap();
+        let json = asset.to_json().unwrap();
+        assert_eq!(json[CREDIT_KEY], "Union archive");
+        assert_eq!(json[LICENSE_KEY], "CC-BY-4.0");
+    }
+
     #[wasm_bindgen_test]
     async fn test_asset_init() {
         let mut asset = Asset::builder()
@@ -4,8 +4,9 @@ pub mod document {
     use std::{collections::HashMap, convert::TryFrom};
 
     use loro::{
-        Container, LoroDoc, LoroError, LoroList, LoroMap, LoroText, LoroValue, TextDelta,
-        ValueOrContainer,
+        cursor::{Cursor, Side},
+        Container, ExpandType, LoroDoc, LoroError, LoroList, LoroMap, LoroText, LoroValue,
+        StyleConfig, TextDelta, ValueOrContainer,
     };
     use loro_delta::DeltaItem;
     use loro_internal::{event::TextMeta, FxHashMap, StringSlice};
@@ -14,6 +15,8 @@ pub mod document {
 
     use crate::{
         model::file::{File, ProseMirrorSchema},
+        render::text::pm_to_plain_text,
+        types::PlainTextOptions,
         FileStore, PM_SCHEMA_KEY,
     };
 
@@ -255,37 +258,7 @@ pub mod document {
         };
 
         // Get attributes map
-        let attrs = match root_doc.get(ATTRIBUTES_KEY) {
-            Some(ValueOrContainer::Container(Container::Map(attrs_map))) => {
-                // Convert attributes to JSON object
-                let mut attrs_obj = Map::new();
-                attrs_map.for_each(|key, value| match value {
-                    ValueOrContainer::Value(LoroValue::String(s)) => {
-                        attrs_obj.insert(key.to_string(), Value::String(s.to_string()));
-                    }
-                    ValueOrContainer::Value(LoroValue::Bool(b)) => {
-                        attrs_obj.insert(key.to_string(), Value::Bool(b));
-                    }
-                    ValueOrContainer::Value(LoroValue::Double(n)) => {
-                        if let Some(num) = serde_json::Number::from_f64(n) {
-                            attrs_obj.insert(key.to_string(), Value::Number(num));
-                        }
-                    }
-                    ValueOrContainer::Value(LoroValue::I64(n)) => {
-                        attrs_obj
-                            .insert(key.to_string(), Value::Number(serde_json::Number::from(n)));
-                    }
-                    _ => {}
-                });
-
-                if attrs_obj.is_empty() {
-                    Value::Null
-                } else {
-                    Value::Object(attrs_obj)
-                }
-            }
-            _ => Value::Null, // No attributes
-        };
+        let attrs = self::read_pm_attrs(&root_doc);
 
         // Get children list
         let children_list = match root_doc.get(CHILDREN_KEY) {
@@ -322,6 +295,56 @@ pub mod document {
         Ok(doc_json)
     }
 
+    /// The text of a Loro document, flattened as
+    /// [`pm_to_plain_text`](crate::render::text::pm_to_plain_text) does
+    pub fn loro_doc_to_plain_text(
+        loro_doc: &LoroDoc,
+        options: &PlainTextOptions,
+    ) -> Result<String, String> {
+        Ok(pm_to_plain_text(&loro_doc_to_pm_doc(loro_doc)?, options))
+    }
+
+    /// A Loro value as JSON; binary values and containers have no JSON form
+    fn loro_value_to_json(value: &LoroValue) -> Value {
+        match value {
+            LoroValue::Null => Value::Null,
+            LoroValue::Bool(b) => Value::Bool(*b),
+            LoroValue::Double(n) => serde_json::Number::from_f64(*n)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            LoroValue::I64(n) => Value::Number(serde_json::Number::from(*n)),
+            LoroValue::String(s) => Value::String(s.to_string()),
+            LoroValue::List(list) => {
+                Value::Array(list.iter().map(self::loro_value_to_json).collect())
+            }
+            LoroValue::Map(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| (key.to_string(), self::loro_value_to_json(value)))
+                    .collect(),
+            ),
+            _ => Value::Null,
+        }
+    }
+
+    /// A node's attributes as a JSON object, or null if it has none
+    fn read_pm_attrs(node_map: &LoroMap) -> Value {
+        let attrs_map = match node_map.get(ATTRIBUTES_KEY) {
+            Some(ValueOrContainer::Container(Container::Map(attrs_map))) => attrs_map,
+            _ => return Value::Null,
+        };
+        let mut attrs_obj = Map::new();
+        attrs_map.for_each(|key, value| {
+            if let ValueOrContainer::Value(value) = value {
+                attrs_obj.insert(key.to_string(), self::loro_value_to_json(&value));
+            }
+        });
+        if attrs_obj.is_empty() {
+            Value::Null
+        } else {
+            Value::Object(attrs_obj)
+        }
+    }
+
     /// Helper to convert a Loro map (non-text node) to a ProseMirror node
     pub fn convert_loro_map_to_pm_node(map: &LoroMap) -> Result<Value, String> {
         // Get node type (nodeName in the Loro-ProseMirror convention)
@@ -331,37 +354,7 @@ pub mod document {
         };
 
         // Get attributes as (pre-)JSON map
-        let attrs = match map.get(ATTRIBUTES_KEY) {
-            Some(ValueOrContainer::Container(Container::Map(attrs_map))) => {
-                // Convert attributes to JSON object
-                let mut attrs_obj = Map::new();
-                attrs_map.for_each(|key, value| match value {
-                    ValueOrContainer::Value(LoroValue::String(s)) => {
-                        attrs_obj.insert(key.to_string(), Value::String(s.to_string()));
-                    }
-                    ValueOrContainer::Value(LoroValue::Bool(b)) => {
-                        attrs_obj.insert(key.to_string(), Value::Bool(b));
-                    }
-                    ValueOrContainer::Value(LoroValue::Double(n)) => {
-                        if let Some(num) = serde_json::Number::from_f64(n) {
-                            attrs_obj.insert(key.to_string(), Value::Number(num));
-                        }
-                    }
-                    ValueOrContainer::Value(LoroValue::I64(n)) => {
-                        attrs_obj
-                            .insert(key.to_string(), Value::Number(serde_json::Number::from(n)));
-                    }
-                    _ => {}
-                });
-
-                if attrs_obj.is_empty() {
-                    Value::Null
-                } else {
-                    Value::Object(attrs_obj)
-                }
-            }
-            _ => Value::Null, // No attributes
-        };
+        let attrs = self::read_pm_attrs(map);
 
         // Get children list
         let children_list = match map.get(CHILDREN_KEY) {
@@ -411,11 +404,272 @@ pub mod document {
         }))
     }
 
-    /// Helper to find a text node and its position in the document based on prosemirror position
-    pub fn find_text_at_position(
+    /// Replace the rich-text body of a Loro doc with a ProseMirror JSON document
+    ///
+    /// This is the inverse of `loro_doc_to_pm_doc`: nodes become maps with
+    /// nodeName/attributes/children, and each run of adjacent text nodes
+    /// becomes one LoroText with its marks stored as text styles.
+    pub fn pm_json_to_loro(loro_doc: &LoroDoc, doc_json: &Value) -> Result<(), String> {
+        // Marks not named in the schema still need a style config to be applied
+        loro_doc.config_default_text_style(Some(StyleConfig {
+            expand: ExpandType::After,
+        }));
+
+        let root_map = loro_doc.get_map(ROOT_DOC_KEY);
+        let node_type = doc_json
+            .get("type")
+            .and_then(|t| t.as_str())
+            .unwrap_or("doc");
+        root_map
+            .insert(NODE_NAME_KEY, node_type.to_string())
+            .map_err(|e| e.to_string())?;
+
+        let attrs = root_map
+            .get_or_create_container(ATTRIBUTES_KEY, LoroMap::new())
+            .map_err(|e| e.to_string())?;
+        attrs.clear().map_err(|e| e.to_string())?;
+        self::write_pm_attrs(&attrs, doc_json.get("attrs"))?;
+
+        let children = root_map
+            .get_or_create_container(CHILDREN_KEY, LoroList::new())
+            .map_err(|e| e.to_string())?;
+        children.clear().map_err(|e| e.to_string())?;
+        self::write_pm_content(&children, doc_json.get("content"))?;
+
+        loro_doc.commit();
+        Ok(())
+    }
+
+    /// A new Loro doc holding a ProseMirror JSON document
+    ///
+    /// Reading it back with `loro_doc_to_pm_doc` gives the same document,
+    /// as compared by `normalize_pm_doc`.
+    pub fn pm_doc_to_loro_doc(doc_json: &Value) -> Result<LoroDoc, String> {
+        let loro_doc = LoroDoc::new();
+        self::pm_json_to_loro(&loro_doc, doc_json)?;
+        Ok(loro_doc)
+    }
+
+    /// A ProseMirror node in the form a round trip through Loro keeps
+    ///
+    /// Null and empty attrs, content and marks are left out, marks are
+    /// sorted by type, and adjacent text nodes with the same marks are
+    /// joined, as Loro stores them in one text.
+    pub fn normalize_pm_doc(node: &Value) -> Value {
+        let mut normalized = Map::new();
+        if let Some(node_type) = node.get("type") {
+            normalized.insert("type".to_string(), node_type.clone());
+        }
+        if let Some(Value::Object(attrs)) = node.get("attrs") {
+            let attrs: Map<String, Value> = attrs
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect();
+            if !attrs.is_empty() {
+                normalized.insert("attrs".to_string(), Value::Object(attrs));
+            }
+        }
+        if node.get("type").and_then(Value::as_str) == Some("text") {
+            normalized.insert("text".to_string(), node["text"].clone());
+            let marks = self::normalize_marks(node.get("marks"));
+            if !marks.is_empty() {
+                normalized.insert("marks".to_string(), Value::Array(marks));
+            }
+        }
+
+        let mut content: Vec<Value> = Vec::new();
+        if let Some(Value::Array(children)) = node.get("content") {
+            for child in children.iter().map(self::normalize_pm_doc) {
+                if child["type"] == "text" {
+                    if child["text"].as_str().map_or(true, str::is_empty) {
+                        continue;
+                    }
+                    if let Some(last) = content.last_mut() {
+                        if last["type"] == "text" && last.get("marks") == child.get("marks") {
+                            let joined = format!(
+                                "{}{}",
+                                last["text"].as_str().unwrap_or(""),
+                                child["text"].as_str().unwrap_or("")
+                            );
+                            last["text"] = Value::String(joined);
+                            continue;
+                        }
+                    }
+                }
+                content.push(child);
+            }
+        }
+        if !content.is_empty() {
+            normalized.insert("content".to_string(), Value::Array(content));
+        }
+        Value::Object(normalized)
+    }
+
+    fn normalize_marks(marks: Option<&Value>) -> Vec<Value> {
+        let mut marks: Vec<Value> = match marks {
+            Some(Value::Array(marks)) => marks
+                .iter()
+                .filter_map(|mark| {
+                    let mut normalized = Map::new();
+                    normalized.insert("type".to_string(), mark.get("type")?.clone());
+                    match mark.get("attrs") {
+                        Some(Value::Object(attrs)) if !attrs.is_empty() => {
+                            normalized.insert("attrs".to_string(), Value::Object(attrs.clone()));
+                        }
+                        _ => {}
+                    }
+                    Some(Value::Object(normalized))
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        marks.sort_by(|a, b| a["type"].as_str().cmp(&b["type"].as_str()));
+        marks
+    }
+
+    fn write_pm_attrs(attrs_map: &LoroMap, attrs: Option<&Value>) -> Result<(), String> {
+        if let Some(Value::Object(attrs)) = attrs {
+            for (key, value) in attrs {
+                // Null attrs are the schema default, so leave them unset
+                if value.is_null() {
+                    continue;
+                }
+                attrs_map
+                    .insert(key, LoroValue::from(value.clone()))
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_pm_node(node_map: &LoroMap, node: &Value) -> Result<(), String> {
+        let node_type = node
+            .get("type")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| "Node missing 'type'".to_string())?;
+        node_map
+            .insert(NODE_NAME_KEY, node_type.to_string())
+            .map_err(|e| e.to_string())?;
+
+        let attrs = node_map
+            .insert_container(ATTRIBUTES_KEY, LoroMap::new())
+            .map_err(|e| e.to_string())?;
+        self::write_pm_attrs(&attrs, node.get("attrs"))?;
+
+        let children = node_map
+            .insert_container(CHILDREN_KEY, LoroList::new())
+            .map_err(|e| e.to_string())?;
+        self::write_pm_content(&children, node.get("content"))
+    }
+
+    fn write_pm_content(children: &LoroList, content: Option<&Value>) -> Result<(), String> {
+        self::write_pm_content_at(children, children.len(), content).map(|_| ())
+    }
+
+    /// Write nodes into `children` from `index` on, returning the index after them
+    fn write_pm_content_at(
+        children: &LoroList,
+        mut index: usize,
+        content: Option<&Value>,
+    ) -> Result<usize, String> {
+        let nodes = match content.and_then(|c| c.as_array()) {
+            Some(nodes) => nodes,
+            None => return Ok(index),
+        };
+
+        let mut text_run: Vec<&Value> = Vec::new();
+        for node in nodes {
+            if node.get("type").and_then(|t| t.as_str()) == Some("text") {
+                text_run.push(node);
+                continue;
+            }
+
+            index = self::write_text_run(children, index, &mut text_run)?;
+            let node_map = children
+                .insert_container(index, LoroMap::new())
+                .map_err(|e| e.to_string())?;
+            self::write_pm_node(&node_map, node)?;
+            index += 1;
+        }
+
+        self::write_text_run(children, index, &mut text_run)
+    }
+
+    /// Insert ProseMirror nodes into a Loro doc's body before its top-level
+    /// node at `index`, or after the last one if None
+    ///
+    /// The nodes are new containers, so this merges with concurrent edits
+    /// like any other insertion. Returns the range of top-level indexes the
+    /// nodes now occupy.
+    pub fn insert_pm_nodes(
         loro_doc: &LoroDoc,
-        position: usize,
-    ) -> Result<(LoroText, usize, usize), String> {
+        index: Option<usize>,
+        nodes: &[Value],
+    ) -> Result<(usize, usize), String> {
+        let root_map = loro_doc.get_map(ROOT_DOC_KEY);
+        let children = match root_map.get(CHILDREN_KEY) {
+            Some(ValueOrContainer::Container(Container::List(list))) => list,
+            _ => return Err(format!("Document root missing '{}' list", CHILDREN_KEY)),
+        };
+        let from = index.map_or(children.len(), |index| index.min(children.len()));
+        let content = Value::Array(nodes.to_vec());
+        let to = self::write_pm_content_at(&children, from, Some(&content))?;
+        loro_doc.commit();
+        Ok((from, to))
+    }
+
+    fn write_text_run(
+        children: &LoroList,
+        index: usize,
+        text_run: &mut Vec<&Value>,
+    ) -> Result<usize, String> {
+        if text_run.is_empty() {
+            return Ok(index);
+        }
+
+        let mut delta = Vec::new();
+        for node in text_run.drain(..) {
+            let insert = node.get("text").and_then(|t| t.as_str()).unwrap_or("");
+            if insert.is_empty() {
+                continue;
+            }
+
+            let mut attributes: FxHashMap<String, LoroValue> = FxHashMap::default();
+            if let Some(marks) = node.get("marks").and_then(|m| m.as_array()) {
+                for mark in marks {
+                    if let Some(mark_type) = mark.get("type").and_then(|t| t.as_str()) {
+                        // Marks without attrs are stored as an empty object, as
+                        // a null style value would remove the mark instead
+                        let attrs = match mark.get("attrs") {
+                            Some(Value::Object(attrs)) => Value::Object(attrs.clone()),
+                            _ => json!({}),
+                        };
+                        attributes.insert(mark_type.to_string(), attrs.into());
+                    }
+                }
+            }
+
+            delta.push(TextDelta::Insert {
+                insert: insert.to_string(),
+                attributes: if attributes.is_empty() {
+                    None
+                } else {
+                    Some(attributes)
+                },
+            });
+        }
+
+        let text = children
+            .insert_container(index, LoroText::new())
+            .map_err(|e| e.to_string())?;
+        text.apply_delta(&delta).map_err(|e| e.to_string())?;
+        Ok(index + 1)
+    }
+
+    /// Every text node in the document, with the ProseMirror position it
+    /// starts at
+    fn text_nodes(loro_doc: &LoroDoc) -> Result<Vec<(LoroText, usize)>, String> {
         // Get the root map
         let root_map = loro_doc.get_map(ROOT_DOC_KEY);
 
@@ -427,6 +681,7 @@ pub mod document {
 
         // Keep track of the current position as we traverse the document
         let mut current_pos = 0;
+        let mut texts = Vec::new();
 
         // Iterate through the top-level nodes (paragraphs, etc.)
         for i in 0..children.len() {
@@ -446,16 +701,11 @@ pub mod document {
                     for j in 0..node_children.len() {
                         match node_children.get(j) {
                             Some(ValueOrContainer::Container(Container::Text(text))) => {
-                                // Calculate the start and end position of this text node
+                                // Calculate the start position of this text node
                                 let text_len = text.len_unicode();
                                 let text_start = current_pos;
-                                let text_end = text_start + text_len;
 
-                                // Check if the position is within this text node
-                                if position >= text_start && position <= text_end {
-                                    // Return the text node, its start position, and relative position
-                                    return Ok((text.clone(), text_start, position - text_start));
-                                }
+                                texts.push((text.clone(), text_start));
 
                                 // Move the current position forward
                                 current_pos += text_len;
@@ -476,11 +726,8 @@ pub mod document {
                     // Direct text node at the root level
                     let text_len = text.len_unicode();
                     let text_start = current_pos;
-                    let text_end = text_start + text_len;
 
-                    if position >= text_start && position <= text_end {
-                        return Ok((text.clone(), text_start, position - text_start));
-                    }
+                    texts.push((text.clone(), text_start));
 
                     current_pos += text_len;
                 }
@@ -492,9 +739,50 @@ pub mod document {
             }
         }
 
+        Ok(texts)
+    }
+
+    /// Helper to find a text node and its position in the document based on prosemirror position
+    pub fn find_text_at_position(
+        loro_doc: &LoroDoc,
+        position: usize,
+    ) -> Result<(LoroText, usize, usize), String> {
+        for (text, text_start) in self::text_nodes(loro_doc)? {
+            // Check if the position is within this text node
+            if position >= text_start && position <= text_start + text.len_unicode() {
+                // Return the text node, its start position, and relative position
+                return Ok((text, text_start, position - text_start));
+            }
+        }
+
         Err(format!("No text node found at position {}", position)) // or maybe insert a text node at this position?
     }
 
+    /// A stable cursor at a ProseMirror position, which keeps to the same
+    /// spot in the text through later edits
+    pub fn cursor_at_position(loro_doc: &LoroDoc, position: usize) -> Result<Cursor, String> {
+        let (text, _, offset) = self::find_text_at_position(loro_doc, position)?;
+        text.get_cursor(offset, Side::Middle)
+            .ok_or_else(|| format!("No cursor at position {}", position))
+    }
+
+    /// Where a cursor is now, as a ProseMirror position, along with an
+    /// up-to-date cursor to use in its place if Loro suggests one
+    pub fn cursor_position(
+        loro_doc: &LoroDoc,
+        cursor: &Cursor,
+    ) -> Result<(usize, Option<Cursor>), String> {
+        let found = loro_doc
+            .get_cursor_pos(cursor)
+            .map_err(|e| format!("Failed to resolve cursor: {:?}", e))?;
+        let text_start = self::text_nodes(loro_doc)?
+            .into_iter()
+            .find(|(text, _)| text.id() == cursor.container)
+            .map(|(_, text_start)| text_start)
+            .ok_or_else(|| "The text the cursor was in has been removed".to_string())?;
+        Ok((text_start + found.current.pos, found.update))
+    }
+
     /// Apply ProseMirror steps to a Loro document
     pub fn apply_steps_to_loro_doc(loro_doc: &LoroDoc, steps: &[Value]) -> Result<(), JsValue> {
         // console_log!("Applying steps to Loro document");
@@ -969,6 +1257,15 @@ pub mod document {
                 }
             }
 
+            // Refuse the whole batch if any step brings in nodes or marks
+            // the document's schema doesn't have
+            let schema = self.schema();
+            for step in steps {
+                schema
+                    .check_step(step)
+                    .map_err(|e| format!("Step doesn't fit the schema: {}", e))?;
+            }
+
             let doc = match self.store() {
                 FileStore::Full(doc) => doc,
                 FileStore::Cache(_) => return Err("Cannot get content from cache".to_string()),
@@ -1267,6 +1564,29 @@ mod tests {
         assert!(find_text_at_position(&doc, 100).is_err());
     }
 
+    #[wasm_bindgen_test]
+    fn test_cursor_position() {
+        use loro::cursor::Cursor;
+
+        let doc = LoroDoc::new();
+        initialize_richtext_document(&doc, &ProseMirrorSchema::default())
+            .expect("Failed to initialize");
+        let (text, _, _) = find_text_at_position(&doc, 0).unwrap();
+        text.insert(0, "Hello World").unwrap();
+        doc.commit();
+
+        // The cursor stays on the "W" as text is added before it
+        let cursor = cursor_at_position(&doc, 6).expect("Failed to create cursor");
+        text.insert(0, "Big ").unwrap();
+        doc.commit();
+        let (position, _) = cursor_position(&doc, &cursor).expect("Failed to resolve cursor");
+        assert_eq!(position, 10);
+
+        // Encoded cursors resolve the same way
+        let decoded = Cursor::decode(&cursor.encode()).unwrap();
+        assert_eq!(cursor_position(&doc, &decoded).unwrap().0, 10);
+    }
+
     #[wasm_bindgen_test]
     fn test_configure_text_style() {
         let doc = LoroDoc::new();
@@ -1289,4 +1609,165 @@ mod tests {
             Some(ValueOrContainer::Container(Container::Map(_)))
         ));
     }
+
+    #[wasm_bindgen_test]
+    fn test_pm_json_to_loro() {
+        let doc = LoroDoc::new();
+        initialize_richtext_document(&doc, &ProseMirrorSchema::default())
+            .expect("Failed to initialize");
+
+        let pm_doc = json!({
+            "type": "doc",
+            "content": [
+                { "type": "heading", "attrs": { "level": 1 }, "content": [
+                    { "type": "text", "text": "Title" }
+                ]},
+                { "type": "paragraph", "content": [
+                    { "type": "text", "text": "plain " },
+                    { "type": "text", "text": "bold", "marks": [{ "type": "strong" }] }
+                ]}
+            ]
+        });
+
+        pm_json_to_loro(&doc, &pm_doc).expect("Failed to write document");
+
+        let result = loro_doc_to_pm_doc(&doc).expect("Failed to read document");
+        let content = result["content"].as_array().unwrap();
+
+        // The empty paragraph from initialization is replaced
+        assert_eq!(content.len(), 2);
+        assert_eq!(content[0]["type"], "heading");
+        assert_eq!(content[0]["attrs"]["level"], 1);
+        assert_eq!(content[0]["content"][0]["text"], "Title");
+        assert_eq!(content[1]["content"][0]["text"], "plain ");
+        assert_eq!(content[1]["content"][1]["text"], "bold");
+        assert_eq!(content[1]["content"][1]["marks"][0]["type"], "strong");
+    }
+
+    /// Documents whose every node, mark and attr must survive Loro
+    fn round_trip_corpus() -> Vec<Value> {
+        let text = |text: &str| json!({ "type": "text", "text": text });
+        let marked =
+            |text: &str, marks: Value| json!({ "type": "text", "text": text, "marks": marks });
+        vec![
+            json!({ "type": "doc", "content": [] }),
+            json!({ "type": "doc", "content": [{ "type": "paragraph" }] }),
+            json!({ "type": "doc", "content": [
+                { "type": "heading", "attrs": { "level": 2, "id": "intro" }, "content": [text("Intro")] },
+                { "type": "paragraph", "content": [
+                    text("plain, "),
+                    marked("bold", json!([{ "type": "strong" }])),
+                    text(", "),
+                    marked("both", json!([{ "type": "em" }, { "type": "strong" }])),
+                    text(" and "),
+                    marked("a link", json!([{ "type": "link", "attrs": { "href": "/about", "title": "About" } }])),
+                ]}
+            ]}),
+            json!({ "type": "doc", "content": [
+                { "type": "blockquote", "content": [
+                    { "type": "bullet_list", "content": [
+                        { "type": "list_item", "content": [
+                            { "type": "paragraph", "content": [text("nested")] },
+                            { "type": "ordered_list", "attrs": { "order": 3 }, "content": [
+                                { "type": "list_item", "content": [
+                                    { "type": "paragraph", "content": [text("deeper")] }
+                                ]}
+                            ]}
+                        ]}
+                    ]}
+                ]},
+                { "type": "code_block", "attrs": { "language": "rust" }, "content": [text("fn main() {}\n")] },
+                { "type": "horizontal_rule" }
+            ]}),
+            json!({ "type": "doc", "content": [
+                { "type": "paragraph", "content": [
+                    text("before"),
+                    { "type": "hard_break" },
+                    { "type": "image", "attrs": {
+                        "src": "/assets/a1/march.jpg",
+                        "alt": "Marchers",
+                        "width": 640,
+                        "scale": 0.5,
+                        "caption": false,
+                        "crop": { "x": 10, "y": 20 },
+                        "tags": ["protest", "street"]
+                    }},
+                    text("after")
+                ]},
+                { "type": "block_ref", "attrs": { "id": "bio" } }
+            ]}),
+        ]
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pm_round_trip() {
+        for document in round_trip_corpus() {
+            let loro_doc = pm_doc_to_loro_doc(&document).expect("Failed to write document");
+            let read = loro_doc_to_pm_doc(&loro_doc).expect("Failed to read document");
+            assert_eq!(
+                normalize_pm_doc(&read),
+                normalize_pm_doc(&document),
+                "round trip changed {}",
+                document
+            );
+
+            // Writing what was read changes nothing further
+            let again = loro_doc_to_pm_doc(&pm_doc_to_loro_doc(&read).unwrap()).unwrap();
+            assert_eq!(normalize_pm_doc(&again), normalize_pm_doc(&read));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_normalize_pm_doc() {
+        let written = json!({ "type": "doc", "attrs": {}, "content": [
+            { "type": "paragraph", "attrs": { "align": null }, "content": [
+                { "type": "text", "text": "one " },
+                { "type": "text", "text": "two", "marks": [] },
+                { "type": "text", "text": "" },
+                { "type": "text", "text": "!", "marks": [{ "type": "strong" }, { "type": "em", "attrs": {} }] }
+            ]}
+        ]});
+        assert_eq!(
+            normalize_pm_doc(&written),
+            json!({ "type": "doc", "content": [
+                { "type": "paragraph", "content": [
+                    { "type": "text", "text": "one two" },
+                    { "type": "text", "text": "!", "marks": [{ "type": "em" }, { "type": "strong" }] }
+                ]}
+            ]})
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_insert_pm_nodes() {
+        let doc = LoroDoc::new();
+        let paragraph = |text: &str| {
+            json!({ "type": "paragraph", "content": [{ "type": "text", "text": text }] })
+        };
+        pm_json_to_loro(
+            &doc,
+            &json!({ "type": "doc", "content": [paragraph("first"), paragraph("last")] }),
+        )
+        .expect("Failed to write document");
+
+        let snippet = [
+            json!({ "type": "blockquote", "content": [paragraph("Callout")] }),
+            paragraph("Bio"),
+        ];
+        assert_eq!(insert_pm_nodes(&doc, Some(1), &snippet), Ok((1, 3)));
+        assert_eq!(insert_pm_nodes(&doc, Some(99), &snippet[1..]), Ok((4, 5)));
+
+        let result = loro_doc_to_pm_doc(&doc).expect("Failed to read document");
+        let texts: Vec<&str> = result["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|node| match node["type"].as_str() {
+                Some("blockquote") => node["content"][0]["content"][0]["text"].as_str(),
+                _ => node["content"][0]["text"].as_str(),
+            })
+            .map(Option::unwrap)
+            .collect();
+        assert_eq!(texts, vec!["first", "Callout", "Bio", "last", "Bio"]);
+    }
 }
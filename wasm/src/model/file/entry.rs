@@ -0,0 +1,197 @@
+use crate::model::file::{Chainable, File, FileBuilder, FileStore, TYPE_KEY, VERSION_KEY};
+use loro::{LoroMap, LoroValue};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Entries are the files of user-defined collections (events, members,
+/// publications). They have no document of their own: every field lives in
+/// the cached metadata on the collection files tree.
+///
+/// Entry meta contains:
+/// - type (the collection name)
+/// - id
+/// - name
+/// - version
+/// - one key per collection field
+#[derive(Debug, Clone, Default)]
+pub struct Entry {
+    pub store: FileStore,
+    collection_type: String,
+}
+
+impl File for Entry {
+    fn builder() -> FileBuilder<Self> {
+        FileBuilder::new("entry")
+    }
+
+    async fn init(&mut self, meta: Option<&LoroMap>) -> Result<(), String> {
+        let collection_type = self.collection_type.clone();
+        self.set_type(&collection_type).await?;
+
+        let id = self
+            .load_string_field_with_meta(meta, "id")
+            .unwrap_or_default();
+        let name = self
+            .load_string_field_with_meta(meta, "name")
+            .unwrap_or_default();
+        let version = self
+            .get_i64_field_with_meta(meta, "version")
+            .unwrap_or_default();
+
+        self.set_id(&id).await?;
+        self.set_name(&name).await?;
+        self.set_version(version).await?;
+        Ok(())
+    }
+
+    async fn build_from(builder: FileBuilder<Self>) -> Result<Self, String> {
+        let collection_type = builder.collection_type();
+
+        // Ensure we have a store
+        let store = builder.store.ok_or("No file store provided")?;
+
+        let mut entry = Entry {
+            store,
+            collection_type,
+        };
+        entry
+            .init(None)
+            .await
+            .map_err(|e| format!("Failed to initialize entry: {}", e))?;
+        Ok(entry)
+    }
+
+    fn store(&self) -> &FileStore {
+        &self.store
+    }
+
+    fn mut_store(&mut self) -> &mut FileStore {
+        &mut self.store
+    }
+
+    fn get_type(&self) -> String {
+        self.collection_type.clone()
+    }
+
+    fn to_json(&self) -> Result<Value, String> {
+        let mut result = Map::new();
+        self.add_field(&mut result, "id", &self.id()?.to_string())?;
+        self.add_field(&mut result, "collection_type", &self.get_type())?;
+        self.add_field_or_default(&mut result, "name", self.name())?;
+
+        // Include every collection field that has a value
+        for (key, value) in self.meta_fields() {
+            if result.contains_key(&key) || key == TYPE_KEY || key == VERSION_KEY {
+                continue;
+            }
+            result.insert(key, value);
+        }
+
+        Ok(Value::Object(result))
+    }
+}
+
+impl Serialize for Entry {
+    /// As `to_json` gives it
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.to_json()
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Entry {
+    /// From what `to_json` gives, as an entry cached outside any project
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let fields = Map::<String, Value>::deserialize(deserializer)?;
+        let meta = LoroMap::new();
+        let mut collection_type = String::new();
+        for (key, value) in fields {
+            let key = if key == "collection_type" {
+                collection_type = value.as_str().unwrap_or_default().to_string();
+                TYPE_KEY.to_string()
+            } else {
+                key
+            };
+            meta.insert(&key, LoroValue::from(value))
+                .map_err(serde::de::Error::custom)?;
+        }
+        Ok(Entry {
+            store: FileStore::Cache(meta),
+            collection_type,
+        })
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    async fn test_entry_builder() {
+        let entry = Entry::builder_for("events")
+            .with_meta(LoroMap::new())
+            .expect("Failed to set meta")
+            .build()
+            .await
+            .expect("Failed to build entry");
+
+        assert_eq!(entry.get_type(), "events");
+        assert_eq!(entry.version().unwrap(), 0);
+        assert!(!entry.id().unwrap().is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_entry_to_json_includes_fields() {
+        let entry = Entry::builder_for("events")
+            .with_meta(LoroMap::new())
+            .expect("Failed to set meta")
+            .build()
+            .await
+            .expect("Failed to build entry");
+
+        entry
+            .set_field_value("location", &json!("Town Hall"))
+            .await
+            .expect("Failed to set location");
+        entry
+            .set_field_value("capacity", &json!(120))
+            .await
+            .expect("Failed to set capacity");
+
+        let json = entry.to_json().expect("Failed to convert to JSON");
+        assert_eq!(json["collection_type"], "events");
+        assert_eq!(json["location"], "Town Hall");
+        assert_eq!(json["capacity"], 120);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_entry_serde_round_trip() {
+        let json = json!({
+            "id": "e1",
+            "collection_type": "events",
+            "name": "fair",
+            "location": "Town Hall",
+            "capacity": 120
+        });
+        let entry: Entry = serde_json::from_value(json.clone()).expect("Failed to deserialize");
+        assert_eq!(entry.get_type(), "events");
+        assert_eq!(entry.id().unwrap(), "e1");
+        assert_eq!(serde_json::to_value(&entry).unwrap(), json);
+    }
+}
@@ -1,5 +1,7 @@
 use js_sys::Uint8Array;
-use loro::{Container, LoroDoc, LoroMap, LoroValue, TreeID, ValueOrContainer};
+use loro::{
+    Container, LoroDoc, LoroList, LoroMap, LoroText, LoroValue, TreeID, ValueOrContainer,
+};
 use serde_json::{Map, Value};
 use std::{convert::TryFrom, marker::PhantomData};
 use uuid::Uuid;
@@ -92,6 +94,8 @@ pub const TYPE_KEY: &str = "type";
 pub const URL_KEY: &str = "url";
 pub const ALT_KEY: &str = "alt";
 pub const MIME_TYPE_KEY: &str = "mime_type";
+// Defined with the renderer, which reads them without the model
+pub use crate::render::head::{HEAD_HTML_KEY, PAGE_CSS_KEY};
 
 pub trait File {
     fn builder_for(collection_type: &str) -> FileBuilder<Self>
@@ -207,8 +211,7 @@ pub trait File {
         // We need both a store and an ID to save
         if let (Ok(id), FileStore::Full(doc)) = (self.id(), self.store()) {
             // Export the LoroDoc to bytes
-            let export_data = doc
-                .export(loro::ExportMode::all_updates())
+            let export_data = crate::model::project::export_document(doc)
                 .map_err(|e| format!("Failed to export LoroDoc: {}", e))?;
 
             // Convert to Uint8Array for JS
@@ -229,6 +232,55 @@ pub trait File {
         self.meta()
             .insert(field, value.to_string())
             .map_err(|e| e.to_string())?;
+
+        // Cached metadata lives in the project doc, which is saved with the project
+        if self.store().is_cache() {
+            return Ok(());
+        }
+        self.save_to_indexeddb().await
+    }
+
+    /// Set a typed field value; arrays are stored as lists
+    async fn set_field_value(&self, field: &str, value: &Value) -> Result<(), String> {
+        let meta = self.meta();
+        match value {
+            Value::Array(items) => {
+                let list = meta
+                    .insert_container(field, LoroList::new())
+                    .map_err(|e| e.to_string())?;
+                for item in items {
+                    list.push(LoroValue::from(item.clone()))
+                        .map_err(|e| e.to_string())?;
+                }
+            }
+            value => {
+                meta.insert(field, LoroValue::from(value.clone()))
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+
+        if self.store().is_cache() {
+            return Ok(());
+        }
+        self.save_to_indexeddb().await
+    }
+
+    /// Set a field held as text, so concurrent edits to it merge
+    async fn set_text_field(&self, field: &str, value: &str) -> Result<(), String> {
+        let meta = self.meta();
+        let text = match meta.get(field) {
+            Some(ValueOrContainer::Container(Container::Text(text))) => text,
+            _ => meta
+                .insert_container(field, LoroText::new())
+                .map_err(|e| e.to_string())?,
+        };
+        text.delete(0, text.len_unicode())
+            .map_err(|e| e.to_string())?;
+        text.insert(0, value).map_err(|e| e.to_string())?;
+
+        if self.store().is_cache() {
+            return Ok(());
+        }
         self.save_to_indexeddb().await
     }
 
@@ -266,6 +318,9 @@ pub trait File {
             Some(ValueOrContainer::Value(LoroValue::I64(value))) => {
                 Ok(Value::Number(serde_json::Number::from(value)))
             }
+            Some(ValueOrContainer::Value(value @ LoroValue::Map(_))) => {
+                serde_json::to_value(&value).map_err(|e| e.to_string())
+            }
             Some(ValueOrContainer::Container(Container::Text(text))) => {
                 Ok(Value::String(text.to_string()))
             }
@@ -288,6 +343,9 @@ pub trait File {
                         LoroValue::I64(n) => {
                             array.push(Value::Number(serde_json::Number::from(*n)));
                         }
+                        LoroValue::Map(_) => {
+                            array.push(serde_json::to_value(item).map_err(|e| e.to_string())?);
+                        }
                         _ => return Err("Unsupported list item type".to_string()),
                     }
                 }
@@ -298,6 +356,20 @@ pub trait File {
         }
     }
 
+    /// Every readable meta field, keyed by name
+    fn meta_fields(&self) -> Map<String, Value> {
+        let mut keys = Vec::new();
+        self.meta().for_each(|key, _| keys.push(key.to_string()));
+
+        let mut fields = Map::new();
+        for key in keys {
+            if let Ok(value) = self.get_field(&key) {
+                fields.insert(key, value);
+            }
+        }
+        fields
+    }
+
     fn to_json(&self) -> Result<Value, String>;
 }
 
@@ -523,19 +595,23 @@ impl<T: File> FileBuilder<T> {
             .await
             .map_err(|e| format!("IndexedDB error: {:?}", e))?;
 
-        // Convert the JS string to a byte array
-        let result_str = result.as_string().ok_or("Invalid data format")?;
-        let bytes = result_str.as_bytes();
-
-        // Create a new LoroDoc - it appears LoroDoc::new() returns a LoroDoc directly
-        let doc = LoroDoc::new();
-
-        // Import the snapshot into the document
-        doc.import(bytes)
-            .map_err(|e| format!("Failed to import data: {}", e))?;
+        Ok(FileStore::Full(doc_from_stored(&JsValue::from(result))?))
+    }
+}
 
-        Ok(FileStore::Full(doc))
+/// The document in a value read from the files store
+///
+/// `save_to_indexeddb` stores a Uint8Array of the exported document.
+pub fn doc_from_stored(value: &JsValue) -> Result<LoroDoc, String> {
+    if !value.is_instance_of::<Uint8Array>() {
+        return Err("Invalid data format: expected a Uint8Array".to_string());
     }
+    let bytes = Uint8Array::new(value).to_vec();
+
+    let doc = LoroDoc::new();
+    doc.import(&bytes)
+        .map_err(|e| format!("Failed to import data: {}", e))?;
+    Ok(doc)
 }
 
 pub trait HasTitle: File {
@@ -571,6 +647,26 @@ pub trait HasUrl: File {
     }
 }
 
+/// Head HTML and CSS for one page only, for landing pages that need
+/// something the theme doesn't have
+pub trait HasHeadOverrides: File {
+    fn get_head_html(&self) -> Result<String, String> {
+        self.get_field(HEAD_HTML_KEY)
+            .map(|html| html.as_str().unwrap_or("").to_string())
+    }
+    async fn set_head_html(&self, html: &str) -> Result<(), String> {
+        self.set_text_field(HEAD_HTML_KEY, html).await
+    }
+
+    fn get_page_css(&self) -> Result<String, String> {
+        self.get_field(PAGE_CSS_KEY)
+            .map(|css| css.as_str().unwrap_or("").to_string())
+    }
+    async fn set_page_css(&self, css: &str) -> Result<(), String> {
+        self.set_text_field(PAGE_CSS_KEY, css).await
+    }
+}
+
 pub trait HasAlt: File {
     fn get_alt(&self) -> Result<String, String> {
         let alt = self.meta().get(ALT_KEY);
@@ -759,6 +855,38 @@ mod tests {
         assert_eq!(file.version().unwrap(), 2);
     }
 
+    #[wasm_bindgen_test]
+    async fn test_set_field_on_cached_file() {
+        // Cached metadata is saved with the project doc, so setting a field
+        // succeeds without writing the file to IndexedDB
+        let cache = LoroMap::new();
+        let file = TestFile {
+            store: FileStore::Cache(cache.clone()),
+        };
+        file.set_field("status", "confirmed")
+            .await
+            .expect("Failed to set a cached field");
+
+        assert_eq!(file.get_field("status").unwrap(), "confirmed");
+        assert!(cache.get("status").is_some());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_doc_from_stored_round_trip() {
+        let doc = LoroDoc::new();
+        doc.get_map(META_KEY).insert(NAME_KEY, "zine").unwrap();
+        doc.get_text("body").insert(0, "Hello").unwrap();
+
+        // As save_to_indexeddb stores it
+        let exported = doc.export(loro::ExportMode::all_updates()).unwrap();
+        let stored = JsValue::from(Uint8Array::from(&exported[..]));
+
+        let loaded = doc_from_stored(&stored).expect("Failed to load document");
+        assert_eq!(loaded.get_deep_value(), doc.get_deep_value());
+
+        assert!(doc_from_stored(&JsValue::from_str("not bytes")).is_err());
+    }
+
     #[wasm_bindgen_test]
     async fn test_get_string_field_with_meta() {
         let mut file = TestFile::builder_for("test")
@@ -1,19 +1,23 @@
 mod asset;
 mod document;
+mod entry;
 mod lib;
 mod page;
 mod partial;
 mod post;
+mod replace;
 mod schema;
 mod template;
 mod text;
 
 pub use asset::*;
 pub use document::*;
+pub use entry::*;
 pub use lib::*;
 pub use page::*;
 pub use partial::*;
 pub use post::*;
+pub use replace::*;
 pub use schema::*;
 pub use template::*;
 pub use text::*;
@@ -1,7 +1,9 @@
 use std::convert::TryFrom;
 
 use crate::model::file::schema::ProseMirrorSchema;
-use crate::model::file::{Chainable, File, FileBuilder, FileStore, HasRichText, HasTitle, HasUrl};
+use crate::model::file::{
+    Chainable, File, FileBuilder, FileStore, HasHeadOverrides, HasRichText, HasTitle, HasUrl,
+};
 use loro::{LoroMap, LoroValue, ValueOrContainer};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
@@ -110,6 +112,8 @@ impl HasTitle for Page {}
 
 impl HasUrl for Page {}
 
+impl HasHeadOverrides for Page {}
+
 impl HasRichText for Page {
     fn schema_json(&self) -> String {
         let schema = self.schema();
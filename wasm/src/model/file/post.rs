@@ -1,4 +1,6 @@
-use crate::model::file::{Chainable, File, FileBuilder, FileStore, HasRichText, HasTitle, HasUrl};
+use crate::model::file::{
+    Chainable, File, FileBuilder, FileStore, HasHeadOverrides, HasRichText, HasTitle, HasUrl,
+};
 use loro::LoroMap;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
@@ -104,6 +106,8 @@ impl HasTitle for Post {}
 
 impl HasUrl for Post {}
 
+impl HasHeadOverrides for Post {}
+
 impl HasRichText for Post {}
 
 impl Serialize for Post {
@@ -0,0 +1,254 @@
+//! Find and replace inside file documents.
+//!
+//! Replacements are made as edits to the document's `LoroText`s rather than
+//! by rewriting the document, so they merge with concurrent edits and can be
+//! undone like any other change.
+
+use loro::{Container, LoroDoc, LoroList, LoroMap, LoroText, ValueOrContainer};
+use regex::{Regex, RegexBuilder};
+
+use super::document::{CHILDREN_KEY, CONTENT_KEY, ROOT_DOC_KEY};
+
+/// Characters of context shown either side of a match
+const CONTEXT_CHARS: usize = 30;
+
+/// One occurrence of the query, in byte offsets
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextMatch {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+/// What to look for: literal text or a regular expression
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    pattern: Regex,
+    /// Whether `$1`, `${name}` in the replacement refer to capture groups
+    expand: bool,
+}
+
+impl Matcher {
+    pub fn new(query: &str, regex: bool, case_sensitive: bool) -> Result<Self, String> {
+        if query.is_empty() {
+            return Err("Nothing to find".to_string());
+        }
+        let source = if regex {
+            query.to_string()
+        } else {
+            regex::escape(query)
+        };
+        let pattern = RegexBuilder::new(&source)
+            .case_insensitive(!case_sensitive)
+            .build()
+            .map_err(|e| format!("Invalid pattern: {}", e))?;
+        Ok(Matcher {
+            pattern,
+            expand: regex,
+        })
+    }
+
+    /// Every non-empty match in `text` and what it becomes
+    pub fn find(&self, text: &str, replacement: &str) -> Vec<TextMatch> {
+        self.pattern
+            .captures_iter(text)
+            .filter_map(|caps| {
+                let whole = caps.get(0)?;
+                if whole.start() == whole.end() {
+                    return None;
+                }
+                let replacement = if self.expand {
+                    let mut expanded = String::new();
+                    caps.expand(replacement, &mut expanded);
+                    expanded
+                } else {
+                    replacement.to_string()
+                };
+                Some(TextMatch {
+                    start: whole.start(),
+                    end: whole.end(),
+                    replacement,
+                })
+            })
+            .collect()
+    }
+
+    /// `text` with every match replaced
+    pub fn replace(&self, text: &str, replacement: &str) -> String {
+        let mut replaced = String::with_capacity(text.len());
+        let mut last = 0;
+        for found in self.find(text, replacement) {
+            replaced.push_str(&text[last..found.start]);
+            replaced.push_str(&found.replacement);
+            last = found.end;
+        }
+        replaced.push_str(&text[last..]);
+        replaced
+    }
+}
+
+/// A match with some surrounding text, for showing in a report
+pub fn match_context(text: &str, found: &TextMatch) -> String {
+    let before: String = text[..found.start]
+        .chars()
+        .rev()
+        .take(CONTEXT_CHARS)
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    let after: String = text[found.end..].chars().take(CONTEXT_CHARS).collect();
+    format!("{}{}{}", before, &text[found.start..found.end], after)
+}
+
+fn collect_list_texts(list: &LoroList, texts: &mut Vec<LoroText>) {
+    for i in 0..list.len() {
+        match list.get(i) {
+            Some(ValueOrContainer::Container(Container::Text(text))) => texts.push(text),
+            Some(ValueOrContainer::Container(Container::Map(map))) => {
+                collect_map_texts(&map, texts)
+            }
+            _ => {}
+        }
+    }
+}
+
+fn collect_map_texts(map: &LoroMap, texts: &mut Vec<LoroText>) {
+    match map.get(CHILDREN_KEY) {
+        Some(ValueOrContainer::Container(Container::List(children))) => {
+            collect_list_texts(&children, texts)
+        }
+        _ => {}
+    }
+}
+
+/// The text containers holding a document's body, in document order
+///
+/// Rich-text documents have one per text run; plain-text documents (theme
+/// templates, partials and stylesheets) have just one.
+pub fn document_texts(doc: &LoroDoc) -> Vec<LoroText> {
+    let root = doc.get_map(ROOT_DOC_KEY);
+    if let Some(ValueOrContainer::Container(Container::Text(text))) = root.get(CONTENT_KEY) {
+        return vec![text];
+    }
+    let mut texts = Vec::new();
+    collect_map_texts(&root, &mut texts);
+    texts
+}
+
+fn char_index(text: &str, byte: usize) -> usize {
+    text[..byte].chars().count()
+}
+
+/// Apply matches found in `text.to_string()` as CRDT edits
+///
+/// Each replacement is inserted after the first matched character, inside
+/// any marks covering the match, so a bold word stays bold; then the old
+/// characters either side are deleted.
+pub fn replace_in_text(text: &LoroText, matches: &[TextMatch]) -> Result<(), String> {
+    let current = text.to_string();
+    for found in matches.iter().rev() {
+        let start = char_index(&current, found.start);
+        let length = current[found.start..found.end].chars().count();
+        let inserted = found.replacement.chars().count();
+        text.insert(start + 1, &found.replacement)
+            .map_err(|e| e.to_string())?;
+        text.delete(start, 1).map_err(|e| e.to_string())?;
+        if length > 1 {
+            text.delete(start + inserted, length - 1)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loro::{ExpandType, LoroValue, StyleConfig, TextDelta};
+    use loro_internal::FxHashMap;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_matcher() {
+        let literal = Matcher::new("zine", false, false).unwrap();
+        assert_eq!(
+            literal.replace("Zine fair: zines $1", "book"),
+            "book fair: books $1"
+        );
+        let exact = Matcher::new("a.b", false, true).unwrap();
+        assert_eq!(exact.replace("a.b axb A.B", "$0"), "$0 axb A.B");
+
+        let pattern = Matcher::new(r"(\d{4})-(\d{2})", true, true).unwrap();
+        assert_eq!(pattern.replace("on 2025-05", "$2/$1"), "on 05/2025");
+        assert!(Matcher::new("(", true, true).is_err());
+        assert!(Matcher::new("", false, true).is_err());
+
+        let found = literal.find("The big zine fair", "x");
+        assert_eq!(
+            match_context("The big zine fair", &found[0]),
+            "The big zine fair"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_replace_in_text_keeps_marks() {
+        let doc = LoroDoc::new();
+        doc.config_default_text_style(Some(StyleConfig {
+            expand: ExpandType::After,
+        }));
+        let text = doc.get_text("text");
+        let mut bold = FxHashMap::default();
+        bold.insert("strong".to_string(), LoroValue::from(true));
+        text.apply_delta(&[
+            TextDelta::Insert {
+                insert: "Meet at the ".to_string(),
+                attributes: None,
+            },
+            TextDelta::Insert {
+                insert: "hall".to_string(),
+                attributes: Some(bold),
+            },
+            TextDelta::Insert {
+                insert: ", café hall".to_string(),
+                attributes: None,
+            },
+        ])
+        .unwrap();
+
+        let matcher = Matcher::new("hall", false, true).unwrap();
+        let matches = matcher.find(&text.to_string(), "library");
+        replace_in_text(&text, &matches).unwrap();
+
+        assert_eq!(text.to_string(), "Meet at the library, café library");
+        let delta = text.to_delta();
+        let (insert, attributes) = delta[1].as_insert().unwrap();
+        assert_eq!(insert, "library");
+        assert_eq!(
+            attributes.as_ref().and_then(|a| a.get("strong")),
+            Some(&LoroValue::from(true))
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_document_texts() {
+        let doc = LoroDoc::new();
+        let root = doc.get_map(ROOT_DOC_KEY);
+        let children = root
+            .insert_container(CHILDREN_KEY, LoroList::new())
+            .unwrap();
+        let paragraph = children.insert_container(0, LoroMap::new()).unwrap();
+        let inner = paragraph
+            .insert_container(CHILDREN_KEY, LoroList::new())
+            .unwrap();
+        inner
+            .insert_container(0, LoroText::new())
+            .unwrap()
+            .insert(0, "Hello")
+            .unwrap();
+
+        let texts = document_texts(&doc);
+        assert_eq!(texts.len(), 1);
+        assert_eq!(texts[0].to_string(), "Hello");
+    }
+}
@@ -63,6 +63,81 @@ impl Default for ProseMirrorSchema {
     }
 }
 
+impl ProseMirrorSchema {
+    /// Whether documents may contain a node type; a schema without nodes
+    /// allows any
+    pub fn allows_node(&self, node_type: &str) -> bool {
+        self.nodes.is_empty()
+            || node_type == "text"
+            || node_type == self.top_node
+            || self.nodes.contains_key(node_type)
+    }
+
+    /// Whether documents may contain a mark; a schema without marks allows
+    /// any
+    pub fn allows_mark(&self, mark_type: &str) -> bool {
+        self.marks.is_empty() || self.marks.contains_key(mark_type)
+    }
+
+    /// Check a ProseMirror JSON node, and everything inside it, uses only
+    /// node types and marks the schema has
+    pub fn check_node(&self, node: &Value) -> Result<(), String> {
+        if let Some(node_type) = node.get("type").and_then(Value::as_str) {
+            if !self.allows_node(node_type) {
+                return Err(format!("Unknown node type: {}", node_type));
+            }
+        }
+        for mark in node
+            .get("marks")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            self.check_mark(mark)?;
+        }
+        for child in node
+            .get("content")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            self.check_node(child)?;
+        }
+        Ok(())
+    }
+
+    fn check_mark(&self, mark: &Value) -> Result<(), String> {
+        match mark.get("type").and_then(Value::as_str) {
+            Some(mark_type) if !self.allows_mark(mark_type) => {
+                Err(format!("Unknown mark: {}", mark_type))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check a ProseMirror step only inserts nodes and marks the schema has
+    pub fn check_step(&self, step: &Value) -> Result<(), String> {
+        match step.get("stepType").and_then(Value::as_str) {
+            Some("replace") | Some("replaceAround") => {
+                for node in step
+                    .pointer("/slice/content")
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    self.check_node(node)?;
+                }
+                Ok(())
+            }
+            Some("addMark") | Some("addNodeMark") => match step.get("mark") {
+                Some(mark) => self.check_mark(mark),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
 impl TryFrom<&str> for ProseMirrorSchema {
     type Error = String;
 
@@ -167,6 +242,43 @@ mod tests {
         assert!(schema.nodes.is_empty());
     }
 
+    #[wasm_bindgen_test]
+    fn test_check_step() {
+        let schema = ProseMirrorSchema::try_from(json!({
+            "marks": { "bold": { "inclusive": true } },
+            "nodes": {
+                "doc": { "content": "block+" },
+                "paragraph": { "content": "inline*", "group": "block" }
+            }
+        }))
+        .unwrap();
+
+        let insert = |node: Value| {
+            json!({
+                "stepType": "replace",
+                "from": 1,
+                "to": 1,
+                "slice": { "content": [node] }
+            })
+        };
+        let bold = json!({ "type": "text", "text": "hi", "marks": [{ "type": "bold" }] });
+        let italic = json!({ "type": "text", "text": "hi", "marks": [{ "type": "italic" }] });
+        assert!(schema.check_step(&insert(bold)).is_ok());
+        assert!(schema.check_step(&insert(italic)).is_err());
+        assert!(schema
+            .check_step(&insert(json!({ "type": "heading", "content": [] })))
+            .is_err());
+        assert!(schema
+            .check_step(&json!({ "stepType": "addMark", "mark": { "type": "link" } }))
+            .is_err());
+
+        // An empty schema doesn't constrain documents
+        let any = ProseMirrorSchema::default();
+        assert!(any
+            .check_step(&insert(json!({ "type": "heading", "content": [] })))
+            .is_ok());
+    }
+
     #[wasm_bindgen_test]
     fn test_invalid_schema() {
         let invalid_json = "{ invalid json }";
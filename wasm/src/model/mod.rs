@@ -2,8 +2,12 @@ pub mod collection;
 pub mod file;
 pub mod lib;
 pub mod project;
+pub mod remap;
+pub mod short_id;
 
 pub use collection::*;
 pub use file::*;
 pub use lib::*;
 pub use project::*;
+pub use remap::*;
+pub use short_id::*;
@@ -1,13 +1,22 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
-use crate::model::collection::Collection;
-use crate::model::file::{Asset, File, Page, Partial, Post, Template, Text};
+use crate::model::collection::{stored_pm_schema, Collection};
+use crate::model::file::{Asset, Entry, File, Page, Partial, Post, Template, Text};
 use crate::model::lib::Model;
+use crate::model::short_id::{is_short_id, unique_short_id};
 use crate::model::{HasContent, HasTitle};
-use crate::types::{FieldDefinition, FieldType, ProjectType};
+use crate::types::{
+    Collaborator, DeployRecord, FieldDefinition, FieldType, ProjectType, SiteSettings,
+    ThemeManifest,
+};
 use crate::ProseMirrorSchema;
 
-use loro::{Container, ExportMode, LoroDoc, LoroError, LoroMap, LoroValue, ValueOrContainer};
+use loro::event::Subscriber;
+use loro::{
+    Container, ExportMode, LoroDoc, LoroError, LoroMap, LoroValue, Subscription, ValueOrContainer,
+    VersionVector,
+};
 use uuid::Uuid;
 use wasm_bindgen::prelude::*;
 
@@ -22,6 +31,20 @@ macro_rules! console_log {
     ($($t:tt)*) => (log(&format!("[Project (WASM)] {}", format!($($t)*))))
 }
 
+const SETTINGS_KEY: &str = "settings";
+const DEPLOYS_KEY: &str = "deploys";
+/// Collaborator id → JSON, so people added by different peers all stay
+const COLLABORATORS_KEY: &str = "collaborators";
+const COMPACTED_KEY: &str = "compacted_at";
+const THEME_MANIFEST_KEY: &str = "theme_manifest";
+const READ_ONLY_KEY: &str = "read_only";
+/// Short alias → UUID, for files created before ids were short
+const ID_ALIASES_KEY: &str = "id_aliases";
+const CREATED_KEY: &str = "created";
+const UPDATED_KEY: &str = "updated";
+/// Commit origin of the timestamp writes, which don't count as updates
+const TIMESTAMPS_ORIGIN: &str = "timestamps";
+
 const DEFAULT_STYLE: &str = r#"* {
   font-family: sans-serif;
 }
@@ -54,20 +77,175 @@ const TEMPLATE_CONTENT: &str = r#"<!DOCTYPE html>
 </body>
 </html>"#;
 
+/// The document as a snapshot, which loads faster than its update log
+///
+/// A shallow snapshot also drops the history before the current version.
+pub fn snapshot(doc: &LoroDoc, shallow: bool) -> Result<Vec<u8>, String> {
+    let frontiers = doc.oplog_frontiers();
+    let mode = if shallow {
+        ExportMode::shallow_snapshot(&frontiers)
+    } else {
+        ExportMode::Snapshot
+    };
+    doc.export(mode).map_err(|e| format!("Failed to export snapshot: {}", e))
+}
+
+/// A document's bytes for storage: its update log, or a snapshot if it's
+/// shallow, since a shallow document has no updates from before its snapshot
+pub fn export_document(doc: &LoroDoc) -> Result<Vec<u8>, String> {
+    if doc.is_shallow() {
+        return snapshot(doc, false);
+    }
+    doc.export(ExportMode::all_updates())
+        .map_err(|e| format!("Failed to export: {}", e))
+}
+
+/// The changes a document holds, encoded, for a peer to send what's newer
+pub fn document_version(doc: &LoroDoc) -> Vec<u8> {
+    doc.oplog_vv().encode()
+}
+
+/// Changes in `doc` that a peer at the encoded `version` doesn't have, if any
+///
+/// An empty `version` is a peer with nothing yet.
+pub fn updates_since(doc: &LoroDoc, version: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let from = if version.is_empty() {
+        VersionVector::new()
+    } else {
+        VersionVector::decode(version).map_err(|e| format!("Invalid version: {}", e))?
+    };
+    if from.includes_vv(&doc.oplog_vv()) {
+        return Ok(None);
+    }
+    doc.export(ExportMode::updates(&from))
+        .map(Some)
+        .map_err(|e| format!("Failed to export updates: {}", e))
+}
+
+/// Rough measures of what a document holds in memory
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DocMemory {
+    /// Operations in the history, which are all kept in memory
+    pub ops: usize,
+    pub changes: usize,
+    /// Maps and lists in the current state
+    pub containers: usize,
+    /// Characters of text and string values in the current state
+    pub text_length: usize,
+}
+
+pub fn doc_memory(doc: &LoroDoc) -> DocMemory {
+    let mut memory = DocMemory {
+        ops: doc.len_ops(),
+        changes: doc.len_changes(),
+        ..Default::default()
+    };
+    count_values(&doc.get_deep_value(), &mut memory);
+    memory
+}
+
+fn count_values(value: &LoroValue, memory: &mut DocMemory) {
+    match value {
+        LoroValue::String(text) => memory.text_length += text.chars().count(),
+        LoroValue::List(list) => {
+            memory.containers += 1;
+            for item in list.iter() {
+                count_values(item, memory);
+            }
+        }
+        LoroValue::Map(map) => {
+            memory.containers += 1;
+            for item in map.values() {
+                count_values(item, memory);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collections every project of a type has, in the order they're created
+pub fn required_collections(project_type: &ProjectType) -> &'static [&'static str] {
+    match project_type {
+        ProjectType::Site => &["page", "post", "asset"],
+        ProjectType::Theme => &["template", "partial", "text", "asset"],
+    }
+}
+
+fn required_field(name: &str, field_type: FieldType) -> FieldDefinition {
+    FieldDefinition {
+        name: name.to_string(),
+        field_type,
+        required: true,
+    }
+}
+
+/// The fields a built-in collection starts with
+fn default_model(collection_name: &str) -> Model {
+    let mut model = Model::new();
+    match collection_name {
+        "template" | "partial" | "text" => {
+            model.insert("content", required_field("content", FieldType::Text));
+        }
+        "asset" => {
+            model.insert("mime_type", required_field("mime_type", FieldType::String));
+        }
+        "page" => {
+            model
+                .insert("template", required_field("template", FieldType::String))
+                .insert("title", required_field("title", FieldType::String))
+                .insert("body", required_field("body", FieldType::RichText));
+        }
+        "post" => {
+            model
+                .insert("title", required_field("title", FieldType::String))
+                .insert("body", required_field("body", FieldType::RichText));
+        }
+        _ => {}
+    }
+    model
+}
+
 // Project: Wrapper around a LoroDocument that encapsulates project-specific functionality
 #[derive(Clone, Debug)]
 pub struct Project {
     id: String,
     project_type: ProjectType,
     created: f64, // Store as timestamp
-    updated: f64, // Store as timestamp
+    /// Shared by clones, and moved on by every commit to the document
+    updated: Arc<Mutex<f64>>,
     doc: LoroDoc,
 }
 
+/// A timestamp kept in the project meta
+fn meta_timestamp(meta: &LoroMap, key: &str) -> Option<f64> {
+    match meta.get(key) {
+        Some(ValueOrContainer::Value(LoroValue::Double(at))) => Some(at),
+        Some(ValueOrContainer::Value(LoroValue::I64(at))) => Some(at as f64),
+        _ => None,
+    }
+}
+
 impl Project {
     pub async fn new(
         project_type: ProjectType,
         theme_id: Option<String>,
+    ) -> Result<Project, String> {
+        Self::create(project_type, theme_id, true).await
+    }
+
+    /// A project with its collections but without the sample page, post,
+    /// template and style, for filling from an import
+    pub async fn new_empty(
+        project_type: ProjectType,
+        theme_id: Option<String>,
+    ) -> Result<Project, String> {
+        Self::create(project_type, theme_id, false).await
+    }
+
+    async fn create(
+        project_type: ProjectType,
+        theme_id: Option<String>,
+        samples: bool,
     ) -> Result<Project, String> {
         let id = Uuid::new_v4().to_string();
         let now = chrono::Utc::now().timestamp_millis() as f64;
@@ -81,21 +259,25 @@ impl Project {
         meta_map
             .insert("id", id.clone())
             .map_err(|e| format!("Failed to set project ID: {}", e))?;
+        meta_map
+            .insert(CREATED_KEY, now)
+            .map_err(|e| format!("Failed to set creation time: {}", e))?;
 
         let mut project = Project {
             id,
             project_type: project_type.clone(),
             created: now,
-            updated: now,
+            updated: Arc::new(Mutex::new(now)),
             doc,
         };
+        project.track_updates();
 
         // Initialize based on project type
         match project_type {
             ProjectType::Site => {
                 if let Some(theme_id) = theme_id {
                     project
-                        .init_default_site(&theme_id)
+                        .init_default_site(&theme_id, samples)
                         .await
                         .map_err(|e| format!("Failed to initialize site: {}", e))?;
                 } else {
@@ -104,7 +286,7 @@ impl Project {
             }
             ProjectType::Theme => {
                 project
-                    .init_default_theme()
+                    .init_default_theme(samples)
                     .await
                     .map_err(|e| format!("Failed to initialize theme: {}", e))?;
             }
@@ -117,12 +299,47 @@ impl Project {
         self.project_type.clone()
     }
 
+    /// When the project was created, in ms since the epoch
     pub fn created(&self) -> f64 {
         self.created
     }
 
+    /// When a change to the project was last committed, in ms since the epoch
     pub fn updated(&self) -> f64 {
-        self.updated
+        *self.updated.lock().unwrap()
+    }
+
+    /// Move `updated` on whenever a change is committed, locally or imported
+    fn track_updates(&self) {
+        let updated = self.updated.clone();
+        self.doc
+            .subscribe_root(Arc::new(move |event| {
+                if event.origin == TIMESTAMPS_ORIGIN {
+                    return;
+                }
+                *updated.lock().unwrap() = chrono::Utc::now().timestamp_millis() as f64;
+            }))
+            .detach();
+    }
+
+    /// Record the timestamps in the project meta, so they're saved with it
+    fn persist_timestamps(&self) -> Result<(), String> {
+        // Pending changes are committed first, so they still count as updates
+        self.doc.commit();
+        let meta = self.meta();
+        let mut changed = false;
+        for (key, at) in [(CREATED_KEY, self.created), (UPDATED_KEY, self.updated())] {
+            if meta_timestamp(&meta, key) != Some(at) {
+                meta.insert(key, at)
+                    .map_err(|e| format!("Failed to record {} time: {}", key, e))?;
+                changed = true;
+            }
+        }
+        if changed {
+            self.doc.set_next_commit_origin(TIMESTAMPS_ORIGIN);
+            self.doc.commit();
+        }
+        Ok(())
     }
 
     pub fn analyse(&self) -> Result<(), String> {
@@ -131,6 +348,10 @@ impl Project {
         Ok(())
     }
 
+    /// Load a saved project
+    ///
+    /// `created` and `updated` are only used if the document predates
+    /// timestamps being stored in it.
     pub fn import(
         data: Vec<u8>,
         id: String,
@@ -142,70 +363,32 @@ impl Project {
         doc.import(&data[..])
             .map_err(|e| format!("Failed to import project: {}", e))?;
 
+        let meta = doc.get_map(crate::META_KEY);
         let project = Project {
             id,
             project_type,
-            created,
-            updated,
+            created: meta_timestamp(&meta, CREATED_KEY).unwrap_or(created),
+            updated: Arc::new(Mutex::new(
+                meta_timestamp(&meta, UPDATED_KEY).unwrap_or(updated),
+            )),
             doc,
         };
+        project.track_updates();
 
         Ok(project)
     }
 
     // Helper method to initialize a default theme
-    async fn init_default_theme(&mut self) -> Result<(), String> {
+    async fn init_default_theme(&mut self, samples: bool) -> Result<(), String> {
         let meta = self.meta();
         meta.insert("name", "New Theme".to_string())
             .map_err(|e| format!("Failed to set theme name: {}", e))?;
 
-        // Add template collection
-        let mut template_model = Model::new();
-        template_model.insert(
-            "content",
-            FieldDefinition {
-                name: "content".to_string(),
-                field_type: FieldType::Text,
-                required: true,
-            },
-        );
-        self.add_collection::<Template>("template", template_model)?;
-
-        // Add partial collection
-        let mut partial_model = Model::new();
-        partial_model.insert(
-            "content",
-            FieldDefinition {
-                name: "content".to_string(),
-                field_type: FieldType::Text,
-                required: true,
-            },
-        );
-        self.add_collection::<Partial>("partial", partial_model)?;
-
-        // Add text collection
-        let mut text_model = Model::new();
-        text_model.insert(
-            "content",
-            FieldDefinition {
-                name: "content".to_string(),
-                field_type: FieldType::Text,
-                required: true,
-            },
-        );
-        self.add_collection::<Text>("text", text_model)?;
+        self.add_missing_collections()?;
 
-        // Add asset collection
-        let mut asset_model = Model::new();
-        asset_model.insert(
-            "mime_type",
-            FieldDefinition {
-                name: "mime_type".to_string(),
-                field_type: FieldType::String,
-                required: true,
-            },
-        );
-        self.add_collection::<Asset>("asset", asset_model)?;
+        if !samples {
+            return Ok(());
+        }
 
         // Create default template
         let template_builder: crate::FileBuilder<Template> =
@@ -215,6 +398,7 @@ impl Project {
         template
             .insert_content(TEMPLATE_CONTENT, 0)
             .map_err(|e| format!("Failed to set template content: {}", e))?;
+        template.save_to_indexeddb().await?;
 
         // Create default style
         let style_builder: crate::FileBuilder<Text> =
@@ -223,95 +407,24 @@ impl Project {
         style
             .insert_content(DEFAULT_STYLE, 0)
             .map_err(|e| format!("Failed to set style content: {}", e))?;
-
-        self.updated = chrono::Utc::now().timestamp_millis() as f64;
+        style.save_to_indexeddb().await?;
 
         Ok(())
     }
 
     // Helper method to initialize a default site
-    async fn init_default_site(&mut self, theme_id: &str) -> Result<(), String> {
+    async fn init_default_site(&mut self, theme_id: &str, samples: bool) -> Result<(), String> {
         let meta = self.meta();
         meta.insert("name", "New Site".to_string())
             .map_err(|e| format!("(init_default_site) Failed to set site name: {}", e))?;
         meta.insert("themeId", theme_id.to_string())
             .map_err(|e| format!("(init_default_site) Failed to set theme ID: {}", e))?;
 
-        // Add page collection
-        let mut page_model = Model::new();
-        page_model
-            .insert(
-                "template",
-                FieldDefinition {
-                    name: "template".to_string(),
-                    field_type: FieldType::String,
-                    required: true,
-                },
-            )
-            .insert(
-                "title",
-                FieldDefinition {
-                    name: "title".to_string(),
-                    field_type: FieldType::String,
-                    required: true,
-                },
-            )
-            .insert(
-                "body",
-                FieldDefinition {
-                    name: "body".to_string(),
-                    field_type: FieldType::RichText,
-                    required: true,
-                },
-            );
-
-        console_log!(
-            "(init_default_site) Adding Page collection {:?}",
-            page_model
-        );
-        self.add_collection::<Page>("page", page_model)?;
-
-        // Add post collection
-        let mut post_model = Model::new();
-        post_model
-            .insert(
-                "title",
-                FieldDefinition {
-                    name: "title".to_string(),
-                    field_type: FieldType::String,
-                    required: true,
-                },
-            )
-            .insert(
-                "body",
-                FieldDefinition {
-                    name: "body".to_string(),
-                    field_type: FieldType::RichText,
-                    required: true,
-                },
-            );
-
-        console_log!(
-            "(init_default_site) Adding Post collection {:?}",
-            post_model
-        );
-        self.add_collection::<Post>("post", post_model)?;
+        self.add_missing_collections()?;
 
-        // Add asset collection
-        let mut asset_model = Model::new();
-        asset_model.insert(
-            "mime_type",
-            FieldDefinition {
-                name: "mime_type".to_string(),
-                field_type: FieldType::String,
-                required: true,
-            },
-        );
-        console_log!(
-            "(init_default_site) Adding Asset collection {:?}",
-            asset_model
-        );
-        self.add_collection::<Asset>("asset", asset_model)?;
+        if !samples {
+            return Ok(());
+        }
 
         let pm_schema = ProseMirrorSchema {
             marks: HashMap::new(),
@@ -349,8 +462,6 @@ impl Project {
             .map_err(|e| format!("(init_default_site) Failed to set post title: {}", e))?;
         console_log!("(init_default_site) Added Post {:?}", post);
 
-        self.updated = chrono::Utc::now().timestamp_millis() as f64;
-
         Ok(())
     }
 
@@ -362,6 +473,13 @@ impl Project {
         self.doc.get_map(crate::META_KEY)
     }
 
+    /// Call `callback` whenever changes to the project document are committed
+    ///
+    /// The subscription ends when the returned `Subscription` is dropped.
+    pub fn subscribe(&self, callback: Subscriber) -> Subscription {
+        self.doc.subscribe_root(callback)
+    }
+
     pub fn name(&self) -> Result<String, String> {
         match self.meta().get("name") {
             Some(ValueOrContainer::Value(LoroValue::String(name))) => Ok(name.clone().to_string()),
@@ -372,11 +490,169 @@ impl Project {
     pub fn set_name(&mut self, name: &str) -> Result<(), LoroError> {
         let meta = self.meta();
         meta.insert("name", name.to_string())?;
-        self.updated = chrono::Utc::now().timestamp_millis() as f64;
         self.doc.commit();
         Ok(())
     }
 
+    /// Site settings, stored as JSON in the project meta
+    pub fn settings(&self) -> Result<SiteSettings, String> {
+        match self.meta().get(SETTINGS_KEY) {
+            Some(ValueOrContainer::Value(LoroValue::String(settings))) => {
+                serde_json::from_str(&settings).map_err(|e| format!("Invalid settings: {}", e))
+            }
+            _ => Ok(SiteSettings::default()),
+        }
+    }
+
+    pub fn set_settings(&self, settings: &SiteSettings) -> Result<(), String> {
+        let settings = serde_json::to_string(settings).map_err(|e| e.to_string())?;
+        self.meta()
+            .insert(SETTINGS_KEY, settings)
+            .map_err(|e| format!("Failed to set settings: {}", e))
+    }
+
+    /// What a theme expects of the sites using it, stored as JSON in the
+    /// project meta
+    pub fn theme_manifest(&self) -> Result<ThemeManifest, String> {
+        match self.meta().get(THEME_MANIFEST_KEY) {
+            Some(ValueOrContainer::Value(LoroValue::String(manifest))) => {
+                serde_json::from_str(&manifest)
+                    .map_err(|e| format!("Invalid theme manifest: {}", e))
+            }
+            _ => Ok(ThemeManifest::default()),
+        }
+    }
+
+    pub fn set_theme_manifest(&self, manifest: &ThemeManifest) -> Result<(), String> {
+        let manifest = serde_json::to_string(manifest).map_err(|e| e.to_string())?;
+        self.meta()
+            .insert(THEME_MANIFEST_KEY, manifest)
+            .map_err(|e| format!("Failed to set theme manifest: {}", e))
+    }
+
+    /// The last deploy recorded for a target, if any
+    pub fn last_deploy(&self, target: &str) -> Result<Option<DeployRecord>, String> {
+        match self.doc.get_map(DEPLOYS_KEY).get(target) {
+            Some(ValueOrContainer::Value(LoroValue::String(record))) => {
+                serde_json::from_str(&record)
+                    .map(Some)
+                    .map_err(|e| format!("Invalid deploy record: {}", e))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn record_deploy(&self, target: &str, record: &DeployRecord) -> Result<(), String> {
+        let record = serde_json::to_string(record).map_err(|e| e.to_string())?;
+        self.doc
+            .get_map(DEPLOYS_KEY)
+            .insert(target, record)
+            .map_err(|e| format!("Failed to record deploy: {}", e))
+    }
+
+    /// Everyone credited on the project, by name
+    pub fn collaborators(&self) -> Result<Vec<Collaborator>, String> {
+        let mut stored = Vec::new();
+        self.doc
+            .get_map(COLLABORATORS_KEY)
+            .for_each(|id, value| {
+                if let ValueOrContainer::Value(LoroValue::String(json)) = value {
+                    stored.push((id.to_string(), json.to_string()));
+                }
+            });
+        let mut collaborators = stored
+            .into_iter()
+            .map(|(id, json)| {
+                serde_json::from_str::<Collaborator>(&json)
+                    .map_err(|e| format!("Invalid collaborator {}: {}", id, e))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        collaborators.sort_by(|a, b| {
+            a.name
+                .to_lowercase()
+                .cmp(&b.name.to_lowercase())
+                .then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(collaborators)
+    }
+
+    /// Add a collaborator, or replace the one with the same id
+    pub fn set_collaborator(&self, collaborator: &Collaborator) -> Result<(), String> {
+        let json = serde_json::to_string(collaborator).map_err(|e| e.to_string())?;
+        self.doc
+            .get_map(COLLABORATORS_KEY)
+            .insert(&collaborator.id, json)
+            .map_err(|e| format!("Failed to set collaborator: {}", e))
+    }
+
+    /// Remove a collaborator; false if there was none with `id`
+    pub fn remove_collaborator(&self, id: &str) -> Result<bool, String> {
+        let map = self.doc.get_map(COLLABORATORS_KEY);
+        if map.get(id).is_none() {
+            return Ok(false);
+        }
+        map.delete(id)
+            .map_err(|e| format!("Failed to remove collaborator: {}", e))?;
+        Ok(true)
+    }
+
+    /// When the project's documents were last compacted (RFC 3339)
+    pub fn compacted_at(&self) -> Option<String> {
+        match self.meta().get(COMPACTED_KEY) {
+            Some(ValueOrContainer::Value(LoroValue::String(at))) => Some(at.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn set_compacted_at(&self, at: &str) -> Result<(), String> {
+        self.meta()
+            .insert(COMPACTED_KEY, at.to_string())
+            .map_err(|e| format!("Failed to set compaction time: {}", e))
+    }
+
+    /// Whether the project's content is frozen against changes
+    pub fn read_only(&self) -> bool {
+        matches!(
+            self.meta().get(READ_ONLY_KEY),
+            Some(ValueOrContainer::Value(LoroValue::Bool(true)))
+        )
+    }
+
+    pub fn set_read_only(&self, enabled: bool) -> Result<(), String> {
+        self.meta()
+            .insert(READ_ONLY_KEY, enabled)
+            .map_err(|e| format!("Failed to set read-only mode: {}", e))?;
+        self.doc.commit();
+        Ok(())
+    }
+
+    /// Replace the document with a snapshot of itself, and return the snapshot
+    ///
+    /// Peers still on a version from before a shallow snapshot can no
+    /// longer merge their changes into it.
+    pub fn compact(&mut self, shallow: bool) -> Result<Vec<u8>, String> {
+        self.persist_timestamps()?;
+        let data = snapshot(&self.doc, shallow)?;
+        let doc = LoroDoc::new();
+        doc.import(&data)
+            .map_err(|e| format!("Failed to import snapshot: {}", e))?;
+        self.doc = doc;
+        self.track_updates();
+        Ok(data)
+    }
+
+    /// The id recorded inside the document, which should match `id()`
+    pub fn doc_id(&self) -> Option<String> {
+        match self.meta().get("id") {
+            Some(ValueOrContainer::Value(LoroValue::String(id))) => Some(id.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn memory(&self) -> DocMemory {
+        doc_memory(&self.doc)
+    }
+
     pub fn theme_id(&self) -> Option<String> {
         let meta = self.meta();
         match meta.get("themeId") {
@@ -390,7 +666,6 @@ impl Project {
     pub fn set_theme_id(&mut self, theme_id: &str) -> Result<(), LoroError> {
         let meta = self.meta();
         meta.insert("themeId", theme_id.to_string())?;
-        self.updated = chrono::Utc::now().timestamp_millis() as f64;
         self.doc.commit();
         Ok(())
     }
@@ -435,11 +710,38 @@ impl Project {
         let collection = builder.build(&self.doc)?;
 
         self.doc.commit();
-        self.updated = chrono::Utc::now().timestamp_millis() as f64;
 
         Ok(collection)
     }
 
+    /// Add any of the type's required collections that don't exist yet, and
+    /// return their names
+    pub fn add_missing_collections(&mut self) -> Result<Vec<String>, String> {
+        let existing: Vec<String> = self
+            .get_collections()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let mut added = Vec::new();
+        for name in required_collections(&self.project_type) {
+            if existing.iter().any(|existing| existing == *name) {
+                continue;
+            }
+            let model = default_model(name);
+            match *name {
+                "template" => self.add_collection::<Template>(name, model).map(|_| ())?,
+                "partial" => self.add_collection::<Partial>(name, model).map(|_| ())?,
+                "text" => self.add_collection::<Text>(name, model).map(|_| ())?,
+                "asset" => self.add_collection::<Asset>(name, model).map(|_| ())?,
+                "page" => self.add_collection::<Page>(name, model).map(|_| ())?,
+                "post" => self.add_collection::<Post>(name, model).map(|_| ())?,
+                other => return Err(format!("Unknown built-in collection: {}", other)),
+            }
+            added.push(name.to_string());
+        }
+        Ok(added)
+    }
+
     pub fn get_collection<FileType: File + Default>(
         &self,
         name: &str,
@@ -455,6 +757,17 @@ impl Project {
         }
     }
 
+    /// Schema new documents in a collection start with: the one set on the
+    /// collection, or the default
+    pub fn collection_pm_schema(&self, name: &str) -> Result<ProseMirrorSchema, String> {
+        match self.doc.get_map("collections").get(name) {
+            Some(ValueOrContainer::Container(Container::Map(map))) => {
+                Ok(stored_pm_schema(&map)?.unwrap_or_default())
+            }
+            _ => Err(format!("Collection not found: {}", name)),
+        }
+    }
+
     pub fn get_collections(&self) -> Result<Vec<(String, LoroMap)>, String> {
         let collections = self.doc.get_map("collections");
         let mut result: Vec<(String, LoroMap)> = Vec::new();
@@ -486,10 +799,16 @@ impl Project {
     }
 
     /// Returns a file that is attached to the collection
+    ///
+    /// A file without an id gets a short one, unused in the project.
     pub async fn attach_file<TFile: File + Default>(
         &mut self,
         file_builder: crate::FileBuilder<TFile>,
     ) -> Result<TFile, String> {
+        let file_builder = match file_builder.meta()?.get(crate::ID_KEY) {
+            Some(ValueOrContainer::Value(LoroValue::String(_))) => file_builder,
+            _ => file_builder.with_id(self.new_file_id()?)?,
+        };
         let collection = self.get_collection::<TFile>(&file_builder.collection_type())?;
         let file = collection
             .attach_file(file_builder)
@@ -497,23 +816,118 @@ impl Project {
             .expect("Failed to attach file");
 
         self.doc.commit();
-        self.updated = chrono::Utc::now().timestamp_millis() as f64;
 
         Ok(file)
     }
 
+    /// Ids of every file and folder in the project, and short aliases
+    pub fn file_ids(&self) -> Result<HashSet<String>, String> {
+        let mut ids: HashSet<String> = self.id_aliases().into_keys().collect();
+        for (name, _) in self.get_collections()? {
+            ids.extend(self.get_collection::<Entry>(&name)?.node_ids()?);
+        }
+        Ok(ids)
+    }
+
+    /// A short id no file in the project has
+    pub fn new_file_id(&self) -> Result<String, String> {
+        let taken = self.file_ids()?;
+        unique_short_id(|id| taken.contains(id))
+    }
+
+    /// Short alias → UUID of the files that have one
+    pub fn id_aliases(&self) -> HashMap<String, String> {
+        let mut aliases = HashMap::new();
+        self.doc
+            .get_map(ID_ALIASES_KEY)
+            .for_each(|alias, value| {
+                if let ValueOrContainer::Value(LoroValue::String(id)) = value {
+                    aliases.insert(alias.to_string(), id.to_string());
+                }
+            });
+        aliases
+    }
+
+    /// The id a file is stored under, given its id or short alias
+    pub fn resolve_file_id(&self, id: &str) -> String {
+        match self.doc.get_map(ID_ALIASES_KEY).get(id) {
+            Some(ValueOrContainer::Value(LoroValue::String(full))) => full.to_string(),
+            _ => id.to_string(),
+        }
+    }
+
+    /// Id → short form, giving files with UUIDs an alias if they lack one
+    pub fn short_file_ids(&self, ids: &[String]) -> Result<HashMap<String, String>, String> {
+        let aliases = self.id_aliases();
+        let mut short: HashMap<String, String> = aliases
+            .iter()
+            .map(|(alias, id)| (id.clone(), alias.clone()))
+            .collect();
+        let mut taken: Option<HashSet<String>> = None;
+        let map = self.doc.get_map(ID_ALIASES_KEY);
+        for id in ids {
+            if is_short_id(id) {
+                short.insert(id.clone(), id.clone());
+                continue;
+            }
+            if short.contains_key(id) {
+                continue;
+            }
+            if taken.is_none() {
+                taken = Some(self.file_ids()?);
+            }
+            let taken = taken.as_mut().expect("taken ids were just loaded");
+            let alias = unique_short_id(|candidate| taken.contains(candidate))?;
+            map.insert(&alias, id.clone())
+                .map_err(|e| format!("Failed to add id alias: {}", e))?;
+            taken.insert(alias.clone());
+            short.insert(id.clone(), alias);
+        }
+        let wanted: HashSet<&String> = ids.iter().collect();
+        short.retain(|id, _| wanted.contains(id));
+        Ok(short)
+    }
+
     pub fn save(&mut self) -> Result<(), String> {
         // This would be implemented to export project data
-        self.updated = chrono::Utc::now().timestamp_millis() as f64;
         Ok(())
     }
 
     pub fn export(&self) -> Result<Vec<u8>, String> {
-        let export = self.doc.export(ExportMode::all_updates());
-        match export {
-            Ok(export) => Ok(export),
-            Err(e) => Err(format!("Failed to export: {}", e)),
-        }
+        self.persist_timestamps()?;
+        export_document(&self.doc)
+    }
+
+    /// The project as a snapshot without its edit history, which takes
+    /// less space than [`Project::export`] but can't be merged with edits
+    /// made before it elsewhere
+    pub fn export_snapshot(&self) -> Result<Vec<u8>, String> {
+        self.persist_timestamps()?;
+        snapshot(&self.doc, true)
+    }
+
+    /// See [`document_version`]
+    pub fn version(&self) -> Vec<u8> {
+        document_version(&self.doc)
+    }
+
+    /// Whether edits are waiting in an open transaction, not yet committed
+    pub fn has_uncommitted_changes(&self) -> bool {
+        self.doc.get_pending_txn_len() > 0
+    }
+
+    /// See [`updates_since`]
+    pub fn updates_since(&self, version: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        self.persist_timestamps()?;
+        updates_since(&self.doc, version)
+    }
+
+    /// Merge changes another copy of the project made, as from `updates_since`
+    pub fn merge_updates(&self, data: &[u8]) -> Result<(), String> {
+        self.doc
+            .import(data)
+            .map(|_| ())
+            .map_err(|e| format!("Failed to merge updates: {}", e))
     }
 }
 
@@ -605,6 +1019,63 @@ mod tests {
         assert!(project.updated() > initial_updated);
     }
 
+    #[wasm_bindgen_test]
+    fn test_updates_since() {
+        let ours = LoroDoc::new();
+        ours.get_map(crate::META_KEY).insert("name", "Zine").unwrap();
+        ours.commit();
+        let theirs = LoroDoc::new();
+
+        let data = updates_since(&ours, &document_version(&theirs))
+            .unwrap()
+            .expect("the peer is behind");
+        theirs.import(&data).unwrap();
+        assert_eq!(theirs.get_deep_value(), ours.get_deep_value());
+
+        // Nothing to send once the peer is caught up; everything to a new peer
+        assert_eq!(updates_since(&ours, &document_version(&theirs)), Ok(None));
+        assert!(updates_since(&ours, &[]).unwrap().is_some());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_site_settings() {
+        let project = Project::new(ProjectType::Theme, None).await.unwrap();
+        assert_eq!(project.settings().unwrap(), SiteSettings::default());
+
+        let settings = SiteSettings {
+            url: Some("https://zine.example".to_string()),
+            ..Default::default()
+        };
+        project.set_settings(&settings).unwrap();
+        assert_eq!(project.settings().unwrap(), settings);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_collaborators() {
+        let project = Project::new(ProjectType::Site, None).await.unwrap();
+        assert!(project.collaborators().unwrap().is_empty());
+
+        let collaborator = |id: &str, name: &str| Collaborator {
+            id: id.to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        };
+        project.set_collaborator(&collaborator("c1", "zed")).unwrap();
+        project.set_collaborator(&collaborator("c2", "Ana")).unwrap();
+        project.set_collaborator(&collaborator("c1", "Bo")).unwrap();
+        let names: Vec<String> = project
+            .collaborators()
+            .unwrap()
+            .into_iter()
+            .map(|c| c.name)
+            .collect();
+        assert_eq!(names, vec!["Ana", "Bo"]);
+
+        assert!(project.remove_collaborator("c2").unwrap());
+        assert!(!project.remove_collaborator("c2").unwrap());
+        assert_eq!(project.collaborators().unwrap().len(), 1);
+    }
+
     #[wasm_bindgen_test]
     async fn test_collection_operations() {
         let mut project = Project::new(ProjectType::Theme, None).await.unwrap();
@@ -652,6 +1123,35 @@ mod tests {
         assert!(files.iter().any(|f| f.name().unwrap() == "test_text"));
     }
 
+    #[wasm_bindgen_test]
+    async fn test_short_file_ids() {
+        let mut project = Project::new(ProjectType::Theme, None).await.unwrap();
+
+        let file_builder: crate::FileBuilder<Text> = project
+            .create_file("fresh", "text", crate::FileStore::Cache(LoroMap::new()))
+            .unwrap();
+        let fresh = project.attach_file(file_builder).await.unwrap().id().unwrap();
+        assert!(is_short_id(&fresh), "{}", fresh);
+
+        // A file from before short ids keeps its UUID and gets an alias
+        let legacy_id = Uuid::new_v4().to_string();
+        let file_builder: crate::FileBuilder<Text> = project
+            .create_file("legacy", "text", crate::FileStore::Cache(LoroMap::new()))
+            .unwrap()
+            .with_id(legacy_id.clone())
+            .unwrap();
+        project.attach_file(file_builder).await.unwrap();
+
+        let ids = vec![fresh.clone(), legacy_id.clone()];
+        let short = project.short_file_ids(&ids).unwrap();
+        assert_eq!(short[&fresh], fresh);
+        let alias = short[&legacy_id].clone();
+        assert!(is_short_id(&alias));
+        assert_eq!(project.resolve_file_id(&alias), legacy_id);
+        assert_eq!(project.resolve_file_id(&fresh), fresh);
+        assert_eq!(project.short_file_ids(&ids).unwrap()[&legacy_id], alias);
+    }
+
     #[wasm_bindgen_test]
     async fn test_export_import() {
         let original_project = Project::new(ProjectType::Theme, None).await.unwrap();
@@ -681,6 +1181,67 @@ mod tests {
         assert_eq!(imported_project.updated(), original_project.updated());
     }
 
+    #[wasm_bindgen_test]
+    async fn test_timestamps_saved_with_project() {
+        let mut project = Project::new(ProjectType::Theme, None).await.unwrap();
+        *project.updated.lock().unwrap() = 1.0;
+        project.set_name("Renamed").unwrap();
+        let updated = project.updated();
+        assert!(updated > 1.0);
+
+        let data = project.export().unwrap();
+        // Writing the timestamps isn't itself an update
+        assert_eq!(project.updated(), updated);
+
+        let imported =
+            Project::import(data, project.id(), project.project_type(), 0.0, 0.0).unwrap();
+        assert_eq!(imported.created(), project.created());
+        assert_eq!(imported.updated(), updated);
+
+        // Clones share the timestamp
+        let clone = imported.clone();
+        clone.set_read_only(true).unwrap();
+        assert!(imported.updated() >= updated);
+        assert_eq!(imported.updated(), clone.updated());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_compact() {
+        let mut project = Project::new(ProjectType::Theme, None).await.unwrap();
+        for name in ["One", "Two", "Three"].iter() {
+            project.set_name(name).unwrap();
+        }
+
+        let data = project.compact(true).unwrap();
+        assert_eq!(project.name().unwrap(), "Three");
+        assert!(project.get_collection::<Template>("template").is_ok());
+
+        // Saved compacted projects load like any other
+        let imported = Project::import(
+            project.export().unwrap(),
+            project.id(),
+            project.project_type(),
+            project.created(),
+            project.updated(),
+        )
+        .unwrap();
+        assert_eq!(imported.name().unwrap(), "Three");
+        assert!(!data.is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_memory() {
+        let mut project = Project::new(ProjectType::Theme, None).await.unwrap();
+        let before = project.memory();
+        assert!(before.ops > 0);
+        assert!(before.containers > 0);
+
+        project.set_name("A much longer theme name").unwrap();
+        let after = project.memory();
+        assert!(after.ops > before.ops);
+        assert!(after.text_length > before.text_length);
+    }
+
     #[wasm_bindgen_test]
     async fn test_error_cases() {
         // Test creating site without theme ID
@@ -0,0 +1,252 @@
+//! Giving copied documents fresh ids.
+//!
+//! A file's id appears in its own meta, in its project's files tree, and in
+//! whatever refers to it: asset URLs (`/assets/{id}/{name}`), block
+//! references, image sources in rich text. Copying documents in under the
+//! same ids would have the copies share, and overwrite, the originals'
+//! storage, so every string in a copied document is rewritten, wherever it
+//! sits. Ids are UUIDs or random short ids, so one is vanishingly unlikely
+//! to turn up in a string by chance.
+
+use std::collections::HashMap;
+
+use loro::{Container, ContainerType, ExportMode, LoroDoc, LoroMap, LoroValue, ValueOrContainer};
+
+/// `text` with every old id in `ids` replaced by its new one
+fn remap_str(text: &str, ids: &HashMap<String, String>) -> Option<String> {
+    let mut remapped: Option<String> = None;
+    for (old, new) in ids {
+        let current = remapped.as_deref().unwrap_or(text);
+        if current.contains(old.as_str()) {
+            remapped = Some(current.replace(old.as_str(), new));
+        }
+    }
+    remapped
+}
+
+/// A value with its ids remapped, or None if it has none
+fn remap_value(value: &LoroValue, ids: &HashMap<String, String>) -> Option<LoroValue> {
+    match value {
+        LoroValue::String(text) => remap_str(text, ids).map(LoroValue::from),
+        LoroValue::List(items) => {
+            let remapped: Vec<Option<LoroValue>> =
+                items.iter().map(|item| remap_value(item, ids)).collect();
+            if remapped.iter().all(Option::is_none) {
+                return None;
+            }
+            let items: Vec<LoroValue> = items
+                .iter()
+                .zip(remapped)
+                .map(|(item, new)| new.unwrap_or_else(|| item.clone()))
+                .collect();
+            Some(LoroValue::from(items))
+        }
+        LoroValue::Map(entries) => {
+            let mut changed = false;
+            let mut remapped = HashMap::new();
+            for (key, item) in entries.iter() {
+                let new = remap_value(item, ids);
+                changed |= new.is_some();
+                remapped.insert(
+                    remap_str(key, ids).unwrap_or_else(|| key.clone()),
+                    new.unwrap_or_else(|| item.clone()),
+                );
+            }
+            if changed || remapped.keys().any(|key| !entries.contains_key(key)) {
+                Some(LoroValue::from(remapped))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn remap_map(map: &LoroMap, ids: &HashMap<String, String>) -> Result<(), String> {
+    let mut entries = Vec::new();
+    map.for_each(|key, value| entries.push((key.to_string(), value)));
+    for (key, value) in entries {
+        match value {
+            ValueOrContainer::Value(value) => {
+                let new_key = remap_str(&key, ids);
+                let new_value = remap_value(&value, ids);
+                if new_key.is_none() && new_value.is_none() {
+                    continue;
+                }
+                if new_key.is_some() {
+                    map.delete(&key).map_err(|e| e.to_string())?;
+                }
+                map.insert(
+                    new_key.as_deref().unwrap_or(&key),
+                    new_value.unwrap_or(value),
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            ValueOrContainer::Container(container) => remap_container(&container, ids)?,
+        }
+    }
+    Ok(())
+}
+
+fn remap_container(container: &Container, ids: &HashMap<String, String>) -> Result<(), String> {
+    match container {
+        Container::Map(map) => remap_map(map, ids),
+        Container::List(list) => {
+            for index in 0..list.len() {
+                match list.get(index) {
+                    Some(ValueOrContainer::Value(value)) => {
+                        if let Some(new) = remap_value(&value, ids) {
+                            list.delete(index, 1).map_err(|e| e.to_string())?;
+                            list.insert(index, new).map_err(|e| e.to_string())?;
+                        }
+                    }
+                    Some(ValueOrContainer::Container(child)) => remap_container(&child, ids)?,
+                    None => {}
+                }
+            }
+            Ok(())
+        }
+        Container::MovableList(list) => {
+            for index in 0..list.len() {
+                match list.get(index) {
+                    Some(ValueOrContainer::Value(value)) => {
+                        if let Some(new) = remap_value(&value, ids) {
+                            list.set(index, new).map_err(|e| e.to_string())?;
+                        }
+                    }
+                    Some(ValueOrContainer::Container(child)) => remap_container(&child, ids)?,
+                    None => {}
+                }
+            }
+            Ok(())
+        }
+        Container::Text(text) => {
+            let content = text.to_string();
+            // From the end, so earlier positions stay put
+            let mut matches = Vec::new();
+            for (old, new) in ids {
+                for (byte, _) in content.match_indices(old.as_str()) {
+                    let position = content[..byte].chars().count();
+                    matches.push((position, old.chars().count(), new));
+                }
+            }
+            matches.sort_by(|a, b| b.0.cmp(&a.0));
+            for (position, length, new) in matches {
+                text.splice(position, length, new)
+                    .map_err(|e| e.to_string())?;
+            }
+            Ok(())
+        }
+        Container::Tree(tree) => {
+            for node in tree.get_nodes(false) {
+                let meta = tree.get_meta(node.id).map_err(|e| e.to_string())?;
+                remap_map(&meta, ids)?;
+            }
+            Ok(())
+        }
+        #[allow(unreachable_patterns)]
+        _ => Ok(()),
+    }
+}
+
+/// Rewrite every old id in `ids` to its new one, throughout `doc`
+pub fn remap_ids(doc: &LoroDoc, ids: &HashMap<String, String>) -> Result<(), String> {
+    let roots = match doc.get_value() {
+        LoroValue::Map(roots) => roots,
+        _ => return Ok(()),
+    };
+    for (name, value) in roots.iter() {
+        let container_type = match value {
+            LoroValue::Container(id) => id.container_type(),
+            _ => continue,
+        };
+        let container = match container_type {
+            ContainerType::Map => Container::Map(doc.get_map(name.as_str())),
+            ContainerType::List => Container::List(doc.get_list(name.as_str())),
+            ContainerType::MovableList => {
+                Container::MovableList(doc.get_movable_list(name.as_str()))
+            }
+            ContainerType::Text => Container::Text(doc.get_text(name.as_str())),
+            ContainerType::Tree => Container::Tree(doc.get_tree(name.as_str())),
+            _ => continue,
+        };
+        remap_container(&container, ids)?;
+    }
+    doc.commit();
+    Ok(())
+}
+
+/// An exported document's bytes with its ids remapped
+pub fn remap_document(bytes: &[u8], ids: &HashMap<String, String>) -> Result<Vec<u8>, String> {
+    let doc = LoroDoc::new();
+    doc.import(bytes)
+        .map_err(|e| format!("Failed to import document: {}", e))?;
+    remap_ids(&doc, ids)?;
+    doc.export(ExportMode::all_updates())
+        .map_err(|e| format!("Failed to export document: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use loro::{LoroList, LoroText};
+    use wasm_bindgen_test::*;
+
+    const OLD: &str = "0b1c6a2e-4f1d-4a55-9d0e-5c2b8f3e7a11";
+    const NEW: &str = "9f8e7d6c-5b4a-4392-8170-6f5e4d3c2b1a";
+
+    fn ids() -> HashMap<String, String> {
+        let mut ids = HashMap::new();
+        ids.insert(OLD.to_string(), NEW.to_string());
+        ids
+    }
+
+    #[wasm_bindgen_test]
+    fn test_remap_ids() {
+        let doc = LoroDoc::new();
+        let meta = doc.get_map("meta");
+        meta.insert("id", OLD).unwrap();
+        meta.insert("url", format!("/assets/{}/logo.png", OLD))
+            .unwrap();
+        meta.insert("title", "Untouched").unwrap();
+
+        let tree = doc.get_tree("files");
+        let node = tree.create(None).unwrap();
+        tree.get_meta(node).unwrap().insert("id", OLD).unwrap();
+
+        let body = doc.get_map("body");
+        let text = body.insert_container("text", LoroText::new()).unwrap();
+        text.insert(0, &format!("See ![logo](/assets/{}/logo.png) — ok", OLD))
+            .unwrap();
+        let list = body.insert_container("refs", LoroList::new()).unwrap();
+        list.push(OLD).unwrap();
+        doc.commit();
+
+        remap_ids(&doc, &ids()).unwrap();
+
+        let value = serde_json::to_string(&doc.get_deep_value()).unwrap();
+        assert!(!value.contains(OLD));
+        assert_eq!(meta.get("id").unwrap().get_deep_value(), NEW.into());
+        assert_eq!(
+            text.to_string(),
+            format!("See ![logo](/assets/{}/logo.png) — ok", NEW)
+        );
+        assert!(value.contains("Untouched"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_remap_document() {
+        let doc = LoroDoc::new();
+        doc.get_map("meta").insert("id", OLD).unwrap();
+        doc.commit();
+        let bytes = doc.export(ExportMode::all_updates()).unwrap();
+
+        let remapped = remap_document(&bytes, &ids()).unwrap();
+        let copy = LoroDoc::new();
+        copy.import(&remapped).unwrap();
+        assert_eq!(
+            copy.get_map("meta").get("id").unwrap().get_deep_value(),
+            NEW.into()
+        );
+    }
+}
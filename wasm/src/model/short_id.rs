@@ -0,0 +1,59 @@
+//! Short file ids.
+//!
+//! Files get ids like `k3v9x2mq` rather than UUIDs, since ids show up in
+//! asset URLs, links and the editor. They're drawn from Crockford's base32
+//! alphabet (no `i`, `l`, `o` or `u`, so they read back unambiguously) and
+//! checked against the ids already in the project. Files created before
+//! short ids keep their UUIDs, with a short alias for addressing them.
+
+/// Lowercase Crockford base32: 32 letters, so a random byte maps evenly
+const ALPHABET: &[u8; 32] = b"0123456789abcdefghjkmnpqrstvwxyz";
+pub const SHORT_ID_LENGTH: usize = 8;
+/// Give up rather than loop forever if the random source misbehaves
+const MAX_ATTEMPTS: usize = 64;
+
+/// A random short id, not necessarily unique
+pub fn random_short_id() -> Result<String, String> {
+    let mut bytes = [0u8; SHORT_ID_LENGTH];
+    getrandom::getrandom(&mut bytes).map_err(|e| format!("Failed to generate id: {}", e))?;
+    Ok(bytes
+        .iter()
+        .map(|byte| ALPHABET[(*byte & 31) as usize] as char)
+        .collect())
+}
+
+/// A random short id for which `taken` is false
+pub fn unique_short_id(taken: impl Fn(&str) -> bool) -> Result<String, String> {
+    for _ in 0..MAX_ATTEMPTS {
+        let id = random_short_id()?;
+        if !taken(&id) {
+            return Ok(id);
+        }
+    }
+    Err("Failed to generate an unused id".to_string())
+}
+
+/// Whether an id has the short form, as opposed to a UUID
+pub fn is_short_id(id: &str) -> bool {
+    id.len() == SHORT_ID_LENGTH && id.bytes().all(|byte| ALPHABET.contains(&byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_unique_short_id() {
+        let id = random_short_id().unwrap();
+        assert!(is_short_id(&id), "{}", id);
+        assert!(!is_short_id("9b1deb4d-3b7d-4bad-9bdd-2b0d7b3dcb6d"));
+        assert!(!is_short_id("k3v9x2mo"));
+
+        let taken: HashSet<String> = (0..100).map(|_| random_short_id().unwrap()).collect();
+        let id = unique_short_id(|id| taken.contains(id)).unwrap();
+        assert!(!taken.contains(&id));
+        assert!(unique_short_id(|_| true).is_err());
+    }
+}
@@ -0,0 +1,149 @@
+//! Resolving image references in rich text to the assets they show.
+//!
+//! An image keeps the URL its asset had when it was inserted,
+//! `/assets/{id}/{name}`. Renaming the asset or moving it into a folder
+//! changes where it's published, so references are looked up by the asset
+//! id in them rather than trusted as written. Images imported with relative
+//! paths (`march.jpg`, `images/march.jpg`) are matched by file name, when
+//! only one asset has it.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use serde_json::Value;
+
+/// Maps an image `src` to where the image is published
+pub trait AssetResolver: Debug {
+    /// The URL to use instead of `src`, or None to leave it as written
+    fn resolve(&self, src: &str) -> Option<String>;
+}
+
+/// The site's assets by id and by file name
+#[derive(Debug, Clone, Default)]
+pub struct AssetIndex {
+    /// Asset id → published URL
+    by_id: HashMap<String, String>,
+    /// File name → published URL, or None if several assets share it
+    by_name: HashMap<String, Option<String>>,
+}
+
+fn is_external(src: &str) -> bool {
+    src.contains("://") || src.starts_with("//") || src.starts_with("data:")
+}
+
+/// The last segment of a path, without its query or fragment
+fn file_name(src: &str) -> &str {
+    let path = src.split(|c| c == '?' || c == '#').next().unwrap_or(src);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+impl AssetIndex {
+    /// Index asset JSON as returned by `Asset::to_json`, published at its
+    /// `path` where the store gives one and at its `url` otherwise
+    pub fn new(asset_meta: &[Value]) -> Self {
+        let mut index = AssetIndex::default();
+        for asset in asset_meta {
+            let field = |key: &str| {
+                asset
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .filter(|value| !value.is_empty())
+            };
+            let (id, url) = match (field("id"), field("path").or_else(|| field("url"))) {
+                (Some(id), Some(url)) => (id, url.to_string()),
+                _ => continue,
+            };
+            index.by_id.insert(id.to_string(), url.clone());
+            if let Some(name) = field("name") {
+                index
+                    .by_name
+                    .entry(name.to_string())
+                    .and_modify(|shared| *shared = None)
+                    .or_insert(Some(url));
+            }
+        }
+        index
+    }
+}
+
+impl AssetResolver for AssetIndex {
+    fn resolve(&self, src: &str) -> Option<String> {
+        if src.is_empty() || is_external(src) {
+            return None;
+        }
+        if let Some(path) = src.strip_prefix("/assets/") {
+            // Folders come before the id, so any segment may be it
+            let path = path.split(|c| c == '?' || c == '#').next().unwrap_or(path);
+            return path
+                .split('/')
+                .find_map(|segment| self.by_id.get(segment))
+                .filter(|url| url.as_str() != src)
+                .cloned();
+        }
+        if src.starts_with('/') {
+            return None;
+        }
+        self.by_name.get(file_name(src)).cloned().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn index() -> AssetIndex {
+        AssetIndex::new(&[
+            json!({
+                "id": "a1",
+                "name": "march-2024.jpg",
+                "url": "/assets/a1/march.jpg",
+                "path": "/assets/a1/march-2024.jpg"
+            }),
+            json!({
+                "id": "b2",
+                "name": "logo.png",
+                "url": "/assets/b2/logo.png",
+                "path": "/assets/branding/b2/logo.png"
+            }),
+            json!({ "id": "c3", "name": "map.png", "url": "/assets/c3/map.png" }),
+            json!({ "id": "d4", "name": "map.png", "url": "/assets/d4/map.png" }),
+        ])
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resolve_by_id() {
+        let index = index();
+        // Renamed since it was inserted
+        assert_eq!(
+            index.resolve("/assets/a1/march.jpg"),
+            Some("/assets/a1/march-2024.jpg".to_string())
+        );
+        // Already right, so left alone
+        assert_eq!(index.resolve("/assets/c3/map.png"), None);
+        // Moved into a folder
+        assert_eq!(
+            index.resolve("/assets/b2/logo.png"),
+            Some("/assets/branding/b2/logo.png".to_string())
+        );
+        assert_eq!(index.resolve("/assets/zz/gone.png"), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resolve_by_name() {
+        let index = index();
+        assert_eq!(
+            index.resolve("images/march-2024.jpg?w=200"),
+            Some("/assets/a1/march-2024.jpg".to_string())
+        );
+        assert_eq!(
+            index.resolve("logo.png"),
+            Some("/assets/branding/b2/logo.png".to_string())
+        );
+        // Two assets are called map.png, so neither is guessed
+        assert_eq!(index.resolve("map.png"), None);
+        assert_eq!(index.resolve("https://example.org/logo.png"), None);
+        assert_eq!(index.resolve("/about/logo.png"), None);
+    }
+}
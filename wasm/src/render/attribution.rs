@@ -0,0 +1,149 @@
+//! Credit lines for uploaded images and files.
+//!
+//! An asset's caption, credit and license are edited like its alt text and
+//! merge between peers key by key, while its bytes never change once
+//! stored. Templates print the credit with `{{attribution image}}`, given
+//! the asset's URL or id, or an object with `credit` and `license` keys.
+
+use std::collections::HashMap;
+
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext, RenderErrorReason,
+};
+use serde_json::{Map, Value};
+
+use super::html::escape_html;
+use super::rights::{license_name, license_url};
+
+fn meta_text<'a>(meta: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    meta.get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+/// `<small class="attribution">` with an asset's credit and license, or
+/// nothing if it has neither
+///
+/// Creative Commons licenses link to their deed, as do licenses given as
+/// a URL.
+pub fn attribution_html(meta: &Map<String, Value>) -> String {
+    let mut parts = Vec::new();
+    if let Some(credit) = meta_text(meta, "credit") {
+        parts.push(format!(
+            "<span class=\"credit\">{}</span>",
+            escape_html(credit)
+        ));
+    }
+    if let Some(license) = meta_text(meta, "license") {
+        parts.push(match license_url(license) {
+            Some(url) => format!(
+                "<a rel=\"license\" href=\"{}\">{}</a>",
+                escape_html(&url),
+                escape_html(&license_name(license))
+            ),
+            None => format!("<span class=\"license\">{}</span>", escape_html(license)),
+        });
+    }
+    if parts.is_empty() {
+        return String::new();
+    }
+    format!("<small class=\"attribution\">{}</small>", parts.join(", "))
+}
+
+/// `{{attribution image}}` — the credit line of an uploaded asset
+pub struct AttributionHelper {
+    /// Asset URL or id → its metadata
+    assets: HashMap<String, Map<String, Value>>,
+}
+
+impl AttributionHelper {
+    /// From asset JSON as returned by `Asset::to_json`
+    pub fn new(assets: &[Value]) -> Self {
+        let mut by_key = HashMap::new();
+        for meta in assets.iter().filter_map(Value::as_object) {
+            for key in ["id", "url"] {
+                if let Some(value) = meta_text(meta, key) {
+                    by_key.insert(value.to_string(), meta.clone());
+                }
+            }
+        }
+        AttributionHelper { assets: by_key }
+    }
+}
+
+impl HelperDef for AttributionHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let param = h
+            .param(0)
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex("attribution", 0))?;
+        // An asset the site doesn't have, or no image at all, has no credit
+        let html = match param.value() {
+            Value::String(key) => self
+                .assets
+                .get(key.trim())
+                .map(attribution_html)
+                .unwrap_or_default(),
+            Value::Object(meta) => attribution_html(meta),
+            _ => String::new(),
+        };
+        out.write(&html)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Renderer;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_attribution_html() {
+        let meta = json!({ "credit": "Ana & Co", "license": "CC-BY-4.0" });
+        assert_eq!(
+            attribution_html(meta.as_object().unwrap()),
+            "<small class=\"attribution\"><span class=\"credit\">Ana &amp; Co</span>, \
+             <a rel=\"license\" href=\"https://creativecommons.org/licenses/by/4.0/\">CC BY 4.0</a></small>"
+        );
+
+        let meta = json!({ "license": "All rights reserved" });
+        assert_eq!(
+            attribution_html(meta.as_object().unwrap()),
+            "<small class=\"attribution\"><span class=\"license\">All rights reserved</span></small>"
+        );
+        assert_eq!(attribution_html(&Map::new()), "");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_attribution_helper() {
+        let assets = vec![json!({
+            "id": "a1",
+            "url": "/assets/a1/march.jpg",
+            "credit": "Union archive",
+        })];
+        let mut renderer = Renderer::new();
+        renderer.register_helper("attribution", Box::new(AttributionHelper::new(&assets)));
+        renderer
+            .register_template(
+                "page",
+                "{{attribution image}}|{{attribution \"a1\"}}|{{attribution missing}}",
+            )
+            .unwrap();
+
+        let html = renderer
+            .render("page", &json!({ "image": "/assets/a1/march.jpg" }))
+            .unwrap();
+        let credit =
+            "<small class=\"attribution\"><span class=\"credit\">Union archive</span></small>";
+        assert_eq!(html, format!("{}|{}|", credit, credit));
+    }
+}
@@ -0,0 +1,169 @@
+//! Shared blocks referenced from pages.
+//!
+//! Instead of a copy of a snippet, a page can hold a `block_ref` node naming
+//! the snippet by id:
+//!
+//! ```text
+//! { "type": "block_ref", "attrs": { "id": "..." } }
+//! ```
+//!
+//! Refs are replaced by the snippet's current content at render time, so
+//! editing a shared block, such as a footer notice, updates every page that
+//! references it. Blocks can reference other blocks; a ref back to a block
+//! that's already being expanded is dropped and reported.
+
+use std::collections::BTreeMap;
+
+use serde_json::Value;
+
+use super::site::{page_url, PageSource};
+
+/// Node type of a reference to a shared block
+pub const BLOCK_REF_NODE: &str = "block_ref";
+
+/// Id of the block a `block_ref` node points at
+pub fn block_ref_id(node: &Value) -> Option<&str> {
+    if node.get("type").and_then(Value::as_str) != Some(BLOCK_REF_NODE) {
+        return None;
+    }
+    node.get("attrs")
+        .and_then(|attrs| attrs.get("id"))
+        .and_then(Value::as_str)
+}
+
+fn expand(
+    nodes: &[Value],
+    blocks: &BTreeMap<String, Value>,
+    expanding: &mut Vec<String>,
+    problems: &mut Vec<String>,
+) -> Vec<Value> {
+    let mut expanded = Vec::new();
+    for node in nodes {
+        if node.get("type").and_then(Value::as_str) == Some(BLOCK_REF_NODE) {
+            let id = match block_ref_id(node) {
+                Some(id) => id,
+                None => {
+                    problems.push("block reference without an id".to_string());
+                    continue;
+                }
+            };
+            if expanding.iter().any(|open| open == id) {
+                problems.push(format!("block {} references itself", id));
+                continue;
+            }
+            let content: &[Value] = match blocks.get(id).map(|block| block.get("content")) {
+                Some(Some(Value::Array(content))) => content.as_slice(),
+                Some(_) => &[],
+                None => {
+                    problems.push(format!("no shared block with id {}", id));
+                    continue;
+                }
+            };
+            expanding.push(id.to_string());
+            expanded.extend(expand(content, blocks, expanding, problems));
+            expanding.pop();
+            continue;
+        }
+
+        let mut node = node.clone();
+        if let Some(Value::Array(children)) = node.get("content") {
+            let children = expand(children, blocks, expanding, problems);
+            node["content"] = Value::Array(children);
+        }
+        expanded.push(node);
+    }
+    expanded
+}
+
+/// `body` with each `block_ref` replaced by its block's content, plus a
+/// description of each ref that couldn't be resolved
+pub fn resolve_block_refs(body: &Value, blocks: &BTreeMap<String, Value>) -> (Value, Vec<String>) {
+    let mut problems = Vec::new();
+    let resolved = expand(
+        std::slice::from_ref(body),
+        blocks,
+        &mut Vec::new(),
+        &mut problems,
+    );
+    let body = resolved.into_iter().next().unwrap_or(Value::Null);
+    (body, problems)
+}
+
+/// Every page with its block refs resolved, and warnings naming the pages
+/// whose refs couldn't be
+pub fn resolve_all_block_refs(
+    pages: &[PageSource],
+    blocks: &BTreeMap<String, Value>,
+) -> (Vec<PageSource>, Vec<String>) {
+    let mut warnings = Vec::new();
+    let resolved = pages
+        .iter()
+        .map(|page| {
+            let (body, problems) = resolve_block_refs(&page.body, blocks);
+            let url = page_url(page);
+            warnings.extend(
+                problems
+                    .into_iter()
+                    .map(|problem| format!("{}: {}", url, problem)),
+            );
+            PageSource {
+                body,
+                ..page.clone()
+            }
+        })
+        .collect();
+    (resolved, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn block_ref(id: &str) -> Value {
+        json!({ "type": "block_ref", "attrs": { "id": id } })
+    }
+
+    fn paragraph(text: &str) -> Value {
+        json!({ "type": "paragraph", "content": [{ "type": "text", "text": text }] })
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resolve_block_refs() {
+        let mut blocks = BTreeMap::new();
+        blocks.insert(
+            "notice".to_string(),
+            json!({ "type": "doc", "content": [paragraph("Closed Monday"), block_ref("sign")] }),
+        );
+        blocks.insert(
+            "sign".to_string(),
+            json!({ "type": "doc", "content": [paragraph("— The team"), block_ref("notice")] }),
+        );
+
+        let body = json!({ "type": "doc", "content": [
+            paragraph("Welcome"),
+            { "type": "blockquote", "content": [block_ref("notice")] },
+            block_ref("missing"),
+        ]});
+        let (resolved, problems) = resolve_block_refs(&body, &blocks);
+
+        assert_eq!(
+            resolved,
+            json!({ "type": "doc", "content": [
+                paragraph("Welcome"),
+                { "type": "blockquote", "content": [
+                    paragraph("Closed Monday"),
+                    paragraph("— The team"),
+                ]},
+            ]})
+        );
+        assert_eq!(
+            problems,
+            vec![
+                "block notice references itself".to_string(),
+                "no shared block with id missing".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Layout inheritance for theme templates.
+//!
+//! A layout marks the parts a template may replace as named blocks, each
+//! with default contents:
+//!
+//! ```text
+//! <main>{{#block "main"}}<p>Nothing here yet</p>{{/block}}</main>
+//! ```
+//!
+//! A template names its layout on its first line, as Ghost themes do, and
+//! overrides whichever of the layout's blocks it needs. Everything else,
+//! including new blocks of its own, fills the layout's `body` block:
+//!
+//! ```text
+//! {{!< base}}
+//! {{#block "main"}}<article>{{{content}}}</article>{{/block}}
+//! ```
+//!
+//! Layouts can extend other layouts. Inheritance is resolved before the
+//! templates are registered, so Handlebars only ever sees plain templates.
+
+use std::collections::{HashMap, HashSet};
+
+use regex::Regex;
+
+/// The block text outside a child template's blocks goes into
+pub const BODY_BLOCK: &str = "body";
+/// Layouts extending layouts beyond this are assumed to be a mistake
+const MAX_DEPTH: usize = 16;
+
+/// Template text, or a named block with its default contents
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Block { name: String, content: Vec<Segment> },
+}
+
+fn block_tag_regex() -> Regex {
+    Regex::new(r#"\{\{~?\s*(?:#block\s+"([^"]+)"|/block)\s*~?\}\}"#).expect("valid block regex")
+}
+
+fn layout_regex() -> Regex {
+    Regex::new(r"^\s*\{\{!<\s*([^\s}]+)\s*\}\}[ \t]*\r?\n?").expect("valid layout regex")
+}
+
+/// The layout a template extends, and the rest of its source
+fn split_layout(source: &str) -> (Option<String>, &str) {
+    match layout_regex().captures(source) {
+        Some(caps) => (
+            Some(caps[1].to_string()),
+            &source[caps.get(0).map_or(0, |m| m.end())..],
+        ),
+        None => (None, source),
+    }
+}
+
+/// The layout a template extends, if it names one
+pub fn layout_of(source: &str) -> Option<String> {
+    split_layout(source).0
+}
+
+fn parse_segments(source: &str) -> Result<Vec<Segment>, String> {
+    // Each open block: its name and the segments before it
+    let mut stack: Vec<(String, Vec<Segment>)> = Vec::new();
+    let mut current = Vec::new();
+    let mut last = 0;
+    for caps in block_tag_regex().captures_iter(source) {
+        let tag = caps.get(0).expect("whole match");
+        if tag.start() > last {
+            current.push(Segment::Text(source[last..tag.start()].to_string()));
+        }
+        last = tag.end();
+
+        match caps.get(1) {
+            Some(name) => {
+                stack.push((name.as_str().to_string(), current));
+                current = Vec::new();
+            }
+            None => {
+                let (name, mut outer) = stack.pop().ok_or("{{/block}} without an opening block")?;
+                outer.push(Segment::Block {
+                    name,
+                    content: current,
+                });
+                current = outer;
+            }
+        }
+    }
+    if let Some((name, _)) = stack.last() {
+        return Err(format!("Block \"{}\" is never closed", name));
+    }
+    if last < source.len() {
+        current.push(Segment::Text(source[last..].to_string()));
+    }
+    Ok(current)
+}
+
+fn write_segments(
+    segments: &[Segment],
+    overrides: &HashMap<String, Vec<Segment>>,
+    output: &mut String,
+) {
+    for segment in segments {
+        match segment {
+            Segment::Text(text) => output.push_str(text),
+            Segment::Block { name, content } => {
+                let content = overrides.get(name).unwrap_or(content);
+                write_segments(content, overrides, output);
+            }
+        }
+    }
+}
+
+fn collect_block_names(segments: &[Segment], names: &mut HashSet<String>) {
+    for segment in segments {
+        if let Segment::Block { name, content } = segment {
+            names.insert(name.clone());
+            collect_block_names(content, names);
+        }
+    }
+}
+
+/// Names of the blocks a layout and the layouts it extends declare
+fn declared_blocks(layout: &str, templates: &HashMap<&str, &str>) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let mut next = Some(layout.to_string());
+    for _ in 0..MAX_DEPTH {
+        let source = match next.as_deref().and_then(|name| templates.get(name)) {
+            Some(source) => source,
+            None => break,
+        };
+        let (layout, rest) = split_layout(source);
+        if let Ok(segments) = parse_segments(rest) {
+            collect_block_names(&segments, &mut names);
+        }
+        next = layout;
+    }
+    names
+}
+
+/// A template with its layouts applied, as plain Handlebars
+fn resolve(
+    name: &str,
+    templates: &HashMap<&str, &str>,
+    mut overrides: HashMap<String, Vec<Segment>>,
+    chain: &mut Vec<String>,
+) -> Result<String, String> {
+    if chain.iter().any(|seen| seen == name) {
+        chain.push(name.to_string());
+        return Err(format!("Layouts extend each other: {}", chain.join(" → ")));
+    }
+    if chain.len() >= MAX_DEPTH {
+        return Err(format!("Layouts are nested more than {} deep", MAX_DEPTH));
+    }
+    chain.push(name.to_string());
+
+    let source = templates
+        .get(name)
+        .ok_or_else(|| format!("Layout not found: {}", name))?;
+    let (layout, rest) = split_layout(source);
+    let segments = parse_segments(rest)?;
+
+    let layout = match layout {
+        Some(layout) => layout,
+        None => {
+            let mut output = String::new();
+            write_segments(&segments, &overrides, &mut output);
+            return Ok(output);
+        }
+    };
+
+    // Blocks the layouts declare are overrides, and those from templates
+    // further down the chain win; any other block is new, and stays put
+    let declared = declared_blocks(&layout, templates);
+    let mut body = Vec::new();
+    for segment in segments {
+        match segment {
+            Segment::Block { name, content } if declared.contains(&name) => {
+                overrides.entry(name).or_insert(content);
+            }
+            segment => body.push(segment),
+        }
+    }
+    let has_body = body.iter().any(|segment| match segment {
+        Segment::Text(text) => !text.trim().is_empty(),
+        Segment::Block { .. } => true,
+    });
+    if has_body {
+        overrides.entry(BODY_BLOCK.to_string()).or_insert(body);
+    }
+    resolve(&layout, templates, overrides, chain)
+}
+
+/// Apply layouts to every template that extends one
+///
+/// Templates that can't be resolved are kept as they are, with a warning.
+pub fn resolve_layouts(templates: &[(String, String)]) -> (Vec<(String, String)>, Vec<String>) {
+    let by_name: HashMap<&str, &str> = templates
+        .iter()
+        .map(|(name, source)| (name.as_str(), source.as_str()))
+        .collect();
+    let tags = block_tag_regex();
+
+    let mut resolved = Vec::new();
+    let mut warnings = Vec::new();
+    for (name, source) in templates {
+        let (layout, _) = split_layout(source);
+        if layout.is_none() && !tags.is_match(source) {
+            resolved.push((name.clone(), source.clone()));
+            continue;
+        }
+        match resolve(name, &by_name, HashMap::new(), &mut Vec::new()) {
+            Ok(source) => resolved.push((name.clone(), source)),
+            Err(e) => {
+                warnings.push(format!("template {}: {}", name, e));
+                resolved.push((name.clone(), source.clone()));
+            }
+        }
+    }
+    (resolved, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn templates(sources: &[(&str, &str)]) -> Vec<(String, String)> {
+        sources
+            .iter()
+            .map(|(name, source)| (name.to_string(), source.to_string()))
+            .collect()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resolve_layouts() {
+        let (resolved, warnings) = resolve_layouts(&templates(&[
+            (
+                "base",
+                "<title>{{#block \"title\"}}{{site.name}}{{/block}}</title>\
+                 <main>{{#block \"body\"}}{{/block}}</main>\
+                 <footer>{{#block \"footer\"}}Made by hand{{/block}}</footer>",
+            ),
+            (
+                "article",
+                "{{!< base}}\n\
+                 {{#block \"title\"}}{{title}}{{/block}}\
+                 <article>{{#block \"article\"}}{{{content}}}{{/block}}</article>",
+            ),
+            (
+                "post",
+                "{{!< article}}\n\
+                 {{#block \"article\"}}<time>{{date}}</time>{{{content}}}{{/block}}\
+                 {{#block \"footer\"}}Filed under {{tags}}{{/block}}",
+            ),
+        ]));
+
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(
+            resolved[0].1,
+            "<title>{{site.name}}</title><main></main><footer>Made by hand</footer>"
+        );
+        assert_eq!(
+            resolved[1].1,
+            "<title>{{title}}</title><main><article>{{{content}}}</article></main>\
+             <footer>Made by hand</footer>"
+        );
+        assert_eq!(
+            resolved[2].1,
+            "<title>{{title}}</title>\
+             <main><article><time>{{date}}</time>{{{content}}}</article></main>\
+             <footer>Filed under {{tags}}</footer>"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resolve_layouts_problems() {
+        let (resolved, warnings) = resolve_layouts(&templates(&[
+            ("plain", "<p>{{title}}</p>"),
+            ("a", "{{!< b}}\nA"),
+            ("b", "{{!< a}}\nB"),
+            ("orphan", "{{!< missing}}\n<p>Hi</p>"),
+            ("open", "{{#block \"main\"}}never closed"),
+        ]));
+
+        assert_eq!(resolved[0].1, "<p>{{title}}</p>");
+        assert_eq!(resolved[3].1, "{{!< missing}}\n<p>Hi</p>");
+        assert_eq!(
+            warnings,
+            vec![
+                "template a: Layouts extend each other: a → b → a",
+                "template b: Layouts extend each other: b → a → b",
+                "template orphan: Layout not found: missing",
+                "template open: Block \"main\" is never closed",
+            ]
+        );
+    }
+}
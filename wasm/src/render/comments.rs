@@ -0,0 +1,210 @@
+//! Comments on published pages.
+//!
+//! Nothing is hosted by the exported site: webmentions are collected by
+//! webmention.io and fetched by the reader's browser, and third-party
+//! providers are embedded from a snippet. Templates place them with
+//! `{{comments}}` (in the body) and `{{comments_head}}` (in `<head>`).
+
+use handlebars::{Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext};
+use serde_json::{Map, Value};
+
+use super::html::escape_html;
+use crate::types::{CommentsConfig, CommentsProvider};
+
+/// Context key set on each page to say whether it shows comments
+pub const COMMENTS_ENABLED_KEY: &str = "comments_enabled";
+
+/// Whether a page shows comments, from its `comments` meta field
+///
+/// The field may be a bool or the strings "true"/"false" (as set through
+/// `FileUpdate::SetField`); without it, posts follow the site default and
+/// pages stay off.
+pub fn comments_enabled(
+    config: &CommentsConfig,
+    collection: &str,
+    meta: &Map<String, Value>,
+) -> bool {
+    match meta.get("comments") {
+        Some(Value::Bool(enabled)) => *enabled,
+        Some(Value::String(enabled)) if enabled == "true" => true,
+        Some(Value::String(enabled)) if enabled == "false" => false,
+        _ => collection == "post" && config.posts_by_default,
+    }
+}
+
+/// Settings the comments provider needs before it can work
+pub fn missing_settings(config: &CommentsConfig, site_url: Option<&str>) -> Vec<String> {
+    let mut missing = Vec::new();
+    match config.provider {
+        CommentsProvider::Webmention => {
+            if config.webmention_domain.as_deref().unwrap_or("").is_empty() {
+                missing.push("webmentions need a webmention.io domain".to_string());
+            }
+            if site_url.is_none() {
+                missing.push("webmentions need the site URL".to_string());
+            }
+        }
+        CommentsProvider::Snippet => {
+            if config.snippet.as_deref().unwrap_or("").trim().is_empty() {
+                missing.push("the comments snippet is empty".to_string());
+            }
+        }
+    }
+    missing
+}
+
+/// `<link>` tags advertising the webmention endpoint
+pub fn comments_head(config: &CommentsConfig) -> String {
+    match (config.provider, config.webmention_domain.as_deref()) {
+        (CommentsProvider::Webmention, Some(domain)) if !domain.is_empty() => {
+            let domain = escape_html(domain);
+            format!(
+                "<link rel=\"webmention\" href=\"https://webmention.io/{}/webmention\">\
+                 <link rel=\"pingback\" href=\"https://webmention.io/{}/xmlrpc\">",
+                domain, domain
+            )
+        }
+        _ => String::new(),
+    }
+}
+
+/// Responses to a page, fetched from webmention.io when it is read
+pub fn webmentions_html(target: &str) -> String {
+    format!(
+        "<section class=\"comments webmentions\" data-target=\"{}\">\
+         <h2>Responses</h2><ol class=\"webmentions-list\"></ol>\
+         <script>(function(){{\
+         var s=document.currentScript.parentNode,l=s.querySelector('ol');\
+         fetch('https://webmention.io/api/mentions.jf2?target='+encodeURIComponent(s.dataset.target))\
+         .then(function(r){{return r.json()}}).then(function(d){{(d.children||[]).forEach(function(m){{\
+         var li=document.createElement('li'),a=document.createElement('a');\
+         a.href=m.url;a.textContent=(m.author&&m.author.name)||m.url;li.appendChild(a);\
+         if(m.content&&m.content.text){{var p=document.createElement('p');p.textContent=m.content.text;li.appendChild(p)}}\
+         l.appendChild(li)}})}})}})();</script></section>",
+        escape_html(target)
+    )
+}
+
+/// `{{comments}}` and `{{comments_head}}`
+pub struct CommentsHelper {
+    config: CommentsConfig,
+    site_url: Option<String>,
+    head: bool,
+}
+
+impl CommentsHelper {
+    pub fn body(config: &CommentsConfig, site_url: Option<&str>) -> Self {
+        CommentsHelper {
+            config: config.clone(),
+            site_url: site_url.map(|url| url.trim_end_matches('/').to_string()),
+            head: false,
+        }
+    }
+
+    pub fn head(config: &CommentsConfig) -> Self {
+        CommentsHelper {
+            config: config.clone(),
+            site_url: None,
+            head: true,
+        }
+    }
+}
+
+impl HelperDef for CommentsHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        _: &Helper<'rc>,
+        r: &'reg Handlebars<'reg>,
+        ctx: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let page = ctx.data();
+        if page.get(COMMENTS_ENABLED_KEY).and_then(Value::as_bool) != Some(true) {
+            return Ok(());
+        }
+        if self.head {
+            out.write(&comments_head(&self.config))?;
+            return Ok(());
+        }
+
+        match self.config.provider {
+            CommentsProvider::Webmention => {
+                let url = page.get("url").and_then(Value::as_str).unwrap_or("");
+                let target = match &self.site_url {
+                    Some(site_url) => format!("{}{}", site_url, url),
+                    None => url.to_string(),
+                };
+                out.write(&webmentions_html(&target))?;
+            }
+            CommentsProvider::Snippet => {
+                if let Some(snippet) = &self.config.snippet {
+                    let html = r.render_template(snippet, page)?;
+                    out.write(&html)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Renderer;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn config(provider: CommentsProvider) -> CommentsConfig {
+        CommentsConfig {
+            provider,
+            posts_by_default: true,
+            webmention_domain: Some("zine.example".to_string()),
+            snippet: Some("<div data-page=\"{{url}}\"></div>".to_string()),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_comments_enabled() {
+        let config = config(CommentsProvider::Webmention);
+        let mut meta = Map::new();
+        assert!(comments_enabled(&config, "post", &meta));
+        assert!(!comments_enabled(&config, "page", &meta));
+
+        meta.insert("comments".to_string(), json!("false"));
+        assert!(!comments_enabled(&config, "post", &meta));
+        meta.insert("comments".to_string(), json!(true));
+        assert!(comments_enabled(&config, "page", &meta));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_comments_helpers() {
+        let mut renderer = Renderer::new();
+        let snippet = config(CommentsProvider::Snippet);
+        renderer.register_helper("comments", Box::new(CommentsHelper::body(&snippet, None)));
+        renderer
+            .register_template("post", "<main>{{comments}}</main>")
+            .unwrap();
+
+        let html = renderer
+            .render("post", &json!({ "url": "/posts/hi/", COMMENTS_ENABLED_KEY: true }))
+            .unwrap();
+        assert_eq!(html, "<main><div data-page=\"/posts/hi/\"></div></main>");
+
+        let html = renderer
+            .render("post", &json!({ "url": "/posts/hi/", COMMENTS_ENABLED_KEY: false }))
+            .unwrap();
+        assert_eq!(html, "<main></main>");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_webmention_settings() {
+        let mut config = config(CommentsProvider::Webmention);
+        assert!(comments_head(&config).contains("https://webmention.io/zine.example/webmention"));
+        assert!(missing_settings(&config, Some("https://zine.example")).is_empty());
+
+        config.webmention_domain = None;
+        assert_eq!(comments_head(&config), "");
+        assert_eq!(missing_settings(&config, None).len(), 2);
+    }
+}
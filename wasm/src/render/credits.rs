@@ -0,0 +1,94 @@
+//! Credits for the people behind a site.
+//!
+//! The site's collaborators are written to `humans.txt` and given to every
+//! template as `credits`, so a theme can have a credits page or partial
+//! listing who wrote, edited and drew what.
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+use crate::types::Collaborator;
+
+pub const HUMANS_URL: &str = "/humans.txt";
+
+/// `humans.txt` (humanstxt.org) listing everyone who worked on the site
+pub fn humans_txt(collaborators: &[Collaborator], generated_at: &DateTime<Utc>) -> String {
+    let mut text = String::from("/* TEAM */\n");
+    for collaborator in collaborators {
+        text.push_str(&format!("\n    Name: {}\n", one_line(&collaborator.name)));
+        if !collaborator.roles.is_empty() {
+            let roles: Vec<String> = collaborator.roles.iter().map(|r| one_line(r)).collect();
+            text.push_str(&format!("    Role: {}\n", roles.join(", ")));
+        }
+        for link in &collaborator.links {
+            text.push_str(&format!("    Link: {}\n", one_line(link)));
+        }
+    }
+    text.push_str(&format!(
+        "\n/* SITE */\n\n    Last update: {}\n    Software: Organ Pages\n",
+        generated_at.format("%Y/%m/%d")
+    ));
+    text
+}
+
+/// Text on one line, for a plain-text field
+fn one_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The `credits` templates see: each collaborator's name, roles (and the
+/// roles joined as `role`) and links
+pub fn credits_context(collaborators: &[Collaborator]) -> Value {
+    json!(collaborators
+        .iter()
+        .map(|collaborator| json!({
+            "name": collaborator.name,
+            "roles": collaborator.roles,
+            "role": collaborator.roles.join(", "),
+            "links": collaborator.links,
+        }))
+        .collect::<Vec<Value>>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use wasm_bindgen_test::*;
+
+    fn collaborators() -> Vec<Collaborator> {
+        vec![
+            Collaborator {
+                id: "c1".to_string(),
+                name: "Ana  Ruiz".to_string(),
+                roles: vec!["Editor".to_string(), "Layout".to_string()],
+                links: vec!["https://ana.example".to_string()],
+            },
+            Collaborator {
+                id: "c2".to_string(),
+                name: "Bo".to_string(),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[wasm_bindgen_test]
+    fn test_humans_txt() {
+        let at = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+        assert_eq!(
+            humans_txt(&collaborators(), &at),
+            "/* TEAM */\n\
+             \n    Name: Ana Ruiz\n    Role: Editor, Layout\n    Link: https://ana.example\n\
+             \n    Name: Bo\n\
+             \n/* SITE */\n\n    Last update: 2025/03/01\n    Software: Organ Pages\n"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_credits_context() {
+        let credits = credits_context(&collaborators());
+        assert_eq!(credits[0]["role"], "Editor, Layout");
+        assert_eq!(credits[1]["links"], json!([]));
+        assert!(credits[0].get("id").is_none());
+    }
+}
@@ -0,0 +1,577 @@
+//! Cross-checking a theme's stylesheets against its markup.
+//!
+//! Class and id selectors are read from each stylesheet and compared with
+//! the `class` and `id` attributes in templates, partials and rendered
+//! pages. Selectors naming a class or id that appears nowhere can't match
+//! anything; classes templates use that no stylesheet mentions are probably
+//! typos. Classes added by scripts or built from template expressions
+//! (`class="{{kind}}"`) can't be seen, so treat the report as a hint.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use regex::Regex;
+use serde::Serialize;
+
+use super::site::SiteSource;
+
+/// At-rules whose blocks hold ordinary rules
+const GROUPING_AT_RULES: [&str; 5] = ["@media", "@supports", "@layer", "@container", "@document"];
+
+/// A selector in a stylesheet
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CssSelector {
+    pub stylesheet: String,
+    pub selector: String,
+    /// Classes (`.name`) and ids (`#name`) it requires
+    pub classes: Vec<String>,
+    pub ids: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnusedSelector {
+    pub stylesheet: String,
+    pub selector: String,
+    /// The `.class`es and `#id`s no markup uses
+    pub missing: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UndefinedClass {
+    pub class: String,
+    /// Templates and partials using it
+    pub used_in: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CssReport {
+    pub selectors: usize,
+    pub unused_selectors: Vec<UnusedSelector>,
+    pub undefined_classes: Vec<UndefinedClass>,
+}
+
+/// CSS with comments, and string contents if `blank_strings`, blanked out
+fn strip_comments(css: &str, blank_strings: bool) -> String {
+    let mut stripped = String::with_capacity(css.len());
+    let mut chars = css.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                stripped.push(' ');
+            }
+            '"' | '\'' => {
+                let quote = c;
+                let mut contents = String::new();
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    if escaped {
+                        escaped = false;
+                    } else if c == '\\' {
+                        escaped = true;
+                    } else if c == quote {
+                        break;
+                    }
+                    contents.push(c);
+                }
+                stripped.push(quote);
+                if !blank_strings {
+                    stripped.push_str(&contents);
+                }
+                stripped.push(quote);
+            }
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+fn name_regex(sigil: &str) -> Regex {
+    Regex::new(&format!(r"{}(-?[_a-zA-Z][_a-zA-Z0-9-]*)", sigil)).expect("valid name regex")
+}
+
+/// Every selector in a stylesheet, with the classes and ids it names
+///
+/// Rules inside `@media` and similar blocks are included; keyframes,
+/// font faces and declarations are skipped.
+pub fn css_selectors(stylesheet: &str, css: &str) -> Vec<CssSelector> {
+    let class = name_regex(r"\.");
+    let id = name_regex("#");
+    let css = strip_comments(css, true);
+
+    let mut selectors = Vec::new();
+    // Whether each open block holds rules (true) or declarations (false)
+    let mut blocks: Vec<bool> = Vec::new();
+    let mut prelude = String::new();
+    for c in css.chars() {
+        match c {
+            '{' => {
+                let text = prelude.trim().to_string();
+                prelude.clear();
+                let in_rules = blocks.last().copied().unwrap_or(true);
+                if !in_rules {
+                    blocks.push(false);
+                    continue;
+                }
+                if text.starts_with('@') {
+                    let name = text.split_whitespace().next().unwrap_or("");
+                    blocks.push(GROUPING_AT_RULES.contains(&name.to_lowercase().as_str()));
+                    continue;
+                }
+                blocks.push(false);
+                for selector in text.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+                    let names = |regex: &Regex| -> Vec<String> {
+                        let mut names: Vec<String> = Vec::new();
+                        for caps in regex.captures_iter(selector) {
+                            if !names.contains(&caps[1].to_string()) {
+                                names.push(caps[1].to_string());
+                            }
+                        }
+                        names
+                    };
+                    selectors.push(CssSelector {
+                        stylesheet: stylesheet.to_string(),
+                        selector: selector.split_whitespace().collect::<Vec<_>>().join(" "),
+                        classes: names(&class),
+                        ids: names(&id),
+                    });
+                }
+            }
+            '}' => {
+                blocks.pop();
+                prelude.clear();
+            }
+            ';' => prelude.clear(),
+            _ => prelude.push(c),
+        }
+    }
+    selectors
+}
+
+/// Class names and ids set in markup, ignoring template expressions
+pub fn markup_names(html: &str) -> (BTreeSet<String>, BTreeSet<String>) {
+    let attribute = Regex::new(r#"(?i)\b(class|id)\s*=\s*(?:"([^"]*)"|'([^']*)')"#)
+        .expect("valid attribute regex");
+    let expression = Regex::new(r"\{\{[^}]*\}\}").expect("valid expression regex");
+
+    let mut classes = BTreeSet::new();
+    let mut ids = BTreeSet::new();
+    for caps in attribute.captures_iter(html) {
+        let value = caps
+            .get(2)
+            .or_else(|| caps.get(3))
+            .map_or("", |v| v.as_str());
+        let value = expression.replace_all(value, " ");
+        let names = value.split_whitespace().map(str::to_string);
+        if caps[1].eq_ignore_ascii_case("class") {
+            classes.extend(names);
+        } else {
+            ids.extend(names);
+        }
+    }
+    (classes, ids)
+}
+
+/// Compare stylesheets with the markup that uses them
+///
+/// `templates` are theme templates and partials by name; `rendered` is
+/// output HTML, which also carries classes from content and the renderer.
+pub fn css_report(
+    styles: &[(String, String)],
+    templates: &[(String, String)],
+    rendered: &[&str],
+) -> CssReport {
+    let selectors: Vec<CssSelector> = styles
+        .iter()
+        .flat_map(|(name, css)| css_selectors(name, css))
+        .collect();
+
+    let mut used_classes = BTreeSet::new();
+    let mut used_ids = BTreeSet::new();
+    let mut template_classes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (name, template) in templates {
+        let (classes, ids) = markup_names(template);
+        for class in &classes {
+            template_classes
+                .entry(class.clone())
+                .or_default()
+                .push(name.clone());
+        }
+        used_classes.extend(classes);
+        used_ids.extend(ids);
+    }
+    for html in rendered {
+        let (classes, ids) = markup_names(html);
+        used_classes.extend(classes);
+        used_ids.extend(ids);
+    }
+
+    let unused_selectors = selectors
+        .iter()
+        .filter_map(|selector| {
+            let missing: Vec<String> = selector
+                .classes
+                .iter()
+                .filter(|class| !used_classes.contains(*class))
+                .map(|class| format!(".{}", class))
+                .chain(
+                    selector
+                        .ids
+                        .iter()
+                        .filter(|id| !used_ids.contains(*id))
+                        .map(|id| format!("#{}", id)),
+                )
+                .collect();
+            if missing.is_empty() {
+                return None;
+            }
+            Some(UnusedSelector {
+                stylesheet: selector.stylesheet.clone(),
+                selector: selector.selector.clone(),
+                missing,
+            })
+        })
+        .collect();
+
+    let defined: BTreeSet<&String> = selectors
+        .iter()
+        .flat_map(|selector| &selector.classes)
+        .collect();
+    let undefined_classes = template_classes
+        .into_iter()
+        .filter(|(class, _)| !defined.contains(class))
+        .map(|(class, used_in)| UndefinedClass { class, used_in })
+        .collect();
+
+    CssReport {
+        selectors: selectors.len(),
+        unused_selectors,
+        undefined_classes,
+    }
+}
+
+/// What kind of control a theme variable needs
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VariableKind {
+    Color,
+    Length,
+    Number,
+    Font,
+    Other,
+}
+
+/// A custom property (`--primary: #c33`) declared in a theme stylesheet
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ThemeVariable {
+    /// Including the leading `--`
+    pub name: String,
+    /// The theme's value
+    pub default: String,
+    /// The site's override, or the theme's value
+    pub value: String,
+    pub kind: VariableKind,
+    pub stylesheet: String,
+    pub selector: String,
+}
+
+const COLOR_FUNCTIONS: [&str; 8] = [
+    "rgb(", "rgba(", "hsl(", "hsla(", "hwb(", "lab(", "lch(", "oklch(",
+];
+const LENGTH_UNITS: [&str; 10] = ["px", "rem", "em", "%", "vh", "vw", "ch", "ex", "pt", "fr"];
+const GENERIC_FONTS: [&str; 5] = ["serif", "sans-serif", "monospace", "cursive", "system-ui"];
+
+/// Guess the kind of a value, so the UI can pick a colour picker, etc.
+pub fn variable_kind(value: &str) -> VariableKind {
+    let value = value.trim();
+    let lower = value.to_lowercase();
+    let is_hex = value.starts_with('#')
+        && [4, 5, 7, 9].contains(&value.len())
+        && value[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if is_hex || COLOR_FUNCTIONS.iter().any(|f| lower.starts_with(f)) {
+        return VariableKind::Color;
+    }
+    if value.parse::<f64>().is_ok() {
+        return VariableKind::Number;
+    }
+    let is_length = LENGTH_UNITS.iter().any(|unit| {
+        lower
+            .strip_suffix(unit)
+            .map_or(false, |number| number.parse::<f64>().is_ok())
+    });
+    if is_length {
+        return VariableKind::Length;
+    }
+    let is_font = value.starts_with('"')
+        || value.starts_with('\'')
+        || lower
+            .split(',')
+            .any(|family| GENERIC_FONTS.contains(&family.trim()));
+    if is_font {
+        return VariableKind::Font;
+    }
+    VariableKind::Other
+}
+
+/// Custom property declarations in a stylesheet, in order, as
+/// (name, value, selector)
+pub fn custom_properties(css: &str) -> Vec<(String, String, String)> {
+    let css = strip_comments(css, false);
+    let mut properties = Vec::new();
+    let mut selectors: Vec<String> = Vec::new();
+    let mut text = String::new();
+    let mut quote: Option<char> = None;
+    for c in css.chars() {
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            }
+            text.push(c);
+            continue;
+        }
+        match c {
+            '"' | '\'' => {
+                quote = Some(c);
+                text.push(c);
+            }
+            '{' => {
+                selectors.push(text.split_whitespace().collect::<Vec<_>>().join(" "));
+                text.clear();
+            }
+            ';' | '}' => {
+                let declaration = text.trim();
+                if declaration.starts_with("--") {
+                    if let Some((name, value)) = declaration.split_once(':') {
+                        properties.push((
+                            name.trim().to_string(),
+                            value.trim().to_string(),
+                            selectors.last().cloned().unwrap_or_default(),
+                        ));
+                    }
+                }
+                text.clear();
+                if c == '}' {
+                    selectors.pop();
+                }
+            }
+            _ => text.push(c),
+        }
+    }
+    properties
+}
+
+/// Whether an override can go in a stylesheet without escaping its rule
+fn is_safe_override(name: &str, value: &str) -> bool {
+    name.len() > 2
+        && name.starts_with("--")
+        && name[2..]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !value.trim().is_empty()
+        && !value.contains(|c| matches!(c, '{' | '}' | ';' | '<'))
+}
+
+/// Every variable the theme declares, with the site's overrides applied
+///
+/// A variable declared more than once is listed where it's first declared
+/// on `:root`, or failing that where it's first declared.
+pub fn theme_variables(source: &SiteSource) -> Vec<ThemeVariable> {
+    let mut variables: Vec<ThemeVariable> = Vec::new();
+    for (stylesheet, css) in &source.styles {
+        for (name, default, selector) in custom_properties(css) {
+            let existing = variables.iter().position(|v| v.name == name);
+            let on_root = selector == ":root";
+            match existing {
+                Some(index) if on_root && variables[index].selector != ":root" => {
+                    variables.remove(index);
+                }
+                Some(_) => continue,
+                None => {}
+            }
+            let value = source
+                .settings
+                .theme_variables
+                .get(&name)
+                .filter(|value| is_safe_override(&name, value))
+                .cloned()
+                .unwrap_or_else(|| default.clone());
+            variables.push(ThemeVariable {
+                kind: variable_kind(&default),
+                name,
+                default,
+                value,
+                stylesheet: stylesheet.clone(),
+                selector,
+            });
+        }
+    }
+    variables
+}
+
+/// A `:root` rule setting the site's overrides, appended to stylesheets
+///
+/// Returns the rule (empty without overrides) and a warning for each
+/// override that was skipped.
+pub fn variable_overrides_css(overrides: &BTreeMap<String, String>) -> (String, Vec<String>) {
+    let mut warnings = Vec::new();
+    let mut declarations = String::new();
+    for (name, value) in overrides {
+        if is_safe_override(name, value) {
+            declarations.push_str(&format!("  {}: {};\n", name, value.trim()));
+        } else {
+            warnings.push(format!("theme variable {}: invalid name or value", name));
+        }
+    }
+    if declarations.is_empty() {
+        return (String::new(), warnings);
+    }
+    (format!("\n:root {{\n{}}}\n", declarations), warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_css_selectors() {
+        let css = r#"
+            /* .commented { } */
+            body { color: #333; background: url("a.b#c.png") }
+            .post-title, #main > .nav a:hover { margin: 0 }
+            @media (max-width: 40em) { .nav.open { display: block } }
+            @keyframes fade { from { opacity: 0 } to { opacity: 1 } }
+            @font-face { font-family: "Zine"; src: url(zine.woff2) }
+        "#;
+        let selectors = css_selectors("style", css);
+        let summary: Vec<(&str, Vec<String>, Vec<String>)> = selectors
+            .iter()
+            .map(|s| (s.selector.as_str(), s.classes.clone(), s.ids.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("body", vec![], vec![]),
+                (".post-title", vec!["post-title".to_string()], vec![]),
+                (
+                    "#main > .nav a:hover",
+                    vec!["nav".to_string()],
+                    vec!["main".to_string()]
+                ),
+                (
+                    ".nav.open",
+                    vec!["nav".to_string(), "open".to_string()],
+                    vec![]
+                ),
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_css_report() {
+        let styles = vec![(
+            "style".to_string(),
+            ".nav { } .nav.open { } #main { } .heading-link { } .old-banner { }".to_string(),
+        )];
+        let templates = vec![
+            (
+                "index".to_string(),
+                r#"<main id="main"><nav class="nav {{#if open}}open{{/if}}"></nav>
+                   <p class="byline {{kind}}"></p></main>"#
+                    .to_string(),
+            ),
+            (
+                "footer".to_string(),
+                r#"<p class='byline'></p>"#.to_string(),
+            ),
+        ];
+        let rendered = vec![r##"<h2 id="x">X<a class="heading-link" href="#x">#</a></h2>"##];
+
+        let report = css_report(&styles, &templates, &rendered);
+        assert_eq!(report.selectors, 5);
+        assert_eq!(
+            report.unused_selectors,
+            vec![UnusedSelector {
+                stylesheet: "style".to_string(),
+                selector: ".old-banner".to_string(),
+                missing: vec![".old-banner".to_string()],
+            }]
+        );
+        assert_eq!(
+            report.undefined_classes,
+            vec![UndefinedClass {
+                class: "byline".to_string(),
+                used_in: vec!["index".to_string(), "footer".to_string()],
+            }]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_theme_variables() {
+        let css = r#"
+            /* --commented: 1px; */
+            :root { --primary: #c33; --font: "Zine Sans", sans-serif; --gap: 1.5rem }
+            .dark { --primary: rgb(0 0 0); --ratio: 1.25; }
+            @media (min-width: 40em) { :root { --gap: 2rem; --shadow: 0 1px red } }
+        "#;
+        let mut source = SiteSource::default();
+        source.styles = vec![("style".to_string(), css.to_string())];
+        source
+            .settings
+            .theme_variables
+            .insert("--primary".to_string(), "#0a7".to_string());
+
+        let variables = theme_variables(&source);
+        let summary: Vec<(&str, &str, &str, VariableKind)> = variables
+            .iter()
+            .map(|v| {
+                (
+                    v.name.as_str(),
+                    v.value.as_str(),
+                    v.selector.as_str(),
+                    v.kind,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("--primary", "#0a7", ":root", VariableKind::Color),
+                (
+                    "--font",
+                    "\"Zine Sans\", sans-serif",
+                    ":root",
+                    VariableKind::Font
+                ),
+                ("--gap", "1.5rem", ":root", VariableKind::Length),
+                ("--ratio", "1.25", ".dark", VariableKind::Number),
+                ("--shadow", "0 1px red", ":root", VariableKind::Other),
+            ]
+        );
+        assert_eq!(variables[0].default, "#c33");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_variable_overrides_css() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert("--primary".to_string(), "#0a7".to_string());
+        overrides.insert(
+            "--evil".to_string(),
+            "red } body { display: none".to_string(),
+        );
+
+        let (css, warnings) = variable_overrides_css(&overrides);
+        assert_eq!(css, "\n:root {\n  --primary: #0a7;\n}\n");
+        assert_eq!(
+            warnings,
+            vec!["theme variable --evil: invalid name or value"]
+        );
+        assert_eq!(variable_overrides_css(&BTreeMap::new()).0, "");
+    }
+}
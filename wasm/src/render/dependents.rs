@@ -0,0 +1,214 @@
+//! What would break if a theme file or asset were deleted.
+//!
+//! Deleting a template leaves its pages unrendered, deleting a partial
+//! breaks every template including it, and deleting an asset leaves broken
+//! images and links. These checks list the dependents so the UI can ask
+//! before going ahead.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::site::{page_url, SiteSource};
+use super::usage::template_usage;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Dependent {
+    /// "page", "post", "listing", "template", "partial", "style", or an entry's collection
+    pub kind: String,
+    pub name: String,
+    /// Where the dependent is published, when it has its own page
+    pub url: Option<String>,
+}
+
+impl Dependent {
+    fn new(kind: &str, name: &str, url: Option<String>) -> Self {
+        Dependent {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            url,
+        }
+    }
+}
+
+/// Pages, posts and listings rendered with a template, and templates
+/// extending it as a layout
+pub fn template_dependents(source: &SiteSource, template: &str) -> Vec<Dependent> {
+    let usage = template_usage(source);
+    let usage = match usage.templates.iter().find(|usage| usage.name == template) {
+        Some(usage) => usage,
+        None => return Vec::new(),
+    };
+    let pages = usage
+        .pages
+        .iter()
+        .map(|page| Dependent::new(&page.collection, &page.name, Some(page.url.clone())));
+    let listings = usage
+        .listings
+        .iter()
+        .map(|collection| Dependent::new("listing", collection, None));
+    let children = usage
+        .extended_by
+        .iter()
+        .map(|name| Dependent::new("template", name, None));
+    pages.chain(listings).chain(children).collect()
+}
+
+/// Templates and partials that include a partial
+pub fn partial_dependents(source: &SiteSource, partial: &str) -> Vec<Dependent> {
+    let usage = template_usage(source);
+    let usage = match usage.partials.iter().find(|usage| usage.name == partial) {
+        Some(usage) => usage,
+        None => return Vec::new(),
+    };
+    usage
+        .included_by
+        .iter()
+        .map(|name| {
+            let kind = if source
+                .templates
+                .iter()
+                .any(|(template, _)| template == name)
+            {
+                "template"
+            } else {
+                "partial"
+            };
+            Dependent::new(kind, name, None)
+        })
+        .collect()
+}
+
+fn mentions(value: &Value, needle: &str) -> bool {
+    match value {
+        Value::String(text) => text.contains(needle),
+        Value::Array(items) => items.iter().any(|item| mentions(item, needle)),
+        Value::Object(fields) => fields.values().any(|field| mentions(field, needle)),
+        _ => false,
+    }
+}
+
+/// Everything mentioning an asset's URL prefix (`/assets/{id}/`)
+///
+/// Bodies, metadata, entries, stylesheets, templates and partials are all
+/// searched, since any of them can link to or embed an upload.
+pub fn asset_dependents(source: &SiteSource, asset_prefix: &str) -> Vec<Dependent> {
+    let mut dependents = Vec::new();
+
+    for page in &source.pages {
+        let meta = Value::Object(page.meta.clone());
+        if mentions(&page.body, asset_prefix) || mentions(&meta, asset_prefix) {
+            dependents.push(Dependent::new(
+                &page.collection,
+                &page.name,
+                Some(page_url(page)),
+            ));
+        }
+    }
+
+    for collection in &source.collections {
+        for entry in &collection.entries {
+            if mentions(entry, asset_prefix) {
+                let name = entry.get("name").and_then(Value::as_str).unwrap_or("");
+                let url = entry.get("url").and_then(Value::as_str).map(str::to_string);
+                dependents.push(Dependent::new(&collection.name, name, url));
+            }
+        }
+    }
+
+    let theme_files = [
+        ("style", &source.styles),
+        ("template", &source.templates),
+        ("partial", &source.partials),
+    ];
+    for (kind, files) in theme_files.iter() {
+        for (name, content) in files.iter() {
+            if content.contains(asset_prefix) {
+                dependents.push(Dependent::new(kind, name, None));
+            }
+        }
+    }
+
+    dependents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::site::{CollectionSource, PageSource};
+    use serde_json::{json, Map};
+    use wasm_bindgen_test::*;
+
+    fn page(collection: &str, name: &str, body: Value) -> PageSource {
+        PageSource {
+            collection: collection.to_string(),
+            name: name.to_string(),
+            meta: Map::new(),
+            body,
+        }
+    }
+
+    fn source() -> SiteSource {
+        let image = json!({ "type": "doc", "content": [{ "type": "paragraph", "content": [
+            { "type": "image", "attrs": { "src": "/assets/a1/cat.png" } }
+        ]}]});
+        SiteSource {
+            templates: vec![
+                ("index".to_string(), "{{> header}}".to_string()),
+                ("post".to_string(), "{{> header}}".to_string()),
+            ],
+            partials: vec![
+                ("header".to_string(), "{{> logo}}".to_string()),
+                (
+                    "logo".to_string(),
+                    "<img src=\"/assets/a1/cat.png\">".to_string(),
+                ),
+            ],
+            styles: vec![("style".to_string(), "body { color: red }".to_string())],
+            pages: vec![
+                page("page", "about", image),
+                page("post", "hello", json!({ "type": "doc", "content": [] })),
+            ],
+            collections: vec![CollectionSource {
+                name: "events".to_string(),
+                entries: vec![json!({ "name": "Fair", "poster": "/assets/a1/cat.png" })],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_template_and_partial_dependents() {
+        let source = source();
+        assert_eq!(
+            template_dependents(&source, "post"),
+            vec![Dependent::new(
+                "post",
+                "hello",
+                Some("/posts/hello/".to_string())
+            )]
+        );
+        assert!(template_dependents(&source, "missing").is_empty());
+        assert_eq!(
+            partial_dependents(&source, "header"),
+            vec![
+                Dependent::new("template", "index", None),
+                Dependent::new("template", "post", None)
+            ]
+        );
+        assert_eq!(
+            partial_dependents(&source, "logo"),
+            vec![Dependent::new("partial", "header", None)]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_asset_dependents() {
+        let kinds: Vec<String> = asset_dependents(&source(), "/assets/a1/")
+            .into_iter()
+            .map(|dependent| format!("{} {}", dependent.kind, dependent.name))
+            .collect();
+        assert_eq!(kinds, vec!["page about", "events Fair", "partial logo"]);
+        assert!(asset_dependents(&source(), "/assets/b2/").is_empty());
+    }
+}
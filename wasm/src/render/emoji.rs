@@ -0,0 +1,350 @@
+//! `:shortcode:` emoji in rich text.
+//!
+//! Shortcodes are the GitHub/Slack names for the emoji people use most, in a
+//! table bundled here. Anything between colons that isn't in it is left as
+//! typed, so times like 10:30:00 and `key:value` text come through intact,
+//! and so does code.
+
+use serde_json::Value;
+
+use super::html::escape_html;
+
+/// Where emoji images come from when a site doesn't say
+pub const TWEMOJI_BASE: &str = "https://cdn.jsdelivr.net/gh/jdecked/twemoji@15.0.3/assets/svg";
+
+/// Shortcode → emoji, sorted by shortcode for binary search
+#[rustfmt::skip]
+const EMOJI: &[(&str, &str)] = &[
+    ("+1", "👍"),
+    ("-1", "👎"),
+    ("100", "💯"),
+    ("alien", "👽"),
+    ("angry", "😠"),
+    ("apple", "🍎"),
+    ("art", "🎨"),
+    ("baby", "👶"),
+    ("balloon", "🎈"),
+    ("bangbang", "‼️"),
+    ("bee", "🐝"),
+    ("beer", "🍺"),
+    ("bell", "🔔"),
+    ("bike", "🚲"),
+    ("bird", "🐦"),
+    ("birthday", "🎂"),
+    ("blush", "😊"),
+    ("book", "📖"),
+    ("books", "📚"),
+    ("boom", "💥"),
+    ("bouquet", "💐"),
+    ("broken_heart", "💔"),
+    ("bug", "🐛"),
+    ("bulb", "💡"),
+    ("cake", "🍰"),
+    ("calendar", "📆"),
+    ("camera", "📷"),
+    ("cat", "🐱"),
+    ("champagne", "🍾"),
+    ("clap", "👏"),
+    ("clock3", "🕒"),
+    ("cloud", "☁️"),
+    ("coffee", "☕"),
+    ("confused", "😕"),
+    ("cookie", "🍪"),
+    ("cool", "🆒"),
+    ("cry", "😢"),
+    ("crystal_ball", "🔮"),
+    ("dancer", "💃"),
+    ("dog", "🐶"),
+    ("dove", "🕊️"),
+    ("earth_africa", "🌍"),
+    ("earth_americas", "🌎"),
+    ("earth_asia", "🌏"),
+    ("envelope", "✉️"),
+    ("eyes", "👀"),
+    ("fire", "🔥"),
+    ("flushed", "😳"),
+    ("four_leaf_clover", "🍀"),
+    ("frowning", "😦"),
+    ("gift", "🎁"),
+    ("globe_with_meridians", "🌐"),
+    ("grin", "😁"),
+    ("grinning", "😀"),
+    ("guitar", "🎸"),
+    ("headphones", "🎧"),
+    ("heart", "❤️"),
+    ("heart_eyes", "😍"),
+    ("hearts", "♥️"),
+    ("hibiscus", "🌺"),
+    ("hourglass", "⌛"),
+    ("house", "🏠"),
+    ("hugs", "🤗"),
+    ("hushed", "😯"),
+    ("innocent", "😇"),
+    ("joy", "😂"),
+    ("key", "🔑"),
+    ("kiss", "💋"),
+    ("laughing", "😆"),
+    ("leaves", "🍃"),
+    ("link", "🔗"),
+    ("lock", "🔒"),
+    ("loudspeaker", "📢"),
+    ("mag", "🔍"),
+    ("mailbox", "📫"),
+    ("maple_leaf", "🍁"),
+    ("memo", "📝"),
+    ("microphone", "🎤"),
+    ("moon", "🌙"),
+    ("muscle", "💪"),
+    ("musical_note", "🎵"),
+    ("newspaper", "📰"),
+    ("no_entry", "⛔"),
+    ("ok_hand", "👌"),
+    ("open_book", "📖"),
+    ("paperclip", "📎"),
+    ("partying_face", "🥳"),
+    ("pencil2", "✏️"),
+    ("pensive", "😔"),
+    ("pizza", "🍕"),
+    ("point_down", "👇"),
+    ("point_left", "👈"),
+    ("point_right", "👉"),
+    ("point_up", "☝️"),
+    ("pray", "🙏"),
+    ("pushpin", "📌"),
+    ("question", "❓"),
+    ("rainbow", "🌈"),
+    ("raised_hands", "🙌"),
+    ("relaxed", "☺️"),
+    ("rocket", "🚀"),
+    ("rose", "🌹"),
+    ("scissors", "✂️"),
+    ("see_no_evil", "🙈"),
+    ("seedling", "🌱"),
+    ("shrug", "🤷"),
+    ("slightly_smiling_face", "🙂"),
+    ("smile", "😄"),
+    ("smiley", "😃"),
+    ("smirk", "😏"),
+    ("sob", "😭"),
+    ("sparkles", "✨"),
+    ("sparkling_heart", "💖"),
+    ("speech_balloon", "💬"),
+    ("star", "⭐"),
+    ("star2", "🌟"),
+    ("sunflower", "🌻"),
+    ("sunglasses", "😎"),
+    ("sunny", "☀️"),
+    ("sweat_smile", "😅"),
+    ("tada", "🎉"),
+    ("tea", "🍵"),
+    ("thinking", "🤔"),
+    ("thumbsdown", "👎"),
+    ("thumbsup", "👍"),
+    ("tulip", "🌷"),
+    ("unicorn", "🦄"),
+    ("v", "✌️"),
+    ("warning", "⚠️"),
+    ("wave", "👋"),
+    ("white_check_mark", "✅"),
+    ("wink", "😉"),
+    ("x", "❌"),
+    ("yum", "😋"),
+    ("zap", "⚡"),
+    ("zzz", "💤"),
+];
+
+/// The emoji a shortcode (without its colons) stands for
+pub fn lookup(shortcode: &str) -> Option<&'static str> {
+    EMOJI
+        .binary_search_by_key(&shortcode, |(name, _)| name)
+        .ok()
+        .map(|index| EMOJI[index].1)
+}
+
+fn is_shortcode_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-'
+}
+
+/// A piece of text, split at its shortcodes
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Emoji {
+        shortcode: &'a str,
+        emoji: &'static str,
+    },
+}
+
+/// `text` split into runs of text and the emoji between them
+pub fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut from = 0;
+    while let Some(open) = text[from..].find(':').map(|i| from + i) {
+        let close = match text[open + 1..].find(':') {
+            Some(i) => open + 1 + i,
+            None => break,
+        };
+        let shortcode = &text[open + 1..close];
+        let emoji = Some(shortcode)
+            .filter(|name| !name.is_empty() && name.chars().all(is_shortcode_char))
+            .and_then(lookup);
+        match emoji {
+            Some(emoji) => {
+                if open > start {
+                    segments.push(Segment::Text(&text[start..open]));
+                }
+                segments.push(Segment::Emoji { shortcode, emoji });
+                start = close + 1;
+                from = start;
+            }
+            // The closing colon may open the next shortcode
+            None => from = close,
+        }
+    }
+    if start < text.len() {
+        segments.push(Segment::Text(&text[start..]));
+    }
+    segments
+}
+
+/// `text` with its shortcodes replaced by the emoji
+pub fn expand(text: &str) -> String {
+    segments(text)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text,
+            Segment::Emoji { emoji, .. } => emoji,
+        })
+        .collect()
+}
+
+/// An emoji's image under `base`, named by its code points as Twemoji does:
+/// ❤️ → `2764.svg`, leaving out the variation selector
+pub fn image_url(base: &str, emoji: &str) -> String {
+    let codepoints: Vec<String> = emoji
+        .chars()
+        .filter(|&c| c != '\u{FE0F}')
+        .map(|c| format!("{:x}", c as u32))
+        .collect();
+    format!(
+        "{}/{}.svg",
+        base.trim_end_matches('/'),
+        codepoints.join("-")
+    )
+}
+
+/// An `<img>` showing an emoji, with the emoji itself as its alt text
+pub fn image_html(base: &str, shortcode: &str, emoji: &str) -> String {
+    format!(
+        "<img class=\"emoji\" src=\"{}\" alt=\"{}\" title=\":{}:\" draggable=\"false\">",
+        escape_html(&image_url(base, emoji)),
+        emoji,
+        escape_html(shortcode)
+    )
+}
+
+fn is_code(node: &Value) -> bool {
+    let node_type = node.get("type").and_then(Value::as_str);
+    if matches!(node_type, Some("code_block") | Some("codeBlock")) {
+        return true;
+    }
+    match node.get("marks") {
+        Some(Value::Array(marks)) => marks
+            .iter()
+            .any(|mark| mark.get("type").and_then(Value::as_str) == Some("code")),
+        _ => false,
+    }
+}
+
+/// A ProseMirror document with the shortcodes in its text expanded, for
+/// copies of a page that can't show images, like its Markdown mirror
+pub fn expand_doc(node: &Value) -> Value {
+    let mut node = node.clone();
+    expand_node(&mut node);
+    node
+}
+
+fn expand_node(node: &mut Value) {
+    if is_code(node) {
+        return;
+    }
+    if let Some(Value::String(text)) = node.get_mut("text") {
+        *text = expand(text);
+    }
+    if let Some(Value::Array(children)) = node.get_mut("content") {
+        for child in children {
+            expand_node(child);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_table_is_sorted() {
+        for pair in EMOJI.windows(2) {
+            assert!(pair[0].0 < pair[1].0, "{} is out of order", pair[1].0);
+        }
+        assert_eq!(lookup("tada"), Some("🎉"));
+        assert_eq!(lookup("+1"), Some("👍"));
+        assert_eq!(lookup("no_such_emoji"), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_segments() {
+        assert_eq!(
+            segments("Launch :rocket: at 10:30:00 :tada::sparkles:"),
+            vec![
+                Segment::Text("Launch "),
+                Segment::Emoji {
+                    shortcode: "rocket",
+                    emoji: "🚀"
+                },
+                Segment::Text(" at 10:30:00 "),
+                Segment::Emoji {
+                    shortcode: "tada",
+                    emoji: "🎉"
+                },
+                Segment::Emoji {
+                    shortcode: "sparkles",
+                    emoji: "✨"
+                },
+            ]
+        );
+        assert_eq!(expand("note: :heart: :unknown: :"), "note: ❤️ :unknown: :");
+        assert_eq!(expand("no shortcodes"), "no shortcodes");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_images() {
+        assert_eq!(
+            image_url("https://e.example/", "❤️"),
+            "https://e.example/2764.svg"
+        );
+        assert_eq!(
+            image_html("/emoji", "wave", "👋"),
+            "<img class=\"emoji\" src=\"/emoji/1f44b.svg\" alt=\"👋\" title=\":wave:\" \
+             draggable=\"false\">"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_expand_doc() {
+        let doc = json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [
+                { "type": "text", "text": "Done :white_check_mark: " },
+                { "type": "text", "text": ":x:", "marks": [{ "type": "code" }] }
+            ]},
+            { "type": "code_block", "content": [{ "type": "text", "text": ":x:" }] }
+        ]});
+        let expanded = expand_doc(&doc);
+        assert_eq!(expanded["content"][0]["content"][0]["text"], "Done ✅ ");
+        assert_eq!(expanded["content"][0]["content"][1]["text"], ":x:");
+        assert_eq!(expanded["content"][1]["content"][0]["text"], ":x:");
+    }
+}
@@ -0,0 +1,304 @@
+//! Static HTML forms.
+//!
+//! Forms are defined in the site's `form` collection and embedded in
+//! templates with `{{form "contact"}}`. Submissions go to a third-party
+//! provider, so nothing has to be hosted alongside the exported site.
+
+use std::collections::HashMap;
+
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext,
+    RenderErrorReason,
+};
+
+use super::html::escape_html;
+use super::slugify;
+use crate::types::{FormDefinition, FormInput, FormProvider};
+
+fn is_http_url(url: &str) -> bool {
+    url.starts_with("https://") || url.starts_with("http://")
+}
+
+/// Settings a form's provider needs before it can be published
+///
+/// Returns one message per problem; an empty list means the form is ready.
+pub fn missing_settings(form: &FormDefinition) -> Vec<String> {
+    let mut missing = Vec::new();
+    let endpoint = form
+        .endpoint
+        .as_deref()
+        .map(str::trim)
+        .filter(|endpoint| !endpoint.is_empty());
+
+    match form.provider {
+        FormProvider::Endpoint | FormProvider::Formspree => match endpoint {
+            Some(endpoint) if is_http_url(endpoint) => {}
+            Some(endpoint) => missing.push(format!("endpoint is not a URL: {}", endpoint)),
+            None => missing.push(format!("{:?} forms need an endpoint", form.provider)),
+        },
+        FormProvider::Mailto => match endpoint {
+            Some(address) if address.contains('@') => {}
+            _ => missing.push("Mailto forms need an email address as the endpoint".to_string()),
+        },
+        FormProvider::Netlify => {}
+    }
+
+    if form.fields.is_empty() {
+        missing.push("form has no fields".to_string());
+    }
+    let mut seen = Vec::new();
+    for field in &form.fields {
+        if field.name.trim().is_empty() {
+            missing.push("field name cannot be empty".to_string());
+        } else if seen.contains(&field.name) {
+            missing.push(format!("duplicate field: {}", field.name));
+        } else {
+            seen.push(field.name.clone());
+        }
+        if field.input == FormInput::Select && field.options.is_empty() {
+            missing.push(format!("select field {} has no options", field.name));
+        }
+    }
+
+    missing
+}
+
+fn input_type(input: FormInput) -> &'static str {
+    match input {
+        FormInput::Text => "text",
+        FormInput::Email => "email",
+        FormInput::Tel => "tel",
+        FormInput::Url => "url",
+        FormInput::Number => "number",
+        FormInput::Date => "date",
+        FormInput::Checkbox => "checkbox",
+        // Rendered as their own elements
+        FormInput::Textarea | FormInput::Select => "text",
+    }
+}
+
+/// Accessible HTML for a form: every control has a label, and required
+/// fields are marked for both browsers and assistive technology
+pub fn form_to_html(form: &FormDefinition) -> String {
+    let form_id = format!("form-{}", slugify(&form.name));
+    let endpoint = form.endpoint.as_deref().unwrap_or("").trim();
+
+    let mut attrs = format!(
+        " id=\"{}\" class=\"form\" name=\"{}\" method=\"post\"",
+        form_id,
+        escape_html(&form.name)
+    );
+    match form.provider {
+        FormProvider::Endpoint | FormProvider::Formspree => {
+            attrs.push_str(&format!(" action=\"{}\"", escape_html(endpoint)));
+        }
+        FormProvider::Netlify => {
+            if let Some(success_url) = &form.success_url {
+                attrs.push_str(&format!(" action=\"{}\"", escape_html(success_url)));
+            }
+            attrs.push_str(" data-netlify=\"true\"");
+        }
+        FormProvider::Mailto => {
+            attrs.push_str(&format!(
+                " action=\"mailto:{}\" enctype=\"text/plain\"",
+                escape_html(endpoint)
+            ));
+        }
+    }
+    if form.title.is_some() {
+        attrs.push_str(&format!(" aria-labelledby=\"{}-title\"", form_id));
+    }
+
+    let mut html = format!("<form{}>", attrs);
+    if let Some(title) = &form.title {
+        html.push_str(&format!(
+            "<h2 id=\"{}-title\">{}</h2>",
+            form_id,
+            escape_html(title)
+        ));
+    }
+    match form.provider {
+        FormProvider::Netlify => html.push_str(&format!(
+            "<input type=\"hidden\" name=\"form-name\" value=\"{}\">",
+            escape_html(&form.name)
+        )),
+        FormProvider::Formspree => {
+            if let Some(success_url) = &form.success_url {
+                html.push_str(&format!(
+                    "<input type=\"hidden\" name=\"_next\" value=\"{}\">",
+                    escape_html(success_url)
+                ));
+            }
+        }
+        _ => {}
+    }
+
+    for field in &form.fields {
+        let id = format!("{}-{}", form_id, slugify(&field.name));
+        let name = escape_html(&field.name);
+        let label = format!("<label for=\"{}\">{}</label>", id, escape_html(&field.label));
+        let required = if field.required {
+            " required aria-required=\"true\""
+        } else {
+            ""
+        };
+
+        html.push_str("<div class=\"form-field\">");
+        match field.input {
+            FormInput::Textarea => html.push_str(&format!(
+                "{}<textarea id=\"{}\" name=\"{}\"{}></textarea>",
+                label, id, name, required
+            )),
+            FormInput::Select => {
+                html.push_str(&format!(
+                    "{}<select id=\"{}\" name=\"{}\"{}>",
+                    label, id, name, required
+                ));
+                for option in &field.options {
+                    let option = escape_html(option);
+                    html.push_str(&format!("<option value=\"{}\">{}</option>", option, option));
+                }
+                html.push_str("</select>");
+            }
+            // Checkboxes read best with the label after the box
+            FormInput::Checkbox => html.push_str(&format!(
+                "<input type=\"checkbox\" id=\"{}\" name=\"{}\" value=\"yes\"{}>{}",
+                id, name, required, label
+            )),
+            input => html.push_str(&format!(
+                "{}<input type=\"{}\" id=\"{}\" name=\"{}\"{}>",
+                label,
+                input_type(input),
+                id,
+                name,
+                required
+            )),
+        }
+        html.push_str("</div>");
+    }
+
+    html.push_str(&format!(
+        "<button type=\"submit\">{}</button></form>",
+        escape_html(form.submit_label.as_deref().unwrap_or("Send"))
+    ));
+    html
+}
+
+/// `{{form "name"}}` — renders one of the site's forms
+pub struct FormHelper {
+    forms: HashMap<String, FormDefinition>,
+}
+
+impl FormHelper {
+    pub fn new(forms: &[FormDefinition]) -> Self {
+        FormHelper {
+            forms: forms
+                .iter()
+                .map(|form| (form.name.clone(), form.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl HelperDef for FormHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let name = h
+            .param(0)
+            .and_then(|param| param.value().as_str())
+            .ok_or(RenderErrorReason::ParamNotFoundForIndex("form", 0))?;
+        let form = self
+            .forms
+            .get(name)
+            .ok_or_else(|| RenderErrorReason::Other(format!("form not found: {}", name)))?;
+        out.write(&form_to_html(form))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Renderer;
+    use crate::types::FormField;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn contact_form() -> FormDefinition {
+        FormDefinition {
+            name: "contact".to_string(),
+            title: None,
+            provider: FormProvider::Formspree,
+            endpoint: Some("https://formspree.io/f/abc".to_string()),
+            fields: vec![
+                FormField {
+                    name: "email".to_string(),
+                    label: "Email".to_string(),
+                    input: FormInput::Email,
+                    required: true,
+                    options: Vec::new(),
+                },
+                FormField {
+                    name: "message".to_string(),
+                    label: "Message".to_string(),
+                    input: FormInput::Textarea,
+                    required: false,
+                    options: Vec::new(),
+                },
+            ],
+            submit_label: None,
+            success_url: None,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_form_to_html() {
+        let html = form_to_html(&contact_form());
+
+        assert!(html.starts_with(
+            "<form id=\"form-contact\" class=\"form\" name=\"contact\" method=\"post\" action=\"https://formspree.io/f/abc\">"
+        ));
+        assert!(html.contains(
+            "<label for=\"form-contact-email\">Email</label><input type=\"email\" id=\"form-contact-email\" name=\"email\" required aria-required=\"true\">"
+        ));
+        assert!(html.contains("<textarea id=\"form-contact-message\" name=\"message\"></textarea>"));
+        assert!(html.ends_with("<button type=\"submit\">Send</button></form>"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_missing_settings() {
+        assert!(missing_settings(&contact_form()).is_empty());
+
+        let mut form = contact_form();
+        form.endpoint = None;
+        assert_eq!(
+            missing_settings(&form),
+            vec!["Formspree forms need an endpoint".to_string()]
+        );
+
+        form.provider = FormProvider::Netlify;
+        assert!(missing_settings(&form).is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_form_helper() {
+        let mut renderer = Renderer::new();
+        renderer.register_helper("form", Box::new(FormHelper::new(&[contact_form()])));
+        renderer
+            .register_template("page", "<main>{{form \"contact\"}}</main>")
+            .unwrap();
+        renderer
+            .register_template("broken", "{{form \"newsletter\"}}")
+            .unwrap();
+
+        let html = renderer.render("page", &json!({})).unwrap();
+        assert!(html.starts_with("<main><form id=\"form-contact\""));
+        assert!(renderer.render("broken", &json!({})).is_err());
+    }
+}
@@ -0,0 +1,445 @@
+//! Gemtext export, for publishing a site over Gemini.
+//!
+//! Gemtext is line-based: three levels of heading, list items, quotes,
+//! preformatted blocks and links on lines of their own, with no inline
+//! formatting. Rich text is mapped to the nearest equivalent, and anything
+//! that can't be represented is reported rather than silently dropped.
+
+use std::collections::BTreeSet;
+
+use chrono::NaiveDate;
+use serde_json::Value;
+
+use super::output_path;
+use super::site::{page_url, PageSource, SiteOutput};
+
+/// The capsule's home page, listing every page and post
+pub const GEMTEXT_INDEX: &str = "index.gmi";
+
+/// A document converted to gemtext
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Gemtext {
+    pub text: String,
+    /// Constructs that were approximated or left out
+    pub unsupported: Vec<String>,
+}
+
+#[derive(Default)]
+struct Writer {
+    blocks: Vec<String>,
+    unsupported: BTreeSet<String>,
+}
+
+/// Inline content flattened to text, with its links pulled out
+#[derive(Default)]
+struct Inline {
+    text: String,
+    links: Vec<(String, String)>,
+}
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(Value::as_str).unwrap_or("")
+}
+
+fn attr<'a>(node: &'a Value, name: &str) -> Option<&'a Value> {
+    node.get("attrs").and_then(|attrs| attrs.get(name))
+}
+
+fn attr_str<'a>(node: &'a Value, name: &str) -> Option<&'a str> {
+    attr(node, name)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+fn children(node: &Value) -> &[Value] {
+    match node.get("content") {
+        Some(Value::Array(children)) => children,
+        _ => &[],
+    }
+}
+
+fn link_line(href: &str, label: &str) -> String {
+    let label = label.split_whitespace().collect::<Vec<_>>().join(" ");
+    if label.is_empty() || label == href {
+        format!("=> {}", href)
+    } else {
+        format!("=> {} {}", href, label)
+    }
+}
+
+impl Writer {
+    fn inline(&mut self, node: &Value, inline: &mut Inline) {
+        match node_type(node) {
+            "text" => {
+                let text = node.get("text").and_then(Value::as_str).unwrap_or("");
+                inline.text.push_str(text);
+                let marks = node.get("marks").and_then(Value::as_array);
+                for mark in marks.into_iter().flatten() {
+                    match node_type(mark) {
+                        "link" => {
+                            let href = attr_str(mark, "href").unwrap_or("");
+                            match inline.links.last_mut() {
+                                // One link split into runs by other marks
+                                Some((last, label)) if last == href => label.push_str(text),
+                                _ => inline.links.push((href.to_string(), text.to_string())),
+                            }
+                        }
+                        _ => {
+                            self.unsupported.insert("inline formatting".to_string());
+                        }
+                    }
+                }
+            }
+            "hard_break" | "hardBreak" => inline.text.push('\n'),
+            "image" => {
+                let src = attr_str(node, "src").unwrap_or("");
+                let alt = attr_str(node, "alt").unwrap_or("Image");
+                inline.links.push((src.to_string(), alt.to_string()));
+            }
+            _ => {
+                for child in children(node) {
+                    self.inline(child, inline);
+                }
+            }
+        }
+    }
+
+    fn inline_of(&mut self, node: &Value) -> Inline {
+        let mut inline = Inline::default();
+        for child in children(node) {
+            self.inline(child, &mut inline);
+        }
+        inline
+    }
+
+    /// Lines of text, then a line for each link
+    fn push_lines(&mut self, prefix: &str, inline: Inline) {
+        let mut lines: Vec<String> = inline
+            .text
+            .lines()
+            .map(|line| format!("{}{}", prefix, line.trim()))
+            .filter(|line| !line.trim().is_empty())
+            .collect();
+        lines.extend(
+            inline
+                .links
+                .iter()
+                .filter(|(href, _)| !href.is_empty())
+                .map(|(href, label)| link_line(href, label)),
+        );
+        if !lines.is_empty() {
+            self.blocks.push(lines.join("\n"));
+        }
+    }
+
+    fn list(&mut self, node: &Value, ordered: bool, depth: usize) {
+        if depth > 0 {
+            self.unsupported.insert("nested lists".to_string());
+        }
+        let start = attr(node, "order").and_then(Value::as_u64).unwrap_or(1);
+        let mut lines = Vec::new();
+        let mut links = Vec::new();
+        let mut nested = Vec::new();
+        for (index, item) in children(node).iter().enumerate() {
+            let mut inline = Inline::default();
+            for child in children(item) {
+                match node_type(child) {
+                    "bullet_list" | "bulletList" => nested.push((child, false)),
+                    "ordered_list" | "orderedList" => nested.push((child, true)),
+                    _ => {
+                        self.inline(child, &mut inline);
+                        inline.text.push(' ');
+                    }
+                }
+            }
+            let text = inline.text.split_whitespace().collect::<Vec<_>>().join(" ");
+            lines.push(if ordered {
+                format!("{}. {}", start + index as u64, text)
+            } else {
+                format!("* {}", text)
+            });
+            links.extend(inline.links);
+        }
+        lines.extend(
+            links
+                .iter()
+                .filter(|(href, _)| !href.is_empty())
+                .map(|(href, label)| link_line(href, label)),
+        );
+        self.blocks.push(lines.join("\n"));
+        for (list, ordered) in nested {
+            self.list(list, ordered, depth + 1);
+        }
+    }
+
+    fn block(&mut self, node: &Value) {
+        match node_type(node) {
+            "doc" => {
+                for child in children(node) {
+                    self.block(child);
+                }
+            }
+            "paragraph" => {
+                let inline = self.inline_of(node);
+                self.push_lines("", inline);
+            }
+            "heading" => {
+                let level = attr(node, "level").and_then(Value::as_u64).unwrap_or(1);
+                if level > 3 {
+                    self.unsupported
+                        .insert("headings below level 3".to_string());
+                }
+                let prefix = format!("{} ", "#".repeat(level.clamp(1, 3) as usize));
+                let inline = self.inline_of(node);
+                self.push_lines(&prefix, inline);
+            }
+            "blockquote" => {
+                let mut inline = Inline::default();
+                for child in children(node) {
+                    self.inline(child, &mut inline);
+                    inline.text.push('\n');
+                }
+                self.push_lines("> ", inline);
+            }
+            "bullet_list" | "bulletList" => self.list(node, false, 0),
+            "ordered_list" | "orderedList" => self.list(node, true, 0),
+            "code_block" | "codeBlock" => {
+                let code: String = children(node)
+                    .iter()
+                    .filter_map(|child| child.get("text").and_then(Value::as_str))
+                    .collect();
+                let alt = attr_str(node, "language").unwrap_or("");
+                self.blocks
+                    .push(format!("```{}\n{}\n```", alt, code.trim_end_matches('\n')));
+            }
+            "horizontal_rule" | "horizontalRule" => self.blocks.push("---".to_string()),
+            "image" => {
+                let mut inline = Inline::default();
+                self.inline(node, &mut inline);
+                self.push_lines("", inline);
+            }
+            other => {
+                // Keep the words, even if the structure is lost
+                self.unsupported.insert(format!("{} blocks", other));
+                let inline = self.inline_of(node);
+                self.push_lines("", inline);
+            }
+        }
+    }
+}
+
+/// Convert a ProseMirror document to gemtext
+pub fn pm_to_gemtext(doc: &Value) -> Gemtext {
+    let mut writer = Writer::default();
+    writer.block(doc);
+    let mut text = writer.blocks.join("\n\n");
+    if !text.is_empty() {
+        text.push('\n');
+    }
+    Gemtext {
+        text,
+        unsupported: writer.unsupported.into_iter().collect(),
+    }
+}
+
+/// Where a page's gemtext is written: `/about/` → `about/index.gmi`
+pub fn gemtext_path(page: &PageSource) -> String {
+    let path = output_path(&page_url(page));
+    match path.strip_suffix(".html") {
+        Some(stem) => format!("{}.gmi", stem),
+        None => path,
+    }
+}
+
+fn title(page: &PageSource) -> &str {
+    page.meta
+        .get("title")
+        .and_then(Value::as_str)
+        .filter(|title| !title.is_empty())
+        .unwrap_or(&page.name)
+}
+
+/// The post's date, as gemlog subscriptions expect it at the start of a link
+fn post_date(page: &PageSource) -> Option<NaiveDate> {
+    let date = page.meta.get("date").and_then(Value::as_str)?;
+    NaiveDate::parse_from_str(date.get(..10).unwrap_or(date), "%Y-%m-%d").ok()
+}
+
+/// Every page and post as gemtext, plus an index listing them
+///
+/// The home page's content opens the index; posts are listed newest first
+/// in the gemlog format so Gemini clients can subscribe to them.
+pub fn site_to_gemtext(site_name: &str, pages: &[PageSource]) -> SiteOutput {
+    let mut output = SiteOutput::default();
+    let mut index = format!("# {}\n", site_name);
+    let mut posts = Vec::new();
+    let mut listed_pages = Vec::new();
+
+    for page in pages {
+        let converted = pm_to_gemtext(&page.body);
+        let url = page_url(page);
+        for construct in &converted.unsupported {
+            output
+                .warnings
+                .push(format!("{}: {} can't be shown in gemtext", url, construct));
+        }
+
+        let path = gemtext_path(page);
+        if path == GEMTEXT_INDEX {
+            index = format!("# {}\n\n{}", title(page), converted.text);
+            continue;
+        }
+        let mut text = format!("# {}\n\n", title(page));
+        let date = post_date(page).filter(|_| page.collection == "post");
+        if let Some(date) = date {
+            text.push_str(&format!("{}\n\n", date.format("%Y-%m-%d")));
+        }
+        text.push_str(&converted.text);
+        output.files.insert(path, text);
+
+        if page.collection == "post" {
+            posts.push((date, url, title(page).to_string()));
+        } else {
+            listed_pages.push(link_line(&url, title(page)));
+        }
+    }
+
+    posts.sort_by(|a, b| b.0.cmp(&a.0));
+    if !posts.is_empty() {
+        index.push_str("\n## Posts\n\n");
+        for (date, url, title) in &posts {
+            let label = match date {
+                Some(date) => format!("{} {}", date.format("%Y-%m-%d"), title),
+                None => title.clone(),
+            };
+            index.push_str(&link_line(url, &label));
+            index.push('\n');
+        }
+    }
+    if !listed_pages.is_empty() {
+        index.push_str("\n## Pages\n\n");
+        index.push_str(&listed_pages.join("\n"));
+        index.push('\n');
+    }
+    output.files.insert(GEMTEXT_INDEX.to_string(), index);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn text(text: &str) -> Value {
+        json!({ "type": "text", "text": text })
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pm_to_gemtext() {
+        let doc = json!({ "type": "doc", "content": [
+            { "type": "heading", "attrs": { "level": 1 }, "content": [text("Zine fair")] },
+            { "type": "paragraph", "content": [
+                text("Tables at the "),
+                { "type": "text", "text": "town hall", "marks": [
+                    { "type": "link", "attrs": { "href": "/venue/" } }
+                ]},
+                { "type": "text", "text": " this Saturday", "marks": [{ "type": "strong" }] }
+            ]},
+            { "type": "bullet_list", "content": [
+                { "type": "list_item", "content": [
+                    { "type": "paragraph", "content": [text("Comics")] },
+                    { "type": "bullet_list", "content": [
+                        { "type": "list_item", "content": [
+                            { "type": "paragraph", "content": [text("Minicomics")] }
+                        ]}
+                    ]}
+                ]},
+                { "type": "list_item", "content": [
+                    { "type": "paragraph", "content": [text("Poetry")] }
+                ]}
+            ]},
+            { "type": "heading", "attrs": { "level": 5 }, "content": [text("Details")] },
+            { "type": "blockquote", "content": [
+                { "type": "paragraph", "content": [text("Bring cash")] }
+            ]},
+            { "type": "code_block", "content": [text("ls zines/\n")] },
+            { "type": "table", "content": [text("Cell")] }
+        ]});
+
+        let gemtext = pm_to_gemtext(&doc);
+        assert_eq!(
+            gemtext.text,
+            "# Zine fair\n\n\
+             Tables at the town hall this Saturday\n=> /venue/ town hall\n\n\
+             * Comics\n* Poetry\n\n\
+             * Minicomics\n\n\
+             ### Details\n\n\
+             > Bring cash\n\n\
+             ```\nls zines/\n```\n\n\
+             Cell\n"
+        );
+        assert_eq!(
+            gemtext.unsupported,
+            vec![
+                "headings below level 3",
+                "inline formatting",
+                "nested lists",
+                "table blocks"
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_site_to_gemtext() {
+        let page = |collection: &str, name: &str, meta: Value, body: Value| PageSource {
+            collection: collection.to_string(),
+            name: name.to_string(),
+            meta: meta.as_object().unwrap().clone(),
+            body: json!({ "type": "doc", "content": [
+                { "type": "paragraph", "content": [body] }
+            ]}),
+        };
+        let pages = vec![
+            page(
+                "page",
+                "index",
+                json!({ "title": "Welcome" }),
+                text("Hello"),
+            ),
+            page("page", "about", json!({}), text("About us")),
+            page(
+                "post",
+                "first",
+                json!({ "title": "First", "date": "2025-01-10" }),
+                text("One"),
+            ),
+            page(
+                "post",
+                "second",
+                json!({ "title": "Second", "date": "2025-02-01T10:00:00Z" }),
+                json!({ "type": "text", "text": "Two", "marks": [{ "type": "em" }] }),
+            ),
+        ];
+
+        let output = site_to_gemtext("Zine", &pages);
+        assert_eq!(
+            output.files[GEMTEXT_INDEX],
+            "# Welcome\n\nHello\n\n\
+             ## Posts\n\n\
+             => /posts/second/ 2025-02-01 Second\n\
+             => /posts/first/ 2025-01-10 First\n\n\
+             ## Pages\n\n\
+             => /about/ about\n"
+        );
+        assert_eq!(output.files["about/index.gmi"], "# about\n\nAbout us\n");
+        assert_eq!(
+            output.files["posts/first/index.gmi"],
+            "# First\n\n2025-01-10\n\nOne\n"
+        );
+        assert_eq!(
+            output.warnings,
+            vec!["/posts/second/: inline formatting can't be shown in gemtext"]
+        );
+    }
+}
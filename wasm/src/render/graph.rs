@@ -0,0 +1,330 @@
+//! The site as a graph, for the UI's structure view.
+//!
+//! Nodes are pages, posts and entries with their own page, keyed by URL.
+//! Edges are internal links in bodies, references from metadata fields to
+//! another node's URL, and tags two nodes have in common.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::links::{link_hrefs, resolve_internal};
+use super::site::{entry_url, page_url, CollectionSource, PageSource};
+
+/// Meta field holding a list (or comma-separated string) of tags
+pub const TAGS_FIELD: &str = "tags";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphNode {
+    /// The node's URL, which edges refer to
+    pub id: String,
+    /// File id, when known
+    pub file_id: Option<String>,
+    pub collection: String,
+    pub name: String,
+    pub title: String,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeKind {
+    Link,
+    Reference,
+    Tag,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+    pub kind: EdgeKind,
+    /// The meta field for references, or the shared tags
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ContentGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+fn str_field(fields: &Map<String, Value>, key: &str) -> Option<String> {
+    fields
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// Tags as written, trimmed, in first-seen order without repeats
+pub fn tags(fields: &Map<String, Value>) -> Vec<String> {
+    let raw: Vec<String> = match fields.get(TAGS_FIELD) {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::to_string)
+            .collect(),
+        Some(Value::String(text)) => text.split(',').map(str::to_string).collect(),
+        _ => Vec::new(),
+    };
+    let mut seen = HashSet::new();
+    raw.iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty() && seen.insert(tag.to_lowercase()))
+        .collect()
+}
+
+/// A node and the fields to look for references in
+struct Source<'a> {
+    node: GraphNode,
+    fields: &'a Map<String, Value>,
+    body: Option<&'a Value>,
+}
+
+fn page_source(page: &PageSource) -> Source<'_> {
+    Source {
+        node: GraphNode {
+            id: page_url(page),
+            file_id: str_field(&page.meta, "id"),
+            collection: page.collection.clone(),
+            name: page.name.clone(),
+            title: str_field(&page.meta, "title").unwrap_or_else(|| page.name.clone()),
+            tags: tags(&page.meta),
+        },
+        fields: &page.meta,
+        body: Some(&page.body),
+    }
+}
+
+/// Strings in a meta value that could be URLs
+fn field_strings(value: &Value) -> Vec<&str> {
+    match value {
+        Value::String(text) => vec![text.as_str()],
+        Value::Array(items) => items.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Build the graph from pages (with wikilinks already resolved) and collections
+pub fn content_graph(pages: &[PageSource], collections: &[CollectionSource]) -> ContentGraph {
+    let mut sources: Vec<Source> = pages.iter().map(page_source).collect();
+    for collection in collections {
+        let listing = match collection
+            .listing
+            .as_ref()
+            .filter(|listing| listing.detail_template.is_some())
+        {
+            Some(listing) => listing,
+            None => continue,
+        };
+        for entry in &collection.entries {
+            let fields = match entry.as_object() {
+                Some(fields) => fields,
+                None => continue,
+            };
+            let name = str_field(fields, "name").unwrap_or_default();
+            sources.push(Source {
+                node: GraphNode {
+                    id: entry_url(listing, entry),
+                    file_id: str_field(fields, "id"),
+                    collection: collection.name.clone(),
+                    title: str_field(fields, "title").unwrap_or_else(|| name.clone()),
+                    name,
+                    tags: tags(fields),
+                },
+                fields,
+                body: None,
+            });
+        }
+    }
+
+    let ids: HashSet<String> = sources
+        .iter()
+        .map(|source| source.node.id.clone())
+        .collect();
+    let mut edges = Vec::new();
+
+    for source in &sources {
+        let from = &source.node.id;
+        let links: BTreeSet<String> = source
+            .body
+            .map(link_hrefs)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|href| resolve_internal(from, href))
+            .filter(|target| target != from && ids.contains(target))
+            .collect();
+        for target in links {
+            edges.push(GraphEdge {
+                source: from.clone(),
+                target,
+                kind: EdgeKind::Link,
+                label: String::new(),
+            });
+        }
+
+        for (key, value) in source.fields {
+            if matches!(key.as_str(), "id" | "url") {
+                continue;
+            }
+            let targets: BTreeSet<String> = field_strings(value)
+                .into_iter()
+                .filter(|text| text.starts_with('/'))
+                .filter_map(|text| resolve_internal(from, text))
+                .filter(|target| target != from && ids.contains(target))
+                .collect();
+            for target in targets {
+                edges.push(GraphEdge {
+                    source: from.clone(),
+                    target,
+                    kind: EdgeKind::Reference,
+                    label: key.clone(),
+                });
+            }
+        }
+    }
+
+    // One edge per pair of nodes sharing tags, listing them all
+    let mut by_tag: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (index, source) in sources.iter().enumerate() {
+        for tag in &source.node.tags {
+            by_tag.entry(tag.to_lowercase()).or_default().push(index);
+        }
+    }
+    let mut shared: BTreeMap<(usize, usize), Vec<String>> = BTreeMap::new();
+    for (tag, members) in &by_tag {
+        for (i, a) in members.iter().enumerate() {
+            for b in &members[i + 1..] {
+                shared.entry((*a, *b)).or_default().push(tag.clone());
+            }
+        }
+    }
+    for ((a, b), tags) in shared {
+        edges.push(GraphEdge {
+            source: sources[a].node.id.clone(),
+            target: sources[b].node.id.clone(),
+            kind: EdgeKind::Tag,
+            label: tags.join(", "),
+        });
+    }
+
+    ContentGraph {
+        nodes: sources.into_iter().map(|source| source.node).collect(),
+        edges,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ListingConfig;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn page(collection: &str, name: &str, meta: Value, href: Option<&str>) -> PageSource {
+        let content = match href {
+            Some(href) => json!([{ "type": "paragraph", "content": [
+                { "type": "text", "text": "link", "marks": [
+                    { "type": "link", "attrs": { "href": href } }
+                ]}
+            ]}]),
+            None => json!([]),
+        };
+        PageSource {
+            collection: collection.to_string(),
+            name: name.to_string(),
+            meta: meta.as_object().cloned().unwrap_or_default(),
+            body: json!({ "type": "doc", "content": content }),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_content_graph() {
+        let pages = vec![
+            page("page", "about", json!({ "title": "About us" }), None),
+            page(
+                "post",
+                "hello",
+                json!({ "tags": ["Zines", "events"], "related": "/about/" }),
+                Some("/events/zine-fair/"),
+            ),
+            page(
+                "post",
+                "again",
+                json!({ "tags": "zines, Events" }),
+                Some("/about"),
+            ),
+        ];
+        let collections = vec![CollectionSource {
+            name: "events".to_string(),
+            listing: Some(ListingConfig {
+                path: "events".to_string(),
+                template: "events".to_string(),
+                detail_template: Some("event".to_string()),
+                sort_by: None,
+                descending: false,
+                group_by: None,
+                group_by_date: None,
+                upcoming_field: None,
+            }),
+            fields: Vec::new(),
+            entries: vec![json!({ "id": "e1", "name": "Zine Fair", "tags": ["zines"] })],
+        }];
+
+        let graph = content_graph(&pages, &collections);
+
+        let ids: Vec<&str> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "/about/",
+                "/posts/hello/",
+                "/posts/again/",
+                "/events/zine-fair/"
+            ]
+        );
+        assert_eq!(graph.nodes[0].title, "About us");
+        assert_eq!(graph.nodes[3].file_id.as_deref(), Some("e1"));
+
+        let edges: Vec<(&str, &str, &EdgeKind, &str)> = graph
+            .edges
+            .iter()
+            .map(|edge| {
+                (
+                    edge.source.as_str(),
+                    edge.target.as_str(),
+                    &edge.kind,
+                    edge.label.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            edges,
+            vec![
+                ("/posts/hello/", "/events/zine-fair/", &EdgeKind::Link, ""),
+                ("/posts/hello/", "/about/", &EdgeKind::Reference, "related"),
+                ("/posts/again/", "/about/", &EdgeKind::Link, ""),
+                (
+                    "/posts/hello/",
+                    "/posts/again/",
+                    &EdgeKind::Tag,
+                    "events, zines"
+                ),
+                (
+                    "/posts/hello/",
+                    "/events/zine-fair/",
+                    &EdgeKind::Tag,
+                    "zines"
+                ),
+                (
+                    "/posts/again/",
+                    "/events/zine-fair/",
+                    &EdgeKind::Tag,
+                    "zines"
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Additions to a rendered page's `<head>`.
+//!
+//! Besides the tags the renderer adds itself, a page can carry its own head
+//! HTML (`head_html`) and stylesheet (`page_css`), for a one-off landing
+//! page that needs a script or styles the theme doesn't have. Both are
+//! added to that page only, after the theme's own head.
+
+use serde_json::{Map, Value};
+
+/// Extra HTML for a page's head
+pub const HEAD_HTML_KEY: &str = "head_html";
+/// CSS applied to a single page
+pub const PAGE_CSS_KEY: &str = "page_css";
+
+/// Insert `tags` just before `</head>`
+///
+/// Pages without a `</head>` are left alone.
+pub fn insert_into_head(html: &str, tags: &str) -> String {
+    match html.to_ascii_lowercase().find("</head>") {
+        Some(index) if !tags.is_empty() => {
+            format!("{}{}{}", &html[..index], tags, &html[index..])
+        }
+        _ => html.to_string(),
+    }
+}
+
+fn field<'a>(meta: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    meta.get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// The page's own head HTML and `<style>` block
+pub fn page_head_overrides(meta: &Map<String, Value>) -> String {
+    let mut tags = String::new();
+    if let Some(html) = field(meta, HEAD_HTML_KEY) {
+        tags.push_str(html);
+        tags.push('\n');
+    }
+    if let Some(css) = field(meta, PAGE_CSS_KEY) {
+        // The CSS can't close its own <style> element
+        let css = css.replace("</style", "<\\/style");
+        tags.push_str(&format!("<style>\n{}\n</style>\n", css));
+    }
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_page_head_overrides() {
+        let meta = json!({
+            "head_html": "<script src=\"/countdown.js\" defer></script>",
+            "page_css": ".hero { color: red }</style><script>",
+        });
+        let tags = page_head_overrides(meta.as_object().unwrap());
+        assert_eq!(
+            insert_into_head("<head><title>Launch</title></head><body></body>", &tags),
+            "<head><title>Launch</title>\
+             <script src=\"/countdown.js\" defer></script>\n\
+             <style>\n.hero { color: red }<\\/style><script>\n</style>\n\
+             </head><body></body>"
+        );
+        assert_eq!(page_head_overrides(&Map::new()), "");
+        assert_eq!(
+            insert_into_head("<p>No head</p>", "<style></style>"),
+            "<p>No head</p>"
+        );
+    }
+}
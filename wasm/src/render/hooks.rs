@@ -0,0 +1,37 @@
+//! Extension points in the render pipeline.
+//!
+//! [`render_site_with_hooks`](super::site::render_site_with_hooks) registers
+//! their helpers, then calls them around every page it renders and once with
+//! the finished output. A failing
+//! page hook is reported like a template error (the page is left out, with a
+//! warning); a failing `on_export` adds a warning.
+
+use std::collections::BTreeMap;
+
+use handlebars::HelperDef;
+use serde_json::Value;
+
+pub trait RenderHooks {
+    /// Extra template helpers, registered after the built-in ones
+    fn helpers(&self) -> Vec<(String, Box<dyn HelperDef + Send + Sync>)> {
+        Vec::new()
+    }
+
+    /// Adjust a page's template context before it is rendered
+    fn before_render_page(&self, _url: &str, context: Value) -> Result<Value, String> {
+        Ok(context)
+    }
+
+    /// Transform a page's rendered HTML
+    fn after_render_page(&self, _url: &str, html: String) -> Result<String, String> {
+        Ok(html)
+    }
+
+    /// Transform the finished output: path → file contents
+    fn on_export(&self, _files: &mut BTreeMap<String, String>) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// No extensions, for rendering as the crate does on its own
+impl RenderHooks for () {}
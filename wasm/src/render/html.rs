@@ -0,0 +1,477 @@
+//! ProseMirror JSON → HTML.
+//!
+//! Works on the JSON produced by `loro_doc_to_pm_doc`, so node and mark
+//! names follow the ProseMirror basic schema (`bullet_list`, `strong`, ...).
+//! camelCase spellings used by some editor setups are accepted too.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use super::asset_refs::AssetResolver;
+use super::emoji::{self, Segment, TWEMOJI_BASE};
+use super::slugify;
+use super::text::pm_to_text;
+use super::typography::Smartener;
+use crate::types::{EmojiSettings, EmojiStyle, Typography};
+
+/// Escape text for use in HTML element content or attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// How rich text is rendered
+#[derive(Debug, Clone, Default)]
+pub struct HtmlOptions {
+    /// End each heading with a "#" link to itself, for copying deep links
+    pub heading_links: bool,
+    /// Where each image is published now, when it may have moved since it
+    /// was inserted
+    pub images: Option<Arc<dyn AssetResolver>>,
+    pub typography: Typography,
+    pub emoji: EmojiSettings,
+}
+
+/// Gives each heading in a document a unique `id`
+///
+/// The id is the heading's slugified text (or its `id` attr, when set), with
+/// `-2`, `-3`, ... added to repeats, so deep links keep working for as long
+/// as the heading text does.
+#[derive(Debug, Default)]
+pub struct HeadingIds {
+    used: HashSet<String>,
+}
+
+impl HeadingIds {
+    pub fn assign(&mut self, heading: &Value) -> String {
+        let base = match attr_str(heading, "id").filter(|id| !id.trim().is_empty()) {
+            Some(id) => slugify(id),
+            None => slugify(&pm_to_text(heading)),
+        };
+        let base = if base.is_empty() {
+            "section".to_string()
+        } else {
+            base
+        };
+
+        let mut id = base.clone();
+        let mut suffix = 2;
+        while !self.used.insert(id.clone()) {
+            id = format!("{}-{}", base, suffix);
+            suffix += 1;
+        }
+        id
+    }
+}
+
+struct Writer<'a> {
+    html: String,
+    options: &'a HtmlOptions,
+    ids: HeadingIds,
+    /// None when typography is off
+    smartener: Option<Smartener<'a>>,
+}
+
+/// Render a ProseMirror document (or any node) to HTML
+pub fn pm_to_html(node: &Value) -> String {
+    pm_to_html_with(node, &HtmlOptions::default())
+}
+
+pub fn pm_to_html_with(node: &Value, options: &HtmlOptions) -> String {
+    let mut writer = Writer {
+        html: String::new(),
+        options,
+        ids: HeadingIds::default(),
+        smartener: if options.typography.is_enabled() {
+            Some(Smartener::new(&options.typography))
+        } else {
+            None
+        },
+    };
+    write_node(&mut writer, node);
+    writer.html
+}
+
+fn attr<'a>(node: &'a Value, name: &str) -> Option<&'a Value> {
+    node.get("attrs").and_then(|attrs| attrs.get(name))
+}
+
+fn attr_str<'a>(node: &'a Value, name: &str) -> Option<&'a str> {
+    attr(node, name).and_then(Value::as_str)
+}
+
+/// A heading's level, 1 to 6
+pub fn heading_level(heading: &Value) -> u64 {
+    attr(heading, "level")
+        .and_then(|level| {
+            level
+                .as_u64()
+                .or_else(|| level.as_f64().map(|l| l as u64))
+                .or_else(|| level.as_str().and_then(|l| l.parse().ok()))
+        })
+        .unwrap_or(1)
+        .clamp(1, 6)
+}
+
+/// `src` of each image in a ProseMirror document that has no alt text
+pub fn images_missing_alt(node: &Value) -> Vec<String> {
+    let mut missing = Vec::new();
+    collect_missing_alt(node, &mut missing);
+    missing
+}
+
+fn collect_missing_alt(node: &Value, missing: &mut Vec<String>) {
+    if node.get("type").and_then(Value::as_str) == Some("image") {
+        let attrs = node.get("attrs");
+        let alt = attrs
+            .and_then(|attrs| attrs.get("alt"))
+            .and_then(Value::as_str);
+        if alt.map_or(true, |alt| alt.trim().is_empty()) {
+            let src = attrs
+                .and_then(|attrs| attrs.get("src"))
+                .and_then(Value::as_str);
+            missing.push(src.unwrap_or_default().to_string());
+        }
+    }
+    if let Some(Value::Array(children)) = node.get("content") {
+        for child in children {
+            collect_missing_alt(child, missing);
+        }
+    }
+}
+
+fn write_children(w: &mut Writer, node: &Value) {
+    if let Some(Value::Array(children)) = node.get("content") {
+        for child in children {
+            write_node(w, child);
+        }
+    }
+}
+
+fn write_wrapped(w: &mut Writer, tag: &str, node: &Value) {
+    w.html.push('<');
+    w.html.push_str(tag);
+    w.html.push('>');
+    write_children(w, node);
+    w.html.push_str("</");
+    w.html.push_str(tag);
+    w.html.push('>');
+}
+
+fn write_node(w: &mut Writer, node: &Value) {
+    let node_type = node.get("type").and_then(Value::as_str).unwrap_or("");
+    if node_type != "text" {
+        if let Some(smartener) = &mut w.smartener {
+            smartener.reset();
+        }
+    }
+    match node_type {
+        "doc" => write_children(w, node),
+        "paragraph" => write_wrapped(w, "p", node),
+        "heading" => {
+            let level = heading_level(node);
+            let id = escape_html(&w.ids.assign(node));
+            w.html.push_str(&format!("<h{} id=\"{}\">", level, id));
+            write_children(w, node);
+            if w.options.heading_links {
+                w.html.push_str(&format!(
+                    "<a class=\"heading-link\" href=\"#{}\" \
+                     aria-label=\"Link to this section\">#</a>",
+                    id
+                ));
+            }
+            w.html.push_str(&format!("</h{}>", level));
+        }
+        "blockquote" => write_wrapped(w, "blockquote", node),
+        "bullet_list" | "bulletList" => write_wrapped(w, "ul", node),
+        "ordered_list" | "orderedList" => {
+            match attr(node, "order").and_then(Value::as_u64) {
+                Some(start) if start != 1 => w.html.push_str(&format!("<ol start=\"{}\">", start)),
+                _ => w.html.push_str("<ol>"),
+            }
+            write_children(w, node);
+            w.html.push_str("</ol>");
+        }
+        "list_item" | "listItem" => write_wrapped(w, "li", node),
+        "code_block" | "codeBlock" => {
+            w.html.push_str("<pre><code>");
+            // Typed exactly as it should appear
+            let smartener = w.smartener.take();
+            write_children(w, node);
+            w.smartener = smartener;
+            w.html.push_str("</code></pre>");
+        }
+        "horizontal_rule" | "horizontalRule" => w.html.push_str("<hr>"),
+        "hard_break" | "hardBreak" => w.html.push_str("<br>"),
+        "image" => {
+            let src = attr_str(node, "src").unwrap_or("");
+            let resolved = w.options.images.as_ref().and_then(|images| images.resolve(src));
+            let src = resolved.as_deref().unwrap_or(src);
+            let alt = attr_str(node, "alt").unwrap_or("");
+            w.html.push_str(&format!(
+                "<img src=\"{}\" alt=\"{}\"",
+                escape_html(src),
+                escape_html(alt)
+            ));
+            if let Some(title) = attr_str(node, "title") {
+                w.html.push_str(&format!(" title=\"{}\"", escape_html(title)));
+            }
+            w.html.push('>');
+        }
+        "text" => write_text(w, node),
+        // Unknown nodes keep their content so nothing the author wrote is lost
+        _ => write_children(w, node),
+    }
+}
+
+/// Escaped text, with the typography asked for unless it's code
+fn write_smart(html: &mut String, smartener: &mut Option<Smartener>, text: &str, is_code: bool) {
+    match smartener {
+        Some(smartener) if is_code => {
+            smartener.pass(text);
+            html.push_str(&escape_html(text));
+        }
+        Some(smartener) => html.push_str(&escape_html(&smartener.smarten(text))),
+        None => html.push_str(&escape_html(text)),
+    }
+}
+
+fn write_text(w: &mut Writer, node: &Value) {
+    let text = node.get("text").and_then(Value::as_str).unwrap_or("");
+    if text.is_empty() {
+        return;
+    }
+    let html = &mut w.html;
+
+    let marks: Vec<&Value> = match node.get("marks") {
+        Some(Value::Array(marks)) => marks.iter().collect(),
+        _ => Vec::new(),
+    };
+
+    let mut closing = Vec::new();
+    for mark in &marks {
+        let mark_type = mark.get("type").and_then(Value::as_str).unwrap_or("");
+        let (open, close) = match mark_type {
+            "strong" | "bold" => ("<strong>".to_string(), "</strong>"),
+            "em" | "italic" => ("<em>".to_string(), "</em>"),
+            "code" => ("<code>".to_string(), "</code>"),
+            "strike" | "strikethrough" => ("<s>".to_string(), "</s>"),
+            "underline" => ("<u>".to_string(), "</u>"),
+            "link" => {
+                let href = attr_str(mark, "href").unwrap_or("");
+                let open = match attr_str(mark, "title") {
+                    Some(title) => format!(
+                        "<a href=\"{}\" title=\"{}\">",
+                        escape_html(href),
+                        escape_html(title)
+                    ),
+                    None => format!("<a href=\"{}\">", escape_html(href)),
+                };
+                (open, "</a>")
+            }
+            _ => continue,
+        };
+        html.push_str(&open);
+        closing.push(close);
+    }
+
+    let is_code = closing.contains(&"</code>");
+    let settings = &w.options.emoji;
+    if is_code || settings.style == EmojiStyle::Off {
+        write_smart(html, &mut w.smartener, text, is_code);
+    } else {
+        for segment in emoji::segments(text) {
+            match segment {
+                Segment::Text(text) => write_smart(html, &mut w.smartener, text, false),
+                Segment::Emoji {
+                    shortcode,
+                    emoji: character,
+                } => {
+                    if let Some(smartener) = &mut w.smartener {
+                        smartener.pass(character);
+                    }
+                    match settings.style {
+                        EmojiStyle::Image => {
+                            let base = settings.image_base.as_deref().unwrap_or(TWEMOJI_BASE);
+                            html.push_str(&emoji::image_html(base, shortcode, character));
+                        }
+                        _ => html.push_str(character),
+                    }
+                }
+            }
+        }
+    }
+
+    for close in closing.iter().rev() {
+        html.push_str(close);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::asset_refs::AssetIndex;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_pm_to_html() {
+        let doc = json!({
+            "type": "doc",
+            "attrs": null,
+            "content": [
+                { "type": "heading", "attrs": { "level": 2 }, "content": [
+                    { "type": "text", "text": "Tom & Jerry", "marks": null }
+                ]},
+                { "type": "paragraph", "attrs": null, "content": [
+                    { "type": "text", "text": "bold", "marks": [{ "type": "strong", "attrs": {} }] },
+                    { "type": "hard_break" },
+                    { "type": "text", "text": "link", "marks": [
+                        { "type": "link", "attrs": { "href": "/about/" } },
+                        { "type": "em", "attrs": {} }
+                    ]}
+                ]},
+                { "type": "bullet_list", "content": [
+                    { "type": "list_item", "content": [
+                        { "type": "paragraph", "content": [{ "type": "text", "text": "one" }] }
+                    ]}
+                ]},
+                { "type": "image", "attrs": { "src": "/a.png", "alt": "A \"cat\"" } }
+            ]
+        });
+
+        assert_eq!(
+            pm_to_html(&doc),
+            "<h2 id=\"tom-jerry\">Tom &amp; Jerry</h2>\
+             <p><strong>bold</strong><br><a href=\"/about/\"><em>link</em></a></p>\
+             <ul><li><p>one</p></li></ul>\
+             <img src=\"/a.png\" alt=\"A &quot;cat&quot;\">"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_heading_ids() {
+        let heading = |text: &str| {
+            json!({ "type": "heading", "attrs": { "level": 2 }, "content": [
+                { "type": "text", "text": text }
+            ]})
+        };
+        let doc = json!({ "type": "doc", "content": [
+            heading("Getting started"),
+            heading("Getting Started!"),
+            heading("Getting started 2"),
+            heading("¿?"),
+            { "type": "heading", "attrs": { "level": 3, "id": "Custom" }, "content": [
+                { "type": "text", "text": "Renamed" }
+            ]}
+        ]});
+
+        let html = pm_to_html(&doc);
+        assert!(html.contains("<h2 id=\"getting-started\">"));
+        assert!(html.contains("<h2 id=\"getting-started-2\">Getting Started!"));
+        assert!(html.contains("<h2 id=\"getting-started-2-2\">"));
+        assert!(html.contains("<h2 id=\"section\">"));
+        assert!(html.contains("<h3 id=\"custom\">"));
+
+        let options = HtmlOptions {
+            heading_links: true,
+            ..HtmlOptions::default()
+        };
+        assert_eq!(
+            pm_to_html_with(&heading("Notes"), &options),
+            "<h2 id=\"notes\">Notes<a class=\"heading-link\" href=\"#notes\" \
+             aria-label=\"Link to this section\">#</a></h2>"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_typography() {
+        let doc = json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [
+                { "type": "text", "text": "\"Quoted " },
+                { "type": "text", "text": "bold", "marks": [{ "type": "strong" }] },
+                { "type": "text", "text": "\" --- and " },
+                { "type": "text", "text": "\"code\"", "marks": [{ "type": "code" }] }
+            ]},
+            { "type": "code_block", "content": [{ "type": "text", "text": "a -- b" }] }
+        ]});
+        let options = HtmlOptions {
+            typography: Typography {
+                quotes: true,
+                dashes: true,
+                ..Typography::default()
+            },
+            ..HtmlOptions::default()
+        };
+        assert_eq!(
+            pm_to_html_with(&doc, &options),
+            "<p>“Quoted <strong>bold</strong>” — and <code>&quot;code&quot;</code></p>\
+             <pre><code>a -- b</code></pre>"
+        );
+        assert!(pm_to_html(&doc).contains("&quot;Quoted"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_emoji() {
+        let doc = json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [
+                { "type": "text", "text": "We did it :tada: " },
+                { "type": "text", "text": ":tada:", "marks": [{ "type": "code" }] }
+            ]}
+        ]});
+        assert!(pm_to_html(&doc).contains("We did it :tada: "));
+
+        let mut options = HtmlOptions {
+            emoji: EmojiSettings {
+                style: EmojiStyle::Unicode,
+                image_base: None,
+            },
+            ..HtmlOptions::default()
+        };
+        assert_eq!(
+            pm_to_html_with(&doc, &options),
+            "<p>We did it 🎉 <code>:tada:</code></p>"
+        );
+
+        options.emoji = EmojiSettings {
+            style: EmojiStyle::Image,
+            image_base: Some("/emoji".to_string()),
+        };
+        assert!(pm_to_html_with(&doc, &options)
+            .contains("We did it <img class=\"emoji\" src=\"/emoji/1f389.svg\" alt=\"🎉\""));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resolved_images() {
+        let doc = json!({ "type": "doc", "content": [
+            { "type": "image", "attrs": { "src": "/assets/a1/march.jpg", "alt": "March" } },
+            { "type": "image", "attrs": { "src": "https://example.org/x.png", "alt": "X" } }
+        ]});
+        let index = AssetIndex::new(&[json!({
+            "id": "a1",
+            "name": "march-2024.jpg",
+            "url": "/assets/a1/march-2024.jpg"
+        })]);
+        let options = HtmlOptions {
+            images: Some(Arc::new(index)),
+            ..HtmlOptions::default()
+        };
+        assert_eq!(
+            pm_to_html_with(&doc, &options),
+            "<img src=\"/assets/a1/march-2024.jpg\" alt=\"March\">\
+             <img src=\"https://example.org/x.png\" alt=\"X\">"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! iCalendar (`.ics`) feeds for events collections.
+//!
+//! A collection counts as events-style when it has a datetime field named
+//! `start`, `start_date` or `date`. An `end`/`end_date` datetime field and a
+//! `location` field are used when present.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde_json::Value;
+
+use crate::types::{FieldDefinition, FieldType};
+
+const START_FIELDS: [&str; 3] = ["start", "start_date", "date"];
+const END_FIELDS: [&str; 2] = ["end", "end_date"];
+
+/// Which entry fields describe an event
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventFields {
+    pub start: String,
+    pub end: Option<String>,
+    pub location: Option<String>,
+}
+
+/// Detect an events-style collection from its field definitions
+pub fn event_fields(fields: &[FieldDefinition]) -> Option<EventFields> {
+    let datetime_field = |names: &[&str]| {
+        names.iter().find_map(|name| {
+            fields
+                .iter()
+                .find(|field| {
+                    field.name == *name && matches!(field.field_type, FieldType::DateTime)
+                })
+                .map(|field| field.name.clone())
+        })
+    };
+
+    Some(EventFields {
+        start: datetime_field(&START_FIELDS)?,
+        end: datetime_field(&END_FIELDS),
+        location: fields
+            .iter()
+            .find(|field| field.name == "location")
+            .map(|field| field.name.clone()),
+    })
+}
+
+/// Escape a TEXT value (RFC 5545 §3.3.11)
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line to 75 octets without splitting a UTF-8 character
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        let len = c.len_utf8();
+        if width + len > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += len;
+    }
+    folded
+}
+
+/// A DTSTART/DTEND property for a stored date or date-time value
+///
+/// An all-day DTEND is exclusive, so it's the day after the last day.
+fn date_property(name: &str, value: &str) -> Option<String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        let utc = datetime.with_timezone(&Utc);
+        return Some(format!("{}:{}", name, utc.format("%Y%m%dT%H%M%SZ")));
+    }
+    if let Ok(datetime) = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S") {
+        // No timezone was recorded, so this is a floating local time
+        return Some(format!("{}:{}", name, datetime.format("%Y%m%dT%H%M%S")));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let date = if name == "DTEND" {
+            date.succ_opt()?
+        } else {
+            date
+        };
+        return Some(format!("{};VALUE=DATE:{}", name, date.format("%Y%m%d")));
+    }
+    None
+}
+
+fn entry_str<'a>(entry: &'a Value, field: &str) -> Option<&'a str> {
+    entry
+        .get(field)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+/// Build a calendar from a collection's entries
+///
+/// `domain` makes event UIDs globally unique; `site_url` (if any) turns
+/// relative entry URLs into absolute links; `dtstamp` is the generation
+/// time in UTC. Entries without a start or an id are left out, as neither
+/// can be placed on a calendar.
+pub fn entries_to_ics(
+    calendar_name: &str,
+    fields: &EventFields,
+    entries: &[Value],
+    domain: &str,
+    site_url: Option<&str>,
+    dtstamp: &DateTime<Utc>,
+) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//organ-pages//minissg//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(calendar_name)),
+    ];
+
+    for entry in entries {
+        let start = entry_str(entry, &fields.start).and_then(|s| date_property("DTSTART", s));
+        let start = match start {
+            Some(start) => start,
+            None => continue,
+        };
+
+        // The UID has to stay the same from one export to the next
+        let id = match entry_str(entry, "id") {
+            Some(id) => id,
+            None => continue,
+        };
+        let summary = entry_str(entry, "title")
+            .or_else(|| entry_str(entry, "name"))
+            .unwrap_or("");
+
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}@{}", id, domain));
+        lines.push(format!("DTSTAMP:{}", dtstamp.format("%Y%m%dT%H%M%SZ")));
+        lines.push(start);
+        if let Some(end) = fields
+            .end
+            .as_ref()
+            .and_then(|field| entry_str(entry, field))
+            .and_then(|end| date_property("DTEND", end))
+        {
+            lines.push(end);
+        }
+        lines.push(format!("SUMMARY:{}", escape_text(summary)));
+        if let Some(location) = fields
+            .location
+            .as_ref()
+            .and_then(|field| entry_str(entry, field))
+        {
+            lines.push(format!("LOCATION:{}", escape_text(location)));
+        }
+        if let Some(description) = entry_str(entry, "description") {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        if let Some(url) = entry_str(entry, "url") {
+            let url = match site_url {
+                Some(site_url) if !url.contains("://") => {
+                    format!("{}{}", site_url.trim_end_matches('/'), url)
+                }
+                _ => url.to_string(),
+            };
+            lines.push(format!("URL:{}", url));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = String::new();
+    for line in lines {
+        ics.push_str(&fold_line(&line));
+        ics.push_str("\r\n");
+    }
+    ics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn field(name: &str, field_type: FieldType) -> FieldDefinition {
+        FieldDefinition {
+            name: name.to_string(),
+            field_type,
+            required: false,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_event_fields_detection() {
+        let fields = vec![
+            field("start", FieldType::DateTime),
+            field("end", FieldType::DateTime),
+            field("location", FieldType::String),
+        ];
+        assert_eq!(
+            event_fields(&fields),
+            Some(EventFields {
+                start: "start".to_string(),
+                end: Some("end".to_string()),
+                location: Some("location".to_string()),
+            })
+        );
+
+        // A text field called "date" isn't enough
+        assert_eq!(event_fields(&[field("date", FieldType::String)]), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_entries_to_ics() {
+        let fields = EventFields {
+            start: "date".to_string(),
+            end: Some("end".to_string()),
+            location: Some("location".to_string()),
+        };
+        let entries = vec![
+            json!({
+                "id": "abc",
+                "name": "Zine Fair",
+                "date": "2025-05-03",
+                "location": "Library, Room 2",
+                "url": "/events/zine-fair/"
+            }),
+            json!({ "id": "def", "name": "Reading", "date": "2025-04-12T19:30:00+10:00" }),
+            json!({ "id": "ghi", "name": "Undated" }),
+            json!({ "name": "No id", "date": "2025-06-01" }),
+            json!({
+                "id": "jkl",
+                "name": "Residency",
+                "date": "2025-07-01",
+                "end": "2025-07-03",
+                "url": "https://residency.example/"
+            }),
+        ];
+        let dtstamp = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+
+        let ics = entries_to_ics(
+            "Events",
+            &fields,
+            &entries,
+            "site-id",
+            Some("https://zine.example/"),
+            &dtstamp,
+        );
+
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 3);
+        assert!(!ics.contains("UID:@"));
+        assert!(ics.contains("UID:abc@site-id\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20250503\r\n"));
+        assert!(ics.contains("LOCATION:Library\\, Room 2\r\n"));
+        assert!(ics.contains("URL:https://zine.example/events/zine-fair/\r\n"));
+        assert!(ics.contains("DTSTART:20250412T093000Z\r\n"));
+        assert!(ics.contains("DTSTAMP:20250101T000000Z\r\n"));
+        // The last day is the 3rd, so the event ends as the 4th begins
+        assert!(ics.contains("DTEND;VALUE=DATE:20250704\r\n"));
+        assert!(ics.contains("URL:https://residency.example/\r\n"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_fold_line() {
+        let line = format!("DESCRIPTION:{}", "é".repeat(60));
+        let folded = fold_line(&line);
+        for part in folded.split("\r\n") {
+            assert!(part.len() <= 75);
+        }
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
@@ -0,0 +1,203 @@
+//! Internal links between pages.
+//!
+//! Link marks in page and post bodies are collected at render time and
+//! inverted into a backlinks index, so each page can list the pages that
+//! mention it (`{{#each backlinks}}`), wiki-style.
+
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+
+use super::site::{page_url, PageSource};
+
+/// Link targets in a ProseMirror node, in document order
+pub fn link_hrefs(node: &Value) -> Vec<String> {
+    let mut hrefs = Vec::new();
+    collect_hrefs(node, &mut hrefs);
+    hrefs
+}
+
+fn collect_hrefs(node: &Value, hrefs: &mut Vec<String>) {
+    if let Some(Value::Array(marks)) = node.get("marks") {
+        for mark in marks {
+            if mark.get("type").and_then(Value::as_str) != Some("link") {
+                continue;
+            }
+            if let Some(href) = mark.pointer("/attrs/href").and_then(Value::as_str) {
+                hrefs.push(href.to_string());
+            }
+        }
+    }
+    if let Some(Value::Array(children)) = node.get("content") {
+        for child in children {
+            collect_hrefs(child, hrefs);
+        }
+    }
+}
+
+/// The site URL a link on the page at `from` points to, if it is internal
+///
+/// Query strings and fragments are dropped, relative links are resolved and
+/// directory URLs get their trailing slash, so links compare equal to
+/// [`page_url`].
+pub fn resolve_internal(from: &str, href: &str) -> Option<String> {
+    let href = href.trim();
+    let path = href.split(|c| c == '#' || c == '?').next().unwrap_or("");
+    if path.is_empty() || path.starts_with("//") || path.contains(':') {
+        return None;
+    }
+
+    let joined = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        let base = &from[..from.rfind('/').map_or(0, |i| i + 1)];
+        format!("{}{}", base, path)
+    };
+
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    if segments.last() == Some(&"index.html") {
+        segments.pop();
+        return Some(directory_url(&segments));
+    }
+    match segments.last() {
+        Some(last) if last.contains('.') => Some(format!("/{}", segments.join("/"))),
+        _ => Some(directory_url(&segments)),
+    }
+}
+
+fn directory_url(segments: &[&str]) -> String {
+    if segments.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}/", segments.join("/"))
+    }
+}
+
+/// What a backlink shows: enough to link back to the page
+fn backlink(page: &PageSource) -> Value {
+    let title = page
+        .meta
+        .get("title")
+        .and_then(Value::as_str)
+        .filter(|title| !title.is_empty())
+        .unwrap_or(&page.name);
+    json!({
+        "name": page.name,
+        "title": title,
+        "url": page_url(page),
+        "collection": page.collection,
+    })
+}
+
+/// Target URL → the pages linking to it, in site order
+///
+/// Each page is listed once per target however often it links there, and
+/// pages linking to themselves are left out.
+pub fn backlinks_index(pages: &[PageSource]) -> BTreeMap<String, Vec<Value>> {
+    let mut index: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for page in pages {
+        let from = page_url(page);
+        let mut targets: Vec<String> = link_hrefs(&page.body)
+            .iter()
+            .filter_map(|href| resolve_internal(&from, href))
+            .filter(|target| *target != from)
+            .collect();
+        targets.sort();
+        targets.dedup();
+
+        for target in targets {
+            index.entry(target).or_default().push(backlink(page));
+        }
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+    use wasm_bindgen_test::*;
+
+    fn page(collection: &str, name: &str, hrefs: &[&str]) -> PageSource {
+        let links: Vec<Value> = hrefs
+            .iter()
+            .map(|href| {
+                json!({ "type": "text", "text": "link", "marks": [
+                    { "type": "link", "attrs": { "href": href } }
+                ]})
+            })
+            .collect();
+        PageSource {
+            collection: collection.to_string(),
+            name: name.to_string(),
+            meta: Map::new(),
+            body: json!({ "type": "doc", "content": [
+                { "type": "paragraph", "content": links }
+            ]}),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resolve_internal() {
+        let from = "/posts/hello/";
+        assert_eq!(
+            resolve_internal(from, "/about"),
+            Some("/about/".to_string())
+        );
+        assert_eq!(
+            resolve_internal(from, "../other/#intro"),
+            Some("/posts/other/".to_string())
+        );
+        assert_eq!(
+            resolve_internal(from, "/index.html?ref=nav"),
+            Some("/".to_string())
+        );
+        assert_eq!(
+            resolve_internal(from, "/style.css"),
+            Some("/style.css".to_string())
+        );
+        assert_eq!(resolve_internal(from, "#top"), None);
+        assert_eq!(resolve_internal(from, "https://example.com/"), None);
+        assert_eq!(resolve_internal(from, "//example.com/"), None);
+        assert_eq!(resolve_internal(from, "mailto:hi@example.com"), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_backlinks_index() {
+        let pages = vec![
+            page("page", "about", &["/posts/hello/", "/about/"]),
+            page(
+                "post",
+                "hello",
+                &["/about", "/about/#team", "https://example.com"],
+            ),
+            page("post", "again", &["../hello/"]),
+        ];
+        let index = backlinks_index(&pages);
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(
+            index["/about/"],
+            vec![json!({
+                "name": "hello",
+                "title": "hello",
+                "url": "/posts/hello/",
+                "collection": "post"
+            })]
+        );
+        let names: Vec<&str> = index["/posts/hello/"]
+            .iter()
+            .map(|backlink| backlink["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["about", "again"]);
+    }
+}
@@ -0,0 +1,182 @@
+//! Listing pages for data collections.
+//!
+//! A collection's [`ListingConfig`] decides which entries appear, in what
+//! order, and how they are grouped. The result is plain JSON handed to the
+//! listing template as `entries` and `groups`.
+
+use std::cmp::Ordering;
+
+use chrono::NaiveDate;
+use serde_json::{json, Value};
+
+use crate::types::{DateGrouping, ListingConfig};
+
+/// Order two field values: numbers numerically, everything else as text
+/// (ISO dates sort correctly as text). Missing values sort last.
+fn compare_values(a: Option<&Value>, b: Option<&Value>) -> Ordering {
+    match (a, b) {
+        (Some(Value::Number(a)), Some(Value::Number(b))) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Some(a), Some(b)) => value_text(a).cmp(&value_text(b)),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Group key and human-readable label for a value
+fn group_key(value: Option<&Value>, grouping: Option<DateGrouping>) -> (String, String) {
+    let text = value.map(value_text).unwrap_or_default();
+    let grouping = match grouping {
+        Some(grouping) => grouping,
+        None => return (text.clone(), text),
+    };
+
+    let date = text
+        .get(..10)
+        .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok());
+    match date {
+        Some(date) => match grouping {
+            DateGrouping::Day => (
+                date.format("%Y-%m-%d").to_string(),
+                date.format("%A %-d %B %Y").to_string(),
+            ),
+            DateGrouping::Month => (
+                date.format("%Y-%m").to_string(),
+                date.format("%B %Y").to_string(),
+            ),
+            DateGrouping::Year => (
+                date.format("%Y").to_string(),
+                date.format("%Y").to_string(),
+            ),
+        },
+        None => (text.clone(), text),
+    }
+}
+
+/// Filter, sort and group entries for a listing page
+///
+/// `today` is an ISO date (`YYYY-MM-DD`) used for `upcoming_field`.
+pub fn build_listing(config: &ListingConfig, entries: &[Value], today: &str) -> Value {
+    let mut entries: Vec<Value> = entries
+        .iter()
+        .filter(|entry| match &config.upcoming_field {
+            Some(field) => entry
+                .get(field)
+                .and_then(Value::as_str)
+                .map(|date| date.get(..10).unwrap_or(date) >= today)
+                .unwrap_or(false),
+            None => true,
+        })
+        .cloned()
+        .collect();
+
+    if let Some(sort_by) = &config.sort_by {
+        entries.sort_by(|a, b| {
+            let ordering = compare_values(a.get(sort_by), b.get(sort_by));
+            if config.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+    }
+
+    let mut groups: Vec<(String, String, Vec<Value>)> = Vec::new();
+    if let Some(group_by) = &config.group_by {
+        for entry in &entries {
+            let (key, label) = group_key(entry.get(group_by), config.group_by_date);
+            match groups.iter_mut().find(|(k, _, _)| *k == key) {
+                Some((_, _, members)) => members.push(entry.clone()),
+                None => groups.push((key, label, vec![entry.clone()])),
+            }
+        }
+    }
+
+    let groups: Vec<Value> = groups
+        .into_iter()
+        .map(|(key, label, entries)| json!({ "key": key, "label": label, "entries": entries }))
+        .collect();
+
+    json!({
+        "entries": entries,
+        "groups": groups,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn config() -> ListingConfig {
+        ListingConfig {
+            path: "events".to_string(),
+            template: "events".to_string(),
+            detail_template: None,
+            sort_by: Some("date".to_string()),
+            descending: false,
+            group_by: Some("date".to_string()),
+            group_by_date: Some(DateGrouping::Month),
+            upcoming_field: Some("date".to_string()),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_build_listing_groups_upcoming_by_month() {
+        let entries = vec![
+            json!({ "name": "picnic", "date": "2025-04-02" }),
+            json!({ "name": "reading", "date": "2025-03-21T19:30:00" }),
+            json!({ "name": "past", "date": "2025-01-10" }),
+            json!({ "name": "undated" }),
+            json!({ "name": "launch", "date": "2025-03-28" }),
+        ];
+
+        let listing = build_listing(&config(), &entries, "2025-03-01");
+
+        let names: Vec<&str> = listing["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|e| e["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, vec!["reading", "launch", "picnic"]);
+
+        let groups = listing["groups"].as_array().unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0]["key"], "2025-03");
+        assert_eq!(groups[0]["label"], "March 2025");
+        assert_eq!(groups[0]["entries"].as_array().unwrap().len(), 2);
+        assert_eq!(groups[1]["label"], "April 2025");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_build_listing_sorts_numbers_descending() {
+        let mut config = config();
+        config.sort_by = Some("capacity".to_string());
+        config.descending = true;
+        config.group_by = None;
+        config.upcoming_field = None;
+
+        let entries = vec![
+            json!({ "name": "a", "capacity": 9 }),
+            json!({ "name": "b", "capacity": 40 }),
+            json!({ "name": "c", "capacity": 120 }),
+        ];
+        let listing = build_listing(&config, &entries, "2025-01-01");
+
+        assert_eq!(listing["entries"][0]["name"], "c");
+        assert_eq!(listing["entries"][2]["name"], "a");
+        assert_eq!(listing["groups"], json!([]));
+    }
+}
@@ -0,0 +1,271 @@
+//! Describing rendered output for the frontend: content types, hashes and
+//! encodings that let a service worker serve the site without a server.
+
+use serde_json::{json, Map, Value};
+
+use super::site::SiteOutput;
+
+/// Content type for an output path, from its extension
+pub fn mime_type_for_path(path: &str) -> &'static str {
+    let extension = path
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "rss" => "application/rss+xml",
+        "atom" => "application/atom+xml",
+        "ics" => "text/calendar; charset=utf-8",
+        "txt" | "md" | "gmi" => "text/plain; charset=utf-8",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "avif" => "image/avif",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "mp3" => "audio/mpeg",
+        "mp4" => "video/mp4",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Short content hash (64-bit FNV-1a, hex) for cache busting and change
+/// detection. Not suitable where collisions could be forced deliberately.
+pub fn content_hash(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard (padded) base64, for carrying bytes through JSON
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        encoded.push(BASE64_ALPHABET[(n >> 18) as usize & 63] as char);
+        encoded.push(BASE64_ALPHABET[(n >> 12) as usize & 63] as char);
+        encoded.push(if chunk.len() > 1 {
+            BASE64_ALPHABET[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        encoded.push(if chunk.len() > 2 {
+            BASE64_ALPHABET[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+    encoded
+}
+
+/// Bytes from standard base64, padded or not
+pub fn base64_decode(encoded: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<u32> = encoded
+        .trim_end_matches('=')
+        .bytes()
+        .map(|c| {
+            BASE64_ALPHABET
+                .iter()
+                .position(|&digit| digit == c)
+                .map(|value| value as u32)
+                .ok_or_else(|| format!("Invalid base64 character: {}", c as char))
+        })
+        .collect::<Result<_, _>>()?;
+    if digits.len() % 4 == 1 {
+        return Err("Invalid base64 length".to_string());
+    }
+
+    let mut bytes = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0, |n, (i, digit)| n | digit << (18 - 6 * i));
+        bytes.push((n >> 16) as u8);
+        if chunk.len() > 2 {
+            bytes.push((n >> 8) as u8);
+        }
+        if chunk.len() > 3 {
+            bytes.push(n as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Every output path (rendered files and assets) with its bytes and type
+pub fn output_files(output: &SiteOutput) -> Vec<(String, Vec<u8>, String)> {
+    let mut files: Vec<(String, Vec<u8>, String)> = output
+        .files
+        .iter()
+        .map(|(path, contents)| {
+            (
+                path.clone(),
+                contents.as_bytes().to_vec(),
+                mime_type_for_path(path).to_string(),
+            )
+        })
+        .collect();
+    files.extend(
+        output
+            .assets
+            .iter()
+            .map(|asset| (asset.path.clone(), asset.data.clone(), asset.mime_type.clone())),
+    );
+    files
+}
+
+/// Output path → content hash
+pub fn output_hashes(output: &SiteOutput) -> Map<String, Value> {
+    output_files(output)
+        .into_iter()
+        .map(|(path, bytes, _)| (path, json!(content_hash(&bytes))))
+        .collect()
+}
+
+/// Paths added, changed and removed between two sets of output hashes
+pub fn publish_diff(previous: &Map<String, Value>, current: &Map<String, Value>) -> Value {
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    let mut unchanged = 0;
+    for (path, hash) in current {
+        match previous.get(path) {
+            None => added.push(path.clone()),
+            Some(previous_hash) if previous_hash != hash => changed.push(path.clone()),
+            Some(_) => unchanged += 1,
+        }
+    }
+    let removed: Vec<&String> = previous
+        .keys()
+        .filter(|path| !current.contains_key(*path))
+        .collect();
+
+    json!({
+        "added": added,
+        "changed": changed,
+        "removed": removed,
+        "unchanged": unchanged,
+    })
+}
+
+/// Path → {body (base64), mime, hash, size}, plus a version hash that
+/// changes whenever any file does
+pub fn preview_manifest(output: &SiteOutput) -> Value {
+    let mut files = Map::new();
+    let mut hashes = String::new();
+    for (path, bytes, mime) in output_files(output) {
+        let hash = content_hash(&bytes);
+        hashes.push_str(&path);
+        hashes.push_str(&hash);
+        files.insert(
+            path,
+            json!({
+                "body": base64_encode(&bytes),
+                "mime": mime,
+                "hash": hash,
+                "size": bytes.len(),
+            }),
+        );
+    }
+
+    json!({
+        "version": content_hash(hashes.as_bytes()),
+        "files": files,
+        "warnings": output.warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::site::SiteAsset;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_base64_decode() {
+        let samples: [&[u8]; 6] = [b"", b"f", b"fo", b"foo", b"foobar", &[0, 255, 128, 7]];
+        for bytes in samples.iter() {
+            assert_eq!(base64_decode(&base64_encode(bytes)).unwrap(), *bytes);
+        }
+        assert_eq!(base64_decode("Zm8").unwrap(), b"fo");
+        assert!(base64_decode("Zm9v!").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_mime_type_for_path() {
+        assert_eq!(mime_type_for_path("index.html"), "text/html; charset=utf-8");
+        assert_eq!(mime_type_for_path("events.ics"), "text/calendar; charset=utf-8");
+        assert_eq!(mime_type_for_path("assets/a/photo.JPG"), "image/jpeg");
+        assert_eq!(mime_type_for_path("v1.2/README"), "application/octet-stream");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_publish_diff() {
+        let previous = json!({ "index.html": "a", "about/index.html": "b", "old.css": "c" });
+        let current = json!({ "index.html": "a", "about/index.html": "x", "new.css": "d" });
+
+        let diff = publish_diff(
+            previous.as_object().unwrap(),
+            current.as_object().unwrap(),
+        );
+        assert_eq!(diff["added"], json!(["new.css"]));
+        assert_eq!(diff["changed"], json!(["about/index.html"]));
+        assert_eq!(diff["removed"], json!(["old.css"]));
+        assert_eq!(diff["unchanged"], json!(1));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_preview_manifest() {
+        let mut output = SiteOutput::default();
+        output
+            .files
+            .insert("index.html".to_string(), "<h1>Hi</h1>".to_string());
+        output.assets.push(SiteAsset {
+            path: "assets/1/logo.png".to_string(),
+            mime_type: "image/png".to_string(),
+            data: vec![0x89, 0x50, 0x4e, 0x47],
+        });
+
+        let manifest = preview_manifest(&output);
+        assert_eq!(
+            manifest["files"]["index.html"]["body"],
+            json!(base64_encode(b"<h1>Hi</h1>"))
+        );
+        assert_eq!(manifest["files"]["assets/1/logo.png"]["mime"], json!("image/png"));
+        assert_eq!(manifest["files"]["assets/1/logo.png"]["size"], json!(4));
+        let version = manifest["version"].clone();
+
+        output
+            .files
+            .insert("index.html".to_string(), "<h1>Hello</h1>".to_string());
+        assert_ne!(preview_manifest(&output)["version"], version);
+    }
+}
@@ -0,0 +1,336 @@
+//! Markdown and plain-text mirrors of rendered pages.
+//!
+//! Each page can be written a second and third time as `index.md` and
+//! `index.txt` beside its `index.html`, for text browsers, feed readers and
+//! tools that would rather not parse HTML. The HTML links to its mirrors
+//! with `<link rel="alternate">` so they can be discovered.
+
+use serde_json::Value;
+
+use super::output_path;
+use super::text::pm_to_text;
+use crate::types::TextMirrors;
+
+/// A mirror of a page: where it's published, its type and contents
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mirror {
+    pub url: String,
+    pub mime_type: &'static str,
+    pub contents: String,
+}
+
+fn node_type(node: &Value) -> &str {
+    node.get("type").and_then(Value::as_str).unwrap_or("")
+}
+
+fn attr_str<'a>(node: &'a Value, name: &str) -> Option<&'a str> {
+    node.get("attrs")
+        .and_then(|attrs| attrs.get(name))
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+fn children(node: &Value) -> &[Value] {
+    match node.get("content") {
+        Some(Value::Array(children)) => children,
+        _ => &[],
+    }
+}
+
+/// Backslash-escape characters Markdown would read as formatting
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']' | '<') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn write_text(markdown: &mut String, node: &Value) {
+    let text = node.get("text").and_then(Value::as_str).unwrap_or("");
+    if text.is_empty() {
+        return;
+    }
+    let marks = match node.get("marks") {
+        Some(Value::Array(marks)) => marks.as_slice(),
+        _ => &[],
+    };
+    let is_code = marks.iter().any(|mark| node_type(mark) == "code");
+
+    let mut closing = Vec::new();
+    for mark in marks {
+        let (open, close) = match node_type(mark) {
+            "strong" | "bold" => ("**", "**".to_string()),
+            "em" | "italic" => ("*", "*".to_string()),
+            "code" => ("`", "`".to_string()),
+            "strike" | "strikethrough" => ("~~", "~~".to_string()),
+            "link" => ("[", format!("]({})", attr_str(mark, "href").unwrap_or(""))),
+            _ => continue,
+        };
+        markdown.push_str(open);
+        closing.push(close);
+    }
+    if is_code {
+        markdown.push_str(text);
+    } else {
+        markdown.push_str(&escape_markdown(text));
+    }
+    for close in closing.iter().rev() {
+        markdown.push_str(close);
+    }
+}
+
+fn write_inline(markdown: &mut String, node: &Value) {
+    for child in children(node) {
+        match node_type(child) {
+            "text" => write_text(markdown, child),
+            "hard_break" | "hardBreak" => markdown.push_str("\\\n"),
+            "image" => write_image(markdown, child),
+            _ => write_inline(markdown, child),
+        }
+    }
+}
+
+fn write_image(markdown: &mut String, node: &Value) {
+    let src = attr_str(node, "src").unwrap_or("");
+    let alt = attr_str(node, "alt").unwrap_or("");
+    markdown.push_str(&format!("![{}]({})", escape_markdown(alt), src));
+}
+
+/// Prefix every line but the first of `block` with `indent`
+fn indent_lines(block: &str, first: &str, indent: &str) -> String {
+    block
+        .lines()
+        .enumerate()
+        .map(|(index, line)| match (index, line.is_empty()) {
+            (0, _) => format!("{}{}", first, line),
+            (_, true) => String::new(),
+            _ => format!("{}{}", indent, line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn write_list(blocks: &mut Vec<String>, node: &Value, ordered: bool) {
+    let start = node
+        .get("attrs")
+        .and_then(|attrs| attrs.get("order"))
+        .and_then(Value::as_u64)
+        .unwrap_or(1);
+    let items: Vec<String> = children(node)
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let marker = if ordered {
+                format!("{}. ", start + index as u64)
+            } else {
+                "- ".to_string()
+            };
+            let mut item_blocks = Vec::new();
+            for child in children(item) {
+                write_block(&mut item_blocks, child);
+            }
+            let indent = " ".repeat(marker.len());
+            indent_lines(&item_blocks.join("\n"), &marker, &indent)
+        })
+        .collect();
+    blocks.push(items.join("\n"));
+}
+
+fn write_block(blocks: &mut Vec<String>, node: &Value) {
+    let mut markdown = String::new();
+    match node_type(node) {
+        "doc" => {
+            for child in children(node) {
+                write_block(blocks, child);
+            }
+            return;
+        }
+        "paragraph" => write_inline(&mut markdown, node),
+        "heading" => {
+            let level = node
+                .get("attrs")
+                .and_then(|attrs| attrs.get("level"))
+                .and_then(Value::as_u64)
+                .unwrap_or(1)
+                .clamp(1, 6);
+            markdown.push_str(&"#".repeat(level as usize));
+            markdown.push(' ');
+            write_inline(&mut markdown, node);
+        }
+        "blockquote" => {
+            let mut quoted = Vec::new();
+            for child in children(node) {
+                write_block(&mut quoted, child);
+            }
+            markdown = quoted
+                .join("\n\n")
+                .lines()
+                .map(|line| format!("> {}", line).trim_end().to_string())
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+        "bullet_list" | "bulletList" => return write_list(blocks, node, false),
+        "ordered_list" | "orderedList" => return write_list(blocks, node, true),
+        "code_block" | "codeBlock" => {
+            let code: String = children(node)
+                .iter()
+                .filter_map(|child| child.get("text").and_then(Value::as_str))
+                .collect();
+            let language = attr_str(node, "language").unwrap_or("");
+            markdown = format!("```{}\n{}\n```", language, code.trim_end_matches('\n'));
+        }
+        "horizontal_rule" | "horizontalRule" => markdown.push_str("---"),
+        "image" => write_image(&mut markdown, node),
+        // Unknown nodes keep their content so nothing the author wrote is lost
+        _ => write_inline(&mut markdown, node),
+    }
+    if !markdown.is_empty() {
+        blocks.push(markdown);
+    }
+}
+
+/// The Markdown equivalent of a ProseMirror document
+pub fn pm_to_markdown(doc: &Value) -> String {
+    let mut blocks = Vec::new();
+    write_block(&mut blocks, doc);
+    blocks.join("\n\n")
+}
+
+/// URL of a page's mirror: `/about/` → `/about/index.md`
+pub fn mirror_url(page_url: &str, extension: &str) -> String {
+    let path = output_path(page_url);
+    let stem = path.strip_suffix(".html").unwrap_or(&path);
+    format!("/{}.{}", stem, extension)
+}
+
+/// The mirrors `settings` asks for, each headed by the page title
+pub fn page_mirrors(settings: &TextMirrors, url: &str, title: &str, body: &Value) -> Vec<Mirror> {
+    let mut mirrors = Vec::new();
+    if settings.markdown {
+        mirrors.push(Mirror {
+            url: mirror_url(url, "md"),
+            mime_type: "text/markdown",
+            contents: format!("# {}\n\n{}\n", escape_markdown(title), pm_to_markdown(body)),
+        });
+    }
+    if settings.text {
+        mirrors.push(Mirror {
+            url: mirror_url(url, "txt"),
+            mime_type: "text/plain",
+            contents: format!("{}\n\n{}\n", title, pm_to_text(body)),
+        });
+    }
+    mirrors
+}
+
+/// Add `<link rel="alternate">`s for the mirrors to a page's head
+///
+/// Pages without a `</head>` are left alone.
+pub fn link_mirrors(html: &str, mirrors: &[Mirror]) -> String {
+    let index = match html.to_ascii_lowercase().find("</head>") {
+        Some(index) if !mirrors.is_empty() => index,
+        _ => return html.to_string(),
+    };
+    let links: String = mirrors
+        .iter()
+        .map(|mirror| {
+            format!(
+                "<link rel=\"alternate\" type=\"{}\" href=\"{}\">\n",
+                mirror.mime_type, mirror.url
+            )
+        })
+        .collect();
+    format!("{}{}{}", &html[..index], links, &html[index..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn text(text: &str) -> Value {
+        json!({ "type": "text", "text": text })
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pm_to_markdown() {
+        let doc = json!({ "type": "doc", "content": [
+            { "type": "heading", "attrs": { "level": 2 }, "content": [text("Zine fair")] },
+            { "type": "paragraph", "content": [
+                text("Tables at the "),
+                { "type": "text", "text": "town hall", "marks": [
+                    { "type": "link", "attrs": { "href": "/venue/" } }
+                ]},
+                text(" on "),
+                { "type": "text", "text": "*Saturday*", "marks": [{ "type": "strong" }] },
+                { "type": "hard_break" },
+                { "type": "text", "text": "git_log", "marks": [{ "type": "code" }] }
+            ]},
+            { "type": "bullet_list", "content": [
+                { "type": "list_item", "content": [
+                    { "type": "paragraph", "content": [text("Comics")] },
+                    { "type": "ordered_list", "content": [
+                        { "type": "list_item", "content": [
+                            { "type": "paragraph", "content": [text("Minicomics")] }
+                        ]}
+                    ]}
+                ]},
+                { "type": "list_item", "content": [
+                    { "type": "paragraph", "content": [text("Poetry")] }
+                ]}
+            ]},
+            { "type": "blockquote", "content": [
+                { "type": "paragraph", "content": [text("Bring cash")] },
+                { "type": "paragraph", "content": [text("Or cards")] }
+            ]},
+            { "type": "code_block", "attrs": { "language": "sh" }, "content": [text("ls *\n")] },
+            { "type": "horizontal_rule" },
+            { "type": "image", "attrs": { "src": "/assets/1/map.png", "alt": "Map" } }
+        ]});
+
+        assert_eq!(
+            pm_to_markdown(&doc),
+            "## Zine fair\n\n\
+             Tables at the [town hall](/venue/) on **\\*Saturday\\***\\\n`git_log`\n\n\
+             - Comics\n  1. Minicomics\n- Poetry\n\n\
+             > Bring cash\n>\n> Or cards\n\n\
+             ```sh\nls *\n```\n\n\
+             ---\n\n\
+             ![Map](/assets/1/map.png)"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_page_mirrors() {
+        assert_eq!(mirror_url("/", "md"), "/index.md");
+        assert_eq!(mirror_url("/about/", "txt"), "/about/index.txt");
+        assert_eq!(mirror_url("/feed.html", "md"), "/feed.md");
+
+        let body = json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [text("Hello")] }
+        ]});
+        let settings = TextMirrors {
+            markdown: true,
+            text: true,
+        };
+        let mirrors = page_mirrors(&settings, "/about/", "About", &body);
+        assert_eq!(mirrors.len(), 2);
+        assert_eq!(mirrors[0].contents, "# About\n\nHello\n");
+        assert_eq!(mirrors[1].contents, "About\n\nHello\n");
+
+        let html = link_mirrors("<html><head><title>About</title></head></html>", &mirrors);
+        assert_eq!(
+            html,
+            "<html><head><title>About</title>\
+             <link rel=\"alternate\" type=\"text/markdown\" href=\"/about/index.md\">\n\
+             <link rel=\"alternate\" type=\"text/plain\" href=\"/about/index.txt\">\n\
+             </head></html>"
+        );
+        assert_eq!(link_mirrors("<p>No head</p>", &mirrors), "<p>No head</p>");
+    }
+}
@@ -0,0 +1,180 @@
+//! Rendering: turns a site and its theme into output files.
+//!
+//! Rich-text bodies are converted to HTML ([`html`]), placed into the
+//! theme's Handlebars templates ([`Renderer`]) and assembled into a set of
+//! output paths by [`site::render_site`]. Everything here works on plain
+//! data gathered by the store, so it can be tested without IndexedDB.
+
+use handlebars::{Handlebars, HelperDef};
+use serde_json::Value;
+
+pub mod asset_refs;
+pub mod attribution;
+pub mod block_refs;
+pub mod blocks;
+pub mod comments;
+pub mod credits;
+pub mod css;
+pub mod dependents;
+pub mod emoji;
+pub mod form;
+pub mod gemtext;
+pub mod graph;
+pub mod head;
+pub mod hooks;
+pub mod html;
+pub mod ical;
+pub mod links;
+pub mod listing;
+pub mod manifest;
+pub mod mirrors;
+pub mod outline;
+pub mod partial;
+pub mod permalinks;
+pub mod protected;
+pub mod redirects;
+pub mod rights;
+pub mod search;
+pub mod seo;
+pub mod single_file;
+pub mod site;
+pub mod site_zip;
+pub mod structured_data;
+pub mod syndication;
+pub mod template_ast;
+pub mod text;
+pub mod typography;
+pub mod urls;
+pub mod usage;
+pub mod validate;
+pub mod wikilinks;
+
+/// Helpers provided by Handlebars or the renderer, which extensions can't replace
+pub const BUILT_IN_HELPERS: [&str; 21] = [
+    "if", "unless", "each", "with", "lookup", "log", "raw", "eq", "ne", "gt", "gte", "lt", "lte",
+    "and", "or", "not", "len", "form", "comments", "comments_head", "attribution",
+];
+
+/// Handlebars wrapper holding a theme's templates and partials
+pub struct Renderer {
+    handlebars: Handlebars<'static>,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Renderer {
+            handlebars: Handlebars::new(),
+        }
+    }
+
+    pub fn register_template(&mut self, name: &str, content: &str) -> Result<(), String> {
+        self.handlebars
+            .register_template_string(name, content)
+            .map_err(|e| format!("Template error in {}: {}", name, e))
+    }
+
+    pub fn register_partial(&mut self, name: &str, content: &str) -> Result<(), String> {
+        self.handlebars
+            .register_partial(name, content)
+            .map_err(|e| format!("Failed to register partial {}: {}", name, e))
+    }
+
+    pub fn register_helper(&mut self, name: &str, helper: Box<dyn HelperDef + Send + Sync>) {
+        self.handlebars.register_helper(name, helper);
+    }
+
+    /// Make variables the context doesn't have errors instead of empty
+    pub fn set_strict(&mut self, strict: bool) {
+        self.handlebars.set_strict_mode(strict);
+    }
+
+    pub fn has_template(&self, name: &str) -> bool {
+        self.handlebars.has_template(name)
+    }
+
+    pub fn render(&self, name: &str, context: &Value) -> Result<String, String> {
+        self.handlebars
+            .render(name, context)
+            .map_err(|e| format!("Rendering error in {}: {}", name, e))
+    }
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lowercase, ASCII-only, hyphen-separated form of a name for use in URLs
+pub fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    let mut pending_hyphen = false;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if pending_hyphen && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_hyphen = false;
+            slug.push(c.to_ascii_lowercase());
+        } else {
+            pending_hyphen = true;
+        }
+    }
+    slug
+}
+
+/// Output file for a URL: directories get an index.html
+pub fn output_path(url: &str) -> String {
+    let path = url.trim_start_matches('/');
+    if path.is_empty() || path.ends_with('/') {
+        return format!("{}index.html", path);
+    }
+    let last = path.rsplit('/').next().unwrap_or(path);
+    if last.contains('.') {
+        path.to_string()
+    } else {
+        format!("{}/index.html", path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  Spring Zine #3 "), "spring-zine-3");
+        assert_eq!(slugify("test_post"), "test-post");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_output_path() {
+        assert_eq!(output_path("/"), "index.html");
+        assert_eq!(output_path("/about/"), "about/index.html");
+        assert_eq!(output_path("/about"), "about/index.html");
+        assert_eq!(output_path("/feed.xml"), "feed.xml");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_renderer_with_partial() {
+        let mut renderer = Renderer::new();
+        renderer
+            .register_partial("header", "<header>{{site.name}}</header>")
+            .unwrap();
+        renderer
+            .register_template("index", "{{> header}}<main>{{{content}}}</main>")
+            .unwrap();
+
+        let html = renderer
+            .render(
+                "index",
+                &json!({ "site": { "name": "Zine" }, "content": "<p>Hi</p>" }),
+            )
+            .unwrap();
+        assert_eq!(html, "<header>Zine</header><main><p>Hi</p></main>");
+        assert!(!renderer.has_template("missing"));
+    }
+}
@@ -0,0 +1,149 @@
+//! Document outlines: the heading tree of a rich-text body.
+//!
+//! The editor shows it as a sidebar. Each heading carries its ProseMirror
+//! position, so the sidebar can follow the cursor, and the anchor the
+//! rendered page gives it, so entries can link to the published section.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::html::{heading_level, HeadingIds};
+use super::text::pm_to_text;
+
+/// A heading, with the headings of lower levels that follow it
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct OutlineHeading {
+    pub level: u64,
+    pub text: String,
+    /// `id` of the heading on the rendered page
+    pub anchor: String,
+    /// ProseMirror position just before the heading
+    pub position: usize,
+    pub children: Vec<OutlineHeading>,
+}
+
+/// Size of a node in ProseMirror positions
+fn node_size(node: &Value) -> usize {
+    if node.get("type").and_then(Value::as_str) == Some("text") {
+        // Positions count UTF-16 code units, as in JavaScript
+        let text = node.get("text").and_then(Value::as_str).unwrap_or("");
+        return text.encode_utf16().count();
+    }
+    match node.get("content").and_then(Value::as_array) {
+        Some(children) => 2 + children.iter().map(node_size).sum::<usize>(),
+        None if is_leaf(node) => 1,
+        None => 2,
+    }
+}
+
+/// Nodes that never have content, which take up a single position
+fn is_leaf(node: &Value) -> bool {
+    matches!(
+        node.get("type").and_then(Value::as_str),
+        Some("hard_break")
+            | Some("hardBreak")
+            | Some("horizontal_rule")
+            | Some("horizontalRule")
+            | Some("image")
+    )
+}
+
+/// Headings in document order, with the positions their content starts at
+fn collect_headings(
+    node: &Value,
+    start: usize,
+    ids: &mut HeadingIds,
+    headings: &mut Vec<OutlineHeading>,
+) {
+    let mut position = start;
+    for child in node
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        if child.get("type").and_then(Value::as_str) == Some("heading") {
+            headings.push(OutlineHeading {
+                level: heading_level(child),
+                text: pm_to_text(child),
+                anchor: ids.assign(child),
+                position,
+                children: Vec::new(),
+            });
+        } else {
+            collect_headings(child, position + 1, ids, headings);
+        }
+        position += node_size(child);
+    }
+}
+
+/// Nest each heading under the nearest earlier one of a lower level
+fn nest(headings: Vec<OutlineHeading>) -> Vec<OutlineHeading> {
+    let mut roots: Vec<OutlineHeading> = Vec::new();
+    // Open headings, outermost first, not yet added to their parents
+    let mut open: Vec<OutlineHeading> = Vec::new();
+    let close = |open: &mut Vec<OutlineHeading>, roots: &mut Vec<OutlineHeading>| {
+        let heading = open.pop().unwrap();
+        match open.last_mut() {
+            Some(parent) => parent.children.push(heading),
+            None => roots.push(heading),
+        }
+    };
+    for heading in headings {
+        while open
+            .last()
+            .map_or(false, |last| last.level >= heading.level)
+        {
+            close(&mut open, &mut roots);
+        }
+        open.push(heading);
+    }
+    while !open.is_empty() {
+        close(&mut open, &mut roots);
+    }
+    roots
+}
+
+/// The heading tree of a ProseMirror document
+pub fn outline(doc: &Value) -> Vec<OutlineHeading> {
+    let mut headings = Vec::new();
+    collect_headings(doc, 0, &mut HeadingIds::default(), &mut headings);
+    nest(headings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn heading(level: u64, text: &str) -> Value {
+        json!({ "type": "heading", "attrs": { "level": level }, "content": [
+            { "type": "text", "text": text }
+        ]})
+    }
+
+    #[wasm_bindgen_test]
+    fn test_outline() {
+        let doc = json!({ "type": "doc", "content": [
+            heading(1, "Intro"),
+            { "type": "paragraph", "content": [{ "type": "text", "text": "Hello" }] },
+            heading(2, "Details"),
+            heading(3, "Intro"),
+            heading(2, "More")
+        ]});
+
+        let outline = outline(&doc);
+        assert_eq!(outline.len(), 1);
+        let intro = &outline[0];
+        assert_eq!((intro.position, intro.anchor.as_str()), (0, "intro"));
+        let sections: Vec<(&str, usize)> = intro
+            .children
+            .iter()
+            .map(|h| (h.text.as_str(), h.position))
+            .collect();
+        // "Intro" is 7 positions and the paragraph 7 more
+        assert_eq!(sections, vec![("Details", 14), ("More", 30)]);
+        assert_eq!(intro.children[0].children[0].anchor, "intro-2");
+    }
+}
@@ -0,0 +1,198 @@
+//! Partial exports.
+//!
+//! An [`ExportFilter`] narrows a site down to one section, such as its
+//! documentation, so that section can be deployed on its own. Only the
+//! selected pages are rendered, and the `pages` and `posts` templates list
+//! are narrowed with them, so navigation doesn't link out of the section.
+//! What the selected pages depend on comes along: templates, partials and
+//! stylesheets are always available, shared blocks are resolved into the
+//! pages, and uploaded assets are kept if anything rendered refers to them.
+
+use serde_json::Value;
+
+use super::graph::tags;
+use super::site::{page_url, PageSource, SiteAsset, SiteOutput, SiteSource};
+use crate::types::ExportFilter;
+
+/// `path` as a URL directory: "/docs" and "docs/" both become "/docs/"
+fn section(path: &str) -> String {
+    let trimmed = path.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        "/".to_string()
+    } else {
+        format!("/{}/", trimmed)
+    }
+}
+
+fn in_section(url: &str, section: &str) -> bool {
+    url.starts_with(section) || format!("{}/", url) == section
+}
+
+/// Whether the filter's section criteria (not `files`) select a page
+fn section_matches(
+    filter: &ExportFilter,
+    collection: &str,
+    url: &str,
+    page_tags: &[String],
+) -> bool {
+    if filter.collections.is_empty() && filter.tags.is_empty() && filter.path.is_none() {
+        return false;
+    }
+    let collection_ok =
+        filter.collections.is_empty() || filter.collections.iter().any(|name| name == collection);
+    let tags_ok = filter.tags.is_empty()
+        || filter.tags.iter().any(|wanted| {
+            page_tags
+                .iter()
+                .any(|tag| tag.eq_ignore_ascii_case(wanted.trim()))
+        });
+    let path_ok = filter
+        .path
+        .as_deref()
+        .map_or(true, |path| in_section(url, &section(path)));
+    collection_ok && tags_ok && path_ok
+}
+
+/// Whether a page or post is part of the export
+pub fn page_selected(filter: &ExportFilter, page: &PageSource) -> bool {
+    if filter.is_empty() {
+        return true;
+    }
+    let id = page.meta.get("id").and_then(Value::as_str);
+    if id.map_or(false, |id| filter.files.iter().any(|file| file == id)) {
+        return true;
+    }
+    section_matches(filter, &page.collection, &page_url(page), &tags(&page.meta))
+}
+
+/// The source with only the selected pages, and only the custom collections
+/// whose listings fall in the selected section
+///
+/// Collections outside it keep their entries, so templates that show them
+/// still can, but get no listing or detail pages of their own.
+pub fn filter_source(source: &SiteSource, filter: &ExportFilter) -> SiteSource {
+    let mut filtered = source.clone();
+    if filter.is_empty() {
+        return filtered;
+    }
+    filtered.pages.retain(|page| page_selected(filter, page));
+    for collection in filtered.collections.iter_mut() {
+        let selected = collection.listing.as_ref().map_or(false, |listing| {
+            let url = section(&listing.path);
+            section_matches(filter, &collection.name, &url, &[])
+        });
+        if !selected {
+            collection.listing = None;
+        }
+    }
+    filtered
+}
+
+/// Whether any rendered file links to or embeds an asset
+fn asset_used(output: &SiteOutput, asset: &SiteAsset) -> bool {
+    // Links name the asset's directory, which holds its id
+    let dir = match asset.path.rsplit_once('/') {
+        Some((dir, _)) => format!("{}/", dir),
+        None => return true,
+    };
+    output
+        .files
+        .values()
+        .any(|contents| contents.contains(&dir))
+}
+
+/// Drop uploaded assets that nothing in the output uses
+pub fn prune_assets(output: &mut SiteOutput) {
+    let assets = std::mem::take(&mut output.assets);
+    let used: Vec<SiteAsset> = assets
+        .into_iter()
+        .filter(|asset| asset_used(output, asset))
+        .collect();
+    output.assets = used;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Map};
+    use wasm_bindgen_test::*;
+
+    fn page(collection: &str, name: &str, meta: Value) -> PageSource {
+        PageSource {
+            collection: collection.to_string(),
+            name: name.to_string(),
+            meta: meta.as_object().cloned().unwrap_or_else(Map::new),
+            body: json!({ "type": "doc", "content": [] }),
+        }
+    }
+
+    fn names(source: &SiteSource) -> Vec<&str> {
+        source.pages.iter().map(|page| page.name.as_str()).collect()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_filter_source() {
+        let source = SiteSource {
+            pages: vec![
+                page("page", "index", json!({ "id": "p1" })),
+                page(
+                    "page",
+                    "install",
+                    json!({ "id": "p2", "url": "/docs/install/" }),
+                ),
+                page("page", "docs", json!({ "id": "p3", "url": "/docs" })),
+                page(
+                    "post",
+                    "Release",
+                    json!({ "id": "p4", "tags": "Docs, news" }),
+                ),
+                page("post", "Picnic", json!({ "id": "p5", "tags": ["events"] })),
+            ],
+            ..Default::default()
+        };
+
+        let docs = ExportFilter {
+            path: Some("docs".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            names(&filter_source(&source, &docs)),
+            vec!["install", "docs"]
+        );
+
+        let tagged_posts = ExportFilter {
+            collections: vec!["post".to_string()],
+            tags: vec!["docs".to_string()],
+            files: vec!["p1".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            names(&filter_source(&source, &tagged_posts)),
+            vec!["index", "Release"]
+        );
+
+        assert_eq!(
+            filter_source(&source, &ExportFilter::default()).pages.len(),
+            5
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_prune_assets() {
+        let asset = |path: &str| SiteAsset {
+            path: path.to_string(),
+            mime_type: "image/png".to_string(),
+            data: Vec::new(),
+        };
+        let mut output = SiteOutput::default();
+        output.files.insert(
+            "docs/index.html".to_string(),
+            "<img src=\"/assets/a1/diagram.png\">".to_string(),
+        );
+        output.assets = vec![asset("assets/a1/diagram.png"), asset("assets/a2/cat.png")];
+
+        prune_assets(&mut output);
+        let paths: Vec<&str> = output.assets.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(paths, vec!["assets/a1/diagram.png"]);
+    }
+}
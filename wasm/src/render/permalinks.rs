@@ -0,0 +1,275 @@
+//! URL patterns for pages and posts.
+//!
+//! A collection can publish its files at a pattern such as
+//! `/blog/:year/:month/:slug/` instead of the default `/posts/:slug/`. The
+//! tokens are filled in from each file when the site is loaded, by setting
+//! the `url` [`page_url`] reads, so every output (pages, feeds, links,
+//! gemtext) agrees on where a file lives. A file with its own `url` keeps it.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Datelike, NaiveDate};
+use serde_json::{json, Map, Value};
+
+use super::site::{page_url, PageSource};
+use super::slugify;
+
+/// Tokens a pattern can use
+pub const URL_TOKENS: [&str; 5] = ["year", "month", "day", "slug", "collection"];
+/// Fields a file's date is read from, in order
+const DATE_FIELDS: [&str; 2] = ["date", "created"];
+
+/// One part of a pattern: literal text or a `:token`
+#[derive(Debug, Clone, PartialEq)]
+enum Part<'a> {
+    Text(&'a str),
+    Token(&'a str),
+}
+
+fn parse_pattern(pattern: &str) -> Vec<Part<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = pattern;
+    while let Some(start) = rest.find(':') {
+        let name_len = rest[start + 1..]
+            .find(|c: char| !c.is_ascii_lowercase())
+            .unwrap_or(rest.len() - start - 1);
+        if name_len == 0 {
+            // A colon that isn't followed by a name is kept as it is
+            parts.push(Part::Text(&rest[..=start]));
+            rest = &rest[start + 1..];
+            continue;
+        }
+        if start > 0 {
+            parts.push(Part::Text(&rest[..start]));
+        }
+        parts.push(Part::Token(&rest[start + 1..start + 1 + name_len]));
+        rest = &rest[start + 1 + name_len..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+    parts
+}
+
+/// Check a pattern is a site path that gives each file its own URL
+pub fn validate_url_pattern(pattern: &str) -> Result<(), String> {
+    if !pattern.starts_with('/') {
+        return Err(format!("{} must start with /", pattern));
+    }
+    let mut has_slug = false;
+    for part in parse_pattern(pattern) {
+        if let Part::Token(token) = part {
+            if !URL_TOKENS.contains(&token) {
+                return Err(format!(
+                    "{} uses :{}; the tokens are :{}",
+                    pattern,
+                    token,
+                    URL_TOKENS.join(", :")
+                ));
+            }
+            has_slug |= token == "slug";
+        }
+    }
+    if !has_slug {
+        return Err(format!(
+            "{} needs :slug, or every file would share it",
+            pattern
+        ));
+    }
+    Ok(())
+}
+
+/// Day a date field refers to: an ISO date or datetime, or a timestamp in ms
+fn page_date(meta: &Map<String, Value>) -> Option<NaiveDate> {
+    DATE_FIELDS
+        .iter()
+        .find_map(|field| match meta.get(*field)? {
+            Value::String(text) => text
+                .get(..10)
+                .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()),
+            Value::Number(ms) => {
+                DateTime::from_timestamp_millis(ms.as_f64()? as i64).map(|time| time.date_naive())
+            }
+            _ => None,
+        })
+}
+
+/// The URL a pattern gives a page, ignoring any `url` the page sets
+pub fn expand_url_pattern(pattern: &str, page: &PageSource) -> Result<String, String> {
+    let mut url = String::new();
+    for part in parse_pattern(pattern) {
+        let token = match part {
+            Part::Text(text) => {
+                url.push_str(text);
+                continue;
+            }
+            Part::Token(token) => token,
+        };
+        let value = match token {
+            "slug" => slugify(&page.name),
+            "collection" => slugify(&page.collection),
+            "year" | "month" | "day" => {
+                let date = page_date(&page.meta)
+                    .ok_or_else(|| format!("{} has no date for :{}", page.name, token))?;
+                match token {
+                    "year" => format!("{:04}", date.year()),
+                    "month" => format!("{:02}", date.month()),
+                    _ => format!("{:02}", date.day()),
+                }
+            }
+            _ => return Err(format!("Unknown URL token :{}", token)),
+        };
+        url.push_str(&value);
+    }
+    Ok(url)
+}
+
+/// The URL a page is published at under `patterns`
+///
+/// Like [`page_url`], but for a page that hasn't been through
+/// [`apply_url_patterns`]; a pattern that can't be filled in falls back to
+/// the default URL.
+pub fn patterned_url(patterns: &BTreeMap<String, String>, page: &PageSource) -> String {
+    let has_url = matches!(page.meta.get("url"), Some(Value::String(url)) if !url.is_empty());
+    match patterns.get(&page.collection) {
+        Some(pattern) if !has_url => {
+            expand_url_pattern(pattern, page).unwrap_or_else(|_| page_url(page))
+        }
+        _ => page_url(page),
+    }
+}
+
+/// Set the `url` of each page a pattern applies to
+///
+/// Returns warnings for pages that fall back to their default URL and for
+/// URLs more than one page ends up at.
+pub fn apply_url_patterns(
+    patterns: &BTreeMap<String, String>,
+    pages: &mut [PageSource],
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for page in pages.iter_mut() {
+        let pattern = match patterns.get(&page.collection) {
+            Some(pattern) => pattern,
+            None => continue,
+        };
+        if matches!(page.meta.get("url"), Some(Value::String(url)) if !url.is_empty()) {
+            continue;
+        }
+        match validate_url_pattern(pattern).and_then(|_| expand_url_pattern(pattern, page)) {
+            Ok(url) => {
+                page.meta.insert("url".to_string(), json!(url));
+            }
+            Err(e) => warnings.push(format!("{} {}: {}", page.collection, page.name, e)),
+        }
+    }
+
+    for (url, names) in url_collisions(pages) {
+        warnings.push(format!("{} is the URL of {}", url, names.join(", ")));
+    }
+    warnings
+}
+
+/// URLs shared by more than one page, with the pages' names
+pub fn url_collisions(pages: &[PageSource]) -> Vec<(String, Vec<String>)> {
+    let mut by_url: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for page in pages {
+        by_url
+            .entry(page_url(page))
+            .or_default()
+            .push(format!("{} {}", page.collection, page.name));
+    }
+    by_url
+        .into_iter()
+        .filter(|(_, names)| names.len() > 1)
+        .collect()
+}
+
+/// Old URL → new URL for each page that moves when `old` patterns become `new`
+pub fn pattern_redirects(
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+    pages: &[PageSource],
+) -> Vec<(String, String)> {
+    pages
+        .iter()
+        .filter_map(|page| {
+            let from = patterned_url(old, page);
+            let to = patterned_url(new, page);
+            if from == to {
+                None
+            } else {
+                Some((from, to))
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn post(name: &str, date: &str) -> PageSource {
+        let mut meta = Map::new();
+        meta.insert("date".to_string(), json!(date));
+        PageSource {
+            collection: "post".to_string(),
+            name: name.to_string(),
+            meta,
+            body: Value::Null,
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_expand_url_pattern() {
+        let page = post("Hello World", "2024-03-07T10:00:00Z");
+        assert_eq!(
+            expand_url_pattern("/blog/:year/:month/:day/:slug/", &page).unwrap(),
+            "/blog/2024/03/07/hello-world/"
+        );
+        assert_eq!(
+            expand_url_pattern("/:collection/:slug.html", &page).unwrap(),
+            "/post/hello-world.html"
+        );
+        assert!(expand_url_pattern("/:year/:slug/", &post("Undated", "")).is_err());
+
+        assert!(validate_url_pattern("/blog/:year/:slug/").is_ok());
+        assert!(validate_url_pattern("blog/:slug/").is_err());
+        assert!(validate_url_pattern("/blog/:year/").is_err());
+        assert!(validate_url_pattern("/:author/:slug/").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_pattern_redirects_and_collisions() {
+        let mut pages = vec![
+            post("First", "2024-01-02"),
+            post("Second", "2023-12-31"),
+            post("Pinned", "2024-01-02"),
+        ];
+        pages[2].meta.insert("url".to_string(), json!("/pinned/"));
+
+        let old = BTreeMap::new();
+        let mut new = BTreeMap::new();
+        new.insert("post".to_string(), "/blog/:year/:slug/".to_string());
+        assert_eq!(
+            pattern_redirects(&old, &new, &pages),
+            vec![
+                ("/posts/first/".to_string(), "/blog/2024/first/".to_string()),
+                (
+                    "/posts/second/".to_string(),
+                    "/blog/2023/second/".to_string()
+                ),
+            ]
+        );
+
+        pages.push(post("first", "2024-06-01"));
+        let warnings = apply_url_patterns(&new, &mut pages);
+        assert_eq!(pages[0].meta["url"], json!("/blog/2024/first/"));
+        assert_eq!(pages[2].meta["url"], json!("/pinned/"));
+        assert_eq!(
+            warnings,
+            vec!["/blog/2024/first/ is the URL of post First, post first"]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Password-protected pages.
+//!
+//! A page or post with a `password` meta field is published encrypted, as
+//! staticrypt does: its rendered HTML is sealed with AES-256-GCM under a key
+//! derived from the password with PBKDF2-SHA256, and the file served in its
+//! place asks for the password and decrypts in the browser with Web Crypto.
+//! Members-only pages can then go on any static host.
+//!
+//! The password is never passed to templates, and protected pages are left
+//! out of the search index, the sitemap and text mirrors.
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use pbkdf2::pbkdf2_hmac;
+use serde_json::{json, Map, Value};
+use sha2::Sha256;
+
+use super::manifest::base64_encode;
+
+/// Meta field holding the password a page is encrypted with
+pub const PASSWORD_FIELD: &str = "password";
+/// OWASP's recommended work factor for PBKDF2-SHA256
+pub const PBKDF2_ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// A page's HTML, encrypted, with what a browser needs to decrypt it
+#[derive(Debug, Clone, PartialEq)]
+pub struct SealedPage {
+    pub salt: [u8; SALT_LEN],
+    pub nonce: [u8; NONCE_LEN],
+    pub iterations: u32,
+    /// Ciphertext followed by the GCM tag, as Web Crypto expects
+    pub data: Vec<u8>,
+}
+
+/// The password a page is protected with, if it has one
+pub fn page_password(meta: &Map<String, Value>) -> Option<&str> {
+    meta.get(PASSWORD_FIELD)
+        .and_then(Value::as_str)
+        .filter(|password| !password.is_empty())
+}
+
+fn cipher(password: &str, salt: &[u8], iterations: u32) -> Result<Aes256Gcm, String> {
+    let mut key = [0u8; 32];
+    pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut key);
+    Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())
+}
+
+/// Encrypt `html` under `password`
+pub fn seal(
+    html: &str,
+    password: &str,
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+    iterations: u32,
+) -> Result<SealedPage, String> {
+    let data = cipher(password, &salt, iterations)?
+        .encrypt(Nonce::from_slice(&nonce), html.as_bytes())
+        .map_err(|_| "Failed to encrypt page".to_string())?;
+    Ok(SealedPage {
+        salt,
+        nonce,
+        iterations,
+        data,
+    })
+}
+
+/// Decrypt a sealed page, failing if the password is wrong, as the unlock
+/// page does in the browser
+#[cfg(test)]
+fn unseal(sealed: &SealedPage, password: &str) -> Result<String, String> {
+    let html = cipher(password, &sealed.salt, sealed.iterations)?
+        .decrypt(Nonce::from_slice(&sealed.nonce), sealed.data.as_slice())
+        .map_err(|_| "Wrong password".to_string())?;
+    String::from_utf8(html).map_err(|e| e.to_string())
+}
+
+/// The page served in place of a protected one: a password form that
+/// decrypts the sealed HTML and replaces itself with it
+pub fn unlock_page(sealed: &SealedPage) -> String {
+    let payload = json!({
+        "salt": base64_encode(&sealed.salt),
+        "iv": base64_encode(&sealed.nonce),
+        "iterations": sealed.iterations,
+        "data": base64_encode(&sealed.data),
+    });
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <meta name=\"robots\" content=\"noindex, nofollow\">\n\
+         <title>Protected page</title>\n\
+         <style>body{{font-family:system-ui,sans-serif;max-width:24rem;margin:20vh auto;\
+         padding:0 1rem}}input,button{{font:inherit}}</style>\n</head>\n<body>\n\
+         <form id=\"unlock\">\n<p><label for=\"password\">\
+         This page is protected. Enter the password to read it.</label></p>\n\
+         <p><input id=\"password\" type=\"password\" autocomplete=\"current-password\" \
+         required autofocus> <button>Unlock</button></p>\n\
+         <p id=\"unlock-error\" role=\"alert\" hidden>That password didn't work.</p>\n\
+         </form>\n\
+         <script>(function(){{var p={};\
+         function b(s){{return Uint8Array.from(atob(s),function(c){{return c.charCodeAt(0)}})}}\
+         document.getElementById('unlock').addEventListener('submit',function(e){{\
+         e.preventDefault();var c=crypto.subtle,\
+         k=new TextEncoder().encode(document.getElementById('password').value);\
+         c.importKey('raw',k,'PBKDF2',false,['deriveKey']).then(function(m){{\
+         return c.deriveKey({{name:'PBKDF2',salt:b(p.salt),\
+         iterations:p.iterations,hash:'SHA-256'}},\
+         m,{{name:'AES-GCM',length:256}},false,['decrypt'])}}).then(function(key){{\
+         return c.decrypt({{name:'AES-GCM',iv:b(p.iv)}},key,b(p.data))}}).then(function(h){{\
+         document.open();document.write(new TextDecoder().decode(h));document.close()}},\
+         function(){{document.getElementById('unlock-error').hidden=false}})}})}})();</script>\n\
+         </body>\n</html>\n",
+        payload
+    )
+}
+
+/// Encrypt a rendered page with a fresh salt and nonce, returning the
+/// unlock page to publish in its place
+pub fn protect_page(html: &str, password: &str) -> Result<String, String> {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::getrandom(&mut salt).map_err(|e| e.to_string())?;
+    getrandom::getrandom(&mut nonce).map_err(|e| e.to_string())?;
+    let sealed = seal(html, password, salt, nonce, PBKDF2_ITERATIONS)?;
+    Ok(unlock_page(&sealed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_seal_and_unseal() {
+        let html = "<h1>Members' meeting notes</h1>";
+        let sealed = seal(html, "hunter2", [7; SALT_LEN], [9; NONCE_LEN], 1_000).unwrap();
+
+        assert_eq!(sealed.data.len(), html.len() + 16);
+        assert_eq!(unseal(&sealed, "hunter2").unwrap(), html);
+        assert!(unseal(&sealed, "hunter3").is_err());
+
+        let page = unlock_page(&sealed);
+        assert!(page.contains(&base64_encode(&sealed.data)));
+        assert!(page.contains("\"iterations\":1000"));
+        assert!(!page.contains("meeting notes"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_page_password() {
+        let meta = json!({ "password": "hunter2" }).as_object().cloned().unwrap();
+        assert_eq!(page_password(&meta), Some("hunter2"));
+        let meta = json!({ "password": "" }).as_object().cloned().unwrap();
+        assert_eq!(page_password(&meta), None);
+    }
+}
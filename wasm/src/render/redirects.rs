@@ -0,0 +1,137 @@
+//! Redirects from old URLs.
+//!
+//! Entries of the `redirects` collection each move one URL (`from`) to
+//! another (`to`), typically a post's address on the platform it was
+//! imported from. Every redirect is written as a small HTML page at the old
+//! path, which works on any static host, and listed in a `_redirects` file
+//! for hosts (Netlify, Cloudflare Pages) that can answer with a real 301.
+
+use serde_json::Value;
+
+use super::html::escape_html;
+use super::output_path;
+use super::site::{CollectionSource, SiteOutput};
+use super::urls::is_internal;
+
+/// Collection whose entries are redirects
+pub const REDIRECTS_COLLECTION: &str = "redirects";
+pub const FROM_FIELD: &str = "from";
+pub const TO_FIELD: &str = "to";
+/// Where hosts that support it read redirect rules from
+pub const REDIRECTS_FILE: &str = "_redirects";
+
+/// A page that sends browsers (and search engines) on to `to`
+///
+/// Links in the page are rewritten with the rest of the output, but the
+/// refresh isn't an attribute that's rewritten, so it gets `base_path` here.
+pub fn redirect_page(to: &str, base_path: &str) -> String {
+    let refresh = if is_internal(to) {
+        format!("{}{}", base_path, to)
+    } else {
+        to.to_string()
+    };
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Redirecting…</title>\n<link rel=\"canonical\" href=\"{0}\">\n\
+         <meta http-equiv=\"refresh\" content=\"0; url={1}\">\n\
+         <meta name=\"robots\" content=\"noindex\">\n</head>\n<body>\n\
+         <p>This page has moved to <a href=\"{0}\">{0}</a>.</p>\n</body>\n</html>\n",
+        escape_html(to),
+        escape_html(&refresh)
+    )
+}
+
+fn field<'a>(entry: &'a Value, name: &str) -> Option<&'a str> {
+    entry
+        .get(name)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Write a page for each redirect, plus the `_redirects` file
+///
+/// Redirects from a URL the site already publishes are skipped, with a
+/// warning, so they never replace real pages. `base_path` prefixes the
+/// paths in `_redirects`, which isn't rewritten with the rest of the output.
+pub fn render_redirects(collection: &CollectionSource, base_path: &str, output: &mut SiteOutput) {
+    let mut rules = Vec::new();
+    for entry in &collection.entries {
+        let name = field(entry, "name").unwrap_or("");
+        let (from, to) = match (field(entry, FROM_FIELD), field(entry, TO_FIELD)) {
+            (Some(from), Some(to)) => (from, to),
+            _ => {
+                output
+                    .warnings
+                    .push(format!("redirect {}: needs both from and to", name));
+                continue;
+            }
+        };
+        if !is_internal(from) {
+            output.warnings.push(format!(
+                "redirect {}: {} isn't a path on this site",
+                name, from
+            ));
+            continue;
+        }
+
+        let path = output_path(from);
+        if output.files.contains_key(&path) {
+            output
+                .warnings
+                .push(format!("redirect {}: {} is already a page", name, from));
+            continue;
+        }
+        output.files.insert(path, redirect_page(to, base_path));
+
+        let target = if is_internal(to) {
+            format!("{}{}", base_path, to)
+        } else {
+            to.to_string()
+        };
+        rules.push(format!("{}{} {} 301", base_path, from, target));
+    }
+
+    if !rules.is_empty() {
+        rules.push(String::new());
+        output
+            .files
+            .insert(REDIRECTS_FILE.to_string(), rules.join("\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_render_redirects() {
+        let collection = CollectionSource {
+            name: REDIRECTS_COLLECTION.to_string(),
+            entries: vec![
+                json!({ "name": "fair", "from": "/@zines/fair-1a2b3c", "to": "/posts/fair/" }),
+                json!({ "name": "home", "from": "/", "to": "/posts/" }),
+                json!({ "name": "old", "from": "https://medium.com/x", "to": "/posts/x/" }),
+            ],
+            ..Default::default()
+        };
+        let mut output = SiteOutput::default();
+        output
+            .files
+            .insert("index.html".to_string(), "<h1>Home</h1>".to_string());
+
+        render_redirects(&collection, "/zine", &mut output);
+
+        let page = &output.files["@zines/fair-1a2b3c/index.html"];
+        assert!(page.contains("content=\"0; url=/zine/posts/fair/\""));
+        assert!(page.contains("<a href=\"/posts/fair/\">"));
+        assert_eq!(
+            output.files[REDIRECTS_FILE],
+            "/zine/@zines/fair-1a2b3c /zine/posts/fair/ 301\n"
+        );
+        assert_eq!(output.files["index.html"], "<h1>Home</h1>");
+        assert_eq!(output.warnings.len(), 2);
+    }
+}
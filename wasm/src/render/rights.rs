@@ -0,0 +1,235 @@
+//! Licenses: what a site's pages and files may be reused under.
+//!
+//! A site can have a license (`SiteSettings::license`), which its pages and
+//! posts carry unless they give their own in a `license` field. Licensed
+//! pages get a `rel="license"` link in their head and a `license` in their
+//! structured data, and the export gets `rights.json`, listing the license
+//! of each page and of each asset with a credit or license, for archives
+//! and harvesters.
+//!
+//! A license is an SPDX id such as `CC-BY-SA-4.0`, a URL, or any other
+//! wording ("All rights reserved").
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use super::head::insert_into_head;
+use super::html::escape_html;
+
+/// Meta field holding a page's own license
+pub const LICENSE_FIELD: &str = "license";
+pub const RIGHTS_URL: &str = "/rights.json";
+
+/// Creative Commons terms, in the order their names list them
+const CC_TERMS: [&str; 4] = ["BY", "NC", "SA", "ND"];
+const CC_VERSIONS: [&str; 4] = ["1.0", "2.0", "3.0", "4.0"];
+
+/// Display name and deed URL of a Creative Commons license, from its SPDX
+/// id or written out ("CC BY-SA 4.0")
+pub fn license_link(license: &str) -> Option<(String, String)> {
+    let id = license.trim().to_ascii_uppercase().replace(' ', "-");
+    if id == "CC0" || id == "CC0-1.0" {
+        return Some((
+            "CC0 1.0".to_string(),
+            "https://creativecommons.org/publicdomain/zero/1.0/".to_string(),
+        ));
+    }
+    let (terms, version) = id.strip_prefix("CC-")?.rsplit_once('-')?;
+    let known = terms.starts_with("BY") && terms.split('-').all(|term| CC_TERMS.contains(&term));
+    if !known || !CC_VERSIONS.contains(&version) {
+        return None;
+    }
+    Some((
+        format!("CC {} {}", terms, version),
+        format!(
+            "https://creativecommons.org/licenses/{}/{}/",
+            terms.to_ascii_lowercase(),
+            version
+        ),
+    ))
+}
+
+/// Where a license can be read: a Creative Commons deed, or the license
+/// itself if it is a URL
+pub fn license_url(license: &str) -> Option<String> {
+    let license = license.trim();
+    match license_link(license) {
+        Some((_, url)) => Some(url),
+        None if license.starts_with("https://") || license.starts_with("http://") => {
+            Some(license.to_string())
+        }
+        None => None,
+    }
+}
+
+/// How a license is shown to readers
+pub fn license_name(license: &str) -> String {
+    match license_link(license) {
+        Some((name, _)) => name,
+        None => license.trim().to_string(),
+    }
+}
+
+fn meta_text<'a>(meta: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    meta.get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+/// A page's license: its own, or else the site's
+pub fn page_license<'a>(
+    meta: &'a Map<String, Value>,
+    site_license: Option<&'a str>,
+) -> Option<&'a str> {
+    meta_text(meta, LICENSE_FIELD).or_else(|| {
+        site_license
+            .map(str::trim)
+            .filter(|license| !license.is_empty())
+    })
+}
+
+/// Add a `rel="license"` link to a page's head, if `license` can be
+/// linked to
+///
+/// Pages without a `</head>` are left alone.
+pub fn add_license_link(html: &str, license: &str) -> String {
+    match license_url(license) {
+        Some(href) => insert_into_head(
+            html,
+            &format!("<link rel=\"license\" href=\"{}\">\n", escape_html(&href)),
+        ),
+        None => html.to_string(),
+    }
+}
+
+/// One licensed page or file in `rights.json`
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RightsEntry {
+    pub url: String,
+    pub title: String,
+    /// The license as it was given
+    pub license: Option<String>,
+    pub license_name: Option<String>,
+    pub license_url: Option<String>,
+    pub credit: Option<String>,
+}
+
+impl RightsEntry {
+    pub fn new(url: &str, title: &str, license: Option<&str>, credit: Option<&str>) -> Self {
+        RightsEntry {
+            url: url.to_string(),
+            title: title.to_string(),
+            license: license.map(|license| license.trim().to_string()),
+            license_name: license.map(license_name),
+            license_url: license.and_then(license_url),
+            credit: credit.map(str::to_string),
+        }
+    }
+}
+
+/// `rights.json`: the site's license, and the license and credit of each
+/// page and asset that has one
+pub fn rights_manifest(
+    site_license: Option<&str>,
+    pages: &[RightsEntry],
+    assets: &[RightsEntry],
+) -> String {
+    let site = site_license.map(|license| {
+        json!({
+            "license": license.trim(),
+            "license_name": license_name(license),
+            "license_url": license_url(license),
+        })
+    });
+    let manifest = json!({
+        "site": site,
+        "pages": pages,
+        "assets": assets,
+    });
+    serde_json::to_string_pretty(&manifest).unwrap_or_default()
+}
+
+/// The `rights.json` entry for an asset, if it has a credit or license
+pub fn asset_rights(asset: &Map<String, Value>) -> Option<RightsEntry> {
+    let license = meta_text(asset, LICENSE_FIELD);
+    let credit = meta_text(asset, "credit");
+    if license.is_none() && credit.is_none() {
+        return None;
+    }
+    Some(RightsEntry::new(
+        meta_text(asset, "url").unwrap_or(""),
+        meta_text(asset, "name").unwrap_or(""),
+        license,
+        credit,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_license_link() {
+        assert_eq!(
+            license_link("CC-BY-SA-4.0"),
+            Some((
+                "CC BY-SA 4.0".to_string(),
+                "https://creativecommons.org/licenses/by-sa/4.0/".to_string()
+            ))
+        );
+        assert_eq!(
+            license_url("cc by-nc-nd 3.0"),
+            Some("https://creativecommons.org/licenses/by-nc-nd/3.0/".to_string())
+        );
+        assert_eq!(license_name("CC0"), "CC0 1.0");
+        assert_eq!(license_link("CC-SA-4.0"), None);
+        assert_eq!(license_url("All rights reserved"), None);
+        assert_eq!(
+            license_url("https://example.org/license"),
+            Some("https://example.org/license".to_string())
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_page_license() {
+        let own = json!({ "license": "CC-BY-4.0" });
+        let own = own.as_object().unwrap();
+        assert_eq!(page_license(own, Some("CC0")), Some("CC-BY-4.0"));
+        assert_eq!(page_license(&Map::new(), Some("CC0")), Some("CC0"));
+        assert_eq!(page_license(&Map::new(), Some(" ")), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_add_license_link() {
+        assert_eq!(
+            add_license_link("<head></head>", "CC-BY-4.0"),
+            "<head><link rel=\"license\" href=\"https://creativecommons.org/licenses/by/4.0/\">\n</head>"
+        );
+        assert_eq!(
+            add_license_link("<head></head>", "All rights reserved"),
+            "<head></head>"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_rights_manifest() {
+        let pages = vec![RightsEntry::new("/about/", "About", Some("CC0"), None)];
+        let asset = json!({ "url": "/assets/a1/march.jpg", "name": "march.jpg", "credit": "Ana" });
+        let assets: Vec<RightsEntry> = asset_rights(asset.as_object().unwrap())
+            .into_iter()
+            .collect();
+        assert_eq!(asset_rights(&Map::new()), None);
+
+        let manifest: Value =
+            serde_json::from_str(&rights_manifest(Some("CC-BY-SA-4.0"), &pages, &assets)).unwrap();
+        assert_eq!(manifest["site"]["license_name"], "CC BY-SA 4.0");
+        assert_eq!(
+            manifest["pages"][0]["license_url"],
+            "https://creativecommons.org/publicdomain/zero/1.0/"
+        );
+        assert_eq!(manifest["assets"][0]["credit"], "Ana");
+        assert_eq!(manifest["assets"][0]["license"], Value::Null);
+    }
+}
@@ -0,0 +1,113 @@
+//! Client-side search.
+//!
+//! Every export includes `search.json`, a prebuilt index of pages, posts and
+//! entry pages. A theme gets a search page by adding a `search` template,
+//! which is rendered at /search/ with `{{{search_script}}}` available: it
+//! fetches the index and fills `#search-results` as the reader types into
+//! `#search-input`.
+
+use serde_json::{json, Value};
+
+use super::html::escape_html;
+use super::text::excerpt;
+
+/// Where the index is written
+pub const SEARCH_INDEX_URL: &str = "/search.json";
+/// Template that opts a theme into a search page
+pub const SEARCH_TEMPLATE: &str = "search";
+pub const SEARCH_PAGE_URL: &str = "/search/";
+
+const EXCERPT_LENGTH: usize = 160;
+
+const STOP_WORDS: [&str; 24] = [
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "is", "it", "of", "on",
+    "or", "that", "the", "this", "to", "was", "were", "with", "you",
+];
+
+/// A page as seen by the search index
+#[derive(Debug, Clone, Default)]
+pub struct SearchDocument {
+    pub title: String,
+    pub url: String,
+    pub text: String,
+}
+
+/// Distinct lowercase words worth searching for, in first-seen order
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    for word in text.split(|c: char| !c.is_alphanumeric()) {
+        let word = word.to_lowercase();
+        if word.chars().count() < 2 || STOP_WORDS.contains(&word.as_str()) {
+            continue;
+        }
+        if !tokens.contains(&word) {
+            tokens.push(word);
+        }
+    }
+    tokens
+}
+
+/// The JSON index: `{documents: [{title, url, excerpt, tokens}]}`
+pub fn build_search_index(documents: &[SearchDocument]) -> Value {
+    let documents: Vec<Value> = documents
+        .iter()
+        .map(|document| {
+            json!({
+                "title": document.title,
+                "url": document.url,
+                "excerpt": excerpt(&document.text, EXCERPT_LENGTH),
+                "tokens": tokenize(&format!("{} {}", document.title, document.text)),
+            })
+        })
+        .collect();
+    json!({ "documents": documents })
+}
+
+/// Script for the search page; matches documents containing every query
+/// word as a prefix of one of their tokens
+pub fn search_script(index_url: &str) -> String {
+    format!(
+        "<script data-index=\"{}\">(function(){{\
+         var s=document.currentScript,input=document.getElementById('search-input'),\
+         out=document.getElementById('search-results'),docs=[];\
+         if(!input||!out)return;\
+         fetch(s.dataset.index).then(function(r){{return r.json()}}).then(function(d){{docs=d.documents||[]}});\
+         input.addEventListener('input',function(){{\
+         var q=input.value.toLowerCase().split(/[^\\p{{L}}\\p{{N}}]+/u).filter(function(w){{return w.length>1}});\
+         out.innerHTML='';if(!q.length)return;\
+         docs.filter(function(doc){{return q.every(function(w){{\
+         return doc.tokens.some(function(t){{return t.indexOf(w)===0}})}})}}).forEach(function(doc){{\
+         var li=document.createElement('li'),a=document.createElement('a'),p=document.createElement('p');\
+         a.href=doc.url;a.textContent=doc.title;p.textContent=doc.excerpt;\
+         li.appendChild(a);li.appendChild(p);out.appendChild(li)}})}})}})();</script>",
+        escape_html(index_url)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("The Zine Fair is at the Library, zine-makers welcome!"),
+            vec!["zine", "fair", "library", "makers", "welcome"]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_build_search_index() {
+        let index = build_search_index(&[SearchDocument {
+            title: "Zine Fair".to_string(),
+            url: "/events/zine-fair/".to_string(),
+            text: "Tables for makers.".to_string(),
+        }]);
+
+        let document = &index["documents"][0];
+        assert_eq!(document["url"], json!("/events/zine-fair/"));
+        assert_eq!(document["excerpt"], json!("Tables for makers."));
+        assert_eq!(document["tokens"], json!(["zine", "fair", "tables", "makers"]));
+    }
+}
@@ -0,0 +1,221 @@
+//! Canonical URLs, robots directives and the sitemap.
+//!
+//! A page can name another URL as its canonical copy (`canonical_url`, for
+//! a post syndicated from elsewhere) and ask not to be indexed (`noindex`)
+//! or have its links followed (`nofollow`). These become tags in the page's
+//! head, and pages that are noindexed or canonical elsewhere are left out
+//! of `sitemap.xml`, which is only written when the site's URL is known.
+
+use serde_json::{Map, Value};
+
+use super::head::insert_into_head;
+use super::html::escape_html;
+use super::urls::is_internal;
+
+/// Meta field holding the URL of the page's canonical copy
+pub const CANONICAL_FIELD: &str = "canonical_url";
+pub const NOINDEX_FIELD: &str = "noindex";
+pub const NOFOLLOW_FIELD: &str = "nofollow";
+pub const SITEMAP_URL: &str = "/sitemap.xml";
+/// Fields a page's last-modified date is read from, in order
+const LASTMOD_FIELDS: [&str; 2] = ["updated", "date"];
+
+/// A page listed in the sitemap
+#[derive(Debug, Clone, PartialEq)]
+pub struct SitemapPage {
+    /// Root-relative URL
+    pub url: String,
+    /// `YYYY-MM-DD`
+    pub lastmod: Option<String>,
+}
+
+fn flag(meta: &Map<String, Value>, key: &str) -> bool {
+    match meta.get(key) {
+        Some(Value::Bool(value)) => *value,
+        Some(Value::String(value)) => matches!(value.trim(), "true" | "yes" | "1"),
+        _ => false,
+    }
+}
+
+/// Whether a page asks search engines not to index it
+pub fn is_noindex(meta: &Map<String, Value>) -> bool {
+    flag(meta, NOINDEX_FIELD)
+}
+
+/// The absolute URL of a site path, or the path itself if the site's URL
+/// isn't known
+pub fn absolute(url: &str, site_root: Option<&str>) -> String {
+    match site_root {
+        Some(root) if is_internal(url) => format!("{}{}", root, url),
+        _ => url.to_string(),
+    }
+}
+
+/// The page's canonical URL: its override, or its own URL once the site's
+/// URL is known
+pub fn canonical_url(
+    meta: &Map<String, Value>,
+    url: &str,
+    site_root: Option<&str>,
+) -> Option<String> {
+    match meta
+        .get(CANONICAL_FIELD)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|canonical| !canonical.is_empty())
+    {
+        Some(canonical) => Some(absolute(canonical, site_root)),
+        None => site_root.map(|_| absolute(url, site_root)),
+    }
+}
+
+/// Whether a page's canonical copy is somewhere else
+fn canonical_elsewhere(meta: &Map<String, Value>, url: &str, site_root: Option<&str>) -> bool {
+    match canonical_url(meta, url, site_root) {
+        Some(canonical) => canonical != absolute(url, site_root),
+        None => false,
+    }
+}
+
+/// The `robots` meta content for a page, if it has any directives
+pub fn robots_directives(meta: &Map<String, Value>) -> Option<String> {
+    let directives: Vec<&str> = [(NOINDEX_FIELD, "noindex"), (NOFOLLOW_FIELD, "nofollow")]
+        .iter()
+        .filter(|(key, _)| flag(meta, key))
+        .map(|(_, directive)| *directive)
+        .collect();
+    if directives.is_empty() {
+        None
+    } else {
+        Some(directives.join(", "))
+    }
+}
+
+/// Add the canonical link and robots meta tags to a page's head
+///
+/// Pages without a `</head>` are left alone.
+pub fn add_head_tags(
+    html: &str,
+    meta: &Map<String, Value>,
+    url: &str,
+    site_root: Option<&str>,
+) -> String {
+    let mut tags = String::new();
+    if let Some(canonical) = canonical_url(meta, url, site_root) {
+        tags.push_str(&format!(
+            "<link rel=\"canonical\" href=\"{}\">\n",
+            escape_html(&canonical)
+        ));
+    }
+    if let Some(directives) = robots_directives(meta) {
+        tags.push_str(&format!(
+            "<meta name=\"robots\" content=\"{}\">\n",
+            directives
+        ));
+    }
+
+    insert_into_head(html, &tags)
+}
+
+/// A page's sitemap entry, or None if it shouldn't be listed
+pub fn sitemap_page(
+    meta: &Map<String, Value>,
+    url: &str,
+    site_root: Option<&str>,
+) -> Option<SitemapPage> {
+    if is_noindex(meta) || canonical_elsewhere(meta, url, site_root) {
+        return None;
+    }
+    let lastmod = LASTMOD_FIELDS.iter().find_map(|field| {
+        meta.get(*field)
+            .and_then(Value::as_str)
+            .and_then(|date| date.get(..10))
+            .filter(|day| chrono::NaiveDate::parse_from_str(day, "%Y-%m-%d").is_ok())
+            .map(str::to_string)
+    });
+    Some(SitemapPage {
+        url: url.to_string(),
+        lastmod,
+    })
+}
+
+/// `sitemap.xml` listing `pages` under the site's absolute URL
+pub fn sitemap_xml(pages: &[SitemapPage], site_root: &str) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for page in pages {
+        xml.push_str("<url><loc>");
+        xml.push_str(&escape_html(&absolute(&page.url, Some(site_root))));
+        xml.push_str("</loc>");
+        if let Some(lastmod) = &page.lastmod {
+            xml.push_str(&format!("<lastmod>{}</lastmod>", lastmod));
+        }
+        xml.push_str("</url>\n");
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn meta(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_add_head_tags() {
+        let html = "<html><head><title>Post</title></head><body></body></html>";
+        let syndicated = meta(json!({
+            "canonical_url": "https://medium.com/@zine/post",
+            "noindex": true,
+            "nofollow": "yes",
+        }));
+
+        assert_eq!(
+            add_head_tags(html, &syndicated, "/posts/post/", None),
+            "<html><head><title>Post</title>\
+             <link rel=\"canonical\" href=\"https://medium.com/@zine/post\">\n\
+             <meta name=\"robots\" content=\"noindex, nofollow\">\n\
+             </head><body></body></html>"
+        );
+        assert_eq!(
+            add_head_tags(html, &Map::new(), "/about/", Some("https://zine.example")),
+            "<html><head><title>Post</title>\
+             <link rel=\"canonical\" href=\"https://zine.example/about/\">\n\
+             </head><body></body></html>"
+        );
+        assert_eq!(add_head_tags(html, &Map::new(), "/about/", None), html);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_sitemap() {
+        let root = Some("https://zine.example");
+        let pages: Vec<SitemapPage> = vec![
+            ("/", meta(json!({ "updated": "2025-02-01T09:00:00Z" }))),
+            ("/drafts/", meta(json!({ "noindex": true }))),
+            (
+                "/copy/",
+                meta(json!({ "canonical_url": "https://elsewhere.example/" })),
+            ),
+            ("/self/", meta(json!({ "canonical_url": "/self/" }))),
+        ]
+        .iter()
+        .filter_map(|(url, meta)| sitemap_page(meta, url, root))
+        .collect();
+
+        assert_eq!(
+            sitemap_xml(&pages, "https://zine.example"),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n\
+             <url><loc>https://zine.example/</loc><lastmod>2025-02-01</lastmod></url>\n\
+             <url><loc>https://zine.example/self/</loc></url>\n\
+             </urlset>\n"
+        );
+    }
+}
@@ -0,0 +1,301 @@
+//! Single-file export: a whole site, or one page, as one portable HTML file.
+//!
+//! Stylesheets are inlined as `<style>` blocks and small assets become
+//! `data:` URLs. For a whole site each page's body becomes a `<section>`
+//! and links between pages become links to those sections, so the file
+//! works from an email attachment or a USB stick with no server.
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+
+use regex::Regex;
+use serde::Serialize;
+
+use super::manifest::base64_encode;
+use super::output_path;
+use super::search::SEARCH_PAGE_URL;
+use super::site::SiteOutput;
+use super::slugify;
+use super::urls::{is_internal, rewrite_html};
+
+/// Assets larger than this are left as links unless the caller says otherwise
+pub const DEFAULT_MAX_INLINE_BYTES: usize = 256 * 1024;
+
+/// What to export and how much to inline
+#[derive(Debug, Clone)]
+pub struct SingleFileOptions {
+    /// URL of one page to export, or `None` for the whole site
+    pub page: Option<String>,
+    pub max_asset_bytes: usize,
+}
+
+impl Default for SingleFileOptions {
+    fn default() -> Self {
+        SingleFileOptions {
+            page: None,
+            max_asset_bytes: DEFAULT_MAX_INLINE_BYTES,
+        }
+    }
+}
+
+/// The exported document
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SingleFile {
+    pub html: String,
+    /// Pages included, in order
+    pub pages: Vec<String>,
+    pub inlined_assets: usize,
+    pub warnings: Vec<String>,
+}
+
+/// The output path a URL is served from, ignoring any query or fragment
+fn url_path(url: &str) -> String {
+    let end = url.find(|c| c == '?' || c == '#').unwrap_or(url.len());
+    output_path(&url[..end])
+}
+
+/// Id of the section holding the page at `path`
+fn section_id(path: &str) -> String {
+    let slug = slugify(path.trim_end_matches("index.html"));
+    if slug.is_empty() {
+        "page-home".to_string()
+    } else {
+        format!("page-{}", slug)
+    }
+}
+
+fn capture(pattern: &str, html: &str) -> Option<String> {
+    Regex::new(pattern)
+        .expect("valid pattern")
+        .captures(html)
+        .and_then(|caps| caps.get(1))
+        .map(|found| found.as_str().to_string())
+}
+
+/// Contents of `<body>`, or the whole document if it has none
+fn body_of(html: &str) -> String {
+    capture(r"(?is)<body\b[^>]*>(.*)</body>", html).unwrap_or_else(|| html.to_string())
+}
+
+fn head_of(html: &str) -> String {
+    capture(r"(?is)<head\b[^>]*>(.*)</head>", html).unwrap_or_default()
+}
+
+/// Replace `<link rel="stylesheet">`s pointing into the site with the CSS
+fn inline_stylesheets(html: &str, output: &SiteOutput, warnings: &mut BTreeSet<String>) -> String {
+    let link = Regex::new(r"(?is)<link\b[^>]*>").expect("valid link regex");
+    let rel = Regex::new(r#"(?i)\brel\s*=\s*["']?[^"'>]*stylesheet"#).expect("valid rel regex");
+    let href = Regex::new(r#"(?i)\bhref\s*=\s*(?:"([^"]*)"|'([^']*)')"#).expect("valid href regex");
+
+    link.replace_all(html, |caps: &regex::Captures| {
+        let tag = &caps[0];
+        if !rel.is_match(tag) {
+            return tag.to_string();
+        }
+        let url = match href
+            .captures(tag)
+            .and_then(|found| found.get(1).or_else(|| found.get(2)))
+        {
+            Some(url) if is_internal(url.as_str()) => url.as_str().to_string(),
+            _ => return tag.to_string(),
+        };
+        match output.files.get(&url_path(&url)) {
+            Some(css) => format!("<style>\n{}\n</style>", css),
+            None => {
+                warnings.insert(format!("stylesheet not found: {}", url));
+                tag.to_string()
+            }
+        }
+    })
+    .into_owned()
+}
+
+/// Export `output` as a single HTML file
+///
+/// Expects output rendered with root-relative URLs and no base path.
+/// Scripts and assets over the size limit are left as links and reported.
+pub fn single_file_html(
+    output: &SiteOutput,
+    options: &SingleFileOptions,
+) -> Result<SingleFile, String> {
+    let search_page = output_path(SEARCH_PAGE_URL);
+    let pages: Vec<String> = match &options.page {
+        Some(url) => {
+            let path = url_path(url);
+            if !output.files.contains_key(&path) {
+                return Err(format!("No page at {}", url));
+            }
+            vec![path]
+        }
+        None => {
+            // The home page first, since its head is used for the whole file
+            let mut pages: Vec<String> = output
+                .files
+                .keys()
+                .filter(|path| path.ends_with(".html") && **path != search_page)
+                .cloned()
+                .collect();
+            pages.sort_by_key(|path| path != "index.html");
+            pages
+        }
+    };
+    let first = match pages.first() {
+        Some(first) => &output.files[first],
+        None => return Err("The site has no pages".to_string()),
+    };
+
+    // Shared with the URL map, which `rewrite_html` only borrows
+    let warnings = RefCell::new(BTreeSet::new());
+    let inlined = RefCell::new(BTreeSet::new());
+    let whole_site = options.page.is_none();
+    let map = |url: &str| -> Option<String> {
+        if !is_internal(url) {
+            return None;
+        }
+        let path = url_path(url);
+        if whole_site && pages.contains(&path) {
+            return Some(format!("#{}", section_id(&path)));
+        }
+        let asset = output.assets.iter().find(|asset| asset.path == path)?;
+        if asset.data.len() > options.max_asset_bytes {
+            warnings.borrow_mut().insert(format!(
+                "{}: {} bytes, too large to inline",
+                url,
+                asset.data.len()
+            ));
+            return None;
+        }
+        inlined.borrow_mut().insert(path);
+        Some(format!(
+            "data:{};base64,{}",
+            asset.mime_type,
+            base64_encode(&asset.data)
+        ))
+    };
+
+    let html = if whole_site {
+        let head = inline_stylesheets(&head_of(first), output, &mut warnings.borrow_mut());
+        let html_tag = capture(r"(?is)(<html\b[^>]*>)", first).unwrap_or_else(|| "<html>".into());
+        let mut document = format!(
+            "<!DOCTYPE html>\n{}\n<head>{}</head>\n<body>\n",
+            html_tag, head
+        );
+        for path in &pages {
+            document.push_str(&format!(
+                "<section class=\"single-file-page\" id=\"{}\">\n{}\n</section>\n",
+                section_id(path),
+                body_of(&output.files[path])
+            ));
+        }
+        document.push_str("</body>\n</html>\n");
+        rewrite_html(&document, &map)
+    } else {
+        let page = inline_stylesheets(first, output, &mut warnings.borrow_mut());
+        rewrite_html(&page, &map)
+    };
+
+    Ok(SingleFile {
+        html,
+        pages,
+        inlined_assets: inlined.into_inner().len(),
+        warnings: warnings.into_inner().into_iter().collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::site::SiteAsset;
+    use wasm_bindgen_test::*;
+
+    fn page(title: &str, body: &str) -> String {
+        format!(
+            "<!DOCTYPE html><html lang=\"en\"><head><title>{}</title>\
+             <link rel=\"stylesheet\" href=\"/style.css\"></head><body>{}</body></html>",
+            title, body
+        )
+    }
+
+    fn site() -> SiteOutput {
+        let mut output = SiteOutput::default();
+        output.files.insert(
+            "index.html".to_string(),
+            page(
+                "Home",
+                "<a href=\"/about/\">About</a><img src=\"/assets/1/logo.png\">",
+            ),
+        );
+        output.files.insert(
+            "about/index.html".to_string(),
+            page("About", "<p>Zines</p><img src=\"/assets/2/big.jpg\">"),
+        );
+        output.files.insert(
+            "style.css".to_string(),
+            "body { background: url(/assets/1/logo.png) }".to_string(),
+        );
+        output.assets.push(SiteAsset {
+            path: "assets/1/logo.png".to_string(),
+            mime_type: "image/png".to_string(),
+            data: b"png".to_vec(),
+        });
+        output.assets.push(SiteAsset {
+            path: "assets/2/big.jpg".to_string(),
+            mime_type: "image/jpeg".to_string(),
+            data: vec![0; 64],
+        });
+        output
+    }
+
+    #[wasm_bindgen_test]
+    fn test_whole_site() {
+        let options = SingleFileOptions {
+            page: None,
+            max_asset_bytes: 16,
+        };
+        let file = single_file_html(&site(), &options).unwrap();
+
+        assert_eq!(file.pages, vec!["index.html", "about/index.html"]);
+        assert!(file.html.starts_with("<!DOCTYPE html>\n<html lang=\"en\">"));
+        assert!(file
+            .html
+            .contains("<section class=\"single-file-page\" id=\"page-home\">"));
+        assert!(file
+            .html
+            .contains("<section class=\"single-file-page\" id=\"page-about\">"));
+        assert!(file.html.contains("<a href=\"#page-about\">About</a>"));
+        assert!(file
+            .html
+            .contains("<img src=\"data:image/png;base64,cG5n\">"));
+        assert!(file
+            .html
+            .contains("<style>\nbody { background: url(data:image/png;base64,cG5n) }\n</style>"));
+        assert!(!file.html.contains("stylesheet"));
+        assert!(file.html.contains("<img src=\"/assets/2/big.jpg\">"));
+        assert_eq!(file.inlined_assets, 1);
+        assert_eq!(
+            file.warnings,
+            vec!["/assets/2/big.jpg: 64 bytes, too large to inline"]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_single_page() {
+        let options = SingleFileOptions {
+            page: Some("/about/".to_string()),
+            ..Default::default()
+        };
+        let file = single_file_html(&site(), &options).unwrap();
+
+        assert_eq!(file.pages, vec!["about/index.html"]);
+        assert!(file.html.contains("<title>About</title>"));
+        assert!(file.html.contains("<img src=\"data:image/jpeg;base64,"));
+        assert!(!file.html.contains("Home"));
+        assert!(file.warnings.is_empty());
+
+        let missing = SingleFileOptions {
+            page: Some("/nope/".to_string()),
+            ..Default::default()
+        };
+        assert!(single_file_html(&site(), &missing).is_err());
+    }
+}
@@ -0,0 +1,1301 @@
+//! Whole-site rendering.
+//!
+//! The store gathers a [`SiteSource`] from the active site and theme (file
+//! metadata, template text and rich-text bodies), and [`render_site`] turns
+//! it into a map of output paths to file contents.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use super::asset_refs::AssetIndex;
+use super::attribution::AttributionHelper;
+use super::block_refs::resolve_all_block_refs;
+use super::blocks::resolve_layouts;
+use super::comments::{self, CommentsHelper, COMMENTS_ENABLED_KEY};
+use super::credits::{credits_context, humans_txt, HUMANS_URL};
+use super::css::variable_overrides_css;
+use super::emoji::expand_doc;
+use super::form::{self, FormHelper};
+use super::head::{insert_into_head, page_head_overrides};
+use super::hooks::RenderHooks;
+use super::html::{images_missing_alt, pm_to_html_with, HtmlOptions};
+use super::ical::{entries_to_ics, event_fields};
+use super::links::{backlinks_index, link_hrefs, resolve_internal};
+use super::listing::build_listing;
+use super::mirrors::{link_mirrors, page_mirrors};
+use super::protected::{page_password, protect_page, PASSWORD_FIELD};
+use super::redirects::{render_redirects, REDIRECTS_COLLECTION};
+use super::rights::{
+    add_license_link, asset_rights, page_license, rights_manifest, RightsEntry, RIGHTS_URL,
+};
+use super::search::{
+    build_search_index, search_script, SearchDocument, SEARCH_INDEX_URL, SEARCH_PAGE_URL,
+    SEARCH_TEMPLATE,
+};
+use super::seo::{
+    add_head_tags, canonical_url, sitemap_page, sitemap_xml, SitemapPage, SITEMAP_URL,
+};
+use super::structured_data::{
+    json_ld_script, schema_type, site_json, unsupported_types, work_json, Work,
+};
+use super::syndication::{syndication_report, SyndicatedItem};
+use super::text::pm_to_text;
+use super::urls::{apply_url_strategy, normalize_base_path, site_root};
+use super::validate::{Severity, ValidationIssue};
+use super::wikilinks::resolve_all_wikilinks;
+use super::{output_path, slugify, Renderer, BUILT_IN_HELPERS};
+use crate::types::{
+    Collaborator, EmojiStyle, FieldDefinition, FormDefinition, ListingConfig, SiteSettings,
+    Strictness,
+};
+
+/// Template used when a page doesn't name one
+pub const DEFAULT_TEMPLATE: &str = "index";
+/// Template used for posts when the theme has one
+pub const POST_TEMPLATE: &str = "post";
+
+/// A page or post ready to render
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PageSource {
+    /// "page" or "post"
+    pub collection: String,
+    pub name: String,
+    /// Meta fields (title, url, template, ...)
+    pub meta: Map<String, Value>,
+    /// ProseMirror JSON body
+    pub body: Value,
+}
+
+/// A custom collection and its entries
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CollectionSource {
+    pub name: String,
+    pub listing: Option<ListingConfig>,
+    pub fields: Vec<FieldDefinition>,
+    /// Entry JSON as returned by `Entry::to_json`
+    pub entries: Vec<Value>,
+}
+
+/// Everything needed to render a site, loaded up front by the store
+///
+/// Also deserializable, so a server can render a project exported as JSON.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SiteSource {
+    /// Site-wide values exposed to templates as `site`
+    pub site: Map<String, Value>,
+    pub settings: SiteSettings,
+    /// Template name → Handlebars source
+    pub templates: Vec<(String, String)>,
+    pub partials: Vec<(String, String)>,
+    /// Stylesheet name → CSS, written to `{name}.css`
+    pub styles: Vec<(String, String)>,
+    pub pages: Vec<PageSource>,
+    /// Shared block id → ProseMirror JSON, spliced in where pages reference it
+    pub blocks: BTreeMap<String, Value>,
+    pub collections: Vec<CollectionSource>,
+    /// Forms available to the `form` helper
+    pub forms: Vec<FormDefinition>,
+    /// Asset JSON as returned by `Asset::to_json`, plus the `path` each is
+    /// served at, for the `attribution` helper and resolving image sources
+    pub asset_meta: Vec<Value>,
+    /// People credited in `humans.txt` and the `credits` templates see
+    pub collaborators: Vec<Collaborator>,
+    /// Today's date (`YYYY-MM-DD`) for upcoming-only listings
+    pub today: String,
+    /// When the site is being built, stamped into calendar feeds
+    pub generated_at: DateTime<Utc>,
+}
+
+/// A binary file copied into the output as-is
+#[derive(Debug, Clone, Default)]
+pub struct SiteAsset {
+    pub path: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Rendered output: relative path → file contents
+#[derive(Debug, Clone, Default)]
+pub struct SiteOutput {
+    pub files: BTreeMap<String, String>,
+    /// Uploaded assets; only loaded by the store when the output is exported
+    pub assets: Vec<SiteAsset>,
+    pub warnings: Vec<String>,
+    /// Problems the site's strict profile won't export with
+    pub errors: Vec<ValidationIssue>,
+    /// Posts from other sites that were published here
+    pub syndicated: Vec<SyndicatedItem>,
+}
+
+impl SiteOutput {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "files": self.files,
+            "warnings": self.warnings,
+            "errors": self.errors,
+            "syndicated": self.syndicated,
+        })
+    }
+}
+
+fn meta_str<'a>(meta: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    meta.get(key)
+        .and_then(Value::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+/// The URL a page is published at
+pub fn page_url(page: &PageSource) -> String {
+    if let Some(url) = meta_str(&page.meta, "url") {
+        return url.to_string();
+    }
+    let slug = slugify(&page.name);
+    match page.collection.as_str() {
+        "post" => format!("/posts/{}/", slug),
+        _ if slug == "index" => "/".to_string(),
+        _ => format!("/{}/", slug),
+    }
+}
+
+/// The URL of a collection's calendar feed, if it is an events collection
+pub fn calendar_url(collection: &CollectionSource) -> Option<String> {
+    event_fields(&collection.fields).map(|_| format!("/{}.ics", slugify(&collection.name)))
+}
+
+/// The URL of an entry's detail page
+pub fn entry_url(listing: &ListingConfig, entry: &Value) -> String {
+    let name = entry.get("name").and_then(Value::as_str).unwrap_or("");
+    format!("/{}/{}/", listing.path.trim_matches('/'), slugify(name))
+}
+
+/// The template a page renders with, given whether the theme has a post template
+pub fn page_template(page: &PageSource, has_post_template: bool) -> String {
+    if let Some(template) = meta_str(&page.meta, "template") {
+        return template.to_string();
+    }
+    if page.collection == "post" && has_post_template {
+        return POST_TEMPLATE.to_string();
+    }
+    DEFAULT_TEMPLATE.to_string()
+}
+
+/// Page metadata plus its URL, as listed in `posts` and `pages`
+fn page_summary(page: &PageSource) -> Value {
+    let mut summary = page.meta.clone();
+    summary.remove(PASSWORD_FIELD);
+    summary.insert("name".to_string(), json!(page.name));
+    summary.insert("url".to_string(), json!(page_url(page)));
+    Value::Object(summary)
+}
+
+/// Record a problem with a page: an error under the strict profile, a
+/// warning otherwise
+fn report_issue(
+    output: &mut SiteOutput,
+    strictness: Strictness,
+    kind: &'static str,
+    page: &PageSource,
+    problem: String,
+) {
+    let message = format!("{} {}: {}", page.collection, page.name, problem);
+    match strictness {
+        Strictness::Strict => output.errors.push(ValidationIssue {
+            severity: Severity::Error,
+            kind,
+            collection: page.collection.clone(),
+            name: page.name.clone(),
+            message,
+        }),
+        Strictness::Lenient => output.warnings.push(message),
+    }
+}
+
+/// Report links in pages to site URLs the output has no file for
+///
+/// Assets are only added when the site is exported, so links to them are
+/// taken on trust.
+fn check_internal_links(pages: &[PageSource], strictness: Strictness, output: &mut SiteOutput) {
+    for page in pages {
+        let from = page_url(page);
+        let mut reported: Vec<String> = Vec::new();
+        for href in link_hrefs(&page.body) {
+            let target = match resolve_internal(&from, &href) {
+                Some(target) => target,
+                None => continue,
+            };
+            if target.starts_with("/assets/")
+                || reported.contains(&target)
+                || output.files.contains_key(&output_path(&target))
+            {
+                continue;
+            }
+            report_issue(
+                output,
+                strictness,
+                "broken_link",
+                page,
+                format!("link to {} goes to no page", href),
+            );
+            reported.push(target);
+        }
+    }
+}
+
+/// Render a template for the page at `url`, passing through the hooks
+fn render_page(
+    renderer: &Renderer,
+    hooks: &dyn RenderHooks,
+    template: &str,
+    url: &str,
+    context: Value,
+) -> Result<String, String> {
+    let context = hooks.before_render_page(url, context)?;
+    let html = renderer.render(template, &context)?;
+    hooks.after_render_page(url, html)
+}
+
+/// Render every page, post, listing and stylesheet in the site
+pub fn render_site(source: &SiteSource) -> Result<SiteOutput, String> {
+    render_site_with_hooks(source, &())
+}
+
+/// [`render_site`], with `hooks` called around each page and on the output
+pub fn render_site_with_hooks(
+    source: &SiteSource,
+    hooks: &dyn RenderHooks,
+) -> Result<SiteOutput, String> {
+    let mut output = SiteOutput::default();
+    let mut renderer = Renderer::new();
+    let strictness = source.settings.strictness;
+    renderer.set_strict(strictness == Strictness::Strict);
+
+    for (name, content) in &source.partials {
+        if let Err(e) = renderer.register_partial(name, content) {
+            output.warnings.push(e);
+        }
+    }
+    let (templates, mut layout_warnings) = resolve_layouts(&source.templates);
+    output.warnings.append(&mut layout_warnings);
+    for (name, content) in &templates {
+        if let Err(e) = renderer.register_template(name, content) {
+            output.warnings.push(e);
+        }
+    }
+
+    // Forms render regardless, but won't submit anywhere until configured
+    renderer.register_helper("form", Box::new(FormHelper::new(&source.forms)));
+    for form in &source.forms {
+        for problem in form::missing_settings(form) {
+            output.warnings.push(format!("form {}: {}", form.name, problem));
+        }
+    }
+
+    renderer.register_helper(
+        "attribution",
+        Box::new(AttributionHelper::new(&source.asset_meta)),
+    );
+
+    let root = site_root(&source.settings);
+    if let Some(config) = &source.settings.comments {
+        let site_url = root.as_deref();
+        renderer.register_helper("comments", Box::new(CommentsHelper::body(config, site_url)));
+        renderer.register_helper("comments_head", Box::new(CommentsHelper::head(config)));
+        for problem in comments::missing_settings(config, site_url) {
+            output.warnings.push(format!("comments: {}", problem));
+        }
+    }
+
+    for (name, helper) in hooks.helpers() {
+        if BUILT_IN_HELPERS.contains(&name.as_str()) {
+            output
+                .warnings
+                .push(format!("helper {}: built-in helpers can't be replaced", name));
+            continue;
+        }
+        renderer.register_helper(&name, helper);
+    }
+
+    // Entries get their detail URL so listings and other pages can link to them
+    let collections: Vec<CollectionSource> = source
+        .collections
+        .iter()
+        .map(|collection| {
+            let mut collection = collection.clone();
+            if let Some(listing) = collection
+                .listing
+                .as_ref()
+                .filter(|listing| listing.detail_template.is_some())
+            {
+                for entry in collection.entries.iter_mut() {
+                    let url = entry_url(listing, entry);
+                    if let Some(fields) = entry.as_object_mut() {
+                        fields.insert("url".to_string(), json!(url));
+                    }
+                }
+            }
+            collection
+        })
+        .collect();
+
+    let site = Value::Object(source.site.clone());
+    let posts: Vec<Value> = source
+        .pages
+        .iter()
+        .filter(|page| page.collection == "post")
+        .map(page_summary)
+        .collect();
+    let pages: Vec<Value> = source
+        .pages
+        .iter()
+        .filter(|page| page.collection == "page")
+        .map(page_summary)
+        .collect();
+    let entries_by_collection: Map<String, Value> = collections
+        .iter()
+        .map(|collection| (collection.name.clone(), json!(collection.entries)))
+        .collect();
+
+    let credits = credits_context(&source.collaborators);
+    let base_context = |fields: Map<String, Value>| -> Value {
+        let mut context = fields;
+        context.insert("site".to_string(), site.clone());
+        context.insert("credits".to_string(), credits.clone());
+        context.insert("posts".to_string(), json!(posts));
+        context.insert("pages".to_string(), json!(pages));
+        context.insert(
+            "collections".to_string(),
+            Value::Object(entries_by_collection.clone()),
+        );
+        Value::Object(context)
+    };
+
+    // Shared blocks go in first, so their links count as the page's own
+    let (expanded_pages, mut block_warnings) =
+        resolve_all_block_refs(&source.pages, &source.blocks);
+    output.warnings.append(&mut block_warnings);
+
+    // Wikilinks are resolved first so they count as backlinks too
+    let (linked_pages, unresolved) = resolve_all_wikilinks(&expanded_pages);
+    for link in unresolved {
+        output
+            .warnings
+            .push(format!("{}: no page named [[{}]]", link.page, link.target));
+    }
+
+    let backlinks = backlinks_index(&linked_pages);
+    let site_license = source
+        .settings
+        .license
+        .as_deref()
+        .map(str::trim)
+        .filter(|license| !license.is_empty());
+    let mut licensed = Vec::new();
+    let structured = &source.settings.structured_data;
+    if !structured.disabled {
+        output.warnings.extend(unsupported_types(structured));
+    }
+    let html_options = HtmlOptions {
+        heading_links: source.settings.heading_links,
+        images: Some(Arc::new(AssetIndex::new(&source.asset_meta))),
+        typography: source.settings.typography.clone(),
+        emoji: source.settings.emoji.clone(),
+    };
+    for page in &linked_pages {
+        let template = page_template(page, renderer.has_template(POST_TEMPLATE));
+        if !renderer.has_template(&template) {
+            output.warnings.push(format!(
+                "{} {}: template not found: {}",
+                page.collection, page.name, template
+            ));
+            continue;
+        }
+
+        let mut fields = match page_summary(page) {
+            Value::Object(fields) => fields,
+            _ => Map::new(),
+        };
+        for src in images_missing_alt(&page.body) {
+            let problem = format!("image {} has no alt text", src);
+            report_issue(&mut output, strictness, "missing_alt", page, problem);
+        }
+
+        let content = pm_to_html_with(&page.body, &html_options);
+        fields.insert("content".to_string(), json!(content));
+        let url = page_url(page);
+        let mentioned_in = backlinks.get(&url).cloned().unwrap_or_default();
+        fields.insert("backlinks".to_string(), json!(mentioned_in));
+        if let Some(config) = &source.settings.comments {
+            let enabled = comments::comments_enabled(config, &page.collection, &page.meta);
+            fields.insert(COMMENTS_ENABLED_KEY.to_string(), json!(enabled));
+        }
+
+        match render_page(&renderer, hooks, &template, &url, base_context(fields)) {
+            Ok(html) => {
+                let title = meta_str(&page.meta, "title").unwrap_or(&page.name);
+                let password = page_password(&page.meta);
+                let mirrors = match password {
+                    Some(_) => Vec::new(),
+                    None if source.settings.emoji.style == EmojiStyle::Off => {
+                        page_mirrors(&source.settings.mirrors, &url, title, &page.body)
+                    }
+                    // Shortcodes as emoji characters, which text can show
+                    None => {
+                        let body = expand_doc(&page.body);
+                        page_mirrors(&source.settings.mirrors, &url, title, &body)
+                    }
+                };
+                let html = add_head_tags(&html, &page.meta, &url, root.as_deref());
+                let license = page_license(&page.meta, site_license);
+                if let (Some(license), None) = (license, password) {
+                    licensed.push(RightsEntry::new(&url, title, Some(license), None));
+                }
+                let work_url =
+                    canonical_url(&page.meta, &url, root.as_deref()).unwrap_or_else(|| url.clone());
+                let work = Work {
+                    schema_type: schema_type(structured, &page.collection, false),
+                    title,
+                    url: &work_url,
+                    meta: &page.meta,
+                    license,
+                    event: None,
+                };
+                let html = add_work_tags(&html, source, &work, &url, root.as_deref());
+                let html = insert_into_head(&html, &page_head_overrides(&page.meta));
+                // Never publish a protected page unencrypted
+                let html = match password.map(|password| protect_page(&html, password)) {
+                    Some(Ok(sealed)) => sealed,
+                    Some(Err(e)) => {
+                        output
+                            .warnings
+                            .push(format!("{} {}: {}", page.collection, page.name, e));
+                        continue;
+                    }
+                    None => html,
+                };
+                output
+                    .files
+                    .insert(output_path(&url), link_mirrors(&html, &mirrors));
+                for mirror in mirrors {
+                    output.files.insert(output_path(&mirror.url), mirror.contents);
+                }
+            }
+            Err(e) => report_issue(&mut output, strictness, "render_failed", page, e),
+        }
+    }
+
+    output.syndicated = syndication_report(&linked_pages, &output);
+
+    let credited: Vec<RightsEntry> = source
+        .asset_meta
+        .iter()
+        .filter_map(Value::as_object)
+        .filter_map(asset_rights)
+        .collect();
+    if site_license.is_some() || !licensed.is_empty() || !credited.is_empty() {
+        output.files.insert(
+            output_path(RIGHTS_URL),
+            rights_manifest(site_license, &licensed, &credited),
+        );
+    }
+
+    for collection in &collections {
+        let listing = match &collection.listing {
+            Some(listing) => listing,
+            None => continue,
+        };
+        render_listing(
+            &renderer,
+            hooks,
+            collection,
+            listing,
+            source,
+            &base_context,
+            &mut output,
+        );
+    }
+
+    for collection in &collections {
+        render_calendar(collection, source, root.as_deref(), &mut output);
+    }
+
+    if !source.collaborators.is_empty() {
+        output.files.insert(
+            output_path(HUMANS_URL),
+            humans_txt(&source.collaborators, &source.generated_at),
+        );
+    }
+
+    if let Some(root) = &root {
+        render_sitemap(&linked_pages, &collections, root, &mut output);
+    }
+
+    render_search(
+        &renderer,
+        hooks,
+        &linked_pages,
+        &collections,
+        &base_context,
+        &mut output,
+    );
+
+    // After everything else, so redirects can't replace real pages
+    let base_path = normalize_base_path(source.settings.base_path.as_deref().unwrap_or(""));
+    for collection in &collections {
+        if collection.name == REDIRECTS_COLLECTION {
+            render_redirects(collection, &base_path, &mut output);
+        }
+    }
+
+    let (overrides, mut override_warnings) =
+        variable_overrides_css(&source.settings.theme_variables);
+    output.warnings.append(&mut override_warnings);
+    for (name, css) in &source.styles {
+        output
+            .files
+            .insert(format!("{}.css", name), format!("{}{}", css, overrides));
+    }
+
+    check_internal_links(&linked_pages, strictness, &mut output);
+
+    apply_url_strategy(&mut output, &source.settings);
+
+    if let Err(e) = hooks.on_export(&mut output.files) {
+        output.warnings.push(format!("export: {}", e));
+    }
+
+    Ok(output)
+}
+
+/// Add the license link and structured data for a page or entry published
+/// at `url`
+fn add_work_tags(
+    html: &str,
+    source: &SiteSource,
+    work: &Work,
+    url: &str,
+    root: Option<&str>,
+) -> String {
+    let html = match work.license {
+        Some(license) => add_license_link(html, license),
+        None => html.to_string(),
+    };
+    let settings = &source.settings.structured_data;
+    if settings.disabled {
+        return html;
+    }
+    let site_name = meta_str(&source.site, "name");
+    let mut scripts = json_ld_script(&work_json(work, site_name, root));
+    if let ("/", Some(name)) = (url, site_name) {
+        scripts.push_str(&json_ld_script(&site_json(settings, name, root)));
+    }
+    insert_into_head(&html, &scripts)
+}
+
+/// Write `sitemap.xml`, leaving out pages that are noindexed or canonical
+/// elsewhere
+fn render_sitemap(
+    pages: &[PageSource],
+    collections: &[CollectionSource],
+    site_root: &str,
+    output: &mut SiteOutput,
+) {
+    let mut listed: Vec<SitemapPage> = pages
+        .iter()
+        .filter(|page| page_password(&page.meta).is_none())
+        .filter(|page| output.files.contains_key(&output_path(&page_url(page))))
+        .filter_map(|page| sitemap_page(&page.meta, &page_url(page), Some(site_root)))
+        .collect();
+    // Entries only have a URL if they have a detail page
+    for entry in collections.iter().flat_map(|collection| &collection.entries) {
+        let fields = match entry.as_object() {
+            Some(fields) => fields,
+            None => continue,
+        };
+        let url = match meta_str(fields, "url") {
+            Some(url) if output.files.contains_key(&output_path(url)) => url,
+            _ => continue,
+        };
+        listed.extend(sitemap_page(fields, url, Some(site_root)));
+    }
+
+    output
+        .files
+        .insert(output_path(SITEMAP_URL), sitemap_xml(&listed, site_root));
+}
+
+/// Write the search index, and the search page if the theme has one
+fn render_search(
+    renderer: &Renderer,
+    hooks: &dyn RenderHooks,
+    pages: &[PageSource],
+    collections: &[CollectionSource],
+    base_context: &dyn Fn(Map<String, Value>) -> Value,
+    output: &mut SiteOutput,
+) {
+    let mut documents: Vec<SearchDocument> = pages
+        .iter()
+        .filter(|page| page_password(&page.meta).is_none())
+        .map(|page| SearchDocument {
+            title: meta_str(&page.meta, "title")
+                .unwrap_or(&page.name)
+                .to_string(),
+            url: page_url(page),
+            text: pm_to_text(&page.body),
+        })
+        .collect();
+
+    // Only entries with their own page can be linked to from results
+    for entry in collections.iter().flat_map(|collection| &collection.entries) {
+        let fields = match entry.as_object() {
+            Some(fields) => fields,
+            None => continue,
+        };
+        let url = match meta_str(fields, "url") {
+            Some(url) => url.to_string(),
+            None => continue,
+        };
+        let title = meta_str(fields, "title")
+            .or_else(|| meta_str(fields, "name"))
+            .unwrap_or("")
+            .to_string();
+        let text = fields
+            .iter()
+            .filter(|(key, _)| !["id", "collection_type", "url"].contains(&key.as_str()))
+            .filter_map(|(_, value)| value.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
+        documents.push(SearchDocument { title, url, text });
+    }
+
+    output.files.insert(
+        output_path(SEARCH_INDEX_URL),
+        build_search_index(&documents).to_string(),
+    );
+
+    if !renderer.has_template(SEARCH_TEMPLATE) {
+        return;
+    }
+    let mut fields = Map::new();
+    fields.insert("title".to_string(), json!("Search"));
+    fields.insert("url".to_string(), json!(SEARCH_PAGE_URL));
+    fields.insert("search_index".to_string(), json!(SEARCH_INDEX_URL));
+    fields.insert(
+        "search_script".to_string(),
+        json!(search_script(SEARCH_INDEX_URL)),
+    );
+    let context = base_context(fields);
+    match render_page(renderer, hooks, SEARCH_TEMPLATE, SEARCH_PAGE_URL, context) {
+        Ok(html) => {
+            output.files.insert(output_path(SEARCH_PAGE_URL), html);
+        }
+        Err(e) => output.warnings.push(format!("search page: {}", e)),
+    }
+}
+
+/// Write an `.ics` feed for an events collection
+fn render_calendar(
+    collection: &CollectionSource,
+    source: &SiteSource,
+    site_url: Option<&str>,
+    output: &mut SiteOutput,
+) {
+    let (fields, url) = match (event_fields(&collection.fields), calendar_url(collection)) {
+        (Some(fields), Some(url)) => (fields, url),
+        _ => return,
+    };
+    let domain = meta_str(&source.site, "id").unwrap_or("organ-pages");
+    let ics = entries_to_ics(
+        &collection.name,
+        &fields,
+        &collection.entries,
+        domain,
+        site_url,
+        &source.generated_at,
+    );
+    output.files.insert(url.trim_start_matches('/').to_string(), ics);
+}
+
+fn render_listing(
+    renderer: &Renderer,
+    hooks: &dyn RenderHooks,
+    collection: &CollectionSource,
+    listing: &ListingConfig,
+    source: &SiteSource,
+    base_context: &dyn Fn(Map<String, Value>) -> Value,
+    output: &mut SiteOutput,
+) {
+    let path = format!("/{}/", listing.path.trim_matches('/'));
+
+    if renderer.has_template(&listing.template) {
+        let mut fields = match build_listing(listing, &collection.entries, &source.today) {
+            Value::Object(fields) => fields,
+            _ => Map::new(),
+        };
+        fields.insert("collection".to_string(), json!(collection.name));
+        fields.insert("title".to_string(), json!(collection.name));
+        fields.insert("url".to_string(), json!(path));
+        if let Some(calendar) = calendar_url(collection) {
+            fields.insert("calendar".to_string(), json!(calendar));
+        }
+
+        match render_page(renderer, hooks, &listing.template, &path, base_context(fields)) {
+            Ok(html) => {
+                output.files.insert(output_path(&path), html);
+            }
+            Err(e) => output
+                .warnings
+                .push(format!("{} listing: {}", collection.name, e)),
+        }
+    } else {
+        output.warnings.push(format!(
+            "{} listing: template not found: {}",
+            collection.name, listing.template
+        ));
+    }
+
+    let detail_template = match &listing.detail_template {
+        Some(template) => template,
+        None => return,
+    };
+    if !renderer.has_template(detail_template) {
+        output.warnings.push(format!(
+            "{} entries: template not found: {}",
+            collection.name, detail_template
+        ));
+        return;
+    }
+
+    let event = event_fields(&collection.fields);
+    let root = site_root(&source.settings);
+    for entry in &collection.entries {
+        let mut fields = entry.as_object().cloned().unwrap_or_default();
+        fields.insert("collection".to_string(), json!(collection.name));
+        if !fields.contains_key("title") {
+            if let Some(name) = fields.get("name").cloned() {
+                fields.insert("title".to_string(), name);
+            }
+        }
+
+        let url = entry_url(listing, entry);
+        match render_page(
+            renderer,
+            hooks,
+            detail_template,
+            &url,
+            base_context(fields.clone()),
+        ) {
+            Ok(html) => {
+                let title = meta_str(&fields, "title").unwrap_or("");
+                let work = Work {
+                    schema_type: schema_type(
+                        &source.settings.structured_data,
+                        &collection.name,
+                        event.is_some(),
+                    ),
+                    title,
+                    url: &url,
+                    meta: &fields,
+                    license: page_license(&fields, source.settings.license.as_deref()),
+                    event: event.as_ref(),
+                };
+                let html = add_work_tags(&html, source, &work, &url, root.as_deref());
+                output.files.insert(output_path(&url), html);
+            }
+            Err(e) => output.warnings.push(format!(
+                "{} {}: {}",
+                collection.name,
+                entry.get("name").and_then(Value::as_str).unwrap_or(""),
+                e
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        CommentsConfig, CommentsProvider, DateGrouping, FieldType, FormProvider, TextMirrors,
+    };
+    use wasm_bindgen_test::*;
+
+    fn source() -> SiteSource {
+        let mut about_meta = Map::new();
+        about_meta.insert("title".to_string(), json!("About us"));
+
+        SiteSource {
+            site: json!({ "name": "Community Zine" }).as_object().cloned().unwrap(),
+            settings: SiteSettings::default(),
+            templates: vec![
+                (
+                    "index".to_string(),
+                    "<title>{{title}}</title>{{{content}}}<nav>{{#each posts}}{{url}};{{/each}}</nav>"
+                        .to_string(),
+                ),
+                (
+                    "events".to_string(),
+                    "{{#each groups}}<h2>{{label}}</h2>{{#each entries}}<a href=\"{{url}}\">{{name}}</a>{{/each}}{{/each}}"
+                        .to_string(),
+                ),
+                ("event".to_string(), "<h1>{{title}}</h1>{{location}}".to_string()),
+            ],
+            partials: Vec::new(),
+            styles: vec![("style".to_string(), "body { margin: 0 }".to_string())],
+            pages: vec![
+                PageSource {
+                    collection: "page".to_string(),
+                    name: "about".to_string(),
+                    meta: about_meta,
+                    body: json!({ "type": "doc", "content": [
+                        { "type": "paragraph", "content": [{ "type": "text", "text": "Hello" }] }
+                    ]}),
+                },
+                PageSource {
+                    collection: "post".to_string(),
+                    name: "First Post".to_string(),
+                    meta: Map::new(),
+                    body: json!({ "type": "doc", "content": [] }),
+                },
+            ],
+            collections: vec![CollectionSource {
+                name: "events".to_string(),
+                listing: Some(ListingConfig {
+                    path: "events".to_string(),
+                    template: "events".to_string(),
+                    detail_template: Some("event".to_string()),
+                    sort_by: Some("date".to_string()),
+                    descending: false,
+                    group_by: Some("date".to_string()),
+                    group_by_date: Some(DateGrouping::Month),
+                    upcoming_field: None,
+                }),
+                fields: vec![FieldDefinition {
+                    name: "date".to_string(),
+                    field_type: FieldType::DateTime,
+                    required: true,
+                }],
+                entries: vec![
+                    json!({
+                        "id": "e1", "name": "Zine Fair", "date": "2025-05-03", "location": "Library"
+                    }),
+                    json!({
+                        "id": "e2", "name": "Reading", "date": "2025-04-12", "location": "Cafe"
+                    }),
+                ],
+            }],
+            blocks: BTreeMap::new(),
+            forms: Vec::new(),
+            asset_meta: Vec::new(),
+            collaborators: Vec::new(),
+            today: "2025-01-01".to_string(),
+            generated_at: DateTime::default(),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_pages_and_posts() {
+        let output = render_site(&source()).expect("Failed to render site");
+
+        assert_eq!(
+            output.files["about/index.html"],
+            "<title>About us</title><p>Hello</p><nav>/posts/first-post/;</nav>"
+        );
+        assert!(output.files.contains_key("posts/first-post/index.html"));
+        assert_eq!(output.files["style.css"], "body { margin: 0 }");
+        assert!(output.warnings.is_empty(), "{:?}", output.warnings);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_backlinks() {
+        let mut source = source();
+        source.templates[0].1 =
+            "{{#each backlinks}}<a href=\"{{url}}\">{{title}}</a>{{/each}}".to_string();
+        source.pages[1].body = json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [
+                { "type": "text", "text": "see", "marks": [
+                    { "type": "link", "attrs": { "href": "/about/#team" } }
+                ]}
+            ]}
+        ]});
+
+        let output = render_site(&source).expect("Failed to render site");
+        assert_eq!(
+            output.files["about/index.html"],
+            "<a href=\"/posts/first-post/\">First Post</a>"
+        );
+        assert_eq!(output.files["posts/first-post/index.html"], "");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_mirrors() {
+        let mut source = source();
+        source.templates[0].1 = "<head><title>{{title}}</title></head>{{{content}}}".to_string();
+        source.settings.structured_data.disabled = true;
+        source.settings.mirrors = TextMirrors {
+            markdown: true,
+            text: false,
+        };
+
+        let output = render_site(&source).expect("Failed to render site");
+        assert_eq!(
+            output.files["about/index.html"],
+            "<head><title>About us</title>\
+             <link rel=\"alternate\" type=\"text/markdown\" href=\"/about/index.md\">\n\
+             </head><p>Hello</p>"
+        );
+        assert_eq!(output.files["about/index.md"], "# About us\n\nHello\n");
+        assert!(output.files.contains_key("posts/first-post/index.md"));
+        assert!(!output.files.contains_key("about/index.txt"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_listing_and_detail_pages() {
+        let output = render_site(&source()).expect("Failed to render site");
+
+        assert_eq!(
+            output.files["events/index.html"],
+            "<h2>April 2025</h2><a href=\"/events/reading/\">Reading</a>\
+             <h2>May 2025</h2><a href=\"/events/zine-fair/\">Zine Fair</a>"
+        );
+        assert_eq!(
+            output.files["events/zine-fair/index.html"],
+            "<h1>Zine Fair</h1>Library"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_calendar_feed() {
+        let output = render_site(&source()).expect("Failed to render site");
+
+        let ics = &output.files["events.ics"];
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+        assert!(ics.contains("URL:/events/zine-fair/\r\n"));
+
+        let mut source = source();
+        source.collections[0].fields.clear();
+        let output = render_site(&source).expect("Failed to render site");
+        assert!(!output.files.contains_key("events.ics"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_form_settings_check() {
+        let mut source = source();
+        source.forms.push(FormDefinition {
+            name: "contact".to_string(),
+            title: None,
+            provider: FormProvider::Endpoint,
+            endpoint: None,
+            fields: Vec::new(),
+            submit_label: None,
+            success_url: None,
+        });
+
+        let output = render_site(&source).expect("Failed to render site");
+        assert_eq!(
+            output.warnings,
+            vec![
+                "form contact: Endpoint forms need an endpoint".to_string(),
+                "form contact: form has no fields".to_string(),
+            ]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_comments_per_post() {
+        let mut source = source();
+        source.settings.comments = Some(CommentsConfig {
+            provider: CommentsProvider::Snippet,
+            posts_by_default: true,
+            webmention_domain: None,
+            snippet: Some("<aside>{{title}}</aside>".to_string()),
+        });
+        source.templates[0].1 = "{{name}}{{comments}}".to_string();
+        source.pages.push(PageSource {
+            collection: "post".to_string(),
+            name: "Quiet Post".to_string(),
+            meta: json!({ "comments": "false" }).as_object().cloned().unwrap(),
+            body: json!({ "type": "doc", "content": [] }),
+        });
+
+        let output = render_site(&source).expect("Failed to render site");
+        assert_eq!(output.files["about/index.html"], "about");
+        assert_eq!(
+            output.files["posts/first-post/index.html"],
+            "First Post<aside></aside>"
+        );
+        assert_eq!(output.files["posts/quiet-post/index.html"], "Quiet Post");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_with_base_path() {
+        let mut source = source();
+        source.settings.url = Some("https://zine.example/".to_string());
+        source.settings.base_path = Some("/zine/".to_string());
+        // Only URLs in attributes are rewritten, not text
+        source.templates[0].1 =
+            "<nav>{{#each posts}}<a href=\"{{url}}\">{{url}}</a>{{/each}}</nav>".to_string();
+
+        let output = render_site(&source).expect("Failed to render site");
+        assert_eq!(
+            output.files["about/index.html"],
+            "<nav><a href=\"/zine/posts/first-post/\">/posts/first-post/</a></nav>"
+        );
+        assert!(output.files["events/index.html"].contains("href=\"/zine/events/reading/\""));
+        assert!(output.files["events.ics"]
+            .contains("URL:https://zine.example/zine/events/zine-fair/\r\n"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_noindex_and_sitemap() {
+        let mut source = source();
+        source.settings.url = Some("https://zine.example".to_string());
+        source.templates[0].1 = "<head><title>{{title}}</title></head>{{{content}}}".to_string();
+        source.settings.structured_data.disabled = true;
+        source.pages[1].meta.insert("noindex".to_string(), json!(true));
+
+        let output = render_site(&source).expect("Failed to render site");
+        assert_eq!(
+            output.files["posts/first-post/index.html"],
+            "<head><title></title>\
+             <link rel=\"canonical\" href=\"https://zine.example/posts/first-post/\">\n\
+             <meta name=\"robots\" content=\"noindex\">\n\
+             </head>"
+        );
+        let sitemap = &output.files["sitemap.xml"];
+        assert!(sitemap.contains("<loc>https://zine.example/about/</loc>"));
+        assert!(sitemap.contains("<loc>https://zine.example/events/zine-fair/</loc>"));
+        assert!(!sitemap.contains("first-post"));
+    }
+
+    struct TestHooks;
+
+    handlebars::handlebars_helper!(shout: |text: String| text.to_uppercase());
+
+    impl RenderHooks for TestHooks {
+        fn helpers(&self) -> Vec<(String, Box<dyn handlebars::HelperDef + Send + Sync>)> {
+            vec![
+                ("shout".to_string(), Box::new(shout)),
+                ("form".to_string(), Box::new(shout)),
+            ]
+        }
+
+        fn before_render_page(&self, url: &str, mut context: Value) -> Result<Value, String> {
+            if url == "/about/" {
+                context["title"] = json!("Hooked");
+            }
+            Ok(context)
+        }
+
+        fn after_render_page(&self, _url: &str, html: String) -> Result<String, String> {
+            Ok(format!("{}<script src=\"/stats.js\"></script>", html))
+        }
+
+        fn on_export(&self, files: &mut BTreeMap<String, String>) -> Result<(), String> {
+            files.insert("robots.txt".to_string(), "User-agent: *".to_string());
+            Ok(())
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_with_hooks() {
+        let mut source = source();
+        source
+            .templates
+            .push(("search".to_string(), "<h1>{{shout title}}</h1>".to_string()));
+        let output = render_site_with_hooks(&source, &TestHooks).expect("Failed to render site");
+
+        let about = &output.files["about/index.html"];
+        assert!(about.starts_with("<title>Hooked</title>"));
+        assert!(about.ends_with("<script src=\"/stats.js\"></script>"));
+        assert!(output.files["events/zine-fair/index.html"].contains("/stats.js"));
+        assert!(!output.files["style.css"].contains("/stats.js"));
+        assert_eq!(output.files["robots.txt"], "User-agent: *");
+        assert!(output.files["search/index.html"].starts_with("<h1>SEARCH</h1>"));
+        assert_eq!(
+            output.warnings,
+            vec!["helper form: built-in helpers can't be replaced"]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_search_index() {
+        let mut source = source();
+        source.templates.push((
+            "search".to_string(),
+            "<input id=\"search-input\">{{{search_script}}}".to_string(),
+        ));
+
+        let output = render_site(&source).expect("Failed to render site");
+        let index: Value = serde_json::from_str(&output.files["search.json"]).unwrap();
+        let urls: Vec<&str> = index["documents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|document| document["url"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            urls,
+            vec!["/about/", "/posts/first-post/", "/events/zine-fair/", "/events/reading/"]
+        );
+        assert_eq!(index["documents"][0]["title"], json!("About us"));
+        assert!(output.files["search/index.html"].contains("data-index=\"/search.json\""));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_password_protected_page() {
+        let mut source = source();
+        source.templates[0].1 = "<h1>{{title}}</h1>{{password}}{{{content}}}".to_string();
+        source.pages[0]
+            .meta
+            .insert("password".to_string(), json!("members"));
+
+        let output = render_site(&source).expect("Failed to render site");
+        let page = &output.files["about/index.html"];
+        assert!(page.contains("<title>Protected page</title>"));
+        assert!(!page.contains("About us"));
+        assert!(!page.contains("Hello"));
+        assert!(!page.contains("members"));
+        assert!(!output.files["search.json"].contains("/about/"));
+        assert!(output.warnings.is_empty(), "{:?}", output.warnings);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_reports_missing_template() {
+        let mut source = source();
+        source.pages[0]
+            .meta
+            .insert("template".to_string(), json!("landing"));
+
+        let output = render_site(&source).expect("Failed to render site");
+        assert!(!output.files.contains_key("about/index.html"));
+        assert_eq!(
+            output.warnings,
+            vec!["page about: template not found: landing".to_string()]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_strictness() {
+        let mut source = source();
+        source.pages[0].body = json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [
+                { "type": "text", "text": "Gone", "marks": [
+                    { "type": "link", "attrs": { "href": "/gone/" } }
+                ] },
+                { "type": "text", "text": "Here", "marks": [
+                    { "type": "link", "attrs": { "href": "/about/#team" } }
+                ] }
+            ] },
+            { "type": "image", "attrs": { "src": "/assets/a1/map.png" } }
+        ]});
+
+        let output = render_site(&source).expect("Failed to render site");
+        assert!(output.errors.is_empty());
+        assert!(output.files.contains_key("posts/first-post/index.html"));
+        assert_eq!(
+            output.warnings,
+            vec![
+                "page about: image /assets/a1/map.png has no alt text".to_string(),
+                "page about: link to /gone/ goes to no page".to_string(),
+            ]
+        );
+
+        // The post has no title for the template
+        source.settings.strictness = Strictness::Strict;
+        let output = render_site(&source).expect("Failed to render site");
+        let kinds: Vec<(&str, &str)> = output
+            .errors
+            .iter()
+            .map(|issue| (issue.kind, issue.name.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("missing_alt", "about"),
+                ("render_failed", "First Post"),
+                ("broken_link", "about"),
+            ]
+        );
+        assert!(!output.files.contains_key("posts/first-post/index.html"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_licenses() {
+        let mut source = source();
+        source.templates[0].1 = "<head><title>{{title}}</title></head>".to_string();
+        let output = render_site(&source).expect("Failed to render site");
+        assert!(!output.files.contains_key("rights.json"));
+
+        source.settings.license = Some("CC-BY-SA-4.0".to_string());
+        source.pages[1]
+            .meta
+            .insert("license".to_string(), json!("All rights reserved"));
+        source.asset_meta = vec![json!({
+            "id": "a1",
+            "name": "map.png",
+            "url": "/assets/a1/map.png",
+            "credit": "Archive",
+        })];
+
+        let output = render_site(&source).expect("Failed to render site");
+        assert!(output.files["about/index.html"].contains(
+            "<link rel=\"license\" href=\"https://creativecommons.org/licenses/by-sa/4.0/\">"
+        ));
+        let post = &output.files["posts/first-post/index.html"];
+        assert!(!post.contains("rel=\"license\""));
+        assert!(post.contains("\"license\":\"All rights reserved\""));
+
+        let rights: Value = serde_json::from_str(&output.files["rights.json"]).unwrap();
+        assert_eq!(rights["site"]["license"], "CC-BY-SA-4.0");
+        assert_eq!(rights["pages"].as_array().unwrap().len(), 2);
+        assert_eq!(rights["assets"][0]["url"], "/assets/a1/map.png");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_structured_data() {
+        let mut source = source();
+        source.settings.url = Some("https://zine.example".to_string());
+        source.templates[0].1 = "<head></head>".to_string();
+        source.templates[2].1 = "<head></head><h1>{{title}}</h1>".to_string();
+        source.pages[1].meta.insert("date".to_string(), json!("2025-01-02"));
+
+        let output = render_site(&source).expect("Failed to render site");
+        let post = &output.files["posts/first-post/index.html"];
+        assert!(post.contains("\"@type\":\"BlogPosting\""));
+        assert!(post.contains("\"datePublished\":\"2025-01-02\""));
+        assert!(post.contains("\"url\":\"https://zine.example/posts/first-post/\""));
+        assert!(output.files["about/index.html"].contains("\"@type\":\"WebPage\""));
+        let event = &output.files["events/zine-fair/index.html"];
+        assert!(event.contains("\"@type\":\"Event\""));
+        assert!(event.contains("\"startDate\":\"2025-05-03\""));
+
+        source.settings.structured_data.disabled = true;
+        let output = render_site(&source).expect("Failed to render site");
+        assert!(!output.files["about/index.html"].contains("application/ld+json"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_render_site_credits() {
+        let mut source = source();
+        source.templates[0].1 = "{{#each credits}}{{name}} ({{role}});{{/each}}".to_string();
+        let output = render_site(&source).expect("Failed to render site");
+        assert!(!output.files.contains_key("humans.txt"));
+
+        source.collaborators = vec![Collaborator {
+            id: "c1".to_string(),
+            name: "Ana".to_string(),
+            roles: vec!["Editor".to_string()],
+            links: Vec::new(),
+        }];
+        let output = render_site(&source).expect("Failed to render site");
+        assert_eq!(output.files["about/index.html"], "Ana (Editor);");
+        assert!(output.files["humans.txt"].contains("Name: Ana\n    Role: Editor\n"));
+    }
+}
@@ -0,0 +1,101 @@
+//! The rendered site as a ZIP archive, to download and host anywhere.
+//!
+//! Paths in the archive are the output paths, so unzipping it at a web
+//! server's root serves the site as it was previewed.
+
+use std::io::{Cursor, Write};
+
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::manifest::output_files;
+use super::site::SiteOutput;
+
+/// Types that are compressed already, so are stored rather than deflated
+fn is_compressed(mime_type: &str) -> bool {
+    (mime_type.starts_with("image/") && mime_type != "image/svg+xml")
+        || mime_type.starts_with("audio/")
+        || mime_type.starts_with("video/")
+        || mime_type.starts_with("font/")
+        || mime_type == "application/pdf"
+}
+
+/// Every rendered file and asset of `output`, zipped
+///
+/// An asset at the same path as a rendered file is left out, as it would
+/// be overwritten when the site is served.
+pub fn site_zip(output: &SiteOutput) -> Result<Vec<u8>, String> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut written: Vec<String> = Vec::new();
+    for (path, bytes, mime_type) in output_files(output) {
+        let name = path.trim_start_matches('/').to_string();
+        if name.is_empty() || written.contains(&name) {
+            continue;
+        }
+        let method = if is_compressed(&mime_type) {
+            CompressionMethod::Stored
+        } else {
+            CompressionMethod::Deflated
+        };
+        writer
+            .start_file(
+                name.as_str(),
+                FileOptions::default().compression_method(method),
+            )
+            .map_err(|e| format!("Failed to add {} to the archive: {}", name, e))?;
+        writer
+            .write_all(&bytes)
+            .map_err(|e| format!("Failed to write {}: {}", name, e))?;
+        written.push(name);
+    }
+    let archive = writer
+        .finish()
+        .map_err(|e| format!("Failed to finish the archive: {}", e))?;
+    Ok(archive.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::site::SiteAsset;
+    use std::io::Read;
+    use wasm_bindgen_test::*;
+    use zip::ZipArchive;
+
+    #[wasm_bindgen_test]
+    fn test_site_zip() {
+        let mut output = SiteOutput::default();
+        output
+            .files
+            .insert("index.html".to_string(), "<h1>Zine</h1>".to_string());
+        output
+            .files
+            .insert("style.css".to_string(), "h1 { color: red }".to_string());
+        output.assets.push(SiteAsset {
+            path: "/assets/a1/march.jpg".to_string(),
+            mime_type: "image/jpeg".to_string(),
+            data: vec![0xff, 0xd8, 0xff],
+        });
+        output.assets.push(SiteAsset {
+            path: "index.html".to_string(),
+            mime_type: "text/html".to_string(),
+            data: b"shadowed".to_vec(),
+        });
+
+        let zip = site_zip(&output).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(zip)).unwrap();
+        assert_eq!(archive.len(), 3);
+
+        let mut index = String::new();
+        archive
+            .by_name("index.html")
+            .unwrap()
+            .read_to_string(&mut index)
+            .unwrap();
+        assert_eq!(index, "<h1>Zine</h1>");
+
+        let image = archive.by_name("assets/a1/march.jpg").unwrap();
+        assert_eq!(image.compression(), CompressionMethod::Stored);
+        assert_eq!(image.size(), 3);
+    }
+}
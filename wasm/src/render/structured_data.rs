@@ -0,0 +1,293 @@
+//! schema.org structured data, as JSON-LD in each page's head.
+//!
+//! Posts are described as `BlogPosting`s, pages as `WebPage`s and entries
+//! of events-style collections as `Event`s, from the fields they already
+//! have (title, date, author, image, location). A site can map any
+//! collection to another type, and its home page also describes the site
+//! itself, as an `Organization` if the settings say it is one.
+
+use serde_json::{json, Map, Value};
+
+use super::ical::EventFields;
+use super::rights::license_url;
+use super::seo::absolute;
+use crate::types::StructuredData;
+
+/// Types a collection can be mapped to
+pub const SCHEMA_TYPES: [&str; 6] = [
+    "Article",
+    "BlogPosting",
+    "NewsArticle",
+    "WebPage",
+    "Event",
+    "CreativeWork",
+];
+/// Types that describe writing, with a headline, dates and an author
+const ARTICLE_TYPES: [&str; 3] = ["Article", "BlogPosting", "NewsArticle"];
+/// Fields a description is read from, in order
+const DESCRIPTION_FIELDS: [&str; 3] = ["description", "summary", "excerpt"];
+
+fn meta_str<'a>(meta: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    meta.get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// The schema.org type for a page or entry of `collection`
+pub fn schema_type<'a>(settings: &'a StructuredData, collection: &str, is_event: bool) -> &'a str {
+    match settings.types.get(collection) {
+        Some(mapped) if SCHEMA_TYPES.contains(&mapped.as_str()) => mapped,
+        _ if is_event => "Event",
+        _ if collection == "post" => "BlogPosting",
+        _ => "WebPage",
+    }
+}
+
+/// A warning for each collection mapped to a type that isn't supported,
+/// whose pages keep the type they'd have otherwise
+pub fn unsupported_types(settings: &StructuredData) -> Vec<String> {
+    settings
+        .types
+        .iter()
+        .filter(|(_, mapped)| !SCHEMA_TYPES.contains(&mapped.as_str()))
+        .map(|(collection, mapped)| {
+            format!(
+                "structured data: {} can't be used for {}",
+                mapped, collection
+            )
+        })
+        .collect()
+}
+
+/// A page or entry to describe
+pub struct Work<'a> {
+    pub schema_type: &'a str,
+    pub title: &'a str,
+    /// Root-relative URL, or the page's canonical URL
+    pub url: &'a str,
+    pub meta: &'a Map<String, Value>,
+    pub license: Option<&'a str>,
+    /// The fields an event's dates and place are in
+    pub event: Option<&'a EventFields>,
+}
+
+/// People named in an `author` field, given as text or a list
+fn authors(meta: &Map<String, Value>) -> Vec<Value> {
+    let names: Vec<&str> = match meta.get("author") {
+        Some(Value::String(name)) => vec![name.as_str()],
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    names
+        .into_iter()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| json!({ "@type": "Person", "name": name }))
+        .collect()
+}
+
+/// The JSON-LD for one page or entry
+///
+/// `site_name` is given as the publisher of articles; `site_root` makes
+/// URLs absolute once the site's URL is known.
+pub fn work_json(work: &Work, site_name: Option<&str>, site_root: Option<&str>) -> Value {
+    let mut data = Map::new();
+    data.insert("@context".to_string(), json!("https://schema.org"));
+    data.insert("@type".to_string(), json!(work.schema_type));
+    let is_article = ARTICLE_TYPES.contains(&work.schema_type);
+    let title_key = if is_article { "headline" } else { "name" };
+    data.insert(title_key.to_string(), json!(work.title));
+    data.insert("url".to_string(), json!(absolute(work.url, site_root)));
+
+    let meta = work.meta;
+    if let Some(description) = DESCRIPTION_FIELDS
+        .iter()
+        .find_map(|field| meta_str(meta, field))
+    {
+        data.insert("description".to_string(), json!(description));
+    }
+    if let Some(image) = meta_str(meta, "image") {
+        data.insert("image".to_string(), json!(absolute(image, site_root)));
+    }
+    if let Some(license) = work.license {
+        let license = license_url(license).unwrap_or_else(|| license.trim().to_string());
+        data.insert("license".to_string(), json!(license));
+    }
+
+    if is_article {
+        if let Some(date) = meta_str(meta, "date") {
+            data.insert("datePublished".to_string(), json!(date));
+        }
+        if let Some(updated) = meta_str(meta, "updated") {
+            data.insert("dateModified".to_string(), json!(updated));
+        }
+        let authors = authors(meta);
+        if !authors.is_empty() {
+            data.insert("author".to_string(), json!(authors));
+        }
+        if let Some(name) = site_name {
+            data.insert(
+                "publisher".to_string(),
+                json!({ "@type": "Organization", "name": name }),
+            );
+        }
+    }
+
+    if let (Some(event), "Event") = (work.event, work.schema_type) {
+        if let Some(start) = meta_str(meta, &event.start) {
+            data.insert("startDate".to_string(), json!(start));
+        }
+        if let Some(end) = event.end.as_deref().and_then(|end| meta_str(meta, end)) {
+            data.insert("endDate".to_string(), json!(end));
+        }
+        if let Some(place) = event
+            .location
+            .as_deref()
+            .and_then(|location| meta_str(meta, location))
+        {
+            data.insert(
+                "location".to_string(),
+                json!({ "@type": "Place", "name": place, "address": place }),
+            );
+        }
+    }
+
+    Value::Object(data)
+}
+
+/// The JSON-LD describing the site, for its home page: an `Organization`,
+/// or a `WebSite`
+pub fn site_json(settings: &StructuredData, site_name: &str, site_root: Option<&str>) -> Value {
+    let mut data = Map::new();
+    data.insert("@context".to_string(), json!("https://schema.org"));
+    let schema_type = if settings.organization {
+        "Organization"
+    } else {
+        "WebSite"
+    };
+    data.insert("@type".to_string(), json!(schema_type));
+    data.insert("name".to_string(), json!(site_name));
+    data.insert("url".to_string(), json!(absolute("/", site_root)));
+    if let (true, Some(logo)) = (settings.organization, settings.logo.as_deref()) {
+        data.insert("logo".to_string(), json!(absolute(logo, site_root)));
+    }
+    Value::Object(data)
+}
+
+/// A `<script type="application/ld+json">` holding `data`
+pub fn json_ld_script(data: &Value) -> String {
+    // Nothing in the JSON can close the script early
+    format!(
+        "<script type=\"application/ld+json\">{}</script>\n",
+        data.to_string().replace("</", "<\\/")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_schema_type() {
+        let mut settings = StructuredData::default();
+        assert_eq!(schema_type(&settings, "post", false), "BlogPosting");
+        assert_eq!(schema_type(&settings, "page", false), "WebPage");
+        assert_eq!(schema_type(&settings, "events", true), "Event");
+
+        settings
+            .types
+            .insert("page".to_string(), "Article".to_string());
+        settings
+            .types
+            .insert("post".to_string(), "Recipe".to_string());
+        assert_eq!(schema_type(&settings, "page", false), "Article");
+        assert_eq!(schema_type(&settings, "post", false), "BlogPosting");
+        assert_eq!(
+            unsupported_types(&settings),
+            vec!["structured data: Recipe can't be used for post".to_string()]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_work_json() {
+        let meta = json!({
+            "date": "2024-05-01",
+            "author": ["Ana", " "],
+            "summary": "Notes from the picket line",
+            "image": "/assets/a1/march.jpg",
+        });
+        let work = Work {
+            schema_type: "BlogPosting",
+            title: "Strike diary",
+            url: "/posts/strike-diary/",
+            meta: meta.as_object().unwrap(),
+            license: Some("CC-BY-4.0"),
+            event: None,
+        };
+        let data = work_json(&work, Some("Zine"), Some("https://zine.example"));
+
+        assert_eq!(data["headline"], "Strike diary");
+        assert_eq!(data["url"], "https://zine.example/posts/strike-diary/");
+        assert_eq!(data["image"], "https://zine.example/assets/a1/march.jpg");
+        assert_eq!(data["datePublished"], "2024-05-01");
+        assert_eq!(
+            data["author"],
+            json!([{ "@type": "Person", "name": "Ana" }])
+        );
+        assert_eq!(data["publisher"]["name"], "Zine");
+        assert_eq!(data["description"], "Notes from the picket line");
+        assert_eq!(
+            data["license"],
+            "https://creativecommons.org/licenses/by/4.0/"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_event_json() {
+        let meta = json!({ "start": "2025-05-03T10:00", "venue": "Library" });
+        let fields = EventFields {
+            start: "start".to_string(),
+            end: None,
+            location: Some("venue".to_string()),
+        };
+        let work = Work {
+            schema_type: "Event",
+            title: "Zine Fair",
+            url: "/events/zine-fair/",
+            meta: meta.as_object().unwrap(),
+            license: None,
+            event: Some(&fields),
+        };
+        let data = work_json(&work, Some("Zine"), None);
+
+        assert_eq!(data["name"], "Zine Fair");
+        assert_eq!(data["url"], "/events/zine-fair/");
+        assert_eq!(data["startDate"], "2025-05-03T10:00");
+        assert_eq!(data["location"]["name"], "Library");
+        assert!(data.get("publisher").is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_site_json() {
+        let mut settings = StructuredData {
+            logo: Some("/assets/a1/logo.png".to_string()),
+            ..Default::default()
+        };
+        let data = site_json(&settings, "Zine", Some("https://zine.example"));
+        assert_eq!(data["@type"], "WebSite");
+        assert!(data.get("logo").is_none());
+
+        settings.organization = true;
+        let data = site_json(&settings, "Zine", Some("https://zine.example"));
+        assert_eq!(data["@type"], "Organization");
+        assert_eq!(data["logo"], "https://zine.example/assets/a1/logo.png");
+
+        let script = json_ld_script(&json!({ "name": "</script>" }));
+        assert_eq!(
+            script,
+            "<script type=\"application/ld+json\">{\"name\":\"<\\/script>\"}</script>\n"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Cross-site syndication.
+//!
+//! A site can show posts from other sites in the same workspace, picked
+//! by id or tag in its [`SyndicationSource`] settings. The store loads the
+//! other site's posts and passes them through [`syndicated_post`], which
+//! records where each came from (`syndicated_from`, for templates to credit
+//! the original) and points search engines at the original copy. The post
+//! then renders like one of the site's own, at a URL this site chooses, and
+//! the export reports every syndicated post it published.
+//!
+//! Bodies are rendered as written: shared blocks are resolved from the
+//! original site, but site-relative links and uploaded images still point
+//! at paths on that site.
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use super::graph::tags;
+use super::output_path;
+use super::seo::CANONICAL_FIELD;
+use super::site::{page_url, PageSource, SiteOutput};
+use crate::types::SyndicationSource;
+
+/// Meta field describing the site a syndicated post was published on
+pub const SYNDICATED_FIELD: &str = "syndicated_from";
+/// Status of posts that aren't ready to publish, which are never syndicated
+const DRAFT_STATUS: &str = "draft";
+
+/// A syndicated post published by an export
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SyndicatedItem {
+    pub url: String,
+    pub title: String,
+    pub site_id: String,
+    pub site_name: String,
+    /// Where the post is published on its own site
+    pub original_url: String,
+}
+
+fn is_draft(post: &PageSource) -> bool {
+    post.meta
+        .get("status")
+        .and_then(Value::as_str)
+        .map_or(false, |status| {
+            status.trim().eq_ignore_ascii_case(DRAFT_STATUS)
+        })
+}
+
+/// Whether a source asks for a post from its site
+pub fn source_selects(source: &SyndicationSource, post: &PageSource) -> bool {
+    if post.collection != "post" || is_draft(post) {
+        return false;
+    }
+    if source.posts.is_empty() && source.tags.is_empty() {
+        return true;
+    }
+    let id = post.meta.get("id").and_then(Value::as_str);
+    if id.map_or(false, |id| source.posts.iter().any(|wanted| wanted == id)) {
+        return true;
+    }
+    let post_tags = tags(&post.meta);
+    source.tags.iter().any(|wanted| {
+        post_tags
+            .iter()
+            .any(|tag| tag.eq_ignore_ascii_case(wanted.trim()))
+    })
+}
+
+/// A post from another site, ready to render on this one
+///
+/// `post` should already have the other site's URL patterns applied, so
+/// `original_url` is where it really lives. That URL is dropped, letting
+/// this site's patterns place the copy, and becomes the canonical URL
+/// unless the post names its own, provided the other site's URL is known.
+pub fn syndicated_post(
+    post: &PageSource,
+    site_id: &str,
+    site_name: &str,
+    site_root: Option<&str>,
+) -> PageSource {
+    let path = page_url(post);
+    let original_url = match site_root {
+        Some(root) => format!("{}{}", root, path),
+        None => path,
+    };
+
+    let mut meta = post.meta.clone();
+    meta.remove("url");
+    if site_root.is_some() {
+        meta.entry(CANONICAL_FIELD.to_string())
+            .or_insert_with(|| json!(original_url));
+    }
+    meta.insert(
+        SYNDICATED_FIELD.to_string(),
+        json!({ "site_id": site_id, "site_name": site_name, "url": original_url }),
+    );
+
+    PageSource {
+        meta,
+        ..post.clone()
+    }
+}
+
+fn syndicated_from(meta: &Map<String, Value>) -> Option<&Map<String, Value>> {
+    meta.get(SYNDICATED_FIELD).and_then(Value::as_object)
+}
+
+/// The syndicated posts that made it into the output
+pub fn syndication_report(pages: &[PageSource], output: &SiteOutput) -> Vec<SyndicatedItem> {
+    let field = |origin: &Map<String, Value>, key: &str| {
+        origin
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string()
+    };
+    pages
+        .iter()
+        .filter_map(|page| {
+            let origin = syndicated_from(&page.meta)?;
+            let url = page_url(page);
+            if !output.files.contains_key(&output_path(&url)) {
+                return None;
+            }
+            Some(SyndicatedItem {
+                title: page
+                    .meta
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .unwrap_or(&page.name)
+                    .to_string(),
+                url,
+                site_id: field(origin, "site_id"),
+                site_name: field(origin, "site_name"),
+                original_url: field(origin, "url"),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn post(name: &str, meta: Value) -> PageSource {
+        PageSource {
+            collection: "post".to_string(),
+            name: name.to_string(),
+            meta: meta.as_object().cloned().unwrap_or_default(),
+            body: json!({ "type": "doc", "content": [] }),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_syndicated_post() {
+        let source = SyndicationSource {
+            site_id: "a".to_string(),
+            posts: vec!["p1".to_string()],
+            tags: vec!["Zines".to_string()],
+        };
+        let picked = post("Launch", json!({ "id": "p1", "url": "/news/launch/" }));
+        let tagged = post("Fair", json!({ "id": "p2", "tags": "zines, fairs" }));
+        let draft = post("Soon", json!({ "id": "p1", "status": "draft" }));
+        assert!(source_selects(&source, &picked));
+        assert!(source_selects(&source, &tagged));
+        assert!(!source_selects(&source, &draft));
+        assert!(!source_selects(
+            &source,
+            &post("Other", json!({ "id": "p3" }))
+        ));
+
+        let copy = syndicated_post(&picked, "a", "Zine A", Some("https://a.example"));
+        assert_eq!(page_url(&copy), "/posts/launch/");
+        assert_eq!(
+            copy.meta[CANONICAL_FIELD],
+            json!("https://a.example/news/launch/")
+        );
+        assert_eq!(copy.meta[SYNDICATED_FIELD]["site_name"], json!("Zine A"));
+
+        let mut output = SiteOutput::default();
+        output
+            .files
+            .insert("posts/launch/index.html".to_string(), String::new());
+        let unrendered = syndicated_post(&tagged, "a", "Zine A", None);
+        let report = syndication_report(&[copy, unrendered], &output);
+        assert_eq!(
+            report,
+            vec![SyndicatedItem {
+                url: "/posts/launch/".to_string(),
+                title: "Launch".to_string(),
+                site_id: "a".to_string(),
+                site_name: "Zine A".to_string(),
+                original_url: "https://a.example/news/launch/".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,722 @@
+//! A light parse of Handlebars templates for the editor.
+//!
+//! The tree records each mustache tag (expressions, blocks, partials and
+//! comments) with its position, so the editor can highlight tags, complete
+//! context variables and jump to partials. Text between tags is left out.
+//! Positions count characters, with lines and columns starting at 1.
+//! Syntax errors are collected rather than stopping the parse, so a
+//! template being typed still gets a useful tree.
+
+use serde::Serialize;
+
+use super::{Renderer, BUILT_IN_HELPERS};
+
+/// Where something is in the template source
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// An argument to a helper, block or partial
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Param {
+    Path {
+        path: String,
+        span: Span,
+    },
+    Literal {
+        value: String,
+        span: Span,
+    },
+    Hash {
+        key: String,
+        value: Box<Param>,
+        span: Span,
+    },
+    Subexpression {
+        helper: String,
+        params: Vec<Param>,
+        span: Span,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TemplateNode {
+    Comment {
+        span: Span,
+    },
+    /// `{{path}}`, or `{{{path}}}` when not escaped
+    Variable {
+        path: String,
+        escaped: bool,
+        span: Span,
+    },
+    /// `{{helper arg key=value}}`
+    Helper {
+        name: String,
+        params: Vec<Param>,
+        escaped: bool,
+        span: Span,
+    },
+    /// `{{#name ...}}…{{else}}…{{/name}}`; `{{else if …}}` chains become a
+    /// block in the inverse section
+    Block {
+        name: String,
+        params: Vec<Param>,
+        block_params: Vec<String>,
+        /// `{{^name}}`
+        inverted: bool,
+        span: Span,
+        close: Option<Span>,
+        children: Vec<TemplateNode>,
+        inverse: Vec<TemplateNode>,
+    },
+    /// `{{> name}}`, or `{{#> name}}…{{/name}}` with fallback content
+    Partial {
+        name: String,
+        params: Vec<Param>,
+        span: Span,
+        close: Option<Span>,
+        children: Vec<TemplateNode>,
+    },
+}
+
+/// A name or path and where it appears
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Reference {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TemplateError {
+    pub message: String,
+    /// Missing when only Handlebars itself noticed the problem
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TemplateAst {
+    pub nodes: Vec<TemplateNode>,
+    /// Every context path used, for completion
+    pub variables: Vec<Reference>,
+    pub helpers: Vec<Reference>,
+    pub partials: Vec<Reference>,
+    pub errors: Vec<TemplateError>,
+}
+
+/// Turns byte offsets into spans
+struct Locator<'a> {
+    source: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> Locator<'a> {
+    fn new(source: &'a str) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(source.match_indices('\n').map(|(index, _)| index + 1));
+        Locator {
+            source,
+            line_starts,
+        }
+    }
+
+    fn span(&self, start: usize, end: usize) -> Span {
+        let line = match self.line_starts.binary_search(&start) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        Span {
+            start: self.source[..start].chars().count(),
+            end: self.source[..end].chars().count(),
+            line: line + 1,
+            column: self.source[self.line_starts[line]..start].chars().count() + 1,
+        }
+    }
+}
+
+/// A whitespace-separated piece of an expression, at a byte offset
+struct Token<'a> {
+    text: &'a str,
+    start: usize,
+}
+
+/// Split an expression into tokens, keeping quoted strings, `(…)`
+/// subexpressions and `|…|` block params whole
+fn tokenize(text: &str, base: usize) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i].1.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        if chars[i].1 == '|' {
+            i += 1;
+            while i < chars.len() && chars[i].1 != '|' {
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+        } else {
+            let mut depth = 0;
+            let mut quote: Option<char> = None;
+            while i < chars.len() {
+                let c = chars[i].1;
+                match quote {
+                    Some(q) if c == q => quote = None,
+                    Some(_) => {}
+                    None => match c {
+                        '"' | '\'' => quote = Some(c),
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        c if c.is_whitespace() && depth <= 0 => break,
+                        _ => {}
+                    },
+                }
+                i += 1;
+            }
+        }
+        let from = chars[start].0;
+        let to = chars.get(i).map_or(text.len(), |(index, _)| *index);
+        tokens.push(Token {
+            text: &text[from..to],
+            start: base + from,
+        });
+    }
+    tokens
+}
+
+fn is_literal(text: &str) -> bool {
+    ["true", "false", "null", "undefined"].contains(&text)
+        || text.parse::<f64>().is_ok()
+        || text.starts_with('"')
+        || text.starts_with('\'')
+}
+
+fn unquote(text: &str) -> &str {
+    let quoted = text.len() >= 2
+        && (text.starts_with('"') && text.ends_with('"')
+            || text.starts_with('\'') && text.ends_with('\''));
+    if quoted {
+        &text[1..text.len() - 1]
+    } else {
+        text
+    }
+}
+
+/// Position of the `=` in `key=value`, if the token is a hash argument
+fn hash_split(text: &str) -> Option<usize> {
+    let index = text.find('=')?;
+    let key = &text[..index];
+    let is_key = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '@');
+    if is_key {
+        Some(index)
+    } else {
+        None
+    }
+}
+
+struct Parser<'a> {
+    locator: Locator<'a>,
+    ast: TemplateAst,
+}
+
+/// A block whose closing tag hasn't been reached yet
+struct OpenBlock {
+    name: String,
+    params: Vec<Param>,
+    block_params: Vec<String>,
+    inverted: bool,
+    partial: bool,
+    span: Span,
+    children: Vec<TemplateNode>,
+    inverse: Vec<TemplateNode>,
+    in_inverse: bool,
+    /// Opened by `{{else name …}}`, so closed by its parent's closing tag
+    chained: bool,
+}
+
+impl OpenBlock {
+    fn finish(self, close: Option<Span>) -> TemplateNode {
+        if self.partial {
+            return TemplateNode::Partial {
+                name: self.name,
+                params: self.params,
+                span: self.span,
+                close,
+                children: self.children,
+            };
+        }
+        TemplateNode::Block {
+            name: self.name,
+            params: self.params,
+            block_params: self.block_params,
+            inverted: self.inverted,
+            span: self.span,
+            close,
+            children: self.children,
+            inverse: self.inverse,
+        }
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn span(&self, start: usize, end: usize) -> Span {
+        self.locator.span(start, end)
+    }
+
+    fn error(&mut self, message: String, span: Span) {
+        self.ast.errors.push(TemplateError {
+            message,
+            span: Some(span),
+        });
+    }
+
+    fn param(&mut self, token: &Token) -> Param {
+        let end = token.start + token.text.len();
+        let span = self.span(token.start, end);
+        let text = token.text;
+
+        if text.starts_with('(') {
+            let inner_end = if text.ends_with(')') && text.len() > 1 {
+                text.len() - 1
+            } else {
+                self.error("Unclosed subexpression".to_string(), span);
+                text.len()
+            };
+            let tokens = tokenize(&text[1..inner_end], token.start + 1);
+            let helper = tokens.first().map_or("", |token| token.text).to_string();
+            if let Some(first) = tokens.first() {
+                let span = self.span(first.start, first.start + first.text.len());
+                self.ast.helpers.push(Reference {
+                    name: helper.clone(),
+                    span,
+                });
+            }
+            let params = tokens.iter().skip(1).map(|t| self.param(t)).collect();
+            return Param::Subexpression {
+                helper,
+                params,
+                span,
+            };
+        }
+        if is_literal(text) {
+            return Param::Literal {
+                value: unquote(text).to_string(),
+                span,
+            };
+        }
+        if let Some(index) = hash_split(text) {
+            let value = Token {
+                text: &text[index + 1..],
+                start: token.start + index + 1,
+            };
+            return Param::Hash {
+                key: text[..index].to_string(),
+                value: Box::new(self.param(&value)),
+                span,
+            };
+        }
+        self.ast.variables.push(Reference {
+            name: text.to_string(),
+            span,
+        });
+        Param::Path {
+            path: text.to_string(),
+            span,
+        }
+    }
+
+    /// Parameters after the name, and any `as |a b|` block params
+    fn params(&mut self, tokens: &[Token]) -> (Vec<Param>, Vec<String>) {
+        let mut params = Vec::new();
+        let mut block_params = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            if token.text == "as" && tokens.get(i + 1).map_or(false, |t| t.text.starts_with('|')) {
+                block_params = tokens[i + 1]
+                    .text
+                    .trim_matches('|')
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect();
+                i += 2;
+                continue;
+            }
+            params.push(self.param(token));
+            i += 1;
+        }
+        (params, block_params)
+    }
+
+    fn push(&mut self, stack: &mut Vec<OpenBlock>, node: TemplateNode) {
+        match stack.last_mut() {
+            Some(open) if open.in_inverse => open.inverse.push(node),
+            Some(open) => open.children.push(node),
+            None => self.ast.nodes.push(node),
+        }
+    }
+
+    /// Handle one tag; `inner` is its contents without braces or `~`
+    fn tag(
+        &mut self,
+        stack: &mut Vec<OpenBlock>,
+        inner: &str,
+        base: usize,
+        span: Span,
+        escaped: bool,
+    ) {
+        let trimmed = inner.trim_start();
+        let base = base + inner.len() - trimmed.len();
+        let trimmed = trimmed.trim_end();
+        let first = trimmed.chars().next().unwrap_or(' ');
+
+        match first {
+            '!' => self.push(stack, TemplateNode::Comment { span }),
+            '#' | '^' if trimmed.len() > 1 || first == '#' => {
+                let partial = trimmed[1..].trim_start().starts_with('>');
+                let skip = if partial {
+                    trimmed.find('>').unwrap_or(0) + 1
+                } else {
+                    1
+                };
+                let body = trimmed[skip..].trim_start_matches('*');
+                let body_start = base + trimmed.len() - body.len();
+                let tokens = tokenize(body, body_start);
+                let name = match tokens.first() {
+                    Some(token) => token.text.to_string(),
+                    None => {
+                        self.error("Block without a name".to_string(), span);
+                        return;
+                    }
+                };
+                let name_span = self.span(tokens[0].start, tokens[0].start + name.len());
+                let reference = Reference {
+                    name: unquote(&name).to_string(),
+                    span: name_span,
+                };
+                if partial {
+                    self.ast.partials.push(reference);
+                } else {
+                    self.ast.helpers.push(reference);
+                }
+                let (params, block_params) = self.params(&tokens[1..]);
+                stack.push(OpenBlock {
+                    name: unquote(&name).to_string(),
+                    params,
+                    block_params,
+                    inverted: first == '^',
+                    partial,
+                    span,
+                    children: Vec::new(),
+                    inverse: Vec::new(),
+                    in_inverse: false,
+                    chained: false,
+                });
+            }
+            '^' => self.else_tag(stack, "", base, span),
+            _ if trimmed == "else" || trimmed.starts_with("else ") => {
+                let rest = &trimmed[4..];
+                self.else_tag(stack, rest, base + 4, span)
+            }
+            '/' => self.close_tag(stack, trimmed[1..].trim(), span),
+            '>' => {
+                let tokens = tokenize(&trimmed[1..], base + 1);
+                let name = match tokens.first() {
+                    Some(token) => token.text,
+                    None => {
+                        self.error("Partial without a name".to_string(), span);
+                        return;
+                    }
+                };
+                let name_span = self.span(tokens[0].start, tokens[0].start + name.len());
+                self.ast.partials.push(Reference {
+                    name: unquote(name).to_string(),
+                    span: name_span,
+                });
+                let (params, _) = self.params(&tokens[1..]);
+                let node = TemplateNode::Partial {
+                    name: unquote(name).to_string(),
+                    params,
+                    span,
+                    close: None,
+                    children: Vec::new(),
+                };
+                self.push(stack, node);
+            }
+            _ => {
+                let (escaped, body, body_start) = match trimmed.strip_prefix('&') {
+                    Some(body) => (false, body, base + 1),
+                    None => (escaped, trimmed, base),
+                };
+                let tokens = tokenize(body, body_start);
+                let name = match tokens.first() {
+                    Some(token) => token.text.to_string(),
+                    None => {
+                        self.error("Empty expression".to_string(), span);
+                        return;
+                    }
+                };
+                let name_span = self.span(tokens[0].start, tokens[0].start + name.len());
+                let node = if tokens.len() == 1 && !BUILT_IN_HELPERS.contains(&name.as_str()) {
+                    self.ast.variables.push(Reference {
+                        name: name.clone(),
+                        span: name_span,
+                    });
+                    TemplateNode::Variable {
+                        path: name,
+                        escaped,
+                        span,
+                    }
+                } else {
+                    self.ast.helpers.push(Reference {
+                        name: name.clone(),
+                        span: name_span,
+                    });
+                    let (params, _) = self.params(&tokens[1..]);
+                    TemplateNode::Helper {
+                        name,
+                        params,
+                        escaped,
+                        span,
+                    }
+                };
+                self.push(stack, node);
+            }
+        }
+    }
+
+    /// `{{else}}`, `{{^}}` or `{{else helper …}}`
+    fn else_tag(&mut self, stack: &mut Vec<OpenBlock>, rest: &str, base: usize, span: Span) {
+        let open = match stack.last_mut() {
+            Some(open) if !open.partial => open,
+            _ => {
+                self.error("{{else}} outside a block".to_string(), span);
+                return;
+            }
+        };
+        open.in_inverse = true;
+
+        let tokens = tokenize(rest, base);
+        let name = match tokens.first() {
+            Some(token) => token.text.to_string(),
+            None => return,
+        };
+        let name_span = self.span(tokens[0].start, tokens[0].start + name.len());
+        self.ast.helpers.push(Reference {
+            name: name.clone(),
+            span: name_span,
+        });
+        let (params, block_params) = self.params(&tokens[1..]);
+        stack.push(OpenBlock {
+            name,
+            params,
+            block_params,
+            inverted: false,
+            partial: false,
+            span,
+            children: Vec::new(),
+            inverse: Vec::new(),
+            in_inverse: false,
+            chained: true,
+        });
+    }
+
+    fn close_tag(&mut self, stack: &mut Vec<OpenBlock>, name: &str, span: Span) {
+        let name = unquote(name);
+        // `{{else if}}` chains end with their outermost block
+        while stack.last().map_or(false, |open| open.chained) {
+            let open = stack.pop().expect("checked above");
+            let node = open.finish(Some(span));
+            self.push(stack, node);
+        }
+        let open = match stack.pop() {
+            Some(open) => open,
+            None => {
+                self.error(format!("{{{{/{}}}}} doesn't close anything", name), span);
+                return;
+            }
+        };
+        if open.name != name {
+            self.error(
+                format!("{{{{/{}}}}} closes {{{{#{}}}}}", name, open.name),
+                span,
+            );
+        }
+        let node = open.finish(Some(span));
+        self.push(stack, node);
+    }
+}
+
+/// Parse a template, collecting syntax errors as it goes
+pub fn parse_template(source: &str) -> TemplateAst {
+    let mut parser = Parser {
+        locator: Locator::new(source),
+        ast: TemplateAst::default(),
+    };
+    let mut stack: Vec<OpenBlock> = Vec::new();
+
+    let mut pos = 0;
+    while let Some(found) = source[pos..].find("{{") {
+        let start = pos + found;
+        // `\{{` is literal text
+        if source[..start].ends_with('\\') {
+            pos = start + 2;
+            continue;
+        }
+        let rest = &source[start..];
+        let (open, close, escaped) = if rest.starts_with("{{!--") {
+            ("{{", "--}}", true)
+        } else if rest.starts_with("{{{") {
+            ("{{{", "}}}", false)
+        } else {
+            ("{{", "}}", true)
+        };
+        let inner_start = start + open.len();
+        let inner_end = match source[inner_start..].find(close) {
+            Some(index) => inner_start + index,
+            None => {
+                let span = parser.span(start, source.len());
+                parser.error("Unclosed {{".to_string(), span);
+                break;
+            }
+        };
+        let end = inner_end + close.len();
+        let span = parser.span(start, end);
+
+        let mut inner = &source[inner_start..inner_end];
+        let mut base = inner_start;
+        if let Some(stripped) = inner.strip_prefix('~') {
+            inner = stripped;
+            base += 1;
+        }
+        let inner = inner.strip_suffix('~').unwrap_or(inner);
+        parser.tag(&mut stack, inner, base, span, escaped);
+        pos = end;
+    }
+
+    while let Some(open) = stack.pop() {
+        if !open.chained {
+            let message = format!("{{{{#{}}}}} is never closed", open.name);
+            parser.error(message, open.span);
+        }
+        let node = open.finish(None);
+        parser.push(&mut stack, node);
+    }
+
+    // Catch anything this parse is too forgiving about
+    if parser.ast.errors.is_empty() {
+        if let Err(e) = Renderer::new().register_template("template", source) {
+            parser.ast.errors.push(TemplateError {
+                message: e,
+                span: None,
+            });
+        }
+    }
+    parser.ast
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn names(references: &[Reference]) -> Vec<&str> {
+        references.iter().map(|r| r.name.as_str()).collect()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_template() {
+        let source = "<h1>{{title}}</h1>\n\
+                      {{! note }}{{{content}}}\n\
+                      {{#each posts as |post|}}{{> card post size=\"small\"}}\
+                      {{else}}None{{/each}}\n\
+                      {{#if (eq site.name \"Zine\")}}A{{else if draft}}B{{else}}C{{/if}}";
+        let ast = parse_template(source);
+
+        assert!(ast.errors.is_empty(), "{:?}", ast.errors);
+        assert_eq!(
+            names(&ast.variables),
+            vec!["title", "content", "posts", "post", "site.name", "draft"]
+        );
+        assert_eq!(names(&ast.helpers), vec!["each", "if", "eq", "if"]);
+        assert_eq!(names(&ast.partials), vec!["card"]);
+        assert_eq!(
+            ast.variables[0].span,
+            Span {
+                start: 6,
+                end: 11,
+                line: 1,
+                column: 7
+            }
+        );
+        assert_eq!(ast.partials[0].span.line, 3);
+
+        assert_eq!(ast.nodes.len(), 5);
+        match &ast.nodes[2] {
+            TemplateNode::Variable { path, escaped, .. } => {
+                assert_eq!(path, "content");
+                assert!(!escaped);
+            }
+            other => panic!("Expected variable, got {:?}", other),
+        }
+        match &ast.nodes[3] {
+            TemplateNode::Block {
+                name,
+                block_params,
+                children,
+                close,
+                ..
+            } => {
+                assert_eq!(name, "each");
+                assert_eq!(block_params, &vec!["post".to_string()]);
+                assert!(close.is_some());
+                match &children[0] {
+                    TemplateNode::Partial { name, params, .. } => {
+                        assert_eq!(name, "card");
+                        assert_eq!(params.len(), 2);
+                        assert!(matches!(&params[1], Param::Hash { key, .. } if key == "size"));
+                    }
+                    other => panic!("Expected partial, got {:?}", other),
+                }
+            }
+            other => panic!("Expected block, got {:?}", other),
+        }
+        match &ast.nodes[4] {
+            TemplateNode::Block {
+                params, inverse, ..
+            } => {
+                assert!(
+                    matches!(&params[0], Param::Subexpression { helper, .. } if helper == "eq")
+                );
+                assert!(matches!(&inverse[0], TemplateNode::Block { name, .. } if name == "if"));
+            }
+            other => panic!("Expected block, got {:?}", other),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_parse_template_errors() {
+        let ast = parse_template("{{#if a}}{{/each}}{{#with b}}");
+        let messages: Vec<&str> = ast.errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["{{/each}} closes {{#if}}", "{{#with}} is never closed"]
+        );
+
+        let ast = parse_template("Hello {{name");
+        assert_eq!(ast.errors[0].message, "Unclosed {{");
+        assert_eq!(ast.errors[0].span.unwrap().start, 6);
+    }
+}
@@ -0,0 +1,135 @@
+//! ProseMirror JSON → plain text.
+
+use serde_json::Value;
+
+use crate::types::PlainTextOptions;
+
+/// Nodes that end a line of plain text
+const BLOCK_NODES: [&str; 12] = [
+    "paragraph",
+    "heading",
+    "blockquote",
+    "list_item",
+    "listItem",
+    "code_block",
+    "codeBlock",
+    "bullet_list",
+    "bulletList",
+    "ordered_list",
+    "orderedList",
+    "horizontal_rule",
+];
+
+/// The text of a ProseMirror document, with `options.block_separator`
+/// between blocks
+///
+/// Nested blocks, like a paragraph in a list item, are separated once,
+/// and empty blocks not at all. This is the one flattening search,
+/// excerpts and word counts all use.
+pub fn pm_to_plain_text(node: &Value, options: &PlainTextOptions) -> String {
+    let mut text = String::new();
+    let mut pending_separator = false;
+    write_plain_text(&mut text, &mut pending_separator, node, options);
+    text.trim().to_string()
+}
+
+fn write_plain_text(
+    text: &mut String,
+    pending_separator: &mut bool,
+    node: &Value,
+    options: &PlainTextOptions,
+) {
+    let mut push = |text: &mut String, value: &str| {
+        if *pending_separator && !text.is_empty() {
+            text.push_str(&options.block_separator);
+        }
+        *pending_separator = false;
+        text.push_str(value);
+    };
+
+    let node_type = node.get("type").and_then(Value::as_str).unwrap_or("");
+    match node_type {
+        "text" => {
+            if let Some(value) = node.get("text").and_then(Value::as_str) {
+                push(text, value);
+            }
+        }
+        "hard_break" | "hardBreak" => push(text, &options.line_break),
+        _ => {
+            if let Some(Value::Array(children)) = node.get("content") {
+                for child in children {
+                    write_plain_text(text, pending_separator, child, options);
+                }
+            }
+            if BLOCK_NODES.contains(&node_type) {
+                *pending_separator = true;
+            }
+        }
+    }
+}
+
+/// The text of a document, one line per block
+pub fn pm_to_text(node: &Value) -> String {
+    pm_to_plain_text(node, &PlainTextOptions::default())
+}
+
+/// The first `max_chars` characters of some text, cut at a word boundary
+pub fn excerpt(text: &str, max_chars: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= max_chars {
+        return text;
+    }
+    let cut: String = text.chars().take(max_chars).collect();
+    match cut.rfind(' ') {
+        Some(index) if index > 0 => format!("{}…", &cut[..index]),
+        _ => format!("{}…", cut),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_pm_to_text() {
+        let doc = json!({ "type": "doc", "content": [
+            { "type": "heading", "attrs": { "level": 1 }, "content": [{ "type": "text", "text": "Title" }] },
+            { "type": "paragraph", "content": [
+                { "type": "text", "text": "One " },
+                { "type": "text", "text": "two", "marks": [{ "type": "strong" }] }
+            ]},
+            { "type": "bullet_list", "content": [
+                { "type": "list_item", "content": [
+                    { "type": "paragraph", "content": [{ "type": "text", "text": "Item" }] }
+                ]}
+            ]}
+        ]});
+        assert_eq!(pm_to_text(&doc), "Title\nOne two\nItem");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_plain_text_separators() {
+        let doc = json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [
+                { "type": "text", "text": "One" },
+                { "type": "hard_break" },
+                { "type": "text", "text": "two" }
+            ]},
+            { "type": "paragraph" },
+            { "type": "paragraph", "content": [{ "type": "text", "text": "Three" }] }
+        ]});
+        let options = PlainTextOptions {
+            block_separator: "\n\n".to_string(),
+            line_break: " / ".to_string(),
+        };
+        assert_eq!(pm_to_plain_text(&doc, &options), "One / two\n\nThree");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_excerpt() {
+        assert_eq!(excerpt("short  text", 20), "short text");
+        assert_eq!(excerpt("a quick brown fox", 10), "a quick…");
+    }
+}
@@ -0,0 +1,257 @@
+//! Smart typography for rich text ("smartypants").
+//!
+//! Text is rewritten one text node at a time as it's written out, so a
+//! [`Smartener`] carries the last character over from node to node: a quote
+//! opening just before some bold text still curls the right way. Blocks
+//! start afresh, and code is passed over as written.
+
+use crate::types::Typography;
+
+/// Thin non-breaking space, used in French before `;:!?` and inside « »
+pub const NARROW_NBSP: char = '\u{202F}';
+
+/// A language's quotation marks
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Quotes {
+    double: (char, char),
+    single: (char, char),
+    /// Spaced from what they quote, as in French
+    spaced: bool,
+}
+
+/// The quotation marks for a locale such as "fr" or "de-AT"
+fn quotes_for(locale: Option<&str>) -> Quotes {
+    let language = locale
+        .and_then(|locale| locale.split(|c| c == '-' || c == '_').next())
+        .unwrap_or("en")
+        .to_ascii_lowercase();
+    match language.as_str() {
+        "fr" => Quotes {
+            double: ('«', '»'),
+            single: ('“', '”'),
+            spaced: true,
+        },
+        "de" => Quotes {
+            double: ('„', '“'),
+            single: ('‚', '‘'),
+            spaced: false,
+        },
+        _ => Quotes {
+            double: ('“', '”'),
+            single: ('‘', '’'),
+            spaced: false,
+        },
+    }
+}
+
+/// Punctuation a quote may open right after, as in `("quoted")`
+fn opens_after(c: char) -> bool {
+    c.is_whitespace() || "([{/-–—«„‚“‘".contains(c)
+}
+
+#[derive(Debug)]
+pub struct Smartener<'a> {
+    options: &'a Typography,
+    quotes: Quotes,
+    /// The last character written in this block
+    prev: Option<char>,
+    /// A single quote has opened and not closed, so the next `'` not
+    /// followed by a letter closes it rather than being an apostrophe
+    single_open: bool,
+}
+
+impl<'a> Smartener<'a> {
+    pub fn new(options: &'a Typography) -> Self {
+        Smartener {
+            options,
+            quotes: quotes_for(options.locale.as_deref()),
+            prev: None,
+            single_open: false,
+        }
+    }
+
+    /// A new block begins
+    pub fn reset(&mut self) {
+        self.prev = None;
+        self.single_open = false;
+    }
+
+    /// Text written as it is, such as code
+    pub fn pass(&mut self, text: &str) {
+        if let Some(last) = text.chars().last() {
+            self.prev = Some(last);
+        }
+    }
+
+    fn spaced(&self) -> bool {
+        self.options.spacing && self.quotes.spaced
+    }
+
+    /// Whether a quote mark here opens a quotation; at the end of a text
+    /// node, what follows isn't known yet
+    fn opens(&self, next: Option<char>) -> bool {
+        self.prev.map_or(true, opens_after) && next.map_or(true, |next| !next.is_whitespace())
+    }
+
+    /// Put a thin space before closing punctuation, replacing a plain one
+    fn space_before(&self, out: &mut String, always: bool) {
+        if out.ends_with(' ') {
+            out.pop();
+            out.push(NARROW_NBSP);
+        } else if always && !self.prev.map_or(true, char::is_whitespace) {
+            out.push(NARROW_NBSP);
+        }
+    }
+
+    pub fn smarten(&mut self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            let mut used = 1;
+            match c {
+                '"' if self.options.quotes => {
+                    if self.opens(next) {
+                        out.push(self.quotes.double.0);
+                        if self.spaced() {
+                            out.push(NARROW_NBSP);
+                            if next == Some(' ') {
+                                used = 2;
+                            }
+                        }
+                    } else {
+                        if self.spaced() {
+                            self.space_before(&mut out, true);
+                        }
+                        out.push(self.quotes.double.1);
+                    }
+                }
+                '\'' if self.options.quotes => {
+                    let before_digit = next.map_or(false, |next| next.is_ascii_digit());
+                    if self.opens(next) && !before_digit {
+                        out.push(self.quotes.single.0);
+                        self.single_open = true;
+                    } else if self.single_open && !next.map_or(false, char::is_alphanumeric) {
+                        out.push(self.quotes.single.1);
+                        self.single_open = false;
+                    } else {
+                        // '90s, don't, the students'
+                        out.push('’');
+                    }
+                }
+                '-' if self.options.dashes && next == Some('-') => {
+                    let run = chars[i..].iter().take_while(|&&c| c == '-').count();
+                    match run {
+                        2 => out.push('–'),
+                        3 => out.push('—'),
+                        // A rule drawn with hyphens
+                        _ => out.extend(&chars[i..i + run]),
+                    }
+                    used = run;
+                }
+                '.' if self.options.ellipses && chars[i..].starts_with(&['.', '.', '.']) => {
+                    out.push('…');
+                    used = 3;
+                }
+                '«' if self.spaced() => {
+                    out.push(c);
+                    out.push(NARROW_NBSP);
+                    if next == Some(' ') {
+                        used = 2;
+                    }
+                }
+                '»' if self.spaced() => {
+                    self.space_before(&mut out, true);
+                    out.push(c);
+                }
+                ';' | ':' | '!' | '?' if self.spaced() => {
+                    self.space_before(&mut out, false);
+                    out.push(c);
+                }
+                _ => out.push(c),
+            }
+            i += used;
+            self.prev = out.chars().last();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn smarten(text: &str, options: &Typography) -> String {
+        Smartener::new(options).smarten(text)
+    }
+
+    fn all(locale: Option<&str>) -> Typography {
+        Typography {
+            quotes: true,
+            dashes: true,
+            ellipses: true,
+            spacing: true,
+            locale: locale.map(str::to_string),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_english() {
+        let options = all(None);
+        assert_eq!(
+            smarten("\"Don't,\" she said --- 'it's the '90s'...", &options),
+            "“Don’t,” she said — ‘it’s the ’90s’…"
+        );
+        assert_eq!(smarten("pages 10--12", &options), "pages 10–12");
+        assert_eq!(
+            smarten("(\"quoted\") ---- rule", &options),
+            "(“quoted”) ---- rule"
+        );
+        assert_eq!(
+            smarten("the students' work", &options),
+            "the students’ work"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_french_spacing() {
+        let options = all(Some("fr-CA"));
+        assert_eq!(
+            smarten("\"Bonjour\" ; vraiment ? Oui!", &options),
+            "«\u{202F}Bonjour\u{202F}»\u{202F}; vraiment\u{202F}? Oui!"
+        );
+        assert_eq!(
+            smarten("« Déjà typé »", &options),
+            "«\u{202F}Déjà typé\u{202F}»"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_options_apply_separately() {
+        let options = Typography {
+            dashes: true,
+            locale: Some("de".to_string()),
+            ..Typography::default()
+        };
+        assert_eq!(smarten("\"Ja\" -- ...", &options), "\"Ja\" – ...");
+        let options = Typography {
+            quotes: true,
+            ..options
+        };
+        assert_eq!(smarten("\"Ja\"", &options), "„Ja“");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_carries_across_nodes() {
+        let options = all(None);
+        let mut smartener = Smartener::new(&options);
+        assert_eq!(smartener.smarten("He said \""), "He said “");
+        assert_eq!(smartener.smarten("hello"), "hello");
+        assert_eq!(smartener.smarten("\" twice."), "” twice.");
+        smartener.reset();
+        assert_eq!(smartener.smarten("\"Next"), "“Next");
+    }
+}
@@ -0,0 +1,310 @@
+//! Rewriting internal URLs in rendered output.
+//!
+//! Templates and content link to pages and assets with root-relative URLs
+//! ("/about/", "/assets/…"). When a site is hosted somewhere other than the
+//! root of a domain, those links are rewritten after rendering: in HTML
+//! attributes, `srcset`s, inline and embedded CSS, and stylesheets.
+
+use regex::{Captures, Regex};
+
+use super::site::SiteOutput;
+use crate::types::{SiteSettings, UrlStrategy};
+
+/// Whether a URL points into the site from its root (not protocol-relative)
+pub fn is_internal(url: &str) -> bool {
+    url.starts_with('/') && !url.starts_with("//")
+}
+
+/// Normalise a base path to "/path" (no trailing slash), or "" for the root
+pub fn normalize_base_path(base_path: &str) -> String {
+    let trimmed = base_path.trim().trim_matches('/');
+    if trimmed.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", trimmed)
+    }
+}
+
+/// Absolute URL of the site root (host URL plus base path), if the host is known
+pub fn site_root(settings: &SiteSettings) -> Option<String> {
+    let url = settings.url.as_deref()?.trim_end_matches('/');
+    let base_path = normalize_base_path(settings.base_path.as_deref().unwrap_or(""));
+    Some(format!("{}{}", url, base_path))
+}
+
+fn attribute_regex() -> Regex {
+    Regex::new(
+        r#"(?i)\b(href|src|action|poster|srcset|data-target|data-index)(\s*=\s*)("([^"]*)"|'([^']*)')"#,
+    )
+    .expect("valid attribute regex")
+}
+
+fn css_url_regex() -> Regex {
+    Regex::new(r#"url\(\s*(?:"([^"]*)"|'([^']*)'|([^)'"\s]+))\s*\)"#)
+        .expect("valid url() regex")
+}
+
+fn css_import_regex() -> Regex {
+    Regex::new(r#"@import\s+(?:"([^"]*)"|'([^']*)')"#).expect("valid @import regex")
+}
+
+fn rewrite_srcset(srcset: &str, map: &dyn Fn(&str) -> Option<String>) -> String {
+    srcset
+        .split(',')
+        .map(|candidate| {
+            let candidate = candidate.trim();
+            let (url, descriptor) = match candidate.find(char::is_whitespace) {
+                Some(index) => candidate.split_at(index),
+                None => (candidate, ""),
+            };
+            format!("{}{}", map(url).unwrap_or_else(|| url.to_string()), descriptor)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Rewrite URLs in CSS `url()`s and `@import`s
+pub fn rewrite_css(css: &str, map: &dyn Fn(&str) -> Option<String>) -> String {
+    let css = css_url_regex().replace_all(css, |caps: &Captures| {
+        let (url, quote) = match (caps.get(1), caps.get(2), caps.get(3)) {
+            (Some(url), _, _) => (url.as_str(), "\""),
+            (_, Some(url), _) => (url.as_str(), "'"),
+            (_, _, Some(url)) => (url.as_str(), ""),
+            _ => return caps[0].to_string(),
+        };
+        match map(url) {
+            Some(url) => format!("url({}{}{})", quote, url, quote),
+            None => caps[0].to_string(),
+        }
+    });
+
+    css_import_regex()
+        .replace_all(&css, |caps: &Captures| {
+            let (url, quote) = match (caps.get(1), caps.get(2)) {
+                (Some(url), _) => (url.as_str(), "\""),
+                (_, Some(url)) => (url.as_str(), "'"),
+                _ => return caps[0].to_string(),
+            };
+            match map(url) {
+                Some(url) => format!("@import {}{}{}", quote, url, quote),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+/// Rewrite URLs in HTML attributes, plus any CSS in the document
+pub fn rewrite_html(html: &str, map: &dyn Fn(&str) -> Option<String>) -> String {
+    let html = attribute_regex().replace_all(html, |caps: &Captures| {
+        let attribute = &caps[1];
+        let (value, quote) = match (caps.get(4), caps.get(5)) {
+            (Some(value), _) => (value.as_str(), "\""),
+            (_, Some(value)) => (value.as_str(), "'"),
+            _ => return caps[0].to_string(),
+        };
+        let rewritten = if attribute.eq_ignore_ascii_case("srcset") {
+            rewrite_srcset(value, map)
+        } else {
+            match map(value) {
+                Some(url) => url,
+                None => return caps[0].to_string(),
+            }
+        };
+        format!("{}{}{}{}{}", attribute, &caps[2], quote, rewritten, quote)
+    });
+
+    // Inline styles and <style> blocks
+    rewrite_css(&html, map)
+}
+
+/// Rewrite every HTML and CSS file in the output with `map`
+pub fn rewrite_output(output: &mut SiteOutput, map: &dyn Fn(&str, &str) -> Option<String>) {
+    for (path, contents) in output.files.iter_mut() {
+        let for_file = |url: &str| map(path, url);
+        if path.ends_with(".html") {
+            *contents = rewrite_html(contents, &for_file);
+        } else if path.ends_with(".css") {
+            *contents = rewrite_css(contents, &for_file);
+        }
+    }
+}
+
+/// Prefix internal URLs with the site's base path
+pub fn apply_base_path(output: &mut SiteOutput, base_path: &str) {
+    let base_path = normalize_base_path(base_path);
+    if base_path.is_empty() {
+        return;
+    }
+    rewrite_output(output, &|_, url| {
+        if is_internal(url) {
+            Some(format!("{}{}", base_path, url))
+        } else {
+            None
+        }
+    });
+}
+
+/// A root-relative URL rewritten relative to the output file at `from_path`
+///
+/// Directory URLs get an explicit `index.html` so they work over file://.
+pub fn relative_url(from_path: &str, url: &str) -> String {
+    let split = url.find(|c| c == '?' || c == '#').unwrap_or(url.len());
+    let (path, suffix) = url.split_at(split);
+
+    let mut target = path.trim_start_matches('/').to_string();
+    if target.is_empty() || target.ends_with('/') {
+        target.push_str("index.html");
+    }
+    let depth = from_path.matches('/').count();
+    format!("{}{}{}", "../".repeat(depth), target, suffix)
+}
+
+/// Rewrite internal URLs for the chosen strategy
+///
+/// Absolute URLs need the site URL; without one the output falls back to
+/// root-relative links and a warning is added.
+pub fn apply_url_strategy(output: &mut SiteOutput, settings: &SiteSettings) {
+    let base_path = settings.base_path.as_deref().unwrap_or("");
+    match settings.url_strategy {
+        UrlStrategy::Root => apply_base_path(output, base_path),
+        UrlStrategy::Relative => rewrite_output(output, &|path, url| {
+            if is_internal(url) {
+                Some(relative_url(path, url))
+            } else {
+                None
+            }
+        }),
+        UrlStrategy::Absolute => match site_root(settings) {
+            Some(root) => rewrite_output(output, &|_, url| {
+                if is_internal(url) {
+                    Some(format!("{}{}", root, url))
+                } else {
+                    None
+                }
+            }),
+            None => {
+                output
+                    .warnings
+                    .push("absolute URLs need the site URL; using root-relative links".to_string());
+                apply_base_path(output, base_path);
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    fn prefix(url: &str) -> Option<String> {
+        if is_internal(url) {
+            Some(format!("/site{}", url))
+        } else {
+            None
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_normalize_base_path() {
+        assert_eq!(normalize_base_path("/"), "");
+        assert_eq!(normalize_base_path("~user/site/"), "/~user/site");
+    }
+
+    #[wasm_bindgen_test]
+    fn test_rewrite_html() {
+        let html = r#"<a href="/about/">About</a><a href='https://example.com/'>x</a><a href="//cdn.example/x.js">cdn</a><img src="/assets/1/a.png" srcset="/assets/1/a.png 1x, /assets/1/b.png 2x"><div style="background: url(/assets/1/bg.png)"></div>"#;
+        assert_eq!(
+            rewrite_html(html, &prefix),
+            r#"<a href="/site/about/">About</a><a href='https://example.com/'>x</a><a href="//cdn.example/x.js">cdn</a><img src="/site/assets/1/a.png" srcset="/site/assets/1/a.png 1x, /site/assets/1/b.png 2x"><div style="background: url(/site/assets/1/bg.png)"></div>"#
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_rewrite_css() {
+        let css = r#"@import "/theme.css"; body { background: url('/assets/1/bg.png') } .x { background: url(data:image/png;base64,AA==) }"#;
+        assert_eq!(
+            rewrite_css(css, &prefix),
+            r#"@import "/site/theme.css"; body { background: url('/site/assets/1/bg.png') } .x { background: url(data:image/png;base64,AA==) }"#
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_relative_url() {
+        assert_eq!(relative_url("index.html", "/about/"), "about/index.html");
+        assert_eq!(relative_url("posts/hi/index.html", "/"), "../../index.html");
+        assert_eq!(
+            relative_url("posts/hi/index.html", "/style.css?v=2"),
+            "../../style.css?v=2"
+        );
+        assert_eq!(
+            relative_url("about/index.html", "/events/#may"),
+            "../events/index.html#may"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_url_strategy() {
+        let page = || {
+            let mut output = SiteOutput::default();
+            output.files.insert(
+                "about/index.html".to_string(),
+                "<a href=\"/posts/\">Posts</a>".to_string(),
+            );
+            output.files.insert(
+                "style.css".to_string(),
+                "body { background: url(/assets/1/bg.png) }".to_string(),
+            );
+            output
+        };
+        let mut settings = SiteSettings {
+            url_strategy: UrlStrategy::Relative,
+            ..Default::default()
+        };
+
+        let mut output = page();
+        apply_url_strategy(&mut output, &settings);
+        assert_eq!(
+            output.files["about/index.html"],
+            "<a href=\"../posts/index.html\">Posts</a>"
+        );
+        assert_eq!(
+            output.files["style.css"],
+            "body { background: url(assets/1/bg.png) }"
+        );
+
+        settings.url_strategy = UrlStrategy::Absolute;
+        let mut output = page();
+        apply_url_strategy(&mut output, &settings);
+        assert_eq!(output.warnings.len(), 1);
+        assert_eq!(output.files["about/index.html"], "<a href=\"/posts/\">Posts</a>");
+
+        settings.url = Some("https://zine.example".to_string());
+        settings.base_path = Some("zine".to_string());
+        let mut output = page();
+        apply_url_strategy(&mut output, &settings);
+        assert_eq!(
+            output.files["about/index.html"],
+            "<a href=\"https://zine.example/zine/posts/\">Posts</a>"
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_base_path() {
+        let mut output = SiteOutput::default();
+        output.files.insert(
+            "index.html".to_string(),
+            "<a href=\"/posts/\">Posts</a><a href=\"style.css\">Style</a>".to_string(),
+        );
+        output
+            .files
+            .insert("events.ics".to_string(), "URL:/events/fair/".to_string());
+
+        apply_base_path(&mut output, "site/");
+        assert_eq!(
+            output.files["index.html"],
+            "<a href=\"/site/posts/\">Posts</a><a href=\"style.css\">Style</a>"
+        );
+        assert_eq!(output.files["events.ics"], "URL:/events/fair/");
+    }
+}
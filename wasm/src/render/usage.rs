@@ -0,0 +1,338 @@
+//! Which templates and partials a site actually uses.
+//!
+//! Templates are used by the pages and posts rendered with them, by
+//! listings and entry pages, (for `search`) by the search page, and as
+//! layouts by used templates extending them.
+//! Partials are used when a used template includes them, directly or
+//! through other partials, so anything reported unused can be deleted
+//! without changing the output.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use regex::Regex;
+use serde::Serialize;
+
+use super::blocks::layout_of;
+use super::search::SEARCH_TEMPLATE;
+use super::site::{page_template, page_url, SiteSource, POST_TEMPLATE};
+
+fn partial_regex() -> Regex {
+    // `{{> name}}`, `{{~> name}}` and block partials `{{#> name}}`
+    Regex::new(r#"\{\{~?#?>\s*["']?([A-Za-z0-9_\-./]+)"#).expect("valid partial regex")
+}
+
+/// Partials a template includes by name, in order of first use
+pub fn partial_references(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for caps in partial_regex().captures_iter(template) {
+        let name = caps[1].to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageUse {
+    pub collection: String,
+    pub name: String,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TemplateUse {
+    pub name: String,
+    pub pages: Vec<PageUse>,
+    /// Collections whose listing or entry pages use the template
+    pub listings: Vec<String>,
+    /// Partials the template includes
+    pub partials: Vec<String>,
+    /// Templates that extend it as their layout
+    pub extended_by: Vec<String>,
+    pub used: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PartialUse {
+    pub name: String,
+    /// Templates and partials that include it
+    pub included_by: Vec<String>,
+    /// Partials it includes
+    pub partials: Vec<String>,
+    /// Whether a used template reaches it
+    pub used: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TemplateUsage {
+    pub templates: Vec<TemplateUse>,
+    pub partials: Vec<PartialUse>,
+    pub unused_templates: Vec<String>,
+    pub unused_partials: Vec<String>,
+    /// Template names pages, listings or layouts ask for that the theme doesn't have
+    pub missing_templates: Vec<String>,
+    /// Partial names that are included but don't exist
+    pub missing_partials: Vec<String>,
+}
+
+/// Work out template and partial usage for a site and its theme
+pub fn template_usage(source: &SiteSource) -> TemplateUsage {
+    let has_template = |name: &str| source.templates.iter().any(|(t, _)| t == name);
+    let has_post_template = has_template(POST_TEMPLATE);
+
+    let mut templates: BTreeMap<String, TemplateUse> = source
+        .templates
+        .iter()
+        .map(|(name, content)| {
+            let usage = TemplateUse {
+                name: name.clone(),
+                partials: partial_references(content),
+                ..Default::default()
+            };
+            (name.clone(), usage)
+        })
+        .collect();
+    let mut missing_templates = BTreeSet::new();
+
+    for page in &source.pages {
+        let template = page_template(page, has_post_template);
+        match templates.get_mut(&template) {
+            Some(usage) => usage.pages.push(PageUse {
+                collection: page.collection.clone(),
+                name: page.name.clone(),
+                url: page_url(page),
+            }),
+            None => {
+                missing_templates.insert(template);
+            }
+        }
+    }
+
+    for collection in &source.collections {
+        let listing = match &collection.listing {
+            Some(listing) => listing,
+            None => continue,
+        };
+        let names = std::iter::once(&listing.template).chain(listing.detail_template.as_ref());
+        for template in names {
+            match templates.get_mut(template) {
+                Some(usage) => {
+                    if !usage.listings.contains(&collection.name) {
+                        usage.listings.push(collection.name.clone());
+                    }
+                }
+                None => {
+                    missing_templates.insert(template.clone());
+                }
+            }
+        }
+    }
+
+    for usage in templates.values_mut() {
+        usage.used =
+            !usage.pages.is_empty() || !usage.listings.is_empty() || usage.name == SEARCH_TEMPLATE;
+    }
+
+    // A layout is used by any used template extending it, however indirectly
+    let layouts: Vec<(String, String)> = source
+        .templates
+        .iter()
+        .filter_map(|(name, content)| Some((name.clone(), layout_of(content)?)))
+        .collect();
+    for (name, layout) in &layouts {
+        match templates.get_mut(layout) {
+            Some(usage) => usage.extended_by.push(name.clone()),
+            None => {
+                missing_templates.insert(layout.clone());
+            }
+        }
+    }
+    let mut pending: Vec<String> = templates
+        .values()
+        .filter(|usage| usage.used)
+        .map(|usage| usage.name.clone())
+        .collect();
+    while let Some(name) = pending.pop() {
+        for (_, layout) in layouts.iter().filter(|(child, _)| *child == name) {
+            if let Some(usage) = templates.get_mut(layout) {
+                if !usage.used {
+                    usage.used = true;
+                    pending.push(layout.clone());
+                }
+            }
+        }
+    }
+
+    let mut partials: BTreeMap<String, PartialUse> = source
+        .partials
+        .iter()
+        .map(|(name, content)| {
+            let usage = PartialUse {
+                name: name.clone(),
+                partials: partial_references(content),
+                ..Default::default()
+            };
+            (name.clone(), usage)
+        })
+        .collect();
+
+    let mut includes: Vec<(String, String)> = Vec::new();
+    for usage in templates.values() {
+        for partial in &usage.partials {
+            includes.push((usage.name.clone(), partial.clone()));
+        }
+    }
+    for usage in partials.values() {
+        for partial in &usage.partials {
+            includes.push((usage.name.clone(), partial.clone()));
+        }
+    }
+    let mut missing_partials = BTreeSet::new();
+    for (from, partial) in includes {
+        match partials.get_mut(&partial) {
+            Some(usage) => {
+                if !usage.included_by.contains(&from) {
+                    usage.included_by.push(from);
+                }
+            }
+            None if templates.contains_key(&partial) => {}
+            None => {
+                missing_partials.insert(partial);
+            }
+        }
+    }
+
+    // Walk the includes from every used template
+    let mut pending: Vec<String> = templates
+        .values()
+        .filter(|usage| usage.used)
+        .flat_map(|usage| usage.partials.clone())
+        .collect();
+    while let Some(name) = pending.pop() {
+        if let Some(usage) = partials.get_mut(&name) {
+            if !usage.used {
+                usage.used = true;
+                pending.extend(usage.partials.clone());
+            }
+        }
+    }
+
+    TemplateUsage {
+        unused_templates: templates
+            .values()
+            .filter(|usage| !usage.used)
+            .map(|usage| usage.name.clone())
+            .collect(),
+        unused_partials: partials
+            .values()
+            .filter(|usage| !usage.used)
+            .map(|usage| usage.name.clone())
+            .collect(),
+        templates: templates.into_iter().map(|(_, usage)| usage).collect(),
+        partials: partials.into_iter().map(|(_, usage)| usage).collect(),
+        missing_templates: missing_templates.into_iter().collect(),
+        missing_partials: missing_partials.into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::site::{CollectionSource, PageSource};
+    use crate::types::ListingConfig;
+    use serde_json::{json, Map};
+    use wasm_bindgen_test::*;
+
+    fn page(collection: &str, name: &str, template: Option<&str>) -> PageSource {
+        let mut meta = Map::new();
+        if let Some(template) = template {
+            meta.insert("template".to_string(), json!(template));
+        }
+        PageSource {
+            collection: collection.to_string(),
+            name: name.to_string(),
+            meta,
+            body: json!({ "type": "doc", "content": [] }),
+        }
+    }
+
+    fn named(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(name, content)| (name.to_string(), content.to_string()))
+            .collect()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_partial_references() {
+        assert_eq!(
+            partial_references(
+                "{{> header}}{{~> nav }}{{#> layout title=\"x\"}}{{/layout}}{{> header}}"
+            ),
+            vec!["header", "nav", "layout"]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_template_usage() {
+        let source = SiteSource {
+            templates: named(&[
+                ("index", "{{> header}}{{{content}}}"),
+                ("post", "{{> header}}{{> byline}}"),
+                ("events", "{{#each entries}}{{/each}}"),
+                ("old", "{{> legacy}}"),
+            ]),
+            partials: named(&[
+                ("header", "{{> nav}}"),
+                ("nav", "<nav></nav>"),
+                ("legacy", "<marquee></marquee>"),
+                ("footer", ""),
+            ]),
+            pages: vec![
+                page("page", "about", None),
+                page("post", "hello", None),
+                page("page", "contact", Some("contact")),
+            ],
+            collections: vec![CollectionSource {
+                name: "events".to_string(),
+                listing: Some(ListingConfig {
+                    path: "events".to_string(),
+                    template: "events".to_string(),
+                    detail_template: Some("event".to_string()),
+                    sort_by: None,
+                    descending: false,
+                    group_by: None,
+                    group_by_date: None,
+                    upcoming_field: None,
+                }),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let usage = template_usage(&source);
+
+        let index = usage.templates.iter().find(|t| t.name == "index").unwrap();
+        assert_eq!(index.pages.len(), 1);
+        assert_eq!(index.pages[0].url, "/about/");
+        let events = usage.templates.iter().find(|t| t.name == "events").unwrap();
+        assert_eq!(events.listings, vec!["events"]);
+
+        assert_eq!(usage.unused_templates, vec!["old"]);
+        assert_eq!(usage.unused_partials, vec!["footer", "legacy"]);
+        assert_eq!(usage.missing_templates, vec!["contact", "event"]);
+        assert_eq!(usage.missing_partials, vec!["byline"]);
+
+        let header = usage.partials.iter().find(|p| p.name == "header").unwrap();
+        assert_eq!(header.included_by, vec!["index", "post"]);
+        assert!(
+            usage
+                .partials
+                .iter()
+                .find(|p| p.name == "nav")
+                .unwrap()
+                .used
+        );
+    }
+}
@@ -0,0 +1,349 @@
+//! Checks a site's content before it is exported.
+//!
+//! Errors would publish a broken site (a page rendered with a template the
+//! theme doesn't have, two pages at one URL, an entry missing a field its
+//! collection requires) and block the export; warnings are worth a look
+//! but don't stop it. Problems only rendering finds (template variables a
+//! page doesn't have, images without alt text, broken internal links) are
+//! errors or warnings as the site's [`Strictness`](crate::types::Strictness)
+//! says.
+
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::site::{entry_url, page_template, page_url, SiteSource, POST_TEMPLATE};
+use crate::types::FieldDefinition;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    /// "missing_field", "missing_template", "duplicate_url", "empty_title",
+    /// "past_draft", "render_failed", "missing_alt" or "broken_link"
+    pub kind: &'static str,
+    pub collection: String,
+    pub name: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ValidationReport {
+    /// Whether any issue is an error, so the export should wait
+    pub blocking: bool,
+    pub errors: Vec<ValidationIssue>,
+    pub warnings: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn add(&mut self, issue: ValidationIssue) {
+        match issue.severity {
+            Severity::Error => {
+                self.blocking = true;
+                self.errors.push(issue);
+            }
+            Severity::Warning => self.warnings.push(issue),
+        }
+    }
+
+    fn push(
+        &mut self,
+        severity: Severity,
+        kind: &'static str,
+        collection: &str,
+        name: &str,
+        message: String,
+    ) {
+        self.add(ValidationIssue {
+            severity,
+            kind,
+            collection: collection.to_string(),
+            name: name.to_string(),
+            message,
+        });
+    }
+}
+
+/// Whether a metadata value counts as filled in
+fn is_filled(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => false,
+        Some(Value::String(text)) => !text.trim().is_empty(),
+        Some(Value::Array(items)) => !items.is_empty(),
+        Some(_) => true,
+    }
+}
+
+/// Day an ISO date or datetime refers to
+fn meta_day(meta: &Map<String, Value>, key: &str) -> Option<NaiveDate> {
+    meta.get(key)
+        .and_then(Value::as_str)
+        .and_then(|text| text.get(..10))
+        .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+}
+
+fn check_required(
+    report: &mut ValidationReport,
+    collection: &str,
+    name: &str,
+    meta: &Map<String, Value>,
+    fields: &[FieldDefinition],
+) {
+    for field in fields.iter().filter(|field| field.required) {
+        if !is_filled(meta.get(&field.name)) {
+            report.push(
+                Severity::Error,
+                "missing_field",
+                collection,
+                name,
+                format!(
+                    "{} {} has no {}, which is required",
+                    collection, name, field.name
+                ),
+            );
+        }
+    }
+}
+
+/// Problems in `source` that an export would publish
+///
+/// `page_fields` holds the fields the page and post collections define;
+/// custom collections carry their own. Drafts are dated against `source.today`.
+pub fn validate_site(
+    source: &SiteSource,
+    page_fields: &BTreeMap<String, Vec<FieldDefinition>>,
+) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let today = NaiveDate::parse_from_str(&source.today, "%Y-%m-%d").ok();
+    let has_post_template = source
+        .templates
+        .iter()
+        .any(|(name, _)| name == POST_TEMPLATE);
+    let has_template = |name: &str| source.templates.iter().any(|(t, _)| t == name);
+    // URL → the first file published there
+    let mut urls: BTreeMap<String, (String, String)> = BTreeMap::new();
+
+    for page in &source.pages {
+        let (collection, name) = (page.collection.as_str(), page.name.as_str());
+        if let Some(fields) = page_fields.get(collection) {
+            check_required(&mut report, collection, name, &page.meta, fields);
+        }
+
+        let template = page_template(page, has_post_template);
+        if !has_template(template.as_str()) {
+            report.push(
+                Severity::Error,
+                "missing_template",
+                collection,
+                name,
+                format!(
+                    "{} {} uses template {}, which the theme doesn't have",
+                    collection, name, template
+                ),
+            );
+        }
+
+        if !is_filled(page.meta.get("title")) {
+            report.push(
+                Severity::Warning,
+                "empty_title",
+                collection,
+                name,
+                format!("{} {} has no title", collection, name),
+            );
+        }
+
+        let is_draft = page
+            .meta
+            .get("status")
+            .and_then(Value::as_str)
+            .map_or(false, |status| status.eq_ignore_ascii_case("draft"));
+        if let (true, Some(date), Some(today)) = (is_draft, meta_day(&page.meta, "date"), today) {
+            if date < today {
+                report.push(
+                    Severity::Warning,
+                    "past_draft",
+                    collection,
+                    name,
+                    format!(
+                        "{} {} is dated {} but is still a draft",
+                        collection,
+                        name,
+                        date.format("%Y-%m-%d")
+                    ),
+                );
+            }
+        }
+
+        check_url(&mut report, &mut urls, page_url(page), collection, name);
+    }
+
+    for collection in &source.collections {
+        for entry in &collection.entries {
+            let meta = match entry.as_object() {
+                Some(meta) => meta,
+                None => continue,
+            };
+            let name = meta.get("name").and_then(Value::as_str).unwrap_or("");
+            check_required(
+                &mut report,
+                &collection.name,
+                name,
+                meta,
+                &collection.fields,
+            );
+            if let Some(listing) = &collection.listing {
+                if listing.detail_template.is_some() {
+                    let url = entry_url(listing, entry);
+                    check_url(&mut report, &mut urls, url, &collection.name, name);
+                }
+            }
+        }
+    }
+
+    report
+}
+
+/// Record that a file is published at `url`, reporting it if another already is
+fn check_url(
+    report: &mut ValidationReport,
+    urls: &mut BTreeMap<String, (String, String)>,
+    url: String,
+    collection: &str,
+    name: &str,
+) {
+    match urls.get(&url) {
+        Some((first_collection, first_name)) => report.push(
+            Severity::Error,
+            "duplicate_url",
+            collection,
+            name,
+            format!(
+                "{} {} and {} {} would both be published at {}",
+                first_collection, first_name, collection, name, url
+            ),
+        ),
+        None => {
+            urls.insert(url, (collection.to_string(), name.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::site::{CollectionSource, PageSource};
+    use crate::types::{FieldType, ListingConfig};
+    use serde_json::json;
+    use wasm_bindgen_test::*;
+
+    fn page(collection: &str, name: &str, meta: Value) -> PageSource {
+        PageSource {
+            collection: collection.to_string(),
+            name: name.to_string(),
+            meta: meta.as_object().cloned().unwrap_or_default(),
+            body: json!({ "type": "doc", "content": [] }),
+        }
+    }
+
+    fn required(name: &str) -> FieldDefinition {
+        FieldDefinition {
+            name: name.to_string(),
+            field_type: FieldType::String,
+            required: true,
+        }
+    }
+
+    fn kinds(issues: &[ValidationIssue]) -> Vec<(&str, &str)> {
+        issues
+            .iter()
+            .map(|issue| (issue.kind, issue.name.as_str()))
+            .collect()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_validate_site() {
+        let source = SiteSource {
+            today: "2024-06-01".to_string(),
+            templates: vec![("index".to_string(), String::new())],
+            pages: vec![
+                page("page", "about", json!({ "title": "About" })),
+                page("page", "About", json!({ "title": " " })),
+                page(
+                    "page",
+                    "contact",
+                    json!({ "title": "Contact", "template": "contact" }),
+                ),
+                page(
+                    "post",
+                    "hello",
+                    json!({ "title": "Hello", "status": "draft", "date": "2024-05-01" }),
+                ),
+                page(
+                    "post",
+                    "later",
+                    json!({ "title": "Later", "status": "draft", "date": "2024-07-01", "summary": "x" }),
+                ),
+            ],
+            collections: vec![CollectionSource {
+                name: "events".to_string(),
+                listing: Some(ListingConfig {
+                    path: "events".to_string(),
+                    template: "index".to_string(),
+                    detail_template: Some("index".to_string()),
+                    sort_by: None,
+                    descending: false,
+                    group_by: None,
+                    group_by_date: None,
+                    upcoming_field: None,
+                }),
+                fields: vec![required("venue")],
+                entries: vec![
+                    json!({ "name": "launch", "venue": "Hall" }),
+                    json!({ "name": "party", "venue": "" }),
+                ],
+            }],
+            ..Default::default()
+        };
+        let mut page_fields = BTreeMap::new();
+        page_fields.insert("post".to_string(), vec![required("summary")]);
+
+        let report = validate_site(&source, &page_fields);
+
+        assert!(report.blocking);
+        assert_eq!(
+            kinds(&report.errors),
+            vec![
+                ("duplicate_url", "About"),
+                ("missing_template", "contact"),
+                ("missing_field", "hello"),
+                ("missing_field", "party"),
+            ]
+        );
+        assert_eq!(
+            kinds(&report.warnings),
+            vec![("empty_title", "About"), ("past_draft", "hello")]
+        );
+        assert!(report.errors[0].message.contains("page about"));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_validate_clean_site() {
+        let source = SiteSource {
+            templates: vec![("index".to_string(), String::new())],
+            pages: vec![page("page", "about", json!({ "title": "About" }))],
+            ..Default::default()
+        };
+        let report = validate_site(&source, &BTreeMap::new());
+        assert!(!report.blocking);
+        assert!(report.errors.is_empty() && report.warnings.is_empty());
+    }
+}
@@ -0,0 +1,273 @@
+//! `[[Page Name]]` links.
+//!
+//! Authors can link to another page or post by writing its name or title in
+//! double brackets, optionally with a label: `[[Page Name|see here]]`. The
+//! brackets are plain text in the document; at render time they become link
+//! marks, and names that match nothing are reported instead.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::site::{page_url, PageSource};
+use super::slugify;
+
+/// A `[[...]]` that didn't match any page
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnresolvedLink {
+    /// URL of the page containing the link
+    pub page: String,
+    pub target: String,
+}
+
+/// Page names and titles, slugified, mapped to URLs
+#[derive(Debug, Clone, Default)]
+pub struct WikiTargets {
+    urls: HashMap<String, String>,
+}
+
+impl WikiTargets {
+    /// Names take priority over titles, and earlier pages over later ones
+    pub fn new(pages: &[PageSource]) -> Self {
+        let mut urls = HashMap::new();
+        for page in pages {
+            urls.entry(slugify(&page.name))
+                .or_insert_with(|| page_url(page));
+        }
+        for page in pages {
+            if let Some(title) = page.meta.get("title").and_then(Value::as_str) {
+                urls.entry(slugify(title)).or_insert_with(|| page_url(page));
+            }
+        }
+        urls.remove("");
+        WikiTargets { urls }
+    }
+
+    pub fn resolve(&self, target: &str) -> Option<&str> {
+        self.urls.get(&slugify(target)).map(String::as_str)
+    }
+}
+
+/// A piece of a text node: plain text, or a `[[target|label]]`
+enum Part<'a> {
+    Text(&'a str),
+    Link {
+        target: &'a str,
+        label: &'a str,
+        /// The link as written, kept when it can't be resolved
+        raw: &'a str,
+    },
+}
+
+fn split_wikilinks(text: &str) -> Vec<Part<'_>> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        let end = match rest[start + 2..].find("]]") {
+            Some(end) => start + 2 + end,
+            None => break,
+        };
+        let inner = &rest[start + 2..end];
+        // `[[` inside the brackets means the first pair wasn't a link
+        if inner.contains("[[") || inner.trim().is_empty() {
+            parts.push(Part::Text(&rest[..start + 2]));
+            rest = &rest[start + 2..];
+            continue;
+        }
+        if start > 0 {
+            parts.push(Part::Text(&rest[..start]));
+        }
+        let (target, label) = match inner.find('|') {
+            Some(bar) => (inner[..bar].trim(), inner[bar + 1..].trim()),
+            None => (inner.trim(), inner.trim()),
+        };
+        parts.push(Part::Link {
+            target,
+            label,
+            raw: &rest[start..end + 2],
+        });
+        rest = &rest[end + 2..];
+    }
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest));
+    }
+    parts
+}
+
+fn has_mark(node: &Value, mark_types: &[&str]) -> bool {
+    node.get("marks")
+        .and_then(Value::as_array)
+        .map_or(false, |marks| {
+            marks.iter().any(|mark| {
+                mark.get("type")
+                    .and_then(Value::as_str)
+                    .map_or(false, |mark_type| mark_types.contains(&mark_type))
+            })
+        })
+}
+
+/// Replace a text node with its pieces, linking the resolved ones
+fn link_text(
+    node: &Value,
+    targets: &WikiTargets,
+    page: &str,
+    unresolved: &mut Vec<UnresolvedLink>,
+) -> Vec<Value> {
+    let text = node.get("text").and_then(Value::as_str).unwrap_or("");
+    if !text.contains("[[") || has_mark(node, &["code", "link"]) {
+        return vec![node.clone()];
+    }
+
+    let mut nodes = Vec::new();
+    for part in split_wikilinks(text) {
+        let mut piece = node.clone();
+        match part {
+            Part::Text(text) => piece["text"] = json!(text),
+            Part::Link { target, label, raw } => match targets.resolve(target) {
+                Some(url) => {
+                    piece["text"] = json!(if label.is_empty() { target } else { label });
+                    let link = json!({ "type": "link", "attrs": { "href": url, "title": null } });
+                    match piece["marks"].as_array_mut() {
+                        Some(marks) => marks.push(link),
+                        None => piece["marks"] = json!([link]),
+                    }
+                }
+                None => {
+                    unresolved.push(UnresolvedLink {
+                        page: page.to_string(),
+                        target: target.to_string(),
+                    });
+                    piece["text"] = json!(raw);
+                }
+            },
+        }
+        nodes.push(piece);
+    }
+    nodes
+}
+
+fn link_children(
+    node: &mut Value,
+    targets: &WikiTargets,
+    page: &str,
+    unresolved: &mut Vec<UnresolvedLink>,
+) {
+    let node_type = node.get("type").and_then(Value::as_str).unwrap_or("");
+    if matches!(node_type, "code_block" | "codeBlock") {
+        return;
+    }
+    let children = match node.get_mut("content").and_then(Value::as_array_mut) {
+        Some(children) => children,
+        None => return,
+    };
+    let mut linked = Vec::with_capacity(children.len());
+    for mut child in children.drain(..) {
+        if child.get("type").and_then(Value::as_str) == Some("text") {
+            linked.extend(link_text(&child, targets, page, unresolved));
+        } else {
+            link_children(&mut child, targets, page, unresolved);
+            linked.push(child);
+        }
+    }
+    *children = linked;
+}
+
+/// Turn the wikilinks in a page's body into link marks
+///
+/// Returns the links that couldn't be resolved; they are left as text.
+pub fn resolve_wikilinks(page: &mut PageSource, targets: &WikiTargets) -> Vec<UnresolvedLink> {
+    let url = page_url(page);
+    let mut unresolved = Vec::new();
+    link_children(&mut page.body, targets, &url, &mut unresolved);
+    unresolved
+}
+
+/// Every page with its wikilinks resolved, plus the ones that couldn't be
+pub fn resolve_all_wikilinks(pages: &[PageSource]) -> (Vec<PageSource>, Vec<UnresolvedLink>) {
+    let targets = WikiTargets::new(pages);
+    let mut linked = pages.to_vec();
+    let mut unresolved = Vec::new();
+    for page in linked.iter_mut() {
+        unresolved.extend(resolve_wikilinks(page, &targets));
+    }
+    (linked, unresolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Map;
+    use wasm_bindgen_test::*;
+
+    fn page(collection: &str, name: &str, title: Option<&str>, body: Value) -> PageSource {
+        let mut meta = Map::new();
+        if let Some(title) = title {
+            meta.insert("title".to_string(), json!(title));
+        }
+        PageSource {
+            collection: collection.to_string(),
+            name: name.to_string(),
+            meta,
+            body,
+        }
+    }
+
+    fn paragraph(inlines: Value) -> Value {
+        json!({ "type": "doc", "content": [{ "type": "paragraph", "content": inlines }] })
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resolve_wikilinks() {
+        let pages = vec![
+            page("page", "about", Some("About Us"), json!({})),
+            page("post", "Zine Fair", None, json!({})),
+        ];
+        let targets = WikiTargets::new(&pages);
+
+        let mut source = page(
+            "page",
+            "notes",
+            None,
+            paragraph(json!([
+                { "type": "text", "text": "See [[About Us]] and [[zine fair|the fair]]. [[Nowhere]]" },
+                { "type": "text", "text": "[[about]]", "marks": [{ "type": "code" }] }
+            ])),
+        );
+        let unresolved = resolve_wikilinks(&mut source, &targets);
+
+        assert_eq!(
+            unresolved,
+            vec![UnresolvedLink {
+                page: "/notes/".to_string(),
+                target: "Nowhere".to_string()
+            }]
+        );
+        let link =
+            |href: &str| json!([{ "type": "link", "attrs": { "href": href, "title": null } }]);
+        assert_eq!(
+            source.body["content"][0]["content"],
+            json!([
+                { "type": "text", "text": "See " },
+                { "type": "text", "text": "About Us", "marks": link("/about/") },
+                { "type": "text", "text": " and " },
+                { "type": "text", "text": "the fair", "marks": link("/posts/zine-fair/") },
+                { "type": "text", "text": ". " },
+                { "type": "text", "text": "[[Nowhere]]" },
+                { "type": "text", "text": "[[about]]", "marks": [{ "type": "code" }] }
+            ])
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_split_wikilinks_ignores_stray_brackets() {
+        let texts: Vec<String> = split_wikilinks("a [[ [[b]] c]] [[")
+            .into_iter()
+            .map(|part| match part {
+                Part::Text(text) => format!("text:{}", text),
+                Part::Link { target, .. } => format!("link:{}", target),
+            })
+            .collect();
+        assert_eq!(texts, vec!["text:a [[", "text: ", "link:b", "text: c]] [["]);
+    }
+}
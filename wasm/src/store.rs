@@ -1,17 +1,59 @@
 use crate::messages::{FileUpdate, Message, Response};
-use crate::model::collection::Collection;
-use crate::model::file::{File, HasTitle, HasUrl};
+use crate::model::file::{File, HasHeadOverrides, HasTitle, HasUrl};
 use crate::model::project::Project;
-use crate::model::{Asset, Page, Partial, Post, Template, Text};
-use crate::types::{FileType, ProjectType};
-use crate::{js_conversions::*, FileStore, ProseMirrorSchema};
+use crate::model::{Asset, Entry, Page, Partial, Post, Template, Text};
+use crate::types::{FieldType, FileType, ListFilesOptions, ProjectType};
+use crate::{js_conversions::*, EventEmitter, FileStore};
 use loro::{LoroDoc, LoroMap};
 use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::{Arc, Mutex};
 use wasm_bindgen::prelude::*;
 
+mod accessibility;
+mod assets;
+mod changes;
+mod collaborators;
+mod collections;
+mod cursors;
+mod delete;
+mod dirty;
+mod documents;
+mod file_list;
+#[cfg(feature = "fixtures")]
+mod fixtures;
+mod flush;
+mod folders;
+mod forms;
+mod freeze;
+mod ids;
+mod imports;
+mod install;
+mod journal;
+mod maintenance;
+mod meta;
+mod plugins;
+mod preview;
+mod publish;
+mod quota;
+mod render;
+mod replace;
+mod report;
+mod roles;
+mod schema;
+mod settings;
+mod share;
+mod skeletons;
+mod snippets;
+mod startup;
+mod stats;
+mod syndication;
+mod tabs;
 mod tests;
+mod theme;
+mod verify;
+mod workspace;
 
 #[wasm_bindgen]
 extern "C" {
@@ -27,6 +69,7 @@ macro_rules! console_log {
 pub const IDB_DB_NAME: &str = "organ_db";
 pub const IDB_PROJECTS_STORE: &str = "projects";
 pub const IDB_FILES_STORE: &str = "files";
+pub const IDB_ASSETS_STORE: &str = "assets";
 
 #[wasm_bindgen]
 pub struct Store {
@@ -44,10 +87,125 @@ impl Store {
                 active_site: Arc::new(Mutex::new(None)),
                 active_theme: Arc::new(Mutex::new(None)),
                 active_file: Arc::new(Mutex::new(None)),
+                events: EventEmitter::new(),
+                preview_watch: Arc::new(Mutex::new(None)),
+                open_sites: Arc::new(Mutex::new(HashMap::new())),
+                plugins: plugins::PluginRegistry::default(),
+                role: Arc::new(Mutex::new(roles::Role::default())),
+                changes: Arc::new(Mutex::new(changes::ChangeFeed::default())),
+                saved_versions: Arc::new(Mutex::new(HashMap::new())),
+                tabs: Arc::new(Mutex::new(tabs::TabState::default())),
             }),
         }
     }
 
+    /// Register a JS function for a render hook
+    ///
+    /// Hooks are "beforeRenderPage" (`{url, context}` → context),
+    /// "afterRenderPage" (`{url, html}` → html) and "onExport"
+    /// (`{files}` → files). Return `undefined` to leave the value unchanged.
+    #[wasm_bindgen]
+    pub fn register_hook(
+        &self,
+        plugin: &str,
+        hook: &str,
+        callback: js_sys::Function,
+    ) -> Result<(), JsValue> {
+        console_log!("Registering {} hook for plugin {}", hook, plugin);
+        self.inner
+            .plugins
+            .register(plugin, hook, callback)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Register a JS function as a template helper, usable as `{{name ...}}`
+    ///
+    /// It is called with `(params, hash)` and returns the helper's value,
+    /// which is escaped like any other `{{...}}` unless used as `{{{...}}}`.
+    #[wasm_bindgen]
+    pub fn register_helper(
+        &self,
+        plugin: &str,
+        name: &str,
+        callback: js_sys::Function,
+    ) -> Result<(), JsValue> {
+        console_log!("Registering helper {} for plugin {}", name, plugin);
+        self.inner
+            .plugins
+            .register_helper(plugin, name, callback)
+            .map_err(|e| JsValue::from_str(&e))
+    }
+
+    /// Remove every hook and helper registered by a plugin
+    #[wasm_bindgen]
+    pub fn unregister_plugin(&self, plugin: &str) {
+        console_log!("Unregistering plugin {}", plugin);
+        self.inner.plugins.unregister(plugin);
+    }
+
+    /// Limit what messages this store accepts: "viewer", "author", "editor" or "admin"
+    ///
+    /// The host sets this from whatever it uses to identify the user; it
+    /// isn't a message, so the UI can't raise its own role.
+    #[wasm_bindgen]
+    pub fn set_role(&self, role: &str) -> Result<(), JsValue> {
+        let role = roles::Role::parse(role).map_err(|e| JsValue::from_str(&e))?;
+        self.inner.set_role(role);
+        Ok(())
+    }
+
+    /// Recover from an unclean shutdown and check what's saved
+    ///
+    /// Call once, before the first `LoadState`. Resolves to a JSON health
+    /// report: projects found, projects and files recovered, records
+    /// quarantined because they couldn't be read, and sites that are gone.
+    #[wasm_bindgen]
+    pub fn initialize(&self) -> js_sys::Promise {
+        let store = self.inner.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let report = store.initialize().await;
+            serde_json::to_string(&report)
+                .map(|json| JsValue::from_str(&json))
+                .map_err(|e| JsValue::from_str(&format!("Failed to serialize report: {}", e)))
+        })
+    }
+
+    /// Write projects with unsaved changes, and the open file's uncommitted
+    /// edits, to localStorage, synchronously, and return how many were written
+    ///
+    /// Meant for a `beforeunload` or `pagehide` handler, which can't wait
+    /// for `SaveState`. What it writes is merged into the stored documents
+    /// by the next `LoadState`.
+    #[wasm_bindgen]
+    pub fn flush_sync_best_effort(&self) -> usize {
+        self.inner.flush_sync_best_effort()
+    }
+
+    /// Elect one open tab to write to storage, and resolve to this tab's
+    /// role: "writer" or "reader"
+    ///
+    /// Call once, before `initialize`. A reader refuses messages that write
+    /// to storage, gets `tabs:external_change` events when the writer has
+    /// written, and a `tabs:role` event if it takes over as the writer;
+    /// reload with `LoadState` on either.
+    #[wasm_bindgen]
+    pub fn coordinate_tabs(&self) -> js_sys::Promise {
+        let store = self.inner.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let role = store
+                .coordinate_tabs()
+                .await
+                .map_err(|e| JsValue::from_str(&e))?;
+            Ok(JsValue::from_str(role.as_str()))
+        })
+    }
+
+    /// Events emitted outside of responses, such as `preview:updated`
+    #[wasm_bindgen]
+    pub fn events(&self) -> EventEmitter {
+        self.inner.events.clone()
+    }
+
     /// Process a message and return a response
     #[wasm_bindgen]
     pub fn process_message(&self, message_json: &str) -> Result<js_sys::Promise, JsValue> {
@@ -98,6 +256,18 @@ pub struct StoreInner {
     active_site: Arc<Mutex<Option<Project>>>,
     active_theme: Arc<Mutex<Option<Project>>>,
     active_file: Arc<Mutex<Option<FileType>>>,
+    events: EventEmitter,
+    preview_watch: Arc<Mutex<Option<preview::PreviewWatch>>>,
+    /// Workspace sites loaded this session other than the active one
+    open_sites: Arc<Mutex<HashMap<String, Project>>>,
+    plugins: plugins::PluginRegistry,
+    role: Arc<Mutex<roles::Role>>,
+    /// Recent committed changes, for `GetChangesSince`
+    changes: Arc<Mutex<changes::ChangeFeed>>,
+    /// Project id → the version last loaded from or saved to IndexedDB
+    saved_versions: Arc<Mutex<HashMap<String, Vec<u8>>>>,
+    /// Whether this tab writes to storage, and its channel to the others
+    tabs: Arc<Mutex<tabs::TabState>>,
 }
 
 #[wasm_bindgen]
@@ -111,15 +281,44 @@ impl StoreInner {
             active_theme: Arc::new(Mutex::new(None)),
             active_site: Arc::new(Mutex::new(None)),
             active_file: Arc::new(Mutex::new(None)),
+            events: EventEmitter::new(),
+            preview_watch: Arc::new(Mutex::new(None)),
+            open_sites: Arc::new(Mutex::new(HashMap::new())),
+            plugins: plugins::PluginRegistry::default(),
+            role: Arc::new(Mutex::new(roles::Role::default())),
+            changes: Arc::new(Mutex::new(changes::ChangeFeed::default())),
+            saved_versions: Arc::new(Mutex::new(HashMap::new())),
+            tabs: Arc::new(Mutex::new(tabs::TabState::default())),
         };
         console_log!("Actor instance created successfully");
         actor
     }
 
     /// Handle a message and return a response
-    async fn handle_message(&self, message: Message) -> Response {
+    async fn handle_message(&self, mut message: Message) -> Response {
         console_log!("Handling message: {:?}", message);
 
+        self.resolve_file_ids(&mut message);
+
+        if let Err(e) = self.authorize(&message).await {
+            console_log!("Rejected message: {}", e);
+            return Response::error(&e);
+        }
+        if let Err(e) = self.check_read_only(&message) {
+            console_log!("Rejected message: {}", e);
+            return Response::error(&e);
+        }
+        if let Err(e) = self.check_tab_writer(&message) {
+            console_log!("Rejected message: {}", e);
+            return Response::error(&e);
+        }
+        if let Err(e) = self.check_alt_text_policy(&message).await {
+            console_log!("Blocked by the alt text policy: {}", e);
+            return Response::error(&e);
+        }
+
+        let change = changes::change_of(&message);
+        let storage_write = tabs::storage_write(&message);
         let response = match message {
             Message::InitDefault => {
                 console_log!("Processing InitDefault message");
@@ -139,20 +338,57 @@ impl StoreInner {
                 self.create_theme(name).await
             }
             Message::GetTheme => self.get_theme(),
-            // Message::AddCollection {
-            //     project_type,
-            //     name,
-            //     fields,
-            // } => self.add_collection(project_type, name, fields),
+            Message::GetThemeManifest => self.get_theme_manifest(),
+            Message::SetThemeManifest { manifest } => self.set_theme_manifest(manifest).await,
+            Message::ListTemplates { collection } => self.list_templates(collection).await,
+            Message::CheckThemeRequirements => self.check_theme_requirements().await,
+            Message::SelectTheme { theme_id } => self.select_theme(theme_id).await,
+            Message::ListSites => self.list_sites().await,
+            Message::SwitchSite { site_id } => self.switch_site(site_id).await,
+            Message::SetProjectReadOnly {
+                project_type,
+                enabled,
+            } => self.set_project_read_only(project_type, enabled),
+            Message::AddCollection {
+                project_type,
+                name,
+                fields,
+            } => self.add_collection(project_type, name, fields),
             Message::GetCollection { project_type, name } => {
                 self.get_collection(project_type, name)
             }
-            Message::ListCollections { project_type } => self.list_collections(project_type),
+            Message::ListCollections { project_type } => {
+                self.list_collections(project_type).await
+            }
+            Message::ExportSchema { project_type } => self.export_schema(project_type),
+            Message::ImportSchema {
+                project_type,
+                schema,
+            } => self.import_schema(project_type, schema),
+            Message::SetCollectionListing {
+                project_type,
+                collection,
+                listing,
+            } => self.set_collection_listing(project_type, collection, listing),
+            Message::SetCollectionSchema {
+                project_type,
+                collection,
+                schema,
+            } => self.set_collection_schema(project_type, collection, schema).await,
             Message::CreateFile {
                 project_type,
                 collection_name,
                 name,
-            } => self.create_file(project_type, collection_name, name).await,
+                skeleton,
+            } => {
+                let response = self
+                    .create_file(project_type, collection_name.clone(), name, skeleton)
+                    .await;
+                if let Response::Success(created) = &response {
+                    self.mark_new_draft(&collection_name, created).await;
+                }
+                response
+            }
             Message::UpdateFile {
                 project_type,
                 collection_name,
@@ -167,12 +403,87 @@ impl StoreInner {
                 collection_name,
                 file_id,
             } => self.get_file(project_type, collection_name, file_id).await,
+            Message::GetMeta {
+                project_type,
+                collection_name,
+                file_id,
+            } => self.get_meta(project_type, collection_name, file_id).await,
             Message::ListFiles {
                 project_type,
                 collection_name,
-            } => self.list_files(project_type, collection_name).await,
+                options,
+            } => self.list_files(project_type, collection_name, options).await,
+            Message::FindReplace {
+                query,
+                replacement,
+                scope,
+                regex,
+                case_sensitive,
+                dry_run,
+            } => {
+                self.find_replace(query, replacement, scope, regex, case_sensitive, dry_run)
+                    .await
+            }
+            Message::InsertSnippet {
+                file_id,
+                snippet_id,
+                position,
+                reference,
+            } => {
+                self.insert_snippet(file_id, snippet_id, position, reference)
+                    .await
+            }
+            Message::CheckDelete {
+                project_type,
+                collection_name,
+                file_id,
+            } => {
+                self.check_delete(project_type, collection_name, file_id)
+                    .await
+            }
+            Message::DeleteFile {
+                project_type,
+                collection_name,
+                file_id,
+                force,
+            } => {
+                self.delete_file(project_type, collection_name, file_id, force)
+                    .await
+            }
+            Message::FindOrphanAssets => self.find_orphan_assets().await,
+            Message::TrashOrphanAssets { asset_ids } => self.trash_orphan_assets(asset_ids).await,
+            Message::CreateAssetFolder {
+                project_type,
+                name,
+                parent_id,
+            } => self.create_asset_folder(project_type, name, parent_id),
+            Message::RenameAssetFolder {
+                project_type,
+                folder_id,
+                name,
+            } => self.rename_asset_folder(project_type, folder_id, name),
+            Message::MoveAssetFolder {
+                project_type,
+                folder_id,
+                parent_id,
+            } => self.move_asset_folder(project_type, folder_id, parent_id),
+            Message::MoveAsset {
+                project_type,
+                asset_id,
+                folder_id,
+            } => self.move_asset(project_type, asset_id, folder_id),
+            Message::TransformAsset {
+                project_type,
+                asset_id,
+                transform,
+            } => self.transform_asset(project_type, asset_id, transform).await,
+            Message::SanitizeSvgAssets { project_type } => {
+                self.sanitize_svg_assets(project_type).await
+            }
             Message::SaveState { project_type } => self.save_state(project_type).await,
             Message::LoadState { site_id, theme_id } => self.load_state(site_id, theme_id).await,
+            Message::CompactProjects { shallow } => self.compact_projects(shallow).await,
+            Message::VerifyProject { repair } => self.verify_project(repair).await,
             Message::ExportProject { project_type } => self.export_project(project_type),
             Message::ImportProject {
                 data,
@@ -187,9 +498,103 @@ impl StoreInner {
                 created,
                 updated,
             ),
+            Message::ImportDocx { data, name } => self.import_docx(data, name).await,
+            Message::ImportCsv {
+                collection,
+                mapping,
+                data,
+            } => self.import_csv(collection, mapping, data).await,
+            Message::ImportSubmission { submission } => self.import_submission(submission).await,
+            Message::ImportFromUrl { url } => self.import_from_url(url).await,
+            Message::ImportAssets { entries } => self.import_assets(entries).await,
+            Message::ImportGhost { data, site_url } => self.import_ghost(data, site_url).await,
+            Message::ImportArchive { data, blog_url } => self.import_archive(data, blog_url).await,
+            Message::ImportStaticSite { files } => self.import_static_site(files).await,
+            #[cfg(feature = "fixtures")]
+            Message::GenerateFixture { options } => self.generate_fixture(options).await,
+            Message::ListSubmissions { review } => self.list_submissions(review).await,
+            Message::InstallFromUrl { url, sha256 } => self.install_from_url(url, sha256).await,
+            Message::GetSiteSettings => self.get_site_settings(),
+            Message::SetSiteSettings { settings } => self.set_site_settings(settings).await,
+            Message::SetUrlPattern {
+                collection,
+                pattern,
+            } => self.set_url_pattern(collection, pattern).await,
+            Message::ListCollaborators { project_type } => self.list_collaborators(project_type),
+            Message::SetCollaborator {
+                project_type,
+                collaborator,
+            } => self.set_collaborator(project_type, collaborator),
+            Message::RemoveCollaborator { project_type, id } => {
+                self.remove_collaborator(project_type, id)
+            }
+            Message::SaveForm { form } => self.save_form(form).await,
+            Message::RenderSite {
+                url_strategy,
+                filter,
+            } => self.render_site(url_strategy, filter).await,
+            Message::BuildSite {
+                url_strategy,
+                filter,
+            } => self.build_site(url_strategy, filter).await,
+            Message::GetPreviewManifest => self.get_preview_manifest().await,
+            Message::ExportSingleFile {
+                page,
+                max_asset_bytes,
+            } => self.export_single_file(page, max_asset_bytes).await,
+            Message::ExportGemtext => self.export_gemtext().await,
+            Message::ValidateExport { filter } => self.validate_export(filter).await,
+            Message::GetPublishDiff { target } => self.get_publish_diff(target).await,
+            Message::RecordDeploy { target } => self.record_deploy(target).await,
+            Message::WatchPreview { file_id } => self.watch_preview(file_id).await,
+            Message::UnwatchPreview => self.unwatch_preview(),
+            Message::ListPlugins => self.list_plugins(),
+            Message::GetContentReport { options } => self.get_content_report(options).await,
+            Message::AuditAltText => self.audit_alt_text().await,
+            Message::GetProjectStats => self.get_project_stats().await,
+            Message::GetMemoryReport => self.get_memory_report(),
+            Message::GetDirtyState => self.get_dirty_state(),
+            Message::GetStorageEstimate => self.get_storage_estimate().await,
+            Message::GetTabRole => self.get_tab_role(),
+            Message::GetCapabilities => self.get_capabilities(),
+            Message::GetChangesSince { cursor } => self.get_changes_since(cursor),
+            Message::GetShareState { project_type } => self.get_share_state(project_type).await,
+            Message::GetShareUpdates {
+                project_type,
+                versions,
+            } => self.get_share_updates(project_type, versions).await,
+            Message::ApplyShareUpdates {
+                project_type,
+                project_id,
+                updates,
+            } => {
+                self.apply_share_updates(project_type, project_id, updates)
+                    .await
+            }
+            Message::GetBacklinks { url } => self.get_backlinks(url).await,
+            Message::GetUnresolvedLinks => self.get_unresolved_links().await,
+            Message::GetContentGraph => self.get_content_graph().await,
+            Message::GetTemplateUsage => self.get_template_usage().await,
+            Message::GetCssReport => self.get_css_report().await,
+            Message::ParseTemplate { file_id } => self.parse_template(file_id).await,
+            Message::GetThemeVariables => self.get_theme_variables().await,
             // Message::RenderFile { file_id, context } => self.render_file(file_id, context),
 
             // Document operations for ProseMirror integration
+            Message::GetPlainText { file_id, options } => {
+                self.get_plain_text(file_id, options).await
+            }
+            Message::GetOutline { file_id } => self.get_outline(file_id).await,
+            Message::CreateAnchor { file_id, position } => {
+                self.create_anchor(file_id, position).await
+            }
+            Message::ResolveAnchor { file_id, anchor } => {
+                self.resolve_anchor(file_id, anchor).await
+            }
+            Message::SaveCursor { file_id, position } => {
+                self.save_cursor(file_id, position).await
+            }
+            Message::RestoreCursor { file_id } => self.restore_cursor(file_id).await,
             // TODO: Rename to GetActiveFile
             Message::GetDocument { document_id } => {
                 console_log!("Processing GetDocument message - id: {}", document_id);
@@ -208,6 +613,13 @@ impl StoreInner {
               // }
         };
 
+        if let Some(change) = change {
+            self.record_change(change, &response);
+        }
+        if let Some(kind) = storage_write {
+            self.announce_storage_write(kind, &response);
+        }
+
         console_log!("Message handling complete with response: {:?}", response);
         response
     }
@@ -275,6 +687,22 @@ impl StoreInner {
         Ok(())
     }
 
+    /// Get a handle on the active project of the given type
+    ///
+    /// Cloned projects share their LoroDoc, so changes made through the
+    /// handle land in the active project.
+    fn active_project(&self, project_type: ProjectType) -> Result<Project, String> {
+        let guard = match project_type {
+            ProjectType::Site => self.active_site.lock(),
+            ProjectType::Theme => self.active_theme.lock(),
+        }
+        .map_err(|_| "Failed to acquire project lock".to_string())?;
+
+        guard
+            .clone()
+            .ok_or_else(|| format!("No active {:?} project", project_type))
+    }
+
     fn export_to_json(&self) -> Result<Value, String> {
         // TODO: Implement export
         Ok(serde_json::json!({}))
@@ -297,7 +725,10 @@ impl StoreInner {
             Ok(mut project) => {
                 project.set_name(&name);
 
-                // Set as active site in the store
+                // Set as active site in the store, keeping the previous one open
+                if let Err(e) = self.stash_active_site() {
+                    console_log!("Failed to keep previous site open: {}", e);
+                }
                 self.set_site(project.clone());
 
                 // Get project ID
@@ -346,7 +777,10 @@ impl StoreInner {
             return Response::success(json!({
                 "id": site.id(),
                 "name": site.name().unwrap_or_else(|_| "Unnamed".to_string()),
-                "themeId": theme_id
+                "themeId": theme_id,
+                "readOnly": site.read_only(),
+                "created": site.created(),
+                "updated": site.updated()
             }));
         }
 
@@ -361,7 +795,10 @@ impl StoreInner {
         if let Some(theme) = self.active_theme.lock().unwrap().clone() {
             return Response::success(json!({
                 "id": theme.id(),
-                "name": theme.name().unwrap_or_else(|_| "Unnamed".to_string())
+                "name": theme.name().unwrap_or_else(|_| "Unnamed".to_string()),
+                "readOnly": theme.read_only(),
+                "created": theme.created(),
+                "updated": theme.updated()
             }));
         }
 
@@ -369,76 +806,6 @@ impl StoreInner {
         Response::error("No active theme found")
     }
 
-    /// ACTOR add a collection to a project
-    // fn add_collection(
-    //     &self,
-    //     project_type: String,
-    //     name: String,
-    //     fields: Vec<FieldDefinition>,
-    // ) -> Response {
-    //     let project_type = match js_conversions::string_to_project_type(&project_type) {
-    //         Ok(pt) => pt,
-    //         Err(e) => return Response::error(&format!("Failed to convert project type: {}", e)),
-    //     };
-    //     console_log!("Adding collection: {} to {:?}", name, project_type);
-    //     // Get the appropriate project based on project_type
-    //     let mut project = match project_type {
-    //         ProjectType::Site => {
-    //             if let Some(site) = self.active_site.read().unwrap().clone() {
-    //                 site.clone()
-    //             } else {
-    //                 return Response::error("Failed to acquire site lock");
-    //             }
-    //         }
-    //         ProjectType::Theme => {
-    //             if let Some(theme) = self.active_theme.read().unwrap().clone() {
-    //                 theme.clone()
-    //             } else {
-    //                 return Response::error("Failed to acquire theme lock");
-    //             }
-    //         }
-    //     };
-    //     // Create model
-    //     let mut model = crate::model::Model::new();
-    //     // Add fields
-    //     for field in &fields {
-    //         let field_type =
-    //             match js_conversions::string_to_field_type(&field.field_type.to_string()) {
-    //                 Ok(ft) => ft,
-    //                 Err(e) => return Response::error(&e),
-    //             };
-    //         model.insert(
-    //             &field.name,
-    //             crate::types::FieldDefinition {
-    //                 name: field.name.clone(),
-    //                 field_type,
-    //                 required: field.required,
-    //             },
-    //         );
-    //     }
-    //     // Add collection
-    //     match project.add_collection(&name, model) {
-    //         Ok(collection) => {
-    //             // Update the project in our store
-    //             match project_type {
-    //                 ProjectType::Site => {
-    //                     self.set_site(project);
-    //                 }
-    //                 ProjectType::Theme => {
-    //                     self.set_theme(project);
-    //                 }
-    //             }
-    //             match js_conversions::collection_to_json(&collection) {
-    //                 Ok(json_value) => Response::success(json_value),
-    //                 Err(e) => {
-    //                     Response::error(&format!("Failed to convert collection to JSON: {}", e))
-    //                 }
-    //             }
-    //         }
-    //         Err(e) => Response::error(&format!("Failed to add collection: {}", e)),
-    //     }
-    // }
-
     /// ACTOR get a collection from a project
     fn get_collection(&self, project_type: String, name: String) -> Response {
         let project_type = match js_conversions::string_to_project_type(&project_type) {
@@ -479,20 +846,22 @@ impl StoreInner {
         }
 
         match name.as_str() {
-            "page" => get_collection_generic::<Page>(&project, &name),
+            "page" | "snippet" | "skeleton" => get_collection_generic::<Page>(&project, &name),
             "post" => get_collection_generic::<Post>(&project, &name),
             "asset" => get_collection_generic::<Asset>(&project, &name),
             "template" => get_collection_generic::<Template>(&project, &name),
             "partial" => get_collection_generic::<Partial>(&project, &name),
             "text" => get_collection_generic::<Text>(&project, &name),
-            _ => Response::error(&format!("Collection not found: {}", name)),
+            _ => get_collection_generic::<Entry>(&project, &name),
         }
     }
 
     /// ACTOR List collections in a project
     ///
-    /// Returns a JSON list of collections in the project
-    fn list_collections(&self, project_type: String) -> Response {
+    /// Returns a JSON list of collections in the project, each with its
+    /// field definitions, listing and `stats` for the sidebar. A collection
+    /// that can't be read is listed by name with an `error` instead.
+    async fn list_collections(&self, project_type: String) -> Response {
         let project_type = match js_conversions::string_to_project_type(&project_type) {
             Ok(pt) => pt,
             Err(e) => return Response::error(&format!("Failed to convert project type: {}", e)),
@@ -500,73 +869,40 @@ impl StoreInner {
 
         console_log!("Listing collections for {:?}", project_type);
 
-        // Get the appropriate project based on project_type
-        let mut guard = match project_type {
-            ProjectType::Site => self
-                .active_site
-                .lock()
-                .expect("Failed to acquire site lock"),
-            ProjectType::Theme => self
-                .active_theme
-                .lock()
-                .expect("Failed to acquire theme lock"),
+        let project = match self.active_project(project_type) {
+            Ok(project) => project,
+            Err(e) => return Response::error(&e),
         };
 
-        if let Some(ref mut project) = *guard {
-            let collections = match project.get_collections() {
-                Ok(collections) => collections,
-                Err(e) => return Response::error(&format!("Failed to get collections: {}", e)),
-            };
-
-            console_log!("Collections: {:#?}", collections);
-
-            let collections: Vec<Value> = collections
-                .iter()
-                .map(|(name, map)| match name.as_str() {
-                    "page" => {
-                        let collection =
-                            Collection::<Page>::builder(name.clone())?.build_detached()?;
-                        js_conversions::collection_to_json(&collection)
-                    }
-                    "post" => {
-                        let collection =
-                            Collection::<Post>::builder(name.clone())?.build_detached()?;
-                        js_conversions::collection_to_json(&collection)
-                    }
-                    "asset" => {
-                        let collection =
-                            Collection::<Asset>::builder(name.clone())?.build_detached()?;
-                        js_conversions::collection_to_json(&collection)
-                    }
-                    "template" => {
-                        let collection =
-                            Collection::<Template>::builder(name.clone())?.build_detached()?;
-                        js_conversions::collection_to_json(&collection)
-                    }
-                    "partial" => {
-                        let collection =
-                            Collection::<Partial>::builder(name.clone())?.build_detached()?;
-                        js_conversions::collection_to_json(&collection)
-                    }
-                    "text" => {
-                        let collection =
-                            Collection::<Text>::builder(name.clone())?.build_detached()?;
-                        js_conversions::collection_to_json(&collection)
-                    }
-                    _ => Err(format!("Collection not found: {}", name)),
-                })
-                .map(|c| c.unwrap())
-                .collect();
+        let names = match project.get_collections() {
+            Ok(collections) => collections,
+            Err(e) => return Response::error(&format!("Failed to get collections: {}", e)),
+        };
 
-            return Response::success(json!(collections));
-        } else {
-            return Response::error("No active project");
+        let mut listed = Vec::new();
+        for (name, _) in names {
+            let summary = match name.as_str() {
+                "page" | "snippet" | "skeleton" => {
+                    collections::collection_summary::<Page>(&project, &name).await
+                }
+                "post" => collections::collection_summary::<Post>(&project, &name).await,
+                "asset" => collections::collection_summary::<Asset>(&project, &name).await,
+                "template" => collections::collection_summary::<Template>(&project, &name).await,
+                "partial" => collections::collection_summary::<Partial>(&project, &name).await,
+                "text" => collections::collection_summary::<Text>(&project, &name).await,
+                _ => collections::collection_summary::<Entry>(&project, &name).await,
+            };
+            // One broken collection shouldn't hide the rest from the sidebar
+            match summary {
+                Ok(summary) => listed.push(summary),
+                Err(e) => {
+                    console_log!("Failed to list collection {}: {}", name, e);
+                    listed.push(json!({ "name": name, "error": e }));
+                }
+            }
         }
 
-        // match js_conversions::collections_to_json(&collections) {
-        //     Ok(json_value) => Response::success(json_value),
-        //     Err(e) => Response::error(&format!("Failed to convert collections to JSON: {}", e)),
-        // }
+        Response::success(json!(listed))
     }
 
     async fn create_file_generic<T: File + Default + Debug>(
@@ -574,7 +910,8 @@ impl StoreInner {
         project_type: ProjectType,
         collection_name: &str,
         name: &str,
-        pm_schema: Option<ProseMirrorSchema>,
+        rich_text: bool,
+        body: Option<&Value>,
         store: crate::FileStore,
     ) -> Response {
         let mut guard = match project_type {
@@ -595,7 +932,17 @@ impl StoreInner {
                 Err(e) => return Response::error(&format!("Failed to create file: {}", e)),
             };
 
-            if let Some(pm_schema) = pm_schema {
+            if rich_text {
+                // New documents take the collection's schema, if it has one
+                let pm_schema = match project.collection_pm_schema(collection_name) {
+                    Ok(pm_schema) => pm_schema,
+                    Err(e) => return Response::error(&format!("Failed to get PM schema: {}", e)),
+                };
+                if let Some(body) = body {
+                    if let Err(e) = pm_schema.check_node(body) {
+                        return Response::error(&format!("Skeleton doesn't fit the schema: {}", e));
+                    }
+                }
                 file_builder = match file_builder.with_pm_schema(pm_schema) {
                     Ok(builder) => builder,
                     Err(e) => return Response::error(&format!("Failed to set PM schema: {}", e)),
@@ -607,8 +954,20 @@ impl StoreInner {
                 Err(_) => return Response::error("Failed to attach file"),
             };
 
+            if let Some(body) = body {
+                if let Err(e) = skeletons::copy_body(&file, body).await {
+                    return Response::error(&format!("Failed to copy skeleton: {}", e));
+                }
+            }
+
             match js_conversions::file_to_json::<T>(&file) {
-                Ok(json_value) => Response::success(json_value),
+                Ok(mut json_value) => {
+                    let added = ids::add_short_ids(project, std::slice::from_mut(&mut json_value));
+                    if let Err(e) = added {
+                        console_log!("Failed to add short id: {}", e);
+                    }
+                    Response::success(json_value)
+                }
                 Err(e) => Response::error(&format!("Failed to convert file to JSON: {}", e)),
             }
         } else {
@@ -622,6 +981,7 @@ impl StoreInner {
         project_type: String,
         collection_name: String,
         name: String,
+        skeleton: Option<String>,
     ) -> Response {
         let project_type = match js_conversions::string_to_project_type(&project_type) {
             Ok(pt) => pt,
@@ -635,13 +995,29 @@ impl StoreInner {
             project_type
         );
 
+        let body = match skeleton {
+            Some(skeleton_id) => match self.skeleton_body(&skeleton_id).await {
+                Ok(body) => Some(body),
+                Err(e) => return Response::error(&format!("Failed to create file: {}", e)),
+            },
+            None => None,
+        };
+        let rich_text = matches!(
+            collection_name.as_str(),
+            "page" | "post" | "snippet" | "skeleton"
+        );
+        if body.is_some() && !rich_text {
+            return Response::error("Only pages, posts and snippets can start from a skeleton");
+        }
+
         match collection_name.as_str() {
             "page" => {
                 self.create_file_generic::<Page>(
                     project_type,
                     &collection_name,
                     &name,
-                    Some(ProseMirrorSchema::default()),
+                    true,
+                    body.as_ref(),
                     crate::FileStore::Full(LoroDoc::new()),
                 )
                 .await
@@ -651,7 +1027,36 @@ impl StoreInner {
                     project_type,
                     &collection_name,
                     &name,
-                    Some(ProseMirrorSchema::default()),
+                    true,
+                    body.as_ref(),
+                    crate::FileStore::Full(LoroDoc::new()),
+                )
+                .await
+            }
+            "snippet" => {
+                if let Err(e) = self.ensure_snippets(&project_type) {
+                    return Response::error(&format!("Failed to create snippet: {}", e));
+                }
+                self.create_file_generic::<Page>(
+                    project_type,
+                    &collection_name,
+                    &name,
+                    true,
+                    body.as_ref(),
+                    crate::FileStore::Full(LoroDoc::new()),
+                )
+                .await
+            }
+            "skeleton" => {
+                if let Err(e) = self.ensure_skeletons(&project_type) {
+                    return Response::error(&format!("Failed to create skeleton: {}", e));
+                }
+                self.create_file_generic::<Page>(
+                    project_type,
+                    &collection_name,
+                    &name,
+                    true,
+                    body.as_ref(),
                     crate::FileStore::Full(LoroDoc::new()),
                 )
                 .await
@@ -661,6 +1066,7 @@ impl StoreInner {
                     project_type,
                     &collection_name,
                     &name,
+                    false,
                     None,
                     crate::FileStore::Cache(LoroMap::new()),
                 )
@@ -671,6 +1077,7 @@ impl StoreInner {
                     project_type,
                     &collection_name,
                     &name,
+                    false,
                     None,
                     crate::FileStore::Full(LoroDoc::new()),
                 )
@@ -681,6 +1088,7 @@ impl StoreInner {
                     project_type,
                     &collection_name,
                     &name,
+                    false,
                     None,
                     crate::FileStore::Full(LoroDoc::new()),
                 )
@@ -691,12 +1099,23 @@ impl StoreInner {
                     project_type,
                     &collection_name,
                     &name,
+                    false,
                     None,
                     crate::FileStore::Full(LoroDoc::new()),
                 )
                 .await
             }
-            _ => Response::error(&format!("Collection not found: {}", collection_name)),
+            _ => {
+                self.create_file_generic::<Entry>(
+                    project_type,
+                    &collection_name,
+                    &name,
+                    false,
+                    None,
+                    crate::FileStore::Cache(LoroMap::new()),
+                )
+                .await
+            }
         }
     }
 
@@ -727,6 +1146,13 @@ impl StoreInner {
             update
         );
 
+        // Custom metadata can go on a file in any collection
+        if let FileUpdate::SetMeta(updates) = &update {
+            return self
+                .set_meta(project_type, &collection_name, &file_id, updates)
+                .await;
+        }
+
         // Get the appropriate project based on project_type
         // Get a reference to the project instead of cloning
         let guard = match project_type {
@@ -741,8 +1167,8 @@ impl StoreInner {
 
         // only supports page and post for now?
         match collection_name.as_str() {
-            "page" => {
-                let collection = project.get_collection::<Page>("page");
+            "page" | "snippet" | "skeleton" => {
+                let collection = project.get_collection::<Page>(&collection_name);
                 let collection = match collection {
                     Ok(collection) => collection,
                     Err(e) => return Response::error(&format!("Failed to get collection: {}", e)),
@@ -774,6 +1200,16 @@ impl StoreInner {
                             return Response::error(&format!("Failed to set field: {}", e));
                         }
                     }
+                    FileUpdate::SetHeadHtml(html) => {
+                        if let Err(e) = file.set_head_html(&html).await {
+                            return Response::error(&format!("Failed to set head HTML: {}", e));
+                        }
+                    }
+                    FileUpdate::SetPageCss(css) => {
+                        if let Err(e) = file.set_page_css(&css).await {
+                            return Response::error(&format!("Failed to set page CSS: {}", e));
+                        }
+                    }
                     _ => return Response::error(&format!("Unsupported update: {:?}", update)),
                 }
             }
@@ -810,6 +1246,16 @@ impl StoreInner {
                             return Response::error(&format!("Failed to set field: {}", e));
                         }
                     }
+                    FileUpdate::SetHeadHtml(html) => {
+                        if let Err(e) = file.set_head_html(&html).await {
+                            return Response::error(&format!("Failed to set head HTML: {}", e));
+                        }
+                    }
+                    FileUpdate::SetPageCss(css) => {
+                        if let Err(e) = file.set_page_css(&css).await {
+                            return Response::error(&format!("Failed to set page CSS: {}", e));
+                        }
+                    }
                     _ => return Response::error(&format!("Unsupported update: {:?}", update)),
                 }
             }
@@ -840,7 +1286,12 @@ impl StoreInner {
         };
 
         match js_conversions::file_to_json(&file) {
-            Ok(json_value) => Response::success(json_value),
+            Ok(mut json_value) => {
+                if let Err(e) = ids::add_short_ids(project, std::slice::from_mut(&mut json_value)) {
+                    console_log!("Failed to add short id: {}", e);
+                }
+                Response::success(json_value)
+            }
             Err(e) => Response::error(&format!("Failed to convert file to JSON: {}", e)),
         }
     }
@@ -886,7 +1337,7 @@ impl StoreInner {
         };
 
         match collection_name.as_str() {
-            "page" => {
+            "page" | "snippet" | "skeleton" => {
                 self.get_file_generic::<Page>(&project, &collection_name, &file_id)
                     .await
             }
@@ -910,7 +1361,10 @@ impl StoreInner {
                 self.get_file_generic::<Text>(&project, &collection_name, &file_id)
                     .await
             }
-            _ => Response::error(&format!("Collection not found: {}", collection_name)),
+            _ => {
+                self.get_file_generic::<Entry>(&project, &collection_name, &file_id)
+                    .await
+            }
         }
     }
 
@@ -919,6 +1373,7 @@ impl StoreInner {
         &self,
         project: &Project,
         collection_name: &str,
+        options: &ListFilesOptions,
     ) -> Response {
         let collection = match project.get_collection::<T>(collection_name) {
             Ok(collection) => collection,
@@ -930,14 +1385,80 @@ impl StoreInner {
             Err(e) => return Response::error(&format!("Failed to get files: {}", e)),
         };
 
-        match js_conversions::files_to_json(&files) {
-            Ok(json_value) => Response::success(json_value),
-            Err(e) => Response::error(&format!("Failed to convert files to JSON: {}", e)),
+        // Filters and sorting look at all of each file's metadata
+        let listed = files
+            .into_iter()
+            .map(|file| {
+                let meta = file.meta_fields();
+                (file, meta)
+            })
+            .collect();
+        let files = file_list::filter_and_sort(listed, options);
+
+        if options.tree && (options.limit.is_some() || options.cursor.is_some()) {
+            return Response::error("A tree of files can't be paged");
+        }
+        let (page, next_cursor) = match js_conversions::page_files(files, options) {
+            Ok(page) => page,
+            Err(e) => return Response::error(&e),
+        };
+        let mut listed = match js_conversions::files_to_json(&page) {
+            Ok(Value::Array(listed)) => listed,
+            Ok(_) => Vec::new(),
+            Err(e) => return Response::error(&format!("Failed to convert files to JSON: {}", e)),
+        };
+
+        if let Err(e) = ids::add_short_ids(project, &mut listed) {
+            console_log!("Failed to add short ids: {}", e);
+        }
+        let file_folders = collection.file_folders().unwrap_or_default();
+        for file in listed.iter_mut() {
+            let folder = file["id"].as_str().and_then(|id| file_folders.get(id));
+            if let (Value::Object(fields), Some(folder)) = (file, folder) {
+                fields.insert("folder".to_string(), json!(folder));
+            }
+        }
+
+        // Long-form fields are the bulk of most entries
+        let body_fields: Vec<String> = collection
+            .get_fields()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|field| matches!(field.field_type, FieldType::RichText | FieldType::Text))
+            .map(|field| field.name)
+            .collect();
+        let listed: Vec<Value> = listed
+            .into_iter()
+            .map(|file| js_conversions::select_file_fields(file, options, &body_fields))
+            .collect();
+
+        if options.tree {
+            let folders = match collection.folders() {
+                Ok(folders) => folders,
+                Err(e) => return Response::error(&format!("Failed to get folders: {}", e)),
+            };
+            return Response::success(js_conversions::nest_files_json(
+                listed,
+                &folders,
+                &file_folders,
+            ));
+        }
+        match options.limit {
+            Some(_) => Response::success(json!({ "files": listed, "next_cursor": next_cursor })),
+            None => Response::success(json!(listed)),
         }
     }
 
     /// ACTOR List files in a collection
-    async fn list_files(&self, project_type: String, collection_name: String) -> Response {
+    ///
+    /// With default options this is every file's JSON; see `ListFilesOptions`
+    /// for trimming fields and paging through large collections.
+    async fn list_files(
+        &self,
+        project_type: String,
+        collection_name: String,
+        options: ListFilesOptions,
+    ) -> Response {
         let project_type = match js_conversions::string_to_project_type(&project_type) {
             Ok(pt) => pt,
             Err(e) => return Response::error(&format!("Failed to convert project type: {}", e)),
@@ -961,31 +1482,34 @@ impl StoreInner {
         };
 
         match collection_name.as_str() {
-            "page" => {
-                self.list_files_generic::<Page>(project, &collection_name)
+            "page" | "snippet" | "skeleton" => {
+                self.list_files_generic::<Page>(project, &collection_name, &options)
                     .await
             }
             "post" => {
-                self.list_files_generic::<Post>(project, &collection_name)
+                self.list_files_generic::<Post>(project, &collection_name, &options)
                     .await
             }
             "asset" => {
-                self.list_files_generic::<Asset>(project, &collection_name)
+                self.list_files_generic::<Asset>(project, &collection_name, &options)
                     .await
             }
             "template" => {
-                self.list_files_generic::<Template>(project, &collection_name)
+                self.list_files_generic::<Template>(project, &collection_name, &options)
                     .await
             }
             "partial" => {
-                self.list_files_generic::<Partial>(project, &collection_name)
+                self.list_files_generic::<Partial>(project, &collection_name, &options)
                     .await
             }
             "text" => {
-                self.list_files_generic::<Text>(project, &collection_name)
+                self.list_files_generic::<Text>(project, &collection_name, &options)
+                    .await
+            }
+            _ => {
+                self.list_files_generic::<Entry>(project, &collection_name, &options)
                     .await
             }
-            _ => Response::error(&format!("Collection not found: {}", collection_name)),
         }
     }
 
@@ -1021,8 +1545,19 @@ impl StoreInner {
 
         let project_id = project.id();
 
+        // Near the quota, the edit history is dropped to make room
+        let snapshot_only = match self.storage_estimate().await {
+            Some(estimate) => estimate.is_near_quota(),
+            None => false,
+        };
+        let exported = if snapshot_only {
+            project.export_snapshot()
+        } else {
+            project.export()
+        };
+
         // Export the site and theme to JSON for storage
-        let project_export = match project.export() {
+        let project_export = match exported {
             Ok(json) => {
                 console_log!("Project exported for storage, size: {} chars", json.len());
                 json
@@ -1043,6 +1578,7 @@ impl StoreInner {
         match crate::save_data(IDB_PROJECTS_STORE, &project_id, project_export).await {
             Ok(_) => {
                 console_log!("Site data saved, size: {} bytes", length);
+                self.mark_saved(&project);
             }
             Err(e) => {
                 console_log!("Failed to save site data: {:#?}", e);
@@ -1050,9 +1586,23 @@ impl StoreInner {
             }
         };
 
+        if let ProjectType::Site = project_type {
+            if let Err(e) = self.register_site(&project).await {
+                console_log!("Failed to add site to workspace: {}", e);
+            }
+        }
+
+        // Scheduled compaction piggybacks on saves, so it needs no timer
+        let compaction = match project_type {
+            ProjectType::Site => self.compact_if_due().await,
+            ProjectType::Theme => None,
+        };
+
         Response::success(json!({
             "status": "saved",
             "project_type": project_type,
+            "compaction": compaction,
+            "snapshot_only": snapshot_only,
         }))
     }
 
@@ -1071,6 +1621,22 @@ impl StoreInner {
             theme_id
         );
 
+        // Finish what a closed tab left undone, unless another tab writes
+        if self.tab_role() == tabs::TabRole::Writer {
+            // Any multi-key save left half done
+            match journal::recover().await {
+                Ok(Some(journal)) => console_log!("Recovered interrupted {}", journal.operation),
+                Ok(None) => {}
+                Err(e) => console_log!("Failed to recover journal: {}", e),
+            }
+            // Then merge in what it flushed as it closed
+            match flush::replay_pending_flush().await {
+                Ok(0) => {}
+                Ok(replayed) => console_log!("Replayed flushed changes to {} documents", replayed),
+                Err(e) => console_log!("Failed to replay flushed changes: {}", e),
+            }
+        }
+
         // Check if we have projects in memory first
         let has_site_in_memory = self.active_site.lock().unwrap().is_some();
         let has_theme_in_memory = self.active_theme.lock().unwrap().is_some();
@@ -1148,8 +1714,8 @@ impl StoreInner {
                 site_bytes,
                 site_id.clone(),
                 ProjectType::Site,
-                0.0, // We could retrieve created time from metadata if needed
-                0.0, // We could retrieve updated time from metadata if needed
+                0.0, // Read from the document's meta when it has them
+                0.0,
             ) {
                 Ok(project) => project,
                 Err(e) => {
@@ -1164,8 +1730,8 @@ impl StoreInner {
                 theme_bytes,
                 theme_id.clone(),
                 ProjectType::Theme,
-                0.0, // We could retrieve created time from metadata if needed
-                0.0, // We could retrieve updated time from metadata if needed
+                0.0, // Read from the document's meta when it has them
+                0.0,
             ) {
                 Ok(project) => project,
                 Err(e) => {
@@ -1176,6 +1742,8 @@ impl StoreInner {
 
             // Set the loaded projects in the store
             console_log!("Setting loaded projects in store");
+            store_clone.mark_saved(&site);
+            store_clone.mark_saved(&theme);
             if let Err(e) = store_clone.set_site(site) {
                 console_log!("Failed to set site: {}", e);
                 return Response::Error(format!("Failed to set site: {}", e));
@@ -1348,6 +1916,7 @@ impl StoreInner {
             FileType::Text(text) => self.get_active_file_json_generic::<Text>(text),
             FileType::Partial(partial) => self.get_active_file_json_generic::<Partial>(partial),
             FileType::Post(post) => self.get_active_file_json_generic::<Post>(post),
+            FileType::Entry(entry) => self.get_active_file_json_generic::<Entry>(entry),
         };
 
         match file {
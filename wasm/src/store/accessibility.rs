@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::delete::is_trashed;
+use super::render::{load_full_file, load_pages};
+use super::roles::{status_update, DRAFT};
+use super::StoreInner;
+use crate::messages::{Message, Response};
+use crate::model::file::{loro_doc_to_pm_doc, File, HasAlt, HasMimeType, HasUrl};
+use crate::model::{Asset, Page, Post};
+use crate::render::html::images_missing_alt;
+use crate::render::site::PageSource;
+use crate::types::ProjectType;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// An image asset as the alt text audit sees it
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub(super) struct AssetAlt {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub alt: String,
+}
+
+/// Image assets without alt text, and images in pages and posts without it
+///
+/// An image whose asset has alt text gets it as a suggestion.
+pub(super) fn alt_text_audit(assets: &[AssetAlt], pages: &[PageSource]) -> Value {
+    let alt_by_url: HashMap<&str, &str> = assets
+        .iter()
+        .filter(|asset| !asset.alt.trim().is_empty())
+        .map(|asset| (asset.url.as_str(), asset.alt.as_str()))
+        .collect();
+
+    let missing_assets: Vec<&AssetAlt> = assets
+        .iter()
+        .filter(|asset| asset.alt.trim().is_empty())
+        .collect();
+    let mut images = Vec::new();
+    for page in pages {
+        for src in images_missing_alt(&page.body) {
+            images.push(json!({
+                "collection": page.collection,
+                "id": page.meta.get("id").cloned().unwrap_or(Value::Null),
+                "name": page.name,
+                "src": src,
+                "suggestion": alt_by_url.get(src.as_str()),
+            }));
+        }
+    }
+
+    json!({
+        "ok": missing_assets.is_empty() && images.is_empty(),
+        "assets": missing_assets,
+        "images": images,
+    })
+}
+
+/// ProseMirror JSON of a stored page or post
+async fn file_body<T: File + Default>(collection_name: &str, id: &str) -> Result<Value, String> {
+    let file = load_full_file::<T>(collection_name, id).await?;
+    match file.store().as_full() {
+        Some(doc) => loro_doc_to_pm_doc(doc),
+        None => Err(format!("No stored document for {} {}", collection_name, id)),
+    }
+}
+
+impl StoreInner {
+    /// ACTOR List image assets, and images in pages and posts, without alt text
+    pub(super) async fn audit_alt_text(&self) -> Response {
+        console_log!("Auditing alt text");
+
+        let result = async {
+            let site = self.active_project(ProjectType::Site)?;
+            let mut assets = Vec::new();
+            for asset in site
+                .get_collection::<Asset>("asset")?
+                .get_files("asset")
+                .await?
+            {
+                let is_image = asset
+                    .get_mime_type()
+                    .map_or(false, |mime_type| mime_type.starts_with("image/"));
+                if !is_image || is_trashed(&asset) {
+                    continue;
+                }
+                assets.push(AssetAlt {
+                    id: asset.id()?,
+                    name: asset.name()?,
+                    url: asset.get_url().unwrap_or_default(),
+                    alt: asset.get_alt().unwrap_or_default(),
+                });
+            }
+
+            let mut warnings = Vec::new();
+            let mut pages = load_pages::<Page>(&site, "page", &mut warnings).await?;
+            pages.extend(load_pages::<Post>(&site, "post", &mut warnings).await?);
+
+            let mut audit = alt_text_audit(&assets, &pages);
+            audit["required"] = json!(site.settings()?.require_alt_text);
+            audit["warnings"] = json!(warnings);
+            Ok::<_, String>(audit)
+        }
+        .await;
+
+        match result {
+            Ok(audit) => Response::success(audit),
+            Err(e) => Response::error(&format!("Failed to audit alt text: {}", e)),
+        }
+    }
+
+    /// Refuse to publish a page or post with images missing alt text, when
+    /// the site requires it
+    ///
+    /// Publishing is setting a site page or post's status to anything but
+    /// draft.
+    pub(super) async fn check_alt_text_policy(&self, message: &Message) -> Result<(), String> {
+        let (collection_name, file_id) = match message {
+            Message::UpdateFile {
+                project_type,
+                collection_name,
+                file_id,
+                updates,
+            } if project_type.eq_ignore_ascii_case("site")
+                && matches!(collection_name.as_str(), "page" | "post") =>
+            {
+                match status_update(updates) {
+                    Some(status) if !status.eq_ignore_ascii_case(DRAFT) => {
+                        (collection_name, file_id)
+                    }
+                    _ => return Ok(()),
+                }
+            }
+            _ => return Ok(()),
+        };
+        let site = self.active_project(ProjectType::Site)?;
+        if !site.settings()?.require_alt_text {
+            return Ok(());
+        }
+
+        let body = match collection_name.as_str() {
+            "post" => file_body::<Post>("post", file_id).await?,
+            _ => file_body::<Page>("page", file_id).await?,
+        };
+        let missing = images_missing_alt(&body);
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "This site requires alt text before publishing; add it to {}",
+            missing.join(", ")
+        ))
+    }
+}
@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+
+use image::GenericImageView;
+use serde::Serialize;
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::delete::is_trashed;
+use super::install::save_bytes;
+use super::maintenance::load_bytes;
+use super::{StoreInner, IDB_ASSETS_STORE};
+use crate::import::bundle::sha256_hex;
+use crate::import::images::{apply_transform, decode, encode_as, is_decodable, thumbnail};
+use crate::import::svg::{sanitize_bytes, SVG_MIME_TYPE};
+use crate::import::{mime_type_for, path_folders, ImportedAsset};
+use crate::js_conversions::js_conversions;
+use crate::messages::Response;
+use crate::model::file::{
+    thumbnail_key, File, HasMimeType, HEIGHT_KEY, SANITIZED_KEY, SHA256_KEY, SIZE_KEY,
+    THUMBNAIL_KEY, UNSAFE_KEY, WIDTH_KEY,
+};
+use crate::model::project::Project;
+use crate::model::{Asset, Collection, Folder};
+use crate::types::{AssetEntry, AssetTransform, ProjectType};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// What happened to one file of an `ImportAssets` batch
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub(super) struct AssetImport {
+    pub path: String,
+    /// "created", "duplicate" or "failed"
+    pub status: &'static str,
+    /// The new asset, or the existing one it duplicates
+    pub id: Option<String>,
+    pub folder: Option<String>,
+    /// Bytes stored for it, thumbnail included
+    pub bytes: usize,
+    pub error: Option<String>,
+    /// Stored, but not usable as it is, e.g. an SVG that couldn't be made safe
+    pub warning: Option<String>,
+}
+
+/// The id of the folder at `names`, creating any folders that are missing
+fn ensure_folder(
+    assets: &Collection<Asset>,
+    folders: &mut Vec<Folder>,
+    names: &[String],
+) -> Result<Option<String>, String> {
+    let mut parent: Option<String> = None;
+    for name in names {
+        let existing = folders
+            .iter()
+            .find(|folder| folder.parent == parent && folder.name == *name)
+            .map(|folder| folder.id.clone());
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                let folder = assets.create_folder(name, parent.as_deref())?;
+                let id = folder.id.clone();
+                folders.push(folder);
+                id
+            }
+        };
+        parent = Some(id);
+    }
+    Ok(parent)
+}
+
+/// Hash → id of each asset still in use, hashing (and recording the hash
+/// of) assets stored before hashes were kept
+async fn asset_hashes(assets: &[Asset]) -> Result<HashMap<String, String>, String> {
+    let mut hashes = HashMap::new();
+    for asset in assets {
+        if is_trashed(asset) {
+            continue;
+        }
+        let id = asset.id()?;
+        let hash = match asset.get_field(SHA256_KEY) {
+            Ok(serde_json::Value::String(hash)) if !hash.is_empty() => hash,
+            _ => match load_bytes(IDB_ASSETS_STORE, &id).await? {
+                Some(bytes) => {
+                    let hash = sha256_hex(&bytes);
+                    asset.set_field(SHA256_KEY, &hash).await?;
+                    hash
+                }
+                None => continue,
+            },
+        };
+        hashes.entry(hash).or_insert(id);
+    }
+    Ok(hashes)
+}
+
+impl StoreInner {
+    /// ACTOR Add many files to the site's assets at once, as dropped from a folder
+    ///
+    /// Folders are created to match each file's path. Files whose bytes
+    /// are already stored aren't stored again, and images get a thumbnail.
+    /// One file failing doesn't stop the others.
+    pub(super) async fn import_assets(&self, entries: Vec<AssetEntry>) -> Response {
+        console_log!("Importing {} assets", entries.len());
+
+        let result = async {
+            let mut site = self.active_project(ProjectType::Site)?;
+            let assets = site.get_collection::<Asset>("asset")?;
+            let mut hashes = asset_hashes(&assets.get_files("asset").await?).await?;
+            let mut folders = assets.folders()?;
+
+            let mut results = Vec::new();
+            for entry in entries {
+                let hash = sha256_hex(&entry.data);
+                let path = if entry.path.is_empty() {
+                    entry.name.clone()
+                } else {
+                    entry.path.clone()
+                };
+                if let Some(id) = hashes.get(&hash) {
+                    results.push(AssetImport {
+                        path,
+                        status: "duplicate",
+                        id: Some(id.clone()),
+                        ..Default::default()
+                    });
+                    continue;
+                }
+
+                match self
+                    .import_asset(&mut site, &assets, &mut folders, &entry, &hash)
+                    .await
+                {
+                    Ok(import) => {
+                        if let Some(id) = &import.id {
+                            hashes.insert(hash, id.clone());
+                        }
+                        results.push(AssetImport { path, ..import });
+                    }
+                    Err(e) => results.push(AssetImport {
+                        path,
+                        status: "failed",
+                        error: Some(e),
+                        ..Default::default()
+                    }),
+                }
+            }
+            Ok::<_, String>(results)
+        }
+        .await;
+
+        match result {
+            Ok(results) => {
+                let count = |status: &str| results.iter().filter(|r| r.status == status).count();
+                Response::success(json!({
+                    "created": count("created"),
+                    "duplicates": count("duplicate"),
+                    "failed": count("failed"),
+                    "bytes_stored": results.iter().map(|r| r.bytes).sum::<usize>(),
+                    "files": results,
+                }))
+            }
+            Err(e) => Response::error(&format!("Failed to import assets: {}", e)),
+        }
+    }
+
+    /// Store one new asset in its folder, with its hash, size and thumbnail
+    async fn import_asset(
+        &self,
+        site: &mut Project,
+        assets: &Collection<Asset>,
+        folders: &mut Vec<Folder>,
+        entry: &AssetEntry,
+        hash: &str,
+    ) -> Result<AssetImport, String> {
+        let name = match entry.name.trim() {
+            "" => entry
+                .path
+                .rsplit(|c: char| c == '/' || c == '\\')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_string(),
+            name => name.to_string(),
+        };
+        if name.is_empty() {
+            return Err("A file needs a name".to_string());
+        }
+        if entry.data.is_empty() {
+            return Err(format!("{} is empty", name));
+        }
+
+        let folder = ensure_folder(assets, folders, &path_folders(&entry.path))?;
+        let mime_type = mime_type_for(&name).to_string();
+        let asset = self
+            .store_asset(
+                site,
+                &ImportedAsset {
+                    key: entry.path.clone(),
+                    name,
+                    mime_type: mime_type.clone(),
+                    data: entry.data.clone(),
+                },
+            )
+            .await?;
+        let id = asset.id()?;
+        let warning = match asset.get_field(UNSAFE_KEY) {
+            Ok(serde_json::Value::String(problem)) => Some(format!(
+                "It won't be exported, as it may run scripts: {}",
+                problem
+            )),
+            _ => None,
+        };
+        if folder.is_some() {
+            assets.move_to_folder(&id, folder.as_deref())?;
+        }
+        asset.set_field(SHA256_KEY, hash).await?;
+        asset
+            .set_field_value(SIZE_KEY, &json!(entry.data.len()))
+            .await?;
+
+        let mut bytes = entry.data.len();
+        if is_decodable(&mime_type) {
+            // The asset is kept even if it can't be decoded, just without a thumbnail
+            match decode(&entry.data).and_then(|image| Ok((image.dimensions(), thumbnail(&image)?)))
+            {
+                Ok(((width, height), small)) => {
+                    asset.set_field_value(WIDTH_KEY, &json!(width)).await?;
+                    asset.set_field_value(HEIGHT_KEY, &json!(height)).await?;
+                    let key = thumbnail_key(&id);
+                    save_bytes(IDB_ASSETS_STORE, &key, &small.data).await?;
+                    asset.set_field(THUMBNAIL_KEY, &key).await?;
+                    bytes += small.data.len();
+                }
+                Err(e) => console_log!("No thumbnail for {}: {}", id, e),
+            }
+        }
+
+        Ok(AssetImport {
+            status: "created",
+            id: Some(id),
+            folder,
+            bytes,
+            warning,
+            ..Default::default()
+        })
+    }
+
+    /// ACTOR Crop, turn or shrink an image asset
+    ///
+    /// The edits are kept beside the asset and made to the copy that's
+    /// exported, so its stored bytes are always the original. A transform
+    /// that changes nothing undoes the edits. The thumbnail shows the result.
+    pub(super) async fn transform_asset(
+        &self,
+        project_type: String,
+        asset_id: String,
+        transform: AssetTransform,
+    ) -> Response {
+        console_log!("Transforming asset {}: {:?}", asset_id, transform);
+
+        let result = async {
+            let asset = self
+                .asset_collection(&project_type)?
+                .get_file(&asset_id, "asset")
+                .await?;
+            let mime_type = asset
+                .get_mime_type()
+                .ok()
+                .filter(|mime_type| !mime_type.is_empty())
+                .unwrap_or_else(|| mime_type_for(&asset.name().unwrap_or_default()).to_string());
+            if !is_decodable(&mime_type) {
+                return Err(format!("{} images can't be edited", mime_type));
+            }
+            let original = load_bytes(IDB_ASSETS_STORE, &asset_id)
+                .await?
+                .ok_or_else(|| format!("No stored data for asset {}", asset_id))?;
+
+            // Check the edits work before keeping them
+            let edited = apply_transform(&decode(&original)?, &transform)?;
+            if !transform.is_identity() {
+                encode_as(&edited, &mime_type)?;
+            }
+            asset.set_transform(&transform).await?;
+
+            let small = thumbnail(&edited)?;
+            let key = thumbnail_key(&asset_id);
+            save_bytes(IDB_ASSETS_STORE, &key, &small.data).await?;
+            asset.set_field(THUMBNAIL_KEY, &key).await?;
+            Ok::<_, String>(edited.dimensions())
+        }
+        .await;
+
+        match result {
+            Ok((width, height)) => Response::success(json!({
+                "id": asset_id,
+                "transform": if transform.is_identity() { None } else { Some(transform) },
+                "width": width,
+                "height": height,
+            })),
+            Err(e) => Response::error(&format!("Failed to transform asset: {}", e)),
+        }
+    }
+
+    /// ACTOR Take scripts out of SVG assets stored before uploads were
+    /// sanitized, flagging any that can't be made safe
+    pub(super) async fn sanitize_svg_assets(&self, project_type: String) -> Response {
+        console_log!("Sanitizing {} SVG assets", project_type);
+
+        let result = async {
+            let assets = self.asset_collection(&project_type)?;
+            let optimize = match js_conversions::string_to_project_type(&project_type)? {
+                ProjectType::Site => {
+                    self.active_project(ProjectType::Site)?
+                        .settings()?
+                        .optimize_svg
+                }
+                ProjectType::Theme => false,
+            };
+
+            let mut sanitized = Vec::new();
+            let mut problems = Vec::new();
+            for asset in assets.get_files("asset").await? {
+                let checked =
+                    asset.get_field(SANITIZED_KEY).is_ok() || asset.get_field(UNSAFE_KEY).is_ok();
+                if checked || asset.get_mime_type().ok().as_deref() != Some(SVG_MIME_TYPE) {
+                    continue;
+                }
+                let id = asset.id()?;
+                let bytes = match load_bytes(IDB_ASSETS_STORE, &id).await? {
+                    Some(bytes) => bytes,
+                    None => continue,
+                };
+                match sanitize_bytes(&bytes, optimize) {
+                    Ok(clean) => {
+                        save_bytes(IDB_ASSETS_STORE, &id, clean.svg.as_bytes()).await?;
+                        asset.set_field_value(SANITIZED_KEY, &json!(true)).await?;
+                        sanitized.push(json!({ "id": id, "removed": clean.removed }));
+                    }
+                    Err(e) => {
+                        asset.set_field(UNSAFE_KEY, &e).await?;
+                        problems.push(json!({ "id": id, "name": asset.name()?, "problem": e }));
+                    }
+                }
+            }
+            Ok::<_, String>((sanitized, problems))
+        }
+        .await;
+
+        match result {
+            Ok((sanitized, problems)) => Response::success(json!({
+                "sanitized": sanitized,
+                "unsafe": problems,
+            })),
+            Err(e) => Response::error(&format!("Failed to sanitize SVG assets: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Feed of committed changes, for tools following a project from outside.
+//!
+//! Each message that changes a project's content and succeeds appends a
+//! compact event to a bounded in-memory log. A backup daemon or sync bridge
+//! polls `GetChangesSince` with the last version it saw, and is told when
+//! events it never read have been dropped (or the store restarted), so it
+//! knows to resync in full.
+
+use std::collections::VecDeque;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::freeze::changed_project;
+use super::StoreInner;
+use crate::messages::{Message, Response};
+use crate::types::ProjectType;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Events kept before the oldest are dropped
+pub const CHANGE_FEED_LIMIT: usize = 1000;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(super) struct ChangeEvent {
+    /// Position in the feed, counting from 1; callers pass the last one they
+    /// saw as the cursor
+    pub version: u64,
+    /// Id of the site or theme changed
+    pub project: String,
+    /// The file changed, when the message names a single one
+    pub file: Option<String>,
+    /// "file_created", "file_updated", "file_deleted", "collection_changed",
+    /// "folders_changed", "assets_changed", "content_replaced",
+    /// "settings_changed", "form_saved", "collaborators_changed", "shared"
+    /// or "imported"
+    pub kind: &'static str,
+}
+
+/// The last `CHANGE_FEED_LIMIT` events, oldest first
+#[derive(Debug, Clone, Default)]
+pub(super) struct ChangeFeed {
+    events: VecDeque<ChangeEvent>,
+    latest: u64,
+}
+
+impl ChangeFeed {
+    pub fn push(&mut self, project: String, file: Option<String>, kind: &'static str) {
+        self.latest += 1;
+        self.events.push_back(ChangeEvent {
+            version: self.latest,
+            project,
+            file,
+            kind,
+        });
+        while self.events.len() > CHANGE_FEED_LIMIT {
+            self.events.pop_front();
+        }
+    }
+
+    /// Events after `cursor`, the latest version, and whether any events
+    /// after `cursor` are no longer in the feed
+    pub fn since(&self, cursor: u64) -> (Vec<ChangeEvent>, u64, bool) {
+        let oldest = self.events.front().map_or(self.latest + 1, |e| e.version);
+        let missed = cursor > self.latest || cursor + 1 < oldest;
+        let events = self
+            .events
+            .iter()
+            .filter(|event| event.version > cursor)
+            .cloned()
+            .collect();
+        (events, self.latest, missed)
+    }
+}
+
+/// What a message changes, before it runs: the kind of change and the file
+/// it names
+///
+/// A created file's id is only known from the response.
+pub(super) fn change_of(message: &Message) -> Option<(ProjectType, &'static str, Option<String>)> {
+    let project_type = changed_project(message)?;
+    let (kind, file) = match message {
+        Message::CreateFile { .. } => ("file_created", None),
+        Message::UpdateFile { file_id, .. } | Message::InsertSnippet { file_id, .. } => {
+            ("file_updated", Some(file_id.clone()))
+        }
+        Message::DeleteFile { file_id, .. } => ("file_deleted", Some(file_id.clone())),
+        Message::MoveAsset { asset_id, .. } | Message::TransformAsset { asset_id, .. } => {
+            ("file_updated", Some(asset_id.clone()))
+        }
+        Message::AddCollection { .. }
+        | Message::SetCollectionListing { .. }
+        | Message::SetCollectionSchema { .. }
+        | Message::ImportSchema { .. } => ("collection_changed", None),
+        Message::CreateAssetFolder { .. }
+        | Message::RenameAssetFolder { .. }
+        | Message::MoveAssetFolder { .. } => ("folders_changed", None),
+        Message::SanitizeSvgAssets { .. } | Message::TrashOrphanAssets { .. } => {
+            ("assets_changed", None)
+        }
+        Message::FindReplace { .. } => ("content_replaced", None),
+        Message::SetThemeManifest { .. }
+        | Message::SelectTheme { .. }
+        | Message::SetSiteSettings { .. }
+        | Message::SetUrlPattern { .. } => ("settings_changed", None),
+        Message::SaveForm { .. } => ("form_saved", None),
+        Message::SetCollaborator { .. } | Message::RemoveCollaborator { .. } => {
+            ("collaborators_changed", None)
+        }
+        Message::ApplyShareUpdates { .. } => ("shared", None),
+        _ => ("imported", None),
+    };
+    Some((project_type, kind, file))
+}
+
+impl StoreInner {
+    /// Add an event for a change that went through
+    pub(super) fn record_change(
+        &self,
+        change: (ProjectType, &'static str, Option<String>),
+        response: &Response,
+    ) {
+        let created = match response {
+            Response::Success(value) => value.get("id").and_then(Value::as_str),
+            Response::Error(_) => return,
+        };
+        let (project_type, kind, file) = change;
+        let file = match kind {
+            "file_created" => created.map(str::to_string),
+            _ => file,
+        };
+        let project = match self.active_project(project_type) {
+            Ok(project) => project.id(),
+            Err(e) => {
+                console_log!("Failed to record change: {}", e);
+                return;
+            }
+        };
+        self.changes.lock().unwrap().push(project, file, kind);
+    }
+
+    /// ACTOR Changes committed after `cursor`, oldest first
+    ///
+    /// `missed` is set when some of them were dropped from the feed or the
+    /// store has restarted since `cursor`, so the caller should resync.
+    pub(super) fn get_changes_since(&self, cursor: u64) -> Response {
+        console_log!("Getting changes since {}", cursor);
+
+        let (changes, latest, missed) = self.changes.lock().unwrap().since(cursor);
+        Response::success(json!({
+            "changes": changes,
+            "cursor": latest,
+            "missed": missed,
+        }))
+    }
+}
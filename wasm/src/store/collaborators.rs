@@ -0,0 +1,93 @@
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::StoreInner;
+use crate::js_conversions::js_conversions;
+use crate::messages::Response;
+use crate::model::short_id::unique_short_id;
+use crate::types::Collaborator;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Trimmed, non-empty entries of a list
+fn tidy(items: &[String]) -> Vec<String> {
+    items
+        .iter()
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+impl StoreInner {
+    /// ACTOR Everyone credited on the active project, by name
+    pub(super) fn list_collaborators(&self, project_type: String) -> Response {
+        let result = js_conversions::string_to_project_type(&project_type)
+            .and_then(|project_type| self.active_project(project_type))
+            .and_then(|project| project.collaborators());
+        match result {
+            Ok(collaborators) => Response::success(json!({ "collaborators": collaborators })),
+            Err(e) => Response::error(&format!("Failed to list collaborators: {}", e)),
+        }
+    }
+
+    /// ACTOR Add a collaborator, or update the one with the same id
+    ///
+    /// One without an id is new and is given one. Roles and links are
+    /// trimmed and blank ones dropped.
+    pub(super) fn set_collaborator(
+        &self,
+        project_type: String,
+        collaborator: Collaborator,
+    ) -> Response {
+        console_log!("Setting collaborator {}", collaborator.name);
+
+        let result = js_conversions::string_to_project_type(&project_type)
+            .and_then(|project_type| self.active_project(project_type))
+            .and_then(|project| {
+                let name = collaborator.name.trim();
+                if name.is_empty() {
+                    return Err("A collaborator needs a name".to_string());
+                }
+                let existing = project.collaborators()?;
+                let id = match collaborator.id.trim() {
+                    "" => unique_short_id(|id| existing.iter().any(|c| c.id == id))?,
+                    id => id.to_string(),
+                };
+                let collaborator = Collaborator {
+                    id,
+                    name: name.to_string(),
+                    roles: tidy(&collaborator.roles),
+                    links: tidy(&collaborator.links),
+                };
+                project.set_collaborator(&collaborator)?;
+                Ok(collaborator)
+            });
+        match result {
+            Ok(collaborator) => Response::success(collaborator),
+            Err(e) => Response::error(&format!("Failed to set collaborator: {}", e)),
+        }
+    }
+
+    /// ACTOR Stop crediting a collaborator
+    pub(super) fn remove_collaborator(&self, project_type: String, id: String) -> Response {
+        console_log!("Removing collaborator {}", id);
+
+        let result = js_conversions::string_to_project_type(&project_type)
+            .and_then(|project_type| self.active_project(project_type))
+            .and_then(|project| project.remove_collaborator(&id));
+        match result {
+            Ok(true) => Response::success(json!({ "id": id, "status": "removed" })),
+            Ok(false) => Response::error(&format!("No collaborator {}", id)),
+            Err(e) => Response::error(&format!("Failed to remove collaborator: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+use loro::LoroMap;
+use serde_json::{json, Map, Value};
+use wasm_bindgen::prelude::*;
+
+use super::file_list::{meta_time, DEFAULT_STATUS};
+use super::render::load_full_file;
+use super::report::{CREATED_FIELD, STATUS_FIELD, UPDATED_FIELD};
+use super::roles::is_draftable;
+use super::StoreInner;
+use crate::js_conversions::js_conversions;
+use crate::messages::Response;
+use crate::model::file::{File, ID_KEY, NAME_KEY, TYPE_KEY, VERSION_KEY};
+use crate::model::project::Project;
+use crate::model::{configure_text_style, loro_doc_to_pm_doc, Entry, Model, Page, Post};
+use crate::render::redirects::{FROM_FIELD, REDIRECTS_COLLECTION, TO_FIELD};
+use crate::types::{FieldDefinition, FieldType, ListingConfig, ProjectType};
+use crate::{FileStore, ProseMirrorSchema, PM_SCHEMA_KEY};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Collections backed by their own file types; everything else holds entries
+pub(super) const BUILT_IN_COLLECTIONS: [&str; 9] = [
+    "page", "post", "asset", "template", "partial", "text", "form", "snippet", "skeleton",
+];
+
+/// Meta keys every file already uses, so they can't be collection fields
+pub(super) const RESERVED_FIELDS: [&str; 4] = [ID_KEY, NAME_KEY, TYPE_KEY, VERSION_KEY];
+
+pub(super) fn is_built_in_collection(name: &str) -> bool {
+    BUILT_IN_COLLECTIONS.contains(&name)
+}
+
+/// File count, when a file last changed and, for pages and posts, how many
+/// files have each status, from the files' metadata
+///
+/// `lastUpdated` is in ms since the epoch, falling back to a file's
+/// creation time, and null if no file records either.
+pub(super) fn collection_stats(name: &str, files: &[Map<String, Value>]) -> Value {
+    let last_updated = files
+        .iter()
+        .filter_map(|meta| {
+            meta_time(meta, UPDATED_FIELD).or_else(|| meta_time(meta, CREATED_FIELD))
+        })
+        .max();
+    let mut stats = json!({ "files": files.len(), "lastUpdated": last_updated });
+
+    if is_draftable(name) {
+        let mut statuses: BTreeMap<String, usize> = BTreeMap::new();
+        for meta in files {
+            let status = meta
+                .get(STATUS_FIELD)
+                .and_then(Value::as_str)
+                .map(|status| status.trim().to_lowercase())
+                .filter(|status| !status.is_empty())
+                .unwrap_or_else(|| DEFAULT_STATUS.to_string());
+            *statuses.entry(status).or_default() += 1;
+        }
+        stats["statuses"] = json!(statuses);
+    }
+    stats
+}
+
+/// A collection's JSON, from the attached collection so custom fields come
+/// through, with `stats` on its files and folder count
+pub(super) async fn collection_summary<T: File + Default>(
+    project: &Project,
+    name: &str,
+) -> Result<Value, String> {
+    let collection = project.get_collection::<T>(name)?;
+    let files: Vec<Map<String, Value>> = collection
+        .get_files(name)
+        .await?
+        .iter()
+        .map(File::meta_fields)
+        .collect();
+
+    let mut summary = js_conversions::collection_to_json(&collection)?;
+    let mut stats = collection_stats(name, &files);
+    stats["folders"] = json!(collection.folders()?.len());
+    summary["stats"] = stats;
+    Ok(summary)
+}
+
+/// Move every document in a collection onto a new schema, returning how
+/// many were migrated
+///
+/// Nothing changes unless every document already fits the schema, so nodes
+/// and marks can be added freely but only dropped once no document uses
+/// them. Files whose document hasn't been stored yet are left alone.
+async fn migrate_documents<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+    schema: &ProseMirrorSchema,
+) -> Result<usize, String> {
+    let cached = project
+        .get_collection::<T>(collection_name)?
+        .get_files(collection_name)
+        .await?;
+
+    let mut documents = Vec::new();
+    let mut misfits = Vec::new();
+    for file in &cached {
+        let full = match load_full_file::<T>(collection_name, &file.id()?).await {
+            Ok(full) => full,
+            Err(_) => continue,
+        };
+        let doc = full
+            .store()
+            .as_full()
+            .ok_or_else(|| "Missing document".to_string())?;
+        if let Err(e) = loro_doc_to_pm_doc(doc).and_then(|body| schema.check_node(&body)) {
+            misfits.push(format!("{}: {}", file.name()?, e));
+        }
+        documents.push(full);
+    }
+    if !misfits.is_empty() {
+        return Err(format!(
+            "Some documents don't fit the schema: {}",
+            misfits.join("; ")
+        ));
+    }
+
+    let schema_json = schema.to_string();
+    for full in &documents {
+        full.set_field(PM_SCHEMA_KEY, &schema_json).await?;
+        if let Some(doc) = full.store().as_full() {
+            configure_text_style(doc, schema)?;
+        }
+        full.save_to_indexeddb().await?;
+    }
+    Ok(documents.len())
+}
+
+impl StoreInner {
+    /// ACTOR Set the ProseMirror schema for a collection's documents
+    ///
+    /// New documents start with it, and existing ones are migrated to it;
+    /// steps applied to a document are checked against its schema.
+    pub(super) async fn set_collection_schema(
+        &self,
+        project_type: String,
+        collection_name: String,
+        schema: Value,
+    ) -> Response {
+        let project_type = match js_conversions::string_to_project_type(&project_type) {
+            Ok(pt) => pt,
+            Err(e) => return Response::error(&format!("Failed to convert project type: {}", e)),
+        };
+
+        console_log!(
+            "Setting schema for collection: {} in {:?}",
+            collection_name,
+            project_type
+        );
+
+        let schema = match ProseMirrorSchema::try_from(schema) {
+            Ok(schema) => schema,
+            Err(e) => return Response::error(&e),
+        };
+        let project = match self.active_project(project_type) {
+            Ok(project) => project,
+            Err(e) => return Response::error(&e),
+        };
+
+        let migrated = match collection_name.as_str() {
+            "page" | "snippet" | "skeleton" => {
+                migrate_documents::<Page>(&project, &collection_name, &schema).await
+            }
+            "post" => migrate_documents::<Post>(&project, &collection_name, &schema).await,
+            other => Err(format!("{} documents aren't rich text", other)),
+        };
+        let migrated = match migrated {
+            Ok(migrated) => migrated,
+            Err(e) => return Response::error(&format!("Failed to set schema: {}", e)),
+        };
+
+        let stored = project
+            .get_collection::<Entry>(&collection_name)
+            .and_then(|collection| collection.set_pm_schema(&schema));
+        if let Err(e) = stored {
+            return Response::error(&format!("Failed to set schema: {}", e));
+        }
+
+        Response::success(json!({
+            "collection": collection_name,
+            "schema": schema,
+            "migrated": migrated,
+        }))
+    }
+
+    /// ACTOR add a custom collection to a project
+    pub(super) fn add_collection(
+        &self,
+        project_type: String,
+        name: String,
+        fields: Vec<FieldDefinition>,
+    ) -> Response {
+        let project_type = match js_conversions::string_to_project_type(&project_type) {
+            Ok(pt) => pt,
+            Err(e) => return Response::error(&format!("Failed to convert project type: {}", e)),
+        };
+
+        console_log!("Adding collection: {} to {:?}", name, project_type);
+
+        let mut project = match self.active_project(project_type.clone()) {
+            Ok(project) => project,
+            Err(e) => return Response::error(&e),
+        };
+
+        match add_custom_collection(&mut project, &name, fields) {
+            Ok(collection) => {
+                let result = match project_type {
+                    ProjectType::Site => self.set_site(project),
+                    ProjectType::Theme => self.set_theme(project),
+                };
+                if let Err(e) = result {
+                    return Response::error(&e);
+                }
+
+                match js_conversions::collection_to_json(&collection) {
+                    Ok(json_value) => Response::success(json_value),
+                    Err(e) => {
+                        Response::error(&format!("Failed to convert collection to JSON: {}", e))
+                    }
+                }
+            }
+            Err(e) => Response::error(&format!("Failed to add collection: {}", e)),
+        }
+    }
+
+    /// ACTOR configure (or remove) a custom collection's listing pages
+    pub(super) fn set_collection_listing(
+        &self,
+        project_type: String,
+        collection_name: String,
+        listing: Option<ListingConfig>,
+    ) -> Response {
+        let project_type = match js_conversions::string_to_project_type(&project_type) {
+            Ok(pt) => pt,
+            Err(e) => return Response::error(&format!("Failed to convert project type: {}", e)),
+        };
+
+        console_log!(
+            "Setting listing for collection: {} in {:?}",
+            collection_name,
+            project_type
+        );
+
+        if is_built_in_collection(&collection_name) {
+            return Response::error(&format!(
+                "{} is not a data collection",
+                collection_name
+            ));
+        }
+        if let Some(listing) = &listing {
+            if listing.path.trim_matches('/').is_empty() || listing.template.is_empty() {
+                return Response::error("A listing needs a path and a template");
+            }
+        }
+
+        let project = match self.active_project(project_type) {
+            Ok(project) => project,
+            Err(e) => return Response::error(&e),
+        };
+        let collection = match project.get_collection::<Entry>(&collection_name) {
+            Ok(collection) => collection,
+            Err(e) => return Response::error(&format!("Failed to get collection: {}", e)),
+        };
+
+        if let Err(e) = collection.set_listing(listing.as_ref()) {
+            return Response::error(&e);
+        }
+
+        Response::success(json!({
+            "collection": collection_name,
+            "listing": listing,
+        }))
+    }
+
+    /// Create an entry in a custom collection and set its field values
+    pub(super) async fn create_entry(
+        &self,
+        project: &mut Project,
+        collection_name: &str,
+        name: &str,
+        values: &Map<String, Value>,
+    ) -> Result<Entry, String> {
+        let builder =
+            project.create_file::<Entry>(name, collection_name, FileStore::Cache(LoroMap::new()))?;
+        let id = project.attach_file(builder).await?.id()?;
+
+        // Entries live on the files tree, so write through the attached copy
+        let entry = project
+            .get_collection::<Entry>(collection_name)?
+            .get_file(&id, collection_name)
+            .await?;
+        for (field, value) in values {
+            entry.set_field_value(field, value).await?;
+        }
+
+        Ok(entry)
+    }
+}
+
+pub(super) fn add_custom_collection(
+    project: &mut Project,
+    name: &str,
+    fields: Vec<FieldDefinition>,
+) -> Result<crate::model::Collection<Entry>, String> {
+    if name.trim().is_empty() {
+        return Err("Collection name cannot be empty".to_string());
+    }
+    if is_built_in_collection(name) {
+        return Err(format!("{} is a built-in collection", name));
+    }
+    if project
+        .get_collections()?
+        .iter()
+        .any(|(existing, _)| existing == name)
+    {
+        return Err(format!("Collection already exists: {}", name));
+    }
+
+    let mut model = Model::new();
+    for field in fields {
+        if RESERVED_FIELDS.contains(&field.name.as_str()) {
+            return Err(format!("{} is a reserved field name", field.name));
+        }
+        model.insert(&field.name.clone(), field);
+    }
+
+    project.add_collection::<Entry>(name, model)
+}
+
+/// Add the `redirects` collection if the project doesn't have it yet
+pub(super) fn ensure_redirects_collection(project: &mut Project) -> Result<(), String> {
+    let exists = project
+        .get_collections()?
+        .iter()
+        .any(|(name, _)| name == REDIRECTS_COLLECTION);
+    if !exists {
+        let field = |name: &str| FieldDefinition {
+            name: name.to_string(),
+            field_type: FieldType::String,
+            required: true,
+        };
+        add_custom_collection(
+            project,
+            REDIRECTS_COLLECTION,
+            vec![field(FROM_FIELD), field(TO_FIELD)],
+        )?;
+    }
+    Ok(())
+}
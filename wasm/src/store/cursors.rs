@@ -0,0 +1,147 @@
+use loro::cursor::Cursor;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::install::save_bytes;
+use super::maintenance::load_bytes;
+use super::{StoreInner, IDB_PROJECTS_STORE};
+use crate::messages::Response;
+use crate::model::{cursor_at_position, cursor_position};
+use crate::render::manifest::{base64_decode, base64_encode};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// A file's saved cursor is kept in the projects store, under its id with
+/// this prefix, so it stays in this browser and out of the shared document
+pub const CURSOR_PREFIX: &str = "cursor:";
+
+pub(super) fn cursor_key(file_id: &str) -> String {
+    format!("{}{}", CURSOR_PREFIX, file_id)
+}
+
+/// Where the user left off in a file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct SavedCursor {
+    /// Encoded Loro cursor, as from `CreateAnchor`
+    anchor: String,
+    /// Position when saved, for when the anchor's text has been removed
+    position: usize,
+    #[serde(rename = "savedAt")]
+    saved_at: String,
+}
+
+async fn load_saved_cursor(file_id: &str) -> Result<Option<SavedCursor>, String> {
+    match load_bytes(IDB_PROJECTS_STORE, &cursor_key(file_id)).await? {
+        Some(bytes) if !bytes.is_empty() => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| format!("Failed to read saved cursor: {}", e)),
+        _ => Ok(None),
+    }
+}
+
+async fn store_saved_cursor(file_id: &str, saved: &SavedCursor) -> Result<(), String> {
+    let bytes = serde_json::to_vec(saved).map_err(|e| e.to_string())?;
+    save_bytes(IDB_PROJECTS_STORE, &cursor_key(file_id), &bytes).await
+}
+
+impl StoreInner {
+    /// ACTOR Remember where the user is in a page, post or snippet
+    ///
+    /// The position is kept as an anchor, so `RestoreCursor` finds the same
+    /// spot after edits made here or merged in from elsewhere.
+    pub(super) async fn save_cursor(&self, file_id: String, position: usize) -> Response {
+        console_log!("Saving cursor in {} at {}", file_id, position);
+
+        match self.save_cursor_at(&file_id, position).await {
+            Ok(saved) => Response::success(json!({
+                "fileId": file_id,
+                "position": saved.position,
+                "savedAt": saved.saved_at,
+            })),
+            Err(e) => Response::error(&format!("Failed to save cursor: {}", e)),
+        }
+    }
+
+    async fn save_cursor_at(&self, file_id: &str, position: usize) -> Result<SavedCursor, String> {
+        let doc = self.rich_text_document(file_id).await?;
+        let cursor = cursor_at_position(&doc, position)?;
+        let saved = SavedCursor {
+            anchor: base64_encode(&cursor.encode()),
+            position,
+            saved_at: chrono::Utc::now().to_rfc3339(),
+        };
+        store_saved_cursor(file_id, &saved).await?;
+        Ok(saved)
+    }
+
+    /// ACTOR Where the user left off in a page, post or snippet
+    ///
+    /// `position` is null if no cursor was saved. If the text it was in has
+    /// since been removed, the position it was saved at is returned with
+    /// `found: false`, for the editor to clamp.
+    pub(super) async fn restore_cursor(&self, file_id: String) -> Response {
+        console_log!("Restoring cursor in {}", file_id);
+
+        let saved = match load_saved_cursor(&file_id).await {
+            Ok(Some(saved)) => saved,
+            Ok(None) => {
+                return Response::success(json!({
+                    "fileId": file_id,
+                    "position": null,
+                }))
+            }
+            Err(e) => return Response::error(&format!("Failed to restore cursor: {}", e)),
+        };
+
+        let resolved = match self.resolve_saved_cursor(&file_id, &saved).await {
+            Ok(resolved) => resolved,
+            Err(e) => return Response::error(&format!("Failed to restore cursor: {}", e)),
+        };
+        let position = match resolved {
+            Some((position, updated)) => {
+                // Keep the faster anchor Loro suggests for next time
+                if let Some(updated) = updated {
+                    let refreshed = SavedCursor {
+                        anchor: base64_encode(&updated.encode()),
+                        ..saved.clone()
+                    };
+                    if let Err(e) = store_saved_cursor(&file_id, &refreshed).await {
+                        console_log!("Failed to refresh cursor in {}: {}", file_id, e);
+                    }
+                }
+                Some(position)
+            }
+            None => None,
+        };
+
+        Response::success(json!({
+            "fileId": file_id,
+            "position": position.unwrap_or(saved.position),
+            "found": position.is_some(),
+            "savedAt": saved.saved_at,
+        }))
+    }
+
+    /// The saved cursor's current position, or `None` if its text is gone
+    async fn resolve_saved_cursor(
+        &self,
+        file_id: &str,
+        saved: &SavedCursor,
+    ) -> Result<Option<(usize, Option<Cursor>)>, String> {
+        let doc = self.rich_text_document(file_id).await?;
+        let cursor = base64_decode(&saved.anchor)
+            .and_then(|bytes| Cursor::decode(&bytes).map_err(|e| e.to_string()))
+            .map_err(|e| format!("Invalid saved cursor: {}", e))?;
+        Ok(cursor_position(&doc, &cursor).ok())
+    }
+}
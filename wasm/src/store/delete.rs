@@ -0,0 +1,333 @@
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::cursors::cursor_key;
+use super::imports::asset_url;
+use super::install::save_bytes;
+use super::stats::stored_bytes;
+use super::{StoreInner, IDB_ASSETS_STORE, IDB_FILES_STORE, IDB_PROJECTS_STORE};
+use crate::js_conversions::js_conversions;
+use crate::messages::Response;
+use crate::model::file::{thumbnail_key, File};
+use crate::model::project::Project;
+use crate::model::{Asset, Entry, Page, Partial, Post, Template, Text};
+use crate::render::dependents::{
+    asset_dependents, partial_dependents, template_dependents, Dependent,
+};
+use crate::types::ProjectType;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Emitted with `{project_type, collection, file_id}` after a file is deleted
+pub const FILE_DELETED_EVENT: &str = "file:deleted";
+
+/// Set on an asset moved to the trash: when it was trashed (RFC 3339)
+///
+/// Trashed assets stay in storage but are left out of the published site.
+pub(super) const TRASHED_FIELD: &str = "trashed_at";
+
+pub(super) fn is_trashed(asset: &Asset) -> bool {
+    matches!(asset.get_field(TRASHED_FIELD), Ok(Value::String(at)) if !at.is_empty())
+}
+
+/// The URL prefix every link to an asset starts with: `/assets/{id}/`
+fn asset_prefix(id: &str, name: &str) -> String {
+    let url = asset_url(id, name);
+    url[..url.len() - name.len()].to_string()
+}
+
+async fn file_name<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+    file_id: &str,
+) -> Result<String, String> {
+    project
+        .get_collection::<T>(collection_name)?
+        .get_file(file_id, collection_name)
+        .await?
+        .name()
+}
+
+fn remove_file<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+    file_id: &str,
+) -> Result<(), String> {
+    project
+        .get_collection::<T>(collection_name)?
+        .remove_file(file_id)
+}
+
+/// Take a file out of its collection's tree
+fn remove_from_collection(
+    project: &Project,
+    collection_name: &str,
+    file_id: &str,
+) -> Result<(), String> {
+    match collection_name {
+        "page" | "snippet" | "skeleton" => remove_file::<Page>(project, collection_name, file_id),
+        "post" => remove_file::<Post>(project, collection_name, file_id),
+        "asset" => remove_file::<Asset>(project, collection_name, file_id),
+        "template" => remove_file::<Template>(project, collection_name, file_id),
+        "partial" => remove_file::<Partial>(project, collection_name, file_id),
+        "text" => remove_file::<Text>(project, collection_name, file_id),
+        _ => remove_file::<Entry>(project, collection_name, file_id),
+    }
+}
+
+/// Delete what's stored for a file outside the project: its document, or
+/// an asset's bytes and thumbnail, and its saved cursor
+async fn delete_stored(collection_name: &str, file_id: &str) -> Result<(), String> {
+    let keys = match collection_name {
+        "asset" => vec![
+            (IDB_ASSETS_STORE, file_id.to_string()),
+            (IDB_ASSETS_STORE, thumbnail_key(file_id)),
+        ],
+        _ => vec![(IDB_FILES_STORE, file_id.to_string())],
+    };
+    for (store_name, key) in keys
+        .into_iter()
+        .chain(Some((IDB_PROJECTS_STORE, cursor_key(file_id))))
+    {
+        crate::delete_data(store_name, &key)
+            .await
+            .map_err(|e| format!("Failed to delete {} {}: {:?}", store_name, key, e))?;
+    }
+    Ok(())
+}
+
+impl StoreInner {
+    /// ACTOR Delete a file: take it out of its collection, then delete its
+    /// stored document or bytes
+    ///
+    /// A file something depends on (see `CheckDelete`) is only deleted with
+    /// `force`, leaving its dependents broken. The project is saved without
+    /// the file before its data is deleted, so a tab closed in between
+    /// leaves unused data behind rather than a file with nothing stored.
+    pub(super) async fn delete_file(
+        &self,
+        project_type: String,
+        collection_name: String,
+        file_id: String,
+        force: bool,
+    ) -> Response {
+        console_log!(
+            "Deleting {} {} (force: {})",
+            collection_name,
+            file_id,
+            force
+        );
+
+        let result = async {
+            let project_type = js_conversions::string_to_project_type(&project_type)?;
+            if !force {
+                let (name, dependents) = self
+                    .delete_dependents(&project_type, &collection_name, &file_id)
+                    .await?;
+                if !dependents.is_empty() {
+                    let names: Vec<&str> = dependents
+                        .iter()
+                        .map(|dependent| dependent.name.as_str())
+                        .collect();
+                    return Err(format!(
+                        "{} is used by {}; delete with force to break them",
+                        name,
+                        names.join(", ")
+                    ));
+                }
+            }
+            let project = self.active_project(project_type.clone())?;
+            remove_from_collection(&project, &collection_name, &file_id)?;
+            save_bytes(IDB_PROJECTS_STORE, &project.id(), &project.export()?).await?;
+            self.mark_saved(&project);
+
+            // The open file and preview can't outlive it
+            let open = self
+                .active_file
+                .lock()
+                .unwrap()
+                .as_ref()
+                .and_then(|file| file.id().ok());
+            if open.as_deref() == Some(file_id.as_str()) {
+                *self.active_file.lock().unwrap() = None;
+            }
+            if self.watched_file().as_deref() == Some(file_id.as_str()) {
+                self.unwatch_preview();
+            }
+
+            delete_stored(&collection_name, &file_id).await?;
+            Ok::<_, String>(project_type)
+        }
+        .await;
+
+        match result {
+            Ok(project_type) => {
+                let payload = json!({
+                    "project_type": project_type.to_string(),
+                    "collection": collection_name,
+                    "file_id": file_id,
+                });
+                self.events
+                    .emit(FILE_DELETED_EVENT, JsValue::from_str(&payload.to_string()));
+                Response::success(json!({
+                    "file_id": file_id,
+                    "collection": collection_name,
+                    "status": "deleted",
+                }))
+            }
+            Err(e) => Response::error(&format!("Failed to delete {}: {}", file_id, e)),
+        }
+    }
+
+    /// What depends on a file: pages using a template, templates including
+    /// a partial, or content referencing an asset
+    pub(super) async fn delete_dependents(
+        &self,
+        project_type: &ProjectType,
+        collection_name: &str,
+        file_id: &str,
+    ) -> Result<(String, Vec<Dependent>), String> {
+        let project = self.active_project(project_type.clone())?;
+        let name = match (project_type, collection_name) {
+            (ProjectType::Theme, "template") => {
+                file_name::<Template>(&project, collection_name, file_id).await?
+            }
+            (ProjectType::Theme, "partial") => {
+                file_name::<Partial>(&project, collection_name, file_id).await?
+            }
+            (_, "asset") => file_name::<Asset>(&project, collection_name, file_id).await?,
+            // Nothing else is referenced by other files
+            _ => return Ok((file_id.to_string(), Vec::new())),
+        };
+
+        let (source, _) = self.load_site_source().await?;
+        let dependents = match collection_name {
+            "template" => template_dependents(&source, &name),
+            "partial" => partial_dependents(&source, &name),
+            _ => asset_dependents(&source, &asset_prefix(file_id, &name)),
+        };
+        Ok((name, dependents))
+    }
+
+    /// ACTOR Check whether deleting a file would break the site
+    ///
+    /// `confirmation_required` is set when anything depends on the file, so
+    /// the UI can list the dependents and ask before deleting.
+    pub(super) async fn check_delete(
+        &self,
+        project_type: String,
+        collection_name: String,
+        file_id: String,
+    ) -> Response {
+        console_log!("Checking dependents of {} {}", collection_name, file_id);
+
+        let project_type = match js_conversions::string_to_project_type(&project_type) {
+            Ok(project_type) => project_type,
+            Err(e) => return Response::error(&format!("Failed to convert project type: {}", e)),
+        };
+        match self
+            .delete_dependents(&project_type, &collection_name, &file_id)
+            .await
+        {
+            Ok((name, dependents)) => Response::success(json!({
+                "file_id": file_id,
+                "name": name,
+                "collection": collection_name,
+                "confirmation_required": !dependents.is_empty(),
+                "dependents": dependents,
+            })),
+            Err(e) => Response::error(&format!("Failed to check dependents: {}", e)),
+        }
+    }
+
+    /// Assets in the active site that nothing links to or embeds
+    async fn orphan_assets(&self) -> Result<Vec<Asset>, String> {
+        let site = self.active_project(ProjectType::Site)?;
+        let (source, _) = self.load_site_source().await?;
+        let mut orphans = Vec::new();
+        for asset in site
+            .get_collection::<Asset>("asset")?
+            .get_files("asset")
+            .await?
+        {
+            if is_trashed(&asset) {
+                continue;
+            }
+            let prefix = asset_prefix(&asset.id()?, &asset.name()?);
+            if asset_dependents(&source, &prefix).is_empty() {
+                orphans.push(asset);
+            }
+        }
+        Ok(orphans)
+    }
+
+    /// ACTOR Assets not referenced by any page, post, entry or theme file,
+    /// with the bytes trashing them would free
+    pub(super) async fn find_orphan_assets(&self) -> Response {
+        console_log!("Finding orphan assets");
+
+        let orphans = match self.orphan_assets().await {
+            Ok(orphans) => orphans,
+            Err(e) => return Response::error(&format!("Failed to find orphan assets: {}", e)),
+        };
+        let mut assets = Vec::new();
+        let mut total_bytes = 0;
+        for asset in &orphans {
+            let id = asset.id().unwrap_or_default();
+            let bytes = stored_bytes(IDB_ASSETS_STORE, &id).await;
+            total_bytes += bytes;
+            assets.push(json!({
+                "id": id,
+                "name": asset.name().unwrap_or_default(),
+                "url": asset_url(&id, &asset.name().unwrap_or_default()),
+                "bytes": bytes,
+            }));
+        }
+        Response::success(json!({
+            "assets": assets,
+            "reclaimable_bytes": total_bytes,
+        }))
+    }
+
+    /// ACTOR Move orphan assets to the trash
+    ///
+    /// Each asset is checked again first, so one that has been used since
+    /// the list was made is left alone and reported as skipped.
+    pub(super) async fn trash_orphan_assets(&self, asset_ids: Vec<String>) -> Response {
+        console_log!("Trashing {} orphan assets", asset_ids.len());
+
+        let orphans = match self.orphan_assets().await {
+            Ok(orphans) => orphans,
+            Err(e) => return Response::error(&format!("Failed to find orphan assets: {}", e)),
+        };
+        let trashed_at = chrono::Utc::now().to_rfc3339();
+        let mut trashed = Vec::new();
+        let mut skipped = Vec::new();
+        for id in asset_ids {
+            let asset = orphans
+                .iter()
+                .find(|asset| asset.id().map_or(false, |asset_id| asset_id == id));
+            match asset {
+                Some(asset) => match asset.set_field(TRASHED_FIELD, &trashed_at).await {
+                    Ok(()) => trashed.push(id),
+                    Err(e) => return Response::error(&format!("Failed to trash {}: {}", id, e)),
+                },
+                None => skipped.push(id),
+            }
+        }
+        Response::success(json!({
+            "trashed": trashed,
+            "skipped": skipped,
+            "trashed_at": trashed_at,
+        }))
+    }
+}
@@ -0,0 +1,103 @@
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::StoreInner;
+use crate::messages::Response;
+use crate::model::project::Project;
+use crate::types::ProjectType;
+use crate::FileStore;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// An open document, and whether it has changes that would be lost
+fn document_state(kind: &str, id: String, uncommitted: bool, unsaved: bool) -> Value {
+    json!({
+        "kind": kind,
+        "id": id,
+        "uncommitted": uncommitted,
+        "unsaved": unsaved,
+        "dirty": uncommitted || unsaved,
+    })
+}
+
+impl StoreInner {
+    /// Remember the version of a project that is now in IndexedDB
+    ///
+    /// Called whenever a project is loaded from or written to storage, so
+    /// `GetDirtyState` can tell which projects have moved on since.
+    pub(super) fn mark_saved(&self, project: &Project) {
+        if let Ok(mut saved) = self.saved_versions.lock() {
+            saved.insert(project.id(), project.version());
+        }
+    }
+
+    /// Whether a project has changes IndexedDB doesn't have; projects
+    /// that were never stored always do
+    fn is_unsaved(&self, project: &Project) -> bool {
+        match self.saved_versions.lock() {
+            Ok(saved) => saved.get(&project.id()) != Some(&project.version()),
+            Err(_) => true,
+        }
+    }
+
+    /// Whether a project has changes that closing the tab would lose
+    pub(super) fn is_dirty(&self, project: &Project) -> bool {
+        project.has_uncommitted_changes() || self.is_unsaved(project)
+    }
+
+    fn project_state(&self, kind: &str, project: &Project) -> Value {
+        document_state(
+            kind,
+            project.id(),
+            project.has_uncommitted_changes(),
+            self.is_unsaved(project),
+        )
+    }
+
+    /// ACTOR Which open documents have uncommitted or unsaved changes
+    ///
+    /// Projects are only written by `SaveState`, so any edit since makes
+    /// them unsaved. Files are written on every change, and can only have
+    /// edits that aren't committed yet.
+    pub(super) fn get_dirty_state(&self) -> Response {
+        console_log!("Checking for unsaved changes");
+
+        let mut documents = Vec::new();
+        let projects = [("site", ProjectType::Site), ("theme", ProjectType::Theme)];
+        for (kind, project_type) in projects.iter() {
+            if let Ok(project) = self.active_project(project_type.clone()) {
+                documents.push(self.project_state(kind, &project));
+            }
+        }
+        let open_sites: Vec<Project> = match self.open_sites.lock() {
+            Ok(open) => open.values().cloned().collect(),
+            Err(_) => return Response::error("Failed to acquire open sites lock"),
+        };
+        for site in &open_sites {
+            documents.push(self.project_state("site", site));
+        }
+        if let Some(file) = self.active_file.lock().unwrap().as_ref() {
+            // Files opened from the project's cache are saved with it
+            if let FileStore::Full(doc) = file.store() {
+                let id = file.id().unwrap_or_default();
+                let uncommitted = doc.get_pending_txn_len() > 0;
+                documents.push(document_state(&file.to_string(), id, uncommitted, false));
+            }
+        }
+
+        let dirty = documents.iter().any(|document| document["dirty"] == true);
+        Response::success(json!({
+            "dirty": dirty,
+            "documents": documents,
+        }))
+    }
+}
@@ -0,0 +1,156 @@
+use loro::cursor::Cursor;
+use loro::LoroDoc;
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::render::load_full_file;
+use super::StoreInner;
+use crate::messages::Response;
+use crate::model::file::File;
+use crate::model::{
+    cursor_at_position, cursor_position, loro_doc_to_plain_text, loro_doc_to_pm_doc, Entry, Page,
+    Post,
+};
+use crate::render::manifest::{base64_decode, base64_encode};
+use crate::render::outline::outline;
+use crate::types::{PlainTextOptions, ProjectType};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Site collections whose files have a rich-text document
+const RICH_TEXT_COLLECTIONS: [&str; 3] = ["page", "post", "snippet"];
+
+impl StoreInner {
+    /// The stored document of a page, post or snippet in the active site
+    pub(super) async fn rich_text_document(&self, file_id: &str) -> Result<LoroDoc, String> {
+        let site = self.active_project(ProjectType::Site)?;
+        for collection_name in RICH_TEXT_COLLECTIONS.iter() {
+            // Snippets only exist once the first one is made
+            let ids = match site.get_collection::<Entry>(collection_name) {
+                Ok(collection) => collection.node_ids()?,
+                Err(_) => continue,
+            };
+            if !ids.iter().any(|id| id == file_id) {
+                continue;
+            }
+            let doc = match *collection_name {
+                "post" => load_full_file::<Post>(collection_name, file_id)
+                    .await?
+                    .store()
+                    .as_full()
+                    .cloned(),
+                _ => load_full_file::<Page>(collection_name, file_id)
+                    .await?
+                    .store()
+                    .as_full()
+                    .cloned(),
+            };
+            return doc.ok_or_else(|| format!("No stored document for {}", file_id));
+        }
+        Err(format!("No page, post or snippet with id {}", file_id))
+    }
+
+    /// ACTOR The plain text of a page, post or snippet
+    ///
+    /// For search indexing, excerpts and word counts; blocks are separated
+    /// as `options` says, one per line by default.
+    pub(super) async fn get_plain_text(
+        &self,
+        file_id: String,
+        options: PlainTextOptions,
+    ) -> Response {
+        console_log!("Getting plain text of {}", file_id);
+
+        let text = match self.rich_text_document(&file_id).await {
+            Ok(doc) => loro_doc_to_plain_text(&doc, &options),
+            Err(e) => Err(e),
+        };
+        match text {
+            Ok(text) => Response::success(json!({
+                "fileId": file_id,
+                "words": text.split_whitespace().count(),
+                "text": text,
+            })),
+            Err(e) => Response::error(&format!("Failed to get plain text: {}", e)),
+        }
+    }
+
+    /// ACTOR The heading tree of a page, post or snippet
+    ///
+    /// Each heading has its level, text, ProseMirror position and the
+    /// anchor it gets on the rendered page, for the editor's outline.
+    pub(super) async fn get_outline(&self, file_id: String) -> Response {
+        console_log!("Getting outline of {}", file_id);
+
+        let body = match self.rich_text_document(&file_id).await {
+            Ok(doc) => loro_doc_to_pm_doc(&doc),
+            Err(e) => Err(e),
+        };
+        match body {
+            Ok(body) => Response::success(json!({
+                "fileId": file_id,
+                "headings": outline(&body),
+            })),
+            Err(e) => Response::error(&format!("Failed to get outline: {}", e)),
+        }
+    }
+
+    /// ACTOR Pin a position in a page, post or snippet
+    ///
+    /// Returns an anchor token that follows the text at `position` through
+    /// later edits, including ones merged in from other devices, for
+    /// comments, bookmarks and "continue reading". `ResolveAnchor` turns it
+    /// back into a position.
+    pub(super) async fn create_anchor(&self, file_id: String, position: usize) -> Response {
+        console_log!("Creating anchor in {} at {}", file_id, position);
+
+        let cursor = match self.rich_text_document(&file_id).await {
+            Ok(doc) => cursor_at_position(&doc, position),
+            Err(e) => Err(e),
+        };
+        match cursor {
+            Ok(cursor) => Response::success(json!({
+                "fileId": file_id,
+                "anchor": base64_encode(&cursor.encode()),
+                "position": position,
+            })),
+            Err(e) => Response::error(&format!("Failed to create anchor: {}", e)),
+        }
+    }
+
+    /// ACTOR Where an anchor from `CreateAnchor` is now
+    ///
+    /// The response carries an anchor to keep in place of the old one; it
+    /// resolves to the same spot, just faster.
+    pub(super) async fn resolve_anchor(&self, file_id: String, anchor: String) -> Response {
+        console_log!("Resolving anchor in {}", file_id);
+
+        let cursor = match base64_decode(&anchor)
+            .and_then(|bytes| Cursor::decode(&bytes).map_err(|e| e.to_string()))
+        {
+            Ok(cursor) => cursor,
+            Err(e) => return Response::error(&format!("Invalid anchor: {}", e)),
+        };
+        let resolved = match self.rich_text_document(&file_id).await {
+            Ok(doc) => cursor_position(&doc, &cursor),
+            Err(e) => Err(e),
+        };
+        match resolved {
+            Ok((position, updated)) => Response::success(json!({
+                "fileId": file_id,
+                "anchor": updated.map_or(anchor, |cursor| base64_encode(&cursor.encode())),
+                "position": position,
+            })),
+            Err(e) => Response::error(&format!("Failed to resolve anchor: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::cmp::Ordering;
+
+use chrono::{DateTime, NaiveDate};
+use serde_json::{Map, Value};
+
+use super::report::{DATE_FIELD, STATUS_FIELD, UPDATED_FIELD};
+use crate::render::graph::tags;
+use crate::types::{FileSort, ListFilesOptions};
+
+/// Status of files that don't set one
+pub(super) const DEFAULT_STATUS: &str = "published";
+
+fn meta_str<'a>(meta: &'a Map<String, Value>, key: &str) -> Option<&'a str> {
+    meta.get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Title, or name if untitled
+fn title(meta: &Map<String, Value>) -> &str {
+    meta_str(meta, "title")
+        .or_else(|| meta_str(meta, "name"))
+        .unwrap_or("")
+}
+
+/// A date or timestamp field as ms since the epoch
+pub(super) fn meta_time(meta: &Map<String, Value>, key: &str) -> Option<i64> {
+    match meta.get(key)? {
+        Value::Number(ms) => ms.as_f64().map(|ms| ms as i64),
+        Value::String(text) => DateTime::parse_from_rfc3339(text.trim())
+            .map(|time| time.timestamp_millis())
+            .ok()
+            .or_else(|| {
+                let day = NaiveDate::parse_from_str(text.get(..10)?, "%Y-%m-%d").ok()?;
+                Some(day.and_hms_opt(0, 0, 0)?.and_utc().timestamp_millis())
+            }),
+        _ => None,
+    }
+}
+
+/// Whether a file's metadata passes the `ListFiles` filters
+fn file_matches(meta: &Map<String, Value>, options: &ListFilesOptions) -> bool {
+    if let Some(status) = &options.status {
+        let file_status = meta_str(meta, STATUS_FIELD).unwrap_or(DEFAULT_STATUS);
+        if !file_status.eq_ignore_ascii_case(status.trim()) {
+            return false;
+        }
+    }
+    if let Some(tag) = &options.tag {
+        if !tags(meta)
+            .iter()
+            .any(|file_tag| file_tag.eq_ignore_ascii_case(tag.trim()))
+        {
+            return false;
+        }
+    }
+    if let Some(template) = &options.template {
+        if meta_str(meta, "template") != Some(template.trim()) {
+            return false;
+        }
+    }
+    if let Some(query) = &options.query {
+        if !title(meta)
+            .to_lowercase()
+            .contains(&query.trim().to_lowercase())
+        {
+            return false;
+        }
+    }
+    true
+}
+
+/// Order two optional keys, with missing ones last whichever the direction
+fn compare_present<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+/// Filter and sort files by the metadata alongside them
+pub(super) fn filter_and_sort<T>(
+    files: Vec<(T, Map<String, Value>)>,
+    options: &ListFilesOptions,
+) -> Vec<T> {
+    let mut files: Vec<(T, Map<String, Value>)> = files
+        .into_iter()
+        .filter(|(_, meta)| file_matches(meta, options))
+        .collect();
+
+    if let Some(sort) = options.sort {
+        let descending = options.descending;
+        files.sort_by(|(_, a), (_, b)| match sort {
+            FileSort::Name => compare_present(
+                meta_str(a, "name").map(str::to_lowercase),
+                meta_str(b, "name").map(str::to_lowercase),
+                descending,
+            ),
+            FileSort::Title => compare_present(
+                Some(title(a).to_lowercase()),
+                Some(title(b).to_lowercase()),
+                descending,
+            ),
+            FileSort::Date => compare_present(
+                meta_time(a, DATE_FIELD),
+                meta_time(b, DATE_FIELD),
+                descending,
+            ),
+            FileSort::Updated => compare_present(
+                meta_time(a, UPDATED_FIELD),
+                meta_time(b, UPDATED_FIELD),
+                descending,
+            ),
+        });
+    }
+
+    files.into_iter().map(|(file, _)| file).collect()
+}
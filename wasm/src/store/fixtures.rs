@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::StoreInner;
+use crate::fixtures::{generate, FixtureOptions};
+use crate::messages::Response;
+use crate::model::file::HasUrl;
+use crate::types::ProjectType;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+impl StoreInner {
+    async fn add_fixture(&self, options: &FixtureOptions) -> Result<serde_json::Value, String> {
+        let fixture = generate(options)?;
+        let mut project = self.active_project(ProjectType::Site)?;
+
+        let mut urls = HashMap::new();
+        for asset in &fixture.assets {
+            let stored = self.store_asset(&mut project, asset).await?;
+            urls.insert(asset.key.clone(), stored.get_url()?);
+        }
+        let mut pages = 0;
+        let mut posts = 0;
+        for page in fixture.pages {
+            match page.collection {
+                "post" => posts += 1,
+                _ => pages += 1,
+            }
+            self.create_imported_page(&mut project, page, &urls).await?;
+        }
+
+        Ok(json!({
+            "seed": options.seed,
+            "pages": pages,
+            "posts": posts,
+            "assets": fixture.assets.len(),
+        }))
+    }
+
+    /// ACTOR Fill the active site with generated sample content
+    ///
+    /// The same options give the same titles, text, dates and images.
+    pub(super) async fn generate_fixture(&self, options: FixtureOptions) -> Response {
+        console_log!("Generating fixture: {:?}", options);
+
+        match self.add_fixture(&options).await {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to generate fixture: {}", e)),
+        }
+    }
+}
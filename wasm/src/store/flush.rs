@@ -0,0 +1,218 @@
+//! Last-chance saves for a closing tab.
+//!
+//! A `beforeunload` or `pagehide` handler can't wait for IndexedDB, so
+//! `Store::flush_sync_best_effort` exports every project with unsaved
+//! changes, and the open file if it has uncommitted edits, and writes them,
+//! at once, to localStorage. On the next `LoadState` they are merged into
+//! the stored documents before anything is loaded. Merging is a CRDT import, so changes that were saved after all
+//! are simply already there.
+
+use loro::LoroDoc;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use super::journal::Journal;
+use super::maintenance::load_bytes;
+use super::tabs::TabRole;
+use super::{StoreInner, IDB_FILES_STORE, IDB_PROJECTS_STORE};
+use crate::model::project::{export_document, Project};
+use crate::render::manifest::{base64_decode, base64_encode};
+use crate::types::ProjectType;
+use crate::FileStore;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// The localStorage key flushed changes wait under
+pub const PENDING_FLUSH_KEY: &str = "organ_pages:pending_flush";
+
+const PENDING_FLUSH_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct FlushedProject {
+    id: String,
+    project_type: String,
+    /// The exported document, base64
+    data: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct FlushedFile {
+    id: String,
+    /// The exported document, base64
+    data: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct PendingFlush {
+    version: u32,
+    /// RFC 3339
+    flushed_at: String,
+    projects: Vec<FlushedProject>,
+    #[serde(default)]
+    files: Vec<FlushedFile>,
+}
+
+/// Merge a flushed document into the stored one, for the journal to write
+async fn merge_flushed(store_name: &str, id: &str, data: &str) -> Result<Vec<u8>, String> {
+    let doc = LoroDoc::new();
+    if let Some(stored) = load_bytes(store_name, id).await? {
+        if !stored.is_empty() {
+            doc.import(&stored)
+                .map_err(|e| format!("Failed to read {}: {}", id, e))?;
+        }
+    }
+    doc.import(&base64_decode(data)?)
+        .map_err(|e| format!("Failed to merge flushed {}: {}", id, e))?;
+    export_document(&doc)
+}
+
+/// Merge changes a closed tab flushed into the stored projects and files,
+/// and return how many documents had some
+///
+/// Flushed changes that can't be read are dropped, as the journal's are.
+pub(super) async fn replay_pending_flush() -> Result<usize, String> {
+    let record = match crate::load_pending_sync(PENDING_FLUSH_KEY) {
+        Some(record) if !record.is_empty() => record,
+        _ => return Ok(0),
+    };
+    let pending: PendingFlush = match serde_json::from_str(&record) {
+        Ok(pending) => pending,
+        Err(e) => {
+            console_log!("Dropping unreadable flushed changes: {}", e);
+            crate::clear_pending_sync(PENDING_FLUSH_KEY);
+            return Ok(0);
+        }
+    };
+    if pending.version != PENDING_FLUSH_VERSION {
+        console_log!("Dropping flushed changes of version {}", pending.version);
+        crate::clear_pending_sync(PENDING_FLUSH_KEY);
+        return Ok(0);
+    }
+
+    console_log!(
+        "Replaying {} projects and {} files flushed at {}",
+        pending.projects.len(),
+        pending.files.len(),
+        pending.flushed_at
+    );
+    let mut journal = Journal::new("replay flushed changes");
+    for project in &pending.projects {
+        let merged = merge_flushed(IDB_PROJECTS_STORE, &project.id, &project.data).await?;
+        journal.write(IDB_PROJECTS_STORE, &project.id, merged);
+    }
+    for file in &pending.files {
+        let merged = merge_flushed(IDB_FILES_STORE, &file.id, &file.data).await?;
+        journal.write(IDB_FILES_STORE, &file.id, merged);
+    }
+    let replayed = journal.writes.len();
+    journal.commit().await?;
+    crate::clear_pending_sync(PENDING_FLUSH_KEY);
+    Ok(replayed)
+}
+
+impl StoreInner {
+    /// Every open project: the active site and theme, and sites open in
+    /// the background
+    fn open_projects(&self) -> Vec<(ProjectType, Project)> {
+        let mut projects = Vec::new();
+        for project_type in [ProjectType::Site, ProjectType::Theme].iter() {
+            if let Ok(project) = self.active_project(project_type.clone()) {
+                projects.push((project_type.clone(), project));
+            }
+        }
+        if let Ok(open) = self.open_sites.lock() {
+            projects.extend(open.values().map(|site| (ProjectType::Site, site.clone())));
+        }
+        projects
+    }
+
+    /// The open file, committed and exported, if it has edits that aren't
+    /// committed yet
+    ///
+    /// Files are saved on every change, so only uncommitted edits are
+    /// missing from IndexedDB, as `GetDirtyState` reports.
+    fn flush_open_file(&self) -> Option<FlushedFile> {
+        let file = self.active_file.lock().ok()?;
+        let file = file.as_ref()?;
+        let doc = match file.store() {
+            FileStore::Full(doc) if doc.get_pending_txn_len() > 0 => doc,
+            _ => return None,
+        };
+        let id = file.id().ok()?;
+        doc.commit();
+        match export_document(doc) {
+            Ok(data) => Some(FlushedFile {
+                id,
+                data: base64_encode(&data),
+            }),
+            Err(e) => {
+                console_log!("Failed to flush {}: {}", id, e);
+                None
+            }
+        }
+    }
+
+    /// Write every project with unsaved changes, and the open file if it
+    /// has uncommitted edits, to localStorage, without waiting, and return
+    /// how many documents were written
+    ///
+    /// Each flush replaces the last, so nothing is queued when nothing is
+    /// unsaved. Failures are logged rather than returned: the tab is closing.
+    /// A read-only tab has nothing of its own to flush.
+    pub(super) fn flush_sync_best_effort(&self) -> usize {
+        if self.tab_role() == TabRole::Reader {
+            return 0;
+        }
+        let mut projects = Vec::new();
+        for (project_type, project) in self.open_projects() {
+            if !self.is_dirty(&project) {
+                continue;
+            }
+            match project.export() {
+                Ok(data) => projects.push(FlushedProject {
+                    id: project.id(),
+                    project_type: project_type.to_string(),
+                    data: base64_encode(&data),
+                }),
+                Err(e) => console_log!("Failed to flush {}: {}", project.id(), e),
+            }
+        }
+        let files: Vec<FlushedFile> = self.flush_open_file().into_iter().collect();
+        if projects.is_empty() && files.is_empty() {
+            crate::clear_pending_sync(PENDING_FLUSH_KEY);
+            return 0;
+        }
+
+        let pending = PendingFlush {
+            version: PENDING_FLUSH_VERSION,
+            flushed_at: chrono::Utc::now().to_rfc3339(),
+            projects,
+            files,
+        };
+        let record = match serde_json::to_string(&pending) {
+            Ok(record) => record,
+            Err(e) => {
+                console_log!("Failed to encode flushed changes: {}", e);
+                return 0;
+            }
+        };
+        if !crate::save_pending_sync(PENDING_FLUSH_KEY, &record) {
+            return 0;
+        }
+        console_log!(
+            "Flushed {} projects and {} files",
+            pending.projects.len(),
+            pending.files.len()
+        );
+        pending.projects.len() + pending.files.len()
+    }
+}
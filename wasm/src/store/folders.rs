@@ -0,0 +1,110 @@
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::StoreInner;
+use crate::js_conversions::js_conversions;
+use crate::messages::Response;
+use crate::model::{Asset, Collection};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+impl StoreInner {
+    pub(super) fn asset_collection(&self, project_type: &str) -> Result<Collection<Asset>, String> {
+        let project_type = js_conversions::string_to_project_type(project_type)?;
+        self.active_project(project_type)?
+            .get_collection::<Asset>("asset")
+    }
+
+    /// ACTOR Add a folder to a project's assets
+    pub(super) fn create_asset_folder(
+        &self,
+        project_type: String,
+        name: String,
+        parent_id: Option<String>,
+    ) -> Response {
+        console_log!("Creating asset folder {} in {:?}", name, parent_id);
+
+        match self
+            .asset_collection(&project_type)
+            .and_then(|assets| assets.create_folder(&name, parent_id.as_deref()))
+        {
+            Ok(folder) => Response::success(folder),
+            Err(e) => Response::error(&format!("Failed to create folder: {}", e)),
+        }
+    }
+
+    /// ACTOR Rename an asset folder
+    pub(super) fn rename_asset_folder(
+        &self,
+        project_type: String,
+        folder_id: String,
+        name: String,
+    ) -> Response {
+        console_log!("Renaming asset folder {} to {}", folder_id, name);
+
+        match self
+            .asset_collection(&project_type)
+            .and_then(|assets| assets.rename_folder(&folder_id, &name))
+        {
+            Ok(()) => Response::success(json!({ "id": folder_id, "name": name.trim() })),
+            Err(e) => Response::error(&format!("Failed to rename folder: {}", e)),
+        }
+    }
+
+    /// ACTOR Move an asset folder, with everything in it
+    pub(super) fn move_asset_folder(
+        &self,
+        project_type: String,
+        folder_id: String,
+        parent_id: Option<String>,
+    ) -> Response {
+        console_log!("Moving asset folder {} to {:?}", folder_id, parent_id);
+
+        let result = self.asset_collection(&project_type).and_then(|assets| {
+            if !assets
+                .folders()?
+                .iter()
+                .any(|folder| folder.id == folder_id)
+            {
+                return Err(format!("Folder {} not found", folder_id));
+            }
+            assets.move_to_folder(&folder_id, parent_id.as_deref())
+        });
+        match result {
+            Ok(()) => Response::success(json!({ "id": folder_id, "parent": parent_id })),
+            Err(e) => Response::error(&format!("Failed to move folder: {}", e)),
+        }
+    }
+
+    /// ACTOR Move an asset into a folder
+    ///
+    /// The asset keeps its URL; only exports file it under its folders.
+    pub(super) fn move_asset(
+        &self,
+        project_type: String,
+        asset_id: String,
+        folder_id: Option<String>,
+    ) -> Response {
+        console_log!("Moving asset {} to {:?}", asset_id, folder_id);
+
+        let result = self.asset_collection(&project_type).and_then(|assets| {
+            if assets.folders()?.iter().any(|folder| folder.id == asset_id) {
+                return Err(format!("{} is a folder, not an asset", asset_id));
+            }
+            assets.move_to_folder(&asset_id, folder_id.as_deref())
+        });
+        match result {
+            Ok(()) => Response::success(json!({ "id": asset_id, "folder": folder_id })),
+            Err(e) => Response::error(&format!("Failed to move asset: {}", e)),
+        }
+    }
+}
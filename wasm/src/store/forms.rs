@@ -0,0 +1,150 @@
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::StoreInner;
+use crate::messages::Response;
+use crate::model::file::{File, NAME_KEY};
+use crate::model::project::Project;
+use crate::model::{Entry, Model};
+use crate::render::form::missing_settings;
+use crate::types::{FieldDefinition, FieldType, FormDefinition, ProjectType};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Site collection holding form definitions, one entry per form
+pub(super) const FORM_COLLECTION: &str = "form";
+
+/// Create the form collection on sites made before forms existed
+fn ensure_form_collection(project: &mut Project) -> Result<(), String> {
+    if project
+        .get_collections()?
+        .iter()
+        .any(|(name, _)| name == FORM_COLLECTION)
+    {
+        return Ok(());
+    }
+
+    let mut model = Model::new();
+    for (name, field_type) in [
+        ("title", FieldType::String),
+        ("provider", FieldType::String),
+        ("endpoint", FieldType::String),
+        ("fields", FieldType::Array),
+        ("submit_label", FieldType::String),
+        ("success_url", FieldType::String),
+    ] {
+        model.insert(
+            name,
+            FieldDefinition {
+                name: name.to_string(),
+                field_type,
+                required: name == "provider",
+            },
+        );
+    }
+    project.add_collection::<Entry>(FORM_COLLECTION, model)?;
+    Ok(())
+}
+
+/// Every form defined in a site; malformed entries are reported, not fatal
+pub(super) async fn load_forms(
+    project: &Project,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<FormDefinition>, String> {
+    if !project
+        .get_collections()?
+        .iter()
+        .any(|(name, _)| name == FORM_COLLECTION)
+    {
+        return Ok(Vec::new());
+    }
+
+    let mut forms = Vec::new();
+    for entry in project
+        .get_collection::<Entry>(FORM_COLLECTION)?
+        .get_files(FORM_COLLECTION)
+        .await?
+    {
+        match serde_json::from_value::<FormDefinition>(entry.to_json()?) {
+            Ok(form) => forms.push(form),
+            Err(e) => warnings.push(format!(
+                "form {}: {}",
+                entry.name().unwrap_or_default(),
+                e
+            )),
+        }
+    }
+    Ok(forms)
+}
+
+impl StoreInner {
+    /// ACTOR Create or replace a form definition in the active site
+    pub(super) async fn save_form(&self, form: FormDefinition) -> Response {
+        console_log!("Saving form: {}", form.name);
+
+        match self.save_form_entry(&form).await {
+            Ok(id) => Response::success(json!({
+                "id": id,
+                "form": form,
+                "warnings": missing_settings(&form),
+            })),
+            Err(e) => Response::error(&format!("Failed to save form: {}", e)),
+        }
+    }
+
+    async fn save_form_entry(&self, form: &FormDefinition) -> Result<String, String> {
+        if form.name.trim().is_empty() {
+            return Err("Form name cannot be empty".to_string());
+        }
+
+        let mut project = self.active_project(ProjectType::Site)?;
+        ensure_form_collection(&mut project)?;
+
+        let mut values = match serde_json::to_value(form).map_err(|e| e.to_string())? {
+            Value::Object(values) => values,
+            _ => return Err("Form must serialize to an object".to_string()),
+        };
+        values.remove(NAME_KEY);
+
+        let collection = project.get_collection::<Entry>(FORM_COLLECTION)?;
+        let mut existing = None;
+        for entry in collection.get_files(FORM_COLLECTION).await? {
+            if entry.name()? == form.name {
+                existing = Some(entry);
+                break;
+            }
+        }
+
+        let entry = match existing {
+            Some(entry) => {
+                for (field, value) in &values {
+                    // Unset optional settings are removed, not stored as null
+                    if value.is_null() {
+                        entry.meta().delete(field).map_err(|e| e.to_string())?;
+                    } else {
+                        entry.set_field_value(field, value).await?;
+                    }
+                }
+                entry
+            }
+            None => {
+                values.retain(|_, value| !value.is_null());
+                self.create_entry(&mut project, FORM_COLLECTION, &form.name, &values)
+                    .await?
+            }
+        };
+
+        let id = entry.id()?;
+        self.set_site(project)?;
+        Ok(id)
+    }
+}
@@ -0,0 +1,113 @@
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::StoreInner;
+use crate::messages::{Message, Response};
+use crate::types::ProjectType;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+fn project_of(project_type: &str) -> ProjectType {
+    if project_type.eq_ignore_ascii_case("theme") {
+        ProjectType::Theme
+    } else {
+        ProjectType::Site
+    }
+}
+
+/// The active project whose content a message changes, if any
+///
+/// Saving, exporting, rendering and recording deploys all leave content as
+/// it is, so they carry on while a project is frozen. So do messages that
+/// create or replace whole projects, and maintenance like compaction.
+pub(super) fn changed_project(message: &Message) -> Option<ProjectType> {
+    match message {
+        Message::AddCollection { project_type, .. }
+        | Message::SetCollectionListing { project_type, .. }
+        | Message::SetCollectionSchema { project_type, .. }
+        | Message::ImportSchema { project_type, .. }
+        | Message::CreateFile { project_type, .. }
+        | Message::UpdateFile { project_type, .. }
+        | Message::DeleteFile { project_type, .. }
+        | Message::CreateAssetFolder { project_type, .. }
+        | Message::RenameAssetFolder { project_type, .. }
+        | Message::MoveAssetFolder { project_type, .. }
+        | Message::MoveAsset { project_type, .. }
+        | Message::TransformAsset { project_type, .. }
+        | Message::SanitizeSvgAssets { project_type }
+        | Message::SetCollaborator { project_type, .. }
+        | Message::RemoveCollaborator { project_type, .. }
+        | Message::ApplyShareUpdates { project_type, .. } => Some(project_of(project_type)),
+        Message::FindReplace {
+            dry_run: false,
+            scope,
+            ..
+        } => Some(project_of(&scope.project_type)),
+        Message::SetThemeManifest { .. } => Some(ProjectType::Theme),
+        Message::SelectTheme { .. }
+        | Message::SetSiteSettings { .. }
+        | Message::SetUrlPattern { .. }
+        | Message::SaveForm { .. }
+        | Message::InsertSnippet { .. }
+        | Message::TrashOrphanAssets { .. }
+        | Message::ImportDocx { .. }
+        | Message::ImportCsv { .. }
+        | Message::ImportSubmission { .. }
+        | Message::ImportFromUrl { .. }
+        | Message::ImportAssets { .. }
+        | Message::ImportGhost { .. }
+        | Message::ImportArchive { .. } => Some(ProjectType::Site),
+        #[cfg(feature = "fixtures")]
+        Message::GenerateFixture { .. } => Some(ProjectType::Site),
+        _ => None,
+    }
+}
+
+impl StoreInner {
+    /// Refuse a message that would change a read-only project
+    pub(super) fn check_read_only(&self, message: &Message) -> Result<(), String> {
+        let project_type = match changed_project(message) {
+            Some(project_type) => project_type,
+            None => return Ok(()),
+        };
+        match self.active_project(project_type.clone()) {
+            Ok(project) if project.read_only() => Err(format!(
+                "The {} is read-only; turn off read-only mode to change it",
+                project_type
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// ACTOR Freeze or unfreeze a project's content
+    ///
+    /// While frozen, anything that would change the project's files,
+    /// collections or settings is refused; it can still be rendered,
+    /// exported and saved. The flag is stored in the project, so it holds
+    /// across sessions once saved and travels with exports.
+    pub(super) fn set_project_read_only(&self, project_type: String, enabled: bool) -> Response {
+        console_log!("Setting {} read-only: {}", project_type, enabled);
+
+        let project_type = project_of(&project_type);
+        let result = self
+            .active_project(project_type.clone())
+            .and_then(|project| project.set_read_only(enabled));
+
+        match result {
+            Ok(()) => Response::success(json!({
+                "projectType": project_type.to_string(),
+                "readOnly": enabled,
+            })),
+            Err(e) => Response::error(&format!("Failed to set read-only mode: {}", e)),
+        }
+    }
+}
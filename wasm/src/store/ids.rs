@@ -0,0 +1,89 @@
+use serde_json::{json, Value};
+
+use super::StoreInner;
+use crate::messages::Message;
+use crate::model::project::Project;
+use crate::types::ProjectType;
+
+/// Key short ids are given under in file JSON
+pub(super) const SHORT_ID_KEY: &str = "shortId";
+
+/// Add each file's short id to file JSON, aliasing older files' UUIDs
+pub(super) fn add_short_ids(project: &Project, files: &mut [Value]) -> Result<(), String> {
+    let ids: Vec<String> = files
+        .iter()
+        .filter_map(|file| file["id"].as_str().map(str::to_string))
+        .collect();
+    let short_ids = project.short_file_ids(&ids)?;
+    for file in files.iter_mut() {
+        let short_id = file["id"].as_str().and_then(|id| short_ids.get(id));
+        if let (Value::Object(fields), Some(short_id)) = (file, short_id) {
+            fields.insert(SHORT_ID_KEY.to_string(), json!(short_id));
+        }
+    }
+    Ok(())
+}
+
+impl StoreInner {
+    /// The id a file is stored under, looking up short aliases in the
+    /// active site and theme
+    fn resolve_file_id(&self, id: &mut String) {
+        for project_type in [ProjectType::Site, ProjectType::Theme] {
+            if let Ok(project) = self.active_project(project_type) {
+                let resolved = project.resolve_file_id(id);
+                if resolved != *id {
+                    *id = resolved;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Replace short aliases in a message with the ids files are stored
+    /// under, so handlers only ever see those
+    ///
+    /// Files created since ids were shortened are stored under their short
+    /// id, which passes through unchanged, as do UUIDs.
+    pub(super) fn resolve_file_ids(&self, message: &mut Message) {
+        match message {
+            Message::UpdateFile { file_id, .. }
+            | Message::GetFile { file_id, .. }
+            | Message::GetMeta { file_id, .. }
+            | Message::CheckDelete { file_id, .. }
+            | Message::DeleteFile { file_id, .. }
+            | Message::WatchPreview { file_id }
+            | Message::ParseTemplate { file_id }
+            | Message::GetPlainText { file_id, .. }
+            | Message::GetOutline { file_id }
+            | Message::CreateAnchor { file_id, .. }
+            | Message::ResolveAnchor { file_id, .. }
+            | Message::SaveCursor { file_id, .. }
+            | Message::RestoreCursor { file_id }
+            | Message::GetDocument {
+                document_id: file_id,
+            }
+            | Message::MoveAsset {
+                asset_id: file_id, ..
+            }
+            | Message::TransformAsset {
+                asset_id: file_id, ..
+            } => self.resolve_file_id(file_id),
+            Message::InsertSnippet {
+                file_id,
+                snippet_id,
+                ..
+            } => {
+                self.resolve_file_id(file_id);
+                self.resolve_file_id(snippet_id);
+            }
+            Message::TrashOrphanAssets { asset_ids } => {
+                asset_ids.iter_mut().for_each(|id| self.resolve_file_id(id))
+            }
+            Message::RenderSite { filter, .. } | Message::BuildSite { filter, .. } => filter
+                .files
+                .iter_mut()
+                .for_each(|id| self.resolve_file_id(id)),
+            _ => {}
+        }
+    }
+}
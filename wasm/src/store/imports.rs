@@ -0,0 +1,742 @@
+use std::collections::HashMap;
+
+use loro::{LoroDoc, LoroMap};
+use serde_json::{json, Map, Value};
+use wasm_bindgen::prelude::*;
+
+use super::collections::{ensure_redirects_collection, is_built_in_collection};
+use super::install::fetch_bytes;
+use super::journal::Journal;
+use super::render::load_full_file;
+use super::report::STATUS_FIELD;
+use super::{StoreInner, IDB_ASSETS_STORE, IDB_PROJECTS_STORE};
+use crate::import::static_site::StaticSite;
+use crate::import::svg::{sanitize_bytes, SVG_MIME_TYPE};
+use crate::import::{self, ImportedAsset, ImportedDocument, ImportedPage};
+use crate::js_conversions::js_conversions;
+use crate::messages::Response;
+use crate::model::file::{File, HasMimeType, HasTitle, HasUrl, SANITIZED_KEY, UNSAFE_KEY};
+use crate::model::project::Project;
+use crate::model::{Asset, Entry, HasContent, Page, Partial, Post, Template, Text};
+use crate::render::permalinks::patterned_url;
+use crate::render::redirects::{FROM_FIELD, REDIRECTS_COLLECTION, TO_FIELD};
+use crate::render::site::PageSource;
+use crate::render::slugify;
+use crate::types::{ProjectType, SourceFile, Submission, SubmissionFormat};
+use crate::{pm_json_to_loro, FileStore};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Marks a post as a submission; "pending" until an editor changes it
+pub(super) const REVIEW_FIELD: &str = "review";
+pub(super) const REVIEW_PENDING: &str = "pending";
+
+/// Where an asset is served from once the site is built
+pub(super) fn asset_url(id: &str, name: &str) -> String {
+    format!("/assets/{}/{}", id, name)
+}
+
+/// Name for a downloaded file: the last segment of its URL's path
+fn file_name_from_url(url: &str) -> String {
+    let path = url.split(|c: char| c == '?' || c == '#').next().unwrap_or("");
+    match path.rsplit('/').next() {
+        Some(name) if !name.is_empty() && !path.ends_with(&format!("//{}", name)) => {
+            name.to_string()
+        }
+        _ => "image".to_string(),
+    }
+}
+
+/// Point quoted or `url(...)` references to the old site's files at the
+/// stored assets
+fn rewrite_asset_urls(source: &str, urls: &HashMap<String, String>) -> String {
+    let mut source = source.to_string();
+    for (old, new) in urls {
+        for quote in &["\"", "'", "("] {
+            source = source.replace(&format!("{}{}", quote, old), &format!("{}{}", quote, new));
+        }
+    }
+    source
+}
+
+/// Create a theme file holding text, such as a template or stylesheet
+async fn create_text_file<T: File + Default + HasContent>(
+    project: &mut Project,
+    collection_name: &str,
+    name: &str,
+    content: &str,
+) -> Result<T, String> {
+    let builder =
+        project.create_file::<T>(name, collection_name, FileStore::Full(LoroDoc::new()))?;
+    let file: T = project.attach_file(builder).await?;
+    file.insert_content(content, 0)?;
+    file.save_to_indexeddb().await?;
+    Ok(file)
+}
+
+/// Copy fields onto a file's stored document and its files-tree entry
+async fn set_meta_fields<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+    file: &T,
+    meta: &Map<String, Value>,
+) -> Result<(), String> {
+    if meta.is_empty() {
+        return Ok(());
+    }
+    let cached = project
+        .get_collection::<T>(collection_name)?
+        .get_file(&file.id()?, collection_name)
+        .await?;
+    for (field, value) in meta {
+        file.set_field_value(field, value).await?;
+        cached.set_field_value(field, value).await?;
+    }
+    Ok(())
+}
+
+impl StoreInner {
+    /// ACTOR Import a Word document as a new post
+    pub(super) async fn import_docx(&self, data: Vec<u8>, name: String) -> Response {
+        console_log!("Importing DOCX as post: {} ({} bytes)", name, data.len());
+
+        let imported = match import::docx::docx_to_pm(&data) {
+            Ok(imported) => imported,
+            Err(e) => return Response::error(&format!("Failed to read DOCX: {}", e)),
+        };
+
+        match self.create_post_from_import(&name, imported).await {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to import DOCX: {}", e)),
+        }
+    }
+
+    /// ACTOR Import CSV rows as entries of a custom collection
+    pub(super) async fn import_csv(
+        &self,
+        collection: String,
+        mapping: HashMap<String, String>,
+        data: String,
+    ) -> Response {
+        console_log!("Importing CSV into collection: {} ({} bytes)", collection, data.len());
+
+        match self.create_entries_from_csv(&collection, &mapping, &data).await {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to import CSV: {}", e)),
+        }
+    }
+
+    /// ACTOR Import an external submission as a draft post awaiting review
+    pub(super) async fn import_submission(&self, submission: Submission) -> Response {
+        console_log!(
+            "Importing submission: {} from {}",
+            submission.title,
+            submission.author.name
+        );
+
+        match self.create_post_from_submission(submission).await {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to import submission: {}", e)),
+        }
+    }
+
+    /// ACTOR Import the article on a web page as a draft post
+    ///
+    /// Images in the article are downloaded as assets; any that can't be
+    /// fetched keep their original URLs, with a warning. The page (and its
+    /// images) must allow cross-origin requests.
+    pub(super) async fn import_from_url(&self, url: String) -> Response {
+        console_log!("Importing post from {}", url);
+
+        match self.create_post_from_url(&url).await {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to import {}: {}", url, e)),
+        }
+    }
+
+    /// ACTOR Import the posts and pages in a Ghost JSON export
+    ///
+    /// Given the Ghost site's URL, images uploaded there are downloaded as
+    /// assets; any that can't be fetched keep their URLs, with a warning.
+    pub(super) async fn import_ghost(&self, data: String, site_url: Option<String>) -> Response {
+        console_log!("Importing Ghost export ({} bytes)", data.len());
+
+        match self.create_posts_from_ghost(&data, site_url.as_deref()).await {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to import Ghost export: {}", e)),
+        }
+    }
+
+    /// ACTOR Import a Medium or Tumblr export archive as posts
+    ///
+    /// Each post's old address gets an entry in the `redirects` collection
+    /// pointing at its new one, so links to the old blog keep working once
+    /// its domain points at this site.
+    pub(super) async fn import_archive(&self, data: Vec<u8>, blog_url: Option<String>) -> Response {
+        console_log!("Importing archive ({} bytes)", data.len());
+
+        match self.create_posts_from_archive(&data, blog_url.as_deref()).await {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to import archive: {}", e)),
+        }
+    }
+
+    /// ACTOR Import a Jekyll or Hugo repository as a new theme and site
+    ///
+    /// Layouts are converted to Handlebars on a best-effort basis; anything
+    /// that couldn't be carried across is listed in the warnings and left in
+    /// a comment in the template. The new theme and site become active.
+    pub(super) async fn import_static_site(&self, files: Vec<SourceFile>) -> Response {
+        console_log!("Importing static site ({} files)", files.len());
+
+        let site = match import::static_site::import_static_site(&files) {
+            Ok(site) => site,
+            Err(e) => return Response::error(&format!("Failed to read site: {}", e)),
+        };
+        match self.create_projects_from_static_site(site).await {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to import site: {}", e)),
+        }
+    }
+
+    /// ACTOR List submitted posts, optionally only those with a review state
+    pub(super) async fn list_submissions(&self, review: Option<String>) -> Response {
+        console_log!("Listing submissions: {:?}", review);
+
+        match self.find_submissions(review.as_deref()).await {
+            Ok(submissions) => Response::success(submissions),
+            Err(e) => Response::error(&format!("Failed to list submissions: {}", e)),
+        }
+    }
+
+    pub(super) async fn create_post_from_submission(
+        &self,
+        submission: Submission,
+    ) -> Result<Value, String> {
+        let mut imported = match submission.format {
+            SubmissionFormat::Markdown => import::markdown::markdown_to_pm(&submission.body),
+            SubmissionFormat::Html => import::html::html_to_pm(&submission.body),
+        };
+        imported.title = Some(submission.title.clone());
+
+        let name = match slugify(&submission.title) {
+            slug if slug.is_empty() => "submission".to_string(),
+            slug => slug,
+        };
+        let mut result = self.create_post_from_import(&name, imported).await?;
+        let id = result["file"]["id"]
+            .as_str()
+            .ok_or_else(|| "Created post has no id".to_string())?
+            .to_string();
+
+        let submitted_at = submission
+            .submitted_at
+            .unwrap_or_else(|| chrono::Utc::now().to_rfc3339());
+        let mut fields = vec![
+            (STATUS_FIELD, "draft".to_string()),
+            (REVIEW_FIELD, REVIEW_PENDING.to_string()),
+            ("submitted_by", submission.author.name),
+            ("submitted_at", submitted_at),
+        ];
+        if let Some(email) = submission.author.email {
+            fields.push(("submitter_email", email));
+        }
+        if let Some(url) = submission.author.url {
+            fields.push(("submitter_url", url));
+        }
+        self.set_post_fields(&id, &fields).await?;
+
+        for (field, value) in fields {
+            result["file"][field] = Value::String(value);
+        }
+        Ok(result)
+    }
+
+    async fn create_post_from_url(&self, url: &str) -> Result<Value, String> {
+        if !url.starts_with("https://") && !url.starts_with("http://") {
+            return Err("Only http and https pages can be imported".to_string());
+        }
+        let page = fetch_bytes(url).await?;
+        let article = import::article::extract_article(&String::from_utf8_lossy(&page));
+
+        let mut imported = import::html::html_to_pm(&article.html);
+        for image in import::article::absolutize_urls(&mut imported.content, url) {
+            match fetch_bytes(&image).await {
+                Ok(data) => {
+                    let name = file_name_from_url(&image);
+                    imported.assets.push(ImportedAsset {
+                        key: image,
+                        mime_type: import::mime_type_for(&name).to_string(),
+                        name,
+                        data,
+                    });
+                }
+                Err(e) => imported
+                    .warnings
+                    .push(format!("Kept remote image {}: {}", image, e)),
+            }
+        }
+        imported.title = article.title.or(imported.title);
+
+        let name = match imported.title.as_deref().map(slugify) {
+            Some(slug) if !slug.is_empty() => slug,
+            _ => "imported-post".to_string(),
+        };
+        let mut result = self.create_post_from_import(&name, imported).await?;
+        let id = result["file"]["id"]
+            .as_str()
+            .ok_or_else(|| "Created post has no id".to_string())?
+            .to_string();
+
+        let mut fields = vec![
+            (STATUS_FIELD, "draft".to_string()),
+            ("source_url", url.to_string()),
+        ];
+        if let Some(date) = article.date {
+            fields.push(("date", date));
+        }
+        self.set_post_fields(&id, &fields).await?;
+
+        for (field, value) in fields {
+            result["file"][field] = Value::String(value);
+        }
+        Ok(result)
+    }
+
+    async fn create_posts_from_ghost(
+        &self,
+        data: &str,
+        site_url: Option<&str>,
+    ) -> Result<Value, String> {
+        let ghost = import::ghost::read_ghost_export(data, site_url)?;
+        let mut project = self.active_project(ProjectType::Site)?;
+        let mut warnings = ghost.warnings.clone();
+
+        let images = site_url.map_or_else(Vec::new, |url| ghost.hosted_images(url));
+        let mut urls = HashMap::new();
+        let mut asset_ids = Vec::new();
+        for image in images {
+            let data = match fetch_bytes(&image).await {
+                Ok(data) => data,
+                Err(e) => {
+                    warnings.push(format!("Kept remote image {}: {}", image, e));
+                    continue;
+                }
+            };
+            let name = file_name_from_url(&image);
+            let asset = ImportedAsset {
+                key: image.clone(),
+                mime_type: import::mime_type_for(&name).to_string(),
+                name,
+                data,
+            };
+            let stored = self.store_asset(&mut project, &asset).await?;
+            urls.insert(image, stored.get_url()?);
+            asset_ids.push(stored.id()?);
+        }
+
+        let mut files = Vec::new();
+        for mut page in ghost.pages {
+            let feature_image = page.meta.get("feature_image").and_then(Value::as_str);
+            if let Some(url) = feature_image.and_then(|image| urls.get(image)) {
+                page.meta.insert("feature_image".to_string(), json!(url));
+            }
+            files.push(self.create_imported_page(&mut project, page, &urls).await?);
+        }
+
+        Ok(json!({
+            "files": files,
+            "assets": asset_ids,
+            "warnings": warnings,
+        }))
+    }
+
+    async fn create_posts_from_archive(
+        &self,
+        data: &[u8],
+        blog_url: Option<&str>,
+    ) -> Result<Value, String> {
+        let archive = import::archive::read_archive(data, blog_url)?;
+        let mut project = self.active_project(ProjectType::Site)?;
+        let mut warnings = archive.warnings.clone();
+
+        let mut urls = HashMap::new();
+        let mut asset_ids = Vec::new();
+        for asset in &archive.assets {
+            let stored = self.store_asset(&mut project, asset).await?;
+            urls.insert(asset.key.clone(), stored.get_url()?);
+            asset_ids.push(stored.id()?);
+        }
+        for image in archive.remote_images() {
+            let data = match fetch_bytes(&image).await {
+                Ok(data) => data,
+                Err(e) => {
+                    warnings.push(format!("Kept remote image {}: {}", image, e));
+                    continue;
+                }
+            };
+            let name = file_name_from_url(&image);
+            let asset = ImportedAsset {
+                key: image.clone(),
+                mime_type: import::mime_type_for(&name).to_string(),
+                name,
+                data,
+            };
+            let stored = self.store_asset(&mut project, &asset).await?;
+            urls.insert(image, stored.get_url()?);
+            asset_ids.push(stored.id()?);
+        }
+
+        ensure_redirects_collection(&mut project)?;
+        let patterns = project.settings()?.url_patterns;
+
+        let mut files = Vec::new();
+        let mut redirects = 0;
+        for post in archive.posts {
+            let mut page = post.page;
+            if let Some(url) = post.canonical_url {
+                page.meta.insert("source_url".to_string(), json!(url));
+            }
+            let new_url = patterned_url(&patterns, &PageSource {
+                collection: page.collection.to_string(),
+                name: page.name.clone(),
+                meta: page.meta.clone(),
+                body: Value::Null,
+            });
+            let name = page.name.clone();
+            files.push(self.create_imported_page(&mut project, page, &urls).await?);
+
+            if let Some(old_path) = post.old_path.filter(|path| *path != new_url) {
+                let mut values = Map::new();
+                values.insert(FROM_FIELD.to_string(), json!(old_path));
+                values.insert(TO_FIELD.to_string(), json!(new_url));
+                self.create_entry(&mut project, REDIRECTS_COLLECTION, &name, &values)
+                    .await?;
+                redirects += 1;
+            }
+        }
+
+        Ok(json!({
+            "platform": archive.platform,
+            "files": files,
+            "assets": asset_ids,
+            "redirects": redirects,
+            "warnings": warnings,
+        }))
+    }
+
+    async fn create_projects_from_static_site(&self, site: StaticSite) -> Result<Value, String> {
+        let name = site.name.clone().unwrap_or_else(|| "Imported Site".to_string());
+        let mut theme = Project::new_empty(ProjectType::Theme, None).await?;
+        theme
+            .set_name(&format!("{} Theme", name))
+            .map_err(|e| e.to_string())?;
+        let mut project = Project::new_empty(ProjectType::Site, Some(theme.id())).await?;
+        project.set_name(&name).map_err(|e| e.to_string())?;
+
+        let mut settings = project.settings()?;
+        settings.url = site.url.clone().or(settings.url);
+        settings.base_path = site.base_path.clone().filter(|path| path != "/");
+        project.set_settings(&settings)?;
+
+        // Old URL → where the stored asset is served from
+        let mut urls = HashMap::new();
+        for asset in &site.assets {
+            let imported = ImportedAsset {
+                key: asset.url.clone(),
+                name: asset.name.clone(),
+                mime_type: asset.mime_type.clone(),
+                data: asset.data.clone(),
+            };
+            let stored = self.store_asset(&mut project, &imported).await?;
+            urls.insert(asset.url.clone(), stored.get_url()?);
+        }
+
+        for (name, source) in &site.templates {
+            let source = rewrite_asset_urls(source, &urls);
+            create_text_file::<Template>(&mut theme, "template", name, &source).await?;
+        }
+        for (name, source) in &site.partials {
+            let source = rewrite_asset_urls(source, &urls);
+            create_text_file::<Partial>(&mut theme, "partial", name, &source).await?;
+        }
+        for (name, source) in &site.styles {
+            let source = rewrite_asset_urls(source, &urls);
+            create_text_file::<Text>(&mut theme, "text", name, &source).await?;
+        }
+
+        let mut pages = 0;
+        let mut posts = 0;
+        for page in site.pages {
+            match page.collection {
+                "post" => posts += 1,
+                _ => pages += 1,
+            }
+            self.create_imported_page(&mut project, page, &urls).await?;
+        }
+
+        // Documents are all saved, so the projects can point at them
+        let mut journal = Journal::new("import static site");
+        for saved in [&theme, &project].iter() {
+            journal.write(IDB_PROJECTS_STORE, &saved.id(), saved.export()?);
+        }
+        journal.commit().await?;
+        self.mark_saved(&theme);
+        self.mark_saved(&project);
+        let (site_id, theme_id) = (project.id(), theme.id());
+        self.set_theme(theme)?;
+        self.set_site(project)?;
+        console_log!(
+            "Imported {:?} site {}: {} pages, {} posts, {} warnings",
+            site.generator,
+            name,
+            pages,
+            posts,
+            site.warnings.len()
+        );
+
+        Ok(json!({
+            "id": site_id,
+            "name": name,
+            "themeId": theme_id,
+            "generator": site.generator,
+            "pages": pages,
+            "posts": posts,
+            "templates": site.templates.len(),
+            "partials": site.partials.len(),
+            "styles": site.styles.len(),
+            "assets": site.assets.len(),
+            "warnings": site.warnings,
+        }))
+    }
+
+    /// Create a post or page from another platform, pointing its images at
+    /// stored assets where `urls` has them
+    pub(super) async fn create_imported_page(
+        &self,
+        project: &mut Project,
+        page: ImportedPage,
+        urls: &HashMap<String, String>,
+    ) -> Result<Value, String> {
+        let mut content = page.content;
+        import::resolve_image_sources(&mut content, urls);
+        let mut meta = page.meta;
+        if page.draft {
+            meta.insert(STATUS_FIELD.to_string(), json!("draft"));
+        }
+
+        if page.collection == "post" {
+            let post = self
+                .create_rich_text_file::<Post>(project, "post", &page.name, &page.title, &content)
+                .await?;
+            set_meta_fields::<Post>(project, "post", &post, &meta).await?;
+            js_conversions::file_to_json(&post)
+        } else {
+            let file = self
+                .create_rich_text_file::<Page>(project, "page", &page.name, &page.title, &content)
+                .await?;
+            set_meta_fields::<Page>(project, "page", &file, &meta).await?;
+            js_conversions::file_to_json(&file)
+        }
+    }
+
+    /// Set metadata on a post's files-tree entry and its stored document
+    async fn set_post_fields(&self, id: &str, fields: &[(&str, String)]) -> Result<(), String> {
+        let project = self.active_project(ProjectType::Site)?;
+        let cached = project
+            .get_collection::<Post>("post")?
+            .get_file(id, "post")
+            .await?;
+        let full = load_full_file::<Post>("post", id).await?;
+        for (field, value) in fields {
+            cached.set_field(field, value).await?;
+            full.set_field(field, value).await?;
+        }
+        Ok(())
+    }
+
+    /// Metadata of submitted posts, oldest first
+    async fn find_submissions(&self, review: Option<&str>) -> Result<Value, String> {
+        let project = self.active_project(ProjectType::Site)?;
+        let posts = project
+            .get_collection::<Post>("post")?
+            .get_files("post")
+            .await?;
+
+        let mut submissions: Vec<Value> = posts
+            .iter()
+            .map(|post| post.meta_fields())
+            .filter(|meta| match meta.get(REVIEW_FIELD).and_then(Value::as_str) {
+                Some(state) => review.map_or(true, |review| review == state),
+                None => false,
+            })
+            .map(Value::Object)
+            .collect();
+        submissions.sort_by(|a, b| {
+            a["submitted_at"]
+                .as_str()
+                .cmp(&b["submitted_at"].as_str())
+        });
+        Ok(Value::Array(submissions))
+    }
+
+    /// Create one entry per valid row; rows with errors are skipped and reported
+    pub(super) async fn create_entries_from_csv(
+        &self,
+        collection: &str,
+        mapping: &HashMap<String, String>,
+        data: &str,
+    ) -> Result<Value, String> {
+        if is_built_in_collection(collection) {
+            return Err(format!("{} is not a custom collection", collection));
+        }
+
+        let mut project = self.active_project(ProjectType::Site)?;
+        let fields = project.get_collection::<Entry>(collection)?.get_fields()?;
+        let rows = import::csv::map_rows(data, mapping, &fields)?;
+
+        let mut created = Vec::new();
+        let mut errors = Vec::new();
+        for row in rows {
+            if !row.errors.is_empty() {
+                errors.push(json!({ "row": row.row, "errors": row.errors }));
+                continue;
+            }
+
+            let row_no = row.row;
+            let name = row
+                .name
+                .unwrap_or_else(|| format!("{}-{}", collection, row_no));
+            match self
+                .create_entry(&mut project, collection, &name, &row.values)
+                .await
+            {
+                Ok(entry) => created.push(js_conversions::file_to_json(&entry)?),
+                Err(e) => errors.push(json!({ "row": row.row, "errors": [e] })),
+            }
+        }
+
+        Ok(json!({
+            "created": created,
+            "errors": errors,
+        }))
+    }
+
+    /// Store an import's assets, then create a post holding its content
+    pub(super) async fn create_post_from_import(
+        &self,
+        name: &str,
+        imported: ImportedDocument,
+    ) -> Result<Value, String> {
+        let mut project = self.active_project(ProjectType::Site)?;
+
+        let mut content = imported.content;
+        let mut urls = HashMap::new();
+        let mut asset_ids = Vec::new();
+        for asset in &imported.assets {
+            let stored = self.store_asset(&mut project, asset).await?;
+            urls.insert(asset.key.clone(), stored.get_url()?);
+            asset_ids.push(stored.id()?);
+        }
+        import::resolve_image_sources(&mut content, &urls);
+
+        let title = imported.title.unwrap_or_else(|| name.to_string());
+        let post = self
+            .create_rich_text_file::<Post>(&mut project, "post", name, &title, &content)
+            .await?;
+
+        Ok(json!({
+            "file": js_conversions::file_to_json(&post)?,
+            "assets": asset_ids,
+            "warnings": imported.warnings,
+        }))
+    }
+
+    /// Create a rich-text file and fill its body from ProseMirror JSON
+    pub(super) async fn create_rich_text_file<T: File + Default + HasTitle>(
+        &self,
+        project: &mut Project,
+        collection_name: &str,
+        name: &str,
+        title: &str,
+        body: &Value,
+    ) -> Result<T, String> {
+        let pm_schema = project.collection_pm_schema(collection_name)?;
+        let builder = project
+            .create_file::<T>(name, collection_name, FileStore::Full(LoroDoc::new()))?
+            .with_pm_schema(pm_schema)?;
+        let file = project.attach_file(builder).await?;
+
+        let doc = file
+            .store()
+            .as_full()
+            .ok_or_else(|| "Rich-text file has no document".to_string())?;
+        pm_json_to_loro(doc, body)?;
+
+        // Setting the title also saves the document to IndexedDB
+        file.set_title(title).await?;
+
+        // Keep the files tree cache in step with the document
+        let cached = project
+            .get_collection::<T>(collection_name)?
+            .get_file(&file.id()?, collection_name)
+            .await?;
+        cached.set_title(title).await?;
+
+        Ok(file)
+    }
+
+    /// Create an asset file for imported bytes and save the bytes to IndexedDB
+    pub(super) async fn store_asset(
+        &self,
+        project: &mut Project,
+        asset: &ImportedAsset,
+    ) -> Result<Asset, String> {
+        // Refused before the asset exists, rather than failing mid-write
+        self.check_asset_quota(&asset.name, asset.data.len()).await?;
+        let builder =
+            project.create_file::<Asset>(&asset.name, "asset", FileStore::Cache(LoroMap::new()))?;
+        let id = project.attach_file(builder).await?.id()?;
+
+        // Asset metadata lives on the files tree, so write through the attached copy
+        let stored = project
+            .get_collection::<Asset>("asset")?
+            .get_file(&id, "asset")
+            .await?;
+        stored.set_mime_type(&asset.mime_type).await?;
+        stored.set_url(&asset_url(&id, &asset.name)).await?;
+
+        // SVGs lose their scripts before anything can embed them; one that
+        // can't be read is kept as uploaded, but flagged and never exported
+        let mut data = &asset.data[..];
+        let sanitized;
+        if asset.mime_type == SVG_MIME_TYPE {
+            match sanitize_bytes(&asset.data, project.settings()?.optimize_svg) {
+                Ok(clean) => {
+                    if !clean.removed.is_empty() {
+                        console_log!("Took out of {}: {}", asset.name, clean.removed.join(", "));
+                    }
+                    sanitized = clean.svg;
+                    data = sanitized.as_bytes();
+                    stored.set_field_value(SANITIZED_KEY, &json!(true)).await?;
+                }
+                Err(e) => stored.set_field(UNSAFE_KEY, &e).await?,
+            }
+        }
+
+        crate::save_data(IDB_ASSETS_STORE, &id, js_sys::Uint8Array::from(data))
+            .await
+            .map_err(|e| format!("Failed to save asset data: {:?}", e))?;
+
+        Ok(stored)
+    }
+}
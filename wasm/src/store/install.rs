@@ -0,0 +1,138 @@
+use serde_json::{json, Value};
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+
+use super::journal::Journal;
+use super::theme::theme_problems;
+use super::{StoreInner, IDB_ASSETS_STORE, IDB_FILES_STORE, IDB_PROJECTS_STORE};
+use crate::import::bundle::{self, Bundle};
+use crate::messages::Response;
+use crate::model::project::Project;
+use crate::types::ProjectType;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+
+    // The global fetch, so this works in windows, workers and Node alike
+    #[wasm_bindgen(js_name = fetch)]
+    fn fetch_with_str(input: &str) -> js_sys::Promise;
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Bundles must come over HTTPS, except from this machine during development
+fn check_bundle_url(url: &str) -> Result<(), String> {
+    if url.starts_with("https://") {
+        return Ok(());
+    }
+    let host = url
+        .strip_prefix("http://")
+        .and_then(|rest| rest.split(|c| c == '/' || c == ':').next());
+    match host {
+        Some("localhost") | Some("127.0.0.1") => Ok(()),
+        _ => Err(format!("Bundles must be fetched over https: {}", url)),
+    }
+}
+
+pub(super) async fn fetch_bytes(url: &str) -> Result<Vec<u8>, String> {
+    let response = JsFuture::from(fetch_with_str(url))
+        .await
+        .map_err(|e| format!("Request failed: {:?}", e))?;
+    let response: web_sys::Response = response
+        .dyn_into()
+        .map_err(|_| "fetch did not return a Response".to_string())?;
+    if !response.ok() {
+        return Err(format!("Server responded with {}", response.status()));
+    }
+
+    let buffer = response
+        .array_buffer()
+        .map_err(|e| format!("Failed to read response: {:?}", e))?;
+    let buffer = JsFuture::from(buffer)
+        .await
+        .map_err(|e| format!("Failed to read response: {:?}", e))?;
+    Ok(js_sys::Uint8Array::new(&buffer).to_vec())
+}
+
+pub(super) async fn save_bytes(store_name: &str, key: &str, bytes: &[u8]) -> Result<(), String> {
+    crate::save_data(store_name, key, js_sys::Uint8Array::from(bytes))
+        .await
+        .map_err(|e| format!("Failed to save {} {}: {:?}", store_name, key, e))
+}
+
+impl StoreInner {
+    /// ACTOR Download a theme or starter site bundle and make it active
+    ///
+    /// The bundle is verified against its manifest (and `sha256`, if given)
+    /// before anything is saved.
+    pub(super) async fn install_from_url(&self, url: String, sha256: Option<String>) -> Response {
+        console_log!("Installing bundle from {}", url);
+
+        match self.install_bundle(&url, sha256.as_deref()).await {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to install {}: {}", url, e)),
+        }
+    }
+
+    async fn install_bundle(&self, url: &str, sha256: Option<&str>) -> Result<Value, String> {
+        check_bundle_url(url)?;
+        let data = fetch_bytes(url).await?;
+        let Bundle {
+            manifest,
+            project,
+            files,
+            assets,
+        } = bundle::read_bundle(&data, sha256)?.with_fresh_ids()?;
+        let project_type = manifest.project_type()?;
+
+        let now = chrono::Utc::now().timestamp_millis() as f64;
+        let id = Uuid::new_v4().to_string();
+        let installed = Project::import(project, id.clone(), project_type.clone(), now, now)?;
+
+        // Files first, so the project never points at documents that aren't there
+        let mut journal = Journal::new("install bundle");
+        for (file_id, bytes) in &files {
+            journal.write(IDB_FILES_STORE, file_id, bytes.clone());
+        }
+        for (asset_id, bytes) in &assets {
+            journal.write(IDB_ASSETS_STORE, asset_id, bytes.clone());
+        }
+        journal.write(IDB_PROJECTS_STORE, &id, installed.export()?);
+        journal.commit().await?;
+        self.mark_saved(&installed);
+
+        // A new theme is checked against the site that will use it
+        let problems = match (&project_type, self.active_project(ProjectType::Site)) {
+            (ProjectType::Theme, Ok(site)) => theme_problems(&site, &installed).await?,
+            _ => Vec::new(),
+        };
+        match project_type {
+            ProjectType::Site => self.set_site(installed)?,
+            ProjectType::Theme => self.set_theme(installed)?,
+        }
+        console_log!(
+            "Installed {} {} ({} files, {} assets)",
+            manifest.project_type,
+            manifest.name,
+            files.len(),
+            assets.len()
+        );
+
+        Ok(json!({
+            "id": id,
+            "name": manifest.name,
+            "type": manifest.project_type,
+            "version": manifest.version,
+            "files": files.len(),
+            "assets": assets.len(),
+            "problems": problems,
+        }))
+    }
+}
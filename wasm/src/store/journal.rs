@@ -0,0 +1,200 @@
+//! Write-ahead journal for saves that span several keys.
+//!
+//! IndexedDB writes one key at a time, so a tab closed halfway through
+//! installing a bundle could leave a project pointing at documents that were
+//! never written. A journaled operation first writes one record holding
+//! every value it's about to save, then saves them, then clears the record.
+//! The record is a single put, so it's either whole or missing: if it's
+//! still there on startup, the operation is rolled forward by writing its
+//! values again. A record that can't be read (from a newer version, say) is
+//! dropped, leaving storage as it was.
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use super::install::save_bytes;
+use super::maintenance::load_bytes;
+use super::IDB_PROJECTS_STORE;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Where the pending operation's record is kept; it's empty when there's none
+pub const JOURNAL_KEY: &str = "journal";
+
+const JOURNAL_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct WriteHeader {
+    store: String,
+    key: String,
+    len: usize,
+}
+
+/// The JSON part of a record, followed in it by each write's bytes in order
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct JournalHeader {
+    version: u32,
+    operation: String,
+    /// RFC 3339
+    started_at: String,
+    writes: Vec<WriteHeader>,
+}
+
+/// One value a journaled operation saves
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct JournalWrite {
+    pub store: String,
+    pub key: String,
+    pub data: Vec<u8>,
+}
+
+/// The writes of one operation, saved together or not at all
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Journal {
+    pub operation: String,
+    pub started_at: String,
+    pub writes: Vec<JournalWrite>,
+}
+
+impl Journal {
+    pub fn new(operation: &str) -> Self {
+        Journal {
+            operation: operation.to_string(),
+            started_at: chrono::Utc::now().to_rfc3339(),
+            writes: Vec::new(),
+        }
+    }
+
+    pub fn write(&mut self, store: &str, key: &str, data: Vec<u8>) {
+        self.writes.push(JournalWrite {
+            store: store.to_string(),
+            key: key.to_string(),
+            data,
+        });
+    }
+
+    /// The record: header length (4 bytes, big-endian), header JSON, data
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let header = JournalHeader {
+            version: JOURNAL_VERSION,
+            operation: self.operation.clone(),
+            started_at: self.started_at.clone(),
+            writes: self
+                .writes
+                .iter()
+                .map(|write| WriteHeader {
+                    store: write.store.clone(),
+                    key: write.key.clone(),
+                    len: write.data.len(),
+                })
+                .collect(),
+        };
+        let header = serde_json::to_vec(&header).map_err(|e| e.to_string())?;
+
+        let mut record = Vec::with_capacity(
+            4 + header.len() + self.writes.iter().map(|w| w.data.len()).sum::<usize>(),
+        );
+        record.extend_from_slice(&(header.len() as u32).to_be_bytes());
+        record.extend_from_slice(&header);
+        for write in &self.writes {
+            record.extend_from_slice(&write.data);
+        }
+        Ok(record)
+    }
+
+    pub fn decode(record: &[u8]) -> Result<Journal, String> {
+        if record.len() < 4 {
+            return Err("Journal record is truncated".to_string());
+        }
+        let mut length = [0u8; 4];
+        length.copy_from_slice(&record[..4]);
+        let header_end = 4 + u32::from_be_bytes(length) as usize;
+        let header: JournalHeader = serde_json::from_slice(
+            record
+                .get(4..header_end)
+                .ok_or("Journal header is truncated")?,
+        )
+        .map_err(|e| format!("Invalid journal header: {}", e))?;
+        if header.version != JOURNAL_VERSION {
+            return Err(format!("Unknown journal version {}", header.version));
+        }
+
+        let mut offset = header_end;
+        let mut writes = Vec::new();
+        for write in header.writes {
+            let data = record
+                .get(offset..offset + write.len)
+                .ok_or_else(|| format!("Journal data for {} is truncated", write.key))?;
+            offset += write.len;
+            writes.push(JournalWrite {
+                store: write.store,
+                key: write.key,
+                data: data.to_vec(),
+            });
+        }
+        if offset != record.len() {
+            return Err("Journal record has trailing data".to_string());
+        }
+
+        Ok(Journal {
+            operation: header.operation,
+            started_at: header.started_at,
+            writes,
+        })
+    }
+
+    /// Record the operation, make its writes, then clear the record
+    pub async fn commit(self) -> Result<(), String> {
+        save_bytes(IDB_PROJECTS_STORE, JOURNAL_KEY, &self.encode()?).await?;
+        self.apply().await?;
+        clear().await
+    }
+
+    async fn apply(&self) -> Result<(), String> {
+        for write in &self.writes {
+            save_bytes(&write.store, &write.key, &write.data).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn clear() -> Result<(), String> {
+    save_bytes(IDB_PROJECTS_STORE, JOURNAL_KEY, &[]).await
+}
+
+/// Finish the operation a closed tab left pending, if there is one, and
+/// return it
+pub(super) async fn recover() -> Result<Option<Journal>, String> {
+    let record = match load_bytes(IDB_PROJECTS_STORE, JOURNAL_KEY).await? {
+        Some(record) if !record.is_empty() => record,
+        _ => return Ok(None),
+    };
+
+    match Journal::decode(&record) {
+        Ok(journal) => {
+            console_log!(
+                "Rolling forward {} from {} ({} writes)",
+                journal.operation,
+                journal.started_at,
+                journal.writes.len()
+            );
+            journal.apply().await?;
+            clear().await?;
+            Ok(Some(journal))
+        }
+        Err(e) => {
+            console_log!("Dropping unreadable journal: {}", e);
+            clear().await?;
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use chrono::{DateTime, Duration, Utc};
+use js_sys::Uint8Array;
+use loro::LoroDoc;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::journal::Journal;
+use super::{StoreInner, IDB_FILES_STORE, IDB_PROJECTS_STORE};
+use crate::messages::Response;
+use crate::model::file::File;
+use crate::model::project::{snapshot, Project};
+use crate::model::{Page, Partial, Post, Template, Text};
+use crate::types::{CompactionSchedule, ProjectType};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// A document's export from before its last compaction is kept in the same
+/// store, under its id with this prefix
+pub const BACKUP_PREFIX: &str = "backup:";
+
+pub(super) fn backup_key(id: &str) -> String {
+    format!("{}{}", BACKUP_PREFIX, id)
+}
+
+/// Whether `schedule` calls for a compaction, given when the last one ran
+pub(super) fn compaction_due(
+    schedule: &CompactionSchedule,
+    last: Option<&str>,
+    now: DateTime<Utc>,
+) -> bool {
+    let days = match schedule.every_days {
+        Some(days) => days,
+        None => return false,
+    };
+    match last.and_then(|last| DateTime::parse_from_rfc3339(last).ok()) {
+        Some(last) => now.signed_duration_since(last) >= Duration::days(days as i64),
+        None => true,
+    }
+}
+
+/// A stored document (in either format) as a snapshot
+pub(super) fn compact_bytes(data: &[u8], shallow: bool) -> Result<Vec<u8>, String> {
+    let doc = LoroDoc::new();
+    doc.import(data)
+        .map_err(|e| format!("Failed to import document: {}", e))?;
+    snapshot(&doc, shallow)
+}
+
+pub(super) async fn load_bytes(store_name: &str, key: &str) -> Result<Option<Vec<u8>>, String> {
+    let result = crate::load_data(store_name, key)
+        .await
+        .map_err(|e| format!("Failed to load {} {}: {:?}", store_name, key, e))?;
+    let value = JsValue::from(result);
+    if value.is_undefined() || value.is_null() {
+        return Ok(None);
+    }
+    Ok(Some(Uint8Array::from(value).to_vec()))
+}
+
+async fn file_ids<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+) -> Result<Vec<String>, String> {
+    let files = project
+        .get_collection::<T>(collection_name)?
+        .get_files(collection_name)
+        .await?;
+    files.iter().map(|file| file.id()).collect()
+}
+
+/// Ids of the files in a project that have their own stored document
+pub(super) async fn document_ids(project: &Project) -> Result<Vec<String>, String> {
+    let mut ids = Vec::new();
+    match project.project_type() {
+        ProjectType::Site => {
+            ids.extend(file_ids::<Page>(project, "page").await?);
+            ids.extend(file_ids::<Post>(project, "post").await?);
+        }
+        ProjectType::Theme => {
+            ids.extend(file_ids::<Template>(project, "template").await?);
+            ids.extend(file_ids::<Partial>(project, "partial").await?);
+            ids.extend(file_ids::<Text>(project, "text").await?);
+        }
+    }
+    Ok(ids)
+}
+
+/// Back up a stored document, then replace it with `compacted`
+async fn replace_document(
+    store_name: &str,
+    id: &str,
+    original: &[u8],
+    compacted: &[u8],
+) -> Result<Value, String> {
+    let mut journal = Journal::new("compact document");
+    journal.write(store_name, &backup_key(id), original.to_vec());
+    journal.write(store_name, id, compacted.to_vec());
+    journal.commit().await?;
+    Ok(json!({
+        "id": id,
+        "before": original.len(),
+        "after": compacted.len(),
+    }))
+}
+
+impl StoreInner {
+    /// ACTOR Compact the active site's and theme's documents into snapshots
+    pub(super) async fn compact_projects(&self, shallow: bool) -> Response {
+        console_log!("Compacting projects (shallow: {})", shallow);
+
+        match self.compact_active_projects(shallow).await {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to compact projects: {}", e)),
+        }
+    }
+
+    /// Compact if the site's schedule says it's time; runs after each save
+    pub(super) async fn compact_if_due(&self) -> Option<Value> {
+        let site = self.active_project(ProjectType::Site).ok()?;
+        let schedule = site.settings().ok()?.compaction;
+        if !compaction_due(&schedule, site.compacted_at().as_deref(), Utc::now()) {
+            return None;
+        }
+        match self.compact_active_projects(schedule.shallow).await {
+            Ok(result) => Some(result),
+            Err(e) => {
+                console_log!("Scheduled compaction failed: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn compact_active_projects(&self, shallow: bool) -> Result<Value, String> {
+        let now = Utc::now().to_rfc3339();
+        let mut documents = Vec::new();
+        let mut warnings = Vec::new();
+
+        for project_type in [ProjectType::Site, ProjectType::Theme].iter() {
+            let mut project = match self.active_project(project_type.clone()) {
+                Ok(project) => project,
+                Err(_) => continue,
+            };
+
+            for id in document_ids(&project).await? {
+                let original = match load_bytes(IDB_FILES_STORE, &id).await? {
+                    Some(original) if !original.is_empty() => original,
+                    // Files that were never edited have no document yet
+                    _ => continue,
+                };
+                match compact_bytes(&original, shallow) {
+                    Ok(compacted) => documents
+                        .push(replace_document(IDB_FILES_STORE, &id, &original, &compacted).await?),
+                    Err(e) => warnings.push(format!("{}: {}", id, e)),
+                }
+            }
+
+            project.set_compacted_at(&now)?;
+            let original = project.export()?;
+            let compacted = project.compact(shallow)?;
+            let id = project.id();
+            documents.push(replace_document(IDB_PROJECTS_STORE, &id, &original, &compacted).await?);
+            self.mark_saved(&project);
+            match project_type {
+                ProjectType::Site => self.set_site(project)?,
+                ProjectType::Theme => self.set_theme(project)?,
+            }
+        }
+        if documents.is_empty() {
+            return Err("No active site or theme".to_string());
+        }
+
+        // The projects have new documents, which the preview isn't watching
+        self.rewatch_preview().await;
+
+        let total = |key: &str| -> u64 {
+            documents
+                .iter()
+                .filter_map(|document| document[key].as_u64())
+                .sum()
+        };
+        Ok(json!({
+            "compacted_at": now,
+            "shallow": shallow,
+            "before": total("before"),
+            "after": total("after"),
+            "documents": documents,
+            "warnings": warnings,
+        }))
+    }
+}
@@ -0,0 +1,192 @@
+use serde_json::{json, Map, Value};
+use wasm_bindgen::prelude::*;
+
+use super::collections::RESERVED_FIELDS;
+use super::StoreInner;
+use crate::js_conversions::js_conversions;
+use crate::messages::Response;
+use crate::model::file::{File, ASSET_BYTES_KEYS};
+use crate::model::project::Project;
+use crate::model::{Asset, Entry, Page, Partial, Post, Template, Text};
+use crate::types::{FieldDefinition, FieldType, ProjectType};
+use crate::PM_SCHEMA_KEY;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// A `SetMeta` value, checked against the collection field of the same name
+///
+/// Keys the collection doesn't define take any value. Text is converted to
+/// the field's type, as front matter would be; `None` removes the key.
+pub(super) fn check_meta_value(
+    key: &str,
+    value: &Value,
+    field: Option<&FieldDefinition>,
+) -> Result<Option<Value>, String> {
+    if key == PM_SCHEMA_KEY || RESERVED_FIELDS.contains(&key) {
+        return Err(format!("{} can't be set as metadata", key));
+    }
+    let field = match field {
+        Some(field) => field,
+        None if value.is_null() => return Ok(None),
+        None => return Ok(Some(value.clone())),
+    };
+
+    let fits = match (field.field_type, value) {
+        (FieldType::RichText, _) | (FieldType::Blob, _) => {
+            return Err(format!("{} can't be set as metadata", key))
+        }
+        (_, Value::Null) if field.required => return Err(format!("{} is required", key)),
+        (_, Value::Null) => return Ok(None),
+        (field_type, Value::String(raw)) => {
+            return field_type
+                .coerce(raw)
+                .map(Some)
+                .map_err(|e| format!("{}: {}", key, e))
+        }
+        (FieldType::Number, value) => value.is_number(),
+        (FieldType::List, value) | (FieldType::Array, value) => value.is_array(),
+        (FieldType::Map, value) | (FieldType::Object, value) => value.is_object(),
+        _ => false,
+    };
+    if fits {
+        Ok(Some(value.clone()))
+    } else {
+        Err(format!(
+            "{} should be a {}",
+            key,
+            field.field_type.to_string()
+        ))
+    }
+}
+
+/// A file's metadata without the keys the store manages
+fn user_meta<T: File>(file: &T) -> Map<String, Value> {
+    file.meta_fields()
+        .into_iter()
+        .filter(|(key, _)| key != PM_SCHEMA_KEY && !RESERVED_FIELDS.contains(&key.as_str()))
+        .collect()
+}
+
+/// Apply `updates`, if any, to a file's metadata, all or nothing, and read
+/// it back along with the fields its collection defines
+async fn file_meta<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+    file_id: &str,
+    updates: Option<&Map<String, Value>>,
+) -> Result<Value, String> {
+    let collection = project.get_collection::<T>(collection_name)?;
+    let fields = collection.get_fields()?;
+    // The files tree holds the metadata templates see
+    let file = collection.get_file(file_id, collection_name).await?;
+
+    if let Some(updates) = updates {
+        let checked = updates
+            .iter()
+            .map(|(key, value)| {
+                let field = fields.iter().find(|field| &field.name == key);
+                Ok((key, check_meta_value(key, value, field)?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        for (key, value) in checked {
+            match value {
+                Some(value) => file.set_field_value(key, &value).await?,
+                None => file.meta().delete(key).map_err(|e| e.to_string())?,
+            }
+        }
+    }
+
+    Ok(json!({
+        "fileId": file_id,
+        "meta": user_meta(&file),
+        "fields": fields.into_iter().map(|field| field.into()).collect::<Vec<Value>>(),
+    }))
+}
+
+async fn collection_file_meta(
+    project: &Project,
+    collection_name: &str,
+    file_id: &str,
+    updates: Option<&Map<String, Value>>,
+) -> Result<Value, String> {
+    match collection_name {
+        "page" | "snippet" | "skeleton" => {
+            file_meta::<Page>(project, collection_name, file_id, updates).await
+        }
+        "post" => file_meta::<Post>(project, collection_name, file_id, updates).await,
+        "asset" => {
+            // The bytes are fixed once stored, so what describes them is too
+            let bytes_key = updates
+                .into_iter()
+                .flat_map(|updates| updates.keys())
+                .find(|key| ASSET_BYTES_KEYS.contains(&key.as_str()));
+            if let Some(key) = bytes_key {
+                return Err(format!("{} is set from the asset's bytes", key));
+            }
+            file_meta::<Asset>(project, collection_name, file_id, updates).await
+        }
+        "template" => file_meta::<Template>(project, collection_name, file_id, updates).await,
+        "partial" => file_meta::<Partial>(project, collection_name, file_id, updates).await,
+        "text" => file_meta::<Text>(project, collection_name, file_id, updates).await,
+        _ => file_meta::<Entry>(project, collection_name, file_id, updates).await,
+    }
+}
+
+impl StoreInner {
+    /// Set or remove custom metadata on a file, for `FileUpdate::SetMeta`
+    pub(super) async fn set_meta(
+        &self,
+        project_type: ProjectType,
+        collection_name: &str,
+        file_id: &str,
+        updates: &Map<String, Value>,
+    ) -> Response {
+        let result = match self.active_project(project_type.clone()) {
+            Ok(project) => {
+                collection_file_meta(&project, collection_name, file_id, Some(updates)).await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(meta) => Response::success(json!({
+                "status": "updated",
+                "project_type": project_type,
+                "meta": meta["meta"],
+            })),
+            Err(e) => Response::error(&format!("Failed to set metadata: {}", e)),
+        }
+    }
+
+    /// ACTOR A file's metadata, front-matter style
+    ///
+    /// Every key templates can read except the ones the store manages, and
+    /// the fields the collection defines, which `SetMeta` values must fit.
+    pub(super) async fn get_meta(
+        &self,
+        project_type: String,
+        collection_name: String,
+        file_id: String,
+    ) -> Response {
+        console_log!("Getting metadata of {} in {}", file_id, collection_name);
+
+        let result = match js_conversions::string_to_project_type(&project_type)
+            .and_then(|project_type| self.active_project(project_type))
+        {
+            Ok(project) => collection_file_meta(&project, &collection_name, &file_id, None).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(meta) => Response::success(meta),
+            Err(e) => Response::error(&format!("Failed to get metadata: {}", e)),
+        }
+    }
+}
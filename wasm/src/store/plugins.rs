@@ -0,0 +1,300 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, RenderContext, RenderError, RenderErrorReason,
+    ScopedJson,
+};
+use serde_json::{json, Map, Value};
+use wasm_bindgen::prelude::*;
+
+use super::StoreInner;
+use crate::messages::Response;
+use crate::render::hooks::RenderHooks;
+use crate::render::BUILT_IN_HELPERS;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Called with `{url, context}`; returns the context to render with
+pub const BEFORE_RENDER_PAGE: &str = "beforeRenderPage";
+/// Called with `{url, html}`; returns the HTML to write
+pub const AFTER_RENDER_PAGE: &str = "afterRenderPage";
+/// Called with `{files}` (path → contents); returns the files to output
+pub const ON_EXPORT: &str = "onExport";
+
+const HOOKS: [&str; 3] = [BEFORE_RENDER_PAGE, AFTER_RENDER_PAGE, ON_EXPORT];
+
+// JS functions can't cross threads, so helpers (which Handlebars requires to
+// be Send + Sync) look their callback up here by name when they're called.
+// wasm has a single thread, so this is the only copy.
+thread_local! {
+    static JS_HELPERS: RefCell<Vec<Registration>> = RefCell::new(Vec::new());
+}
+
+/// A JS callback registered by a plugin under a hook or helper name
+struct Registration {
+    plugin: String,
+    name: String,
+    callback: js_sys::Function,
+}
+
+/// JS functions registered against render hooks and as template helpers
+///
+/// Hooks run in registration order, each receiving the previous one's
+/// result. Returning `undefined` leaves the value unchanged.
+#[derive(Clone, Default)]
+pub struct PluginRegistry {
+    hooks: Arc<Mutex<Vec<Registration>>>,
+}
+
+impl PluginRegistry {
+    pub fn register(
+        &self,
+        plugin: &str,
+        hook: &str,
+        callback: js_sys::Function,
+    ) -> Result<(), String> {
+        if !HOOKS.contains(&hook) {
+            return Err(format!(
+                "Unknown hook: {} (expected one of {})",
+                hook,
+                HOOKS.join(", ")
+            ));
+        }
+        self.hooks.lock().unwrap().push(Registration {
+            plugin: plugin.to_string(),
+            name: hook.to_string(),
+            callback,
+        });
+        Ok(())
+    }
+
+    /// Register a template helper, called with its params and hash
+    ///
+    /// The callback receives `(params, hash)` as JSON-compatible values and
+    /// returns the helper's value; registering a name again replaces it.
+    pub fn register_helper(
+        &self,
+        plugin: &str,
+        name: &str,
+        callback: js_sys::Function,
+    ) -> Result<(), String> {
+        if BUILT_IN_HELPERS.contains(&name) {
+            return Err(format!("Can't replace built-in helper: {}", name));
+        }
+        JS_HELPERS.with(|helpers| {
+            let mut helpers = helpers.borrow_mut();
+            helpers.retain(|helper| helper.name != name);
+            helpers.push(Registration {
+                plugin: plugin.to_string(),
+                name: name.to_string(),
+                callback,
+            });
+        });
+        Ok(())
+    }
+
+    /// Remove every hook and helper a plugin registered
+    pub fn unregister(&self, plugin: &str) {
+        self.hooks
+            .lock()
+            .unwrap()
+            .retain(|hook| hook.plugin != plugin);
+        JS_HELPERS.with(|helpers| {
+            helpers
+                .borrow_mut()
+                .retain(|helper| helper.plugin != plugin)
+        });
+    }
+
+    /// Plugin name → `{hooks, helpers}` it has registered
+    pub fn to_json(&self) -> Value {
+        let mut plugins: BTreeMap<String, (Vec<String>, Vec<String>)> = BTreeMap::new();
+        for hook in self.hooks.lock().unwrap().iter() {
+            plugins
+                .entry(hook.plugin.clone())
+                .or_default()
+                .0
+                .push(hook.name.clone());
+        }
+        JS_HELPERS.with(|helpers| {
+            for helper in helpers.borrow().iter() {
+                plugins
+                    .entry(helper.plugin.clone())
+                    .or_default()
+                    .1
+                    .push(helper.name.clone());
+            }
+        });
+
+        let plugins: Map<String, Value> = plugins
+            .into_iter()
+            .map(|(plugin, (hooks, helpers))| {
+                (plugin, json!({ "hooks": hooks, "helpers": helpers }))
+            })
+            .collect();
+        Value::Object(plugins)
+    }
+
+    /// Plugin name and callback for each registration of `hook`
+    fn callbacks(&self, hook: &str) -> Vec<(String, js_sys::Function)> {
+        // Copied out so callbacks can register or unregister plugins themselves
+        self.hooks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|registered| registered.name == hook)
+            .map(|registered| (registered.plugin.clone(), registered.callback.clone()))
+            .collect()
+    }
+
+    /// Run callbacks in order, threading `field` of the payload through them
+    fn call(
+        &self,
+        callbacks: &[(String, js_sys::Function)],
+        mut payload: Value,
+        field: &str,
+    ) -> Result<Value, String> {
+        for (plugin, callback) in callbacks {
+            let argument = js_sys::JSON::parse(&payload.to_string())
+                .map_err(|e| format!("plugin {}: {:?}", plugin, e))?;
+            let result = callback
+                .call1(&JsValue::null(), &argument)
+                .map_err(|e| format!("plugin {}: {:?}", plugin, e))?;
+            if result.is_undefined() {
+                continue;
+            }
+            let result: String = js_sys::JSON::stringify(&result)
+                .map_err(|e| format!("plugin {}: {:?}", plugin, e))?
+                .into();
+            payload[field] =
+                serde_json::from_str(&result).map_err(|e| format!("plugin {}: {}", plugin, e))?;
+        }
+        Ok(payload[field].take())
+    }
+}
+
+/// A template helper implemented by a registered JS function
+struct JsHelper {
+    name: String,
+}
+
+impl JsHelper {
+    fn call_js(&self, params: Value, hash: Value) -> Result<Value, String> {
+        let callback = JS_HELPERS.with(|helpers| {
+            helpers
+                .borrow()
+                .iter()
+                .find(|helper| helper.name == self.name)
+                .map(|helper| helper.callback.clone())
+        });
+        let callback = callback.ok_or_else(|| format!("helper {} is not registered", self.name))?;
+
+        let to_js = |value: &Value| {
+            js_sys::JSON::parse(&value.to_string()).map_err(|e| format!("{:?}", e))
+        };
+        let result = callback
+            .call2(&JsValue::null(), &to_js(&params)?, &to_js(&hash)?)
+            .map_err(|e| format!("{:?}", e))?;
+        if result.is_undefined() || result.is_null() {
+            return Ok(Value::Null);
+        }
+        let result: String = js_sys::JSON::stringify(&result)
+            .map_err(|e| format!("{:?}", e))?
+            .into();
+        serde_json::from_str(&result).map_err(|e| e.to_string())
+    }
+}
+
+impl HelperDef for JsHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+    ) -> Result<ScopedJson<'rc>, RenderError> {
+        let params: Vec<Value> = h.params().iter().map(|param| param.value().clone()).collect();
+        let hash: Map<String, Value> = h
+            .hash()
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.value().clone()))
+            .collect();
+
+        match self.call_js(json!(params), Value::Object(hash)) {
+            Ok(value) => Ok(ScopedJson::Derived(value)),
+            Err(e) => Err(RenderErrorReason::Other(format!("helper {}: {}", self.name, e)).into()),
+        }
+    }
+}
+
+impl RenderHooks for PluginRegistry {
+    fn helpers(&self) -> Vec<(String, Box<dyn HelperDef + Send + Sync>)> {
+        JS_HELPERS.with(|helpers| {
+            helpers
+                .borrow()
+                .iter()
+                .map(|helper| {
+                    let js_helper = JsHelper {
+                        name: helper.name.clone(),
+                    };
+                    (
+                        helper.name.clone(),
+                        Box::new(js_helper) as Box<dyn HelperDef + Send + Sync>,
+                    )
+                })
+                .collect()
+        })
+    }
+
+    fn before_render_page(&self, url: &str, context: Value) -> Result<Value, String> {
+        let callbacks = self.callbacks(BEFORE_RENDER_PAGE);
+        if callbacks.is_empty() {
+            return Ok(context);
+        }
+        let payload = json!({ "url": url, "context": context });
+        self.call(&callbacks, payload, "context")
+    }
+
+    fn after_render_page(&self, url: &str, html: String) -> Result<String, String> {
+        let callbacks = self.callbacks(AFTER_RENDER_PAGE);
+        if callbacks.is_empty() {
+            return Ok(html);
+        }
+        let payload = json!({ "url": url, "html": html });
+        match self.call(&callbacks, payload, "html")? {
+            Value::String(html) => Ok(html),
+            other => Err(format!("{} must return a string, got {}", AFTER_RENDER_PAGE, other)),
+        }
+    }
+
+    fn on_export(&self, files: &mut BTreeMap<String, String>) -> Result<(), String> {
+        let callbacks = self.callbacks(ON_EXPORT);
+        if callbacks.is_empty() {
+            return Ok(());
+        }
+        let payload = json!({ "files": files });
+        let result = self.call(&callbacks, payload, "files")?;
+        *files = serde_json::from_value(result)
+            .map_err(|e| format!("{} must return path → string: {}", ON_EXPORT, e))?;
+        Ok(())
+    }
+}
+
+impl StoreInner {
+    /// ACTOR List registered plugins and their hooks
+    pub(super) fn list_plugins(&self) -> Response {
+        console_log!("Listing plugins");
+        Response::success(self.plugins.to_json())
+    }
+}
@@ -0,0 +1,215 @@
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use loro::event::Subscriber;
+use loro::Subscription;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+use super::StoreInner;
+use crate::messages::Response;
+use crate::model::file::File;
+use crate::model::project::Project;
+use crate::model::{Page, Post};
+use crate::render::output_path;
+use crate::render::permalinks::patterned_url;
+use crate::render::site::PageSource;
+use crate::types::{ExportFilter, ProjectType};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &js_sys::Function, timeout: i32) -> JsValue;
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Emitted with `{file_id, url, html, warnings}` after a watched page re-renders
+pub const PREVIEW_UPDATED_EVENT: &str = "preview:updated";
+/// Emitted with `{file_id, error}` when a watched page can't be rendered
+pub const PREVIEW_ERROR_EVENT: &str = "preview:error";
+
+/// How long edits must pause before the preview re-renders
+const PREVIEW_DEBOUNCE_MS: i32 = 300;
+
+/// The page being previewed, and the subscriptions that keep it fresh
+pub struct PreviewWatch {
+    file_id: String,
+    running: Arc<AtomicBool>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl Drop for PreviewWatch {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+    }
+}
+
+async fn sleep(ms: i32) {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        set_timeout(&resolve, ms);
+    });
+    let _ = JsFuture::from(promise).await;
+}
+
+/// Subscriber that counts committed changes
+fn change_counter(changes: &Arc<AtomicU32>) -> Subscriber {
+    let changes = changes.clone();
+    Arc::new(move |_| {
+        changes.fetch_add(1, Ordering::Relaxed);
+    })
+}
+
+/// Metadata of a file in a rich-text collection, by id
+async fn find_page_source<T: File + Default>(
+    site: &Project,
+    collection_name: &str,
+    file_id: &str,
+) -> Result<Option<PageSource>, String> {
+    let files = site
+        .get_collection::<T>(collection_name)?
+        .get_files(collection_name)
+        .await?;
+    for file in files {
+        if file.id()? == file_id {
+            return Ok(Some(PageSource {
+                collection: collection_name.to_string(),
+                name: file.name()?,
+                meta: file.meta_fields(),
+                body: Value::Null,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+impl StoreInner {
+    /// ACTOR Re-render a page or post whenever the site or theme changes
+    ///
+    /// Responds with the current preview, then emits `preview:updated` once
+    /// edits have paused. Watching another file replaces this watch.
+    pub(super) async fn watch_preview(&self, file_id: String) -> Response {
+        console_log!("Watching preview for {}", file_id);
+
+        let preview = match self.render_preview(&file_id).await {
+            Ok(preview) => preview,
+            Err(e) => return Response::error(&format!("Failed to render preview: {}", e)),
+        };
+
+        let changes = Arc::new(AtomicU32::new(0));
+        let running = Arc::new(AtomicBool::new(true));
+        let subscriptions = [ProjectType::Site, ProjectType::Theme]
+            .iter()
+            .filter_map(|project_type| self.active_project(project_type.clone()).ok())
+            .map(|project| project.subscribe(change_counter(&changes)))
+            .collect();
+
+        *self.preview_watch.lock().unwrap() = Some(PreviewWatch {
+            file_id: file_id.clone(),
+            running: running.clone(),
+            _subscriptions: subscriptions,
+        });
+
+        let store = self.clone();
+        wasm_bindgen_futures::spawn_local(async move {
+            let mut seen = 0;
+            let mut rendered = 0;
+            while running.load(Ordering::Relaxed) {
+                sleep(PREVIEW_DEBOUNCE_MS).await;
+                let current = changes.load(Ordering::Relaxed);
+                // Wait until a whole interval passes without edits
+                if current != seen {
+                    seen = current;
+                    continue;
+                }
+                if current == rendered || !running.load(Ordering::Relaxed) {
+                    continue;
+                }
+                rendered = current;
+                store.emit_preview(&file_id).await;
+            }
+        });
+
+        Response::success(preview)
+    }
+
+    /// ACTOR Stop re-rendering the watched preview
+    pub(super) fn unwatch_preview(&self) -> Response {
+        match self.preview_watch.lock().unwrap().take() {
+            Some(watch) => console_log!("Stopped watching preview for {}", watch.file_id),
+            None => console_log!("No preview being watched"),
+        }
+        Response::success(true)
+    }
+
+    /// The page whose preview is being watched, if any
+    pub(super) fn watched_file(&self) -> Option<String> {
+        self.preview_watch
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|watch| watch.file_id.clone())
+    }
+
+    /// Watch the same page again, after the projects' documents were replaced
+    pub(super) async fn rewatch_preview(&self) {
+        let file_id = match self.preview_watch.lock().unwrap().as_ref() {
+            Some(watch) => watch.file_id.clone(),
+            None => return,
+        };
+        if let Response::Error(e) = self.watch_preview(file_id).await {
+            console_log!("Failed to watch preview again: {}", e);
+        }
+    }
+
+    async fn emit_preview(&self, file_id: &str) {
+        match self.render_preview(file_id).await {
+            Ok(preview) => self
+                .events
+                .emit(PREVIEW_UPDATED_EVENT, JsValue::from_str(&preview.to_string())),
+            Err(e) => self.events.emit(
+                PREVIEW_ERROR_EVENT,
+                JsValue::from_str(&json!({ "file_id": file_id, "error": e }).to_string()),
+            ),
+        }
+    }
+
+    /// Render the site and pick out one page or post
+    async fn render_preview(&self, file_id: &str) -> Result<Value, String> {
+        let site = self.active_project(ProjectType::Site)?;
+        let page = match find_page_source::<Page>(&site, "page", file_id).await? {
+            Some(page) => page,
+            None => find_page_source::<Post>(&site, "post", file_id)
+                .await?
+                .ok_or_else(|| format!("No page or post with id {}", file_id))?,
+        };
+
+        let url = patterned_url(&site.settings()?.url_patterns, &page);
+        let output = self.build_site_output(None, &ExportFilter::default()).await?;
+        let html = match output.files.get(&output_path(&url)) {
+            Some(html) => html.clone(),
+            None => {
+                return Err(format!(
+                    "{} {} was not rendered: {}",
+                    page.collection,
+                    page.name,
+                    output.warnings.join("; ")
+                ))
+            }
+        };
+
+        Ok(json!({
+            "file_id": file_id,
+            "url": url,
+            "html": html,
+            "warnings": output.warnings,
+        }))
+    }
+}
@@ -0,0 +1,82 @@
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::render::check_strict;
+use super::StoreInner;
+use crate::messages::Response;
+use crate::render::manifest::{output_hashes, publish_diff};
+use crate::types::{DeployRecord, ExportFilter, ProjectType};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Deploy target used when the frontend doesn't name one
+pub(super) const DEFAULT_DEPLOY_TARGET: &str = "default";
+
+impl StoreInner {
+    /// ACTOR What would be uploaded if the site were deployed now
+    pub(super) async fn get_publish_diff(&self, target: Option<String>) -> Response {
+        let target = target.unwrap_or_else(|| DEFAULT_DEPLOY_TARGET.to_string());
+        console_log!("Getting publish diff for target: {}", target);
+
+        let result = async {
+            let output = self.build_site_export(None, &ExportFilter::default()).await?;
+            let site = self.active_project(ProjectType::Site)?;
+            let last_deploy = site.last_deploy(&target)?;
+            let previous = last_deploy
+                .as_ref()
+                .map(|record| record.hashes.clone())
+                .unwrap_or_default();
+
+            let mut diff = publish_diff(&previous, &output_hashes(&output));
+            diff["target"] = json!(target);
+            diff["last_deployed_at"] = json!(last_deploy.map(|record| record.deployed_at));
+            diff["warnings"] = json!(output.warnings);
+            diff["errors"] = json!(output.errors);
+            Ok::<_, String>(diff)
+        }
+        .await;
+
+        match result {
+            Ok(diff) => Response::success(diff),
+            Err(e) => Response::error(&format!("Failed to get publish diff: {}", e)),
+        }
+    }
+
+    /// ACTOR Record the current output hashes as deployed to a target
+    pub(super) async fn record_deploy(&self, target: Option<String>) -> Response {
+        let target = target.unwrap_or_else(|| DEFAULT_DEPLOY_TARGET.to_string());
+        console_log!("Recording deploy for target: {}", target);
+
+        let result = async {
+            let output = self.build_site_export(None, &ExportFilter::default()).await?;
+            check_strict(&output)?;
+            let site = self.active_project(ProjectType::Site)?;
+            let record = DeployRecord {
+                deployed_at: chrono::Utc::now().to_rfc3339(),
+                hashes: output_hashes(&output),
+            };
+            site.record_deploy(&target, &record)?;
+            self.set_site(site)?;
+            Ok::<_, String>(record)
+        }
+        .await;
+
+        match result {
+            Ok(record) => Response::success(json!({
+                "target": target,
+                "deployed_at": record.deployed_at,
+                "files": record.hashes.len(),
+            })),
+            Err(e) => Response::error(&format!("Failed to record deploy: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Staying inside the browser's storage quota.
+//!
+//! IndexedDB fails a write that doesn't fit, possibly halfway through an
+//! upload. Once usage passes `NEAR_QUOTA_RATIO` of the quota the store
+//! emits `storage:quota`, saves projects as snapshots without their edit
+//! history, and refuses new assets over `LARGE_ASSET_BYTES`. An asset
+//! that wouldn't fit at all is refused whatever its size.
+
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::StoreInner;
+use crate::messages::Response;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+pub const STORAGE_QUOTA_EVENT: &str = "storage:quota";
+
+/// Share of the quota past which storage is nearly full
+pub const NEAR_QUOTA_RATIO: f64 = 0.9;
+
+/// Assets larger than this aren't accepted while storage is nearly full
+pub const LARGE_ASSET_BYTES: usize = 1024 * 1024;
+
+/// What the browser reports of the origin's storage, in bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct StorageEstimate {
+    pub usage: f64,
+    pub quota: f64,
+}
+
+impl StorageEstimate {
+    pub fn ratio(&self) -> f64 {
+        if self.quota > 0.0 {
+            self.usage / self.quota
+        } else {
+            1.0
+        }
+    }
+
+    pub fn is_near_quota(&self) -> bool {
+        self.ratio() >= NEAR_QUOTA_RATIO
+    }
+
+    /// Why an asset of `bytes` can't be stored, if it can't
+    pub fn refuse_asset(&self, name: &str, bytes: usize) -> Option<String> {
+        let size = bytes as f64;
+        if self.usage + size > self.quota {
+            return Some(format!(
+                "{} ({}) won't fit: {} of {} storage is free",
+                name,
+                megabytes(size),
+                megabytes((self.quota - self.usage).max(0.0)),
+                megabytes(self.quota)
+            ));
+        }
+        if self.is_near_quota() && bytes > LARGE_ASSET_BYTES {
+            return Some(format!(
+                "Storage is {:.0}% full, so {} ({}) wasn't added; \
+                 free some space or add files under {}",
+                self.ratio() * 100.0,
+                name,
+                megabytes(size),
+                megabytes(LARGE_ASSET_BYTES as f64)
+            ));
+        }
+        None
+    }
+}
+
+fn megabytes(bytes: f64) -> String {
+    format!("{:.1} MB", bytes / (1024.0 * 1024.0))
+}
+
+impl StoreInner {
+    /// The origin's storage, or None where the browser can't say
+    ///
+    /// Storage that's nearly full is reported to `storage:quota` listeners
+    /// each time it's checked.
+    pub(super) async fn storage_estimate(&self) -> Option<StorageEstimate> {
+        let (usage, quota) = match crate::storage_estimate().await {
+            Ok(estimate) => estimate?,
+            Err(e) => {
+                console_log!("Failed to estimate storage: {:?}", e);
+                return None;
+            }
+        };
+        let estimate = StorageEstimate { usage, quota };
+        if estimate.is_near_quota() {
+            let payload = json!({
+                "usage": usage,
+                "quota": quota,
+                "ratio": estimate.ratio(),
+            });
+            self.events
+                .emit(STORAGE_QUOTA_EVENT, JsValue::from_str(&payload.to_string()));
+        }
+        Some(estimate)
+    }
+
+    /// An error if an asset of `bytes` shouldn't be written now
+    pub(super) async fn check_asset_quota(&self, name: &str, bytes: usize) -> Result<(), String> {
+        match self.storage_estimate().await {
+            Some(estimate) => match estimate.refuse_asset(name, bytes) {
+                Some(reason) => Err(reason),
+                None => Ok(()),
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// ACTOR Storage used and available, and whether saving has switched
+    /// to snapshots to save space
+    pub(super) async fn get_storage_estimate(&self) -> Response {
+        match self.storage_estimate().await {
+            Some(estimate) => Response::success(json!({
+                "supported": true,
+                "usage": estimate.usage,
+                "quota": estimate.quota,
+                "ratio": estimate.ratio(),
+                "near_quota": estimate.is_near_quota(),
+                "snapshot_only": estimate.is_near_quota(),
+            })),
+            None => Response::success(json!({ "supported": false })),
+        }
+    }
+}
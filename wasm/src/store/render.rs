@@ -0,0 +1,694 @@
+use std::collections::{BTreeMap, HashMap};
+
+use js_sys::Uint8Array;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::collections::is_built_in_collection;
+use super::delete::is_trashed;
+use super::forms::load_forms;
+use super::imports::asset_url;
+use super::snippets::load_blocks;
+use super::{StoreInner, IDB_ASSETS_STORE};
+use crate::import::images::transformed;
+use crate::import::svg::{sanitize_bytes, SVG_MIME_TYPE};
+use crate::messages::Response;
+use crate::model::file::{loro_doc_to_pm_doc, File, HasContent, HasMimeType};
+use crate::model::project::Project;
+use crate::model::{Asset, Entry, Folder, Page, Partial, Post, Template, Text};
+use crate::render::block_refs::resolve_all_block_refs;
+use crate::render::css::{css_report, theme_variables};
+use crate::render::gemtext::site_to_gemtext;
+use crate::render::graph::content_graph;
+use crate::render::links::{backlinks_index, resolve_internal};
+use crate::render::manifest::{mime_type_for_path, preview_manifest};
+use crate::render::partial::{filter_source, prune_assets};
+use crate::render::permalinks::apply_url_patterns;
+use crate::render::protected::page_password;
+use crate::render::single_file::{single_file_html, SingleFileOptions, DEFAULT_MAX_INLINE_BYTES};
+use crate::render::site::{self, CollectionSource, PageSource, SiteAsset, SiteOutput, SiteSource};
+use crate::render::site_zip::site_zip;
+use crate::render::slugify;
+use crate::render::template_ast::parse_template;
+use crate::render::urls::normalize_base_path;
+use crate::render::usage::template_usage;
+use crate::render::validate::{validate_site, Severity};
+use crate::render::wikilinks::resolve_all_wikilinks;
+use crate::types::{ExportFilter, ProjectType, Strictness, UrlStrategy};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Load a file's full document from IndexedDB
+pub(super) async fn load_full_file<T: File + Default>(
+    collection_name: &str,
+    id: &str,
+) -> Result<T, String> {
+    let mut builder = T::builder_for(collection_name);
+    builder.id = Some(id.to_string());
+    let file = builder.build().await?;
+    if file.store().is_cache() {
+        return Err(format!("No stored document for {} {}", collection_name, id));
+    }
+    Ok(file)
+}
+
+/// Name and plain-text content of every file in a theme collection
+async fn load_text_files<T: File + Default + HasContent>(
+    project: &Project,
+    collection_name: &str,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<(String, String)>, String> {
+    let cached = project
+        .get_collection::<T>(collection_name)?
+        .get_files(collection_name)
+        .await?;
+
+    let mut files = Vec::new();
+    for file in cached {
+        let name = file.name()?;
+        let content = match load_full_file::<T>(collection_name, &file.id()?).await {
+            Ok(full) => full.get_content(),
+            Err(e) => Err(e),
+        };
+        match content {
+            Ok(content) => files.push((name, content)),
+            Err(e) => warnings.push(format!("{} {}: {}", collection_name, name, e)),
+        }
+    }
+    Ok(files)
+}
+
+/// Metadata and body of every file in a rich-text collection
+pub(super) async fn load_pages<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<PageSource>, String> {
+    let cached = project
+        .get_collection::<T>(collection_name)?
+        .get_files(collection_name)
+        .await?;
+
+    let mut pages = Vec::new();
+    for file in cached {
+        let name = file.name()?;
+        // The files tree holds the latest metadata; the document holds the body
+        let body = match load_full_file::<T>(collection_name, &file.id()?).await {
+            Ok(full) => match full.store().as_full() {
+                Some(doc) => loro_doc_to_pm_doc(doc),
+                None => Err("Missing document".to_string()),
+            },
+            Err(e) => Err(e),
+        };
+        let body = match body {
+            Ok(body) => body,
+            Err(e) => {
+                warnings.push(format!("{} {}: {}", collection_name, name, e));
+                json!({ "type": "doc", "content": [] })
+            }
+        };
+
+        pages.push(PageSource {
+            collection: collection_name.to_string(),
+            name,
+            meta: file.meta_fields(),
+            body,
+        });
+    }
+    Ok(pages)
+}
+
+/// Source of a theme template or partial, whichever has the id
+async fn theme_file_content(theme: &Project, file_id: &str) -> Result<String, String> {
+    let templates = theme.get_collection::<Template>("template")?;
+    if templates.get_file(file_id, "template").await.is_ok() {
+        return load_full_file::<Template>("template", file_id).await?.get_content();
+    }
+    let partials = theme.get_collection::<Partial>("partial")?;
+    if partials.get_file(file_id, "partial").await.is_ok() {
+        return load_full_file::<Partial>("partial", file_id).await?.get_content();
+    }
+    Err(format!("No template or partial with id {}", file_id))
+}
+
+/// Folder id → its path under `assets/`, from the slugs of its folders' names
+fn folder_paths(folders: &[Folder]) -> HashMap<String, String> {
+    let mut paths = HashMap::new();
+    for folder in folders {
+        let mut names = Vec::new();
+        let mut current = Some(folder);
+        while let Some(folder) = current {
+            let slug = slugify(&folder.name);
+            names.push(if slug.is_empty() { folder.id.clone() } else { slug });
+            current = folder
+                .parent
+                .as_ref()
+                .and_then(|parent| folders.iter().find(|f| f.id == *parent));
+        }
+        names.reverse();
+        paths.insert(folder.id.clone(), names.join("/"));
+    }
+    paths
+}
+
+/// Point links at assets filed in folders to where the export puts them
+///
+/// Content links to `/assets/{id}/{name}`, which stays the same wherever
+/// an asset is moved to.
+fn relocate_asset_links(output: &mut SiteOutput) {
+    let moves: Vec<(String, String)> = output
+        .assets
+        .iter()
+        .filter_map(|asset| {
+            let (dir, _name) = asset.path.strip_prefix("assets/")?.rsplit_once('/')?;
+            let (folder, id) = dir.rsplit_once('/')?;
+            Some((format!("assets/{}/", id), format!("assets/{}/{}/", folder, id)))
+        })
+        .collect();
+    if moves.is_empty() {
+        return;
+    }
+    for contents in output.files.values_mut() {
+        for (from, to) in &moves {
+            if contents.contains(from.as_str()) {
+                *contents = contents.replace(from.as_str(), to);
+            }
+        }
+    }
+}
+
+/// Refuse to export output the site's strict profile found problems in
+pub(super) fn check_strict(output: &SiteOutput) -> Result<(), String> {
+    if output.errors.is_empty() {
+        return Ok(());
+    }
+    let problems: Vec<&str> = output
+        .errors
+        .iter()
+        .map(|issue| issue.message.as_str())
+        .collect();
+    Err(format!(
+        "strict mode blocks the export: {}",
+        problems.join("; ")
+    ))
+}
+
+/// Bytes of every uploaded asset in a project, at its published path
+async fn load_assets(
+    project: &Project,
+    warnings: &mut Vec<String>,
+) -> Result<Vec<SiteAsset>, String> {
+    let collection = project.get_collection::<Asset>("asset")?;
+    let cached = collection.get_files("asset").await?;
+    let folder_paths = folder_paths(&collection.folders()?);
+    let file_folders = collection.file_folders()?;
+
+    let mut assets = Vec::new();
+    for asset in cached {
+        if is_trashed(&asset) {
+            continue;
+        }
+        let id = asset.id()?;
+        let name = asset.name()?;
+        let data = match crate::load_data(IDB_ASSETS_STORE, &id).await {
+            Ok(result) => Uint8Array::from(JsValue::from(result)).to_vec(),
+            Err(e) => {
+                warnings.push(format!("asset {}: {:?}", name, e));
+                continue;
+            }
+        };
+        if data.is_empty() {
+            warnings.push(format!("asset {}: no stored data", name));
+            continue;
+        }
+
+        let mut path = asset_url(&id, &name).trim_start_matches('/').to_string();
+        if let Some(folder) = file_folders.get(&id).and_then(|id| folder_paths.get(id)) {
+            path = format!("assets/{}/{}/{}", folder, id, name);
+        }
+        let mime_type = asset
+            .get_mime_type()
+            .ok()
+            .filter(|mime_type| !mime_type.is_empty())
+            .unwrap_or_else(|| mime_type_for_path(&path).to_string());
+        // Checked again here, for SVGs stored before uploads were sanitized
+        let data = if mime_type == SVG_MIME_TYPE {
+            match sanitize_bytes(&data, false) {
+                Ok(clean) => clean.svg.into_bytes(),
+                Err(e) => {
+                    warnings.push(format!(
+                        "asset {}: not exported, it may run scripts: {}",
+                        name, e
+                    ));
+                    continue;
+                }
+            }
+        } else {
+            data
+        };
+        // Edited images are exported edited; the stored original is left alone
+        let data = match asset.transform() {
+            Ok(Some(transform)) => match transformed(&data, &mime_type, &transform) {
+                Ok(edited) => edited,
+                Err(e) => {
+                    warnings.push(format!("asset {}: exported unedited: {}", name, e));
+                    data
+                }
+            },
+            Ok(None) => data,
+            Err(e) => {
+                warnings.push(format!("asset {}: {}", name, e));
+                data
+            }
+        };
+        assets.push(SiteAsset {
+            path,
+            mime_type,
+            data,
+        });
+    }
+    Ok(assets)
+}
+
+impl StoreInner {
+    /// ACTOR Render the active site with the active theme, or the part of
+    /// it `filter` selects
+    pub(super) async fn render_site(
+        &self,
+        url_strategy: Option<UrlStrategy>,
+        filter: ExportFilter,
+    ) -> Response {
+        console_log!("Rendering site");
+
+        let result = async {
+            let output = self.build_site_output(url_strategy, &filter).await?;
+            check_strict(&output)?;
+            Ok::<_, String>(output)
+        }
+        .await;
+
+        match result {
+            Ok(output) => Response::success(output.to_json()),
+            Err(e) => Response::error(&format!("Failed to render site: {}", e)),
+        }
+    }
+
+    /// ACTOR Render the site, or the part `filter` selects, with its assets
+    /// as a ZIP archive
+    pub(super) async fn build_site(
+        &self,
+        url_strategy: Option<UrlStrategy>,
+        filter: ExportFilter,
+    ) -> Response {
+        console_log!("Building site archive");
+
+        let result = async {
+            let output = self.build_site_export(url_strategy, &filter).await?;
+            check_strict(&output)?;
+            let zip = site_zip(&output)?;
+            Ok::<_, String>((output, zip))
+        }
+        .await;
+
+        match result {
+            Ok((output, zip)) => Response::success(json!({
+                "files": output.files.len() + output.assets.len(),
+                "size": zip.len(),
+                "zip": zip,
+                "warnings": output.warnings,
+                "syndicated": output.syndicated,
+            })),
+            Err(e) => Response::error(&format!("Failed to build site: {}", e)),
+        }
+    }
+
+    /// ACTOR Render the site with its assets, ready to be served by a service worker
+    pub(super) async fn get_preview_manifest(&self) -> Response {
+        console_log!("Building preview manifest");
+
+        match self.build_site_export(None, &ExportFilter::default()).await {
+            Ok(output) => Response::success(preview_manifest(&output)),
+            Err(e) => Response::error(&format!("Failed to build preview manifest: {}", e)),
+        }
+    }
+
+    /// ACTOR Pages and posts that link to `url`, or the whole backlinks index
+    pub(super) async fn get_backlinks(&self, url: Option<String>) -> Response {
+        let (source, _) = match self.load_site_source().await {
+            Ok(loaded) => loaded,
+            Err(e) => return Response::error(&format!("Failed to load site: {}", e)),
+        };
+        let (pages, _) = resolve_all_wikilinks(&source.pages);
+        let index = backlinks_index(&pages);
+
+        match url {
+            Some(url) => {
+                let target = resolve_internal("/", &url).unwrap_or(url);
+                Response::success(json!({
+                    "url": target,
+                    "backlinks": index.get(&target).cloned().unwrap_or_default(),
+                }))
+            }
+            None => Response::success(json!(index)),
+        }
+    }
+
+    /// ACTOR `[[wikilinks]]` in pages and posts that don't match any page
+    pub(super) async fn get_unresolved_links(&self) -> Response {
+        match self.load_site_source().await {
+            Ok((source, _)) => {
+                let (_, unresolved) = resolve_all_wikilinks(&source.pages);
+                Response::success(json!(unresolved))
+            }
+            Err(e) => Response::error(&format!("Failed to load site: {}", e)),
+        }
+    }
+
+    /// ACTOR Pages, posts and entry pages as nodes, with links, references
+    /// and shared tags as edges
+    pub(super) async fn get_content_graph(&self) -> Response {
+        console_log!("Building content graph");
+
+        match self.load_site_source().await {
+            Ok((source, _)) => {
+                let (pages, _) = resolve_all_wikilinks(&source.pages);
+                Response::success(json!(content_graph(&pages, &source.collections)))
+            }
+            Err(e) => Response::error(&format!("Failed to load site: {}", e)),
+        }
+    }
+
+    /// ACTOR Which pages use each template, and which templates and partials
+    /// nothing uses
+    pub(super) async fn get_template_usage(&self) -> Response {
+        console_log!("Analysing template usage");
+
+        match self.load_site_source().await {
+            Ok((source, _)) => Response::success(json!(template_usage(&source))),
+            Err(e) => Response::error(&format!("Failed to load site: {}", e)),
+        }
+    }
+
+    /// ACTOR Check what an export would publish, or the part `filter`
+    /// selects, reporting errors that should block it and warnings
+    ///
+    /// The site is rendered strictly to find what its templates are missing,
+    /// which is only an error if the site is strict.
+    pub(super) async fn validate_export(&self, filter: ExportFilter) -> Response {
+        console_log!("Validating site before export");
+
+        let result = async {
+            let (source, _) = self.load_site_source().await?;
+            let site = self.active_project(ProjectType::Site)?;
+            let mut page_fields = BTreeMap::new();
+            page_fields.insert(
+                "page".to_string(),
+                site.get_collection::<Page>("page")?.get_fields()?,
+            );
+            page_fields.insert(
+                "post".to_string(),
+                site.get_collection::<Post>("post")?.get_fields()?,
+            );
+            let source = filter_source(&source, &filter);
+            let mut report = validate_site(&source, &page_fields);
+
+            let severity = match source.settings.strictness {
+                Strictness::Strict => Severity::Error,
+                Strictness::Lenient => Severity::Warning,
+            };
+            let mut strict = source;
+            strict.settings.strictness = Strictness::Strict;
+            let output = site::render_site_with_hooks(&strict, &self.plugins)?;
+            for mut issue in output.errors {
+                issue.severity = severity;
+                report.add(issue);
+            }
+            Ok::<_, String>(report)
+        }
+        .await;
+
+        match result {
+            Ok(report) => Response::success(json!(report)),
+            Err(e) => Response::error(&format!("Failed to validate site: {}", e)),
+        }
+    }
+
+    /// ACTOR The whole site, or the page at `page`, as one HTML file with
+    /// stylesheets and small assets inlined
+    pub(super) async fn export_single_file(
+        &self,
+        page: Option<String>,
+        max_asset_bytes: Option<usize>,
+    ) -> Response {
+        console_log!("Exporting single file: {}", page.as_deref().unwrap_or("whole site"));
+
+        let result = async {
+            let (mut source, mut warnings) = self.load_site_source().await?;
+            // Links are rewritten from root-relative URLs, wherever the site is hosted
+            source.settings.url_strategy = UrlStrategy::Root;
+            source.settings.base_path = None;
+            source.site.insert("base_path".to_string(), json!(""));
+
+            let mut output = site::render_site_with_hooks(&source, &self.plugins)?;
+            check_strict(&output)?;
+            let site = self.active_project(ProjectType::Site)?;
+            output.assets = load_assets(&site, &mut warnings).await?;
+            relocate_asset_links(&mut output);
+            let options = SingleFileOptions {
+                page,
+                max_asset_bytes: max_asset_bytes.unwrap_or(DEFAULT_MAX_INLINE_BYTES),
+            };
+            let mut file = single_file_html(&output, &options)?;
+            warnings.append(&mut output.warnings);
+            warnings.append(&mut file.warnings);
+            file.warnings = warnings;
+            Ok::<_, String>(file)
+        }
+        .await;
+
+        match result {
+            Ok(file) => Response::success(file),
+            Err(e) => Response::error(&format!("Failed to export single file: {}", e)),
+        }
+    }
+
+    /// ACTOR Pages and posts as gemtext, with an index, for a Gemini capsule
+    pub(super) async fn export_gemtext(&self) -> Response {
+        console_log!("Exporting gemtext");
+
+        match self.load_site_source().await {
+            Ok((source, mut warnings)) => {
+                let (expanded, mut block_warnings) =
+                    resolve_all_block_refs(&source.pages, &source.blocks);
+                warnings.append(&mut block_warnings);
+                let (mut pages, _) = resolve_all_wikilinks(&expanded);
+                // Gemini has no way to decrypt a page, so protected ones stay out
+                pages.retain(|page| {
+                    let protected = page_password(&page.meta).is_some();
+                    if protected {
+                        warnings.push(format!(
+                            "{} {}: password-protected, left out",
+                            page.collection, page.name
+                        ));
+                    }
+                    !protected
+                });
+                let name = source.site.get("name").and_then(Value::as_str).unwrap_or("");
+                let mut output = site_to_gemtext(name, &pages);
+                warnings.append(&mut output.warnings);
+                output.warnings = warnings;
+                Response::success(output.to_json())
+            }
+            Err(e) => Response::error(&format!("Failed to load site: {}", e)),
+        }
+    }
+
+    /// ACTOR Theme selectors that match nothing, and classes templates use
+    /// that no stylesheet defines, checked against the rendered site
+    pub(super) async fn get_css_report(&self) -> Response {
+        console_log!("Checking theme styles");
+
+        let result = async {
+            let (source, _) = self.load_site_source().await?;
+            let output = site::render_site_with_hooks(&source, &self.plugins)?;
+            let markup: Vec<(String, String)> = source
+                .templates
+                .iter()
+                .chain(&source.partials)
+                .cloned()
+                .collect();
+            let rendered: Vec<&str> = output
+                .files
+                .iter()
+                .filter(|(path, _)| path.ends_with(".html"))
+                .map(|(_, html)| html.as_str())
+                .collect();
+            Ok::<_, String>(css_report(&source.styles, &markup, &rendered))
+        }
+        .await;
+
+        match result {
+            Ok(report) => Response::success(report),
+            Err(e) => Response::error(&format!("Failed to check styles: {}", e)),
+        }
+    }
+
+    /// ACTOR Custom properties the theme's stylesheets declare, with their
+    /// defaults and the site's overrides, for a theme settings panel
+    ///
+    /// Overrides are saved in the site settings' `theme_variables`.
+    pub(super) async fn get_theme_variables(&self) -> Response {
+        match self.load_site_source().await {
+            Ok((source, _)) => Response::success(theme_variables(&source)),
+            Err(e) => Response::error(&format!("Failed to load site: {}", e)),
+        }
+    }
+
+    /// ACTOR Parse a theme template or partial into tags with positions,
+    /// plus the variables, helpers and partials it uses
+    pub(super) async fn parse_template(&self, file_id: String) -> Response {
+        console_log!("Parsing template: {}", file_id);
+
+        let result = async {
+            let theme = self.active_project(ProjectType::Theme)?;
+            theme_file_content(&theme, &file_id).await
+        }
+        .await;
+
+        match result {
+            Ok(source) => Response::success(parse_template(&source)),
+            Err(e) => Response::error(&format!("Failed to parse template: {}", e)),
+        }
+    }
+
+    /// The rendered site plus the site's uploaded assets
+    ///
+    /// A partial export only carries the assets its pages use.
+    pub(super) async fn build_site_export(
+        &self,
+        url_strategy: Option<UrlStrategy>,
+        filter: &ExportFilter,
+    ) -> Result<SiteOutput, String> {
+        let mut output = self.build_site_output(url_strategy, filter).await?;
+        let site = self.active_project(ProjectType::Site)?;
+        output.assets = load_assets(&site, &mut output.warnings).await?;
+        relocate_asset_links(&mut output);
+        if !filter.is_empty() {
+            prune_assets(&mut output);
+        }
+        Ok(output)
+    }
+
+    /// Render the site, or the part `filter` selects, with `url_strategy`
+    /// overriding the site setting
+    pub(super) async fn build_site_output(
+        &self,
+        url_strategy: Option<UrlStrategy>,
+        filter: &ExportFilter,
+    ) -> Result<SiteOutput, String> {
+        let (source, mut warnings) = self.load_site_source().await?;
+        let mut source = filter_source(&source, filter);
+        if let Some(url_strategy) = url_strategy {
+            source.settings.url_strategy = url_strategy;
+        }
+        let mut output = site::render_site_with_hooks(&source, &self.plugins)?;
+        warnings.append(&mut output.warnings);
+        output.warnings = warnings;
+        Ok(output)
+    }
+
+    /// Gather everything the renderer needs from the active site and theme
+    ///
+    /// Returns the source plus warnings for files that couldn't be loaded.
+    pub(super) async fn load_site_source(&self) -> Result<(SiteSource, Vec<String>), String> {
+        let site = self.active_project(ProjectType::Site)?;
+        let theme = self.active_project(ProjectType::Theme)?;
+        let mut warnings = Vec::new();
+
+        let mut source = SiteSource {
+            today: chrono::Utc::now().format("%Y-%m-%d").to_string(),
+            generated_at: chrono::Utc::now(),
+            ..Default::default()
+        };
+        source.site.insert("id".to_string(), json!(site.id()));
+        source
+            .site
+            .insert("name".to_string(), json!(site.name().unwrap_or_default()));
+        source.settings = site.settings()?;
+        if let Some(url) = &source.settings.url {
+            source.site.insert("url".to_string(), json!(url));
+        }
+        if let Some(license) = &source.settings.license {
+            source.site.insert("license".to_string(), json!(license));
+        }
+        source.site.insert(
+            "base_path".to_string(),
+            json!(normalize_base_path(
+                source.settings.base_path.as_deref().unwrap_or("")
+            )),
+        );
+
+        source.templates = load_text_files::<Template>(&theme, "template", &mut warnings).await?;
+        source.partials = load_text_files::<Partial>(&theme, "partial", &mut warnings).await?;
+        source.styles = load_text_files::<Text>(&theme, "text", &mut warnings).await?;
+
+        source.pages = load_pages::<Page>(&site, "page", &mut warnings).await?;
+        source
+            .pages
+            .extend(load_pages::<Post>(&site, "post", &mut warnings).await?);
+        // Before URL patterns, so shared posts get URLs on this site
+        let syndicated = self
+            .syndicated_posts(&site.id(), &source.settings.syndication, &mut warnings)
+            .await;
+        source.pages.extend(syndicated);
+        warnings.extend(apply_url_patterns(
+            &source.settings.url_patterns,
+            &mut source.pages,
+        ));
+
+        source.blocks = load_blocks(&site, &mut warnings).await?;
+        source.forms = load_forms(&site, &mut warnings).await?;
+        source.collaborators = site.collaborators()?;
+        let assets = site.get_collection::<Asset>("asset")?;
+        source.asset_meta = Vec::new();
+        for asset in assets.get_files("asset").await? {
+            if is_trashed(&asset) {
+                continue;
+            }
+            // Its current name, which `url` keeps from when it was uploaded
+            let mut meta = asset.to_json()?;
+            meta["path"] = json!(asset_url(&asset.id()?, &asset.name()?));
+            source.asset_meta.push(meta);
+        }
+
+        for (name, _) in site.get_collections()? {
+            if is_built_in_collection(&name) {
+                continue;
+            }
+            let collection = site.get_collection::<Entry>(&name)?;
+            let entries = collection
+                .get_files(&name)
+                .await?
+                .iter()
+                .map(|entry| entry.to_json())
+                .collect::<Result<Vec<Value>, String>>()?;
+            source.collections.push(CollectionSource {
+                listing: collection.listing()?,
+                fields: collection.get_fields()?,
+                name,
+                entries,
+            });
+        }
+
+        Ok((source, warnings))
+    }
+}
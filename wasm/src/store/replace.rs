@@ -0,0 +1,234 @@
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::render::load_full_file;
+use super::StoreInner;
+use crate::js_conversions::js_conversions;
+use crate::messages::Response;
+use crate::model::file::{
+    document_texts, match_context, replace_in_text, File, Matcher, TextMatch,
+};
+use crate::model::project::Project;
+use crate::model::{Entry, Page, Partial, Post, Template, Text};
+use crate::types::{FindReplaceScope, ProjectType};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// The query, its replacement and whether to write changes
+struct Replace<'a> {
+    matcher: &'a Matcher,
+    replacement: &'a str,
+    scope: &'a FindReplaceScope,
+    dry_run: bool,
+}
+
+fn report_match(field: &str, text: &str, found: &TextMatch) -> Value {
+    json!({
+        "field": field,
+        "context": match_context(text, found),
+        "match": &text[found.start..found.end],
+        "replacement": found.replacement,
+    })
+}
+
+/// Matches in one file, replacing them unless this is a dry run
+///
+/// Data collection entries have no body, just metadata.
+async fn replace_in_file<T: File + Default>(
+    cached: &T,
+    collection_name: &str,
+    has_body: bool,
+    replace: &Replace<'_>,
+) -> Result<Vec<Value>, String> {
+    let id = cached.id()?;
+    let mut matches = Vec::new();
+    let mut meta_updates = Vec::new();
+
+    for field in &replace.scope.meta_fields {
+        let value = match cached.get_field(field) {
+            Ok(Value::String(value)) => value,
+            _ => continue,
+        };
+        let found = replace.matcher.find(&value, replace.replacement);
+        if found.is_empty() {
+            continue;
+        }
+        matches.extend(found.iter().map(|found| report_match(field, &value, found)));
+        meta_updates.push((field, replace.matcher.replace(&value, replace.replacement)));
+    }
+
+    if !replace.scope.content || !has_body {
+        if !replace.dry_run {
+            for (field, value) in &meta_updates {
+                cached.set_field(field, value).await?;
+            }
+        }
+        return Ok(matches);
+    }
+
+    let full = load_full_file::<T>(collection_name, &id).await?;
+    let doc = full
+        .store()
+        .as_full()
+        .ok_or_else(|| "Missing document".to_string())?;
+    let mut edited = false;
+    for text in document_texts(doc) {
+        let content = text.to_string();
+        let found = replace.matcher.find(&content, replace.replacement);
+        if found.is_empty() {
+            continue;
+        }
+        matches.extend(
+            found
+                .iter()
+                .map(|found| report_match("content", &content, found)),
+        );
+        if !replace.dry_run {
+            replace_in_text(&text, &found)?;
+            edited = true;
+        }
+    }
+
+    if replace.dry_run {
+        return Ok(matches);
+    }
+    // Setting a field on the full document saves it, edits included
+    for (field, value) in &meta_updates {
+        cached.set_field(field, value).await?;
+        full.set_field(field, value).await?;
+    }
+    if edited && meta_updates.is_empty() {
+        full.save_to_indexeddb().await?;
+    }
+    Ok(matches)
+}
+
+async fn replace_in_collection<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+    has_body: bool,
+    replace: &Replace<'_>,
+    files: &mut Vec<Value>,
+) -> Result<(), String> {
+    let cached = project
+        .get_collection::<T>(collection_name)?
+        .get_files(collection_name)
+        .await?;
+    for file in cached {
+        let name = file.name().unwrap_or_default();
+        match replace_in_file(&file, collection_name, has_body, replace).await {
+            Ok(matches) if matches.is_empty() => {}
+            Ok(matches) => files.push(json!({
+                "collection": collection_name,
+                "id": file.id()?,
+                "name": name,
+                "count": matches.len(),
+                "matches": matches,
+            })),
+            Err(e) => files.push(json!({
+                "collection": collection_name,
+                "id": file.id()?,
+                "name": name,
+                "error": e,
+            })),
+        }
+    }
+    Ok(())
+}
+
+impl StoreInner {
+    /// ACTOR Find and replace across a project's files
+    ///
+    /// Body text is edited in place through its CRDT, so each replacement
+    /// merges and undoes like a keystroke. Matches don't span formatting
+    /// changes, since each run of text is searched separately.
+    pub(super) async fn find_replace(
+        &self,
+        query: String,
+        replacement: String,
+        scope: FindReplaceScope,
+        regex: bool,
+        case_sensitive: bool,
+        dry_run: bool,
+    ) -> Response {
+        console_log!(
+            "Find and replace {:?} → {:?} (dry run: {})",
+            query,
+            replacement,
+            dry_run
+        );
+
+        let matcher = match Matcher::new(&query, regex, case_sensitive) {
+            Ok(matcher) => matcher,
+            Err(e) => return Response::error(&e),
+        };
+        let project_type = match js_conversions::string_to_project_type(&scope.project_type) {
+            Ok(project_type) => project_type,
+            Err(e) => return Response::error(&format!("Failed to convert project type: {}", e)),
+        };
+        let project = match self.active_project(project_type.clone()) {
+            Ok(project) => project,
+            Err(e) => return Response::error(&e),
+        };
+
+        let collections: Vec<String> = if scope.collections.is_empty() {
+            let defaults: &[&str] = match project_type {
+                ProjectType::Site => &["page", "post"],
+                ProjectType::Theme => &["template", "partial", "text"],
+            };
+            defaults.iter().map(|name| name.to_string()).collect()
+        } else {
+            scope.collections.clone()
+        };
+
+        let replace = Replace {
+            matcher: &matcher,
+            replacement: &replacement,
+            scope: &scope,
+            dry_run,
+        };
+        let mut files = Vec::new();
+        for collection_name in &collections {
+            let name = collection_name.as_str();
+            let files = &mut files;
+            let result = match name {
+                "page" => {
+                    replace_in_collection::<Page>(&project, name, true, &replace, files).await
+                }
+                "post" => {
+                    replace_in_collection::<Post>(&project, name, true, &replace, files).await
+                }
+                "template" => {
+                    replace_in_collection::<Template>(&project, name, true, &replace, files).await
+                }
+                "partial" => {
+                    replace_in_collection::<Partial>(&project, name, true, &replace, files).await
+                }
+                "text" => {
+                    replace_in_collection::<Text>(&project, name, true, &replace, files).await
+                }
+                "asset" => Err("Assets can't be searched".to_string()),
+                _ => replace_in_collection::<Entry>(&project, name, false, &replace, files).await,
+            };
+            if let Err(e) = result {
+                return Response::error(&format!("Failed to search {}: {}", collection_name, e));
+            }
+        }
+
+        let total: u64 = files.iter().filter_map(|file| file["count"].as_u64()).sum();
+        Response::success(json!({
+            "dry_run": dry_run,
+            "total": total,
+            "files": files,
+        }))
+    }
+}
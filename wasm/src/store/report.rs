@@ -0,0 +1,173 @@
+use chrono::{DateTime, Months, NaiveDate};
+use serde_json::{json, Map, Value};
+use wasm_bindgen::prelude::*;
+
+use super::StoreInner;
+use crate::messages::Response;
+use crate::model::file::File;
+use crate::model::project::Project;
+use crate::model::{Page, Post};
+use crate::types::{ContentReportOptions, ProjectType};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// "draft", "scheduled" or "published" (the default)
+pub(super) const STATUS_FIELD: &str = "status";
+/// When a post is (or will be) published
+pub(super) const DATE_FIELD: &str = "date";
+pub(super) const CREATED_FIELD: &str = "created";
+pub(super) const UPDATED_FIELD: &str = "updated";
+
+/// Day a metadata value refers to: an ISO date or datetime, or a timestamp in ms
+pub(super) fn meta_date(value: &Value) -> Option<NaiveDate> {
+    match value {
+        Value::String(text) => text
+            .get(..10)
+            .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok()),
+        Value::Number(ms) => {
+            DateTime::from_timestamp_millis(ms.as_f64()? as i64).map(|time| time.date_naive())
+        }
+        _ => None,
+    }
+}
+
+/// The first of `fields` that holds a date
+fn first_date(meta: &Map<String, Value>, fields: &[&str]) -> Option<NaiveDate> {
+    fields
+        .iter()
+        .find_map(|field| meta.get(*field).and_then(meta_date))
+}
+
+fn report_item(
+    collection: &str,
+    meta: &Map<String, Value>,
+    date_key: &str,
+    date: NaiveDate,
+) -> Value {
+    json!({
+        "collection": collection,
+        "id": meta.get("id").cloned().unwrap_or(Value::Null),
+        "name": meta.get("name").cloned().unwrap_or(Value::Null),
+        "title": meta.get("title").cloned().unwrap_or(Value::Null),
+        date_key: date.format("%Y-%m-%d").to_string(),
+    })
+}
+
+/// Sort report items by one of their date keys, oldest first
+fn sort_by_date(items: &mut [Value], date_key: &str) {
+    items.sort_by(|a, b| a[date_key].as_str().cmp(&b[date_key].as_str()));
+}
+
+/// Scheduled posts, old drafts and stale pages among `files` (collection, metadata)
+///
+/// Files without the dates a check needs are counted as `undated` rather
+/// than guessed at.
+pub(super) fn content_report(
+    files: &[(String, Map<String, Value>)],
+    options: &ContentReportOptions,
+    today: NaiveDate,
+) -> Value {
+    let draft_cutoff = today - chrono::Duration::days(options.draft_days as i64);
+    let stale_cutoff = today
+        .checked_sub_months(Months::new(options.stale_months))
+        .unwrap_or(NaiveDate::MIN);
+
+    let mut scheduled = Vec::new();
+    let mut old_drafts = Vec::new();
+    let mut stale_pages = Vec::new();
+    let mut undated = 0;
+
+    for (collection, meta) in files {
+        let status = meta
+            .get(STATUS_FIELD)
+            .and_then(Value::as_str)
+            .unwrap_or("published")
+            .to_lowercase();
+        let date = meta.get(DATE_FIELD).and_then(meta_date);
+
+        if status == "draft" {
+            match first_date(meta, &[CREATED_FIELD, UPDATED_FIELD, DATE_FIELD]) {
+                Some(created) if created <= draft_cutoff => {
+                    old_drafts.push(report_item(collection, meta, "created", created))
+                }
+                Some(_) => {}
+                None => undated += 1,
+            }
+        } else if collection == "post"
+            && (status == "scheduled" || date.map_or(false, |d| d > today))
+        {
+            match date {
+                Some(date) => scheduled.push(report_item(collection, meta, "date", date)),
+                None => undated += 1,
+            }
+        } else if collection == "page" {
+            match first_date(meta, &[UPDATED_FIELD, CREATED_FIELD]) {
+                Some(updated) if updated <= stale_cutoff => {
+                    stale_pages.push(report_item(collection, meta, "updated", updated))
+                }
+                Some(_) => {}
+                None => undated += 1,
+            }
+        }
+    }
+
+    sort_by_date(&mut scheduled, "date");
+    sort_by_date(&mut old_drafts, "created");
+    sort_by_date(&mut stale_pages, "updated");
+
+    json!({
+        "today": today.format("%Y-%m-%d").to_string(),
+        "scheduled": scheduled,
+        "old_drafts": old_drafts,
+        "stale_pages": stale_pages,
+        "undated": undated,
+    })
+}
+
+/// Collection name and metadata of every file in a collection
+async fn collection_meta<T: File + Default>(
+    site: &Project,
+    collection_name: &str,
+) -> Result<Vec<(String, Map<String, Value>)>, String> {
+    let files = site
+        .get_collection::<T>(collection_name)?
+        .get_files(collection_name)
+        .await?;
+    Ok(files
+        .iter()
+        .map(|file| (collection_name.to_string(), file.meta_fields()))
+        .collect())
+}
+
+impl StoreInner {
+    /// ACTOR Report upcoming posts, old drafts and pages due for review
+    pub(super) async fn get_content_report(&self, options: ContentReportOptions) -> Response {
+        console_log!("Building content report");
+
+        let site = match self.active_project(ProjectType::Site) {
+            Ok(site) => site,
+            Err(e) => return Response::error(&e),
+        };
+
+        let mut files = match collection_meta::<Page>(&site, "page").await {
+            Ok(files) => files,
+            Err(e) => return Response::error(&format!("Failed to read pages: {}", e)),
+        };
+        match collection_meta::<Post>(&site, "post").await {
+            Ok(posts) => files.extend(posts),
+            Err(e) => return Response::error(&format!("Failed to read posts: {}", e)),
+        }
+
+        let today = chrono::Utc::now().date_naive();
+        Response::success(content_report(&files, &options, today))
+    }
+}
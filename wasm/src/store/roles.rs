@@ -0,0 +1,418 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::file_list::DEFAULT_STATUS;
+use super::report::STATUS_FIELD;
+use super::StoreInner;
+use crate::messages::{FileUpdate, Message, Response};
+use crate::model::file::File;
+use crate::model::project::Project;
+use crate::model::{Page, Post};
+use crate::types::ProjectType;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+pub(super) const DRAFT: &str = "draft";
+
+/// Who is using the store, set by the host
+///
+/// A store without a role configured acts as `Admin`, as it always has.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Author,
+    Editor,
+    Admin,
+}
+
+impl Default for Role {
+    fn default() -> Self {
+        Role::Admin
+    }
+}
+
+/// What a message needs the role to allow
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Capability {
+    /// Read and render anything
+    Read,
+    /// Create pages, posts and assets, and edit drafts
+    EditDrafts,
+    /// Edit published content and data collections, and import documents
+    EditContent,
+    /// Change a page or post's status away from draft, and record deploys
+    Publish,
+    /// Collections, listings, forms and site settings
+    ManageSite,
+    /// Anything in the theme project
+    ManageTheme,
+    /// Create, import and install projects
+    Admin,
+}
+
+const ALL_CAPABILITIES: [Capability; 7] = [
+    Capability::Read,
+    Capability::EditDrafts,
+    Capability::EditContent,
+    Capability::Publish,
+    Capability::ManageSite,
+    Capability::ManageTheme,
+    Capability::Admin,
+];
+
+impl Role {
+    pub fn parse(role: &str) -> Result<Role, String> {
+        match role.to_lowercase().as_str() {
+            "viewer" => Ok(Role::Viewer),
+            "author" => Ok(Role::Author),
+            "editor" => Ok(Role::Editor),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!(
+                "Unknown role: {} (expected viewer, author, editor or admin)",
+                role
+            )),
+        }
+    }
+
+    pub fn can(self, capability: Capability) -> bool {
+        match self {
+            Role::Viewer => capability == Capability::Read,
+            Role::Author => matches!(capability, Capability::Read | Capability::EditDrafts),
+            Role::Editor => !matches!(capability, Capability::ManageTheme | Capability::Admin),
+            Role::Admin => true,
+        }
+    }
+
+    pub fn capabilities(self) -> Vec<Capability> {
+        ALL_CAPABILITIES
+            .iter()
+            .copied()
+            .filter(|capability| self.can(*capability))
+            .collect()
+    }
+}
+
+/// Whether a site collection holds drafts authors may work on
+pub(super) fn is_draftable(collection_name: &str) -> bool {
+    matches!(collection_name, "page" | "post")
+}
+
+/// Capability needed to create or change files in a collection
+fn file_capability(project_type: &str, collection_name: &str) -> Capability {
+    if project_type.eq_ignore_ascii_case("theme") {
+        Capability::ManageTheme
+    } else if is_draftable(collection_name) || collection_name == "asset" {
+        Capability::EditDrafts
+    } else {
+        Capability::EditContent
+    }
+}
+
+/// Capability needed for a project-level change
+fn project_capability(project_type: &str) -> Capability {
+    if project_type.eq_ignore_ascii_case("theme") {
+        Capability::ManageTheme
+    } else {
+        Capability::ManageSite
+    }
+}
+
+/// What a message needs, before looking at the files it touches
+///
+/// Every message is listed, so a new one has to be given a capability
+/// rather than falling through to `Read`.
+pub(super) fn required_capability(message: &Message) -> Capability {
+    match message {
+        Message::CreateSite { .. }
+        | Message::ImportProject { .. }
+        | Message::InstallFromUrl { .. }
+        | Message::ImportStaticSite { .. }
+        | Message::CompactProjects { .. }
+        | Message::InitDefault => Capability::Admin,
+        Message::CreateTheme { .. } | Message::SetThemeManifest { .. } => Capability::ManageTheme,
+        Message::SelectTheme { .. } => Capability::ManageSite,
+        Message::AddCollection { project_type, .. }
+        | Message::SetCollectionListing { project_type, .. }
+        | Message::SetCollectionSchema { project_type, .. }
+        | Message::ImportSchema { project_type, .. }
+        | Message::SetProjectReadOnly { project_type, .. }
+        | Message::SetCollaborator { project_type, .. }
+        | Message::RemoveCollaborator { project_type, .. }
+        // A peer's updates can change anything in the project
+        | Message::ApplyShareUpdates { project_type, .. } => project_capability(project_type),
+        Message::SetSiteSettings { .. }
+        | Message::SetUrlPattern { .. }
+        | Message::SaveForm { .. } => Capability::ManageSite,
+        // Head HTML can load scripts on the published site
+        Message::UpdateFile {
+            updates: FileUpdate::SetHeadHtml(_),
+            ..
+        } => Capability::ManageSite,
+        Message::CreateAssetFolder { project_type, .. }
+        | Message::RenameAssetFolder { project_type, .. }
+        | Message::MoveAssetFolder { project_type, .. }
+        | Message::MoveAsset { project_type, .. }
+        | Message::TransformAsset { project_type, .. }
+        | Message::SanitizeSvgAssets { project_type } => file_capability(project_type, "asset"),
+        Message::CreateFile {
+            project_type,
+            collection_name,
+            ..
+        }
+        | Message::UpdateFile {
+            project_type,
+            collection_name,
+            ..
+        }
+        | Message::DeleteFile {
+            project_type,
+            collection_name,
+            ..
+        } => file_capability(project_type, collection_name),
+        Message::ImportDocx { .. }
+        | Message::ImportAssets { .. }
+        | Message::ImportCsv { .. }
+        | Message::ImportGhost { .. }
+        | Message::ImportArchive { .. }
+        | Message::TrashOrphanAssets { .. } => Capability::EditContent,
+        #[cfg(feature = "fixtures")]
+        Message::GenerateFixture { .. } => Capability::EditContent,
+        // Submissions and imported articles always arrive as drafts
+        Message::ImportSubmission { .. } | Message::ImportFromUrl { .. } => Capability::EditDrafts,
+        Message::InsertSnippet { .. } => Capability::EditDrafts,
+        // Saving writes only changes the role was allowed to make
+        Message::SaveState { .. } => Capability::EditDrafts,
+        Message::FindReplace { dry_run: true, .. } => Capability::Read,
+        Message::FindReplace { scope, .. } if scope.project_type.eq_ignore_ascii_case("theme") => {
+            Capability::ManageTheme
+        }
+        Message::FindReplace { .. } => Capability::EditContent,
+        Message::RecordDeploy { .. } => Capability::Publish,
+        Message::VerifyProject { repair } if repair.any() => Capability::Admin,
+        Message::VerifyProject { .. }
+        | Message::GetSite
+        | Message::GetTheme
+        | Message::GetThemeManifest
+        | Message::ListTemplates { .. }
+        | Message::CheckThemeRequirements
+        | Message::ListSites
+        | Message::SwitchSite { .. }
+        | Message::GetCollection { .. }
+        | Message::ListCollections { .. }
+        | Message::ExportSchema { .. }
+        | Message::GetFile { .. }
+        | Message::GetMeta { .. }
+        | Message::ListFiles { .. }
+        | Message::CheckDelete { .. }
+        | Message::FindOrphanAssets
+        | Message::LoadState { .. }
+        | Message::ExportProject { .. }
+        | Message::ListSubmissions { .. }
+        | Message::GetSiteSettings
+        | Message::ListCollaborators { .. }
+        | Message::RenderSite { .. }
+        | Message::BuildSite { .. }
+        | Message::GetPreviewManifest
+        | Message::ExportSingleFile { .. }
+        | Message::ExportGemtext
+        | Message::ValidateExport { .. }
+        | Message::GetPublishDiff { .. }
+        | Message::WatchPreview { .. }
+        | Message::UnwatchPreview
+        | Message::ListPlugins
+        | Message::GetContentReport { .. }
+        | Message::AuditAltText
+        | Message::GetProjectStats
+        | Message::GetMemoryReport
+        | Message::GetDirtyState
+        | Message::GetStorageEstimate
+        | Message::GetTabRole
+        | Message::GetCapabilities
+        | Message::GetChangesSince { .. }
+        | Message::GetShareState { .. }
+        | Message::GetShareUpdates { .. }
+        | Message::GetBacklinks { .. }
+        | Message::GetUnresolvedLinks
+        | Message::GetContentGraph
+        | Message::GetTemplateUsage
+        | Message::GetThemeVariables
+        | Message::ParseTemplate { .. }
+        | Message::GetCssReport
+        | Message::GetDocument { .. }
+        | Message::GetPlainText { .. }
+        | Message::GetOutline { .. }
+        | Message::CreateAnchor { .. }
+        | Message::ResolveAnchor { .. }
+        | Message::SaveCursor { .. }
+        | Message::RestoreCursor { .. } => Capability::Read,
+    }
+}
+
+/// Status an update gives a file, if it sets one
+pub(super) fn status_update(update: &FileUpdate) -> Option<&str> {
+    match update {
+        FileUpdate::SetField { name, value } if name == STATUS_FIELD => Some(value),
+        // Removing the status publishes the file
+        FileUpdate::SetMeta(values) => values
+            .get(STATUS_FIELD)
+            .map(|value| value.as_str().unwrap_or(DEFAULT_STATUS)),
+        _ => None,
+    }
+}
+
+async fn file_status<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+    file_id: &str,
+) -> Result<Option<String>, String> {
+    let file = project
+        .get_collection::<T>(collection_name)?
+        .get_file(file_id, collection_name)
+        .await?;
+    Ok(file
+        .get_field(STATUS_FIELD)
+        .ok()
+        .and_then(|status| status.as_str().map(str::to_lowercase)))
+}
+
+async fn set_draft<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+    file_id: &str,
+) -> Result<(), String> {
+    project
+        .get_collection::<T>(collection_name)?
+        .get_file(file_id, collection_name)
+        .await?
+        .set_field(STATUS_FIELD, DRAFT)
+        .await
+}
+
+impl StoreInner {
+    pub(super) fn role(&self) -> Role {
+        *self.role.lock().unwrap()
+    }
+
+    pub(super) fn set_role(&self, role: Role) {
+        console_log!("Setting role: {:?}", role);
+        *self.role.lock().unwrap() = role;
+    }
+
+    /// Check a message against the current role
+    ///
+    /// Besides the message's capability, a page or post update needs
+    /// `Publish` to move it out of draft, and `EditContent` unless it is
+    /// still a draft.
+    pub(super) async fn authorize(&self, message: &Message) -> Result<(), String> {
+        let role = self.role();
+        let required = required_capability(message);
+        if !role.can(required) {
+            return Err(format!(
+                "The {:?} role can't do that (needs {:?})",
+                role, required
+            ));
+        }
+
+        if let Message::UpdateFile {
+            project_type,
+            collection_name,
+            file_id,
+            updates,
+        } = message
+        {
+            if required != Capability::EditDrafts || !is_draftable(collection_name) {
+                return Ok(());
+            }
+            if let Some(status) = status_update(updates) {
+                if !status.eq_ignore_ascii_case(DRAFT) && !role.can(Capability::Publish) {
+                    return Err(format!("The {:?} role can't publish", role));
+                }
+            }
+            if role.can(Capability::EditContent) {
+                return Ok(());
+            }
+            self.require_draft(role, project_type, collection_name, file_id).await?;
+        }
+
+        // Only drafts can be deleted without `EditContent`, as only they can be edited
+        if let Message::DeleteFile {
+            project_type,
+            collection_name,
+            file_id,
+            ..
+        } = message
+        {
+            if required == Capability::EditDrafts
+                && is_draftable(collection_name)
+                && !role.can(Capability::EditContent)
+            {
+                self.require_draft(role, project_type, collection_name, file_id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// An error unless the page or post is a draft
+    async fn require_draft(
+        &self,
+        role: Role,
+        project_type: &str,
+        collection_name: &str,
+        file_id: &str,
+    ) -> Result<(), String> {
+        let project = self.active_project(ProjectType::Site)?;
+        let status = match collection_name {
+            "page" => file_status::<Page>(&project, collection_name, file_id).await?,
+            _ => file_status::<Post>(&project, collection_name, file_id).await?,
+        };
+        if status.as_deref() != Some(DRAFT) {
+            return Err(format!(
+                "The {:?} role can only edit drafts ({} {} in {})",
+                role, collection_name, file_id, project_type
+            ));
+        }
+        Ok(())
+    }
+
+    /// Pages and posts created by someone who can't publish start as drafts
+    pub(super) async fn mark_new_draft(&self, collection_name: &str, created: &Value) {
+        if self.role().can(Capability::Publish) || !is_draftable(collection_name) {
+            return;
+        }
+        let file_id = match created.get("id").and_then(Value::as_str) {
+            Some(file_id) => file_id,
+            None => return,
+        };
+        let result = match self.active_project(ProjectType::Site) {
+            Ok(project) => match collection_name {
+                "page" => set_draft::<Page>(&project, collection_name, file_id).await,
+                _ => set_draft::<Post>(&project, collection_name, file_id).await,
+            },
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            console_log!("Failed to mark {} as a draft: {}", file_id, e);
+        }
+    }
+
+    /// ACTOR The current role and what it allows
+    pub(super) fn get_capabilities(&self) -> Response {
+        let role = self.role();
+        Response::success(json!({
+            "role": role,
+            "capabilities": role.capabilities(),
+        }))
+    }
+}
@@ -0,0 +1,245 @@
+use std::convert::TryFrom;
+
+use serde_json::{json, Map, Value};
+use wasm_bindgen::prelude::*;
+
+use super::collections::{add_custom_collection, is_built_in_collection};
+use super::StoreInner;
+use crate::js_conversions::js_conversions;
+use crate::messages::Response;
+use crate::model::project::Project;
+use crate::model::Entry;
+use crate::types::{FieldDefinition, FieldType, ListingConfig, ProjectType};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+const JSON_SCHEMA: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// One collection of a project's content model
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct CollectionModel {
+    pub name: String,
+    pub fields: Vec<FieldDefinition>,
+    pub listing: Option<ListingConfig>,
+}
+
+fn field_schema(field_type: FieldType) -> Value {
+    let mut schema = match field_type {
+        FieldType::String | FieldType::Text => json!({ "type": "string" }),
+        FieldType::DateTime => json!({ "type": "string", "format": "date-time" }),
+        FieldType::Number => json!({ "type": "number" }),
+        FieldType::List | FieldType::Array => json!({ "type": "array" }),
+        FieldType::Map | FieldType::Object => json!({ "type": "object" }),
+        FieldType::RichText => json!({
+            "type": "object",
+            "description": "ProseMirror document",
+        }),
+        FieldType::Blob => json!({ "type": "string", "contentEncoding": "base64" }),
+    };
+    // Several field types share a JSON type, so keep the exact one
+    schema["x-field-type"] = json!(field_type.to_string());
+    schema
+}
+
+/// A field type from its schema: `x-field-type` if there is one, otherwise
+/// the closest match to its JSON type, for schemas written by hand
+fn schema_field_type(schema: &Value) -> Result<FieldType, String> {
+    if let Some(field_type) = schema.get("x-field-type").and_then(Value::as_str) {
+        return FieldType::try_from(field_type.to_string());
+    }
+    let format = schema.get("format").and_then(Value::as_str);
+    match schema.get("type").and_then(Value::as_str) {
+        Some("string") if matches!(format, Some("date-time") | Some("date")) => {
+            Ok(FieldType::DateTime)
+        }
+        Some("string") => Ok(FieldType::String),
+        Some("number") | Some("integer") => Ok(FieldType::Number),
+        Some("array") => Ok(FieldType::Array),
+        Some("object") => Ok(FieldType::Object),
+        Some(other) => Err(format!("{} has no matching field type", other)),
+        None => Err("no type".to_string()),
+    }
+}
+
+/// The content model as a JSON Schema document, one `$defs` entry per collection
+pub(super) fn model_to_schema(
+    title: &str,
+    project_type: &ProjectType,
+    collections: &[CollectionModel],
+) -> Value {
+    let mut defs = Map::new();
+    for collection in collections {
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for field in &collection.fields {
+            properties.insert(field.name.clone(), field_schema(field.field_type));
+            if field.required {
+                required.push(field.name.clone());
+            }
+        }
+
+        let mut definition = json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        });
+        if is_built_in_collection(&collection.name) {
+            definition["x-built-in"] = json!(true);
+        }
+        if let Some(listing) = &collection.listing {
+            definition["x-listing"] = json!(listing);
+        }
+        defs.insert(collection.name.clone(), definition);
+    }
+
+    let project_type = match project_type {
+        ProjectType::Site => "site",
+        ProjectType::Theme => "theme",
+    };
+    json!({
+        "$schema": JSON_SCHEMA,
+        "title": title,
+        "x-project-type": project_type,
+        "$defs": defs,
+    })
+}
+
+/// The collections described by a schema from `model_to_schema`, or any
+/// JSON Schema with object definitions under `$defs`
+pub(super) fn schema_to_model(schema: &Value) -> Result<Vec<CollectionModel>, String> {
+    let defs = schema
+        .get("$defs")
+        .and_then(Value::as_object)
+        .ok_or("Schema has no $defs")?;
+
+    let mut collections = Vec::new();
+    for (name, definition) in defs {
+        let required: Vec<&str> = definition
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|required| required.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let mut fields = Vec::new();
+        if let Some(properties) = definition.get("properties").and_then(Value::as_object) {
+            for (field_name, field) in properties {
+                let field_type = schema_field_type(field)
+                    .map_err(|e| format!("{}.{}: {}", name, field_name, e))?;
+                fields.push(FieldDefinition {
+                    name: field_name.clone(),
+                    field_type,
+                    required: required.contains(&field_name.as_str()),
+                });
+            }
+        }
+
+        let listing = match definition.get("x-listing") {
+            Some(listing) => Some(
+                serde_json::from_value(listing.clone())
+                    .map_err(|e| format!("{}: invalid listing: {}", name, e))?,
+            ),
+            None => None,
+        };
+        collections.push(CollectionModel {
+            name: name.clone(),
+            fields,
+            listing,
+        });
+    }
+    Ok(collections)
+}
+
+pub(super) fn collection_models(project: &Project) -> Result<Vec<CollectionModel>, String> {
+    let mut collections = Vec::new();
+    for (name, _) in project.get_collections()? {
+        // Only the fields and listing are read, which every collection shares
+        let collection = project.get_collection::<Entry>(&name)?;
+        collections.push(CollectionModel {
+            fields: collection.get_fields()?,
+            listing: collection.listing()?,
+            name,
+        });
+    }
+    Ok(collections)
+}
+
+/// Add the schema's collections that the project doesn't have yet
+fn apply_model(project: &mut Project, collections: Vec<CollectionModel>) -> Result<Value, String> {
+    let mut added = project.add_missing_collections()?;
+    let mut skipped = Vec::new();
+    for collection in collections {
+        if is_built_in_collection(&collection.name) {
+            continue;
+        }
+        let exists = project
+            .get_collections()?
+            .iter()
+            .any(|(name, _)| *name == collection.name);
+        if exists {
+            skipped.push(collection.name);
+            continue;
+        }
+
+        let created = add_custom_collection(project, &collection.name, collection.fields)?;
+        if let Some(listing) = &collection.listing {
+            created.set_listing(Some(listing))?;
+        }
+        added.push(collection.name);
+    }
+    Ok(json!({ "added": added, "skipped": skipped }))
+}
+
+impl StoreInner {
+    /// ACTOR The project's collections and fields as a JSON Schema document
+    pub(super) fn export_schema(&self, project_type: String) -> Response {
+        console_log!("Exporting content model for {}", project_type);
+
+        let result = js_conversions::string_to_project_type(&project_type)
+            .and_then(|project_type| self.active_project(project_type))
+            .and_then(|project| {
+                let title = project.name().unwrap_or_default();
+                let collections = collection_models(&project)?;
+                Ok(model_to_schema(
+                    &title,
+                    &project.project_type(),
+                    &collections,
+                ))
+            });
+        match result {
+            Ok(schema) => Response::success(schema),
+            Err(e) => Response::error(&format!("Failed to export schema: {}", e)),
+        }
+    }
+
+    /// ACTOR Add the collections from an exported schema to the project
+    ///
+    /// Collections the project already has are left as they are and
+    /// reported as skipped.
+    pub(super) fn import_schema(&self, project_type: String, schema: Value) -> Response {
+        console_log!("Importing content model into {}", project_type);
+
+        let result =
+            js_conversions::string_to_project_type(&project_type).and_then(|project_type| {
+                let mut project = self.active_project(project_type.clone())?;
+                let result = apply_model(&mut project, schema_to_model(&schema)?)?;
+                match project_type {
+                    ProjectType::Site => self.set_site(project)?,
+                    ProjectType::Theme => self.set_theme(project)?,
+                }
+                Ok(result)
+            });
+        match result {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to import schema: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::{json, Map, Value};
+use wasm_bindgen::prelude::*;
+
+use super::collections::ensure_redirects_collection;
+use super::StoreInner;
+use crate::messages::Response;
+use crate::model::file::File;
+use crate::model::project::Project;
+use crate::model::{Entry, Page, Post};
+use crate::render::permalinks::{
+    apply_url_patterns, pattern_redirects, url_collisions, validate_url_pattern,
+};
+use crate::render::redirects::{FROM_FIELD, REDIRECTS_COLLECTION, TO_FIELD};
+use crate::render::site::PageSource;
+use crate::types::{ProjectType, SiteSettings};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Collections whose files can be given a URL pattern
+const PATTERN_COLLECTIONS: [&str; 2] = ["page", "post"];
+
+/// A collection's files with their cached meta, enough to work out URLs
+pub(super) async fn page_meta<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+) -> Result<Vec<PageSource>, String> {
+    let mut pages = Vec::new();
+    for file in project
+        .get_collection::<T>(collection_name)?
+        .get_files(collection_name)
+        .await?
+    {
+        pages.push(PageSource {
+            collection: collection_name.to_string(),
+            name: file.name()?,
+            meta: file.meta_fields(),
+            ..Default::default()
+        });
+    }
+    Ok(pages)
+}
+
+/// URLs that more than one page would share under `patterns`
+fn collisions(patterns: &BTreeMap<String, String>, pages: &[PageSource]) -> BTreeSet<String> {
+    let mut pages = pages.to_vec();
+    apply_url_patterns(patterns, &mut pages);
+    url_collisions(&pages)
+        .into_iter()
+        .map(|(url, _)| url)
+        .collect()
+}
+
+impl StoreInner {
+    /// ACTOR Get the active site's settings
+    pub(super) fn get_site_settings(&self) -> Response {
+        match self
+            .active_project(ProjectType::Site)
+            .and_then(|site| site.settings())
+        {
+            Ok(settings) => Response::success(settings),
+            Err(e) => Response::error(&format!("Failed to get site settings: {}", e)),
+        }
+    }
+
+    /// ACTOR Replace the active site's settings
+    ///
+    /// Changed URL patterns are checked, and redirects added, as in
+    /// `SetUrlPattern`.
+    pub(super) async fn set_site_settings(&self, settings: SiteSettings) -> Response {
+        console_log!("Setting site settings");
+
+        let result = async {
+            let mut site = self.active_project(ProjectType::Site)?;
+            let old = site.settings()?;
+            self.change_url_patterns(&mut site, &old.url_patterns, &settings.url_patterns)
+                .await?;
+            site.set_settings(&settings)?;
+            self.set_site(site)
+        }
+        .await;
+
+        match result {
+            Ok(()) => Response::success(settings),
+            Err(e) => Response::error(&e),
+        }
+    }
+
+    /// ACTOR Set or clear the URL pattern of the site's pages or posts
+    ///
+    /// Every file that moves gets a redirect from its old URL, and existing
+    /// redirects to that URL are pointed at the new one.
+    pub(super) async fn set_url_pattern(
+        &self,
+        collection: String,
+        pattern: Option<String>,
+    ) -> Response {
+        console_log!("Setting URL pattern for {}: {:?}", collection, pattern);
+
+        let result = async {
+            if !PATTERN_COLLECTIONS.contains(&collection.as_str()) {
+                return Err(format!(
+                    "{} has no URL pattern; only pages and posts do",
+                    collection
+                ));
+            }
+            let mut site = self.active_project(ProjectType::Site)?;
+            let mut settings = site.settings()?;
+            let old = settings.url_patterns.clone();
+            match pattern
+                .as_ref()
+                .filter(|pattern| !pattern.trim().is_empty())
+            {
+                Some(pattern) => {
+                    let pattern = pattern.trim().to_string();
+                    settings.url_patterns.insert(collection.clone(), pattern)
+                }
+                None => settings.url_patterns.remove(&collection),
+            };
+
+            let moves = self
+                .change_url_patterns(&mut site, &old, &settings.url_patterns)
+                .await?;
+            site.set_settings(&settings)?;
+            self.set_site(site)?;
+            Ok(json!({
+                "collection": collection,
+                "pattern": settings.url_patterns.get(&collection),
+                "redirects": moves
+                    .iter()
+                    .map(|(from, to)| json!({ "from": from, "to": to }))
+                    .collect::<Vec<_>>(),
+            }))
+        }
+        .await;
+
+        match result {
+            Ok(result) => Response::success(result),
+            Err(e) => Response::error(&format!("Failed to set URL pattern: {}", e)),
+        }
+    }
+
+    /// Check new URL patterns and redirect the files they move
+    ///
+    /// Fails, changing nothing, if a pattern is invalid or would give two
+    /// files a URL they don't already share.
+    async fn change_url_patterns(
+        &self,
+        site: &mut Project,
+        old: &BTreeMap<String, String>,
+        new: &BTreeMap<String, String>,
+    ) -> Result<Vec<(String, String)>, String> {
+        if old == new {
+            return Ok(Vec::new());
+        }
+        for pattern in new.values() {
+            validate_url_pattern(pattern)?;
+        }
+
+        let mut pages = page_meta::<Page>(site, "page").await?;
+        pages.extend(page_meta::<Post>(site, "post").await?);
+
+        let existing = collisions(old, &pages);
+        let added: Vec<String> = collisions(new, &pages)
+            .into_iter()
+            .filter(|url| !existing.contains(url))
+            .collect();
+        if !added.is_empty() {
+            return Err(format!(
+                "More than one file would be published at {}",
+                added.join(", ")
+            ));
+        }
+
+        let moves = pattern_redirects(old, new, &pages);
+        if !moves.is_empty() {
+            self.add_redirects(site, &moves).await?;
+        }
+        Ok(moves)
+    }
+
+    /// Redirect each `from` to its `to`, updating redirects that already
+    /// lead to or start from a moved URL
+    async fn add_redirects(
+        &self,
+        site: &mut Project,
+        moves: &[(String, String)],
+    ) -> Result<(), String> {
+        ensure_redirects_collection(site)?;
+        let existing = site
+            .get_collection::<Entry>(REDIRECTS_COLLECTION)?
+            .get_files(REDIRECTS_COLLECTION)
+            .await?;
+
+        for (from, to) in moves {
+            let mut found = false;
+            for entry in &existing {
+                let field = |name: &str| entry.get_field(name).ok();
+                if field(TO_FIELD).as_ref().and_then(Value::as_str) == Some(from.as_str()) {
+                    // Skip a hop rather than chaining redirects
+                    entry.set_field_value(TO_FIELD, &json!(to)).await?;
+                }
+                if field(FROM_FIELD).as_ref().and_then(Value::as_str) == Some(from.as_str()) {
+                    entry.set_field_value(TO_FIELD, &json!(to)).await?;
+                    found = true;
+                }
+            }
+            if !found {
+                let mut values = Map::new();
+                values.insert(FROM_FIELD.to_string(), json!(from));
+                values.insert(TO_FIELD.to_string(), json!(to));
+                self.create_entry(site, REDIRECTS_COLLECTION, from, &values)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+//! Sharing a project directly with another browser.
+//!
+//! The WebRTC connection (offer, ICE candidates and the copy-paste or QR
+//! invite carrying them) is set up in JS; this side only deals in Loro
+//! updates. Each peer sends its `GetShareState`, the other answers with
+//! `GetShareUpdates` for those versions, and the answer goes through
+//! `ApplyShareUpdates`. Asset bytes are stored outside the documents and
+//! aren't sent.
+
+use std::collections::{HashMap, HashSet};
+
+use loro::LoroDoc;
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::install::save_bytes;
+use super::maintenance::{document_ids, load_bytes};
+use super::{StoreInner, IDB_FILES_STORE, IDB_PROJECTS_STORE};
+use crate::js_conversions::js_conversions;
+use crate::messages::Response;
+use crate::model::project::{document_version, export_document, updates_since, Project};
+use crate::render::manifest::{base64_decode, base64_encode};
+use crate::types::ProjectType;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Emitted with the ids of the documents a peer's updates changed, so open
+/// editors can reload them
+pub const SHARE_UPDATED_EVENT: &str = "share:updated";
+
+/// A stored file document, if the file has been edited
+async fn load_document(id: &str) -> Result<Option<LoroDoc>, String> {
+    match load_bytes(IDB_FILES_STORE, id).await? {
+        Some(data) if !data.is_empty() => {
+            let doc = LoroDoc::new();
+            doc.import(&data)
+                .map_err(|e| format!("Failed to read document {}: {}", id, e))?;
+            Ok(Some(doc))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Document id → encoded version, for the project and each stored file
+async fn share_versions(project: &Project) -> Result<HashMap<String, String>, String> {
+    let mut versions = HashMap::new();
+    versions.insert(project.id(), base64_encode(&project.version()));
+    for id in document_ids(project).await? {
+        if let Some(doc) = load_document(&id).await? {
+            versions.insert(id, base64_encode(&document_version(&doc)));
+        }
+    }
+    Ok(versions)
+}
+
+/// Document id → the updates a peer at `versions` is missing
+async fn share_updates(
+    project: &Project,
+    versions: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, String> {
+    let peer_version = |id: &str| match versions.get(id) {
+        Some(version) => base64_decode(version),
+        None => Ok(Vec::new()),
+    };
+
+    let mut updates = HashMap::new();
+    let id = project.id();
+    if let Some(data) = project.updates_since(&peer_version(&id)?)? {
+        updates.insert(id, base64_encode(&data));
+    }
+    for id in document_ids(project).await? {
+        let doc = match load_document(&id).await? {
+            Some(doc) => doc,
+            None => continue,
+        };
+        if let Some(data) = updates_since(&doc, &peer_version(&id)?)? {
+            updates.insert(id, base64_encode(&data));
+        }
+    }
+    Ok(updates)
+}
+
+/// Merge a peer's updates into a file's stored document, creating it if
+/// this side has none yet
+async fn merge_document(id: &str, data: &[u8]) -> Result<(), String> {
+    let doc = load_document(id).await?.unwrap_or_else(LoroDoc::new);
+    doc.import(data)
+        .map_err(|e| format!("Failed to merge updates into {}: {}", id, e))?;
+    save_bytes(IDB_FILES_STORE, id, &export_document(&doc)?).await
+}
+
+/// A project made from a peer's updates alone, which must then hold its
+/// whole history
+fn project_from_updates(
+    data: Vec<u8>,
+    id: &str,
+    project_type: ProjectType,
+) -> Result<Project, String> {
+    let doc = LoroDoc::new();
+    let status = doc
+        .import(&data)
+        .map_err(|e| format!("Failed to read updates to {}: {}", id, e))?;
+    if status.pending.is_some() {
+        return Err(format!(
+            "The updates to {} build on changes this side doesn't have",
+            id
+        ));
+    }
+    let now = chrono::Utc::now().timestamp_millis() as f64;
+    Project::import(data, id.to_string(), project_type, now, now)
+}
+
+impl StoreInner {
+    /// This side's copy of a project that isn't active, if it has one:
+    /// open in the background, or stored
+    async fn inactive_project(
+        &self,
+        id: &str,
+        project_type: ProjectType,
+    ) -> Result<Option<Project>, String> {
+        if let ProjectType::Site = project_type {
+            let open = self
+                .open_sites
+                .lock()
+                .map_err(|_| "Failed to acquire open sites lock".to_string())?
+                .get(id)
+                .cloned();
+            if open.is_some() {
+                return Ok(open);
+            }
+        }
+        match load_bytes(IDB_PROJECTS_STORE, id).await? {
+            Some(bytes) if !bytes.is_empty() => {
+                let project = Project::import(bytes, id.to_string(), project_type, 0.0, 0.0)?;
+                self.mark_saved(&project);
+                Ok(Some(project))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// ACTOR The active project's id, name and document versions
+    ///
+    /// This goes in an invite, and is sent when a peer connects, so it can
+    /// answer with what this side is missing.
+    pub(super) async fn get_share_state(&self, project_type: String) -> Response {
+        console_log!("Getting share state of the {}", project_type);
+
+        let result = async {
+            let project_type = js_conversions::string_to_project_type(&project_type)?;
+            let project = self.active_project(project_type.clone())?;
+            Ok::<_, String>(json!({
+                "projectType": project_type.to_string(),
+                "projectId": project.id(),
+                "name": project.name().unwrap_or_default(),
+                "versions": share_versions(&project).await?,
+            }))
+        }
+        .await;
+
+        match result {
+            Ok(state) => Response::success(state),
+            Err(e) => Response::error(&format!("Failed to get share state: {}", e)),
+        }
+    }
+
+    /// ACTOR Updates to the active project and its documents that a peer
+    /// at `versions` doesn't have
+    pub(super) async fn get_share_updates(
+        &self,
+        project_type: String,
+        versions: HashMap<String, String>,
+    ) -> Response {
+        console_log!("Getting share updates for the {}", project_type);
+
+        let result = async {
+            let project_type = js_conversions::string_to_project_type(&project_type)?;
+            let project = self.active_project(project_type)?;
+            let updates = share_updates(&project, &versions).await?;
+            Ok::<_, String>(json!({
+                "projectId": project.id(),
+                "updates": updates,
+            }))
+        }
+        .await;
+
+        match result {
+            Ok(updates) => Response::success(updates),
+            Err(e) => Response::error(&format!("Failed to get share updates: {}", e)),
+        }
+    }
+
+    /// ACTOR Merge a peer's updates into the project with `project_id`
+    ///
+    /// If the project isn't active, the updates go into this side's copy of
+    /// it, which then becomes the active one. A peer joining from an invite
+    /// has no copy yet, so the project is made from the updates, as long as
+    /// they hold all of it. Updates to documents outside the project are
+    /// refused.
+    pub(super) async fn apply_share_updates(
+        &self,
+        project_type: String,
+        project_id: String,
+        updates: HashMap<String, String>,
+    ) -> Response {
+        console_log!("Applying {} share updates to {}", updates.len(), project_id);
+
+        let result = async {
+            let project_type = js_conversions::string_to_project_type(&project_type)?;
+            let mut project_update = match updates.get(&project_id) {
+                Some(data) => Some(base64_decode(data)?),
+                None => None,
+            };
+            let active = self
+                .active_project(project_type.clone())
+                .ok()
+                .filter(|project| project.id() == project_id);
+            let activate = active.is_none();
+            let project = match active {
+                Some(project) => project,
+                None => match self
+                    .inactive_project(&project_id, project_type.clone())
+                    .await?
+                {
+                    Some(project) => project,
+                    None => {
+                        let data = project_update
+                            .take()
+                            .ok_or_else(|| format!("No project {} to update", project_id))?;
+                        project_from_updates(data, &project_id, project_type.clone())?
+                    }
+                },
+            };
+            if let Some(data) = &project_update {
+                project.merge_updates(data)?;
+            }
+
+            // Files the peer's project updates added are in the project now
+            let documents: HashSet<String> = document_ids(&project).await?.into_iter().collect();
+            let mut file_updates = Vec::new();
+            for (id, data) in &updates {
+                if *id == project_id {
+                    continue;
+                }
+                if !documents.contains(id) {
+                    return Err(format!("{} isn't a document in project {}", id, project_id));
+                }
+                file_updates.push((id, base64_decode(data)?));
+            }
+
+            let mut changed = Vec::new();
+            for (id, data) in file_updates {
+                merge_document(id, &data).await?;
+                changed.push(id.clone());
+            }
+            if project_update.is_some() || activate {
+                changed.push(project_id.clone());
+            }
+            changed.sort();
+
+            if activate {
+                match project_type {
+                    ProjectType::Site => self.activate_site(project)?,
+                    ProjectType::Theme => self.set_theme(project)?,
+                }
+            }
+            Ok::<_, String>(changed)
+        }
+        .await;
+
+        match result {
+            Ok(changed) => {
+                let payload = json!({ "projectId": project_id, "documents": changed });
+                self.events
+                    .emit(SHARE_UPDATED_EVENT, JsValue::from_str(&payload.to_string()));
+                Response::success(payload)
+            }
+            Err(e) => Response::error(&format!("Failed to apply share updates: {}", e)),
+        }
+    }
+}
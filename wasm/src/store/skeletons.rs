@@ -0,0 +1,83 @@
+use serde_json::Value;
+
+use super::render::load_full_file;
+use super::StoreInner;
+use crate::model::file::{loro_doc_to_pm_doc, File};
+use crate::model::project::Project;
+use crate::model::{Model, Page};
+use crate::pm_json_to_loro;
+use crate::types::{FieldDefinition, FieldType, ProjectType};
+
+/// Collection of document templates, such as "Meeting notes" with its
+/// headings laid out; a new page, post or snippet can start as a copy of one.
+/// Sites and themes can both have them, and they're never published.
+pub(super) const SKELETON_COLLECTION: &str = "skeleton";
+
+/// Create the skeleton collection on projects made before skeletons existed
+pub(super) fn ensure_skeleton_collection(project: &mut Project) -> Result<(), String> {
+    if project
+        .get_collections()?
+        .iter()
+        .any(|(name, _)| name == SKELETON_COLLECTION)
+    {
+        return Ok(());
+    }
+
+    let mut model = Model::new();
+    model.insert(
+        "body",
+        FieldDefinition {
+            name: "body".to_string(),
+            field_type: FieldType::RichText,
+            required: true,
+        },
+    );
+    project.add_collection::<Page>(SKELETON_COLLECTION, model)?;
+    Ok(())
+}
+
+/// Whether a project's skeleton collection holds a file
+fn has_skeleton(project: &Project, skeleton_id: &str) -> bool {
+    match project.get_collection::<Page>(SKELETON_COLLECTION) {
+        Ok(skeletons) => skeletons
+            .node_ids()
+            .map_or(false, |ids| ids.iter().any(|id| id == skeleton_id)),
+        Err(_) => false,
+    }
+}
+
+/// Replace a new file's empty body with a copy of a skeleton's, made as new
+/// operations so the file shares no history with the skeleton
+pub(super) async fn copy_body<T: File>(file: &T, body: &Value) -> Result<(), String> {
+    let doc = file
+        .store()
+        .as_full()
+        .ok_or_else(|| "Rich-text file has no document".to_string())?;
+    pm_json_to_loro(doc, body)?;
+    file.save_to_indexeddb().await
+}
+
+impl StoreInner {
+    /// Make sure the active project has a skeleton collection to create files in
+    pub(super) fn ensure_skeletons(&self, project_type: &ProjectType) -> Result<(), String> {
+        let mut project = self.active_project(project_type.clone())?;
+        ensure_skeleton_collection(&mut project)
+    }
+
+    /// The ProseMirror body of a skeleton in the active site or theme
+    pub(super) async fn skeleton_body(&self, skeleton_id: &str) -> Result<Value, String> {
+        let found = [ProjectType::Site, ProjectType::Theme]
+            .iter()
+            .filter_map(|project_type| self.active_project(project_type.clone()).ok())
+            .any(|project| has_skeleton(&project, skeleton_id));
+        if !found {
+            return Err(format!("No skeleton with id {}", skeleton_id));
+        }
+
+        let skeleton = load_full_file::<Page>(SKELETON_COLLECTION, skeleton_id).await?;
+        match skeleton.store().as_full() {
+            Some(doc) => loro_doc_to_pm_doc(doc),
+            None => Err(format!("No stored document for skeleton {}", skeleton_id)),
+        }
+    }
+}
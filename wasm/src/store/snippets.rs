@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::render::{load_full_file, load_pages};
+use super::StoreInner;
+use crate::messages::Response;
+use crate::model::file::{insert_pm_nodes, loro_doc_to_pm_doc, File};
+use crate::model::project::Project;
+use crate::model::{Model, Page, Post};
+use crate::render::block_refs::BLOCK_REF_NODE;
+use crate::types::{FieldDefinition, FieldType, ProjectType};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Site collection of reusable rich-text fragments, such as callouts and
+/// author bios; its files are rich-text documents like pages, never published
+pub(super) const SNIPPET_COLLECTION: &str = "snippet";
+
+/// Rich-text collections a snippet can be inserted into
+const TARGET_COLLECTIONS: [&str; 3] = ["page", "post", SNIPPET_COLLECTION];
+
+/// Create the snippet collection on sites made before snippets existed
+pub(super) fn ensure_snippet_collection(project: &mut Project) -> Result<(), String> {
+    if project
+        .get_collections()?
+        .iter()
+        .any(|(name, _)| name == SNIPPET_COLLECTION)
+    {
+        return Ok(());
+    }
+
+    let mut model = Model::new();
+    model.insert(
+        "body",
+        FieldDefinition {
+            name: "body".to_string(),
+            field_type: FieldType::RichText,
+            required: true,
+        },
+    );
+    project.add_collection::<Page>(SNIPPET_COLLECTION, model)?;
+    Ok(())
+}
+
+/// Snippet bodies by id, for resolving the shared blocks pages reference
+pub(super) async fn load_blocks(
+    project: &Project,
+    warnings: &mut Vec<String>,
+) -> Result<BTreeMap<String, Value>, String> {
+    if !project
+        .get_collections()?
+        .iter()
+        .any(|(name, _)| name == SNIPPET_COLLECTION)
+    {
+        return Ok(BTreeMap::new());
+    }
+
+    let mut blocks = BTreeMap::new();
+    for snippet in load_pages::<Page>(project, SNIPPET_COLLECTION, warnings).await? {
+        if let Some(id) = snippet.meta.get("id").and_then(Value::as_str) {
+            blocks.insert(id.to_string(), snippet.body);
+        }
+    }
+    Ok(blocks)
+}
+
+/// Insert ProseMirror nodes into a stored rich-text file and save it
+async fn insert_into<T: File + Default>(
+    collection_name: &str,
+    id: &str,
+    position: Option<usize>,
+    nodes: &[Value],
+) -> Result<(usize, usize), String> {
+    let file = load_full_file::<T>(collection_name, id).await?;
+    let doc = file
+        .store()
+        .as_full()
+        .ok_or_else(|| format!("No stored document for {} {}", collection_name, id))?;
+    let inserted = insert_pm_nodes(doc, position, nodes)?;
+    file.save_to_indexeddb().await?;
+    Ok(inserted)
+}
+
+/// Which of the rich-text collections holds a site file
+async fn target_collection(site: &Project, file_id: &str) -> Result<&'static str, String> {
+    for collection_name in TARGET_COLLECTIONS.iter() {
+        let found = match *collection_name {
+            "post" => match site.get_collection::<Post>("post") {
+                Ok(posts) => posts.get_file(file_id, "post").await.is_ok(),
+                Err(_) => false,
+            },
+            name => match site.get_collection::<Page>(name) {
+                Ok(pages) => pages.get_file(file_id, name).await.is_ok(),
+                Err(_) => false,
+            },
+        };
+        if found {
+            return Ok(collection_name);
+        }
+    }
+    Err(format!("No page, post or snippet with id {}", file_id))
+}
+
+impl StoreInner {
+    /// Make sure the active site has a snippet collection to create files in
+    pub(super) fn ensure_snippets(&self, project_type: &ProjectType) -> Result<(), String> {
+        if !matches!(project_type, ProjectType::Site) {
+            return Err("Snippets belong to sites, not themes".to_string());
+        }
+        let mut site = self.active_project(ProjectType::Site)?;
+        ensure_snippet_collection(&mut site)
+    }
+
+    /// ACTOR Copy a snippet's content into a page, post or other snippet
+    ///
+    /// The snippet's nodes go in before the top-level node at `position`
+    /// (at the end if None) as new CRDT operations, so the copy merges with
+    /// edits made elsewhere and later changes to the snippet don't touch it.
+    /// As a `reference`, a single block_ref node goes in instead, and the
+    /// snippet's current content is rendered in its place.
+    pub(super) async fn insert_snippet(
+        &self,
+        file_id: String,
+        snippet_id: String,
+        position: Option<usize>,
+        reference: bool,
+    ) -> Response {
+        console_log!(
+            "Inserting snippet {} into {} at {:?} (reference: {})",
+            snippet_id,
+            file_id,
+            position,
+            reference
+        );
+
+        let result = async {
+            if file_id == snippet_id {
+                return Err("A snippet can't be inserted into itself".to_string());
+            }
+            let site = self.active_project(ProjectType::Site)?;
+            site.get_collection::<Page>(SNIPPET_COLLECTION)
+                .map_err(|_| "This site has no snippets".to_string())?
+                .get_file(&snippet_id, SNIPPET_COLLECTION)
+                .await
+                .map_err(|_| format!("No snippet with id {}", snippet_id))?;
+            let nodes = if reference {
+                vec![json!({ "type": BLOCK_REF_NODE, "attrs": { "id": snippet_id } })]
+            } else {
+                let snippet = load_full_file::<Page>(SNIPPET_COLLECTION, &snippet_id).await?;
+                let body = match snippet.store().as_full() {
+                    Some(doc) => loro_doc_to_pm_doc(doc)?,
+                    None => return Err(format!("No stored document for snippet {}", snippet_id)),
+                };
+                match body.get("content") {
+                    Some(Value::Array(nodes)) => nodes.clone(),
+                    _ => Vec::new(),
+                }
+            };
+            let collection_name = target_collection(&site, &file_id).await?;
+            let (from, to) = match collection_name {
+                "post" => insert_into::<Post>("post", &file_id, position, &nodes).await?,
+                name => insert_into::<Page>(name, &file_id, position, &nodes).await?,
+            };
+            Ok::<_, String>(json!({
+                "file_id": file_id,
+                "collection": collection_name,
+                "snippet_id": snippet_id,
+                "reference": reference,
+                "from": from,
+                "to": to,
+            }))
+        }
+        .await;
+
+        match result {
+            Ok(inserted) => Response::success(inserted),
+            Err(e) => Response::error(&format!("Failed to insert snippet: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+//! Recovery when the store starts.
+//!
+//! `Store::initialize` finishes what a closed tab left undone (the
+//! journal's pending writes, then changes flushed as it closed), then reads
+//! every saved project and its documents. A record that can't be read is
+//! moved aside under a `quarantine:` key, so it can still be sent in with a
+//! bug report, and sites whose record is gone are taken out of the
+//! workspace. Everything found or done is returned as a health report.
+
+use loro::LoroDoc;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use super::flush::replay_pending_flush;
+use super::install::save_bytes;
+use super::journal;
+use super::maintenance::{document_ids, load_bytes};
+use super::tabs::TabRole;
+use super::workspace::{forget_sites, load_workspace, WORKSPACE_KEY};
+use super::{StoreInner, IDB_ASSETS_STORE, IDB_FILES_STORE, IDB_PROJECTS_STORE};
+use crate::model::project::Project;
+use crate::types::ProjectType;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+pub const QUARANTINE_PREFIX: &str = "quarantine:";
+
+fn quarantine_key(key: &str) -> String {
+    format!("{}{}", QUARANTINE_PREFIX, key)
+}
+
+/// A record moved aside because it couldn't be read
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(super) struct Quarantined {
+    pub store: String,
+    pub key: String,
+    pub reason: String,
+}
+
+/// What `initialize` found and repaired
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub(super) struct HealthReport {
+    /// Saved projects that could be read
+    pub projects_found: usize,
+    /// The interrupted operation the journal rolled forward, if any
+    pub journal: Option<String>,
+    /// Projects written back by the journal or from changes flushed on close
+    pub projects_recovered: usize,
+    /// Files and assets written back by the journal
+    pub files_recovered: usize,
+    pub quarantined: Vec<Quarantined>,
+    /// Projects the workspace points to that have no saved record; sites
+    /// among them are taken out of the workspace
+    pub missing: Vec<String>,
+    /// Steps that failed, leaving what they'd have checked as it was
+    pub warnings: Vec<String>,
+}
+
+impl HealthReport {
+    /// Move an unreadable record to its quarantine key
+    async fn quarantine(&mut self, store: &str, key: &str, data: &[u8], reason: String) {
+        console_log!("Quarantining {} {}: {}", store, key, reason);
+        let moved = async {
+            save_bytes(store, &quarantine_key(key), data).await?;
+            save_bytes(store, key, &[]).await
+        }
+        .await;
+        match moved {
+            Ok(()) => self.quarantined.push(Quarantined {
+                store: store.to_string(),
+                key: key.to_string(),
+                reason,
+            }),
+            Err(e) => self
+                .warnings
+                .push(format!("Failed to quarantine {}: {}", key, e)),
+        }
+    }
+
+    /// Read a saved project, quarantining it if it can't be
+    async fn check_project(&mut self, id: &str, project_type: ProjectType) -> Option<Project> {
+        let data = match load_bytes(IDB_PROJECTS_STORE, id).await {
+            Ok(Some(data)) if !data.is_empty() => data,
+            Ok(_) => {
+                self.missing.push(id.to_string());
+                return None;
+            }
+            Err(e) => {
+                self.warnings.push(e);
+                return None;
+            }
+        };
+        match Project::import(data.clone(), id.to_string(), project_type, 0.0, 0.0) {
+            Ok(project) => {
+                self.projects_found += 1;
+                Some(project)
+            }
+            Err(e) => {
+                self.quarantine(IDB_PROJECTS_STORE, id, &data, e).await;
+                None
+            }
+        }
+    }
+
+    /// Quarantine the project's file documents that can't be read
+    ///
+    /// Files that were never edited have no document, which is fine.
+    async fn check_documents(&mut self, project: &Project) {
+        let ids = match document_ids(project).await {
+            Ok(ids) => ids,
+            Err(e) => {
+                self.warnings
+                    .push(format!("Failed to list files of {}: {}", project.id(), e));
+                return;
+            }
+        };
+        for id in ids {
+            let data = match load_bytes(IDB_FILES_STORE, &id).await {
+                Ok(Some(data)) if !data.is_empty() => data,
+                Ok(_) => continue,
+                Err(e) => {
+                    self.warnings.push(e);
+                    continue;
+                }
+            };
+            if let Err(e) = LoroDoc::new().import(&data) {
+                self.quarantine(IDB_FILES_STORE, &id, &data, e.to_string())
+                    .await;
+            }
+        }
+    }
+}
+
+impl StoreInner {
+    /// Recover from an unclean shutdown and check what's saved, before the
+    /// first `LoadState`
+    pub(super) async fn initialize(&self) -> HealthReport {
+        console_log!("Initializing store");
+        let mut report = HealthReport::default();
+        if self.tab_role() == TabRole::Reader {
+            // Repairs are writes, so they wait for the writing tab
+            report
+                .warnings
+                .push("Another tab is writing, so nothing was checked".to_string());
+            return report;
+        }
+
+        match journal::recover().await {
+            Ok(Some(journal)) => {
+                for write in &journal.writes {
+                    match write.store.as_str() {
+                        IDB_PROJECTS_STORE => report.projects_recovered += 1,
+                        IDB_FILES_STORE | IDB_ASSETS_STORE => report.files_recovered += 1,
+                        _ => {}
+                    }
+                }
+                report.journal = Some(journal.operation);
+            }
+            Ok(None) => {}
+            Err(e) => report
+                .warnings
+                .push(format!("Failed to recover journal: {}", e)),
+        }
+        match replay_pending_flush().await {
+            Ok(replayed) => report.projects_recovered += replayed,
+            Err(e) => report
+                .warnings
+                .push(format!("Failed to replay flushed changes: {}", e)),
+        }
+
+        let workspace = match load_workspace().await {
+            Ok(workspace) => workspace,
+            Err(e) => {
+                // Sites are still saved; they just can't be listed until saved again
+                if let Ok(Some(data)) = load_bytes(IDB_PROJECTS_STORE, WORKSPACE_KEY).await {
+                    report
+                        .quarantine(IDB_PROJECTS_STORE, WORKSPACE_KEY, &data, e)
+                        .await;
+                }
+                Default::default()
+            }
+        };
+
+        let mut theme_ids: Vec<String> = Vec::new();
+        for site in &workspace.sites {
+            if let Some(project) = report.check_project(&site.id, ProjectType::Site).await {
+                report.check_documents(&project).await;
+                if let Some(theme_id) = project.theme_id() {
+                    if !theme_ids.contains(&theme_id) {
+                        theme_ids.push(theme_id);
+                    }
+                }
+            }
+        }
+        for theme_id in &theme_ids {
+            if let Some(theme) = report.check_project(theme_id, ProjectType::Theme).await {
+                report.check_documents(&theme).await;
+            }
+        }
+
+        let gone: Vec<String> = workspace
+            .sites
+            .iter()
+            .map(|site| site.id.clone())
+            .filter(|id| {
+                report.missing.contains(id)
+                    || report
+                        .quarantined
+                        .iter()
+                        .any(|q| q.store == IDB_PROJECTS_STORE && q.key == *id)
+            })
+            .collect();
+        if !gone.is_empty() {
+            if let Err(e) = forget_sites(&gone).await {
+                report
+                    .warnings
+                    .push(format!("Failed to update the workspace: {}", e));
+            }
+        }
+
+        console_log!(
+            "Store initialized: {} projects, {} quarantined",
+            report.projects_found,
+            report.quarantined.len()
+        );
+        report
+    }
+}
@@ -0,0 +1,226 @@
+use std::collections::{BTreeMap, HashMap};
+
+use js_sys::Uint8Array;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+use super::render::load_pages;
+use super::report::{meta_date, CREATED_FIELD, DATE_FIELD};
+use super::{StoreInner, IDB_ASSETS_STORE, IDB_FILES_STORE};
+use crate::messages::Response;
+use crate::model::file::File;
+use crate::model::project::{doc_memory, DocMemory, Project};
+use crate::model::{Asset, Page, Post};
+use crate::render::graph::tags;
+use crate::render::site::PageSource;
+use crate::render::text::pm_to_text;
+use crate::types::ProjectType;
+use crate::FileStore;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// How many tags `top_tags` lists
+const TOP_TAGS: usize = 10;
+
+/// Bytes stored in IndexedDB, by kind
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(super) struct StorageUsed {
+    pub documents: usize,
+    pub assets: usize,
+}
+
+fn word_count(page: &PageSource) -> usize {
+    pm_to_text(&page.body).split_whitespace().count()
+}
+
+/// Totals, posts per month, top tags and average post length
+pub(super) fn project_stats(
+    pages: &[PageSource],
+    asset_count: usize,
+    storage: StorageUsed,
+) -> Value {
+    let posts: Vec<&PageSource> = pages
+        .iter()
+        .filter(|page| page.collection == "post")
+        .collect();
+    let post_words: Vec<usize> = posts.iter().map(|post| word_count(post)).collect();
+    let words: usize = pages.iter().map(word_count).sum();
+
+    let mut per_month: BTreeMap<String, usize> = BTreeMap::new();
+    let mut undated = 0;
+    for post in &posts {
+        let date = [DATE_FIELD, CREATED_FIELD]
+            .iter()
+            .find_map(|field| post.meta.get(*field).and_then(meta_date));
+        match date {
+            Some(date) => {
+                *per_month
+                    .entry(date.format("%Y-%m").to_string())
+                    .or_default() += 1
+            }
+            None => undated += 1,
+        }
+    }
+
+    // Counted case-insensitively, shown as first written
+    let mut tag_counts: HashMap<String, (String, usize)> = HashMap::new();
+    for page in pages {
+        for tag in tags(&page.meta) {
+            tag_counts
+                .entry(tag.to_lowercase())
+                .or_insert_with(|| (tag.clone(), 0))
+                .1 += 1;
+        }
+    }
+    let mut top_tags: Vec<(String, usize)> = tag_counts.into_iter().map(|(_, tag)| tag).collect();
+    top_tags.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top_tags.truncate(TOP_TAGS);
+
+    let average_post_words = if post_words.is_empty() {
+        0
+    } else {
+        post_words.iter().sum::<usize>() / post_words.len()
+    };
+
+    json!({
+        "totals": {
+            "pages": pages.len() - posts.len(),
+            "posts": posts.len(),
+            "words": words,
+            "assets": asset_count,
+        },
+        "storage": {
+            "documents": storage.documents,
+            "assets": storage.assets,
+            "total": storage.documents + storage.assets,
+        },
+        "posts_per_month": per_month,
+        "undated_posts": undated,
+        "top_tags": top_tags
+            .iter()
+            .map(|(tag, count)| json!({ "tag": tag, "count": count }))
+            .collect::<Vec<_>>(),
+        "average_post_words": average_post_words,
+    })
+}
+
+/// Size of a stored value, or 0 if there isn't one
+pub(super) async fn stored_bytes(store_name: &str, id: &str) -> usize {
+    match crate::load_data(store_name, id).await {
+        Ok(data) => Uint8Array::from(JsValue::from(data)).length() as usize,
+        Err(_) => 0,
+    }
+}
+
+async fn collection_bytes<T: File + Default>(
+    project: &Project,
+    collection_name: &str,
+    store_name: &str,
+) -> Result<(usize, usize), String> {
+    let files = project
+        .get_collection::<T>(collection_name)?
+        .get_files(collection_name)
+        .await?;
+    let mut bytes = 0;
+    for file in &files {
+        bytes += stored_bytes(store_name, &file.id()?).await;
+    }
+    Ok((files.len(), bytes))
+}
+
+fn document_memory(kind: &str, id: String, memory: DocMemory) -> Value {
+    json!({
+        "kind": kind,
+        "id": id,
+        "ops": memory.ops,
+        "changes": memory.changes,
+        "containers": memory.containers,
+        "text_length": memory.text_length,
+    })
+}
+
+/// Size of the module's linear memory, which grows but never shrinks
+fn wasm_memory_bytes() -> Option<u32> {
+    let memory = wasm_bindgen::memory()
+        .dyn_into::<js_sys::WebAssembly::Memory>()
+        .ok()?;
+    Some(js_sys::ArrayBuffer::from(memory.buffer()).byte_length())
+}
+
+impl StoreInner {
+    /// ACTOR What each open document holds, and how much memory wasm is using
+    ///
+    /// Op counts are the best guide to a slow tab: documents keep their
+    /// whole history in memory until they're compacted.
+    pub(super) fn get_memory_report(&self) -> Response {
+        console_log!("Building memory report");
+
+        let mut documents = Vec::new();
+        let projects = [("site", ProjectType::Site), ("theme", ProjectType::Theme)];
+        for (kind, project_type) in projects.iter() {
+            if let Ok(project) = self.active_project(project_type.clone()) {
+                documents.push(document_memory(kind, project.id(), project.memory()));
+            }
+        }
+        if let Some(file) = self.active_file.lock().unwrap().as_ref() {
+            // Files opened from the project's cache share its document
+            if let FileStore::Full(doc) = file.store() {
+                let id = file.id().unwrap_or_default();
+                documents.push(document_memory(&file.to_string(), id, doc_memory(doc)));
+            }
+        }
+
+        let total = |key: &str| -> u64 {
+            documents
+                .iter()
+                .filter_map(|document| document[key].as_u64())
+                .sum()
+        };
+        Response::success(json!({
+            "ops": total("ops"),
+            "text_length": total("text_length"),
+            "wasm_memory_bytes": wasm_memory_bytes(),
+            "documents": documents,
+        }))
+    }
+
+    /// ACTOR Overview numbers for the active site
+    pub(super) async fn get_project_stats(&self) -> Response {
+        console_log!("Building project stats");
+
+        match self.build_project_stats().await {
+            Ok(stats) => Response::success(stats),
+            Err(e) => Response::error(&format!("Failed to build project stats: {}", e)),
+        }
+    }
+
+    async fn build_project_stats(&self) -> Result<Value, String> {
+        let site = self.active_project(ProjectType::Site)?;
+        let mut warnings = Vec::new();
+        let mut pages = load_pages::<Page>(&site, "page", &mut warnings).await?;
+        pages.extend(load_pages::<Post>(&site, "post", &mut warnings).await?);
+
+        let (_, page_bytes) = collection_bytes::<Page>(&site, "page", IDB_FILES_STORE).await?;
+        let (_, post_bytes) = collection_bytes::<Post>(&site, "post", IDB_FILES_STORE).await?;
+        let (asset_count, asset_bytes) =
+            collection_bytes::<Asset>(&site, "asset", IDB_ASSETS_STORE).await?;
+        let storage = StorageUsed {
+            documents: page_bytes + post_bytes,
+            assets: asset_bytes,
+        };
+
+        let mut stats = project_stats(&pages, asset_count, storage);
+        stats["warnings"] = json!(warnings);
+        Ok(stats)
+    }
+}
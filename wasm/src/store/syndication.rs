@@ -0,0 +1,89 @@
+use serde_json::Value;
+use wasm_bindgen::prelude::*;
+
+use super::render::load_pages;
+use super::snippets::load_blocks;
+use super::StoreInner;
+use crate::model::Post;
+use crate::render::block_refs::resolve_all_block_refs;
+use crate::render::permalinks::apply_url_patterns;
+use crate::render::site::PageSource;
+use crate::render::syndication::{source_selects, syndicated_post};
+use crate::render::urls::site_root;
+use crate::types::SyndicationSource;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+impl StoreInner {
+    /// The posts one source shares into the site with id `own_id`
+    async fn syndicated_from(
+        &self,
+        own_id: &str,
+        source: &SyndicationSource,
+        warnings: &mut Vec<String>,
+    ) -> Result<Vec<PageSource>, String> {
+        if source.site_id == own_id {
+            return Err("a site can't syndicate its own posts".to_string());
+        }
+        console_log!("Syndicating posts from site {}", source.site_id);
+
+        let other = self.load_site(&source.site_id).await?;
+        let settings = other.settings()?;
+        let site_name = other.name().unwrap_or_default();
+        // Problems with the other site's own files are reported when it's built
+        let mut ignored = Vec::new();
+        let mut posts = load_pages::<Post>(&other, "post", &mut ignored).await?;
+        apply_url_patterns(&settings.url_patterns, &mut posts);
+        let blocks = load_blocks(&other, &mut ignored).await?;
+
+        let selected: Vec<PageSource> = posts
+            .into_iter()
+            .filter(|post| source_selects(source, post))
+            .collect();
+        for id in &source.posts {
+            let found = selected
+                .iter()
+                .any(|post| post.meta.get("id").and_then(Value::as_str) == Some(id.as_str()));
+            if !found {
+                warnings.push(format!(
+                    "syndication from {}: no published post with id {}",
+                    source.site_id, id
+                ));
+            }
+        }
+
+        let (selected, _) = resolve_all_block_refs(&selected, &blocks);
+        let root = site_root(&settings);
+        Ok(selected
+            .iter()
+            .map(|post| syndicated_post(post, &source.site_id, &site_name, root.as_deref()))
+            .collect())
+    }
+
+    /// Posts other sites in the workspace share into the site with id
+    /// `own_id`, skipping (with a warning) sources that can't be loaded
+    pub(super) async fn syndicated_posts(
+        &self,
+        own_id: &str,
+        sources: &[SyndicationSource],
+        warnings: &mut Vec<String>,
+    ) -> Vec<PageSource> {
+        let mut posts = Vec::new();
+        for source in sources {
+            match self.syndicated_from(own_id, source, warnings).await {
+                Ok(mut shared) => posts.append(&mut shared),
+                Err(e) => warnings.push(format!("syndication from {}: {}", source.site_id, e)),
+            }
+        }
+        posts
+    }
+}
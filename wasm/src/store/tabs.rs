@@ -0,0 +1,217 @@
+//! One writer among the app's open tabs.
+//!
+//! Every tab has its own copy of each project, and two of them saving
+//! would clobber each other's IndexedDB writes. `Store::coordinate_tabs`
+//! has the tabs elect a writer with a Web Lock; the rest open read-only,
+//! refusing messages that write to storage. The writer tells them over a
+//! BroadcastChannel when it has written, as `tabs:external_change` events,
+//! so they can reload. When the writer closes, the next tab in line takes
+//! the lock and gets a `tabs:role` event.
+//!
+//! A store that never coordinates is the writer, as it was before there
+//! was anything to coordinate.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::freeze::changed_project;
+use super::StoreInner;
+use crate::messages::{Message, Response};
+use crate::types::ProjectType;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// The Web Lock held by the writing tab
+pub const WRITER_LOCK: &str = "organ_pages:writer";
+/// The BroadcastChannel tabs talk over
+pub const TAB_CHANNEL: &str = "organ_pages:tabs";
+
+/// This tab's role changed: `{role}`
+pub const TAB_ROLE_EVENT: &str = "tabs:role";
+/// The writing tab wrote to storage: `{kind, site_id, theme_id}`, where
+/// kind is "saved" for `SaveState` and "changed" for anything else
+pub const EXTERNAL_CHANGE_EVENT: &str = "tabs:external_change";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(super) enum TabRole {
+    Writer,
+    Reader,
+}
+
+impl TabRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TabRole::Writer => "writer",
+            TabRole::Reader => "reader",
+        }
+    }
+}
+
+impl Default for TabRole {
+    fn default() -> Self {
+        TabRole::Writer
+    }
+}
+
+#[derive(Default)]
+pub(super) struct TabState {
+    role: TabRole,
+    coordinated: bool,
+    channel: Option<JsValue>,
+}
+
+/// How a message that writes to storage is announced to other tabs, or
+/// None if it writes nothing
+///
+/// Cursors are left out: each tab keeps its own place.
+pub(super) fn storage_write(message: &Message) -> Option<&'static str> {
+    if changed_project(message).is_some() {
+        return Some("changed");
+    }
+    match message {
+        Message::SaveState { .. } => Some("saved"),
+        Message::CreateSite { .. }
+        | Message::CreateTheme { .. }
+        | Message::SetProjectReadOnly { .. }
+        | Message::CompactProjects { .. }
+        | Message::ImportProject { .. }
+        | Message::ImportStaticSite { .. }
+        | Message::InstallFromUrl { .. }
+        | Message::RecordDeploy { .. } => Some("changed"),
+        Message::VerifyProject { repair } if repair.any() => Some("changed"),
+        _ => None,
+    }
+}
+
+impl StoreInner {
+    pub(super) fn tab_role(&self) -> TabRole {
+        self.tabs.lock().unwrap().role
+    }
+
+    pub(super) fn set_tab_role(&self, role: TabRole) {
+        let changed = {
+            let mut tabs = self.tabs.lock().unwrap();
+            let changed = tabs.role != role;
+            tabs.role = role;
+            changed
+        };
+        if changed {
+            console_log!("This tab is now the {:?}", role);
+            let payload = json!({ "role": role });
+            self.events
+                .emit(TAB_ROLE_EVENT, JsValue::from_str(&payload.to_string()));
+        }
+    }
+
+    /// Join the other tabs and find out whether this one writes
+    ///
+    /// Calling it again returns the role already settled.
+    pub(super) async fn coordinate_tabs(&self) -> Result<TabRole, String> {
+        if self.tabs.lock().unwrap().coordinated {
+            return Ok(self.tab_role());
+        }
+
+        let store = self.clone();
+        let on_message = Closure::wrap(Box::new(move |data: JsValue| {
+            store.receive_tab_message(data);
+        }) as Box<dyn FnMut(JsValue)>);
+        let channel = crate::open_tab_channel(TAB_CHANNEL, on_message.as_ref().unchecked_ref());
+        // Both closures live as long as the tab
+        on_message.forget();
+
+        let store = self.clone();
+        let on_acquired = Closure::wrap(Box::new(move || {
+            store.set_tab_role(TabRole::Writer);
+        }) as Box<dyn FnMut()>);
+        let held = crate::claim_writer_lock(WRITER_LOCK, on_acquired.as_ref().unchecked_ref())
+            .await
+            .map_err(|e| format!("Failed to claim the writer lock: {:?}", e))?;
+        on_acquired.forget();
+
+        {
+            let mut tabs = self.tabs.lock().unwrap();
+            tabs.coordinated = true;
+            tabs.channel = channel;
+        }
+        let role = if held {
+            TabRole::Writer
+        } else {
+            TabRole::Reader
+        };
+        self.set_tab_role(role);
+        Ok(role)
+    }
+
+    /// Refuse a message that writes to storage unless this tab is the writer
+    pub(super) fn check_tab_writer(&self, message: &Message) -> Result<(), String> {
+        if self.tab_role() == TabRole::Writer || storage_write(message).is_none() {
+            return Ok(());
+        }
+        Err(
+            "The workspace is open in another tab, so this one is read-only until it closes"
+                .to_string(),
+        )
+    }
+
+    /// Tell other tabs this one wrote to storage
+    pub(super) fn announce_storage_write(&self, kind: &str, response: &Response) {
+        if let Response::Error(_) = response {
+            return;
+        }
+        let channel = match self.tabs.lock().unwrap().channel.clone() {
+            Some(channel) => channel,
+            None => return,
+        };
+        let id_of = |project_type: ProjectType| {
+            self.active_project(project_type)
+                .ok()
+                .map(|project| project.id())
+        };
+        let payload = json!({
+            "kind": kind,
+            "site_id": id_of(ProjectType::Site),
+            "theme_id": id_of(ProjectType::Theme),
+        });
+        crate::post_tab_message(&channel, &payload.to_string());
+    }
+
+    /// Pass a write announced by the writer on to listeners
+    fn receive_tab_message(&self, data: JsValue) {
+        let payload = match data
+            .as_string()
+            .map(|data| serde_json::from_str::<Value>(&data))
+        {
+            Some(Ok(payload)) => payload,
+            _ => {
+                console_log!("Ignoring unreadable message from another tab");
+                return;
+            }
+        };
+        self.events.emit(
+            EXTERNAL_CHANGE_EVENT,
+            JsValue::from_str(&payload.to_string()),
+        );
+    }
+
+    /// ACTOR Whether this tab writes to storage or is read-only while
+    /// another tab does
+    pub(super) fn get_tab_role(&self) -> Response {
+        let tabs = self.tabs.lock().unwrap();
+        Response::success(json!({
+            "role": tabs.role,
+            "coordinated": tabs.coordinated,
+            "read_only": tabs.role == TabRole::Reader,
+        }))
+    }
+}
@@ -3,6 +3,8 @@
 mod tests {
     use crate::{
         messages::{FileUpdate, Response},
+        model::file::File,
+        types::{ExportFilter, ListFilesOptions},
         ProjectType, StoreInner, ID_KEY,
     };
 
@@ -165,6 +167,7 @@ mod tests {
                 "site".to_string(),
                 "page".to_string(),
                 file_name.to_string(),
+                None,
             )
             .await;
 
@@ -223,6 +226,7 @@ mod tests {
                 "site".to_string(),
                 "page".to_string(),
                 file_name.to_string(),
+                None,
             )
             .await;
 
@@ -282,7 +286,12 @@ mod tests {
         let file_names = vec!["page1", "page2", "page3"];
         for name in &file_names {
             let response = store
-                .create_file("site".to_string(), "page".to_string(), name.to_string())
+                .create_file(
+                    "site".to_string(),
+                    "page".to_string(),
+                    name.to_string(),
+                    None,
+                )
                 .await;
             console_log!("(test_list_files) Create file Response: {:?}", response);
             assert!(
@@ -294,7 +303,11 @@ mod tests {
 
         // List the files
         let response = store
-            .list_files("site".to_string(), "page".to_string())
+            .list_files(
+                "site".to_string(),
+                "page".to_string(),
+                ListFilesOptions::default(),
+            )
             .await;
         match response {
             Response::Success(value) => {
@@ -320,6 +333,1866 @@ mod tests {
         }
     }
 
+    #[wasm_bindgen_test]
+    async fn test_list_files_paged() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        for name in &["page1", "page2", "page3"] {
+            store
+                .create_file(
+                    "site".to_string(),
+                    "page".to_string(),
+                    name.to_string(),
+                    None,
+                )
+                .await;
+        }
+
+        let mut options = ListFilesOptions {
+            fields: Some(vec!["name".to_string()]),
+            limit: Some(3),
+            ..Default::default()
+        };
+        let mut names = Vec::new();
+        let mut pages = 0;
+        loop {
+            let response = store
+                .list_files("site".to_string(), "page".to_string(), options.clone())
+                .await;
+            let value = match response {
+                Response::Success(value) => value,
+                Response::Error(e) => panic!("Failed to list files: {}", e),
+            };
+            pages += 1;
+            for file in value["files"].as_array().expect("Files must be an array") {
+                let keys: Vec<&String> = file.as_object().unwrap().keys().collect();
+                assert_eq!(keys, vec!["id", "name"]);
+                names.push(file["name"].as_str().unwrap().to_string());
+            }
+            match value["next_cursor"].as_str() {
+                Some(cursor) => options.cursor = Some(cursor.to_string()),
+                None => break,
+            }
+        }
+
+        // The default page plus the three created above
+        assert_eq!(pages, 2);
+        assert_eq!(names.len(), 4);
+
+        options.cursor = Some("missing".to_string());
+        let response = store
+            .list_files("site".to_string(), "page".to_string(), options)
+            .await;
+        assert!(matches!(response, Response::Error(_)));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_create_post_from_import() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let imported = crate::import::ImportedDocument {
+            title: Some("Imported Essay".to_string()),
+            content: json!({
+                "type": "doc",
+                "content": [
+                    { "type": "heading", "attrs": { "level": 2 }, "content": [
+                        { "type": "text", "text": "Intro" }
+                    ]},
+                    { "type": "paragraph", "content": [
+                        { "type": "text", "text": "bold", "marks": [{ "type": "strong" }] }
+                    ]}
+                ]
+            }),
+            assets: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        let result = store
+            .create_post_from_import("imported-essay", imported)
+            .await
+            .expect("Failed to create post from import");
+        let file_id = result["file"][ID_KEY].as_str().unwrap().to_string();
+
+        let response = store
+            .get_file("site".to_string(), "post".to_string(), file_id)
+            .await;
+        match response {
+            Response::Success(value) => {
+                assert_eq!(value["name"].as_str().unwrap(), "imported-essay");
+                assert_eq!(value["title"].as_str().unwrap(), "Imported Essay");
+            }
+            Response::Error(e) => panic!("Failed to get imported post: {}", e),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_import_submission() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let submission: crate::types::Submission = serde_json::from_value(json!({
+            "title": "Letters from the Allotment",
+            "body": "Some *notes* from the garden.",
+            "author": { "name": "Sam", "email": "sam@example.com" },
+            "submitted_at": "2025-04-01T09:00:00Z"
+        }))
+        .unwrap();
+
+        let result = match store.import_submission(submission).await {
+            Response::Success(result) => result,
+            Response::Error(e) => panic!("Failed to import submission: {}", e),
+        };
+        assert_eq!(result["file"]["name"], json!("letters-from-the-allotment"));
+        assert_eq!(result["file"]["status"], json!("draft"));
+
+        match store.list_submissions(Some("pending".to_string())).await {
+            Response::Success(submissions) => {
+                let submissions = submissions.as_array().unwrap();
+                assert_eq!(submissions.len(), 1);
+                assert_eq!(submissions[0]["submitted_by"], json!("Sam"));
+                assert_eq!(submissions[0]["review"], json!("pending"));
+            }
+            Response::Error(e) => panic!("Failed to list submissions: {}", e),
+        }
+        match store.list_submissions(Some("approved".to_string())).await {
+            Response::Success(submissions) => assert_eq!(submissions, json!([])),
+            Response::Error(e) => panic!("Failed to list submissions: {}", e),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_find_replace() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let submission: crate::types::Submission = serde_json::from_value(json!({
+            "title": "Garden notes",
+            "body": "Some *notes* from the garden. Garden gnomes welcome."
+        }))
+        .unwrap();
+        if let Response::Error(e) = store.import_submission(submission).await {
+            panic!("Failed to import submission: {}", e);
+        }
+
+        let find = |query: &str, replacement: &str, dry_run: bool| {
+            store.find_replace(
+                query.to_string(),
+                replacement.to_string(),
+                crate::types::FindReplaceScope::default(),
+                false,
+                false,
+                dry_run,
+            )
+        };
+
+        match find("garden", "allotment", true).await {
+            Response::Success(report) => {
+                assert_eq!(report["total"], json!(3));
+                let matches = report["files"][0]["matches"].as_array().unwrap();
+                assert_eq!(matches[0]["field"], json!("title"));
+                assert_eq!(matches[1]["field"], json!("content"));
+                assert_eq!(matches[2]["match"], json!("Garden"));
+            }
+            Response::Error(e) => panic!("Failed to find: {}", e),
+        }
+        match find("garden", "allotment", false).await {
+            Response::Success(report) => assert_eq!(report["total"], json!(3)),
+            Response::Error(e) => panic!("Failed to replace: {}", e),
+        }
+        match find("garden", "allotment", true).await {
+            Response::Success(report) => assert_eq!(report["total"], json!(0)),
+            Response::Error(e) => panic!("Failed to find: {}", e),
+        }
+        match find("allotment", "", true).await {
+            Response::Success(report) => assert_eq!(report["total"], json!(3)),
+            Response::Error(e) => panic!("Failed to find: {}", e),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_import_csv_into_custom_collection() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let fields = vec![
+            crate::types::FieldDefinition {
+                name: "date".to_string(),
+                field_type: crate::types::FieldType::DateTime,
+                required: true,
+            },
+            crate::types::FieldDefinition {
+                name: "capacity".to_string(),
+                field_type: crate::types::FieldType::Number,
+                required: false,
+            },
+        ];
+        let response = store.add_collection("site".to_string(), "events".to_string(), fields);
+        assert!(
+            matches!(response, Response::Success(_)),
+            "Failed to add collection: {:?}",
+            response
+        );
+
+        let mut mapping = std::collections::HashMap::new();
+        mapping.insert("Event".to_string(), "name".to_string());
+        mapping.insert("When".to_string(), "date".to_string());
+        mapping.insert("Seats".to_string(), "capacity".to_string());
+        let data = "Event,When,Seats\nReading,2025-03-21,40\nPicnic,,12\n".to_string();
+
+        let response = store.import_csv("events".to_string(), mapping, data).await;
+        let result = match response {
+            Response::Success(value) => value,
+            Response::Error(e) => panic!("Failed to import CSV: {}", e),
+        };
+        assert_eq!(result["created"].as_array().unwrap().len(), 1);
+        assert_eq!(result["errors"][0]["row"], json!(2));
+
+        let response = store
+            .list_files(
+                "site".to_string(),
+                "events".to_string(),
+                ListFilesOptions::default(),
+            )
+            .await;
+        match response {
+            Response::Success(value) => {
+                let files = value.as_array().expect("Files must be an array");
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0]["name"], json!("Reading"));
+                assert_eq!(files[0]["date"], json!("2025-03-21"));
+                assert_eq!(files[0]["capacity"], json!(40));
+            }
+            Response::Error(e) => panic!("Failed to list entries: {}", e),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_add_collection_rejects_built_in_name() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let response = store.add_collection("site".to_string(), "post".to_string(), Vec::new());
+        assert!(matches!(response, Response::Error(_)));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_render_site_with_listing() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        // Theme template for the listing page
+        let mut theme = store.active_project(ProjectType::Theme).unwrap();
+        let builder = theme
+            .create_file::<crate::model::Template>(
+                "events",
+                "template",
+                crate::FileStore::Full(loro::LoroDoc::new()),
+            )
+            .unwrap();
+        let template = theme.attach_file(builder).await.unwrap();
+        crate::model::HasContent::insert_content(
+            &template,
+            "{{#each entries}}<li>{{name}}</li>{{/each}}",
+            0,
+        )
+        .unwrap();
+        template.save_to_indexeddb().await.unwrap();
+
+        let fields = vec![crate::types::FieldDefinition {
+            name: "date".to_string(),
+            field_type: crate::types::FieldType::DateTime,
+            required: true,
+        }];
+        store.add_collection("site".to_string(), "events".to_string(), fields);
+        let mut mapping = std::collections::HashMap::new();
+        mapping.insert("Event".to_string(), "name".to_string());
+        mapping.insert("When".to_string(), "date".to_string());
+        store
+            .import_csv(
+                "events".to_string(),
+                mapping,
+                "Event,When\nFair,2030-05-03\nReading,2030-04-12\n".to_string(),
+            )
+            .await;
+
+        let listing = crate::types::ListingConfig {
+            path: "events".to_string(),
+            template: "events".to_string(),
+            detail_template: None,
+            sort_by: Some("date".to_string()),
+            descending: false,
+            group_by: None,
+            group_by_date: None,
+            upcoming_field: None,
+        };
+        let response = store.set_collection_listing(
+            "site".to_string(),
+            "events".to_string(),
+            Some(listing),
+        );
+        assert!(matches!(response, Response::Success(_)));
+
+        match store.render_site(None, ExportFilter::default()).await {
+            Response::Success(value) => {
+                assert_eq!(
+                    value["files"]["events/index.html"],
+                    json!("<li>Reading</li><li>Fair</li>")
+                );
+                let main = value["files"]["main/index.html"].as_str().unwrap();
+                assert!(main.contains("<h1>Hello World Title!</h1>"));
+                assert!(value["files"]["style.css"].is_string());
+            }
+            Response::Error(e) => panic!("Failed to render site: {}", e),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_watch_preview() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let site = store.active_project(ProjectType::Site).unwrap();
+        let pages = site
+            .get_collection::<crate::model::Page>("page")
+            .unwrap()
+            .get_files("page")
+            .await
+            .unwrap();
+        let file_id = pages[0].id().unwrap();
+
+        match store.watch_preview(file_id.clone()).await {
+            Response::Success(value) => {
+                assert_eq!(value["file_id"], json!(file_id));
+                assert!(value["html"]
+                    .as_str()
+                    .unwrap()
+                    .contains("<h1>Hello World Title!</h1>"));
+            }
+            Response::Error(e) => panic!("Failed to watch preview: {}", e),
+        }
+        assert!(store.preview_watch.lock().unwrap().is_some());
+
+        assert!(matches!(store.unwatch_preview(), Response::Success(_)));
+        assert!(store.preview_watch.lock().unwrap().is_none());
+
+        let response = store.watch_preview("missing".to_string()).await;
+        assert!(matches!(response, Response::Error(_)));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_install_from_url_requires_https() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+
+        let response = store
+            .install_from_url("http://themes.example.com/zine.zip".to_string(), None)
+            .await;
+        match response {
+            Response::Error(e) => assert!(e.contains("https")),
+            Response::Success(_) => panic!("Installed a bundle over plain http"),
+        }
+        assert!(store.active_theme.lock().unwrap().is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_content_report() {
+        let meta = |value: serde_json::Value| value.as_object().unwrap().clone();
+        let files = vec![
+            (
+                "post".to_string(),
+                meta(json!({ "id": "a", "name": "launch", "date": "2025-03-01" })),
+            ),
+            (
+                "post".to_string(),
+                meta(json!({
+                    "id": "b",
+                    "name": "notes",
+                    "status": "draft",
+                    "created": "2024-11-02",
+                })),
+            ),
+            (
+                "post".to_string(),
+                meta(json!({
+                    "id": "c",
+                    "name": "fresh",
+                    "status": "draft",
+                    "created": "2025-01-20",
+                })),
+            ),
+            (
+                "page".to_string(),
+                meta(json!({ "id": "d", "name": "about", "updated": "2023-06-30T10:00:00Z" })),
+            ),
+            (
+                "page".to_string(),
+                meta(json!({ "id": "e", "name": "contact", "updated": 1735689600000.0 })),
+            ),
+            ("page".to_string(), meta(json!({ "id": "f", "name": "home" }))),
+        ];
+        let today = chrono::NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+
+        let report = crate::store::report::content_report(&files, &Default::default(), today);
+
+        assert_eq!(report["scheduled"][0]["id"], json!("a"));
+        assert_eq!(report["scheduled"][0]["date"], json!("2025-03-01"));
+        assert_eq!(report["old_drafts"].as_array().unwrap().len(), 1);
+        assert_eq!(report["old_drafts"][0]["id"], json!("b"));
+        assert_eq!(report["stale_pages"].as_array().unwrap().len(), 1);
+        assert_eq!(report["stale_pages"][0]["updated"], json!("2023-06-30"));
+        assert_eq!(report["undated"], json!(1));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_project_stats() {
+        use crate::render::site::PageSource;
+
+        let page = |collection: &str, meta: serde_json::Value, text: &str| PageSource {
+            collection: collection.to_string(),
+            name: "file".to_string(),
+            meta: meta.as_object().unwrap().clone(),
+            body: json!({ "type": "doc", "content": [
+                { "type": "paragraph", "content": [{ "type": "text", "text": text }] }
+            ]}),
+        };
+        let pages = vec![
+            page("page", json!({}), "About the zine collective"),
+            page(
+                "post",
+                json!({ "date": "2025-03-02", "tags": ["Zines", "events"] }),
+                "Fair on Saturday",
+            ),
+            page(
+                "post",
+                json!({ "date": "2025-03-20", "tags": "zines" }),
+                "Two new issues out now",
+            ),
+            page("post", json!({ "created": "2025-04-01" }), "Hello"),
+            page("post", json!({}), "Draft"),
+        ];
+        let storage = crate::store::stats::StorageUsed {
+            documents: 1200,
+            assets: 3400,
+        };
+
+        let stats = crate::store::stats::project_stats(&pages, 2, storage);
+
+        assert_eq!(
+            stats["totals"],
+            json!({ "pages": 1, "posts": 4, "words": 14, "assets": 2 })
+        );
+        assert_eq!(stats["storage"]["total"], json!(4600));
+        assert_eq!(stats["posts_per_month"], json!({ "2025-03": 2, "2025-04": 1 }));
+        assert_eq!(stats["undated_posts"], json!(1));
+        assert_eq!(
+            stats["top_tags"],
+            json!([{ "tag": "Zines", "count": 2 }, { "tag": "events", "count": 1 }])
+        );
+        assert_eq!(stats["average_post_words"], json!(2));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_author_role() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+        store.set_role(crate::store::roles::Role::Author);
+
+        let response = store
+            .handle_message(crate::messages::Message::SetSiteSettings {
+                settings: Default::default(),
+            })
+            .await;
+        assert!(matches!(response, Response::Error(_)));
+
+        let response = store
+            .handle_message(crate::messages::Message::CreateFile {
+                project_type: "site".to_string(),
+                collection_name: "post".to_string(),
+                name: "field-notes".to_string(),
+                skeleton: None,
+            })
+            .await;
+        let file_id = match response {
+            Response::Success(value) => value["id"].as_str().unwrap().to_string(),
+            Response::Error(e) => panic!("Author couldn't create a post: {}", e),
+        };
+
+        let update = |updates| crate::messages::Message::UpdateFile {
+            project_type: "site".to_string(),
+            collection_name: "post".to_string(),
+            file_id: file_id.clone(),
+            updates,
+        };
+        let response = store
+            .handle_message(update(FileUpdate::SetTitle("Field notes".to_string())))
+            .await;
+        assert!(matches!(response, Response::Success(_)));
+
+        let publish = FileUpdate::SetField {
+            name: "status".to_string(),
+            value: "published".to_string(),
+        };
+        let response = store.handle_message(update(publish.clone())).await;
+        assert!(matches!(response, Response::Error(_)));
+
+        store.set_role(crate::store::roles::Role::Editor);
+        let response = store.handle_message(update(publish)).await;
+        assert!(matches!(response, Response::Success(_)));
+
+        store.set_role(crate::store::roles::Role::Author);
+        let response = store
+            .handle_message(update(FileUpdate::SetTitle("Edited".to_string())))
+            .await;
+        assert!(matches!(response, Response::Error(_)));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_viewer_role() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+        store.set_role(crate::store::roles::Role::Viewer);
+
+        let response = store
+            .handle_message(crate::messages::Message::InitDefault)
+            .await;
+        assert!(matches!(response, Response::Error(_)));
+
+        let response = store
+            .handle_message(crate::messages::Message::ListCollections {
+                project_type: "site".to_string(),
+            })
+            .await;
+        assert!(matches!(response, Response::Success(_)));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_save_form_replaces_existing() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let mut form: crate::types::FormDefinition = serde_json::from_value(json!({
+            "name": "contact",
+            "provider": "Formspree",
+            "fields": [{ "name": "email", "label": "Email", "input": "Email", "required": true }]
+        }))
+        .unwrap();
+
+        match store.save_form(form.clone()).await {
+            Response::Success(value) => {
+                assert_eq!(value["warnings"], json!(["Formspree forms need an endpoint"]))
+            }
+            Response::Error(e) => panic!("Failed to save form: {}", e),
+        }
+
+        form.endpoint = Some("https://formspree.io/f/abc".to_string());
+        match store.save_form(form).await {
+            Response::Success(value) => assert_eq!(value["warnings"], json!([])),
+            Response::Error(e) => panic!("Failed to update form: {}", e),
+        }
+
+        let site = store.active_project(ProjectType::Site).unwrap();
+        let mut warnings = Vec::new();
+        let forms = super::super::forms::load_forms(&site, &mut warnings)
+            .await
+            .unwrap();
+        assert_eq!(forms.len(), 1);
+        assert_eq!(forms[0].fields[0].name, "email");
+        assert_eq!(
+            forms[0].endpoint.as_deref(),
+            Some("https://formspree.io/f/abc")
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_publish_diff_after_deploy() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        match store.get_publish_diff(None).await {
+            Response::Success(diff) => {
+                assert!(!diff["added"].as_array().unwrap().is_empty());
+                assert_eq!(diff["last_deployed_at"], json!(null));
+            }
+            Response::Error(e) => panic!("Failed to get publish diff: {}", e),
+        }
+
+        assert!(matches!(
+            store.record_deploy(None).await,
+            Response::Success(_)
+        ));
+
+        match store.get_publish_diff(None).await {
+            Response::Success(diff) => {
+                assert_eq!(diff["added"], json!([]));
+                assert_eq!(diff["changed"], json!([]));
+                assert_eq!(diff["removed"], json!([]));
+            }
+            Response::Error(e) => panic!("Failed to get publish diff: {}", e),
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_compaction_due() {
+        use super::super::maintenance::compaction_due;
+        use crate::types::CompactionSchedule;
+        use chrono::{TimeZone, Utc};
+
+        let now = Utc.with_ymd_and_hms(2025, 3, 10, 12, 0, 0).unwrap();
+        let weekly = CompactionSchedule {
+            every_days: Some(7),
+            shallow: false,
+        };
+        assert!(compaction_due(&weekly, None, now));
+        assert!(compaction_due(&weekly, Some("2025-03-03T12:00:00+00:00"), now));
+        assert!(!compaction_due(&weekly, Some("2025-03-04T09:00:00+00:00"), now));
+        assert!(!compaction_due(&CompactionSchedule::default(), None, now));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_compact_projects() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        match store.compact_projects(true).await {
+            Response::Success(result) => {
+                assert_eq!(result["shallow"], json!(true));
+                assert!(!result["documents"].as_array().unwrap().is_empty());
+            }
+            Response::Error(e) => panic!("Failed to compact projects: {}", e),
+        }
+
+        // The compacted site still has its files, and saves as before
+        let site = store.active_site.lock().unwrap().clone().unwrap();
+        assert!(site.compacted_at().is_some());
+        let pages = site
+            .get_collection::<crate::model::Page>("page")
+            .unwrap()
+            .get_files("page")
+            .await
+            .unwrap();
+        assert_eq!(pages.len(), 1);
+        assert!(matches!(
+            store.save_state("site".to_string()).await,
+            Response::Success(_)
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_verify_project_relinks_theme() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let verified = match store.verify_project(Default::default()).await {
+            Response::Success(result) => result,
+            Response::Error(e) => panic!("Failed to verify projects: {}", e),
+        };
+        assert_eq!(verified["issues"], json!([]));
+
+        let mut site = store.active_site.lock().unwrap().clone().unwrap();
+        site.set_theme_id("no-such-theme").unwrap();
+        store.set_site(site).unwrap();
+
+        let repair = crate::types::RepairOptions {
+            relink_theme: true,
+            ..Default::default()
+        };
+        match store.verify_project(repair).await {
+            Response::Success(result) => {
+                assert_eq!(result["issues"][0]["kind"], json!("unresolved_theme"));
+                assert_eq!(result["issues"][0]["repaired"], json!(true));
+                assert_eq!(result["ok"], json!(true));
+            }
+            Response::Error(e) => panic!("Failed to verify projects: {}", e),
+        }
+        let site = store.active_site.lock().unwrap().clone().unwrap();
+        let theme = store.active_theme.lock().unwrap().clone().unwrap();
+        assert_eq!(site.theme_id(), Some(theme.id()));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_switch_site_shares_theme() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+        let first = store.active_site.lock().unwrap().clone().unwrap().id();
+        let theme = store.active_theme.lock().unwrap().clone().unwrap();
+
+        let second = match store.create_site("Branch".to_string(), theme.id()).await {
+            Response::Success(created) => created["id"].as_str().unwrap().to_string(),
+            Response::Error(e) => panic!("Failed to create site: {}", e),
+        };
+        match store.list_sites().await {
+            Response::Success(listed) => {
+                let sites = listed["sites"].as_array().unwrap();
+                assert!(sites
+                    .iter()
+                    .any(|site| site["id"] == json!(first) && site["open"] == json!(true)));
+                assert!(sites
+                    .iter()
+                    .any(|site| site["id"] == json!(second) && site["active"] == json!(true)));
+            }
+            Response::Error(e) => panic!("Failed to list sites: {}", e),
+        }
+
+        assert!(matches!(
+            store.switch_site(first.clone()).await,
+            Response::Success(_)
+        ));
+        let active = store.active_site.lock().unwrap().clone().unwrap();
+        assert_eq!(active.id(), first);
+        // The theme is the same document, not a reloaded copy
+        let active_theme = store.active_theme.lock().unwrap().clone().unwrap();
+        assert_eq!(active_theme.id(), theme.id());
+
+        let mut other = store.open_sites.lock().unwrap()[&second].clone();
+        other.set_theme_id("another-theme").unwrap();
+        assert!(matches!(
+            store.switch_site(second).await,
+            Response::Error(_)
+        ));
+        assert_eq!(store.active_site.lock().unwrap().clone().unwrap().id(), first);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_apply_share_updates_is_checked() {
+        use crate::model::project::{document_version, updates_since};
+        use crate::render::manifest::base64_encode;
+        use loro::LoroDoc;
+        use std::collections::HashMap;
+
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+        let site = store.active_site.lock().unwrap().clone().unwrap();
+
+        // Updates can't reach documents outside the project
+        let mut updates = HashMap::new();
+        updates.insert(
+            "not-in-the-site".to_string(),
+            base64_encode(&site.export().unwrap()),
+        );
+        assert!(matches!(
+            store
+                .apply_share_updates("site".to_string(), site.id(), updates)
+                .await,
+            Response::Error(_)
+        ));
+
+        // Nor can a project this side doesn't have be made from part of one
+        let doc = LoroDoc::new();
+        doc.get_map("meta").insert("name", "Peer").unwrap();
+        doc.commit();
+        let version = document_version(&doc);
+        doc.get_map("meta").insert("name", "Peer site").unwrap();
+        doc.commit();
+        let partial = updates_since(&doc, &version).unwrap().unwrap();
+        let mut updates = HashMap::new();
+        updates.insert("peer-site".to_string(), base64_encode(&partial));
+        assert!(matches!(
+            store
+                .apply_share_updates("site".to_string(), "peer-site".to_string(), updates)
+                .await,
+            Response::Error(_)
+        ));
+        let active = store.active_site.lock().unwrap().clone().unwrap();
+        assert_eq!(active.id(), site.id());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_read_only_project() {
+        use crate::messages::Message;
+
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+        let create = || Message::CreateFile {
+            project_type: "site".to_string(),
+            collection_name: "post".to_string(),
+            name: "frozen".to_string(),
+            skeleton: None,
+        };
+
+        let response = store
+            .handle_message(Message::SetProjectReadOnly {
+                project_type: "site".to_string(),
+                enabled: true,
+            })
+            .await;
+        assert!(matches!(response, Response::Success(_)));
+        assert!(matches!(
+            store.handle_message(create()).await,
+            Response::Error(_)
+        ));
+        // The theme isn't frozen, and the site still renders
+        let response = store
+            .handle_message(Message::CreateFile {
+                project_type: "theme".to_string(),
+                collection_name: "partial".to_string(),
+                name: "footer".to_string(),
+                skeleton: None,
+            })
+            .await;
+        assert!(matches!(response, Response::Success(_)));
+        let response = store
+            .handle_message(Message::RenderSite {
+                url_strategy: None,
+                filter: ExportFilter::default(),
+            })
+            .await;
+        assert!(matches!(response, Response::Success(_)));
+
+        store
+            .handle_message(Message::SetProjectReadOnly {
+                project_type: "site".to_string(),
+                enabled: false,
+            })
+            .await;
+        assert!(matches!(
+            store.handle_message(create()).await,
+            Response::Success(_)
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_journal_round_trip() {
+        use super::super::journal::Journal;
+
+        let mut journal = Journal::new("install bundle");
+        journal.write("files", "a", vec![1, 2, 3]);
+        journal.write("assets", "b", Vec::new());
+        journal.write("projects", "c", vec![4]);
+
+        let record = journal.encode().unwrap();
+        assert_eq!(Journal::decode(&record).unwrap(), journal);
+        // A cut-off record is never replayed
+        assert!(Journal::decode(&record[..record.len() - 1]).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_filter_and_sort_files() {
+        use super::super::file_list::filter_and_sort;
+        use crate::types::FileSort;
+        use serde_json::Value;
+
+        let file = |meta: Value| {
+            let meta = meta.as_object().cloned().unwrap();
+            (json!({ "id": meta["id"] }), meta)
+        };
+        let files = vec![
+            file(json!({ "id": "a", "name": "zine", "date": "2025-03-01", "tags": "art" })),
+            file(json!({ "id": "b", "name": "fair", "title": "Zine Fair", "status": "draft" })),
+            file(json!({ "id": "c", "name": "news", "date": 1735689600000i64, "tags": ["Art"] })),
+        ];
+        let ids = |options: ListFilesOptions| -> Vec<String> {
+            filter_and_sort(files.clone(), &options)
+                .iter()
+                .map(|file| file["id"].as_str().unwrap().to_string())
+                .collect()
+        };
+
+        let by_date = ListFilesOptions {
+            sort: Some(FileSort::Date),
+            descending: true,
+            ..Default::default()
+        };
+        // Undated files go last either way
+        assert_eq!(ids(by_date), vec!["a", "c", "b"]);
+        let by_title = ListFilesOptions {
+            sort: Some(FileSort::Title),
+            ..Default::default()
+        };
+        assert_eq!(ids(by_title), vec!["c", "a", "b"]);
+
+        let art = ListFilesOptions {
+            tag: Some("art".to_string()),
+            status: Some("published".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(art), vec!["a", "c"]);
+        let search = ListFilesOptions {
+            query: Some("zine".to_string()),
+            status: Some("Draft".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(ids(search), vec!["b"]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_collection_stats() {
+        use super::super::collections::collection_stats;
+        use serde_json::{Map, Value};
+
+        let metas: Vec<Map<String, Value>> = vec![
+            json!({ "status": "Draft", "updated": "2025-03-02T10:00:00Z" }),
+            json!({ "created": 1735689600000i64 }),
+            json!({ "status": "scheduled" }),
+        ]
+        .into_iter()
+        .map(|meta| meta.as_object().cloned().unwrap())
+        .collect();
+
+        let stats = collection_stats("post", &metas);
+        assert_eq!(stats["files"], json!(3));
+        assert_eq!(stats["lastUpdated"], json!(1740909600000i64));
+        assert_eq!(
+            stats["statuses"],
+            json!({ "draft": 1, "published": 1, "scheduled": 1 })
+        );
+
+        // Only pages and posts have statuses
+        let stats = collection_stats("template", &[]);
+        assert_eq!(stats["lastUpdated"], Value::Null);
+        assert!(stats.get("statuses").is_none());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_list_collections() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+        store
+            .create_file(
+                "site".to_string(),
+                "post".to_string(),
+                "hello".to_string(),
+                None,
+            )
+            .await;
+
+        let collections = match store.list_collections("site".to_string()).await {
+            Response::Success(value) => value.as_array().cloned().unwrap(),
+            Response::Error(e) => panic!("Failed to list collections: {}", e),
+        };
+        assert!(collections.iter().all(|c| c.get("error").is_none()));
+        let posts = collections.iter().find(|c| c["name"] == "post").unwrap();
+        assert!(posts["stats"]["files"].as_u64().unwrap() >= 1);
+        assert!(posts["stats"]["statuses"].is_object());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_list_collections_reports_broken_collection() {
+        use crate::model::project::Project;
+        use loro::{LoroDoc, LoroMap};
+
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+        store
+            .create_file(
+                "site".to_string(),
+                "post".to_string(),
+                "hello".to_string(),
+                None,
+            )
+            .await;
+
+        // A collection stored under a key its name doesn't match can't be
+        // looked up, as after an interrupted rename
+        let site = store.active_project(ProjectType::Site).unwrap();
+        let doc = LoroDoc::new();
+        doc.import(&site.export().unwrap()).unwrap();
+        let broken = doc
+            .get_map("collections")
+            .insert_container("zines", LoroMap::new())
+            .unwrap();
+        broken.insert("name", "zine").unwrap();
+        doc.commit();
+        let site = Project::import(
+            doc.export(loro::ExportMode::Snapshot).unwrap(),
+            site.id(),
+            ProjectType::Site,
+            0.0,
+            0.0,
+        )
+        .unwrap();
+        store.set_site(site).unwrap();
+
+        let collections = match store.list_collections("site".to_string()).await {
+            Response::Success(value) => value.as_array().cloned().unwrap(),
+            Response::Error(e) => panic!("Failed to list collections: {}", e),
+        };
+        let zine = collections.iter().find(|c| c["name"] == "zine").unwrap();
+        assert!(zine["error"].is_string());
+        assert!(zine.get("stats").is_none());
+        let posts = collections.iter().find(|c| c["name"] == "post").unwrap();
+        assert!(posts.get("error").is_none());
+        assert!(posts["stats"]["files"].as_u64().unwrap() >= 1);
+        assert_eq!(
+            collections.iter().filter(|c| c.get("error").is_some()).count(),
+            1
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_set_collection_schema() {
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let schema = json!({
+            "marks": { "bold": { "inclusive": true } },
+            "nodes": {
+                "doc": { "content": "block+" },
+                "paragraph": { "content": "inline*", "group": "block" }
+            }
+        });
+        let response = store
+            .set_collection_schema("site".to_string(), "post".to_string(), schema)
+            .await;
+        if let Response::Error(e) = response {
+            panic!("Failed to set schema: {}", e);
+        }
+
+        // Stored on the collection, which new posts take it from
+        let project = store.active_project(ProjectType::Site).unwrap();
+        let stored = project.collection_pm_schema("post").unwrap();
+        assert!(stored.marks.contains_key("bold"));
+        assert!(!stored.allows_node("heading"));
+        let listed = match store.get_collection("site".to_string(), "post".to_string()) {
+            Response::Success(value) => value,
+            Response::Error(e) => panic!("Failed to get collection: {}", e),
+        };
+        assert!(listed["schema"]["marks"]["bold"].is_object());
+
+        // Only rich-text collections have a schema
+        let response = store
+            .set_collection_schema("site".to_string(), "asset".to_string(), json!({}))
+            .await;
+        assert!(matches!(response, Response::Error(_)));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_create_file_from_skeleton() {
+        use super::super::render::load_full_file;
+        use crate::messages::Message;
+        use crate::model::file::loro_doc_to_pm_doc;
+        use crate::model::Page;
+        use crate::pm_json_to_loro;
+
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+        let create = |project_type: &str, collection_name: &str, skeleton: Option<&str>| {
+            Message::CreateFile {
+                project_type: project_type.to_string(),
+                collection_name: collection_name.to_string(),
+                name: "meeting-notes".to_string(),
+                skeleton: skeleton.map(str::to_string),
+            }
+        };
+
+        // A theme skeleton, filled in as it would be in the editor
+        let skeleton_id = match store
+            .handle_message(create("theme", "skeleton", None))
+            .await
+        {
+            Response::Success(value) => value["id"].as_str().unwrap().to_string(),
+            Response::Error(e) => panic!("Failed to create skeleton: {}", e),
+        };
+        let body = json!({ "type": "doc", "content": [
+            { "type": "heading", "attrs": { "level": 2 }, "content": [
+                { "type": "text", "text": "Agenda" }
+            ]},
+            { "type": "paragraph" },
+            { "type": "heading", "attrs": { "level": 2 }, "content": [
+                { "type": "text", "text": "Actions" }
+            ]}
+        ]});
+        let skeleton = load_full_file::<Page>("skeleton", &skeleton_id)
+            .await
+            .unwrap();
+        pm_json_to_loro(skeleton.store().as_full().unwrap(), &body).unwrap();
+        skeleton.save_to_indexeddb().await.unwrap();
+
+        let page_id = match store
+            .handle_message(create("site", "page", Some(&skeleton_id)))
+            .await
+        {
+            Response::Success(value) => value["id"].as_str().unwrap().to_string(),
+            Response::Error(e) => panic!("Failed to create page from skeleton: {}", e),
+        };
+        let page = load_full_file::<Page>("page", &page_id).await.unwrap();
+        let copied = loro_doc_to_pm_doc(page.store().as_full().unwrap()).unwrap();
+        assert_eq!(
+            copied,
+            loro_doc_to_pm_doc(skeleton.store().as_full().unwrap()).unwrap()
+        );
+
+        // Only rich-text files have a body to start from
+        let response = store
+            .handle_message(create("site", "asset", Some(&skeleton_id)))
+            .await;
+        assert!(matches!(response, Response::Error(_)));
+        let response = store
+            .handle_message(create("site", "page", Some("missing")))
+            .await;
+        assert!(matches!(response, Response::Error(_)));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_check_meta_value() {
+        use super::super::meta::check_meta_value;
+        use crate::types::{FieldDefinition, FieldType};
+        use serde_json::Value;
+
+        let field = |field_type, required| FieldDefinition {
+            name: "capacity".to_string(),
+            field_type,
+            required,
+        };
+        let number = field(FieldType::Number, true);
+        let date = field(FieldType::DateTime, false);
+
+        // Free-form keys take anything, and null removes them
+        assert_eq!(
+            check_meta_value("mood", &json!({ "colour": "teal" }), None),
+            Ok(Some(json!({ "colour": "teal" })))
+        );
+        assert_eq!(check_meta_value("mood", &Value::Null, None), Ok(None));
+
+        // Defined fields convert text and refuse values of the wrong type
+        assert_eq!(
+            check_meta_value("capacity", &json!("120"), Some(&number)),
+            Ok(Some(json!(120)))
+        );
+        assert!(check_meta_value("capacity", &json!(true), Some(&number)).is_err());
+        assert!(check_meta_value("capacity", &Value::Null, Some(&number)).is_err());
+        assert_eq!(
+            check_meta_value("when", &json!("2025-03-01"), Some(&date)),
+            Ok(Some(json!("2025-03-01")))
+        );
+        assert!(check_meta_value("id", &json!("x"), None).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_change_feed() {
+        use super::super::changes::{ChangeFeed, CHANGE_FEED_LIMIT};
+
+        let mut feed = ChangeFeed::default();
+        assert_eq!(feed.since(0), (Vec::new(), 0, false));
+
+        feed.push("site-1".to_string(), Some("post-1".to_string()), "file_created");
+        feed.push("site-1".to_string(), None, "settings_changed");
+        let (changes, latest, missed) = feed.since(1);
+        assert_eq!(latest, 2);
+        assert!(!missed);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].version, 2);
+        assert_eq!(changes[0].kind, "settings_changed");
+
+        // A cursor from before a restart is ahead of the feed
+        assert!(feed.since(5).2);
+
+        for _ in 0..CHANGE_FEED_LIMIT {
+            feed.push("site-1".to_string(), None, "file_updated");
+        }
+        let (changes, latest, missed) = feed.since(0);
+        assert_eq!(changes.len(), CHANGE_FEED_LIMIT);
+        assert_eq!(latest, CHANGE_FEED_LIMIT as u64 + 2);
+        assert!(missed);
+        assert!(!feed.since(2).2);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_schema_round_trip() {
+        use super::super::schema::{model_to_schema, schema_to_model, CollectionModel};
+        use crate::types::{FieldDefinition, FieldType, ListingConfig};
+
+        let events = CollectionModel {
+            name: "events".to_string(),
+            fields: vec![
+                FieldDefinition {
+                    name: "starts".to_string(),
+                    field_type: FieldType::DateTime,
+                    required: true,
+                },
+                FieldDefinition {
+                    name: "venue".to_string(),
+                    field_type: FieldType::Text,
+                    required: false,
+                },
+            ],
+            listing: Some(ListingConfig {
+                path: "events".to_string(),
+                template: "events".to_string(),
+                detail_template: None,
+                sort_by: Some("starts".to_string()),
+                descending: false,
+                group_by: None,
+                group_by_date: None,
+                upcoming_field: None,
+            }),
+        };
+
+        let schema = model_to_schema("Zine", &ProjectType::Site, &[events.clone()]);
+        assert_eq!(schema["$defs"]["events"]["required"], json!(["starts"]));
+        assert_eq!(
+            schema["$defs"]["events"]["properties"]["starts"]["format"],
+            json!("date-time")
+        );
+        assert_eq!(schema_to_model(&schema).unwrap(), vec![events]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_theme_manifest_problems() {
+        use super::super::schema::CollectionModel;
+        use super::super::theme::manifest_problems;
+        use crate::render::site::PageSource;
+        use crate::types::{
+            CollectionRequirement, FieldDefinition, FieldRequirement, FieldType,
+            PageTemplateInfo, ThemeManifest,
+        };
+        use serde_json::Value;
+
+        let field = |name: &str, field_type: Option<FieldType>| FieldRequirement {
+            name: name.to_string(),
+            field_type,
+            description: None,
+        };
+        let manifest = ThemeManifest {
+            collections: vec![
+                CollectionRequirement {
+                    name: "page".to_string(),
+                    fields: vec![
+                        FieldRequirement {
+                            description: Some("shown behind the title".to_string()),
+                            ..field("hero_image", None)
+                        },
+                        field("title", Some(FieldType::Text)),
+                        field("subtitle", None),
+                    ],
+                },
+                CollectionRequirement {
+                    name: "events".to_string(),
+                    fields: Vec::new(),
+                },
+            ],
+            page_templates: vec![
+                PageTemplateInfo {
+                    name: "default".to_string(),
+                    label: Some("Standard page".to_string()),
+                    ..Default::default()
+                },
+                PageTemplateInfo {
+                    name: "landing".to_string(),
+                    label: Some("Landing page".to_string()),
+                    description: Some("Full-width, no sidebar".to_string()),
+                    ..Default::default()
+                },
+            ],
+        };
+        let collections = vec![CollectionModel {
+            name: "page".to_string(),
+            fields: vec![FieldDefinition {
+                name: "title".to_string(),
+                field_type: FieldType::String,
+                required: true,
+            }],
+            listing: None,
+        }];
+        let page = |name: &str, meta: Value| PageSource {
+            collection: "page".to_string(),
+            name: name.to_string(),
+            meta: meta.as_object().cloned().unwrap(),
+            body: Value::Null,
+        };
+        let pages = vec![
+            page("About", json!({ "template": "default", "subtitle": "Who we are" })),
+            page("Archive", json!({ "template": "wide" })),
+        ];
+
+        assert_eq!(
+            manifest_problems(&manifest, &collections, &pages, &["default".to_string()]),
+            vec![
+                "This theme needs a 'hero_image' field on pages (shown behind the title)",
+                "This theme needs the 'title' field on pages to be text, not string",
+                "This theme needs a collection called 'events'",
+                "The manifest offers a 'landing' page template the theme doesn't have",
+                "Page 'Archive' uses the 'wide' template, which this theme doesn't offer",
+            ]
+        );
+        assert!(manifest_problems(&ThemeManifest::default(), &[], &pages, &[]).is_empty());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_template_choices() {
+        use super::super::theme::template_choices;
+        use crate::types::{PageTemplateInfo, ThemeManifest};
+
+        let pairs = |names: &[(&str, &str)]| -> Vec<(String, String)> {
+            names
+                .iter()
+                .map(|(id, name)| (id.to_string(), name.to_string()))
+                .collect()
+        };
+        let templates = pairs(&[("t1", "default"), ("t2", "landing"), ("t3", "post")]);
+        let assets = pairs(&[("a1", "landing.png")]);
+
+        let all = template_choices(&ThemeManifest::default(), &templates, &assets, None);
+        assert_eq!(all.len(), 3);
+        assert_eq!((all[1].label.as_str(), all[1].id.as_str()), ("landing", "t2"));
+
+        let manifest = ThemeManifest {
+            page_templates: vec![
+                PageTemplateInfo {
+                    name: "landing".to_string(),
+                    label: Some("Landing page".to_string()),
+                    description: Some("Full-width, no sidebar".to_string()),
+                    thumbnail: Some("landing.png".to_string()),
+                    collections: vec!["page".to_string()],
+                },
+                PageTemplateInfo {
+                    name: "post".to_string(),
+                    label: Some("Article".to_string()),
+                    collections: vec!["post".to_string()],
+                    ..Default::default()
+                },
+                PageTemplateInfo {
+                    name: "gone".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let for_pages = template_choices(&manifest, &templates, &assets, Some("page"));
+        assert_eq!(for_pages.len(), 1);
+        assert_eq!(for_pages[0].label, "Landing page");
+        assert_eq!(for_pages[0].thumbnail.as_deref(), Some("a1"));
+        let for_posts = template_choices(&manifest, &templates, &assets, Some("post"));
+        assert_eq!(
+            for_posts.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(),
+            vec!["t3"]
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_alt_text_audit() {
+        use super::super::accessibility::{alt_text_audit, AssetAlt};
+        use crate::render::html::images_missing_alt;
+        use crate::render::site::PageSource;
+        use serde_json::Value;
+
+        let body = json!({ "type": "doc", "content": [
+            { "type": "image", "attrs": { "src": "/assets/a1/map.png", "alt": "  " } },
+            { "type": "paragraph", "content": [
+                { "type": "image", "attrs": { "src": "/assets/a2/cat.jpg", "alt": "A cat" } },
+                { "type": "image", "attrs": { "src": "https://example.com/x.gif" } }
+            ] }
+        ] });
+        assert_eq!(
+            images_missing_alt(&body),
+            vec!["/assets/a1/map.png", "https://example.com/x.gif"]
+        );
+
+        let asset = |id: &str, name: &str, alt: &str| AssetAlt {
+            id: id.to_string(),
+            name: name.to_string(),
+            url: format!("/assets/{}/{}", id, name),
+            alt: alt.to_string(),
+        };
+        let mut meta = serde_json::Map::new();
+        meta.insert("id".to_string(), json!("p1"));
+        let pages = vec![PageSource {
+            collection: "page".to_string(),
+            name: "about".to_string(),
+            meta,
+            body,
+        }];
+        let audit = alt_text_audit(
+            &[asset("a1", "map.png", "Map of the venue"), asset("a2", "cat.jpg", "")],
+            &pages,
+        );
+
+        assert_eq!(audit["ok"], false);
+        assert_eq!(audit["assets"][0]["id"], "a2");
+        assert_eq!(audit["images"].as_array().unwrap().len(), 2);
+        assert_eq!(audit["images"][0]["id"], "p1");
+        assert_eq!(audit["images"][0]["suggestion"], "Map of the venue");
+        assert_eq!(audit["images"][1]["suggestion"], Value::Null);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_insert_snippet() {
+        use super::super::render::load_full_file;
+        use crate::messages::Message;
+        use crate::model::file::loro_doc_to_pm_doc;
+        use crate::model::Page;
+        use crate::pm_json_to_loro;
+
+        setup_panic_hook();
+        let store = StoreInner::new();
+        store.init_default().await;
+        let create = |collection_name: &str, name: &str| Message::CreateFile {
+            project_type: "site".to_string(),
+            collection_name: collection_name.to_string(),
+            name: name.to_string(),
+            skeleton: None,
+        };
+        let snippet_id = match store.handle_message(create("snippet", "bio")).await {
+            Response::Success(value) => value["id"].as_str().unwrap().to_string(),
+            Response::Error(e) => panic!("Failed to create snippet: {}", e),
+        };
+        let snippet = load_full_file::<Page>("snippet", &snippet_id)
+            .await
+            .unwrap();
+        let body = json!({ "type": "doc", "content": [
+            { "type": "paragraph", "content": [
+                { "type": "text", "text": "Written by the collective" }
+            ]}
+        ]});
+        pm_json_to_loro(snippet.store().as_full().unwrap(), &body).unwrap();
+        snippet.save_to_indexeddb().await.unwrap();
+
+        let page_id = match store.handle_message(create("page", "about")).await {
+            Response::Success(value) => value["id"].as_str().unwrap().to_string(),
+            Response::Error(e) => panic!("Failed to create page: {}", e),
+        };
+        let insert = |reference: bool| Message::InsertSnippet {
+            file_id: page_id.clone(),
+            snippet_id: snippet_id.clone(),
+            position: None,
+            reference,
+        };
+        let content = || async {
+            let page = load_full_file::<Page>("page", &page_id).await.unwrap();
+            let body = loro_doc_to_pm_doc(page.store().as_full().unwrap()).unwrap();
+            body["content"].as_array().cloned().unwrap_or_default()
+        };
+
+        // As a reference, one node that points at the snippet
+        match store.handle_message(insert(true)).await {
+            Response::Success(value) => assert_eq!(value["reference"], true),
+            Response::Error(e) => panic!("Failed to insert snippet reference: {}", e),
+        }
+        let nodes = content().await;
+        let last = nodes.last().unwrap();
+        assert_eq!(last["type"], "block_ref");
+        assert_eq!(last["attrs"]["id"], snippet_id.as_str());
+
+        // As a copy, the snippet's own nodes
+        match store.handle_message(insert(false)).await {
+            Response::Success(value) => assert_eq!(value["reference"], false),
+            Response::Error(e) => panic!("Failed to copy snippet: {}", e),
+        }
+        let nodes = content().await;
+        let last = nodes.last().unwrap();
+        assert_eq!(last["type"], "paragraph");
+        assert_eq!(last["content"][0]["text"], "Written by the collective");
+        assert_eq!(nodes[nodes.len() - 2]["type"], "block_ref");
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_collaborators() {
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let collaborator = crate::types::Collaborator {
+            name: " Ana ".to_string(),
+            roles: vec!["Editor".to_string(), " ".to_string()],
+            ..Default::default()
+        };
+        let id = match store.set_collaborator("site".to_string(), collaborator) {
+            Response::Success(value) => {
+                assert_eq!(value["name"], "Ana");
+                assert_eq!(value["roles"], json!(["Editor"]));
+                value["id"].as_str().unwrap().to_string()
+            }
+            Response::Error(e) => panic!("Failed to set collaborator: {}", e),
+        };
+        let nameless = crate::types::Collaborator::default();
+        assert!(matches!(
+            store.set_collaborator("site".to_string(), nameless),
+            Response::Error(_)
+        ));
+
+        match store.list_collaborators("site".to_string()) {
+            Response::Success(value) => assert_eq!(value["collaborators"][0]["id"], id),
+            Response::Error(e) => panic!("Failed to list collaborators: {}", e),
+        }
+        assert!(matches!(
+            store.remove_collaborator("site".to_string(), id.clone()),
+            Response::Success(_)
+        ));
+        assert!(matches!(
+            store.remove_collaborator("site".to_string(), id),
+            Response::Error(_)
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_dirty_state() {
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let site_dirty = |store: &StoreInner| match store.get_dirty_state() {
+            Response::Success(value) => {
+                let site = value["documents"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .find(|document| document["kind"] == "site")
+                    .cloned()
+                    .unwrap();
+                site["dirty"] == true
+            }
+            Response::Error(e) => panic!("Failed to get dirty state: {}", e),
+        };
+
+        // A new site was never saved
+        assert!(site_dirty(&store));
+        assert!(matches!(
+            store.save_state("site".to_string()).await,
+            Response::Success(_)
+        ));
+        assert!(!site_dirty(&store));
+
+        let collaborator = crate::types::Collaborator {
+            name: "Ana".to_string(),
+            ..Default::default()
+        };
+        store.set_collaborator("site".to_string(), collaborator);
+        assert!(site_dirty(&store));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_flush_sync_best_effort() {
+        use super::super::flush::replay_pending_flush;
+        use super::super::maintenance::load_bytes;
+        use super::super::render::load_full_file;
+        use super::super::IDB_PROJECTS_STORE;
+        use crate::model::file::File;
+        use crate::model::Page;
+        use crate::types::FileType;
+
+        let store = StoreInner::new();
+        store.init_default().await;
+        let site_id = store.active_site.lock().unwrap().clone().unwrap().id();
+
+        // Neither project has been saved, so both are flushed
+        assert_eq!(store.flush_sync_best_effort(), 2);
+        assert_eq!(replay_pending_flush().await.unwrap(), 2);
+        assert!(load_bytes(IDB_PROJECTS_STORE, &site_id)
+            .await
+            .unwrap()
+            .is_some());
+        // Replayed changes are cleared
+        assert_eq!(replay_pending_flush().await.unwrap(), 0);
+
+        for project_type in ["site", "theme"] {
+            store.save_state(project_type.to_string()).await;
+        }
+        assert_eq!(store.flush_sync_best_effort(), 0);
+
+        // The open file's uncommitted edits are flushed too
+        let page_id = match store
+            .create_file(
+                "site".to_string(),
+                "page".to_string(),
+                "about".to_string(),
+                None,
+            )
+            .await
+        {
+            Response::Success(value) => value["id"].as_str().unwrap().to_string(),
+            Response::Error(e) => panic!("Failed to create page: {}", e),
+        };
+        let page = load_full_file::<Page>("page", &page_id).await.unwrap();
+        page.meta().insert("title", "About us").unwrap();
+        *store.active_file.lock().unwrap() = Some(FileType::Page(page));
+        assert_eq!(store.flush_sync_best_effort(), 1);
+        assert_eq!(replay_pending_flush().await.unwrap(), 1);
+        let page = load_full_file::<Page>("page", &page_id).await.unwrap();
+        assert_eq!(page.get_field("title").unwrap(), "About us");
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_initialize_quarantines_unreadable_projects() {
+        use super::super::install::save_bytes;
+        use super::super::maintenance::load_bytes;
+        use super::super::startup::QUARANTINE_PREFIX;
+        use super::super::IDB_PROJECTS_STORE;
+
+        let store = StoreInner::new();
+        store.init_default().await;
+        for project_type in ["theme", "site"] {
+            store.save_state(project_type.to_string()).await;
+        }
+        let site_id = store.active_site.lock().unwrap().clone().unwrap().id();
+
+        let report = store.initialize().await;
+        assert!(report.projects_found >= 2);
+        assert!(report.quarantined.iter().all(|q| q.key != site_id));
+
+        let garbage = b"not a loro document".to_vec();
+        save_bytes(IDB_PROJECTS_STORE, &site_id, &garbage)
+            .await
+            .unwrap();
+        let report = store.initialize().await;
+        assert!(report.quarantined.iter().any(|q| q.key == site_id));
+        let quarantined = format!("{}{}", QUARANTINE_PREFIX, site_id);
+        assert_eq!(
+            load_bytes(IDB_PROJECTS_STORE, &quarantined).await.unwrap(),
+            Some(garbage)
+        );
+
+        // The site is out of the workspace, so it isn't reported again
+        let report = store.initialize().await;
+        assert!(!report.missing.contains(&site_id));
+        assert!(report.quarantined.iter().all(|q| q.key != site_id));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_build_site() {
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let response = store
+            .handle_message(crate::messages::Message::BuildSite {
+                url_strategy: None,
+                filter: ExportFilter::default(),
+            })
+            .await;
+        let built = match response {
+            Response::Success(value) => value,
+            Response::Error(e) => panic!("Failed to build site: {}", e),
+        };
+        let zip: Vec<u8> = serde_json::from_value(built["zip"].clone()).unwrap();
+        assert_eq!(&zip[..2], b"PK");
+        assert_eq!(built["size"], zip.len());
+        assert!(built["files"].as_u64().unwrap() > 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_storage_quota() {
+        use super::super::quota::StorageEstimate;
+
+        let mb = 1024.0 * 1024.0;
+        let roomy = StorageEstimate {
+            usage: 10.0 * mb,
+            quota: 100.0 * mb,
+        };
+        assert!(!roomy.is_near_quota());
+        assert_eq!(roomy.refuse_asset("big.jpg", 20 * 1024 * 1024), None);
+
+        let full = StorageEstimate {
+            usage: 95.0 * mb,
+            quota: 100.0 * mb,
+        };
+        assert!(full.is_near_quota());
+        assert_eq!(full.refuse_asset("icon.png", 4096), None);
+        assert_eq!(
+            full.refuse_asset("scan.png", 2 * 1024 * 1024),
+            Some(
+                "Storage is 95% full, so scan.png (2.0 MB) wasn't added; \
+                 free some space or add files under 1.0 MB"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            full.refuse_asset("film.mp4", 6 * 1024 * 1024),
+            Some("film.mp4 (6.0 MB) won't fit: 5.0 MB of 100.0 MB storage is free".to_string())
+        );
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_delete_file() {
+        use crate::messages::Message;
+
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let response = store
+            .handle_message(Message::CreateFile {
+                project_type: "site".to_string(),
+                collection_name: "post".to_string(),
+                name: "old-news".to_string(),
+                skeleton: None,
+            })
+            .await;
+        let file_id = match response {
+            Response::Success(value) => value["id"].as_str().unwrap().to_string(),
+            Response::Error(e) => panic!("Failed to create post: {}", e),
+        };
+        let delete = || Message::DeleteFile {
+            project_type: "site".to_string(),
+            collection_name: "post".to_string(),
+            file_id: file_id.clone(),
+            force: false,
+        };
+
+        let response = store.handle_message(delete()).await;
+        assert!(matches!(response, Response::Success(_)));
+        assert!(matches!(
+            store
+                .get_file("site".to_string(), "post".to_string(), file_id.clone())
+                .await,
+            Response::Error(_)
+        ));
+        // It's gone, so it can't be deleted again
+        assert!(matches!(
+            store.handle_message(delete()).await,
+            Response::Error(_)
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_delete_file_with_dependents() {
+        use crate::messages::Message;
+        use crate::model::Template;
+
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        // The default site's page uses one of the theme's templates
+        let theme = store.active_project(ProjectType::Theme).unwrap();
+        let templates = theme
+            .get_collection::<Template>("template")
+            .unwrap()
+            .get_files("template")
+            .await
+            .unwrap();
+        let mut used = None;
+        for template in &templates {
+            let template_id = template.id().unwrap();
+            let response = store
+                .handle_message(Message::CheckDelete {
+                    project_type: "theme".to_string(),
+                    collection_name: "template".to_string(),
+                    file_id: template_id.clone(),
+                })
+                .await;
+            if let Response::Success(value) = response {
+                if value["confirmation_required"] == true {
+                    used = Some(template_id);
+                    break;
+                }
+            }
+        }
+        let template_id = used.expect("No template is used by a page");
+        let delete = |force: bool| Message::DeleteFile {
+            project_type: "theme".to_string(),
+            collection_name: "template".to_string(),
+            file_id: template_id.clone(),
+            force,
+        };
+
+        // Refused while a page depends on it
+        match store.handle_message(delete(false)).await {
+            Response::Error(e) => assert!(e.contains("force")),
+            Response::Success(_) => panic!("Deleted a template a page uses"),
+        }
+        assert!(matches!(
+            store
+                .get_file("theme".to_string(), "template".to_string(), template_id.clone())
+                .await,
+            Response::Success(_)
+        ));
+
+        // Deleted anyway when forced
+        assert!(matches!(
+            store.handle_message(delete(true)).await,
+            Response::Success(_)
+        ));
+        assert!(matches!(
+            store
+                .get_file("theme".to_string(), "template".to_string(), template_id.clone())
+                .await,
+            Response::Error(_)
+        ));
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_reader_tab_is_read_only() {
+        use super::super::tabs::TabRole;
+        use crate::messages::Message;
+
+        let store = StoreInner::new();
+        store.init_default().await;
+        let create = || Message::CreateFile {
+            project_type: "site".to_string(),
+            collection_name: "post".to_string(),
+            name: "from-another-tab".to_string(),
+            skeleton: None,
+        };
+
+        store.set_tab_role(TabRole::Reader);
+        match store.handle_message(Message::GetTabRole).await {
+            Response::Success(value) => assert_eq!(value["read_only"], true),
+            Response::Error(e) => panic!("Failed to get tab role: {}", e),
+        }
+        assert!(matches!(
+            store.handle_message(create()).await,
+            Response::Error(_)
+        ));
+        assert!(matches!(
+            store
+                .handle_message(Message::SaveState {
+                    project_type: "site".to_string(),
+                })
+                .await,
+            Response::Error(_)
+        ));
+        // Reading is fine
+        assert!(matches!(
+            store.handle_message(Message::GetSite).await,
+            Response::Success(_)
+        ));
+        assert_eq!(store.flush_sync_best_effort(), 0);
+
+        // Once the writer closes, this tab takes over
+        store.set_tab_role(TabRole::Writer);
+        assert!(matches!(
+            store.handle_message(create()).await,
+            Response::Success(_)
+        ));
+    }
+
+    #[cfg(feature = "fixtures")]
+    #[wasm_bindgen_test]
+    async fn test_generate_fixture() {
+        use crate::fixtures::FixtureOptions;
+        use crate::messages::Message;
+
+        let store = StoreInner::new();
+        store.init_default().await;
+
+        let response = store
+            .handle_message(Message::GenerateFixture {
+                options: FixtureOptions {
+                    seed: 7,
+                    pages: 2,
+                    posts: 6,
+                    assets: 2,
+                    paragraphs: 3,
+                },
+            })
+            .await;
+        match response {
+            Response::Success(value) => {
+                assert_eq!(value["pages"], 2);
+                assert_eq!(value["posts"], 6);
+                assert_eq!(value["assets"], 2);
+            }
+            Response::Error(e) => panic!("Failed to generate fixture: {}", e),
+        }
+    }
+
     // #[wasm_bindgen_test]
     // fn test_export_import_project() {
     //     let store = Store::new();
@@ -0,0 +1,351 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+
+use super::maintenance::load_bytes;
+use super::schema::{collection_models, CollectionModel};
+use super::settings::page_meta;
+use super::{StoreInner, IDB_PROJECTS_STORE};
+use crate::messages::Response;
+use crate::model::file::File;
+use crate::model::project::Project;
+use crate::model::{Asset, Entry, Page, Post, Template};
+use crate::render::site::PageSource;
+use crate::types::{PageTemplateInfo, ProjectType, ThemeManifest};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// How a collection's files are referred to in problems
+fn collection_label(name: &str) -> String {
+    match name {
+        "page" => "pages".to_string(),
+        "post" => "posts".to_string(),
+        other => format!("the '{}' collection", other),
+    }
+}
+
+/// A theme template as the editor's template chooser shows it
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(super) struct TemplateChoice {
+    pub id: String,
+    pub name: String,
+    /// The manifest's label, or else the template's name
+    pub label: String,
+    pub description: Option<String>,
+    /// Id of the theme asset previewing the template
+    pub thumbnail: Option<String>,
+    /// Collections whose files may use it; any if empty
+    pub collections: Vec<String>,
+}
+
+/// The templates files in `collection` (any, if None) can choose from
+///
+/// Once a manifest lists page templates only those are offered, in its
+/// order. `templates` and `assets` are the theme's (id, name) pairs.
+pub(super) fn template_choices(
+    manifest: &ThemeManifest,
+    templates: &[(String, String)],
+    assets: &[(String, String)],
+    collection: Option<&str>,
+) -> Vec<TemplateChoice> {
+    let infos: Vec<PageTemplateInfo> = if manifest.page_templates.is_empty() {
+        templates
+            .iter()
+            .map(|(_, name)| PageTemplateInfo {
+                name: name.clone(),
+                ..Default::default()
+            })
+            .collect()
+    } else {
+        manifest.page_templates.clone()
+    };
+
+    infos
+        .into_iter()
+        .filter(|info| collection.map_or(true, |collection| info.applies_to(collection)))
+        .filter_map(|info| {
+            let (id, _) = templates.iter().find(|(_, name)| *name == info.name)?;
+            let thumbnail = info.thumbnail.as_ref().and_then(|thumbnail| {
+                assets
+                    .iter()
+                    .find(|(id, name)| name == thumbnail || id == thumbnail)
+                    .map(|(id, _)| id.clone())
+            });
+            Some(TemplateChoice {
+                id: id.clone(),
+                label: info.label.clone().unwrap_or_else(|| info.name.clone()),
+                name: info.name,
+                description: info.description,
+                thumbnail,
+                collections: info.collections,
+            })
+        })
+        .collect()
+}
+
+/// (id, name) of each of a theme's files in a collection
+async fn theme_files<T: File + Default>(
+    theme: &Project,
+    collection_name: &str,
+) -> Result<Vec<(String, String)>, String> {
+    let mut files = Vec::new();
+    for file in theme
+        .get_collection::<T>(collection_name)?
+        .get_files(collection_name)
+        .await?
+    {
+        files.push((file.id()?, file.name()?));
+    }
+    Ok(files)
+}
+
+/// Ways a site falls short of what a theme's manifest asks for
+///
+/// A field counts as present if its collection declares it or any of the
+/// collection's files sets it. `templates` are the theme's template names.
+pub(super) fn manifest_problems(
+    manifest: &ThemeManifest,
+    collections: &[CollectionModel],
+    files: &[PageSource],
+    templates: &[String],
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    for required in &manifest.collections {
+        let collection = match collections.iter().find(|c| c.name == required.name) {
+            Some(collection) => collection,
+            None => {
+                problems.push(format!(
+                    "This theme needs a collection called '{}'",
+                    required.name
+                ));
+                continue;
+            }
+        };
+        let label = collection_label(&required.name);
+        for field in &required.fields {
+            let declared = collection.fields.iter().find(|f| f.name == field.name);
+            let is_set = files.iter().any(|file| {
+                file.collection == required.name && file.meta.contains_key(&field.name)
+            });
+            match (declared, field.field_type) {
+                (Some(declared), Some(field_type)) if declared.field_type != field_type => problems
+                    .push(format!(
+                        "This theme needs the '{}' field on {} to be {}, not {}",
+                        field.name,
+                        label,
+                        field_type.to_string(),
+                        declared.field_type.to_string()
+                    )),
+                (Some(_), _) => {}
+                (None, _) if is_set => {}
+                (None, _) => {
+                    let mut problem =
+                        format!("This theme needs a '{}' field on {}", field.name, label);
+                    if let Some(description) = &field.description {
+                        problem.push_str(&format!(" ({})", description));
+                    }
+                    problems.push(problem);
+                }
+            }
+        }
+    }
+
+    for info in &manifest.page_templates {
+        if !templates.contains(&info.name) {
+            problems.push(format!(
+                "The manifest offers a '{}' page template the theme doesn't have",
+                info.name
+            ));
+        }
+    }
+    if !manifest.page_templates.is_empty() {
+        for page in files.iter().filter(|file| file.collection == "page") {
+            let template = page
+                .meta
+                .get("template")
+                .and_then(Value::as_str)
+                .filter(|template| !template.is_empty());
+            if let Some(template) = template {
+                let offered = manifest
+                    .page_templates
+                    .iter()
+                    .any(|info| info.name == template && info.applies_to("page"));
+                if !offered {
+                    problems.push(format!(
+                        "Page '{}' uses the '{}' template, which this theme doesn't offer",
+                        page.name, template
+                    ));
+                }
+            }
+        }
+    }
+    problems
+}
+
+/// Ways `site` falls short of `theme`'s manifest
+pub(super) async fn theme_problems(site: &Project, theme: &Project) -> Result<Vec<String>, String> {
+    let manifest = theme.theme_manifest()?;
+    if manifest == ThemeManifest::default() {
+        return Ok(Vec::new());
+    }
+
+    let collections = collection_models(site)?;
+    let mut files = Vec::new();
+    for required in &manifest.collections {
+        if !collections.iter().any(|c| c.name == required.name) {
+            continue;
+        }
+        files.extend(match required.name.as_str() {
+            "page" => page_meta::<Page>(site, "page").await?,
+            "post" => page_meta::<Post>(site, "post").await?,
+            name => page_meta::<Entry>(site, name).await?,
+        });
+    }
+    if !manifest.page_templates.is_empty() && !manifest.collections.iter().any(|c| c.name == "page")
+    {
+        files.extend(page_meta::<Page>(site, "page").await?);
+    }
+
+    let templates: Vec<String> = theme_files::<Template>(theme, "template")
+        .await?
+        .into_iter()
+        .map(|(_, name)| name)
+        .collect();
+    Ok(manifest_problems(
+        &manifest,
+        &collections,
+        &files,
+        &templates,
+    ))
+}
+
+impl StoreInner {
+    /// ACTOR Get the active theme's manifest
+    pub(super) fn get_theme_manifest(&self) -> Response {
+        match self
+            .active_project(ProjectType::Theme)
+            .and_then(|theme| theme.theme_manifest())
+        {
+            Ok(manifest) => Response::success(manifest),
+            Err(e) => Response::error(&format!("Failed to get theme manifest: {}", e)),
+        }
+    }
+
+    /// ACTOR Replace the active theme's manifest
+    ///
+    /// The active site, if there is one, is checked against the new manifest.
+    pub(super) async fn set_theme_manifest(&self, manifest: ThemeManifest) -> Response {
+        console_log!("Setting theme manifest");
+
+        let result = async {
+            let theme = self.active_project(ProjectType::Theme)?;
+            theme.set_theme_manifest(&manifest)?;
+            let problems = match self.active_project(ProjectType::Site) {
+                Ok(site) => theme_problems(&site, &theme).await?,
+                Err(_) => Vec::new(),
+            };
+            self.set_theme(theme)?;
+            Ok::<_, String>(problems)
+        }
+        .await;
+
+        match result {
+            Ok(problems) => Response::success(json!({
+                "manifest": manifest,
+                "problems": problems,
+            })),
+            Err(e) => Response::error(&format!("Failed to set theme manifest: {}", e)),
+        }
+    }
+
+    /// ACTOR The active theme's templates with their display names,
+    /// descriptions and thumbnails, for a template chooser
+    pub(super) async fn list_templates(&self, collection: Option<String>) -> Response {
+        let result = async {
+            let theme = self.active_project(ProjectType::Theme)?;
+            let manifest = theme.theme_manifest()?;
+            let templates = theme_files::<Template>(&theme, "template").await?;
+            let assets = theme_files::<Asset>(&theme, "asset").await?;
+            Ok::<_, String>(template_choices(
+                &manifest,
+                &templates,
+                &assets,
+                collection.as_deref(),
+            ))
+        }
+        .await;
+
+        match result {
+            Ok(choices) => Response::success(choices),
+            Err(e) => Response::error(&format!("Failed to list templates: {}", e)),
+        }
+    }
+
+    /// ACTOR Check the active site against the active theme's manifest
+    pub(super) async fn check_theme_requirements(&self) -> Response {
+        let result = async {
+            let site = self.active_project(ProjectType::Site)?;
+            let theme = self.active_project(ProjectType::Theme)?;
+            theme_problems(&site, &theme).await
+        }
+        .await;
+
+        match result {
+            Ok(problems) => Response::success(json!({
+                "ok": problems.is_empty(),
+                "problems": problems,
+            })),
+            Err(e) => Response::error(&format!("Failed to check theme: {}", e)),
+        }
+    }
+
+    /// ACTOR Use a stored theme for the active site
+    ///
+    /// The theme is selected even if the site doesn't meet its manifest;
+    /// the response lists what's missing.
+    pub(super) async fn select_theme(&self, theme_id: String) -> Response {
+        console_log!("Selecting theme {}", theme_id);
+
+        let result = async {
+            let mut site = self.active_project(ProjectType::Site)?;
+            let theme = match self.active_project(ProjectType::Theme) {
+                Ok(theme) if theme.id() == theme_id => theme,
+                _ => {
+                    let bytes = load_bytes(IDB_PROJECTS_STORE, &theme_id)
+                        .await?
+                        .ok_or_else(|| format!("No theme with id {}", theme_id))?;
+                    let theme =
+                        Project::import(bytes, theme_id.clone(), ProjectType::Theme, 0.0, 0.0)?;
+                    self.mark_saved(&theme);
+                    theme
+                }
+            };
+
+            let problems = theme_problems(&site, &theme).await?;
+            site.set_theme_id(&theme_id)
+                .map_err(|e| format!("Failed to set theme: {}", e))?;
+            self.set_site(site)?;
+            self.set_theme(theme)?;
+            Ok::<_, String>(problems)
+        }
+        .await;
+
+        match result {
+            Ok(problems) => Response::success(json!({
+                "themeId": theme_id,
+                "ok": problems.is_empty(),
+                "problems": problems,
+            })),
+            Err(e) => Response::error(&format!("Failed to select theme: {}", e)),
+        }
+    }
+}
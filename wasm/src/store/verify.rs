@@ -0,0 +1,323 @@
+use loro::{LoroDoc, LoroMap, LoroValue, ValueOrContainer};
+use serde::Serialize;
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::install::save_bytes;
+use super::maintenance::load_bytes;
+use super::{StoreInner, IDB_ASSETS_STORE, IDB_FILES_STORE, IDB_PROJECTS_STORE};
+use crate::messages::Response;
+use crate::model::file::File;
+use crate::model::project::{export_document, required_collections, Project};
+use crate::model::{Asset, Page, Partial, Post, Template, Text};
+use crate::types::{ProjectType, RepairOptions};
+use crate::{ID_KEY, META_KEY};
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// An inconsistency between a project, its files and what's in storage
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub(super) struct Issue {
+    /// "missing_collection", "missing_document", "unreadable_document",
+    /// "id_mismatch", "missing_theme" or "unresolved_theme"
+    pub kind: &'static str,
+    /// "site" or "theme"
+    pub project: &'static str,
+    pub message: String,
+    pub repaired: bool,
+}
+
+/// The id a stored document records for itself
+fn stored_id(doc: &LoroDoc) -> Option<String> {
+    match doc.get_map(META_KEY).get(ID_KEY) {
+        Some(ValueOrContainer::Value(LoroValue::String(id))) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+/// Save a new document for a file, seeded with the meta cached in the project
+async fn create_document(cached: &LoroMap, id: &str) -> Result<(), String> {
+    let doc = LoroDoc::new();
+    let meta = doc.get_map(META_KEY);
+    if let LoroValue::Map(fields) = cached.get_deep_value() {
+        for (key, value) in fields.iter() {
+            meta.insert(key, value.clone())
+                .map_err(|e| format!("Failed to copy {}: {}", key, e))?;
+        }
+    }
+    meta.insert(ID_KEY, id.to_string())
+        .map_err(|e| format!("Failed to set id: {}", e))?;
+    doc.commit();
+    save_bytes(IDB_FILES_STORE, id, &export_document(&doc)?).await
+}
+
+async fn set_stored_id(doc: &LoroDoc, id: &str) -> Result<(), String> {
+    doc.get_map(META_KEY)
+        .insert(ID_KEY, id.to_string())
+        .map_err(|e| format!("Failed to set id: {}", e))?;
+    doc.commit();
+    save_bytes(IDB_FILES_STORE, id, &export_document(doc)?).await
+}
+
+struct Verifier {
+    project: &'static str,
+    repair: RepairOptions,
+    issues: Vec<Issue>,
+}
+
+impl Verifier {
+    fn report(&mut self, kind: &'static str, message: String, repaired: bool) {
+        self.issues.push(Issue {
+            kind,
+            project: self.project,
+            message,
+            repaired,
+        });
+    }
+
+    /// Built-in collections the project should have, added if repairing
+    fn verify_collections(&mut self, project: &mut Project) -> Result<(), String> {
+        let added = if self.repair.add_collections {
+            project.add_missing_collections()?
+        } else {
+            Vec::new()
+        };
+        let existing: Vec<String> = project
+            .get_collections()?
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        for name in required_collections(&project.project_type()) {
+            let repaired = added.iter().any(|added| added == *name);
+            if repaired || !existing.iter().any(|existing| existing == *name) {
+                self.report(
+                    "missing_collection",
+                    format!("The {} collection is missing", name),
+                    repaired,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// The project document's own id against the one it's known by
+    fn verify_project_id(&mut self, project: &Project) {
+        let id = project.id();
+        let recorded = project.doc_id();
+        if recorded.as_deref() == Some(id.as_str()) {
+            return;
+        }
+        let repaired = self.repair.fix_ids && project.meta().insert(ID_KEY, id.clone()).is_ok();
+        self.report(
+            "id_mismatch",
+            format!(
+                "The {} is loaded as {}, but its document says {}",
+                self.project,
+                id,
+                recorded.as_deref().unwrap_or("nothing")
+            ),
+            repaired,
+        );
+    }
+
+    /// Every file in a collection has a readable document under its own id
+    async fn verify_documents<T: File + Default>(
+        &mut self,
+        project: &Project,
+        collection_name: &str,
+    ) -> Result<(), String> {
+        let collection = match project.get_collection::<T>(collection_name) {
+            Ok(collection) => collection,
+            // Already reported as a missing collection
+            Err(_) => return Ok(()),
+        };
+
+        for file in collection.get_files(collection_name).await? {
+            let id = file.id()?;
+            let label = format!(
+                "{} {}",
+                collection_name,
+                file.name().unwrap_or_else(|_| id.clone())
+            );
+
+            let data = match load_bytes(IDB_FILES_STORE, &id).await? {
+                Some(data) if !data.is_empty() => data,
+                _ => {
+                    let repaired = self.repair.create_documents
+                        && create_document(&file.meta(), &id).await.is_ok();
+                    self.report(
+                        "missing_document",
+                        format!("{} has no stored document", label),
+                        repaired,
+                    );
+                    continue;
+                }
+            };
+
+            let doc = LoroDoc::new();
+            if let Err(e) = doc.import(&data) {
+                self.report(
+                    "unreadable_document",
+                    format!("{}'s document can't be read: {}", label, e),
+                    false,
+                );
+                continue;
+            }
+            let recorded = stored_id(&doc);
+            if recorded.as_deref() != Some(id.as_str()) {
+                let repaired = self.repair.fix_ids && set_stored_id(&doc, &id).await.is_ok();
+                self.report(
+                    "id_mismatch",
+                    format!(
+                        "{} is stored under {}, but its document says {}",
+                        label,
+                        id,
+                        recorded.as_deref().unwrap_or("nothing")
+                    ),
+                    repaired,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Every asset still has its bytes
+    async fn verify_assets(&mut self, project: &Project) -> Result<(), String> {
+        let collection = match project.get_collection::<Asset>("asset") {
+            Ok(collection) => collection,
+            Err(_) => return Ok(()),
+        };
+
+        for asset in collection.get_files("asset").await? {
+            let id = asset.id()?;
+            match load_bytes(IDB_ASSETS_STORE, &id).await? {
+                Some(data) if !data.is_empty() => {}
+                _ => {
+                    let repaired =
+                        self.repair.remove_missing_assets && collection.remove_file(&id).is_ok();
+                    self.report(
+                        "missing_document",
+                        format!(
+                            "asset {} has no stored data",
+                            asset.name().unwrap_or_else(|_| id.clone())
+                        ),
+                        repaired,
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The site's theme is the active one, or at least saved
+    async fn verify_theme(&mut self, site: &mut Project, theme: Option<&Project>) {
+        let theme_id = site.theme_id();
+        let resolved = match (&theme_id, theme) {
+            (Some(theme_id), Some(theme)) if *theme_id == theme.id() => true,
+            (Some(theme_id), _) => matches!(
+                load_bytes(IDB_PROJECTS_STORE, theme_id).await,
+                Ok(Some(data)) if !data.is_empty()
+            ),
+            (None, _) => false,
+        };
+        if resolved {
+            return;
+        }
+
+        let repaired = self.repair.relink_theme
+            && theme.map_or(false, |theme| site.set_theme_id(&theme.id()).is_ok());
+        match theme_id {
+            Some(theme_id) => self.report(
+                "unresolved_theme",
+                format!("The site's theme {} can't be found", theme_id),
+                repaired,
+            ),
+            None => self.report(
+                "missing_theme",
+                "The site has no theme".to_string(),
+                repaired,
+            ),
+        }
+    }
+}
+
+impl StoreInner {
+    /// ACTOR Check the active site and theme against what's in storage
+    ///
+    /// Only the inconsistencies `repair` asks for are fixed; everything is
+    /// reported either way.
+    pub(super) async fn verify_project(&self, repair: RepairOptions) -> Response {
+        console_log!("Verifying projects (repair: {:?})", repair);
+
+        match self.verify_active_projects(repair).await {
+            Ok(issues) => Response::success(json!({
+                "ok": issues.iter().all(|issue| issue.repaired),
+                "repaired": issues.iter().filter(|issue| issue.repaired).count(),
+                "issues": issues,
+            })),
+            Err(e) => Response::error(&format!("Failed to verify projects: {}", e)),
+        }
+    }
+
+    async fn verify_active_projects(&self, repair: RepairOptions) -> Result<Vec<Issue>, String> {
+        let mut site = self.active_project(ProjectType::Site).ok();
+        let mut theme = self.active_project(ProjectType::Theme).ok();
+        if site.is_none() && theme.is_none() {
+            return Err("No active site or theme".to_string());
+        }
+        let mut issues = Vec::new();
+
+        if let Some(theme) = theme.as_mut() {
+            let mut verifier = Verifier {
+                project: "theme",
+                repair,
+                issues: Vec::new(),
+            };
+            verifier.verify_collections(theme)?;
+            verifier.verify_project_id(theme);
+            verifier
+                .verify_documents::<Template>(theme, "template")
+                .await?;
+            verifier
+                .verify_documents::<Partial>(theme, "partial")
+                .await?;
+            verifier.verify_documents::<Text>(theme, "text").await?;
+            verifier.verify_assets(theme).await?;
+            issues.extend(verifier.issues);
+        }
+
+        if let Some(site) = site.as_mut() {
+            let mut verifier = Verifier {
+                project: "site",
+                repair,
+                issues: Vec::new(),
+            };
+            verifier.verify_collections(site)?;
+            verifier.verify_project_id(site);
+            verifier.verify_documents::<Page>(site, "page").await?;
+            verifier.verify_documents::<Post>(site, "post").await?;
+            verifier.verify_assets(site).await?;
+            verifier.verify_theme(site, theme.as_ref()).await;
+            issues.extend(verifier.issues);
+        }
+
+        if issues.iter().any(|issue| issue.repaired) {
+            if let Some(site) = site {
+                self.set_site(site)?;
+            }
+            if let Some(theme) = theme {
+                self.set_theme(theme)?;
+            }
+        }
+        Ok(issues)
+    }
+}
@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use wasm_bindgen::prelude::*;
+
+use super::install::save_bytes;
+use super::maintenance::load_bytes;
+use super::{StoreInner, IDB_PROJECTS_STORE};
+use crate::messages::Response;
+use crate::model::project::Project;
+use crate::types::ProjectType;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = console)]
+    fn log(s: &str); // log to JS console
+}
+
+// Helper macro for logging
+macro_rules! console_log {
+    ($($t:tt)*) => (log(&format!("[Store (WASM)] {}", format!($($t)*))))
+}
+
+/// Projects store key for the list of saved sites
+pub(super) const WORKSPACE_KEY: &str = "workspace";
+
+/// A site in the workspace
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub(super) struct WorkspaceSite {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "themeId")]
+    pub theme_id: Option<String>,
+    /// Timestamps in ms, missing for sites registered before they were kept
+    #[serde(default)]
+    pub created: Option<f64>,
+    #[serde(default)]
+    pub updated: Option<f64>,
+}
+
+impl WorkspaceSite {
+    fn of(site: &Project) -> Self {
+        WorkspaceSite {
+            id: site.id(),
+            name: site.name().unwrap_or_else(|_| "Unnamed".to_string()),
+            theme_id: site.theme_id(),
+            created: Some(site.created()),
+            updated: Some(site.updated()),
+        }
+    }
+}
+
+/// Every site saved in this browser, so one can be switched to by id
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub(super) struct Workspace {
+    pub sites: Vec<WorkspaceSite>,
+}
+
+impl Workspace {
+    /// Add a site, or update it in place if it's already listed
+    pub fn upsert(&mut self, site: WorkspaceSite) {
+        match self.sites.iter_mut().find(|listed| listed.id == site.id) {
+            Some(listed) => *listed = site,
+            None => self.sites.push(site),
+        }
+    }
+}
+
+pub(super) async fn load_workspace() -> Result<Workspace, String> {
+    match load_bytes(IDB_PROJECTS_STORE, WORKSPACE_KEY).await? {
+        Some(bytes) if !bytes.is_empty() => {
+            serde_json::from_slice(&bytes).map_err(|e| format!("Failed to read workspace: {}", e))
+        }
+        _ => Ok(Workspace::default()),
+    }
+}
+
+/// Drop sites from the workspace, when their saved copy is gone
+pub(super) async fn forget_sites(ids: &[String]) -> Result<(), String> {
+    let mut workspace = load_workspace().await?;
+    workspace.sites.retain(|site| !ids.contains(&site.id));
+    let bytes = serde_json::to_vec(&workspace).map_err(|e| e.to_string())?;
+    save_bytes(IDB_PROJECTS_STORE, WORKSPACE_KEY, &bytes).await
+}
+
+impl StoreInner {
+    /// Record a saved site in the workspace
+    pub(super) async fn register_site(&self, site: &Project) -> Result<(), String> {
+        let mut workspace = load_workspace().await?;
+        workspace.upsert(WorkspaceSite::of(site));
+        let bytes = serde_json::to_vec(&workspace).map_err(|e| e.to_string())?;
+        save_bytes(IDB_PROJECTS_STORE, WORKSPACE_KEY, &bytes).await
+    }
+
+    /// A site other than the active one: its open copy, with any unsaved
+    /// changes, or else the saved one
+    pub(super) async fn load_site(&self, site_id: &str) -> Result<Project, String> {
+        let open = self
+            .open_sites
+            .lock()
+            .map_err(|_| "Failed to acquire open sites lock".to_string())?
+            .get(site_id)
+            .cloned();
+        if let Some(site) = open {
+            return Ok(site);
+        }
+        let bytes = load_bytes(IDB_PROJECTS_STORE, site_id)
+            .await?
+            .filter(|bytes| !bytes.is_empty())
+            .ok_or_else(|| format!("No site with id {}", site_id))?;
+        let site = Project::import(bytes, site_id.to_string(), ProjectType::Site, 0.0, 0.0)?;
+        self.mark_saved(&site);
+        Ok(site)
+    }
+
+    /// Keep the active site open in the background before another replaces it
+    pub(super) fn stash_active_site(&self) -> Result<(), String> {
+        if let Ok(site) = self.active_project(ProjectType::Site) {
+            self.open_sites
+                .lock()
+                .map_err(|_| "Failed to acquire open sites lock".to_string())?
+                .insert(site.id(), site);
+        }
+        Ok(())
+    }
+
+    /// Make `site` the active site, keeping the current one open
+    pub(super) fn activate_site(&self, site: Project) -> Result<(), String> {
+        self.stash_active_site()?;
+        self.open_sites
+            .lock()
+            .map_err(|_| "Failed to acquire open sites lock".to_string())?
+            .remove(&site.id());
+        self.set_site(site)?;
+        // The open file and preview belonged to the previous site
+        *self
+            .active_file
+            .lock()
+            .map_err(|_| "Failed to acquire file lock".to_string())? = None;
+        *self
+            .preview_watch
+            .lock()
+            .map_err(|_| "Failed to acquire preview lock".to_string())? = None;
+        Ok(())
+    }
+
+    /// ACTOR Sites in the workspace, saved or open, and which is active
+    ///
+    /// `sharesTheme` is false for sites bound to a theme other than the
+    /// active one; they can't be switched to without changing theme.
+    pub(super) async fn list_sites(&self) -> Response {
+        console_log!("Listing sites");
+
+        let result = async {
+            let mut workspace = load_workspace().await?;
+            let open: Vec<Project> = self
+                .open_sites
+                .lock()
+                .map_err(|_| "Failed to acquire open sites lock".to_string())?
+                .values()
+                .cloned()
+                .collect();
+            let active = self.active_project(ProjectType::Site).ok();
+            // Open sites may have unsaved names, so they win over saved entries
+            for site in open.iter().chain(active.iter()) {
+                workspace.upsert(WorkspaceSite::of(site));
+            }
+
+            let active_id = active.as_ref().map(Project::id);
+            let theme_id = self
+                .active_project(ProjectType::Theme)
+                .ok()
+                .map(|theme| theme.id());
+            let sites: Vec<_> = workspace
+                .sites
+                .iter()
+                .map(|site| {
+                    json!({
+                        "id": site.id,
+                        "name": site.name,
+                        "themeId": site.theme_id,
+                        "created": site.created,
+                        "updated": site.updated,
+                        "active": active_id.as_ref() == Some(&site.id),
+                        "open": open.iter().any(|project| project.id() == site.id),
+                        "sharesTheme": theme_id.is_some() && site.theme_id == theme_id,
+                    })
+                })
+                .collect();
+            Ok::<_, String>(json!({ "sites": sites, "themeId": theme_id }))
+        }
+        .await;
+
+        match result {
+            Ok(sites) => Response::success(sites),
+            Err(e) => Response::error(&format!("Failed to list sites: {}", e)),
+        }
+    }
+
+    /// ACTOR Make another site in the workspace the active one
+    ///
+    /// The site must use the active theme. The theme document is never
+    /// reloaded or replaced here: every site renders with the one shared
+    /// copy, so theme edits, saved or not, reach all of them. The site
+    /// switched away from stays open with any unsaved changes.
+    pub(super) async fn switch_site(&self, site_id: String) -> Response {
+        console_log!("Switching to site {}", site_id);
+
+        let result = async {
+            let theme = self.active_project(ProjectType::Theme)?;
+            if let Ok(active) = self.active_project(ProjectType::Site) {
+                if active.id() == site_id {
+                    return Ok(active);
+                }
+            }
+
+            let site = self.load_site(&site_id).await?;
+            if site.theme_id().as_deref() != Some(theme.id().as_str()) {
+                return Err(format!(
+                    "Site {} uses theme {}, not the workspace theme {}",
+                    site_id,
+                    site.theme_id().unwrap_or_else(|| "(none)".to_string()),
+                    theme.id()
+                ));
+            }
+
+            self.activate_site(site.clone())?;
+            Ok::<_, String>(site)
+        }
+        .await;
+
+        match result {
+            Ok(site) => Response::success(json!({
+                "id": site.id(),
+                "name": site.name().unwrap_or_else(|_| "Unnamed".to_string()),
+                "themeId": site.theme_id(),
+            })),
+            Err(e) => Response::error(&format!("Failed to switch site: {}", e)),
+        }
+    }
+}
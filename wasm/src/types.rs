@@ -1,9 +1,13 @@
-use crate::model::file::{Asset, Page, Partial, Post, Template, Text};
+#[cfg(not(feature = "wasi"))]
+use crate::model::file::{Asset, Entry, File, Page, Partial, Post, Template, Text};
+#[cfg(not(feature = "wasi"))]
+use crate::model::FileStore;
+#[cfg(not(feature = "wasi"))]
 use enum_dispatch::enum_dispatch;
 use loro::{LoroMap, LoroValue, ValueOrContainer};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::TryFrom;
 use std::fmt::{self, Display, Formatter};
 
@@ -29,6 +33,7 @@ impl PartialEq for ProjectType {
     }
 }
 
+#[cfg(not(feature = "wasi"))]
 #[enum_dispatch(File)]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum FileType {
@@ -38,9 +43,39 @@ pub enum FileType {
     Text(Text),
     Partial(Partial),
     Post(Post),
+    Entry(Entry),
     // UserModel,
 }
 
+#[cfg(not(feature = "wasi"))]
+impl FileType {
+    /// Where the file's document lives
+    pub fn store(&self) -> &FileStore {
+        match self {
+            FileType::Asset(asset) => asset.store(),
+            FileType::Template(template) => template.store(),
+            FileType::Page(page) => page.store(),
+            FileType::Text(text) => text.store(),
+            FileType::Partial(partial) => partial.store(),
+            FileType::Post(post) => post.store(),
+            FileType::Entry(entry) => entry.store(),
+        }
+    }
+
+    pub fn id(&self) -> Result<String, String> {
+        match self {
+            FileType::Asset(asset) => asset.id(),
+            FileType::Template(template) => template.id(),
+            FileType::Page(page) => page.id(),
+            FileType::Text(text) => text.id(),
+            FileType::Partial(partial) => partial.id(),
+            FileType::Post(post) => post.id(),
+            FileType::Entry(entry) => entry.id(),
+        }
+    }
+}
+
+#[cfg(not(feature = "wasi"))]
 impl Display for FileType {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
@@ -50,12 +85,13 @@ impl Display for FileType {
             FileType::Text(_text) => write!(f, "text"),
             FileType::Partial(_partial) => write!(f, "partial"),
             FileType::Post(_post) => write!(f, "post"),
+            FileType::Entry(_entry) => write!(f, "entry"),
             // FileType::UserModel => write!(f, "userModel"),
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct FieldDefinition {
     pub name: String,
     pub field_type: FieldType,
@@ -129,6 +165,72 @@ impl FieldType {
             FieldType::Blob => "blob".to_string(),
         }
     }
+
+    /// Convert raw text (e.g. a CSV cell) into a value of this field type
+    pub fn coerce(&self, raw: &str) -> Result<Value, String> {
+        let raw = raw.trim();
+        match self {
+            FieldType::String | FieldType::Text | FieldType::RichText => {
+                Ok(Value::String(raw.to_string()))
+            }
+            FieldType::Number => {
+                if let Ok(n) = raw.parse::<i64>() {
+                    return Ok(n.into());
+                }
+                raw.parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .map(Value::Number)
+                    .ok_or_else(|| format!("Not a number: {}", raw))
+            }
+            FieldType::DateTime => coerce_datetime(raw),
+            FieldType::List | FieldType::Array => {
+                let separator = if raw.contains(';') { ';' } else { ',' };
+                Ok(Value::Array(
+                    raw.split(separator)
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(|item| Value::String(item.to_string()))
+                        .collect(),
+                ))
+            }
+            FieldType::Map | FieldType::Object => match serde_json::from_str::<Value>(raw) {
+                Ok(value @ Value::Object(_)) => Ok(value),
+                _ => Err(format!("Not a JSON object: {}", raw)),
+            },
+            FieldType::Blob => Err("Blob fields cannot be set from text".to_string()),
+        }
+    }
+}
+
+/// Accept the common date and date-time spellings, normalised to ISO 8601
+fn coerce_datetime(raw: &str) -> Result<Value, String> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(Value::String(datetime.to_rfc3339()));
+    }
+
+    for format in &[
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(raw, format) {
+            return Ok(Value::String(
+                datetime.format("%Y-%m-%dT%H:%M:%S").to_string(),
+            ));
+        }
+    }
+
+    for format in &["%Y-%m-%d", "%d/%m/%Y", "%d.%m.%Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(raw, format) {
+            return Ok(Value::String(date.format("%Y-%m-%d").to_string()));
+        }
+    }
+
+    Err(format!("Not a date: {}", raw))
 }
 
 impl TryFrom<String> for FieldType {
@@ -157,6 +259,643 @@ impl PartialEq for FieldType {
     }
 }
 
+/// Response shaping for `ListFiles`, so a sidebar needn't fetch whole collections
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ListFilesOptions {
+    /// Only include these keys ("id" is always included)
+    pub fields: Option<Vec<String>>,
+    /// Include the collection's rich text and text fields
+    pub include_body: bool,
+    /// Page size; when set the response is `{files, next_cursor}`
+    pub limit: Option<usize>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    /// Nest files under their folders; the response is `{folders, files}`
+    /// and can't be paged
+    pub tree: bool,
+    /// Order of the files; unsorted, they come in the order they were added
+    pub sort: Option<FileSort>,
+    pub descending: bool,
+    /// Only files with this status ("draft", "scheduled" or "published")
+    pub status: Option<String>,
+    /// Only files with this tag
+    pub tag: Option<String>,
+    /// Only files that name this template
+    pub template: Option<String>,
+    /// Only files whose title (or name, if untitled) contains this text
+    pub query: Option<String>,
+}
+
+impl Default for ListFilesOptions {
+    fn default() -> Self {
+        ListFilesOptions {
+            fields: None,
+            include_body: true,
+            limit: None,
+            cursor: None,
+            tree: false,
+            sort: None,
+            descending: false,
+            status: None,
+            tag: None,
+            template: None,
+            query: None,
+        }
+    }
+}
+
+/// How `GetPlainText` flattens a document
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct PlainTextOptions {
+    /// Put between blocks: paragraphs, headings, list items and so on
+    pub block_separator: String,
+    /// Put in place of hard line breaks
+    pub line_break: String,
+}
+
+impl Default for PlainTextOptions {
+    fn default() -> Self {
+        PlainTextOptions {
+            block_separator: "\n".to_string(),
+            line_break: "\n".to_string(),
+        }
+    }
+}
+
+/// What `ListFiles` sorts by; files without a date sort last either way
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FileSort {
+    Name,
+    /// Title, or name if untitled
+    Title,
+    /// Publication date
+    Date,
+    /// Last edit
+    Updated,
+}
+
+/// Where `FindReplace` looks
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct FindReplaceScope {
+    /// "site" or "theme"
+    pub project_type: String,
+    /// Collections to search; empty means pages and posts for a site, or
+    /// templates, partials and stylesheets for a theme
+    pub collections: Vec<String>,
+    /// Search document bodies
+    pub content: bool,
+    /// Meta fields to search as well, e.g. "title"
+    pub meta_fields: Vec<String>,
+}
+
+impl Default for FindReplaceScope {
+    fn default() -> Self {
+        FindReplaceScope {
+            project_type: "site".to_string(),
+            collections: Vec::new(),
+            content: true,
+            meta_fields: vec!["title".to_string()],
+        }
+    }
+}
+
+/// Thresholds for `GetContentReport`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct ContentReportOptions {
+    /// Report drafts created at least this many days ago
+    pub draft_days: u32,
+    /// Report published pages not updated for this many months
+    pub stale_months: u32,
+}
+
+impl Default for ContentReportOptions {
+    fn default() -> Self {
+        ContentReportOptions {
+            draft_days: 30,
+            stale_months: 12,
+        }
+    }
+}
+
+/// The part of a site to render, for deploying one section on its own
+///
+/// A page is rendered if it is in `files`, or if it matches every one of
+/// `collections`, `tags` and `path` that is set. An empty filter renders
+/// the whole site.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ExportFilter {
+    /// "page", "post" or custom collection names
+    pub collections: Vec<String>,
+    /// Pages and posts tagged with any of these
+    pub tags: Vec<String>,
+    /// Pages at or under this URL, e.g. "/docs/"
+    pub path: Option<String>,
+    /// Ids of pages and posts to include whatever else is set
+    pub files: Vec<String>,
+}
+
+impl ExportFilter {
+    pub fn is_empty(&self) -> bool {
+        self.collections.is_empty()
+            && self.tags.is_empty()
+            && self.path.is_none()
+            && self.files.is_empty()
+    }
+}
+
+/// How a data collection is published as a listing page (and optional detail pages)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ListingConfig {
+    /// Output directory for the listing, e.g. "events" → /events/
+    pub path: String,
+    /// Template used for the listing page
+    pub template: String,
+    /// Template used for one page per entry, at /{path}/{entry name}/
+    #[serde(default)]
+    pub detail_template: Option<String>,
+    #[serde(default)]
+    pub sort_by: Option<String>,
+    #[serde(default)]
+    pub descending: bool,
+    #[serde(default)]
+    pub group_by: Option<String>,
+    /// Bucket date values when grouping by a datetime field
+    #[serde(default)]
+    pub group_by_date: Option<DateGrouping>,
+    /// Only list entries whose value for this date field is today or later
+    #[serde(default)]
+    pub upcoming_field: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum DateGrouping {
+    Day,
+    Month,
+    Year,
+}
+
+/// Posts another local site shares into this one
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SyndicationSource {
+    /// Id of the site the posts come from
+    pub site_id: String,
+    /// Ids of posts to show
+    pub posts: Vec<String>,
+    /// Also show posts tagged with any of these; with no posts or tags
+    /// given, every post that isn't a draft is shown
+    pub tags: Vec<String>,
+}
+
+/// Site-wide settings, stored with the site project
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SiteSettings {
+    /// Public URL of the host the site is published on, e.g. "https://zine.example"
+    pub url: Option<String>,
+    /// Path the site lives under on that host, e.g. "/~user/site" or a
+    /// GitHub Pages project name; internal links are rewritten to include it
+    pub base_path: Option<String>,
+    /// How internal links are written when the site is exported
+    pub url_strategy: UrlStrategy,
+    pub comments: Option<CommentsConfig>,
+    /// Add a "#" link after each heading in rich text, for copying deep links
+    pub heading_links: bool,
+    /// Curly quotes, dashes and the like in rich text
+    pub typography: Typography,
+    /// Whether `:shortcode:` emoji in rich text are expanded, and to what
+    pub emoji: EmojiSettings,
+    /// Write Markdown and/or plain-text copies of each page beside its HTML
+    pub mirrors: TextMirrors,
+    /// Custom property name ("--primary") → value, overriding the theme's
+    pub theme_variables: BTreeMap<String, String>,
+    /// Compact the site's and theme's documents when saving, once it's due
+    pub compaction: CompactionSchedule,
+    /// Collection ("post" or "page") → URL pattern such as
+    /// `/blog/:year/:month/:slug/`, for files without their own `url`
+    pub url_patterns: BTreeMap<String, String>,
+    /// Also strip editor metadata, comments and spare whitespace from
+    /// uploaded SVGs; scripts are always taken out
+    pub optimize_svg: bool,
+    /// Refuse to publish pages and posts with images that have no alt text
+    pub require_alt_text: bool,
+    /// Posts from other sites in the workspace to show on this one
+    pub syndication: Vec<SyndicationSource>,
+    /// Whether template errors, images without alt text and broken internal
+    /// links block exports or are only warned about
+    pub strictness: Strictness,
+    /// License pages and posts are published under unless they give their
+    /// own: an SPDX id such as "CC-BY-SA-4.0", a URL, or other wording
+    pub license: Option<String>,
+    /// schema.org JSON-LD added to pages
+    pub structured_data: StructuredData,
+}
+
+/// A field a theme's templates read from a site collection
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FieldRequirement {
+    pub name: String,
+    /// Any type will do if not given
+    #[serde(default)]
+    pub field_type: Option<FieldType>,
+    /// What the theme uses the field for
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// A site collection a theme needs, with the fields it reads
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CollectionRequirement {
+    pub name: String,
+    #[serde(default)]
+    pub fields: Vec<FieldRequirement>,
+}
+
+/// A template pages can choose, as it's shown to people picking one
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PageTemplateInfo {
+    /// The template's name in the theme
+    pub name: String,
+    #[serde(default)]
+    pub label: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Name of the theme asset previewing the template
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+    /// Collections whose files may use it; any if empty
+    #[serde(default)]
+    pub collections: Vec<String>,
+}
+
+impl PageTemplateInfo {
+    pub fn applies_to(&self, collection: &str) -> bool {
+        self.collections.is_empty() || self.collections.iter().any(|c| c == collection)
+    }
+}
+
+/// What a theme expects of the sites using it, stored with the theme project
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ThemeManifest {
+    pub collections: Vec<CollectionRequirement>,
+    /// Templates pages may use; any template will do if empty
+    pub page_templates: Vec<PageTemplateInfo>,
+}
+
+/// Which inconsistencies `VerifyProject` should fix as well as report
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct RepairOptions {
+    /// Set the id inside each document to the key it's stored under
+    pub fix_ids: bool,
+    /// Recreate missing built-in collections, empty
+    pub add_collections: bool,
+    /// Give files with no stored document one, seeded from their cached meta
+    pub create_documents: bool,
+    /// Take assets whose bytes are gone out of the collection
+    pub remove_missing_assets: bool,
+    /// Point the site at the active theme when its theme can't be found
+    pub relink_theme: bool,
+}
+
+impl RepairOptions {
+    pub fn any(&self) -> bool {
+        *self != RepairOptions::default()
+    }
+}
+
+/// How often saving also compacts the project documents
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct CompactionSchedule {
+    /// Days between compactions; None leaves it to `CompactProjects`
+    pub every_days: Option<u32>,
+    /// Drop the edit history before each snapshot
+    pub shallow: bool,
+}
+
+/// The schema.org JSON-LD added to each page's head
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct StructuredData {
+    /// Leave it out
+    pub disabled: bool,
+    /// Collection → schema.org type, e.g. "events" → "Event"; posts are
+    /// `BlogPosting`s and pages `WebPage`s unless mapped
+    pub types: BTreeMap<String, String>,
+    /// Describe the site on its home page as an `Organization` rather than
+    /// a `WebSite`
+    pub organization: bool,
+    /// URL of the organization's logo
+    pub logo: Option<String>,
+}
+
+/// Which text copies of each page are written alongside the HTML
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct TextMirrors {
+    /// `index.md`
+    pub markdown: bool,
+    /// `index.txt`
+    pub text: bool,
+}
+
+/// Typographic clean-up of rich text as it's rendered; code is left alone
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Typography {
+    /// Straight quotes as the locale's curly ones, and apostrophes as ’
+    pub quotes: bool,
+    /// `--` as an en dash and `---` as an em dash
+    pub dashes: bool,
+    /// `...` as an ellipsis
+    pub ellipses: bool,
+    /// Non-breaking spaces where the locale puts them, such as before
+    /// `;:!?` and inside « » in French
+    pub spacing: bool,
+    /// Language whose conventions are followed, e.g. "en", "fr-CA" or "de";
+    /// English if not given
+    pub locale: Option<String>,
+}
+
+impl Typography {
+    pub fn is_enabled(&self) -> bool {
+        self.quotes || self.dashes || self.ellipses || self.spacing
+    }
+}
+
+/// How `:shortcode:` emoji, such as `:tada:`, are written out
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmojiStyle {
+    /// Left as typed
+    Off,
+    /// As the emoji character itself
+    Unicode,
+    /// As an image of the emoji, for readers whose fonts lack it
+    Image,
+}
+
+impl Default for EmojiStyle {
+    fn default() -> Self {
+        EmojiStyle::Off
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct EmojiSettings {
+    pub style: EmojiStyle,
+    /// Where images come from, as `{image_base}/{codepoints}.svg`;
+    /// Twemoji's if not given
+    pub image_base: Option<String>,
+}
+
+/// How an export treats problems in a site's content and templates
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Strictness {
+    /// Render what can be rendered and list the problems as warnings
+    Lenient,
+    /// Treat template variables that don't exist as errors, and refuse to
+    /// export while any problem remains
+    Strict,
+}
+
+impl Default for Strictness {
+    fn default() -> Self {
+        Strictness::Lenient
+    }
+}
+
+/// How internal URLs appear in exported HTML and CSS
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum UrlStrategy {
+    /// Root-relative ("/about/"), prefixed with the base path
+    Root,
+    /// Relative to each file ("../about/index.html"), for file:// and IPFS
+    Relative,
+    /// Absolute, rooted at the site URL ("https://zine.example/about/")
+    Absolute,
+}
+
+impl Default for UrlStrategy {
+    fn default() -> Self {
+        UrlStrategy::Root
+    }
+}
+
+/// How readers' responses are shown under posts
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommentsConfig {
+    pub provider: CommentsProvider,
+    /// Show comments on posts unless a post sets `comments` to false;
+    /// pages only show comments when they set it to true
+    #[serde(default = "default_true")]
+    pub posts_by_default: bool,
+    /// Domain registered with webmention.io
+    #[serde(default)]
+    pub webmention_domain: Option<String>,
+    /// Third-party embed code, rendered as a template with the page context
+    #[serde(default)]
+    pub snippet: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum CommentsProvider {
+    Webmention,
+    Snippet,
+}
+
+/// Output hashes recorded when a site was deployed
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DeployRecord {
+    /// RFC 3339 timestamp
+    pub deployed_at: String,
+    /// Output path → content hash
+    pub hashes: Map<String, Value>,
+}
+
+/// Someone who worked on a project, credited in `humans.txt` and on the
+/// site's credits
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct Collaborator {
+    /// Given by the store when the collaborator is added
+    pub id: String,
+    pub name: String,
+    /// What they did, e.g. "editor" or "illustration"
+    pub roles: Vec<String>,
+    /// Their website, profiles or contact address
+    pub links: Vec<String>,
+}
+
+/// How a submission's body is written
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubmissionFormat {
+    Markdown,
+    Html,
+}
+
+impl Default for SubmissionFormat {
+    fn default() -> Self {
+        SubmissionFormat::Markdown
+    }
+}
+
+/// Who sent a submission
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SubmissionAuthor {
+    pub name: String,
+    pub email: Option<String>,
+    pub url: Option<String>,
+}
+
+/// Content sent in from outside the editor, such as a zine's open call
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Submission {
+    pub title: String,
+    pub body: String,
+    #[serde(default)]
+    pub format: SubmissionFormat,
+    #[serde(default)]
+    pub author: SubmissionAuthor,
+    /// ISO datetime; defaults to when it is imported
+    #[serde(default)]
+    pub submitted_at: Option<String>,
+}
+
+/// A file dropped into the asset library, possibly inside a folder
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AssetEntry {
+    /// File name; the last part of `path` if empty
+    #[serde(default)]
+    pub name: String,
+    pub data: Vec<u8>,
+    /// Path relative to the dropped folder, e.g. `covers/2024/front.png`
+    #[serde(default)]
+    pub path: String,
+}
+
+/// A rectangle of an image, in pixels from its top left corner
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Edits made to an image asset when it's exported; its stored bytes are
+/// never changed. The crop applies first, then the rotation, then the size
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct AssetTransform {
+    /// Part of the original image to keep
+    pub crop: Option<CropRect>,
+    /// Clockwise quarter turns, in degrees: 0, 90, 180 or 270
+    pub rotate: u32,
+    /// Largest size to export at, keeping the aspect ratio; images are never enlarged
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+}
+
+impl AssetTransform {
+    /// Whether exporting with it gives back the original image
+    pub fn is_identity(&self) -> bool {
+        *self == AssetTransform::default()
+    }
+}
+
+/// A file from another generator's repository, such as a Jekyll or Hugo site
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SourceFile {
+    /// Path from the repository root, e.g. `_posts/2019-03-08-hello.md`
+    pub path: String,
+    /// Text files' contents
+    #[serde(default)]
+    pub content: String,
+    /// Binary files' bytes (images, fonts), in place of `content`
+    #[serde(default)]
+    pub data: Option<Vec<u8>>,
+}
+
+/// A form that can be embedded in pages with the `form` template helper
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FormDefinition {
+    /// File name of the form entry, used as `{{form "name"}}`
+    pub name: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    pub provider: FormProvider,
+    /// Submission URL (or email address for `Mailto`)
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    #[serde(default)]
+    pub fields: Vec<FormField>,
+    #[serde(default)]
+    pub submit_label: Option<String>,
+    /// Where to send people after a successful submission
+    #[serde(default)]
+    pub success_url: Option<String>,
+}
+
+/// Where a static form's submissions go
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum FormProvider {
+    /// Any service accepting a form POST at `endpoint`
+    Endpoint,
+    /// Netlify Forms, detected from the published HTML
+    Netlify,
+    /// Formspree, with `endpoint` set to the form's URL
+    Formspree,
+    /// Opens the reader's mail client addressed to `endpoint`
+    Mailto,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FormField {
+    pub name: String,
+    pub label: String,
+    #[serde(default = "default_form_input")]
+    pub input: FormInput,
+    #[serde(default)]
+    pub required: bool,
+    /// Choices for `Select` inputs
+    #[serde(default)]
+    pub options: Vec<String>,
+}
+
+fn default_form_input() -> FormInput {
+    FormInput::Text
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum FormInput {
+    Text,
+    Email,
+    Tel,
+    Url,
+    Number,
+    Date,
+    Textarea,
+    Checkbox,
+    Select,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UnparsedContentData {
     pub name: String,
@@ -165,6 +904,7 @@ pub struct UnparsedContentData {
     pub url: String,
 }
 
+#[cfg(not(feature = "wasi"))]
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ContentData {
     pub name: String,
@@ -178,11 +918,13 @@ pub struct ContentData {
 // and is interpreted as an integer in the JS context
 pub type UnparsedContentRecord = HashMap<String, UnparsedContentData>;
 
+#[cfg(not(feature = "wasi"))]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ContentRecord {
     pub content: HashMap<String, ContentData>,
 }
 
+#[cfg(not(feature = "wasi"))]
 impl ContentRecord {
     pub fn new() -> Self {
         ContentRecord {
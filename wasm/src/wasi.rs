@@ -0,0 +1,59 @@
+//! Server-side rendering for the `wasi` feature.
+//!
+//! The same renderer the browser uses, without the store: the caller passes
+//! a [`SiteSource`] as JSON (the shape the store gathers from a site and its
+//! theme) and gets `{files, warnings}` back. `src/bin/render.rs` wraps this
+//! as a command for Wasmtime and other WASI runtimes.
+
+use std::io::{self, Read, Write};
+
+use crate::render::site::{render_site, SiteSource};
+
+/// Render a site from `SiteSource` JSON to `{files, warnings}` JSON
+pub fn render_site_json(source: &str) -> Result<String, String> {
+    let source: SiteSource =
+        serde_json::from_str(source).map_err(|e| format!("Invalid site source: {}", e))?;
+    let output = render_site(&source)?;
+    Ok(output.to_json().to_string())
+}
+
+/// Read a site source from stdin and write the rendered output to stdout
+pub fn run() -> Result<(), String> {
+    let mut source = String::new();
+    io::stdin()
+        .read_to_string(&mut source)
+        .map_err(|e| format!("Failed to read stdin: {}", e))?;
+    let output = render_site_json(&source)?;
+    io::stdout()
+        .write_all(output.as_bytes())
+        .map_err(|e| format!("Failed to write stdout: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use wasm_bindgen_test::*;
+
+    #[wasm_bindgen_test]
+    fn test_render_site_json() {
+        let source = json!({
+            "site": { "name": "Community Zine" },
+            "templates": [["index", "<h1>{{site.name}}</h1>{{{content}}}"]],
+            "pages": [{
+                "collection": "page",
+                "name": "index",
+                "body": { "type": "doc", "content": [
+                    { "type": "paragraph", "content": [{ "type": "text", "text": "Hi" }] }
+                ]}
+            }]
+        });
+
+        let output: Value =
+            serde_json::from_str(&render_site_json(&source.to_string()).unwrap()).unwrap();
+        assert_eq!(
+            output["files"]["index.html"],
+            json!("<h1>Community Zine</h1><p>Hi</p>")
+        );
+    }
+}
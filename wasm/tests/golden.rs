@@ -0,0 +1,174 @@
+//! Golden-file tests for the renderer.
+//!
+//! Each directory under `tests/golden` is a case: a `source.json` holding
+//! the site's templates, styles and settings (a `SiteSource` without
+//! pages), a `fixture.json` with the `FixtureOptions` its content is
+//! generated from, and an `expected` directory holding every file rendering
+//! it should produce. A case fails if any file is missing, extra or
+//! different.
+//!
+//! ```sh
+//! cargo test --features fixtures --test golden
+//! ```
+//!
+//! After a deliberate change to rendering, rewrite the expected files and
+//! review the diff before committing it:
+//!
+//! ```sh
+//! BLESS=1 cargo test --features fixtures --test golden
+//! ```
+//!
+//! A new case has no expected files yet; its first run writes them and
+//! fails, so they get reviewed too.
+#![cfg(all(feature = "fixtures", not(feature = "wasi")))]
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use minissg::fixtures::{add_to_site_source, generate, FixtureOptions};
+use minissg::render::site::{render_site, SiteSource};
+
+const EXPECTED_DIR: &str = "expected";
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+fn blessing() -> bool {
+    std::env::var("BLESS").map_or(false, |value| !value.is_empty() && value != "0")
+}
+
+/// Every file under `dir`, by path relative to it
+fn read_tree(dir: &Path) -> BTreeMap<String, String> {
+    fn walk(root: &Path, dir: &Path, files: &mut BTreeMap<String, String>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                walk(root, &path, files);
+            } else {
+                let relative = path.strip_prefix(root).unwrap();
+                let name = relative
+                    .components()
+                    .map(|part| part.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.insert(name, fs::read_to_string(&path).unwrap());
+            }
+        }
+    }
+    let mut files = BTreeMap::new();
+    if dir.is_dir() {
+        walk(dir, dir, &mut files);
+    }
+    files
+}
+
+fn write_tree(dir: &Path, files: &BTreeMap<String, String>) {
+    if dir.exists() {
+        fs::remove_dir_all(dir).unwrap();
+    }
+    for (name, contents) in files {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+/// Where two versions of a file first part ways
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut number = 1;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => number += 1,
+            (None, None) => return "line endings differ".to_string(),
+            (e, a) => {
+                return format!(
+                    "line {}:\n  expected: {}\n  actual:   {}",
+                    number,
+                    e.unwrap_or("<end of file>"),
+                    a.unwrap_or("<end of file>")
+                )
+            }
+        }
+    }
+}
+
+/// Problems with a case's output, or none if it matches
+fn check_case(case: &Path) -> Vec<String> {
+    let source = fs::read_to_string(case.join("source.json")).unwrap();
+    let mut source: SiteSource = match serde_json::from_str(&source) {
+        Ok(source) => source,
+        Err(e) => return vec![format!("invalid source.json: {}", e)],
+    };
+    let options = fs::read_to_string(case.join("fixture.json")).unwrap();
+    let options: FixtureOptions = match serde_json::from_str(&options) {
+        Ok(options) => options,
+        Err(e) => return vec![format!("invalid fixture.json: {}", e)],
+    };
+    match generate(&options) {
+        Ok(fixture) => add_to_site_source(fixture, &mut source),
+        Err(e) => return vec![format!("failed to generate fixture: {}", e)],
+    }
+    let output = match render_site(&source) {
+        Ok(output) => output,
+        Err(e) => return vec![format!("failed to render: {}", e)],
+    };
+
+    let expected_dir = case.join(EXPECTED_DIR);
+    if blessing() || !expected_dir.exists() {
+        let new = !expected_dir.exists();
+        write_tree(&expected_dir, &output.files);
+        if new && !blessing() {
+            return vec!["no expected files yet; wrote them, review and commit".to_string()];
+        }
+        return Vec::new();
+    }
+
+    let expected = read_tree(&expected_dir);
+    let mut problems = Vec::new();
+    for (name, contents) in &expected {
+        match output.files.get(name) {
+            Some(actual) if actual == contents => {}
+            Some(actual) => problems.push(format!(
+                "{} differs at {}",
+                name,
+                first_difference(contents, actual)
+            )),
+            None => problems.push(format!("{} is no longer rendered", name)),
+        }
+    }
+    for name in output.files.keys() {
+        if !expected.contains_key(name) {
+            problems.push(format!("{} is rendered but not expected", name));
+        }
+    }
+    problems
+}
+
+#[test]
+fn golden_files() {
+    let mut cases: Vec<PathBuf> = fs::read_dir(golden_dir())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.join("source.json").is_file() && path.join("fixture.json").is_file())
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty(), "no cases under tests/golden");
+
+    let mut failures = Vec::new();
+    for case in &cases {
+        let name = case.file_name().unwrap().to_string_lossy();
+        for problem in check_case(case) {
+            failures.push(format!("{}: {}", name, problem));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "{} golden file problems (run with BLESS=1 if the change is intended):\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}